@@ -3,8 +3,11 @@
 //! Matches the key=value format from `.loop/config` used by `bin/loop`.
 //! Precedence: CLI flags > `--config` file > `.loop/config` > defaults.
 
+use crate::limits::ResourceLimitsConfig;
+use crate::tool_policy::{parse_tool_patterns, ToolPolicy};
 use crate::types::{
-    ArtifactMode, CompletionMode, MergeStrategy, QueuePolicy, RunNameSource, WorktreeProvider,
+    AgentBackendKind, ArtifactMode, CompletionMode, MergeStrategy, QueuePolicy, RunNameSource,
+    SandboxMode, WorktreeProvider,
 };
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -19,6 +22,8 @@ pub enum ConfigError {
     InvalidBool { key: String, value: String },
     #[error("invalid integer value for {key}: {value}")]
     InvalidInt { key: String, value: String },
+    #[error("invalid number value for {key}: {value}")]
+    InvalidFloat { key: String, value: String },
     #[error("unknown config key: {0}")]
     UnknownKey(String),
 }
@@ -47,6 +52,22 @@ pub struct Config {
     // Reviewer
     pub reviewer: bool,
 
+    // Planner
+    /// Generate a plan from the spec before implementation when a run has no
+    /// `plan_path` (default: false). The plan is written to `plans_dir`.
+    pub planner: bool,
+    /// Fan a plan whose unfinished tasks all carry IDs out into one child run
+    /// per task, merged back in dependency order (default: false).
+    pub parallel_tasks: bool,
+
+    // Session resume
+    /// Continue the previous implementation iteration's agent session with a
+    /// delta prompt instead of starting fresh (default: false; Claude only).
+    pub session_resume: bool,
+    /// Iterations one session may span before a fresh one starts
+    /// (default: 10, 0 = unlimited).
+    pub session_max_turns: u32,
+
     // Prompt customization
     pub prompt_file: Option<PathBuf>,
     pub context_files: Vec<PathBuf>,
@@ -60,8 +81,27 @@ pub struct Config {
     pub claude_retries: u32,
     pub claude_retry_backoff_sec: u32,
 
+    // Agent backend
+    /// Backend that executes implementation/review steps (default: claude).
+    pub agent_backend: AgentBackendKind,
+    /// Claude CLI binary used by the `claude` backend (default: claude).
+    pub claude_bin: PathBuf,
+    /// Shell command for the `command` backend. The prompt is written to stdin
+    /// and `LOOP_MODEL` is exported with the configured model.
+    pub agent_command: Option<String>,
+    /// Base URL for the `openai` backend (default: https://api.openai.com/v1).
+    pub openai_base_url: String,
+    /// Environment variable holding the `openai` backend API key (default: OPENAI_API_KEY).
+    pub openai_api_key_env: String,
+    /// Fixture bundle directory played back by the `replay` backend.
+    pub replay_fixture: Option<PathBuf>,
+
     // Artifacts
     pub artifact_mode: ArtifactMode,
+    /// Compress finished step logs (`.log`, `.jsonl`) with zstd.
+    pub log_compression: bool,
+    /// Cap on a run's iteration log bytes; the oldest iterations are evicted. 0 disables.
+    pub max_run_log_mb: u64,
 
     // Run naming
     pub run_naming_mode: RunNameSource,
@@ -117,6 +157,33 @@ pub struct Config {
     pub max_consecutive_verification_failures: u32,
     /// Fail the run after N consecutive review failures. 0 disables.
     pub max_consecutive_review_failures: u32,
+
+    // Tool permissions
+    /// Allowed and denied agent tools (`tool_policy.allow`, `tool_policy.deny`).
+    /// Empty (default) runs the agent with all permissions.
+    pub tool_policy: ToolPolicy,
+
+    // Sandbox
+    /// Sandbox for agent and verification processes: `none` (default), `fs`, or `fs+net`.
+    pub sandbox: SandboxMode,
+    /// Extra paths sandboxed processes may write to (`sandbox_writable`, pipe-separated).
+    pub sandbox_writable: Vec<PathBuf>,
+    /// TCP ports the agent may still connect to under `fs+net`
+    /// (`sandbox_agent_ports`, pipe-separated). Defaults to `443` so the agent
+    /// can reach its model API; empty denies the agent all TCP.
+    pub sandbox_agent_ports: Vec<u16>,
+
+    // Resource limits
+    /// Memory, CPU-time, process-count and output limits per phase (`limits.*`).
+    pub resource_limits: ResourceLimitsConfig,
+
+    // Budget limits (checked between steps)
+    /// Fail the run once its reported cost reaches this many USD. 0 disables.
+    pub max_run_cost_usd: f64,
+    /// Fail the run once it has used this many tokens (input, output and cache). 0 disables.
+    pub max_run_tokens: u64,
+    /// Fail the run once this many seconds have passed since its first step. 0 disables.
+    pub max_run_wall_clock_sec: u64,
 }
 
 impl Default for Config {
@@ -134,6 +201,10 @@ impl Default for Config {
             iterations: 50,
             completion_mode: CompletionMode::Trailing,
             reviewer: true,
+            planner: false,
+            parallel_tasks: false,
+            session_resume: false,
+            session_max_turns: 10,
             prompt_file: None,
             context_files: Vec::new(),
             verify_cmds: Vec::new(),
@@ -141,7 +212,15 @@ impl Default for Config {
             claude_timeout_sec: 600,
             claude_retries: 0,
             claude_retry_backoff_sec: 5,
+            agent_backend: AgentBackendKind::Claude,
+            claude_bin: PathBuf::from("claude"),
+            agent_command: None,
+            openai_base_url: "https://api.openai.com/v1".to_string(),
+            openai_api_key_env: "OPENAI_API_KEY".to_string(),
+            replay_fixture: None,
             artifact_mode: ArtifactMode::Mirror,
+            log_compression: true,
+            max_run_log_mb: 0,
             run_naming_mode: RunNameSource::Haiku,
             run_naming_model: "haiku".to_string(),
             base_branch: None,
@@ -184,6 +263,15 @@ impl Default for Config {
             // Consecutive failure thresholds (consecutive-failure-detection.md Section 3.2)
             max_consecutive_verification_failures: 3,
             max_consecutive_review_failures: 0,
+            tool_policy: ToolPolicy::default(),
+            sandbox: SandboxMode::None,
+            sandbox_writable: Vec::new(),
+            sandbox_agent_ports: vec![443],
+            resource_limits: ResourceLimitsConfig::default(),
+            // Budget limits
+            max_run_cost_usd: 0.0,
+            max_run_tokens: 0,
+            max_run_wall_clock_sec: 0,
         }
     }
 }
@@ -269,6 +357,15 @@ impl Config {
                 }
             }
             "reviewer" => self.reviewer = Self::parse_bool(key, value)?,
+            "planner" => self.planner = Self::parse_bool(key, value)?,
+            "parallel_tasks" => self.parallel_tasks = Self::parse_bool(key, value)?,
+            "session_resume" => self.session_resume = Self::parse_bool(key, value)?,
+            "session_max_turns" => {
+                self.session_max_turns = value.parse().map_err(|_| ConfigError::InvalidInt {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })?;
+            }
             "prompt_file" => {
                 self.prompt_file = if value.is_empty() {
                     None
@@ -312,6 +409,36 @@ impl Config {
                         value: value.to_string(),
                     })?;
             }
+            "agent_backend" => {
+                self.agent_backend = match value {
+                    "claude" => AgentBackendKind::Claude,
+                    "openai" => AgentBackendKind::Openai,
+                    "command" => AgentBackendKind::Command,
+                    "replay" => AgentBackendKind::Replay,
+                    _ => {
+                        return Err(ConfigError::InvalidLine(format!(
+                            "agent_backend must be 'claude', 'openai', 'command', or 'replay', got '{value}'"
+                        )))
+                    }
+                }
+            }
+            "claude_bin" => self.claude_bin = PathBuf::from(value),
+            "agent_command" => {
+                self.agent_command = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.to_string())
+                };
+            }
+            "openai_base_url" => self.openai_base_url = value.trim_end_matches('/').to_string(),
+            "openai_api_key_env" => self.openai_api_key_env = value.to_string(),
+            "replay_fixture" => {
+                self.replay_fixture = if value.is_empty() {
+                    None
+                } else {
+                    Some(PathBuf::from(value))
+                };
+            }
             "artifact_mode" => {
                 self.artifact_mode = match value {
                     "workspace" => ArtifactMode::Workspace,
@@ -322,6 +449,13 @@ impl Config {
                     ))),
                 }
             }
+            "log_compression" => self.log_compression = Self::parse_bool(key, value)?,
+            "max_run_log_mb" => {
+                self.max_run_log_mb = value.parse().map_err(|_| ConfigError::InvalidInt {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })?;
+            }
             "run_naming_mode" => {
                 self.run_naming_mode = match value {
                     "haiku" => RunNameSource::Haiku,
@@ -354,9 +488,10 @@ impl Config {
                 self.queue_policy = match value {
                     "fifo" => QueuePolicy::Fifo,
                     "newest_first" => QueuePolicy::NewestFirst,
+                    "fair_share" => QueuePolicy::FairShare,
                     _ => {
                         return Err(ConfigError::InvalidLine(format!(
-                            "queue_policy must be 'fifo' or 'newest_first', got '{value}'"
+                            "queue_policy must be 'fifo', 'newest_first', or 'fair_share', got '{value}'"
                         )))
                     }
                 }
@@ -424,6 +559,76 @@ impl Config {
                         value: value.to_string(),
                     })?;
             }
+            // Tool permissions (pipe-separated patterns)
+            "tool_policy.allow" => {
+                self.tool_policy.allow = parse_tool_patterns(value)
+                    .map_err(|e| ConfigError::InvalidLine(format!("{key}: {e}")))?;
+            }
+            "tool_policy.deny" => {
+                self.tool_policy.deny = parse_tool_patterns(value)
+                    .map_err(|e| ConfigError::InvalidLine(format!("{key}: {e}")))?;
+            }
+            "sandbox" => {
+                self.sandbox = match value {
+                    "none" => SandboxMode::None,
+                    "fs" => SandboxMode::Fs,
+                    "fs+net" => SandboxMode::FsNet,
+                    _ => {
+                        return Err(ConfigError::InvalidLine(format!(
+                            "sandbox must be 'none', 'fs', or 'fs+net', got '{value}'"
+                        )))
+                    }
+                }
+            }
+            "sandbox_writable" => {
+                self.sandbox_writable = value
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect();
+            }
+            "sandbox_agent_ports" => {
+                self.sandbox_agent_ports = value
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|port| !port.is_empty())
+                    .map(|port| {
+                        port.parse::<u16>().map_err(|_| {
+                            ConfigError::InvalidLine(format!("{key}: invalid port '{port}'"))
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            _ if key.starts_with("limits.") => {
+                self.resource_limits
+                    .apply_key(&key["limits.".len()..], value)
+                    .map_err(|e| ConfigError::InvalidLine(format!("{key}: {e}")))?;
+            }
+            // Budget limits
+            "max_run_cost_usd" => {
+                self.max_run_cost_usd = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite() && *v >= 0.0)
+                    .ok_or_else(|| ConfigError::InvalidFloat {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })?;
+            }
+            "max_run_tokens" => {
+                self.max_run_tokens = value.parse().map_err(|_| ConfigError::InvalidInt {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })?;
+            }
+            "max_run_wall_clock_sec" => {
+                self.max_run_wall_clock_sec =
+                    value.parse().map_err(|_| ConfigError::InvalidInt {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })?;
+            }
             // Ignored keys from bin/loop that don't apply to daemon
             "mode" | "no_wait" | "no_gum" | "measure_cmd" | "measure_timeout_sec" => {
                 // Silently ignore
@@ -481,6 +686,11 @@ impl Config {
         if self.skills_builtin_dir.is_relative() {
             self.skills_builtin_dir = workspace_root.join(&self.skills_builtin_dir);
         }
+        if let Some(ref fixture) = self.replay_fixture {
+            if fixture.is_relative() {
+                self.replay_fixture = Some(workspace_root.join(fixture));
+            }
+        }
     }
 }
 
@@ -511,6 +721,8 @@ mod tests {
         assert_eq!(config.iterations, 50);
         assert_eq!(config.completion_mode, CompletionMode::Trailing);
         assert!(config.reviewer);
+        assert!(!config.planner);
+        assert!(!config.parallel_tasks);
         assert!(config.prompt_file.is_none());
         assert!(config.context_files.is_empty());
         assert_eq!(config.run_naming_mode, RunNameSource::Haiku);
@@ -524,12 +736,16 @@ mod tests {
 model="sonnet"
 iterations=100
 reviewer=false
+planner=true
+parallel_tasks=true
 completion_mode=exact
 "#;
         config.parse_content(content, "test".into()).unwrap();
         assert_eq!(config.model, "sonnet");
         assert_eq!(config.iterations, 100);
         assert!(!config.reviewer);
+        assert!(config.planner);
+        assert!(config.parallel_tasks);
         assert_eq!(config.completion_mode, CompletionMode::Exact);
     }
 
@@ -621,6 +837,57 @@ worktrunk_copy_ignored=true
         assert!(result.is_err());
     }
 
+    #[test]
+    fn default_config_has_expected_agent_backend_values() {
+        let config = Config::default();
+        assert_eq!(config.agent_backend, AgentBackendKind::Claude);
+        assert_eq!(config.claude_bin, PathBuf::from("claude"));
+        assert!(config.agent_command.is_none());
+        assert_eq!(config.openai_base_url, "https://api.openai.com/v1");
+        assert_eq!(config.openai_api_key_env, "OPENAI_API_KEY");
+    }
+
+    #[test]
+    fn parse_agent_backend_config() {
+        let mut config = Config::default();
+        let content = r#"
+agent_backend=openai
+openai_base_url=http://localhost:11434/v1/
+openai_api_key_env=LOCAL_LLM_KEY
+"#;
+        config.parse_content(content, "test".into()).unwrap();
+        assert_eq!(config.agent_backend, AgentBackendKind::Openai);
+        assert_eq!(config.openai_base_url, "http://localhost:11434/v1");
+        assert_eq!(config.openai_api_key_env, "LOCAL_LLM_KEY");
+
+        let content = r#"
+agent_backend=command
+agent_command="aider --message-file -"
+"#;
+        config.parse_content(content, "test".into()).unwrap();
+        assert_eq!(config.agent_backend, AgentBackendKind::Command);
+        assert_eq!(
+            config.agent_command.as_deref(),
+            Some("aider --message-file -")
+        );
+
+        let content = "agent_backend=replay\nreplay_fixture=fixtures/happy-path\n";
+        config.parse_content(content, "test".into()).unwrap();
+        config.resolve_paths(Path::new("/workspace"));
+        assert_eq!(config.agent_backend, AgentBackendKind::Replay);
+        assert_eq!(
+            config.replay_fixture,
+            Some(PathBuf::from("/workspace/fixtures/happy-path"))
+        );
+    }
+
+    #[test]
+    fn parse_agent_backend_invalid() {
+        let mut config = Config::default();
+        let result = config.parse_content("agent_backend=gemini", "test".into());
+        assert!(result.is_err());
+    }
+
     #[test]
     fn default_config_has_expected_postmortem_values() {
         let config = Config::default();
@@ -711,4 +978,142 @@ max_consecutive_review_failures=2
         config.parse_content(content, "test".into()).unwrap();
         assert_eq!(config.max_consecutive_verification_failures, 0);
     }
+
+    #[test]
+    fn parse_log_retention_config() {
+        let mut config = Config::default();
+        assert!(config.log_compression);
+        assert_eq!(config.max_run_log_mb, 0);
+
+        let content = "log_compression=false\nmax_run_log_mb=512";
+        config.parse_content(content, "test".into()).unwrap();
+        assert!(!config.log_compression);
+        assert_eq!(config.max_run_log_mb, 512);
+
+        let result = config.parse_content("max_run_log_mb=lots", "test".into());
+        assert!(matches!(result, Err(ConfigError::InvalidInt { .. })));
+    }
+
+    #[test]
+    fn default_config_has_expected_budget_values() {
+        let config = Config::default();
+        assert!(config.max_run_cost_usd.abs() < f64::EPSILON);
+        assert_eq!(config.max_run_tokens, 0);
+        assert_eq!(config.max_run_wall_clock_sec, 0);
+    }
+
+    #[test]
+    fn parse_budget_config() {
+        let mut config = Config::default();
+        let content = r#"
+max_run_cost_usd=12.50
+max_run_tokens=2000000
+max_run_wall_clock_sec=7200
+"#;
+        config.parse_content(content, "test".into()).unwrap();
+        assert!((config.max_run_cost_usd - 12.5).abs() < f64::EPSILON);
+        assert_eq!(config.max_run_tokens, 2_000_000);
+        assert_eq!(config.max_run_wall_clock_sec, 7200);
+    }
+
+    #[test]
+    fn parse_budget_config_rejects_invalid_cost() {
+        for value in ["abc", "-1", "inf"] {
+            let mut config = Config::default();
+            let content = format!("max_run_cost_usd={value}");
+            let result = config.parse_content(&content, "test".into());
+            assert!(
+                matches!(result, Err(ConfigError::InvalidFloat { .. })),
+                "expected InvalidFloat for {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_tool_policy_config() {
+        let mut config = Config::default();
+        assert!(config.tool_policy.is_empty());
+        let content = r#"
+tool_policy.allow = "Edit | Write | Bash(cargo:*)"
+tool_policy.deny = Bash(git push:*)
+"#;
+        config.parse_content(content, "test".into()).unwrap();
+        assert_eq!(config.tool_policy.allow, ["Edit", "Write", "Bash(cargo:*)"]);
+        assert_eq!(config.tool_policy.deny, ["Bash(git push:*)"]);
+
+        let result = config.parse_content("tool_policy.deny=Bash(git push", "test".into());
+        assert!(matches!(result, Err(ConfigError::InvalidLine(_))));
+    }
+
+    #[test]
+    fn parse_sandbox_config() {
+        let mut config = Config::default();
+        assert_eq!(config.sandbox, SandboxMode::None);
+        let content = r#"
+sandbox=fs+net
+sandbox_writable="/home/dev/.cache | /tmp/builds"
+"#;
+        config.parse_content(content, "test".into()).unwrap();
+        assert_eq!(config.sandbox, SandboxMode::FsNet);
+        assert_eq!(
+            config.sandbox_writable,
+            [
+                PathBuf::from("/home/dev/.cache"),
+                PathBuf::from("/tmp/builds")
+            ]
+        );
+        assert_eq!(config.sandbox_agent_ports, [443]);
+
+        config
+            .parse_content("sandbox_agent_ports=\"443 | 8443\"", "test".into())
+            .unwrap();
+        assert_eq!(config.sandbox_agent_ports, [443, 8443]);
+        config
+            .parse_content("sandbox_agent_ports=", "test".into())
+            .unwrap();
+        assert!(config.sandbox_agent_ports.is_empty());
+        let result = config.parse_content("sandbox_agent_ports=https", "test".into());
+        assert!(matches!(result, Err(ConfigError::InvalidLine(_))));
+
+        let result = config.parse_content("sandbox=docker", "test".into());
+        assert!(matches!(result, Err(ConfigError::InvalidLine(_))));
+    }
+
+    #[test]
+    fn parse_session_resume_config() {
+        let mut config = Config::default();
+        assert!(!config.session_resume);
+        assert_eq!(config.session_max_turns, 10);
+        config
+            .parse_content("session_resume=true\nsession_max_turns=4\n", "test".into())
+            .unwrap();
+        assert!(config.session_resume);
+        assert_eq!(config.session_max_turns, 4);
+
+        let result = config.parse_content("session_max_turns=many", "test".into());
+        assert!(matches!(result, Err(ConfigError::InvalidInt { .. })));
+    }
+
+    #[test]
+    fn parse_resource_limits_config() {
+        let mut config = Config::default();
+        let content = r#"
+limits.memory_mb=4096
+limits.verification.max_procs=256
+limits.cgroup_parent=/sys/fs/cgroup/loop
+"#;
+        config.parse_content(content, "test".into()).unwrap();
+        let verify = config
+            .resource_limits
+            .for_phase(crate::types::StepPhase::Verification);
+        assert_eq!(verify.memory_mb, Some(4096));
+        assert_eq!(verify.max_procs, Some(256));
+        assert_eq!(
+            config.resource_limits.cgroup_parent,
+            Some(PathBuf::from("/sys/fs/cgroup/loop"))
+        );
+
+        let result = config.parse_content("limits.memory=1", "test".into());
+        assert!(matches!(result, Err(ConfigError::InvalidLine(_))));
+    }
 }
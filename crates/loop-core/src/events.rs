@@ -2,7 +2,11 @@
 //!
 //! Event names and payloads match Section 4.3 of the spec.
 
-use crate::types::{Id, RunNameSource, WatchdogSignal, WorktreeProvider};
+use crate::types::{
+    DependencyCondition, Id, RateLimitKind, RunNameSource, RunStatus, SandboxMode, WatchdogSignal,
+    WorktreeProvider,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Event type names (Section 4.3).
@@ -34,6 +38,26 @@ pub enum EventType {
     SkillsSelected,
     /// Skill load failed (open-skills-orchestration.md Section 4.3).
     SkillsLoadFailed,
+    /// A per-run budget or the daemon's daily spend cap was exhausted.
+    BudgetExceeded,
+    /// The tool policy denied an agent tool call.
+    ToolPermissionDenied,
+    /// A sandboxed agent or verification process was denied filesystem or network access.
+    SandboxViolation,
+    /// The API throttled a step; new launches for the model pause daemon-wide.
+    ApiBackoffStarted,
+    /// A step succeeded after the backoff cooled down; launches resume.
+    ApiBackoffEnded,
+    /// A dependency can no longer be met; the pending run was canceled.
+    RunDependencyFailed,
+    /// A worker claimed a step in distributed mode.
+    LeaseGranted,
+    /// A worker stopped renewing its lease; the step was requeued.
+    LeaseExpired,
+    /// `parallel_tasks` is on but the run implements its plan tasks serially.
+    ParallelTasksSerial,
+    /// Run status changed. Synthetic: published on the event bus, never stored.
+    RunStatusChanged,
 }
 
 impl EventType {
@@ -55,6 +79,16 @@ impl EventType {
             Self::SkillsDiscovered => "SKILLS_DISCOVERED",
             Self::SkillsSelected => "SKILLS_SELECTED",
             Self::SkillsLoadFailed => "SKILLS_LOAD_FAILED",
+            Self::BudgetExceeded => "BUDGET_EXCEEDED",
+            Self::ToolPermissionDenied => "TOOL_PERMISSION_DENIED",
+            Self::SandboxViolation => "SANDBOX_VIOLATION",
+            Self::ApiBackoffStarted => "API_BACKOFF_STARTED",
+            Self::ApiBackoffEnded => "API_BACKOFF_ENDED",
+            Self::RunDependencyFailed => "RUN_DEPENDENCY_FAILED",
+            Self::LeaseGranted => "LEASE_GRANTED",
+            Self::LeaseExpired => "LEASE_EXPIRED",
+            Self::ParallelTasksSerial => "PARALLEL_TASKS_SERIAL",
+            Self::RunStatusChanged => "RUN_STATUS_CHANGED",
         }
     }
 }
@@ -117,6 +151,15 @@ pub struct RunFailedPayload {
     pub reason: String,
 }
 
+/// Payload for `RUN_DEPENDENCY_FAILED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunDependencyFailedPayload {
+    pub run_id: Id,
+    pub dependency_run_id: Id,
+    pub condition: DependencyCondition,
+    pub reason: String,
+}
+
 /// Payload for `WORKTREE_PROVIDER_SELECTED` event.
 ///
 /// See worktrunk-integration.md Section 4.3.
@@ -187,7 +230,7 @@ pub struct SkillsDiscoveredPayload {
     pub run_id: Id,
     /// Number of skills discovered.
     pub count: usize,
-    /// Locations where skills were found (e.g., ["project", "global"]).
+    /// Locations where skills were found (e.g., `["project", "global"]`).
     pub locations: Vec<String>,
     /// Names of discovered skills.
     pub names: Vec<String>,
@@ -235,6 +278,109 @@ pub struct SkillsSelectedPayload {
     pub errors: Vec<String>,
 }
 
+/// Payload for `BUDGET_EXCEEDED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetExceededPayload {
+    pub run_id: Id,
+    /// Limit that was hit: `cost_usd`, `tokens`, `wall_clock_sec`, or `daily_cost_usd`.
+    pub limit: String,
+    /// Amount used when the limit was checked.
+    pub used: f64,
+    /// Configured maximum.
+    pub max: f64,
+}
+
+/// Payload for `TOOL_PERMISSION_DENIED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolPermissionDeniedPayload {
+    pub run_id: Id,
+    pub step_id: Id,
+    pub tool_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    /// Tool input, redacted like the agent action audit log.
+    pub tool_input: serde_json::Value,
+}
+
+/// Payload for `SANDBOX_VIOLATION` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxViolationPayload {
+    pub run_id: Id,
+    pub step_id: Id,
+    /// `agent` or `verification`.
+    pub process: String,
+    /// Sandbox mode in effect (`fs` or `fs+net`).
+    pub sandbox: SandboxMode,
+    /// Command or tool call that hit the sandbox.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Output line reporting the denied access.
+    pub detail: String,
+}
+
+/// Payload for `API_BACKOFF_STARTED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiBackoffStartedPayload {
+    pub run_id: Id,
+    pub step_id: Id,
+    pub model: String,
+    pub signal: RateLimitKind,
+    /// Retry-after hint from the stream, when the API sent one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_sec: Option<u64>,
+    pub backoff_sec: u64,
+    /// When the first probe step may launch.
+    pub until: DateTime<Utc>,
+}
+
+/// Payload for `API_BACKOFF_ENDED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiBackoffEndedPayload {
+    pub run_id: Id,
+    pub step_id: Id,
+    pub model: String,
+    /// Throttled attempts while the backoff was open.
+    pub trips: u32,
+    pub paused_sec: u64,
+}
+
+/// Payload for `LEASE_GRANTED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaseGrantedPayload {
+    pub step_id: Id,
+    pub worker_id: Id,
+    pub attempt: u32,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Payload for `LEASE_EXPIRED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaseExpiredPayload {
+    pub step_id: Id,
+    pub worker_id: Id,
+    pub attempt: u32,
+}
+
+/// Payload for `PARALLEL_TASKS_SERIAL` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParallelTasksSerialPayload {
+    pub run_id: Id,
+    /// `untagged_tasks`, `invalid_graph`, or `no_run_slots`.
+    pub cause: String,
+    pub detail: String,
+}
+
+/// Payload for the synthetic `RUN_STATUS_CHANGED` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStatusChangedPayload {
+    pub run_id: Id,
+    pub workspace_root: String,
+    pub from: RunStatus,
+    pub to: RunStatus,
+}
+
 /// Union type for all event payloads.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -245,6 +391,8 @@ pub enum EventPayload {
     StepFinished(StepFinishedPayload),
     WatchdogRewrite(WatchdogRewritePayload),
     RunCompleted(RunCompletedPayload),
+    // Before `RunFailed`, whose fields it is a superset of (untagged).
+    RunDependencyFailed(RunDependencyFailedPayload),
     RunFailed(RunFailedPayload),
     WorktreeProviderSelected(WorktreeProviderSelectedPayload),
     WorktreeCreated(WorktreeCreatedPayload),
@@ -255,6 +403,16 @@ pub enum EventPayload {
     SkillsDiscovered(SkillsDiscoveredPayload),
     SkillsSelected(SkillsSelectedPayload),
     SkillsLoadFailed(SkillsLoadFailedPayload),
+    BudgetExceeded(BudgetExceededPayload),
+    ToolPermissionDenied(ToolPermissionDeniedPayload),
+    SandboxViolation(SandboxViolationPayload),
+    ApiBackoffStarted(ApiBackoffStartedPayload),
+    ApiBackoffEnded(ApiBackoffEndedPayload),
+    // Before `LeaseExpired`, whose fields it is a superset of (untagged).
+    LeaseGranted(LeaseGrantedPayload),
+    LeaseExpired(LeaseExpiredPayload),
+    ParallelTasksSerial(ParallelTasksSerialPayload),
+    RunStatusChanged(RunStatusChangedPayload),
 }
 
 impl EventPayload {
@@ -266,6 +424,7 @@ impl EventPayload {
             Self::StepFinished(_) => EventType::StepFinished,
             Self::WatchdogRewrite(_) => EventType::WatchdogRewrite,
             Self::RunCompleted(_) => EventType::RunCompleted,
+            Self::RunDependencyFailed(_) => EventType::RunDependencyFailed,
             Self::RunFailed(_) => EventType::RunFailed,
             Self::WorktreeProviderSelected(_) => EventType::WorktreeProviderSelected,
             Self::WorktreeCreated(_) => EventType::WorktreeCreated,
@@ -276,6 +435,15 @@ impl EventPayload {
             Self::SkillsDiscovered(_) => EventType::SkillsDiscovered,
             Self::SkillsSelected(_) => EventType::SkillsSelected,
             Self::SkillsLoadFailed(_) => EventType::SkillsLoadFailed,
+            Self::BudgetExceeded(_) => EventType::BudgetExceeded,
+            Self::ToolPermissionDenied(_) => EventType::ToolPermissionDenied,
+            Self::SandboxViolation(_) => EventType::SandboxViolation,
+            Self::ApiBackoffStarted(_) => EventType::ApiBackoffStarted,
+            Self::ApiBackoffEnded(_) => EventType::ApiBackoffEnded,
+            Self::LeaseGranted(_) => EventType::LeaseGranted,
+            Self::LeaseExpired(_) => EventType::LeaseExpired,
+            Self::ParallelTasksSerial(_) => EventType::ParallelTasksSerial,
+            Self::RunStatusChanged(_) => EventType::RunStatusChanged,
         }
     }
 
@@ -646,4 +814,155 @@ mod tests {
         });
         assert_eq!(load_failed.event_type(), EventType::SkillsLoadFailed);
     }
+
+    #[test]
+    fn budget_exceeded_payload_round_trips() {
+        let payload = EventPayload::BudgetExceeded(BudgetExceededPayload {
+            run_id: Id::from_string("run-123"),
+            limit: "cost_usd".to_string(),
+            used: 5.25,
+            max: 5.0,
+        });
+        assert_eq!(payload.event_type().as_str(), "BUDGET_EXCEEDED");
+
+        let json = payload.to_json().unwrap();
+        let parsed: EventPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event_type(), EventType::BudgetExceeded);
+    }
+
+    #[test]
+    fn tool_permission_denied_payload_round_trips() {
+        let payload = EventPayload::ToolPermissionDenied(ToolPermissionDeniedPayload {
+            run_id: Id::from_string("run-123"),
+            step_id: Id::from_string("step-1"),
+            tool_name: "Bash".to_string(),
+            tool_use_id: Some("toolu_1".to_string()),
+            tool_input: serde_json::json!({"command": "git push"}),
+        });
+        assert_eq!(payload.event_type().as_str(), "TOOL_PERMISSION_DENIED");
+
+        let json = payload.to_json().unwrap();
+        let parsed: EventPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event_type(), EventType::ToolPermissionDenied);
+    }
+
+    #[test]
+    fn sandbox_violation_payload_round_trips() {
+        let payload = EventPayload::SandboxViolation(SandboxViolationPayload {
+            run_id: Id::from_string("run-123"),
+            step_id: Id::from_string("step-1"),
+            process: "verification".to_string(),
+            sandbox: SandboxMode::FsNet,
+            command: Some("cargo test".to_string()),
+            exit_code: Some(101),
+            detail: "error: Permission denied (os error 13)".to_string(),
+        });
+        assert_eq!(payload.event_type().as_str(), "SANDBOX_VIOLATION");
+
+        let json = payload.to_json().unwrap();
+        assert!(json.contains(r#""sandbox":"fs+net""#));
+        let parsed: EventPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event_type(), EventType::SandboxViolation);
+    }
+
+    #[test]
+    fn api_backoff_payloads_round_trip() {
+        let started = EventPayload::ApiBackoffStarted(ApiBackoffStartedPayload {
+            run_id: Id::from_string("run-123"),
+            step_id: Id::from_string("step-1"),
+            model: "opus".to_string(),
+            signal: RateLimitKind::Overloaded,
+            retry_after_sec: Some(20),
+            backoff_sec: 20,
+            until: "2026-01-01T00:00:20Z".parse().unwrap(),
+        });
+        assert_eq!(started.event_type().as_str(), "API_BACKOFF_STARTED");
+        let json = started.to_json().unwrap();
+        assert!(json.contains(r#""signal":"overloaded""#));
+        let parsed: EventPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event_type(), EventType::ApiBackoffStarted);
+
+        let ended = EventPayload::ApiBackoffEnded(ApiBackoffEndedPayload {
+            run_id: Id::from_string("run-123"),
+            step_id: Id::from_string("step-2"),
+            model: "opus".to_string(),
+            trips: 3,
+            paused_sec: 95,
+        });
+        assert_eq!(ended.event_type().as_str(), "API_BACKOFF_ENDED");
+        let parsed: EventPayload = serde_json::from_str(&ended.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.event_type(), EventType::ApiBackoffEnded);
+    }
+
+    #[test]
+    fn run_dependency_failed_payload_is_not_read_as_run_failed() {
+        let payload = EventPayload::RunDependencyFailed(RunDependencyFailedPayload {
+            run_id: Id::from_string("run-b"),
+            dependency_run_id: Id::from_string("run-a"),
+            condition: DependencyCondition::Merged,
+            reason: "run run-a failed".to_string(),
+        });
+        assert_eq!(payload.event_type().as_str(), "RUN_DEPENDENCY_FAILED");
+        let json = payload.to_json().unwrap();
+        assert!(json.contains(r#""condition":"merged""#));
+        let parsed: EventPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event_type(), EventType::RunDependencyFailed);
+
+        let failed = EventPayload::RunFailed(RunFailedPayload {
+            run_id: Id::from_string("run-a"),
+            reason: "max_iterations".to_string(),
+        });
+        let parsed: EventPayload = serde_json::from_str(&failed.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.event_type(), EventType::RunFailed);
+    }
+
+    #[test]
+    fn lease_payloads_round_trip() {
+        let granted = EventPayload::LeaseGranted(LeaseGrantedPayload {
+            step_id: Id::from_string("step-1"),
+            worker_id: Id::from_string("worker-1"),
+            attempt: 2,
+            expires_at: Utc::now(),
+        });
+        assert_eq!(granted.event_type().as_str(), "LEASE_GRANTED");
+        let parsed: EventPayload = serde_json::from_str(&granted.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.event_type(), EventType::LeaseGranted);
+
+        let expired = EventPayload::LeaseExpired(LeaseExpiredPayload {
+            step_id: Id::from_string("step-1"),
+            worker_id: Id::from_string("worker-1"),
+            attempt: 2,
+        });
+        assert_eq!(expired.event_type().as_str(), "LEASE_EXPIRED");
+        let parsed: EventPayload = serde_json::from_str(&expired.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.event_type(), EventType::LeaseExpired);
+    }
+
+    #[test]
+    fn parallel_tasks_serial_payload_round_trips() {
+        let payload = EventPayload::ParallelTasksSerial(ParallelTasksSerialPayload {
+            run_id: Id::from_string("run-123"),
+            cause: "no_run_slots".to_string(),
+            detail: "max_runs_per_workspace is 1".to_string(),
+        });
+        assert_eq!(payload.event_type().as_str(), "PARALLEL_TASKS_SERIAL");
+        let parsed: EventPayload = serde_json::from_str(&payload.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.event_type(), EventType::ParallelTasksSerial);
+    }
+
+    #[test]
+    fn run_status_changed_payload_round_trips() {
+        let payload = EventPayload::RunStatusChanged(RunStatusChangedPayload {
+            run_id: Id::from_string("run-123"),
+            workspace_root: "/workspace".to_string(),
+            from: RunStatus::Running,
+            to: RunStatus::Canceled,
+        });
+        assert_eq!(payload.event_type().as_str(), "RUN_STATUS_CHANGED");
+
+        let json = payload.to_json().unwrap();
+        assert!(json.contains("\"to\":\"CANCELED\""));
+        let parsed: EventPayload = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event_type(), EventType::RunStatusChanged);
+    }
 }
@@ -1,24 +1,36 @@
 pub mod artifacts;
+pub mod audit;
 pub mod completion;
 pub mod config;
 pub mod events;
+pub mod limits;
+pub mod logs;
 pub mod plan;
 pub mod prompt;
 pub mod report;
 pub mod skills;
+pub mod tool_policy;
+pub mod transcript;
 pub mod types;
 
 pub use artifacts::{
     global_run_dir, mirror_artifact, workspace_run_dir, write_and_mirror_artifact,
 };
+pub use audit::{AgentAction, AgentActionKind, AgentActionStatus};
 pub use config::Config;
+pub use limits::{ResourceLimitHit, ResourceLimitKind, ResourceLimits, ResourceLimitsConfig};
 pub use plan::{
-    count_pending_tasks, extract_skill_hints, select_task, select_task_from_content, PlanError,
-    TaskSelection,
+    count_pending_tasks, extract_skill_hints, parse_tasks, select_task, select_task_by_id,
+    select_task_from_content, PlanError, PlanTask, TaskGraph, TaskSelection, TaskState,
 };
 pub use report::{ReportRow, ReportWriter};
+pub use tool_policy::{PermissionDenial, ToolPolicy};
+pub use transcript::{TranscriptBuilder, TranscriptEntry, TranscriptEntryKind};
 pub use types::{
-    Artifact, ArtifactLocation, ArtifactMode, CompletionMode, Event, Id, MergeStrategy,
-    QueuePolicy, ReviewStatus, Run, RunNameSource, RunStatus, RunWorktree, Step, StepPhase,
-    StepStatus, WatchdogDecision, WatchdogSignal, WorktreeProvider,
+    AgentBackendKind, Artifact, ArtifactLocation, ArtifactMode, CompletionMode,
+    DependencyCondition, Event, GcReport, Id, Lease, MergeStrategy, MissedFirePolicy,
+    OverlapPolicy, PruneReason, PrunedRun, QueueBlock, QueueEntry, QueuePolicy, RateLimitKind,
+    RetentionPolicy, ReviewStatus, Run, RunDependency, RunNameSource, RunStatus, RunWorktree,
+    SandboxMode, Schedule, Step, StepPhase, StepStatus, TokenUsage, WatchdogDecision,
+    WatchdogSignal, Worker, WorkerStatus, WorktreeProvider,
 };
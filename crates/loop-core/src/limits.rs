@@ -0,0 +1,291 @@
+//! Resource limits for agent and verification processes.
+//!
+//! Configured in `.loop/config`; `limits.<resource>` applies to every phase
+//! and `limits.<phase>.<resource>` overrides it for one phase:
+//!
+//! ```text
+//! limits.memory_mb = 4096
+//! limits.verification.max_procs = 512
+//! limits.verification.cpu_sec = 900
+//! limits.implementation.max_output_mb = 20
+//! ```
+//!
+//! `0` leaves a limit unset, so a phase falls back to the `limits.<resource>`
+//! value. The daemon enforces memory and process counts with a cgroup v2 child
+//! of `limits.cgroup_parent` when set, and with rlimits otherwise; CPU time
+//! always uses `RLIMIT_CPU`.
+
+use crate::types::StepPhase;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A limited resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceLimitKind {
+    /// Memory, in MB (`memory_mb`).
+    Memory,
+    /// CPU time per process, in seconds (`cpu_sec`).
+    CpuTime,
+    /// Number of processes (`max_procs`).
+    Processes,
+    /// Captured stdout/stderr, in MB (`max_output_mb`).
+    OutputSize,
+}
+
+impl ResourceLimitKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+            Self::CpuTime => "cpu_time",
+            Self::Processes => "processes",
+            Self::OutputSize => "output_size",
+        }
+    }
+
+    /// Config key suffix, e.g. `memory_mb`.
+    pub fn config_key(self) -> &'static str {
+        match self {
+            Self::Memory => "memory_mb",
+            Self::CpuTime => "cpu_sec",
+            Self::Processes => "max_procs",
+            Self::OutputSize => "max_output_mb",
+        }
+    }
+
+    fn from_config_key(key: &str) -> Option<Self> {
+        [
+            Self::Memory,
+            Self::CpuTime,
+            Self::Processes,
+            Self::OutputSize,
+        ]
+        .into_iter()
+        .find(|kind| kind.config_key() == key)
+    }
+
+    /// Human-readable limit, e.g. `512 MB`.
+    fn describe(self, value: u64) -> String {
+        match self {
+            Self::Memory | Self::OutputSize => format!("{value} MB"),
+            Self::CpuTime => format!("{value} CPU seconds"),
+            Self::Processes => format!("{value} processes"),
+        }
+    }
+}
+
+/// Limits for one phase. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    pub memory_mb: Option<u64>,
+    pub cpu_sec: Option<u64>,
+    pub max_procs: Option<u64>,
+    pub max_output_mb: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Whether no limit is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn get(&self, kind: ResourceLimitKind) -> Option<u64> {
+        match kind {
+            ResourceLimitKind::Memory => self.memory_mb,
+            ResourceLimitKind::CpuTime => self.cpu_sec,
+            ResourceLimitKind::Processes => self.max_procs,
+            ResourceLimitKind::OutputSize => self.max_output_mb,
+        }
+    }
+
+    fn set(&mut self, kind: ResourceLimitKind, value: Option<u64>) {
+        match kind {
+            ResourceLimitKind::Memory => self.memory_mb = value,
+            ResourceLimitKind::CpuTime => self.cpu_sec = value,
+            ResourceLimitKind::Processes => self.max_procs = value,
+            ResourceLimitKind::OutputSize => self.max_output_mb = value,
+        }
+    }
+
+    /// These limits, falling back to `defaults` for unset ones.
+    #[must_use]
+    pub fn or(self, defaults: Self) -> Self {
+        Self {
+            memory_mb: self.memory_mb.or(defaults.memory_mb),
+            cpu_sec: self.cpu_sec.or(defaults.cpu_sec),
+            max_procs: self.max_procs.or(defaults.max_procs),
+            max_output_mb: self.max_output_mb.or(defaults.max_output_mb),
+        }
+    }
+
+    /// The report for a breach of `kind`, if that limit is set.
+    pub fn hit(&self, kind: ResourceLimitKind) -> Option<ResourceLimitHit> {
+        self.get(kind).map(|limit| ResourceLimitHit { kind, limit })
+    }
+}
+
+/// Limits per phase plus where to create cgroups.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimitsConfig {
+    /// Applies to every phase (`limits.<resource>`).
+    pub default: ResourceLimits,
+    pub planning: ResourceLimits,
+    pub implementation: ResourceLimits,
+    pub review: ResourceLimits,
+    pub verification: ResourceLimits,
+    /// Delegated cgroup v2 directory to create per-process cgroups in
+    /// (`limits.cgroup_parent`). Its `cgroup.subtree_control` must enable the
+    /// `memory` and `pids` controllers.
+    pub cgroup_parent: Option<PathBuf>,
+}
+
+impl ResourceLimitsConfig {
+    /// Effective limits for a phase.
+    pub fn for_phase(&self, phase: StepPhase) -> ResourceLimits {
+        let overrides = match phase {
+            StepPhase::Planning => self.planning,
+            StepPhase::Implementation => self.implementation,
+            StepPhase::Review => self.review,
+            StepPhase::Verification => self.verification,
+            StepPhase::Watchdog | StepPhase::Merge => ResourceLimits::default(),
+        };
+        overrides.or(self.default)
+    }
+
+    /// Apply a `limits.*` config key (without the `limits.` prefix).
+    pub fn apply_key(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if key == "cgroup_parent" {
+            self.cgroup_parent = (!value.is_empty()).then(|| PathBuf::from(value));
+            return Ok(());
+        }
+
+        let (phase, resource) = match key.split_once('.') {
+            Some((phase, resource)) => (Some(phase), resource),
+            None => (None, key),
+        };
+        let limits = match phase {
+            None => &mut self.default,
+            Some("planning") => &mut self.planning,
+            Some("implementation") => &mut self.implementation,
+            Some("review") => &mut self.review,
+            Some("verification") => &mut self.verification,
+            Some(other) => {
+                return Err(format!(
+                    "unknown phase '{other}' (expected planning, implementation, review, or verification)"
+                ))
+            }
+        };
+        let kind = ResourceLimitKind::from_config_key(resource).ok_or_else(|| {
+            format!(
+                "unknown resource '{resource}' (expected memory_mb, cpu_sec, max_procs, or max_output_mb)"
+            )
+        })?;
+        let value: u64 = value
+            .parse()
+            .map_err(|_| format!("expected a non-negative integer, got '{value}'"))?;
+        limits.set(kind, (value > 0).then_some(value));
+        Ok(())
+    }
+}
+
+/// A limit a process ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimitHit {
+    pub kind: ResourceLimitKind,
+    /// The configured limit, in the resource's config unit.
+    pub limit: u64,
+}
+
+impl std::fmt::Display for ResourceLimitHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} limit of {} exceeded",
+            self.kind.as_str(),
+            self.kind.describe(self.limit)
+        )
+    }
+}
+
+impl ResourceLimitHit {
+    /// Explanation for runner notes, with a hint on how to stay within the limit.
+    pub fn runner_note(&self) -> String {
+        let hint = match self.kind {
+            ResourceLimitKind::Memory => {
+                "Reduce peak memory: run fewer tests or build jobs in parallel \
+                 (e.g. `--test-threads`, `-j`) and avoid loading large inputs at once."
+            }
+            ResourceLimitKind::CpuTime => {
+                "A single process used too much CPU time: look for infinite loops, \
+                 or run a narrower subset of the work."
+            }
+            ResourceLimitKind::Processes => {
+                "Too many processes were running at once: limit parallelism and make \
+                 sure nothing forks in an unbounded loop."
+            }
+            ResourceLimitKind::OutputSize => {
+                "Too much output was produced and the process was stopped: use quieter \
+                 flags or redirect verbose logs to a file and inspect only the relevant part."
+            }
+        };
+        format!(
+            "The process was stopped by the resource limits: {self} ({}).\n{hint}\n",
+            self.kind.config_key()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_limits_override_defaults() {
+        let mut config = ResourceLimitsConfig::default();
+        config.apply_key("memory_mb", "4096").unwrap();
+        config.apply_key("cpu_sec", "600").unwrap();
+        config.apply_key("verification.memory_mb", "1024").unwrap();
+        config.apply_key("verification.cpu_sec", "0").unwrap();
+
+        let verify = config.for_phase(StepPhase::Verification);
+        assert_eq!(verify.memory_mb, Some(1024));
+        // 0 at phase level leaves the default in place.
+        assert_eq!(verify.cpu_sec, Some(600));
+
+        let implementation = config.for_phase(StepPhase::Implementation);
+        assert_eq!(implementation.memory_mb, Some(4096));
+        assert_eq!(implementation.max_procs, None);
+        assert_eq!(config.for_phase(StepPhase::Merge), config.default);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_values() {
+        let mut config = ResourceLimitsConfig::default();
+        assert!(config.apply_key("deploy.memory_mb", "1").is_err());
+        assert!(config.apply_key("swap_mb", "1").is_err());
+        assert!(config.apply_key("memory_mb", "-1").is_err());
+        config
+            .apply_key("cgroup_parent", "/sys/fs/cgroup/loop")
+            .unwrap();
+        assert_eq!(
+            config.cgroup_parent,
+            Some(PathBuf::from("/sys/fs/cgroup/loop"))
+        );
+    }
+
+    #[test]
+    fn hit_explains_the_limit() {
+        let limits = ResourceLimits {
+            memory_mb: Some(512),
+            ..ResourceLimits::default()
+        };
+        assert_eq!(limits.hit(ResourceLimitKind::CpuTime), None);
+        let hit = limits.hit(ResourceLimitKind::Memory).unwrap();
+        assert_eq!(hit.to_string(), "memory limit of 512 MB exceeded");
+        let note = hit.runner_note();
+        assert!(note.contains("memory limit of 512 MB exceeded (memory_mb)"));
+        assert!(note.contains("--test-threads"));
+    }
+}
@@ -0,0 +1,293 @@
+//! Step log storage: zstd compression, per-run size cap, and a reader that
+//! serves compressed and live logs alike.
+//!
+//! Runners write `iter-XX-<phase>.log` (plus `.jsonl` for structured agent
+//! output) uncompressed while a step runs. Once the step finishes the daemon
+//! replaces each file with `<name>.zst`. Step output paths keep naming the
+//! uncompressed file; [`open`] and [`read_from`] resolve whichever form exists.
+//! Artifact records name the file actually mirrored (and checksummed), which is
+//! the `.zst` once the step's log has been compressed.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Suffix appended to a log's file name once it is compressed.
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+
+/// zstd level for finished logs: fast, and text logs still shrink ~10x.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Extensions (after the `iter-XX-<phase>` stem) counted against the size cap.
+const CAPPED_EXTENSIONS: [&str; 4] = ["log", "log.zst", "jsonl", "jsonl.zst"];
+
+/// Path of the compressed form of `path` (`iter-01-impl.log.zst`).
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(COMPRESSED_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Whether `path` names a compressed (`.zst`) log.
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zst")
+}
+
+/// Resolve a log path to the file that currently holds it.
+///
+/// Returns `path` while it is live or uncompressed, its `.zst` sibling once
+/// compressed, and `None` if neither exists (never written, or evicted).
+pub fn resolve(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let compressed = compressed_path(path);
+    compressed.is_file().then_some(compressed)
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("log not found: {}", path.display()),
+    )
+}
+
+/// Open a log for reading, decompressing transparently.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let resolved = resolve(path).ok_or_else(|| not_found(path))?;
+    let file = File::open(&resolved)?;
+    if is_compressed(&resolved) {
+        Ok(Box::new(zstd::Decoder::new(file)?))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Read a log from byte `offset` of its uncompressed content to the current end.
+///
+/// Live logs are read with a seek; compressed logs are decoded up to `offset`.
+pub fn read_from(path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+    let resolved = resolve(path).ok_or_else(|| not_found(path))?;
+    let mut file = File::open(&resolved)?;
+    let mut buf = Vec::new();
+    if is_compressed(&resolved) {
+        let mut decoder = zstd::Decoder::new(file)?;
+        io::copy(&mut (&mut decoder).take(offset), &mut io::sink())?;
+        decoder.read_to_end(&mut buf)?;
+    } else {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_to_end(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+/// Compress a finished log in place, replacing it with `<path>.zst`.
+///
+/// Returns `None` if `path` does not exist (never written or already
+/// compressed). The original is only removed once the compressed copy is
+/// complete, so readers always find one of the two.
+pub fn compress(path: &Path) -> io::Result<Option<PathBuf>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let target = compressed_path(path);
+    let partial = target.with_extension("zst.partial");
+    {
+        let mut input = File::open(path)?;
+        let mut encoder = zstd::Encoder::new(File::create(&partial)?, COMPRESSION_LEVEL)?;
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+    }
+    fs::rename(&partial, &target)?;
+    fs::remove_file(path)?;
+    Ok(Some(target))
+}
+
+/// An iteration's log files in a run directory.
+#[derive(Debug)]
+struct Iteration {
+    files: Vec<(PathBuf, u64)>,
+    modified: SystemTime,
+}
+
+/// Group a run directory's step log files by `iter-XX-<phase>` stem.
+fn iterations(run_dir: &Path) -> io::Result<BTreeMap<String, Iteration>> {
+    let mut iterations: BTreeMap<String, Iteration> = BTreeMap::new();
+    for entry in fs::read_dir(run_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((stem, ext)) = name.split_once('.') else {
+            continue;
+        };
+        if !stem.starts_with("iter-") || !CAPPED_EXTENSIONS.contains(&ext) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let iteration = iterations
+            .entry(stem.to_string())
+            .or_insert_with(|| Iteration {
+                files: Vec::new(),
+                modified,
+            });
+        iteration.modified = iteration.modified.max(modified);
+        iteration.files.push((entry.path(), metadata.len()));
+    }
+    Ok(iterations)
+}
+
+/// Step logs (`iter-XX-<phase>.log`) in a run directory, oldest first.
+///
+/// Paths name the uncompressed log whether or not it has been compressed, so
+/// they can be passed straight to [`open`] and [`read_from`].
+pub fn iteration_logs(run_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut logs: Vec<(SystemTime, PathBuf)> = iterations(run_dir)?
+        .into_iter()
+        .filter(|(_, iteration)| {
+            iteration.files.iter().any(|(path, _)| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                name.ends_with(".log") || name.ends_with(".log.zst")
+            })
+        })
+        .map(|(stem, iteration)| (iteration.modified, run_dir.join(format!("{stem}.log"))))
+        .collect();
+    logs.sort();
+    Ok(logs.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Evict the oldest iterations until a run directory's step logs fit in `max_bytes`.
+///
+/// Counts `.log` and `.jsonl` files, compressed or not; `.tail.txt` files are
+/// kept. The newest iteration is never evicted. Returns the removed files.
+pub fn enforce_size_cap(run_dir: &Path, max_bytes: u64) -> io::Result<Vec<PathBuf>> {
+    let mut iterations: Vec<Iteration> = iterations(run_dir)?.into_values().collect();
+    iterations.sort_by_key(|iteration| iteration.modified);
+    let mut total: u64 = iterations
+        .iter()
+        .flat_map(|iteration| iteration.files.iter().map(|(_, len)| len))
+        .sum();
+
+    let mut evicted = Vec::new();
+    // The newest iteration is the step that just finished (or is still live).
+    iterations.pop();
+    for iteration in iterations {
+        if total <= max_bytes {
+            break;
+        }
+        for (path, len) in iteration.files {
+            fs::remove_file(&path)?;
+            total = total.saturating_sub(len);
+            evicted.push(path);
+        }
+    }
+    Ok(evicted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_log(path: &Path, content: &[u8]) {
+        let mut file = File::create(path).unwrap();
+        file.write_all(content).unwrap();
+    }
+
+    #[test]
+    fn compress_replaces_log_and_reader_decodes_it() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("iter-01-impl.log");
+        let content = "line of agent output\n".repeat(1000);
+        write_log(&log, content.as_bytes());
+
+        let compressed = compress(&log).unwrap().unwrap();
+        assert_eq!(compressed, dir.path().join("iter-01-impl.log.zst"));
+        assert!(!log.exists());
+        assert!(fs::metadata(&compressed).unwrap().len() < content.len() as u64 / 10);
+        assert_eq!(resolve(&log), Some(compressed));
+
+        let mut read = String::new();
+        open(&log).unwrap().read_to_string(&mut read).unwrap();
+        assert_eq!(read, content);
+
+        // Already compressed: nothing to do.
+        assert!(compress(&log).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_from_offsets_live_and_compressed_logs() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("iter-02-review.log");
+        write_log(&log, b"hello world");
+
+        assert_eq!(read_from(&log, 6).unwrap(), b"world");
+        compress(&log).unwrap();
+        assert_eq!(read_from(&log, 6).unwrap(), b"world");
+        assert_eq!(read_from(&log, 0).unwrap(), b"hello world");
+        assert!(read_from(&log, 100).unwrap().is_empty());
+
+        let missing = dir.path().join("iter-09-impl.log");
+        assert_eq!(
+            read_from(&missing, 0).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn iteration_logs_lists_compressed_and_live_logs_oldest_first() {
+        let dir = TempDir::new().unwrap();
+        write_log(&dir.path().join("iter-01-impl.log"), b"one");
+        compress(&dir.path().join("iter-01-impl.log")).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        write_log(&dir.path().join("iter-01-review.log"), b"two");
+        write_log(&dir.path().join("iter-01-review.tail.txt"), b"two");
+        write_log(&dir.path().join("run.log"), b"run");
+
+        let logs = iteration_logs(dir.path()).unwrap();
+        assert_eq!(
+            logs,
+            vec![
+                dir.path().join("iter-01-impl.log"),
+                dir.path().join("iter-01-review.log"),
+            ]
+        );
+    }
+
+    #[test]
+    fn size_cap_evicts_oldest_iterations_and_keeps_newest() {
+        let dir = TempDir::new().unwrap();
+        for (i, stem) in ["iter-01-impl", "iter-01-review", "iter-02-impl"]
+            .iter()
+            .enumerate()
+        {
+            write_log(&dir.path().join(format!("{stem}.log")), &[b'x'; 100]);
+            write_log(&dir.path().join(format!("{stem}.jsonl")), &[b'y'; 100]);
+            write_log(&dir.path().join(format!("{stem}.tail.txt")), b"tail");
+            if i < 2 {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+        }
+
+        // Under the cap: nothing to evict.
+        assert!(enforce_size_cap(dir.path(), 600).unwrap().is_empty());
+
+        let evicted = enforce_size_cap(dir.path(), 450).unwrap();
+        assert_eq!(evicted.len(), 2);
+        assert!(!dir.path().join("iter-01-impl.log").exists());
+        assert!(!dir.path().join("iter-01-impl.jsonl").exists());
+        assert!(dir.path().join("iter-01-impl.tail.txt").exists());
+        assert!(dir.path().join("iter-01-review.log").exists());
+
+        // Even a cap below the newest iteration keeps it.
+        enforce_size_cap(dir.path(), 1).unwrap();
+        assert!(!dir.path().join("iter-01-review.log").exists());
+        assert!(dir.path().join("iter-02-impl.log").exists());
+        assert!(dir.path().join("iter-02-impl.jsonl").exists());
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! Implements the plan task selector from the Open Skills orchestration spec.
 //! Parses plan markdown files with checkbox syntax and selects the next unchecked task.
+//! Tasks may carry IDs and `depends-on` annotations, which [`TaskGraph`] turns into
+//! a dependency graph for fanning a plan out into child runs.
 
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 
@@ -26,6 +29,49 @@ pub enum PlanError {
     IoError(String),
     #[error("no unchecked tasks found in plan")]
     NoTasks,
+    #[error("duplicate task id: {0}")]
+    DuplicateTaskId(String),
+    #[error("task {task} depends on unknown task {dependency}")]
+    UnknownDependency { task: String, dependency: String },
+    #[error("dependency cycle involving task {0}")]
+    DependencyCycle(String),
+}
+
+/// Checkbox state of a plan task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// `[ ]`: not started.
+    Pending,
+    /// `[~]`: blocked; still counts as unfinished.
+    Blocked,
+    /// `[x]` or `[R]`: implemented.
+    Done,
+}
+
+/// A checkbox task with its optional ID and dependencies.
+///
+/// IDs and dependencies are written inline, with the annotation stripped from `label`:
+/// `- [ ] [storage] Add the storage layer (depends-on: types, config)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanTask {
+    pub id: Option<String>,
+    pub label: String,
+    /// Line number in the plan file (1-indexed).
+    pub line_number: usize,
+    pub section: Option<String>,
+    pub depends_on: Vec<String>,
+    pub state: TaskState,
+}
+
+impl PlanTask {
+    fn to_selection(&self) -> TaskSelection {
+        TaskSelection {
+            label: self.label.clone(),
+            line_number: self.line_number,
+            section: self.section.clone(),
+            skill_hints: extract_skill_hints(&self.label),
+        }
+    }
 }
 
 /// Parses a plan file and selects the next unchecked task.
@@ -45,8 +91,41 @@ pub fn select_task(plan_path: &Path) -> Result<Option<TaskSelection>, PlanError>
 
 /// Parses plan content and selects the next unchecked task.
 ///
-/// This is the core parsing logic, separated for testing.
+/// Tasks whose `depends-on` tasks are not yet done are skipped. If every
+/// unchecked task is waiting on another, the first one is returned anyway.
 pub fn select_task_from_content(content: &str) -> Option<TaskSelection> {
+    let tasks = parse_tasks(content);
+    let done: HashSet<&str> = tasks
+        .iter()
+        .filter(|task| task.state == TaskState::Done)
+        .filter_map(|task| task.id.as_deref())
+        .collect();
+
+    let mut pending = tasks.iter().filter(|task| task.state == TaskState::Pending);
+    let first = pending.clone().next()?;
+    let task = pending
+        .find(|task| {
+            task.depends_on
+                .iter()
+                .all(|dep| done.contains(dep.as_str()))
+        })
+        .unwrap_or(first);
+    Some(task.to_selection())
+}
+
+/// Selects the task with the given ID, whatever its state.
+pub fn select_task_by_id(content: &str, id: &str) -> Option<TaskSelection> {
+    parse_tasks(content)
+        .iter()
+        .find(|task| task.id.as_deref() == Some(id))
+        .map(PlanTask::to_selection)
+}
+
+/// Parses every checkbox task outside code blocks and verification sections.
+///
+/// `- [ ]?` manual QA items are skipped, as in [`select_task_from_content`].
+pub fn parse_tasks(content: &str) -> Vec<PlanTask> {
+    let mut tasks = Vec::new();
     let mut in_code_block = false;
     let mut in_verification_section = false;
     let mut current_section: Option<String> = None;
@@ -82,21 +161,194 @@ pub fn select_task_from_content(content: &str) -> Option<TaskSelection> {
             continue;
         }
 
-        // Look for unchecked task checkboxes: `- [ ]` not followed by `?`.
-        // Must match: `- [ ] task text` (with space after checkbox).
-        // Must NOT match: `- [ ]?` (manual QA marker).
-        if let Some(task_text) = parse_unchecked_task(trimmed) {
-            let skill_hints = extract_skill_hints(task_text);
-            return Some(TaskSelection {
-                label: task_text.to_string(),
+        if let Some((state, text)) = parse_task_line(trimmed) {
+            let (id, label, depends_on) = parse_task_annotations(text);
+            tasks.push(PlanTask {
+                id,
+                label,
                 line_number: line_idx + 1,
-                section: current_section,
-                skill_hints,
+                section: current_section.clone(),
+                depends_on,
+                state,
+            });
+        }
+    }
+
+    tasks
+}
+
+/// Parses a checkbox line of any state into its state and text.
+fn parse_task_line(line: &str) -> Option<(TaskState, &str)> {
+    if let Some(text) = parse_unchecked_task(line) {
+        return Some((TaskState::Pending, text));
+    }
+
+    let rest = line.strip_prefix("- [")?;
+    let mut chars = rest.chars();
+    let state = match chars.next()? {
+        '~' => TaskState::Blocked,
+        'x' | 'X' | 'R' => TaskState::Done,
+        _ => return None,
+    };
+    let text = chars.as_str().strip_prefix("] ")?.trim();
+    (!text.is_empty()).then_some((state, text))
+}
+
+/// Splits `[id] label (depends-on: a, b)` into its parts.
+fn parse_task_annotations(text: &str) -> (Option<String>, String, Vec<String>) {
+    let mut label = text;
+
+    let mut id = None;
+    if let Some(rest) = label.strip_prefix('[') {
+        if let Some((candidate, after)) = rest.split_once(']') {
+            if is_valid_task_id(candidate) {
+                id = Some(candidate.to_string());
+                label = after.trim_start();
+            }
+        }
+    }
+
+    let mut depends_on = Vec::new();
+    if let Some(start) = label.rfind("(depends-on:") {
+        if let Some(list) = label[start..]
+            .strip_prefix("(depends-on:")
+            .and_then(|rest| rest.trim_end().strip_suffix(')'))
+        {
+            depends_on = list
+                .split(',')
+                .map(str::trim)
+                .filter(|dep| !dep.is_empty())
+                .map(str::to_string)
+                .collect();
+            label = label[..start].trim_end();
+        }
+    }
+
+    (id, label.to_string(), depends_on)
+}
+
+/// Task IDs: 1-64 chars of lowercase letters, digits, `-` and `_`.
+fn is_valid_task_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Dependency graph over the tasks of a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskGraph {
+    tasks: Vec<PlanTask>,
+}
+
+impl TaskGraph {
+    /// Builds the graph, rejecting duplicate IDs, unknown dependencies and cycles.
+    pub fn from_content(content: &str) -> Result<Self, PlanError> {
+        let graph = Self {
+            tasks: parse_tasks(content),
+        };
+
+        let mut ids = HashSet::new();
+        for id in graph.tasks.iter().filter_map(|task| task.id.as_deref()) {
+            if !ids.insert(id) {
+                return Err(PlanError::DuplicateTaskId(id.to_string()));
+            }
+        }
+        for task in &graph.tasks {
+            if let Some(dependency) = task
+                .depends_on
+                .iter()
+                .find(|dep| !ids.contains(dep.as_str()))
+            {
+                return Err(PlanError::UnknownDependency {
+                    task: task.id.clone().unwrap_or_else(|| task.label.clone()),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+        graph.dependency_order()?;
+
+        Ok(graph)
+    }
+
+    pub fn tasks(&self) -> &[PlanTask] {
+        &self.tasks
+    }
+
+    pub fn get(&self, id: &str) -> Option<&PlanTask> {
+        self.tasks
+            .iter()
+            .find(|task| task.id.as_deref() == Some(id))
+    }
+
+    /// Unfinished tasks, in plan order.
+    pub fn unfinished(&self) -> impl Iterator<Item = &PlanTask> {
+        self.tasks
+            .iter()
+            .filter(|task| task.state != TaskState::Done)
+    }
+
+    /// Whether the plan can be fanned out: at least one unfinished task and
+    /// every unfinished task has an ID.
+    pub fn is_parallelizable(&self) -> bool {
+        let mut unfinished = self.unfinished().peekable();
+        unfinished.peek().is_some() && unfinished.all(|task| task.id.is_some())
+    }
+
+    /// Pending tasks not in `finished` whose dependencies are all done in the
+    /// plan or listed in `finished`, in plan order.
+    pub fn ready(&self, finished: &HashSet<String>) -> Vec<&PlanTask> {
+        self.tasks
+            .iter()
+            .filter(|task| task.state == TaskState::Pending)
+            .filter(|task| task.id.as_ref().is_some_and(|id| !finished.contains(id)))
+            .filter(|task| {
+                task.depends_on
+                    .iter()
+                    .all(|dep| finished.contains(dep) || self.is_done(dep))
+            })
+            .collect()
+    }
+
+    /// Tasks ordered so every task follows its dependencies, otherwise in plan order.
+    pub fn dependency_order(&self) -> Result<Vec<&PlanTask>, PlanError> {
+        let mut ordered: Vec<&PlanTask> = Vec::with_capacity(self.tasks.len());
+        let mut placed: HashSet<&str> = HashSet::new();
+        let mut remaining: Vec<&PlanTask> = self.tasks.iter().collect();
+
+        while !remaining.is_empty() {
+            let before = remaining.len();
+            remaining.retain(|task| {
+                if task
+                    .depends_on
+                    .iter()
+                    .all(|dep| placed.contains(dep.as_str()))
+                {
+                    if let Some(id) = task.id.as_deref() {
+                        placed.insert(id);
+                    }
+                    ordered.push(task);
+                    false
+                } else {
+                    true
+                }
             });
+            if remaining.len() == before {
+                let task = remaining[0];
+                return Err(PlanError::DependencyCycle(
+                    task.id.clone().unwrap_or_else(|| task.label.clone()),
+                ));
+            }
         }
+
+        Ok(ordered)
     }
 
-    None
+    fn is_done(&self, id: &str) -> bool {
+        self.get(id)
+            .is_some_and(|task| task.state == TaskState::Done)
+    }
 }
 
 /// Parses an unchecked task checkbox line.
@@ -530,4 +782,102 @@ mod tests {
         let name = "a".repeat(64);
         assert!(is_valid_skill_name(&name));
     }
+
+    const GRAPH_PLAN: &str = r#"
+## Phase 1
+- [x] [types] Add core types
+- [ ] [storage] Add storage (depends-on: types)
+- [ ] [api] Add API @axum (depends-on: storage, types)
+- [ ] [cli] Add CLI (depends-on: types)
+
+## Verification Checklist
+- [ ] [tests] Run tests
+"#;
+
+    #[test]
+    fn parses_task_ids_and_dependencies() {
+        let tasks = parse_tasks(GRAPH_PLAN);
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(tasks[0].id.as_deref(), Some("types"));
+        assert_eq!(tasks[0].state, TaskState::Done);
+        assert_eq!(tasks[2].label, "Add API @axum");
+        assert_eq!(tasks[2].depends_on, vec!["storage", "types"]);
+        assert_eq!(tasks[2].line_number, 5);
+        assert_eq!(tasks[2].section.as_deref(), Some("Phase 1"));
+    }
+
+    #[test]
+    fn annotations_are_optional_and_validated() {
+        let tasks = parse_tasks("- [ ] [WIP] Not an id\n- [~] Blocked (depends-on:)\n");
+        assert_eq!(tasks[0].id, None);
+        assert_eq!(tasks[0].label, "[WIP] Not an id");
+        assert_eq!(tasks[1].state, TaskState::Blocked);
+        assert_eq!(tasks[1].label, "Blocked");
+        assert!(tasks[1].depends_on.is_empty());
+    }
+
+    #[test]
+    fn selection_skips_tasks_with_unfinished_dependencies() {
+        let content = "- [ ] [b] Second (depends-on: a)\n- [ ] [a] First\n";
+        let selection = select_task_from_content(content).unwrap();
+        assert_eq!(selection.label, "First");
+
+        // Everything waiting: fall back to the first unchecked task.
+        let content = "- [ ] [a] A (depends-on: b)\n- [ ] [b] B (depends-on: a)\n";
+        assert_eq!(select_task_from_content(content).unwrap().label, "A");
+    }
+
+    #[test]
+    fn selects_task_by_id() {
+        let selection = select_task_by_id(GRAPH_PLAN, "api").unwrap();
+        assert_eq!(selection.label, "Add API @axum");
+        assert_eq!(selection.skill_hints, vec!["axum"]);
+        assert!(select_task_by_id(GRAPH_PLAN, "missing").is_none());
+    }
+
+    #[test]
+    fn task_graph_reports_ready_tasks() {
+        let graph = TaskGraph::from_content(GRAPH_PLAN).unwrap();
+        assert!(graph.is_parallelizable());
+
+        let ids = |tasks: Vec<&PlanTask>| -> Vec<String> {
+            tasks.iter().filter_map(|t| t.id.clone()).collect()
+        };
+        let mut finished = HashSet::new();
+        assert_eq!(ids(graph.ready(&finished)), vec!["storage", "cli"]);
+
+        finished.insert("storage".to_string());
+        assert_eq!(ids(graph.ready(&finished)), vec!["api", "cli"]);
+
+        let order = graph.dependency_order().unwrap();
+        let order: Vec<_> = order.iter().filter_map(|t| t.id.as_deref()).collect();
+        assert_eq!(order, vec!["types", "storage", "api", "cli"]);
+    }
+
+    #[test]
+    fn task_graph_rejects_invalid_graphs() {
+        assert_eq!(
+            TaskGraph::from_content("- [ ] [a] A\n- [ ] [a] Again\n"),
+            Err(PlanError::DuplicateTaskId("a".to_string()))
+        );
+        assert_eq!(
+            TaskGraph::from_content("- [ ] [a] A (depends-on: nope)\n"),
+            Err(PlanError::UnknownDependency {
+                task: "a".to_string(),
+                dependency: "nope".to_string(),
+            })
+        );
+        assert_eq!(
+            TaskGraph::from_content("- [ ] [a] A (depends-on: b)\n- [ ] [b] B (depends-on: a)\n"),
+            Err(PlanError::DependencyCycle("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn task_graph_needs_ids_on_unfinished_tasks_to_fan_out() {
+        let graph = TaskGraph::from_content("- [x] Done\n- [ ] [a] A\n- [ ] No id\n").unwrap();
+        assert!(!graph.is_parallelizable());
+        let graph = TaskGraph::from_content("- [x] Done\n").unwrap();
+        assert!(!graph.is_parallelizable());
+    }
 }
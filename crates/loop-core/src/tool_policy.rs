@@ -0,0 +1,202 @@
+//! Per-workspace tool permission policy for agent steps.
+//!
+//! Configured in `.loop/config` as pipe-separated Claude permission rules:
+//!
+//! ```text
+//! tool_policy.allow = Edit | Write | Bash(cargo:*)
+//! tool_policy.deny = Bash(git push:*) | WebFetch
+//! ```
+//!
+//! The runner writes the policy to a Claude settings file for each step.
+//! With no `allow` rules the agent keeps full permissions minus the denied
+//! tools; with `allow` rules only those tools (plus Claude's read-only tools)
+//! may run. Deny rules always win.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Allowed and denied tool patterns, e.g. `Edit` or `Bash(git push:*)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    /// Whether no policy is configured; the agent runs with full permissions.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether only the allowed tools may run.
+    pub fn is_allowlist(&self) -> bool {
+        !self.allow.is_empty()
+    }
+
+    /// Policy for a step that selected skills with `allowed_tools`.
+    ///
+    /// Skill tools are appended to the allow list. Without a configured
+    /// policy the step already has full permissions, so nothing is added.
+    #[must_use]
+    pub fn with_allowed<'a>(&self, tools: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut policy = self.clone();
+        if policy.is_allowlist() {
+            for tool in tools {
+                if !policy.allow.iter().any(|t| t == tool) {
+                    policy.allow.push(tool.to_string());
+                }
+            }
+        }
+        policy
+    }
+
+    /// Claude Code settings file content for this policy.
+    pub fn to_claude_settings(&self) -> Value {
+        json!({
+            "permissions": {
+                "allow": self.allow,
+                "deny": self.deny,
+            }
+        })
+    }
+}
+
+/// Split a pipe-separated list of tool patterns, validating each one.
+///
+/// A pattern is a tool name (`Edit`, `mcp__server__tool`) optionally
+/// followed by a parenthesized specifier (`Bash(git push:*)`).
+pub fn parse_tool_patterns(value: &str) -> Result<Vec<String>, String> {
+    value
+        .split('|')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| {
+            if is_valid_pattern(pattern) {
+                Ok(pattern.to_string())
+            } else {
+                Err(format!("invalid tool pattern '{pattern}'"))
+            }
+        })
+        .collect()
+}
+
+fn is_valid_pattern(pattern: &str) -> bool {
+    let (name, specifier) = match pattern.split_once('(') {
+        Some((name, rest)) => (name, Some(rest)),
+        None => (pattern, None),
+    };
+    let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let valid_specifier = specifier.is_none_or(|rest| {
+        rest.len() > 1 && rest.ends_with(')') && !rest[..rest.len() - 1].contains(')')
+    });
+    valid_name && valid_specifier
+}
+
+/// A tool call the agent was not permitted to make.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionDenial {
+    pub tool_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    #[serde(default)]
+    pub tool_input: Value,
+}
+
+/// Permission denials reported by a Claude stream-json `result` event.
+pub fn permission_denials(event: &Value) -> Vec<PermissionDenial> {
+    if event.get("type").and_then(Value::as_str) != Some("result") {
+        return Vec::new();
+    }
+    event
+        .get("permission_denials")
+        .and_then(Value::as_array)
+        .map(|denials| {
+            denials
+                .iter()
+                .filter_map(|denial| serde_json::from_value(denial.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pipe_separated_patterns() {
+        let patterns =
+            parse_tool_patterns(" Edit | Bash(git push:*) |  | mcp__github__get_issue").unwrap();
+        assert_eq!(
+            patterns,
+            ["Edit", "Bash(git push:*)", "mcp__github__get_issue"]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        for bad in [
+            "Bash(git push:*",
+            "Bash()",
+            "(rm)",
+            "Bash(a)b)",
+            "1Tool",
+            "Bash (x)",
+        ] {
+            assert!(
+                parse_tool_patterns(bad).is_err(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn skill_tools_merge_only_into_allowlists() {
+        let open = ToolPolicy {
+            allow: vec![],
+            deny: vec!["Bash(git push:*)".to_string()],
+        };
+        assert_eq!(open.with_allowed(["Bash(git:*)"]), open);
+
+        let allowlist = ToolPolicy {
+            allow: vec!["Edit".to_string()],
+            deny: vec![],
+        };
+        let merged = allowlist.with_allowed(["Bash(git:*)", "Edit"]);
+        assert_eq!(merged.allow, ["Edit", "Bash(git:*)"]);
+    }
+
+    #[test]
+    fn settings_list_allow_and_deny_rules() {
+        let policy = ToolPolicy {
+            allow: vec!["Edit".to_string()],
+            deny: vec!["Bash(git push:*)".to_string()],
+        };
+        assert_eq!(
+            policy.to_claude_settings(),
+            json!({"permissions": {"allow": ["Edit"], "deny": ["Bash(git push:*)"]}})
+        );
+    }
+
+    #[test]
+    fn reads_denials_from_result_event() {
+        let event = json!({
+            "type": "result",
+            "subtype": "success",
+            "permission_denials": [
+                {"tool_name": "Bash", "tool_use_id": "t1", "tool_input": {"command": "git push"}}
+            ]
+        });
+        let denials = permission_denials(&event);
+        assert_eq!(denials.len(), 1);
+        assert_eq!(denials[0].tool_name, "Bash");
+        assert_eq!(denials[0].tool_input["command"], "git push");
+
+        assert!(permission_denials(&json!({"type": "assistant"})).is_empty());
+        assert!(permission_denials(&json!({"type": "result"})).is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! Typed step transcripts built from Claude `stream-json` output.
+//!
+//! The runner keeps only assistant text in `iter-XX-*.log`. A transcript keeps
+//! the structure: assistant messages, `tool_use` calls with their inputs, and
+//! `tool_result` outputs matched back to the call that produced them, each
+//! stamped with the time the runner observed it.
+//!
+//! Only complete Claude Code protocol messages (`assistant` and `user` events)
+//! are used; partial API deltas would duplicate their content.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Maximum bytes of text kept per entry; longer tool output is truncated.
+pub const MAX_ENTRY_TEXT_BYTES: usize = 64 * 1024;
+
+/// What a transcript entry records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptEntryKind {
+    /// Text the assistant wrote.
+    AssistantMessage,
+    /// A tool call requested by the assistant.
+    ToolUse,
+    /// The output returned to the assistant for a tool call.
+    ToolResult,
+}
+
+impl TranscriptEntryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AssistantMessage => "assistant_message",
+            Self::ToolUse => "tool_use",
+            Self::ToolResult => "tool_result",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "assistant_message" => Some(Self::AssistantMessage),
+            "tool_use" => Some(Self::ToolUse),
+            "tool_result" => Some(Self::ToolResult),
+            _ => None,
+        }
+    }
+}
+
+/// One entry in a step transcript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// Position within the step, starting at 0.
+    pub seq: u32,
+    pub kind: TranscriptEntryKind,
+    /// Assistant text or tool result output (truncated to `MAX_ENTRY_TEXT_BYTES`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Tool call ID linking a `tool_use` to its `tool_result`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    /// Tool name; set on results too, resolved from the matching call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    /// Tool input as sent by the assistant (`tool_use` only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Value>,
+    /// Whether the tool reported an error (`tool_result` only).
+    #[serde(default)]
+    pub is_error: bool,
+    /// When the runner observed the event.
+    pub at: DateTime<Utc>,
+    /// Time between a tool call and its result (`tool_result` only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Builds a transcript from stream-json events as they arrive.
+#[derive(Debug, Default)]
+pub struct TranscriptBuilder {
+    entries: Vec<TranscriptEntry>,
+    /// Pending tool calls by ID: (tool name, time of the call).
+    calls: HashMap<String, (String, DateTime<Utc>)>,
+}
+
+impl TranscriptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record any transcript entries carried by one stream-json event.
+    pub fn observe(&mut self, event: &Value, at: DateTime<Utc>) {
+        let event_type = event.get("type").and_then(Value::as_str);
+        let Some(blocks) = event
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(Value::as_array)
+        else {
+            return;
+        };
+
+        match event_type {
+            Some("assistant") => {
+                for block in blocks {
+                    match block.get("type").and_then(Value::as_str) {
+                        Some("text") => {
+                            let text = block.get("text").and_then(Value::as_str).unwrap_or("");
+                            if !text.trim().is_empty() {
+                                self.push(TranscriptEntryKind::AssistantMessage, at, |entry| {
+                                    entry.text = Some(truncate_text(text));
+                                });
+                            }
+                        }
+                        Some("tool_use") => {
+                            let id = block.get("id").and_then(Value::as_str).map(str::to_string);
+                            let name = block
+                                .get("name")
+                                .and_then(Value::as_str)
+                                .map(str::to_string);
+                            if let (Some(id), Some(name)) = (&id, &name) {
+                                self.calls.insert(id.clone(), (name.clone(), at));
+                            }
+                            self.push(TranscriptEntryKind::ToolUse, at, |entry| {
+                                entry.tool_use_id = id;
+                                entry.tool_name = name;
+                                entry.input = block.get("input").cloned();
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some("user") => {
+                for block in blocks {
+                    if block.get("type").and_then(Value::as_str) != Some("tool_result") {
+                        continue;
+                    }
+                    let id = block
+                        .get("tool_use_id")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                    let call = id.as_ref().and_then(|id| self.calls.remove(id));
+                    let text = block.get("content").map(tool_result_text);
+                    let is_error = block
+                        .get("is_error")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                    self.push(TranscriptEntryKind::ToolResult, at, |entry| {
+                        entry.tool_use_id = id;
+                        entry.text = text.map(|text| truncate_text(&text));
+                        entry.is_error = is_error;
+                        if let Some((name, called_at)) = call {
+                            entry.tool_name = Some(name);
+                            entry.duration_ms =
+                                Some((at - called_at).num_milliseconds().max(0) as u64);
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Entries recorded so far.
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    pub fn finish(self) -> Vec<TranscriptEntry> {
+        self.entries
+    }
+
+    fn push(
+        &mut self,
+        kind: TranscriptEntryKind,
+        at: DateTime<Utc>,
+        fill: impl FnOnce(&mut TranscriptEntry),
+    ) {
+        let mut entry = TranscriptEntry {
+            seq: self.entries.len() as u32,
+            kind,
+            text: None,
+            tool_use_id: None,
+            tool_name: None,
+            input: None,
+            is_error: false,
+            at,
+            duration_ms: None,
+        };
+        fill(&mut entry);
+        self.entries.push(entry);
+    }
+}
+
+/// Flatten `tool_result` content: a string or an array of text blocks.
+fn tool_result_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn truncate_text(text: &str) -> String {
+    if text.len() <= MAX_ENTRY_TEXT_BYTES {
+        return text.to_string();
+    }
+    let mut end = MAX_ENTRY_TEXT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n[truncated {} bytes]", &text[..end], text.len() - end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn observe_all(events: &[(Value, i64)]) -> Vec<TranscriptEntry> {
+        let start = Utc::now();
+        let mut builder = TranscriptBuilder::new();
+        for (event, offset_ms) in events {
+            builder.observe(event, start + Duration::milliseconds(*offset_ms));
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn records_messages_tool_calls_and_results() {
+        let entries = observe_all(&[
+            (
+                json!({"type": "system", "subtype": "init", "session_id": "s1"}),
+                0,
+            ),
+            (
+                json!({"type": "assistant", "message": {"content": [
+                    {"type": "text", "text": "Running the tests."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "Bash",
+                     "input": {"command": "cargo test"}}
+                ]}}),
+                10,
+            ),
+            (
+                json!({"type": "user", "message": {"content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1",
+                     "content": "error[E0425]: cannot find value", "is_error": true}
+                ]}}),
+                1510,
+            ),
+        ]);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].kind, TranscriptEntryKind::AssistantMessage);
+        assert_eq!(entries[0].text.as_deref(), Some("Running the tests."));
+
+        assert_eq!(entries[1].seq, 1);
+        assert_eq!(entries[1].kind, TranscriptEntryKind::ToolUse);
+        assert_eq!(entries[1].tool_name.as_deref(), Some("Bash"));
+        assert_eq!(entries[1].input, Some(json!({"command": "cargo test"})));
+
+        assert_eq!(entries[2].kind, TranscriptEntryKind::ToolResult);
+        assert_eq!(entries[2].tool_use_id.as_deref(), Some("toolu_1"));
+        assert_eq!(entries[2].tool_name.as_deref(), Some("Bash"));
+        assert!(entries[2].is_error);
+        assert_eq!(entries[2].duration_ms, Some(1500));
+    }
+
+    #[test]
+    fn flattens_block_content_in_tool_results() {
+        let entries = observe_all(&[(
+            json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "unknown",
+                 "content": [{"type": "text", "text": "line 1"}, {"type": "text", "text": "line 2"}]}
+            ]}}),
+            0,
+        )]);
+
+        assert_eq!(entries[0].text.as_deref(), Some("line 1\nline 2"));
+        assert!(entries[0].tool_name.is_none());
+        assert!(entries[0].duration_ms.is_none());
+        assert!(!entries[0].is_error);
+    }
+
+    #[test]
+    fn ignores_partial_deltas_and_blank_text() {
+        let entries = observe_all(&[
+            (
+                json!({"type": "stream_event", "event": {"type": "content_block_delta",
+                    "delta": {"type": "text_delta", "text": "partial"}}}),
+                0,
+            ),
+            (
+                json!({"type": "assistant", "message": {"content": [{"type": "text", "text": "  \n"}]}}),
+                0,
+            ),
+        ]);
+
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn truncates_long_tool_output() {
+        let output = "x".repeat(MAX_ENTRY_TEXT_BYTES + 10);
+        let entries = observe_all(&[(
+            json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t", "content": output}
+            ]}}),
+            0,
+        )]);
+
+        let text = entries[0].text.as_deref().unwrap();
+        assert!(text.ends_with("[truncated 10 bytes]"));
+    }
+
+    #[test]
+    fn entry_kind_round_trips() {
+        for kind in [
+            TranscriptEntryKind::AssistantMessage,
+            TranscriptEntryKind::ToolUse,
+            TranscriptEntryKind::ToolResult,
+        ] {
+            assert_eq!(TranscriptEntryKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(TranscriptEntryKind::parse("thinking"), None);
+    }
+}
@@ -71,6 +71,8 @@ impl RunStatus {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum StepPhase {
+    /// Generates a checkbox plan from the spec before the first implementation step.
+    Planning,
     Implementation,
     Review,
     Verification,
@@ -81,6 +83,7 @@ pub enum StepPhase {
 impl StepPhase {
     pub fn as_str(&self) -> &'static str {
         match self {
+            Self::Planning => "planning",
             Self::Implementation => "implementation",
             Self::Review => "review",
             Self::Verification => "verification",
@@ -92,6 +95,7 @@ impl StepPhase {
     /// Short slug for artifact filenames (e.g., iter-01-impl.log).
     pub fn slug(&self) -> &'static str {
         match self {
+            Self::Planning => "plan",
             Self::Implementation => "impl",
             Self::Review => "review",
             Self::Verification => "verify",
@@ -256,6 +260,9 @@ pub enum QueuePolicy {
     Fifo,
     /// Newest first: most recently created pending run is claimed first.
     NewestFirst,
+    /// Weighted fair share: the workspace with the fewest running runs per
+    /// unit of weight goes next; its own runs are taken oldest first.
+    FairShare,
 }
 
 impl QueuePolicy {
@@ -263,6 +270,7 @@ impl QueuePolicy {
         match self {
             Self::Fifo => "fifo",
             Self::NewestFirst => "newest_first",
+            Self::FairShare => "fair_share",
         }
     }
 }
@@ -292,6 +300,93 @@ impl WorktreeProvider {
     }
 }
 
+/// Agent backend used to execute implementation and review steps.
+///
+/// Every backend writes the same `iter-XX-<phase>.log` / `.tail.txt`
+/// artifacts so the rest of the pipeline is backend-agnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentBackendKind {
+    /// Claude CLI (`claude -p --output-format stream-json`).
+    #[default]
+    Claude,
+    /// OpenAI-compatible chat completions endpoint (streaming SSE).
+    Openai,
+    /// Arbitrary shell command that reads the prompt on stdin.
+    Command,
+    /// Recorded fixture bundle played back without a model (tests, CI).
+    Replay,
+}
+
+impl AgentBackendKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Claude => "claude",
+            Self::Openai => "openai",
+            Self::Command => "command",
+            Self::Replay => "replay",
+        }
+    }
+}
+
+/// Process sandbox applied to agent and verification commands.
+///
+/// Uses Landlock, so it needs Linux 5.13+ and no container runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SandboxMode {
+    /// No sandbox: processes run with the daemon user's full privileges.
+    #[default]
+    #[serde(rename = "none")]
+    None,
+    /// Writes are limited to the worktree, a scratch dir, and configured paths.
+    #[serde(rename = "fs")]
+    Fs,
+    /// `Fs` plus TCP restrictions: verification commands get no network and the
+    /// agent may only connect to `sandbox_agent_ports` (default 443, for its
+    /// model API), so it is not fully offline.
+    #[serde(rename = "fs+net")]
+    FsNet,
+}
+
+impl SandboxMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Fs => "fs",
+            Self::FsNet => "fs+net",
+        }
+    }
+
+    /// Whether filesystem writes are restricted.
+    pub fn restricts_fs(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+
+    /// Whether network access is restricted.
+    pub fn restricts_net(&self) -> bool {
+        matches!(self, Self::FsNet)
+    }
+}
+
+/// API throttling signal that opened the daemon-wide backoff for a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKind {
+    /// HTTP 429 / `rate_limit_error`.
+    RateLimited,
+    /// HTTP 529 / `overloaded_error`.
+    Overloaded,
+}
+
+impl RateLimitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RateLimited => "rate_limited",
+            Self::Overloaded => "overloaded",
+        }
+    }
+}
+
 /// Review workflow status for completed runs.
 ///
 /// See daemon-review-api.md Section 3 (Data Model).
@@ -376,6 +471,285 @@ pub struct Run {
     pub pr_url: Option<String>,
     /// Commit SHA from merge (set when review_status = Merged).
     pub merge_commit: Option<String>,
+    /// Scheduling priority; higher runs are claimed first. Default 0.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// When a run dependency counts as satisfied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    /// The run completed.
+    #[default]
+    Completed,
+    /// The run's branch was merged, by auto-merge or the review API.
+    Merged,
+    /// A verification step of the run passed.
+    SucceededVerification,
+}
+
+impl DependencyCondition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Merged => "merged",
+            Self::SucceededVerification => "succeeded_verification",
+        }
+    }
+}
+
+/// A run that must reach `condition` before the dependent run is claimed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunDependency {
+    pub run_id: Id,
+    #[serde(default)]
+    pub condition: DependencyCondition,
+    /// Start the dependent run's worktree from this run's branch.
+    #[serde(default)]
+    pub branch_from: bool,
+}
+
+/// Why a pending run would not be claimed right now.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum QueueBlock {
+    /// Run `depends_on` has not reached `condition` yet.
+    Dependency {
+        depends_on: Id,
+        condition: DependencyCondition,
+    },
+    /// Its workspace already has `cap` runs running or ahead of it in the queue.
+    WorkspaceCap { cap: usize },
+    /// Every one of the daemon's `max` run slots is taken by a run ahead of it.
+    Concurrency { max: usize },
+    /// The daemon-wide daily spend cap has been reached.
+    DailyCostCap { spent_usd: f64, cap_usd: f64 },
+}
+
+impl QueueBlock {
+    /// Short human-readable explanation for `loopctl queue`.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Dependency {
+                depends_on,
+                condition,
+            } => format!("run {depends_on} ({})", condition.as_str()),
+            Self::WorkspaceCap { cap } => format!("workspace cap ({cap} per workspace)"),
+            Self::Concurrency { max } => format!("all {max} run slots in use"),
+            Self::DailyCostCap { spent_usd, cap_usd } => {
+                format!("daily spend cap (${spent_usd:.2} of ${cap_usd:.2})")
+            }
+        }
+    }
+}
+
+/// A pending run's place in the scheduler queue, as returned by `GET /queue`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub run_id: Id,
+    pub name: String,
+    pub workspace_root: String,
+    pub priority: i32,
+    /// Priority after aging; the queue is ordered by this.
+    pub effective_priority: i64,
+    pub created_at: DateTime<Utc>,
+    /// Set when the run is not claimable yet; `None` for runs next in line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<QueueBlock>,
+}
+
+/// What a schedule does with fire times that passed while the daemon was down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedFirePolicy {
+    /// Drop missed fires and wait for the next one.
+    #[default]
+    Skip,
+    /// Enqueue one run for all missed fires, then resume the cron cadence.
+    CatchUp,
+}
+
+impl MissedFirePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::CatchUp => "catch_up",
+        }
+    }
+}
+
+/// What a schedule does when its previous run is still active at fire time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Do not enqueue; wait for the next fire.
+    #[default]
+    Skip,
+    /// Enqueue regardless of the previous run.
+    Allow,
+}
+
+impl OverlapPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Allow => "allow",
+        }
+    }
+}
+
+/// A recurring run: a spec enqueued whenever its cron expression fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: Id,
+    pub name: String,
+    pub workspace_root: String,
+    pub spec_path: String,
+    pub plan_path: Option<String>,
+    /// Config override (file path or JSON), as accepted by `POST /runs`.
+    pub config_override: Option<String>,
+    /// Cron expression, evaluated in the daemon's local time zone.
+    pub cron: String,
+    #[serde(default)]
+    pub missed_fire_policy: MissedFirePolicy,
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+    /// Priority of the runs this schedule enqueues.
+    #[serde(default)]
+    pub priority: i32,
+    pub next_fire_at: DateTime<Utc>,
+    pub last_fire_at: Option<DateTime<Utc>>,
+    /// Most recent run this schedule enqueued.
+    pub last_run_id: Option<Id>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Liveness of a registered worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WorkerStatus {
+    Online,
+    /// No heartbeat within the offline timeout.
+    Offline,
+}
+
+impl WorkerStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Online => "ONLINE",
+            Self::Offline => "OFFLINE",
+        }
+    }
+}
+
+/// A `loopd` process executing steps for a controller in distributed mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Worker {
+    pub id: Id,
+    pub hostname: String,
+    pub status: WorkerStatus,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    pub last_heartbeat: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A step offered to workers, and the worker holding it if it was claimed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    pub step_id: Id,
+    pub run_id: Id,
+    /// Holder of the lease; `None` while the step waits to be claimed.
+    pub worker_id: Option<Id>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Number of times the lease was granted (1 on the first claim).
+    pub attempt: u32,
+}
+
+/// Which finished runs the garbage collector prunes.
+///
+/// Pruning removes a run's log directories, artifact rows, transcripts and
+/// step-level events; the run row, its steps and usage stay for history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Prune finished runs not updated for this many days.
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Keep at most this many unpruned finished runs per workspace.
+    #[serde(default)]
+    pub max_runs_per_workspace: Option<u32>,
+    /// Never prune completed runs whose branch is neither merged nor scrapped.
+    #[serde(default = "default_keep_unmerged")]
+    pub keep_unmerged: bool,
+    /// Keep failed runs for at least this many days regardless of the limits.
+    #[serde(default)]
+    pub keep_failed_days: Option<u32>,
+}
+
+fn default_keep_unmerged() -> bool {
+    true
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: None,
+            max_runs_per_workspace: None,
+            keep_unmerged: default_keep_unmerged(),
+            keep_failed_days: None,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Whether any limit is set; without one nothing is ever pruned.
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days.is_some() || self.max_runs_per_workspace.is_some()
+    }
+}
+
+/// Retention limit that selected a run for pruning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    MaxAge,
+    MaxRunsPerWorkspace,
+}
+
+impl PruneReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MaxAge => "max_age",
+            Self::MaxRunsPerWorkspace => "max_runs_per_workspace",
+        }
+    }
+}
+
+/// A run removed (or, in a dry run, selected) by the garbage collector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrunedRun {
+    pub run_id: Id,
+    pub name: String,
+    pub workspace_root: String,
+    pub status: RunStatus,
+    pub reason: PruneReason,
+    /// Size of the run's log directories.
+    pub bytes: u64,
+}
+
+/// Outcome of one garbage collection pass.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub policy: RetentionPolicy,
+    pub runs: Vec<PrunedRun>,
+    /// Step-level events deleted from pruned runs.
+    pub events_removed: u64,
+    /// Artifact rows deleted, including rows whose file no longer exists.
+    pub artifacts_removed: u64,
+    pub bytes_freed: u64,
 }
 
 /// A single step (iteration) within a run.
@@ -395,6 +769,43 @@ pub struct Step {
     pub prompt_path: Option<String>,
     /// Path to the output log for this step.
     pub output_path: Option<String>,
+    /// Agent session the step ran in (set when sessions are resumed).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// Token usage and reported cost for one or more agent invocations.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    /// Cost in USD as reported by the backend (0 when not reported).
+    pub cost_usd: f64,
+}
+
+impl TokenUsage {
+    /// Sum of all input, output and cache tokens.
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_tokens() == 0 && self.cost_usd == 0.0
+    }
+
+    /// Accumulate another usage record into this one.
+    pub fn add(&mut self, other: &Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cost_usd += other.cost_usd;
+    }
 }
 
 /// An event in the audit log.
@@ -511,4 +922,61 @@ mod tests {
         assert_eq!(WorktreeProvider::Worktrunk.as_str(), "worktrunk");
         assert_eq!(WorktreeProvider::Git.as_str(), "git");
     }
+
+    #[test]
+    fn agent_backend_kind_defaults_to_claude() {
+        assert_eq!(AgentBackendKind::default(), AgentBackendKind::Claude);
+    }
+
+    #[test]
+    fn agent_backend_kind_as_str() {
+        assert_eq!(AgentBackendKind::Claude.as_str(), "claude");
+        assert_eq!(AgentBackendKind::Openai.as_str(), "openai");
+        assert_eq!(AgentBackendKind::Command.as_str(), "command");
+        assert_eq!(AgentBackendKind::Replay.as_str(), "replay");
+        assert_eq!(
+            serde_json::to_string(&AgentBackendKind::Openai).unwrap(),
+            "\"openai\""
+        );
+    }
+
+    #[test]
+    fn sandbox_mode_as_str() {
+        assert_eq!(SandboxMode::default(), SandboxMode::None);
+        assert_eq!(SandboxMode::Fs.as_str(), "fs");
+        assert_eq!(SandboxMode::FsNet.as_str(), "fs+net");
+        assert_eq!(
+            serde_json::to_string(&SandboxMode::FsNet).unwrap(),
+            "\"fs+net\""
+        );
+        assert!(SandboxMode::Fs.restricts_fs());
+        assert!(!SandboxMode::Fs.restricts_net());
+        assert!(!SandboxMode::None.restricts_fs());
+    }
+
+    #[test]
+    fn token_usage_add_and_total() {
+        let mut total = TokenUsage::default();
+        assert!(total.is_empty());
+
+        total.add(&TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_creation_input_tokens: 100,
+            cache_read_input_tokens: 1000,
+            cost_usd: 0.25,
+        });
+        total.add(&TokenUsage {
+            input_tokens: 1,
+            output_tokens: 2,
+            cost_usd: 0.5,
+            ..TokenUsage::default()
+        });
+
+        assert_eq!(total.input_tokens, 11);
+        assert_eq!(total.output_tokens, 7);
+        assert_eq!(total.total_tokens(), 1118);
+        assert!((total.cost_usd - 0.75).abs() < f64::EPSILON);
+        assert!(!total.is_empty());
+    }
 }
@@ -2,7 +2,12 @@
 //!
 //! Communicates with loopd via its local HTTP API (Section 4.1).
 
-use loop_core::types::{MergeStrategy, Run, RunNameSource, RunStatus, Step, WorktreeProvider};
+use loop_core::types::{
+    GcReport, MergeStrategy, MissedFirePolicy, OverlapPolicy, QueueEntry, QueuePolicy,
+    ReviewStatus, Run, RunDependency, RunNameSource, RunStatus, Schedule, Step, TokenUsage,
+    WorktreeProvider,
+};
+use loop_core::TranscriptEntry;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -83,6 +88,10 @@ pub struct CreateRunRequest {
     pub worktrunk_config_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub worktrunk_copy_ignored: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<RunDependency>,
 }
 
 /// Response from create run endpoint.
@@ -95,6 +104,61 @@ pub struct CreateRunResponse {
 #[derive(Debug, Deserialize)]
 pub struct ListRunsResponse {
     pub runs: Vec<Run>,
+    /// Cursor for the next page, absent on the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Filters, sort and paging for `GET /runs`. Unset fields are omitted.
+#[derive(Debug, Clone, Default)]
+pub struct ListRunsParams {
+    pub status: Option<RunStatus>,
+    pub review_status: Option<ReviewStatus>,
+    pub workspace_root: Option<String>,
+    /// Substring of the spec path.
+    pub spec_path: Option<String>,
+    /// Created at or after (Unix epoch milliseconds).
+    pub created_after: Option<i64>,
+    /// Created before (Unix epoch milliseconds).
+    pub created_before: Option<i64>,
+    /// Oldest first instead of newest first.
+    pub oldest_first: bool,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+}
+
+impl ListRunsParams {
+    fn to_query(&self) -> String {
+        let mut params = vec![];
+        if let Some(s) = self.status {
+            params.push(format!("status={}", s.as_str()));
+        }
+        if let Some(r) = self.review_status {
+            params.push(format!("review_status={}", r.as_str()));
+        }
+        if let Some(ws) = &self.workspace_root {
+            params.push(format!("workspace_root={}", urlencoding::encode(ws)));
+        }
+        if let Some(spec) = &self.spec_path {
+            params.push(format!("spec_path={}", urlencoding::encode(spec)));
+        }
+        if let Some(after) = self.created_after {
+            params.push(format!("created_after={after}"));
+        }
+        if let Some(before) = self.created_before {
+            params.push(format!("created_before={before}"));
+        }
+        if self.oldest_first {
+            params.push("sort=created_asc".to_string());
+        }
+        if let Some(limit) = self.limit {
+            params.push(format!("limit={limit}"));
+        }
+        if let Some(cursor) = &self.cursor {
+            params.push(format!("cursor={}", urlencoding::encode(cursor)));
+        }
+        params.join("&")
+    }
 }
 
 /// Response from get run endpoint.
@@ -109,6 +173,13 @@ pub struct ListStepsResponse {
     pub steps: Vec<Step>,
 }
 
+/// Response from step transcript endpoint.
+#[derive(Debug, Deserialize)]
+pub struct TranscriptResponse {
+    pub step: Step,
+    pub entries: Vec<TranscriptEntry>,
+}
+
 /// Worktree information.
 #[derive(Debug, Deserialize)]
 #[allow(dead_code, reason = "API response struct - fields populated from JSON")]
@@ -128,6 +199,60 @@ pub struct ListWorktreesResponse {
     pub worktrees: Vec<WorktreeInfo>,
 }
 
+/// One group in the usage aggregation response.
+#[derive(Debug, Deserialize)]
+#[allow(dead_code, reason = "API response struct - fields populated from JSON")]
+pub struct UsageGroup {
+    pub key: String,
+    pub label: String,
+    pub runs: u64,
+    pub steps: u64,
+    pub usage: TokenUsage,
+}
+
+/// Response from usage endpoint.
+#[derive(Debug, Deserialize)]
+pub struct UsageResponse {
+    pub groups: Vec<UsageGroup>,
+    pub total: TokenUsage,
+}
+
+/// Response from queue endpoint.
+#[derive(Debug, Deserialize)]
+pub struct QueueResponse {
+    pub policy: QueuePolicy,
+    pub entries: Vec<QueueEntry>,
+}
+
+/// Request payload for creating a schedule (POST /schedules).
+#[derive(Debug, Serialize)]
+pub struct CreateScheduleRequest {
+    pub spec_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_path: Option<String>,
+    pub workspace_root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_override: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub cron: String,
+    pub missed_fire_policy: MissedFirePolicy,
+    pub overlap_policy: OverlapPolicy,
+    pub priority: i32,
+}
+
+/// Response from create schedule endpoint.
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduleResponse {
+    pub schedule: Schedule,
+}
+
+/// Response from list schedules endpoint.
+#[derive(Debug, Deserialize)]
+pub struct ListSchedulesResponse {
+    pub schedules: Vec<Schedule>,
+}
+
 /// Error response from API.
 #[derive(Debug, Deserialize)]
 pub struct ErrorResponse {
@@ -290,25 +415,18 @@ impl Client {
         Ok(body.run)
     }
 
-    /// List runs, optionally filtered.
-    /// GET /`runs?status=...&workspace_root`=...
+    /// List one page of runs.
+    /// GET /`runs?status=...&limit=...&cursor`=...
     pub async fn list_runs(
         &self,
-        status: Option<RunStatus>,
-        workspace_root: Option<&str>,
-    ) -> Result<Vec<Run>, ClientError> {
-        let mut url = format!("{}/runs", self.base_url);
-        let mut params = vec![];
-
-        if let Some(s) = status {
-            params.push(format!("status={}", s.as_str()));
-        }
-        if let Some(ws) = workspace_root {
-            params.push(format!("workspace_root={}", urlencoding::encode(ws)));
-        }
-        if !params.is_empty() {
-            url = format!("{}?{}", url, params.join("&"));
-        }
+        params: &ListRunsParams,
+    ) -> Result<ListRunsResponse, ClientError> {
+        let query = params.to_query();
+        let url = if query.is_empty() {
+            format!("{}/runs", self.base_url)
+        } else {
+            format!("{}/runs?{}", self.base_url, query)
+        };
 
         let response = self.http.get(&url).headers(self.headers()).send().await?;
 
@@ -316,12 +434,19 @@ impl Client {
             return Err(self.handle_error(response).await);
         }
 
-        let body: ListRunsResponse = response
+        response
             .json()
             .await
-            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
 
-        Ok(body.runs)
+    /// Get the most recently created run, if any.
+    pub async fn latest_run(&self) -> Result<Option<Run>, ClientError> {
+        let params = ListRunsParams {
+            limit: Some(1),
+            ..ListRunsParams::default()
+        };
+        Ok(self.list_runs(&params).await?.runs.into_iter().next())
     }
 
     /// Get a single run.
@@ -360,6 +485,33 @@ impl Client {
         Ok(body.steps)
     }
 
+    /// Get the typed transcript of a step, optionally for one tool.
+    /// GET /runs/{id}/steps/{step_id}/transcript?tool=...
+    pub async fn get_step_transcript(
+        &self,
+        run_id: &str,
+        step_id: &str,
+        tool: Option<&str>,
+    ) -> Result<TranscriptResponse, ClientError> {
+        let mut url = format!(
+            "{}/runs/{}/steps/{}/transcript",
+            self.base_url, run_id, step_id
+        );
+        if let Some(tool) = tool {
+            url.push_str(&format!("?tool={}", urlencoding::encode(tool)));
+        }
+        let response = self.http.get(&url).headers(self.headers()).send().await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
     /// Pause a run.
     /// POST /runs/{id}/pause
     pub async fn pause_run(&self, run_id: &str) -> Result<(), ClientError> {
@@ -425,6 +577,167 @@ impl Client {
         Ok(())
     }
 
+    /// Change a run's scheduling priority.
+    /// POST /runs/{id}/priority
+    pub async fn set_run_priority(&self, run_id: &str, priority: i32) -> Result<(), ClientError> {
+        let url = format!("{}/runs/{}/priority", self.base_url, run_id);
+        let response = self
+            .http
+            .post(&url)
+            .headers(self.headers())
+            .json(&serde_json::json!({ "priority": priority }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        Ok(())
+    }
+
+    /// Get the pending queue in claim order.
+    /// GET /queue
+    pub async fn get_queue(&self) -> Result<QueueResponse, ClientError> {
+        let url = format!("{}/queue", self.base_url);
+        let response = self.http.get(&url).headers(self.headers()).send().await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    /// Create a cron schedule.
+    /// POST /schedules
+    pub async fn create_schedule(
+        &self,
+        req: CreateScheduleRequest,
+    ) -> Result<Schedule, ClientError> {
+        let url = format!("{}/schedules", self.base_url);
+        let response = self
+            .http
+            .post(&url)
+            .headers(self.headers())
+            .json(&req)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        let body: CreateScheduleResponse = response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        Ok(body.schedule)
+    }
+
+    /// List schedules, optionally for one workspace.
+    /// GET /schedules?workspace_root=...
+    pub async fn list_schedules(
+        &self,
+        workspace_root: Option<&str>,
+    ) -> Result<Vec<Schedule>, ClientError> {
+        let url = match workspace_root {
+            Some(ws) => format!(
+                "{}/schedules?workspace_root={}",
+                self.base_url,
+                urlencoding::encode(ws)
+            ),
+            None => format!("{}/schedules", self.base_url),
+        };
+        let response = self.http.get(&url).headers(self.headers()).send().await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        let body: ListSchedulesResponse = response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        Ok(body.schedules)
+    }
+
+    /// Delete a schedule.
+    /// DELETE /schedules/{id}
+    pub async fn delete_schedule(&self, schedule_id: &str) -> Result<(), ClientError> {
+        let url = format!("{}/schedules/{}", self.base_url, schedule_id);
+        let response = self
+            .http
+            .delete(&url)
+            .headers(self.headers())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        Ok(())
+    }
+
+    /// Apply the retention policy, or preview it with `dry_run`.
+    /// POST /gc
+    pub async fn run_gc(&self, dry_run: bool) -> Result<GcReport, ClientError> {
+        let url = format!("{}/gc", self.base_url);
+        let response = self
+            .http
+            .post(&url)
+            .headers(self.headers())
+            .json(&serde_json::json!({ "dry_run": dry_run }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    /// Aggregate token usage and cost.
+    /// GET /usage?by=...&workspace_root=...&since=...&until=...
+    pub async fn get_usage(
+        &self,
+        by: &str,
+        workspace_root: Option<&str>,
+        since_ms: Option<i64>,
+        until_ms: Option<i64>,
+    ) -> Result<UsageResponse, ClientError> {
+        let mut params = vec![format!("by={by}")];
+        if let Some(ws) = workspace_root {
+            params.push(format!("workspace_root={}", urlencoding::encode(ws)));
+        }
+        if let Some(since) = since_ms {
+            params.push(format!("since={since}"));
+        }
+        if let Some(until) = until_ms {
+            params.push(format!("until={until}"));
+        }
+        let url = format!("{}/usage?{}", self.base_url, params.join("&"));
+
+        let response = self.http.get(&url).headers(self.headers()).send().await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
     /// List worktrees for a workspace.
     /// GET /worktrees?workspace=<path>
     pub async fn list_worktrees(&self, workspace: &str) -> Result<ListWorktreesResponse, ClientError> {
@@ -593,6 +906,27 @@ fn parse_sse_output_event(event_str: &str) -> Option<OutputEvent> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn list_runs_params_omit_unset_fields() {
+        assert_eq!(ListRunsParams::default().to_query(), "");
+
+        let params = ListRunsParams {
+            status: Some(RunStatus::Failed),
+            review_status: Some(ReviewStatus::PrCreated),
+            spec_path: Some("specs/a b.md".to_string()),
+            created_after: Some(1_000),
+            oldest_first: true,
+            limit: Some(20),
+            cursor: Some("0190-abc".to_string()),
+            ..ListRunsParams::default()
+        };
+        assert_eq!(
+            params.to_query(),
+            "status=FAILED&review_status=pr_created&spec_path=specs%2Fa%20b.md\
+             &created_after=1000&sort=created_asc&limit=20&cursor=0190-abc"
+        );
+    }
+
     // --- SSE parsing tests (spec Section 7.2: tail streams live output) ---
 
     #[test]
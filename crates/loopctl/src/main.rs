@@ -12,13 +12,20 @@ mod client;
 mod render;
 
 use clap::{Parser, Subcommand};
-use client::{Client, ClientError};
-use loop_core::types::{MergeStrategy, RunNameSource, RunStatus, WorktreeProvider};
-use loop_core::Config;
+use client::{Client, ClientError, ListRunsParams};
+use loop_core::types::{
+    DependencyCondition, Id, MergeStrategy, MissedFirePolicy, OverlapPolicy, ReviewStatus,
+    RunDependency, RunNameSource, RunStatus, StepPhase, WorktreeProvider,
+};
+use loop_core::{logs, Config};
+use std::collections::{HashMap, HashSet};
 use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use std::process::{Command as ProcessCommand, Stdio};
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How often `tail --log-dir --follow` checks step logs for new output.
+const LOCAL_TAIL_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// CLI client for the loopd orchestrator daemon.
 #[derive(Parser)]
@@ -103,6 +110,18 @@ enum Command {
         /// Copy ignored files when using Worktrunk provider
         #[arg(long)]
         worktrunk_copy_ignored: bool,
+
+        /// Scheduling priority; higher runs are claimed first (default 0)
+        #[arg(long, allow_hyphen_values = true)]
+        priority: Option<i32>,
+
+        /// Wait for another run: RUN_ID[:completed|merged|succeeded_verification] (repeatable)
+        #[arg(long, value_parser = parse_dependency)]
+        depends_on: Vec<RunDependency>,
+
+        /// Start from the first dependency's branch instead of the base branch
+        #[arg(long, requires = "depends_on")]
+        branch_from_dependency: bool,
     },
 
     /// Show the prompt that would be sent (no daemon required)
@@ -128,6 +147,38 @@ enum Command {
         #[arg(long, value_parser = parse_run_status)]
         status: Option<RunStatus>,
 
+        /// Filter by review status (pending, reviewed, scrapped, merged, pr_created)
+        #[arg(long, value_parser = parse_review_status)]
+        review_status: Option<ReviewStatus>,
+
+        /// Show only runs whose spec path contains this text
+        #[arg(long)]
+        spec: Option<String>,
+
+        /// Show runs created on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_date_bound)]
+        since: Option<i64>,
+
+        /// Show runs created before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_date_bound)]
+        until: Option<i64>,
+
+        /// Show oldest runs first
+        #[arg(long)]
+        oldest_first: bool,
+
+        /// Maximum runs to show
+        #[arg(long, default_value = "50", conflicts_with = "all")]
+        limit: u32,
+
+        /// Show every matching run
+        #[arg(long)]
+        all: bool,
+
+        /// Continue from the cursor printed by a previous page
+        #[arg(long)]
+        cursor: Option<String>,
+
         /// Show only runs for current workspace
         #[arg(long)]
         workspace: bool,
@@ -169,6 +220,32 @@ enum Command {
         run_id: String,
     },
 
+    /// Change a run's scheduling priority
+    Priority {
+        /// Run ID
+        run_id: String,
+
+        /// New priority; higher runs are claimed first
+        #[arg(allow_hyphen_values = true)]
+        priority: i32,
+    },
+
+    /// Show pending runs in claim order and why each is waiting
+    Queue,
+
+    /// Manage cron schedules that start runs on a recurring basis
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommand,
+    },
+
+    /// Prune finished runs under the daemon's retention policy
+    Gc {
+        /// Show what would be pruned without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// List worktrees for a workspace
     Worktrees {
         /// Workspace path (defaults to current directory)
@@ -199,6 +276,47 @@ enum Command {
         /// Follow output (like tail -f)
         #[arg(short, long)]
         follow: bool,
+
+        /// Read step logs from this log directory instead of the daemon
+        #[arg(long)]
+        log_dir: Option<PathBuf>,
+    },
+
+    /// Show the tool calls, results and messages of one step
+    Transcript {
+        /// Run ID
+        run_id: String,
+
+        /// Iteration number, as in iter-XX log names (default: latest)
+        #[arg(long)]
+        iteration: Option<u32>,
+
+        /// Step phase: planning, implementation, review, verification, watchdog, merge
+        #[arg(long, default_value = "implementation", value_parser = parse_step_phase)]
+        phase: StepPhase,
+
+        /// Only show calls and results for this tool (e.g. Bash)
+        #[arg(long)]
+        tool: Option<String>,
+    },
+
+    /// Show token usage and cost aggregated by run, spec, or workspace
+    Cost {
+        /// Group by: run, spec, or workspace
+        #[arg(long, default_value = "run", value_parser = parse_usage_group)]
+        by: String,
+
+        /// Include usage recorded on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_date_bound)]
+        since: Option<i64>,
+
+        /// Include usage recorded before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_date_bound)]
+        until: Option<i64>,
+
+        /// Show only usage for current workspace
+        #[arg(long)]
+        workspace: bool,
     },
 
     /// Run postmortem analysis on a completed run
@@ -224,6 +342,56 @@ enum Command {
     },
 }
 
+#[derive(Subcommand)]
+enum ScheduleCommand {
+    /// Schedule a spec to run whenever a cron expression fires
+    Add {
+        /// Path to the spec file
+        spec: PathBuf,
+
+        /// Cron expression in local time, e.g. "0 2 * * *" or @daily
+        #[arg(long)]
+        cron: String,
+
+        /// Path to the plan file (optional)
+        #[arg(long)]
+        plan: Option<PathBuf>,
+
+        /// Config file path (overrides .loop/config)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Schedule name, also used for its runs (default: spec file stem)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Fires missed while the daemon was down: skip or `catch_up` (one run)
+        #[arg(long, default_value = "skip", value_parser = parse_missed_fire_policy)]
+        missed_fire: MissedFirePolicy,
+
+        /// When the previous run is still active: skip or allow
+        #[arg(long, default_value = "skip", value_parser = parse_overlap_policy)]
+        overlap: OverlapPolicy,
+
+        /// Scheduling priority of the runs it starts
+        #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+        priority: i32,
+    },
+
+    /// List schedules
+    List {
+        /// Show only schedules for current workspace
+        #[arg(long)]
+        workspace: bool,
+    },
+
+    /// Delete a schedule (runs it already started are kept)
+    Rm {
+        /// Schedule ID
+        schedule_id: String,
+    },
+}
+
 fn parse_name_source(s: &str) -> Result<RunNameSource, String> {
     match s.to_lowercase().as_str() {
         "haiku" => Ok(RunNameSource::Haiku),
@@ -245,6 +413,50 @@ fn parse_merge_strategy(s: &str) -> Result<MergeStrategy, String> {
     }
 }
 
+fn parse_missed_fire_policy(s: &str) -> Result<MissedFirePolicy, String> {
+    match s.to_lowercase().as_str() {
+        "skip" => Ok(MissedFirePolicy::Skip),
+        "catch_up" | "catch-up" => Ok(MissedFirePolicy::CatchUp),
+        _ => Err(format!(
+            "invalid missed-fire policy '{s}', expected: skip, catch_up"
+        )),
+    }
+}
+
+fn parse_overlap_policy(s: &str) -> Result<OverlapPolicy, String> {
+    match s.to_lowercase().as_str() {
+        "skip" => Ok(OverlapPolicy::Skip),
+        "allow" => Ok(OverlapPolicy::Allow),
+        _ => Err(format!(
+            "invalid overlap policy '{s}', expected: skip, allow"
+        )),
+    }
+}
+
+fn parse_dependency(s: &str) -> Result<RunDependency, String> {
+    let (run_id, condition) = s.split_once(':').unwrap_or((s, "completed"));
+    if run_id.is_empty() {
+        return Err(format!(
+            "invalid dependency '{s}', expected RUN_ID[:condition]"
+        ));
+    }
+    let condition = match condition.to_lowercase().as_str() {
+        "completed" => DependencyCondition::Completed,
+        "merged" => DependencyCondition::Merged,
+        "succeeded_verification" => DependencyCondition::SucceededVerification,
+        _ => {
+            return Err(format!(
+                "invalid dependency condition '{condition}', expected: completed, merged, succeeded_verification"
+            ))
+        }
+    };
+    Ok(RunDependency {
+        run_id: Id::from_string(run_id),
+        condition,
+        branch_from: false,
+    })
+}
+
 fn parse_run_status(s: &str) -> Result<RunStatus, String> {
     match s.to_uppercase().as_str() {
         "PENDING" => Ok(RunStatus::Pending),
@@ -259,6 +471,19 @@ fn parse_run_status(s: &str) -> Result<RunStatus, String> {
     }
 }
 
+fn parse_review_status(s: &str) -> Result<ReviewStatus, String> {
+    match s.to_lowercase().as_str() {
+        "pending" => Ok(ReviewStatus::Pending),
+        "reviewed" => Ok(ReviewStatus::Reviewed),
+        "scrapped" => Ok(ReviewStatus::Scrapped),
+        "merged" => Ok(ReviewStatus::Merged),
+        "pr_created" => Ok(ReviewStatus::PrCreated),
+        _ => Err(format!(
+            "invalid review status '{s}', expected: pending, reviewed, scrapped, merged, pr_created"
+        )),
+    }
+}
+
 fn parse_worktree_provider(s: &str) -> Result<WorktreeProvider, String> {
     match s.to_lowercase().as_str() {
         "auto" => Ok(WorktreeProvider::Auto),
@@ -270,6 +495,44 @@ fn parse_worktree_provider(s: &str) -> Result<WorktreeProvider, String> {
     }
 }
 
+fn parse_step_phase(s: &str) -> Result<StepPhase, String> {
+    match s.to_lowercase().as_str() {
+        "planning" | "plan" => Ok(StepPhase::Planning),
+        "implementation" | "impl" => Ok(StepPhase::Implementation),
+        "review" => Ok(StepPhase::Review),
+        "verification" | "verify" => Ok(StepPhase::Verification),
+        "watchdog" => Ok(StepPhase::Watchdog),
+        "merge" => Ok(StepPhase::Merge),
+        _ => Err(format!(
+            "invalid phase '{s}', expected: planning, implementation, review, verification, watchdog, merge"
+        )),
+    }
+}
+
+fn parse_usage_group(s: &str) -> Result<String, String> {
+    match s.to_lowercase().as_str() {
+        group @ ("run" | "spec" | "workspace") => Ok(group.to_string()),
+        _ => Err(format!(
+            "invalid grouping '{s}', expected: run, spec, workspace"
+        )),
+    }
+}
+
+/// Parse a date bound into Unix epoch milliseconds.
+///
+/// Accepts `YYYY-MM-DD` (midnight UTC) or a full RFC 3339 timestamp.
+fn parse_date_bound(s: &str) -> Result<i64, String> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+            .timestamp_millis());
+    }
+    chrono::DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.timestamp_millis())
+        .map_err(|_| format!("invalid date '{s}', expected YYYY-MM-DD or RFC 3339"))
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
@@ -307,6 +570,9 @@ async fn main() {
             worktrunk_bin,
             worktrunk_config,
             worktrunk_copy_ignored,
+            priority,
+            depends_on,
+            branch_from_dependency,
         } => {
             run_create(
                 &client,
@@ -326,6 +592,9 @@ async fn main() {
                 worktrunk_bin,
                 worktrunk_config,
                 worktrunk_copy_ignored,
+                priority,
+                depends_on,
+                branch_from_dependency,
             )
             .await
         }
@@ -335,20 +604,67 @@ async fn main() {
             config,
             pick,
         } => show_prompt(spec, plan, config, pick),
-        Command::List { status, workspace } => run_list(&client, status, workspace).await,
+        Command::List {
+            status,
+            review_status,
+            spec,
+            since,
+            until,
+            oldest_first,
+            limit,
+            all,
+            cursor,
+            workspace,
+        } => {
+            let params = ListRunsParams {
+                status,
+                review_status,
+                spec_path: spec,
+                created_after: since,
+                created_before: until,
+                oldest_first,
+                limit: (!all).then_some(limit),
+                cursor,
+                ..ListRunsParams::default()
+            };
+            run_list(&client, params, workspace).await
+        }
         Command::Inspect { run_id } => run_inspect(&client, &run_id).await,
         Command::Pause { run_id } => run_pause(&client, &run_id).await,
         Command::Resume { run_id } => run_resume(&client, &run_id).await,
         Command::Cancel { run_id } => run_cancel(&client, &run_id).await,
         Command::Reset { run_id } => run_reset(&client, &run_id).await,
         Command::Retry { run_id } => run_retry(&client, &run_id).await,
+        Command::Priority { run_id, priority } => run_priority(&client, &run_id, priority).await,
+        Command::Queue => run_queue(&client).await,
+        Command::Schedule { command } => run_schedule(&client, command).await,
+        Command::Gc { dry_run } => run_gc(&client, dry_run).await,
         Command::Worktrees { workspace } => run_worktrees(&client, &workspace).await,
         Command::WorktreeRm {
             path,
             workspace,
             force,
         } => run_worktree_rm(&client, &workspace, &path, force).await,
-        Command::Tail { run_id, follow } => run_tail(&client, &run_id, follow).await,
+        Command::Tail {
+            run_id,
+            follow,
+            log_dir,
+        } => match log_dir {
+            Some(log_dir) => tail_local(&log_dir.join(format!("run-{run_id}")), follow).await,
+            None => run_tail(&client, &run_id, follow).await,
+        },
+        Command::Transcript {
+            run_id,
+            iteration,
+            phase,
+            tool,
+        } => run_transcript(&client, &run_id, iteration, phase, tool.as_deref()).await,
+        Command::Cost {
+            by,
+            since,
+            until,
+            workspace,
+        } => run_cost(&client, &by, since, until, workspace).await,
         Command::Analyze {
             run_id,
             latest,
@@ -382,8 +698,14 @@ async fn run_create(
     worktrunk_bin: Option<PathBuf>,
     worktrunk_config: Option<PathBuf>,
     worktrunk_copy_ignored: bool,
+    priority: Option<i32>,
+    mut depends_on: Vec<RunDependency>,
+    branch_from_dependency: bool,
 ) -> Result<(), ClientError> {
     let inputs = resolve_run_inputs(spec, plan, config, pick)?;
+    if let Some(first) = depends_on.first_mut() {
+        first.branch_from = branch_from_dependency;
+    }
 
     let req = client::CreateRunRequest {
         spec_path: inputs.spec_path.to_string_lossy().to_string(),
@@ -407,6 +729,8 @@ async fn run_create(
         worktrunk_bin: worktrunk_bin.map(|p| p.to_string_lossy().to_string()),
         worktrunk_config_path: worktrunk_config.map(|p| p.to_string_lossy().to_string()),
         worktrunk_copy_ignored: worktrunk_copy_ignored.then_some(true),
+        priority,
+        depends_on,
     };
 
     let run = client.create_run(req).await?;
@@ -416,7 +740,26 @@ async fn run_create(
 
 async fn run_list(
     client: &Client,
-    status: Option<RunStatus>,
+    mut params: ListRunsParams,
+    workspace: bool,
+) -> Result<(), ClientError> {
+    if workspace {
+        params.workspace_root = Some(find_workspace_root()?.to_string_lossy().to_string());
+    }
+
+    let page = client.list_runs(&params).await?;
+    render::print_run_list(&page.runs);
+    if let Some(cursor) = page.next_cursor {
+        println!("\nMore runs available: loopctl list --cursor {cursor} (same filters)");
+    }
+    Ok(())
+}
+
+async fn run_cost(
+    client: &Client,
+    by: &str,
+    since: Option<i64>,
+    until: Option<i64>,
     workspace: bool,
 ) -> Result<(), ClientError> {
     let workspace_root = if workspace {
@@ -425,8 +768,34 @@ async fn run_list(
         None
     };
 
-    let runs = client.list_runs(status, workspace_root.as_deref()).await?;
-    render::print_run_list(&runs);
+    let report = client
+        .get_usage(by, workspace_root.as_deref(), since, until)
+        .await?;
+    render::print_usage_report(by, &report);
+    Ok(())
+}
+
+async fn run_transcript(
+    client: &Client,
+    run_id: &str,
+    iteration: Option<u32>,
+    phase: StepPhase,
+    tool: Option<&str>,
+) -> Result<(), ClientError> {
+    let steps = client.list_steps(run_id).await?;
+    let step = steps
+        .iter()
+        .rfind(|step| step.phase == phase && iteration.is_none_or(|n| step.attempt == n))
+        .ok_or_else(|| {
+            let which = iteration.map_or_else(|| "any".to_string(), |n| n.to_string());
+            ClientError::InvalidResponse(format!(
+                "no {} step for iteration {which} in run {run_id}",
+                phase.as_str()
+            ))
+        })?;
+
+    let transcript = client.get_step_transcript(run_id, &step.id.0, tool).await?;
+    render::print_transcript(&transcript.step, &transcript.entries);
     Ok(())
 }
 
@@ -467,6 +836,74 @@ async fn run_retry(client: &Client, run_id: &str) -> Result<(), ClientError> {
     Ok(())
 }
 
+async fn run_priority(client: &Client, run_id: &str, priority: i32) -> Result<(), ClientError> {
+    client.set_run_priority(run_id, priority).await?;
+    println!("Run {run_id} priority set to {priority}");
+    Ok(())
+}
+
+async fn run_queue(client: &Client) -> Result<(), ClientError> {
+    let queue = client.get_queue().await?;
+    render::print_queue(&queue);
+    Ok(())
+}
+
+async fn run_gc(client: &Client, dry_run: bool) -> Result<(), ClientError> {
+    let report = client.run_gc(dry_run).await?;
+    render::print_gc_report(&report);
+    Ok(())
+}
+
+async fn run_schedule(client: &Client, command: ScheduleCommand) -> Result<(), ClientError> {
+    match command {
+        ScheduleCommand::Add {
+            spec,
+            cron,
+            plan,
+            config,
+            name,
+            missed_fire,
+            overlap,
+            priority,
+        } => {
+            let inputs = resolve_run_inputs(Some(spec), plan, config, false)?;
+            let req = client::CreateScheduleRequest {
+                spec_path: inputs.spec_path.to_string_lossy().to_string(),
+                plan_path: inputs
+                    .plan_path
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string()),
+                workspace_root: inputs.workspace_root.to_string_lossy().to_string(),
+                config_override: inputs
+                    .config_path
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string()),
+                name,
+                cron,
+                missed_fire_policy: missed_fire,
+                overlap_policy: overlap,
+                priority,
+            };
+            let schedule = client.create_schedule(req).await?;
+            render::print_schedule_created(&schedule);
+        }
+        ScheduleCommand::List { workspace } => {
+            let workspace_root = if workspace {
+                Some(find_workspace_root()?.to_string_lossy().to_string())
+            } else {
+                None
+            };
+            let schedules = client.list_schedules(workspace_root.as_deref()).await?;
+            render::print_schedules(&schedules);
+        }
+        ScheduleCommand::Rm { schedule_id } => {
+            client.delete_schedule(&schedule_id).await?;
+            println!("Schedule {schedule_id} deleted");
+        }
+    }
+    Ok(())
+}
+
 async fn run_worktrees(client: &Client, workspace: &str) -> Result<(), ClientError> {
     let workspace = std::fs::canonicalize(workspace)
         .map_err(|e| ClientError::IoError(format!("invalid workspace path: {e}")))?;
@@ -526,6 +963,44 @@ async fn run_tail(client: &Client, run_id: &str, follow: bool) -> Result<(), Cli
     client.tail_run(run_id, follow).await
 }
 
+/// Print a run's step logs straight from disk, whether compressed or live.
+///
+/// With `follow`, polls for new output until interrupted.
+async fn tail_local(run_dir: &Path, follow: bool) -> Result<(), ClientError> {
+    use std::io::Write;
+
+    let io_error = |e: std::io::Error| ClientError::IoError(format!("{}: {e}", run_dir.display()));
+    let mut offsets: HashMap<PathBuf, u64> = HashMap::new();
+    // Compressed logs belong to finished steps and have been printed in full.
+    let mut finished: HashSet<PathBuf> = HashSet::new();
+    loop {
+        for path in logs::iteration_logs(run_dir).map_err(io_error)? {
+            if finished.contains(&path) {
+                continue;
+            }
+            let offset = offsets.entry(path.clone()).or_insert(0);
+            let compressed = logs::resolve(&path).is_some_and(|file| logs::is_compressed(&file));
+            match logs::read_from(&path, *offset) {
+                Ok(bytes) => {
+                    print!("{}", String::from_utf8_lossy(&bytes));
+                    *offset += bytes.len() as u64;
+                    if compressed {
+                        finished.insert(path);
+                    }
+                }
+                // Evicted, or swapped for its compressed form mid-read.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+        if !follow {
+            return Ok(());
+        }
+        std::io::stdout().flush().map_err(io_error)?;
+        tokio::time::sleep(LOCAL_TAIL_POLL_INTERVAL).await;
+    }
+}
+
 async fn run_analyze(
     client: &Client,
     run_id: Option<String>,
@@ -539,16 +1014,16 @@ async fn run_analyze(
         id
     } else if latest {
         // Find most recent run from daemon
-        let runs = client.list_runs(None, None).await?;
-        runs.into_iter()
-            .max_by_key(|r| r.created_at)
+        client
+            .latest_run()
+            .await?
             .map(|r| r.id.to_string())
             .ok_or_else(|| ClientError::IoError("no runs found".to_string()))?
     } else {
         // Default to latest if no ID provided
-        let runs = client.list_runs(None, None).await?;
-        runs.into_iter()
-            .max_by_key(|r| r.created_at)
+        client
+            .latest_run()
+            .await?
             .map(|r| r.id.to_string())
             .ok_or_else(|| ClientError::IoError("no runs found".to_string()))?
     };
@@ -761,10 +1236,8 @@ fn parse_report_metadata(
                     last_iter_log = Some(output_path.to_string());
                 }
             }
-            "ITERATION_TAIL" => {
-                if !output_path.is_empty() {
-                    last_iter_tail = Some(output_path.to_string());
-                }
+            "ITERATION_TAIL" if !output_path.is_empty() => {
+                last_iter_tail = Some(output_path.to_string());
             }
             "COMPLETE_DETECTED" => {
                 completion_iter = iteration;
@@ -3,7 +3,12 @@
 //! Formats run and step information for terminal display.
 //! See spec Section 7.2 for diagnostics output requirements.
 
-use loop_core::types::{Run, RunStatus, Step, StepStatus};
+use loop_core::types::{
+    GcReport, QueueBlock, Run, RunStatus, Schedule, Step, StepStatus, TokenUsage,
+};
+use loop_core::{Config, TranscriptEntry, TranscriptEntryKind};
+
+use crate::client::{QueueResponse, UsageResponse};
 
 #[cfg(test)]
 use loop_core::types::ReviewStatus;
@@ -82,6 +87,33 @@ pub fn print_run_details(run: &Run, steps: &[Step]) {
     print!("{}", render_run_details(run, steps));
 }
 
+/// Sandbox mode of a run config, or `None` when unsandboxed.
+///
+/// Under `fs+net` the agent's allowed ports are spelled out, since the agent
+/// keeps network access to its model API.
+fn describe_sandbox(config: &Config) -> Option<String> {
+    if !config.sandbox.restricts_fs() {
+        return None;
+    }
+    if !config.sandbox.restricts_net() {
+        return Some(config.sandbox.as_str().to_string());
+    }
+    let ports: Vec<String> = config
+        .sandbox_agent_ports
+        .iter()
+        .map(u16::to_string)
+        .collect();
+    Some(if ports.is_empty() {
+        format!("{} (no network)", config.sandbox.as_str())
+    } else {
+        format!(
+            "{} (agent may connect to TCP port {})",
+            config.sandbox.as_str(),
+            ports.join(", ")
+        )
+    })
+}
+
 /// Render detailed run information to string.
 ///
 /// Per spec Section 7.2, this shows:
@@ -96,11 +128,22 @@ pub fn render_run_details(run: &Run, steps: &[Step]) -> String {
     writeln!(out, "  Name:           {}", run.name).unwrap();
     writeln!(out, "  Name Source:    {}", run.name_source.as_str()).unwrap();
     writeln!(out, "  Status:         {}", format_status(run.status)).unwrap();
+    if run.priority != 0 {
+        writeln!(out, "  Priority:       {}", run.priority).unwrap();
+    }
     writeln!(out, "  Workspace:      {}", run.workspace_root).unwrap();
     writeln!(out, "  Spec:           {}", run.spec_path).unwrap();
     if let Some(ref plan) = run.plan_path {
         writeln!(out, "  Plan:           {plan}").unwrap();
     }
+    if let Some(sandbox) = run
+        .config_json
+        .as_deref()
+        .and_then(|json| serde_json::from_str::<Config>(json).ok())
+        .and_then(|config| describe_sandbox(&config))
+    {
+        writeln!(out, "  Sandbox:        {sandbox}").unwrap();
+    }
 
     // Worktree info
     if let Some(ref wt) = run.worktree {
@@ -165,6 +208,347 @@ pub fn render_run_details(run: &Run, steps: &[Step]) -> String {
     out
 }
 
+/// Print aggregated token usage and cost.
+pub fn print_usage_report(by: &str, report: &UsageResponse) {
+    print!("{}", render_usage_report(by, report));
+}
+
+/// Render aggregated usage table to string.
+///
+/// Groups are listed in the order returned by the daemon (highest cost first),
+/// followed by a total row.
+pub fn render_usage_report(by: &str, report: &UsageResponse) -> String {
+    let mut out = String::new();
+
+    if report.groups.is_empty() {
+        writeln!(out, "No usage recorded.").unwrap();
+        return out;
+    }
+
+    let key_header = by.to_uppercase();
+    writeln!(
+        out,
+        "{:<40}  {:>5}  {:>6}  {:>12}  {:>12}  {:>12}  {:>10}",
+        key_header, "RUNS", "STEPS", "INPUT", "OUTPUT", "CACHED", "COST"
+    )
+    .unwrap();
+    writeln!(out, "{}", "-".repeat(111)).unwrap();
+
+    let mut runs = 0;
+    let mut steps = 0;
+    for group in &report.groups {
+        runs += group.runs;
+        steps += group.steps;
+        let label = if by == "workspace" {
+            workspace_name(&group.label)
+        } else {
+            group.label.clone()
+        };
+        writeln!(
+            out,
+            "{:<40}  {}",
+            truncate(&label, 40),
+            format_usage_columns(group.runs, group.steps, &group.usage),
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "{}", "-".repeat(111)).unwrap();
+    writeln!(
+        out,
+        "{:<40}  {}",
+        "TOTAL",
+        format_usage_columns(runs, steps, &report.total),
+    )
+    .unwrap();
+    out
+}
+
+/// Print the pending queue.
+pub fn print_queue(queue: &QueueResponse) {
+    print!("{}", render_queue(queue));
+}
+
+/// Render the pending queue in claim order to string.
+///
+/// Priorities show as `effective (base)` once aging has raised them.
+pub fn render_queue(queue: &QueueResponse) -> String {
+    let mut out = String::new();
+
+    if queue.entries.is_empty() {
+        writeln!(out, "Queue is empty.").unwrap();
+        return out;
+    }
+
+    writeln!(
+        out,
+        "{:>3}  {:<36}  {:<20}  {:<16}  {:<9}  WAITING ON",
+        "#", "ID", "NAME", "WORKSPACE", "PRIORITY"
+    )
+    .unwrap();
+    writeln!(out, "{}", "-".repeat(120)).unwrap();
+
+    for (position, entry) in queue.entries.iter().enumerate() {
+        let priority = if entry.effective_priority == i64::from(entry.priority) {
+            entry.priority.to_string()
+        } else {
+            format!("{} ({})", entry.effective_priority, entry.priority)
+        };
+        let waiting = entry
+            .blocked
+            .as_ref()
+            .map_or_else(|| "- (next)".to_string(), QueueBlock::describe);
+        writeln!(
+            out,
+            "{:>3}  {:<36}  {:<20}  {:<16}  {:<9}  {}",
+            position + 1,
+            entry.run_id.0,
+            truncate(&entry.name, 20),
+            truncate(&workspace_name(&entry.workspace_root), 16),
+            priority,
+            waiting,
+        )
+        .unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "{} pending run(s), policy {}",
+        queue.entries.len(),
+        queue.policy.as_str()
+    )
+    .unwrap();
+    out
+}
+
+/// Print confirmation after creating a schedule.
+pub fn print_schedule_created(schedule: &Schedule) {
+    print!("{}", render_schedule_created(schedule));
+}
+
+/// Render schedule creation confirmation to string.
+pub fn render_schedule_created(schedule: &Schedule) -> String {
+    let mut out = String::new();
+    writeln!(out, "Created schedule: {}", schedule.id).unwrap();
+    writeln!(out, "  Name:      {}", schedule.name).unwrap();
+    writeln!(out, "  Spec:      {}", schedule.spec_path).unwrap();
+    writeln!(out, "  Cron:      {}", schedule.cron).unwrap();
+    writeln!(
+        out,
+        "  Policies:  missed fires {}, overlap {}",
+        schedule.missed_fire_policy.as_str(),
+        schedule.overlap_policy.as_str()
+    )
+    .unwrap();
+    writeln!(out, "  Next fire: {}", format_time(&schedule.next_fire_at)).unwrap();
+    out
+}
+
+/// Print schedules in tabular format.
+pub fn print_schedules(schedules: &[Schedule]) {
+    print!("{}", render_schedules(schedules));
+}
+
+/// Render schedules to string.
+pub fn render_schedules(schedules: &[Schedule]) -> String {
+    let mut out = String::new();
+
+    if schedules.is_empty() {
+        writeln!(out, "No schedules found.").unwrap();
+        return out;
+    }
+
+    writeln!(
+        out,
+        "{:<36}  {:<20}  {:<16}  {:<16}  {:<20}  LAST RUN",
+        "ID", "NAME", "WORKSPACE", "CRON", "NEXT FIRE"
+    )
+    .unwrap();
+    writeln!(out, "{}", "-".repeat(140)).unwrap();
+
+    for schedule in schedules {
+        writeln!(
+            out,
+            "{:<36}  {:<20}  {:<16}  {:<16}  {:<20}  {}",
+            schedule.id.0,
+            truncate(&schedule.name, 20),
+            truncate(&workspace_name(&schedule.workspace_root), 16),
+            truncate(&schedule.cron, 16),
+            format_time(&schedule.next_fire_at),
+            schedule
+                .last_run_id
+                .as_ref()
+                .map_or("-", |id| id.0.as_str()),
+        )
+        .unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "{} schedule(s)", schedules.len()).unwrap();
+    out
+}
+
+/// Print a garbage collection report.
+pub fn print_gc_report(report: &GcReport) {
+    print!("{}", render_gc_report(report));
+}
+
+/// Render a garbage collection report to string.
+///
+/// Dry runs describe what would be removed; nothing has been deleted.
+pub fn render_gc_report(report: &GcReport) -> String {
+    let mut out = String::new();
+
+    if !report.policy.is_enabled() {
+        writeln!(
+            out,
+            "No retention limits configured; only artifact rows for missing files are removed."
+        )
+        .unwrap();
+    }
+
+    if report.runs.is_empty() {
+        writeln!(out, "No runs to prune.").unwrap();
+    } else {
+        writeln!(
+            out,
+            "{:<36}  {:<20}  {:<16}  {:<10}  {:<22}  SIZE",
+            "ID", "NAME", "WORKSPACE", "STATUS", "REASON"
+        )
+        .unwrap();
+        writeln!(out, "{}", "-".repeat(124)).unwrap();
+
+        for run in &report.runs {
+            writeln!(
+                out,
+                "{:<36}  {:<20}  {:<16}  {:<10}  {:<22}  {}",
+                run.run_id.0,
+                truncate(&run.name, 20),
+                truncate(&workspace_name(&run.workspace_root), 16),
+                format_status(run.status),
+                run.reason.as_str(),
+                format_bytes(run.bytes),
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "{} {} run(s), {} step event(s), {} artifact row(s), {}",
+        if report.dry_run {
+            "Would prune"
+        } else {
+            "Pruned"
+        },
+        report.runs.len(),
+        report.events_removed,
+        report.artifacts_removed,
+        format_bytes(report.bytes_freed)
+    )
+    .unwrap();
+    if report.dry_run {
+        writeln!(out, "Dry run: nothing was deleted.").unwrap();
+    }
+    out
+}
+
+/// Print a step transcript.
+pub fn print_transcript(step: &Step, entries: &[TranscriptEntry]) {
+    print!("{}", render_transcript(step, entries));
+}
+
+/// Lines of tool output shown per result before eliding the rest.
+const TRANSCRIPT_RESULT_LINES: usize = 20;
+
+/// Render a step transcript to string.
+///
+/// Tool calls show their input (the command for `Bash`, JSON otherwise);
+/// results show their duration and the first lines of output.
+pub fn render_transcript(step: &Step, entries: &[TranscriptEntry]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Step {} ({} iteration {}, {})",
+        step.id,
+        step.phase.as_str(),
+        step.attempt,
+        format_step_status(step.status)
+    )
+    .unwrap();
+
+    if entries.is_empty() {
+        writeln!(out, "No transcript recorded.").unwrap();
+        return out;
+    }
+
+    for entry in entries {
+        writeln!(out).unwrap();
+        let time = entry.at.format("%H:%M:%S");
+        let tool = entry.tool_name.as_deref().unwrap_or("?");
+        match entry.kind {
+            TranscriptEntryKind::AssistantMessage => {
+                writeln!(out, "[{:>3}] {time} assistant", entry.seq).unwrap();
+            }
+            TranscriptEntryKind::ToolUse => {
+                writeln!(out, "[{:>3}] {time} tool_use {tool}", entry.seq).unwrap();
+                if let Some(input) = &entry.input {
+                    let input = match input.get("command").and_then(|c| c.as_str()) {
+                        Some(command) if tool == "Bash" => format!("$ {command}"),
+                        _ => input.to_string(),
+                    };
+                    write_indented(&mut out, &input, usize::MAX);
+                }
+            }
+            TranscriptEntryKind::ToolResult => {
+                let duration = entry
+                    .duration_ms
+                    .map(|ms| format!(" ({ms}ms)"))
+                    .unwrap_or_default();
+                let error = if entry.is_error { " ERROR" } else { "" };
+                writeln!(
+                    out,
+                    "[{:>3}] {time} tool_result {tool}{duration}{error}",
+                    entry.seq
+                )
+                .unwrap();
+            }
+        }
+        if let Some(text) = &entry.text {
+            let max_lines = match entry.kind {
+                TranscriptEntryKind::ToolResult => TRANSCRIPT_RESULT_LINES,
+                _ => usize::MAX,
+            };
+            write_indented(&mut out, text, max_lines);
+        }
+    }
+    out
+}
+
+fn write_indented(out: &mut String, text: &str, max_lines: usize) {
+    let total = text.lines().count();
+    for line in text.lines().take(max_lines) {
+        writeln!(out, "      {line}").unwrap();
+    }
+    if total > max_lines {
+        writeln!(out, "      ... ({} more lines)", total - max_lines).unwrap();
+    }
+}
+
+fn format_usage_columns(runs: u64, steps: u64, usage: &TokenUsage) -> String {
+    format!(
+        "{:>5}  {:>6}  {:>12}  {:>12}  {:>12}  {:>10}",
+        runs,
+        steps,
+        usage.input_tokens,
+        usage.output_tokens,
+        usage.cache_creation_input_tokens + usage.cache_read_input_tokens,
+        format!("${:.2}", usage.cost_usd),
+    )
+}
+
 fn format_status(status: RunStatus) -> &'static str {
     match status {
         RunStatus::Pending => "PENDING",
@@ -187,6 +571,20 @@ fn format_step_status(status: StepStatus) -> &'static str {
     }
 }
 
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
 fn format_time(dt: &chrono::DateTime<chrono::Utc>) -> String {
     dt.format("%Y-%m-%d %H:%M:%S").to_string()
 }
@@ -224,6 +622,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         }
     }
 
@@ -239,6 +638,7 @@ mod tests {
             exit_code: Some(0),
             prompt_path: Some("/workspace/logs/loop/prompt.txt".to_string()),
             output_path: Some("/workspace/logs/loop/output.log".to_string()),
+            session_id: None,
         }
     }
 
@@ -260,6 +660,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::single_char_pattern)]
     fn inspect_shows_last_step() {
         let run = make_test_run();
         let step = make_test_step(&run.id);
@@ -318,6 +719,20 @@ mod tests {
         assert!(output.contains("Path:           ../workspace.run-test-run"));
     }
 
+    #[test]
+    fn inspect_shows_agent_ports_under_fs_net_sandbox() {
+        let mut run = make_test_run();
+        assert!(!render_run_details(&run, &[]).contains("Sandbox:"));
+
+        let config = Config {
+            sandbox: loop_core::SandboxMode::FsNet,
+            ..Config::default()
+        };
+        run.config_json = Some(serde_json::to_string(&config).unwrap());
+        let output = render_run_details(&run, &[]);
+        assert!(output.contains("Sandbox:        fs+net (agent may connect to TCP port 443)"));
+    }
+
     // --- Status formatting tests ---
 
     #[test]
@@ -434,6 +849,7 @@ mod tests {
             exit_code: Some(0),
             prompt_path: None,
             output_path: None,
+            session_id: None,
         };
         let step2 = Step {
             id: Id::from_string("step-2"),
@@ -446,6 +862,7 @@ mod tests {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         };
 
         let output = render_run_details(&run, &[step1, step2]);
@@ -459,6 +876,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::single_char_pattern)]
     fn inspect_shows_dash_for_missing_exit_code() {
         let run = make_test_run();
         let step = Step {
@@ -472,6 +890,7 @@ mod tests {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         };
 
         let output = render_run_details(&run, &[step]);
@@ -488,4 +907,228 @@ mod tests {
         // The exit code column shows "-" (padded with spaces due to column width)
         assert!(step_line.trim_end().ends_with("-"));
     }
+
+    // --- Transcript tests ---
+
+    fn make_entry(seq: u32, kind: TranscriptEntryKind) -> TranscriptEntry {
+        TranscriptEntry {
+            seq,
+            kind,
+            text: None,
+            tool_use_id: Some("toolu_1".to_string()),
+            tool_name: Some("Bash".to_string()),
+            input: None,
+            is_error: false,
+            at: Utc::now(),
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn transcript_shows_empty_message() {
+        let run = make_test_run();
+        let step = make_test_step(&run.id);
+        let output = render_transcript(&step, &[]);
+        assert!(output.contains("implementation iteration 1"));
+        assert!(output.contains("No transcript recorded."));
+    }
+
+    #[test]
+    fn transcript_shows_bash_commands_and_results() {
+        let run = make_test_run();
+        let step = make_test_step(&run.id);
+        let mut message = make_entry(0, TranscriptEntryKind::AssistantMessage);
+        message.tool_use_id = None;
+        message.tool_name = None;
+        message.text = Some("Running the tests.".to_string());
+        let mut call = make_entry(1, TranscriptEntryKind::ToolUse);
+        call.input = Some(serde_json::json!({"command": "cargo test"}));
+        let mut result = make_entry(2, TranscriptEntryKind::ToolResult);
+        result.is_error = true;
+        result.duration_ms = Some(1500);
+        result.text = Some(
+            (1..=25)
+                .map(|n| format!("line {n}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+
+        let output = render_transcript(&step, &[message, call, result]);
+        assert!(output.contains("assistant\n      Running the tests."));
+        assert!(output.contains("tool_use Bash\n      $ cargo test"));
+        assert!(output.contains("tool_result Bash (1500ms) ERROR"));
+        assert!(output.contains("line 20\n"));
+        assert!(!output.contains("line 21"));
+        assert!(output.contains("... (5 more lines)"));
+    }
+
+    // --- Usage report tests ---
+
+    #[test]
+    fn usage_report_shows_empty_message() {
+        let report = UsageResponse {
+            groups: vec![],
+            total: TokenUsage::default(),
+        };
+        let output = render_usage_report("run", &report);
+        assert!(output.contains("No usage recorded."));
+    }
+
+    #[test]
+    fn usage_report_shows_groups_and_total() {
+        let usage = TokenUsage {
+            input_tokens: 1000,
+            output_tokens: 250,
+            cache_creation_input_tokens: 10,
+            cache_read_input_tokens: 90,
+            cost_usd: 1.234,
+        };
+        let report = UsageResponse {
+            groups: vec![crate::client::UsageGroup {
+                key: "/home/me/projects/app".to_string(),
+                label: "/home/me/projects/app".to_string(),
+                runs: 3,
+                steps: 12,
+                usage,
+            }],
+            total: usage,
+        };
+
+        let output = render_usage_report("workspace", &report);
+        assert!(output.contains("WORKSPACE"));
+        assert!(output.contains("COST"));
+        // Workspace paths are shortened to the project name.
+        assert!(output.contains("app"));
+        assert!(!output.contains("/home/me/projects/app"));
+        assert!(output.contains("1000"));
+        assert!(output.contains("$1.23"));
+        assert!(output.lines().last().unwrap().starts_with("TOTAL"));
+    }
+
+    // --- Queue tests ---
+
+    #[test]
+    fn queue_shows_empty_message() {
+        let queue = QueueResponse {
+            policy: loop_core::QueuePolicy::Fifo,
+            entries: vec![],
+        };
+        assert!(render_queue(&queue).contains("Queue is empty."));
+    }
+
+    #[test]
+    fn queue_shows_order_priority_and_block_reason() {
+        let entry = |id: &str, priority, effective_priority, blocked| loop_core::QueueEntry {
+            run_id: Id::from_string(id),
+            name: format!("run-{id}"),
+            workspace_root: "/home/me/projects/app".to_string(),
+            priority,
+            effective_priority,
+            created_at: Utc::now(),
+            blocked,
+        };
+        let queue = QueueResponse {
+            policy: loop_core::QueuePolicy::FairShare,
+            entries: vec![
+                entry("a", 5, 5, None),
+                entry("b", 0, 2, Some(QueueBlock::WorkspaceCap { cap: 1 })),
+            ],
+        };
+
+        let output = render_queue(&queue);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[2].trim_start().starts_with("1  a"));
+        assert!(lines[2].contains("- (next)"));
+        assert!(lines[3].trim_start().starts_with("2  b"));
+        assert!(lines[3].contains("2 (0)"));
+        assert!(lines[3].contains("workspace cap (1 per workspace)"));
+        assert!(output.contains("2 pending run(s), policy fair_share"));
+    }
+
+    fn test_schedule() -> Schedule {
+        let now = Utc::now();
+        Schedule {
+            id: Id::from_string("sched-1"),
+            name: "nightly-audit".to_string(),
+            workspace_root: "/home/me/projects/app".to_string(),
+            spec_path: "/home/me/projects/app/specs/audit.md".to_string(),
+            plan_path: None,
+            config_override: None,
+            cron: "0 2 * * *".to_string(),
+            missed_fire_policy: loop_core::MissedFirePolicy::CatchUp,
+            overlap_policy: loop_core::OverlapPolicy::Skip,
+            priority: 0,
+            next_fire_at: now,
+            last_fire_at: None,
+            last_run_id: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn schedule_created_shows_cron_and_policies() {
+        let output = render_schedule_created(&test_schedule());
+        assert!(output.contains("Created schedule: sched-1"));
+        assert!(output.contains("Cron:      0 2 * * *"));
+        assert!(output.contains("missed fires catch_up, overlap skip"));
+    }
+
+    #[test]
+    fn schedules_list_shows_rows_and_last_run() {
+        assert!(render_schedules(&[]).contains("No schedules found."));
+
+        let fired = Schedule {
+            last_run_id: Some(Id::from_string("run-9")),
+            ..test_schedule()
+        };
+        let output = render_schedules(&[test_schedule(), fired]);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[2].starts_with("sched-1"));
+        assert!(lines[2].contains("nightly-audit"));
+        assert!(lines[2].contains("app"));
+        assert!(lines[2].trim_end().ends_with('-'));
+        assert!(lines[3].trim_end().ends_with("run-9"));
+        assert!(output.contains("2 schedule(s)"));
+    }
+
+    // --- GC report tests ---
+
+    #[test]
+    fn gc_report_lists_pruned_runs_and_totals() {
+        let report = GcReport {
+            dry_run: true,
+            policy: loop_core::RetentionPolicy {
+                max_age_days: Some(30),
+                ..loop_core::RetentionPolicy::default()
+            },
+            runs: vec![loop_core::PrunedRun {
+                run_id: Id::from_string("run-1"),
+                name: "old-run".to_string(),
+                workspace_root: "/home/me/projects/app".to_string(),
+                status: RunStatus::Failed,
+                reason: loop_core::PruneReason::MaxAge,
+                bytes: 3 * 1024 * 1024,
+            }],
+            events_removed: 12,
+            artifacts_removed: 4,
+            bytes_freed: 3 * 1024 * 1024,
+        };
+
+        let output = render_gc_report(&report);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[2].starts_with("run-1"));
+        assert!(lines[2].contains("FAILED"));
+        assert!(lines[2].contains("max_age"));
+        assert!(lines[2].trim_end().ends_with("3.0 MB"));
+        assert!(
+            output.contains("Would prune 1 run(s), 12 step event(s), 4 artifact row(s), 3.0 MB")
+        );
+        assert!(output.contains("Dry run: nothing was deleted."));
+
+        let empty = render_gc_report(&GcReport::default());
+        assert!(empty.starts_with("No retention limits configured"));
+        assert!(empty.contains("No runs to prune."));
+        assert!(empty.contains("Pruned 0 run(s), 0 step event(s), 0 artifact row(s), 0 B"));
+    }
 }
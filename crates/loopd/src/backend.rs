@@ -0,0 +1,758 @@
+//! Agent backend interface and implementations.
+//!
+//! The runner owns retries, timeouts, heartbeats, cancellation and artifact
+//! writing; a backend only knows how to start one agent invocation and which
+//! wire format its output stream uses. Implementations:
+//! - Claude CLI: `claude -p --output-format stream-json` (default).
+//! - OpenAI-compatible HTTP: streaming `POST {base}/chat/completions`.
+//! - Command: any shell command that reads the prompt on stdin and writes
+//!   plain text to stdout.
+//! - Replay: plays back a recorded fixture bundle (see [`crate::replay`]).
+
+use loop_core::{AgentBackendKind, Config, StepPhase, TokenUsage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::replay::ReplayBackend;
+use crate::resources::{exit_signal, ResourceGuard};
+use crate::runner::{Result, RunnerError};
+use crate::sandbox::{Sandbox, SandboxedProcess};
+
+/// Buffer size for the in-memory pipe between HTTP backends and the runner.
+const HTTP_PIPE_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Wire format of a backend's stdout stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentOutputFormat {
+    /// Claude Code `--output-format stream-json` events (one JSON object per line).
+    ClaudeStreamJson,
+    /// OpenAI chat completions SSE (`data: {...}` lines, `data: [DONE]` terminator).
+    #[serde(rename = "openai_sse")]
+    OpenAiSse,
+    /// Plain text, written to the log as-is.
+    Text,
+}
+
+impl AgentOutputFormat {
+    /// Whether raw lines should also be dumped to the `.jsonl` debug sibling.
+    pub fn is_structured(self) -> bool {
+        !matches!(self, Self::Text)
+    }
+}
+
+/// A single agent invocation request.
+#[derive(Debug, Clone, Copy)]
+pub struct AgentRequest<'a> {
+    /// Phase of the step the invocation belongs to.
+    pub phase: StepPhase,
+    pub prompt: &'a str,
+    pub model: &'a str,
+    pub working_dir: &'a Path,
+    /// Tool permissions; `None` runs the agent with all permissions.
+    pub permissions: Option<AgentPermissions<'a>>,
+    /// Process sandbox; in-process backends (OpenAI) have nothing to confine.
+    pub sandbox: Option<&'a Sandbox>,
+    /// Resource limits for the agent process; ignored by in-process backends.
+    pub limits: Option<&'a ResourceGuard>,
+    /// Session to continue instead of starting a new conversation.
+    ///
+    /// Only the Claude backend supports it; other backends start fresh.
+    pub resume_session: Option<&'a str>,
+}
+
+/// Tool permissions for one invocation, materialized as a settings file.
+///
+/// Only the Claude backend enforces them.
+#[derive(Debug, Clone, Copy)]
+pub struct AgentPermissions<'a> {
+    /// Claude settings file holding the `permissions` allow/deny rules.
+    pub settings_path: &'a Path,
+    /// Only allowed tools may run; otherwise everything not denied may run.
+    pub allowlist: bool,
+}
+
+/// Handle to an in-flight agent invocation.
+#[derive(Debug)]
+pub enum AgentHandle {
+    /// A spawned child process.
+    Process(Child),
+    /// An in-process task (HTTP backends). Resolves to a process-style exit code.
+    Task(JoinHandle<std::io::Result<i32>>),
+}
+
+impl AgentHandle {
+    /// Wait for the invocation to finish and return its exit code.
+    ///
+    /// Cancel-safe: may be used inside `tokio::select!`.
+    pub async fn wait(&mut self) -> std::io::Result<i32> {
+        match self {
+            Self::Process(child) => child.wait().await.map(|s| s.code().unwrap_or(-1)),
+            Self::Task(handle) => match handle.await {
+                Ok(result) => result,
+                Err(err) => Err(std::io::Error::other(err)),
+            },
+        }
+    }
+
+    /// Signal that terminated the process, once it has been reaped.
+    pub fn signal(&mut self) -> Option<i32> {
+        match self {
+            Self::Process(child) => {
+                let status = child.try_wait().ok().flatten()?;
+                exit_signal(&status)
+            }
+            Self::Task(_) => None,
+        }
+    }
+
+    /// Terminate the invocation and reap it.
+    pub async fn kill(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Process(child) => {
+                child.kill().await?;
+                let _ = child.wait().await;
+                Ok(())
+            }
+            Self::Task(handle) => {
+                handle.abort();
+                let _ = handle.await;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A started invocation: output streams plus a handle to wait on or kill.
+pub struct AgentInvocation {
+    pub stdout: Option<Box<dyn AsyncRead + Send + Unpin>>,
+    pub stderr: Option<Box<dyn AsyncRead + Send + Unpin>>,
+    pub handle: AgentHandle,
+    pub format: AgentOutputFormat,
+}
+
+impl std::fmt::Debug for AgentInvocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentInvocation")
+            .field("handle", &self.handle)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Trait for agent backends.
+///
+/// Backends translate their native stream into one of the
+/// [`AgentOutputFormat`]s; the runner turns that into the shared
+/// `StepResult` / `iter-XX.log` contract.
+pub trait AgentBackend: Send + Sync + std::fmt::Debug {
+    /// Start one invocation of the agent.
+    fn spawn(&self, request: &AgentRequest<'_>) -> Result<AgentInvocation>;
+
+    /// Get the backend type.
+    fn kind(&self) -> AgentBackendKind;
+}
+
+/// Backend settings extracted from the run config.
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    pub kind: AgentBackendKind,
+    pub claude_bin: PathBuf,
+    pub command: Option<String>,
+    pub openai_base_url: String,
+    pub openai_api_key_env: String,
+    pub replay_fixture: Option<PathBuf>,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl BackendConfig {
+    /// Create from loop-core Config.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            kind: config.agent_backend,
+            claude_bin: config.claude_bin.clone(),
+            command: config.agent_command.clone(),
+            openai_base_url: config.openai_base_url.clone(),
+            openai_api_key_env: config.openai_api_key_env.clone(),
+            replay_fixture: config.replay_fixture.clone(),
+        }
+    }
+}
+
+/// Build the backend implementation for the given config.
+pub fn create_backend(config: &BackendConfig) -> Box<dyn AgentBackend> {
+    match config.kind {
+        AgentBackendKind::Claude => Box::new(ClaudeCliBackend {
+            bin: config.claude_bin.clone(),
+        }),
+        AgentBackendKind::Openai => Box::new(OpenAiBackend {
+            base_url: config.openai_base_url.clone(),
+            api_key_env: config.openai_api_key_env.clone(),
+        }),
+        AgentBackendKind::Command => Box::new(CommandBackend {
+            command: config.command.clone().unwrap_or_default(),
+        }),
+        AgentBackendKind::Replay => Box::new(ReplayBackend::new(
+            config.replay_fixture.clone().unwrap_or_default(),
+        )),
+    }
+}
+
+/// Claude CLI backend (spec Section 4.2).
+#[derive(Debug)]
+pub struct ClaudeCliBackend {
+    bin: PathBuf,
+}
+
+impl AgentBackend for ClaudeCliBackend {
+    fn spawn(&self, request: &AgentRequest<'_>) -> Result<AgentInvocation> {
+        let mut cmd = Command::new(&self.bin);
+        cmd.arg("-p").arg("--verbose");
+        match request.permissions {
+            // In print mode tools outside the allow rules are denied, not prompted.
+            Some(permissions) if permissions.allowlist => {
+                cmd.arg("--permission-mode")
+                    .arg("default")
+                    .arg("--settings")
+                    .arg(permissions.settings_path);
+            }
+            // Deny rules still apply when permission checks are skipped.
+            Some(permissions) => {
+                cmd.arg("--dangerously-skip-permissions")
+                    .arg("--settings")
+                    .arg(permissions.settings_path);
+            }
+            None => {
+                cmd.arg("--dangerously-skip-permissions");
+            }
+        }
+        if let Some(session_id) = request.resume_session {
+            cmd.arg("--resume").arg(session_id);
+        }
+        cmd.arg("--output-format")
+            .arg("stream-json")
+            .arg("--model")
+            .arg(request.model)
+            .arg(request.prompt)
+            .current_dir(request.working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(sandbox) = request.sandbox {
+            sandbox.apply(&mut cmd, SandboxedProcess::Agent)?;
+        }
+        if let Some(limits) = request.limits {
+            limits.apply(&mut cmd)?;
+        }
+
+        debug!(
+            model = %request.model,
+            working_dir = %request.working_dir.display(),
+            "spawning claude process"
+        );
+
+        let mut child = cmd.spawn().map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                RunnerError::ClaudeNotFound
+            } else {
+                RunnerError::Io(e)
+            }
+        })?;
+        if let Some(limits) = request.limits {
+            limits.watch_cpu(child.id());
+        }
+
+        // Claude CLI with --output-format stream-json sends JSON events to stdout.
+        // With --verbose, debug/progress output goes to stderr.
+        Ok(AgentInvocation {
+            stdout: child
+                .stdout
+                .take()
+                .map(|s| Box::new(s) as Box<dyn AsyncRead + Send + Unpin>),
+            stderr: child
+                .stderr
+                .take()
+                .map(|s| Box::new(s) as Box<dyn AsyncRead + Send + Unpin>),
+            handle: AgentHandle::Process(child),
+            format: AgentOutputFormat::ClaudeStreamJson,
+        })
+    }
+
+    fn kind(&self) -> AgentBackendKind {
+        AgentBackendKind::Claude
+    }
+}
+
+/// Generic command backend: `sh -c <agent_command>` with the prompt on stdin.
+///
+/// The configured model is exported as `LOOP_MODEL`. Stdout is treated as
+/// plain text.
+#[derive(Debug)]
+pub struct CommandBackend {
+    command: String,
+}
+
+impl AgentBackend for CommandBackend {
+    fn spawn(&self, request: &AgentRequest<'_>) -> Result<AgentInvocation> {
+        if self.command.trim().is_empty() {
+            return Err(RunnerError::BackendUnavailable(
+                "agent_backend=command requires agent_command".to_string(),
+            ));
+        }
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(&self.command)
+            .env("LOOP_MODEL", request.model)
+            .current_dir(request.working_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(sandbox) = request.sandbox {
+            sandbox.apply(&mut cmd, SandboxedProcess::Agent)?;
+        }
+        if let Some(limits) = request.limits {
+            limits.apply(&mut cmd)?;
+        }
+
+        debug!(
+            command = %self.command,
+            working_dir = %request.working_dir.display(),
+            "spawning agent command"
+        );
+
+        let mut child = cmd.spawn().map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                RunnerError::BackendUnavailable("sh not found".to_string())
+            } else {
+                RunnerError::Io(e)
+            }
+        })?;
+        if let Some(limits) = request.limits {
+            limits.watch_cpu(child.id());
+        }
+
+        // Feed the prompt and close stdin so the command sees EOF.
+        if let Some(mut stdin) = child.stdin.take() {
+            let prompt = request.prompt.to_string();
+            tokio::spawn(async move {
+                if let Err(err) = stdin.write_all(prompt.as_bytes()).await {
+                    warn!(error = %err, "failed to write prompt to agent command stdin");
+                }
+            });
+        }
+
+        Ok(AgentInvocation {
+            stdout: child
+                .stdout
+                .take()
+                .map(|s| Box::new(s) as Box<dyn AsyncRead + Send + Unpin>),
+            stderr: child
+                .stderr
+                .take()
+                .map(|s| Box::new(s) as Box<dyn AsyncRead + Send + Unpin>),
+            handle: AgentHandle::Process(child),
+            format: AgentOutputFormat::Text,
+        })
+    }
+
+    fn kind(&self) -> AgentBackendKind {
+        AgentBackendKind::Command
+    }
+}
+
+/// OpenAI-compatible chat completions backend.
+///
+/// Streams the SSE response body through an in-memory pipe so the runner can
+/// treat it like process stdout. Non-2xx responses are surfaced as an
+/// `error` event followed by exit code 1; 5xx errors therefore read as
+/// `API Error: 5xx ...` and are retried as transient.
+#[derive(Debug)]
+pub struct OpenAiBackend {
+    base_url: String,
+    api_key_env: String,
+}
+
+impl AgentBackend for OpenAiBackend {
+    fn spawn(&self, request: &AgentRequest<'_>) -> Result<AgentInvocation> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let api_key = std::env::var(&self.api_key_env).ok();
+        let body = serde_json::json!({
+            "model": request.model,
+            "stream": true,
+            "stream_options": { "include_usage": true },
+            "messages": [{ "role": "user", "content": request.prompt }],
+        });
+
+        debug!(url = %url, model = %request.model, "starting openai-compatible request");
+
+        let (reader, mut writer) = tokio::io::duplex(HTTP_PIPE_BUFFER_BYTES);
+        let task = tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let mut req = client.post(&url).json(&body);
+            if let Some(key) = api_key {
+                req = req.bearer_auth(key);
+            }
+
+            let mut response = match req.send().await {
+                Ok(response) => response,
+                Err(err) => {
+                    // Connection failures are treated like an overloaded upstream.
+                    write_sse_error(&mut writer, &format!("503 {err}")).await?;
+                    return Ok(1);
+                }
+            };
+
+            let status = response.status();
+            if !status.is_success() {
+                let text = response.text().await.unwrap_or_default();
+                write_sse_error(&mut writer, &format!("{} {}", status.as_u16(), text.trim()))
+                    .await?;
+                return Ok(1);
+            }
+
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => writer.write_all(&chunk).await?,
+                    Ok(None) => break,
+                    Err(err) => {
+                        write_sse_error(&mut writer, &format!("503 {err}")).await?;
+                        return Ok(1);
+                    }
+                }
+            }
+            writer.shutdown().await?;
+            Ok(0)
+        });
+
+        Ok(AgentInvocation {
+            stdout: Some(Box::new(reader)),
+            stderr: None,
+            handle: AgentHandle::Task(task),
+            format: AgentOutputFormat::OpenAiSse,
+        })
+    }
+
+    fn kind(&self) -> AgentBackendKind {
+        AgentBackendKind::Openai
+    }
+}
+
+/// Check whether the configured backend can be used for one-shot prompts.
+pub fn is_oneshot_available(config: &BackendConfig) -> bool {
+    match config.kind {
+        AgentBackendKind::Claude => std::process::Command::new(&config.claude_bin)
+            .arg("--version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false),
+        AgentBackendKind::Command => config
+            .command
+            .as_deref()
+            .is_some_and(|c| !c.trim().is_empty()),
+        AgentBackendKind::Openai | AgentBackendKind::Replay => false,
+    }
+}
+
+/// Run a short, blocking one-shot prompt through the configured backend.
+///
+/// Used by auxiliary callers (run naming, postmortem analysis) that only need
+/// the final stdout. `claude_args` are extra flags passed before the prompt
+/// when the Claude CLI is used. Returns `Ok(None)` for backends without a
+/// synchronous CLI form (`openai`, `replay`).
+pub fn run_oneshot(
+    config: &BackendConfig,
+    model: &str,
+    prompt: &str,
+    working_dir: Option<&Path>,
+    claude_args: &[&str],
+) -> std::io::Result<Option<std::process::Output>> {
+    let mut cmd = match config.kind {
+        AgentBackendKind::Claude => {
+            let mut cmd = std::process::Command::new(&config.claude_bin);
+            cmd.args(claude_args).arg("--model").arg(model).arg(prompt);
+            cmd.stdin(Stdio::null());
+            cmd
+        }
+        AgentBackendKind::Command => {
+            let Some(command) = config.command.as_deref() else {
+                return Ok(None);
+            };
+            let mut cmd = std::process::Command::new("sh");
+            cmd.arg("-c").arg(command).env("LOOP_MODEL", model);
+            cmd.stdin(Stdio::piped());
+            cmd
+        }
+        AgentBackendKind::Openai | AgentBackendKind::Replay => return Ok(None),
+    };
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let mut child = cmd.spawn()?;
+    // Write the prompt from its own thread so a command that produces output
+    // before draining stdin cannot deadlock against a full pipe.
+    let writer = child.stdin.take().map(|mut stdin| {
+        let prompt = prompt.to_string();
+        std::thread::spawn(move || std::io::Write::write_all(&mut stdin, prompt.as_bytes()))
+    });
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        if let Ok(Err(err)) = writer.join() {
+            // The command may exit without reading all of its input.
+            if err.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(err);
+            }
+        }
+    }
+    Ok(Some(output))
+}
+
+async fn write_sse_error<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &str,
+) -> std::io::Result<()> {
+    let event = serde_json::json!({ "error": { "message": message } });
+    writer
+        .write_all(format!("data: {event}\n\n").as_bytes())
+        .await?;
+    writer.shutdown().await
+}
+
+/// Extract text from a single OpenAI chat completions SSE line.
+///
+/// Returns `API Error: <message>` for error events so the runner's
+/// transient-error detection applies uniformly across backends.
+pub fn extract_openai_sse_text(line: &str) -> Option<String> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data.is_empty() || data == "[DONE]" {
+        return None;
+    }
+    let event: serde_json::Value = serde_json::from_str(data).ok()?;
+    if let Some(error) = event.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown error");
+        return Some(format!("API Error: {message}\n"));
+    }
+    event
+        .get("choices")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("delta"))
+        .and_then(|d| d.get("content"))
+        .and_then(|t| t.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Extract token usage from the final OpenAI SSE chunk.
+///
+/// Only present when the request sets `stream_options.include_usage`.
+/// Cached prompt tokens are reported as cache reads, not input tokens.
+pub fn extract_openai_sse_usage(line: &str) -> Option<TokenUsage> {
+    let data = line.strip_prefix("data:")?.trim();
+    let event: serde_json::Value = serde_json::from_str(data).ok()?;
+    let usage = event.get("usage").filter(|u| u.is_object())?;
+    let field = |name: &str| {
+        usage
+            .get(name)
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0)
+    };
+    let cached = usage
+        .get("prompt_tokens_details")
+        .and_then(|d| d.get("cached_tokens"))
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0);
+    Some(TokenUsage {
+        input_tokens: field("prompt_tokens").saturating_sub(cached),
+        output_tokens: field("completion_tokens"),
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: cached,
+        cost_usd: 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn create_backend_respects_kind() {
+        let mut config = BackendConfig::default();
+        assert_eq!(create_backend(&config).kind(), AgentBackendKind::Claude);
+        config.kind = AgentBackendKind::Openai;
+        assert_eq!(create_backend(&config).kind(), AgentBackendKind::Openai);
+        config.kind = AgentBackendKind::Command;
+        assert_eq!(create_backend(&config).kind(), AgentBackendKind::Command);
+        config.kind = AgentBackendKind::Replay;
+        assert_eq!(create_backend(&config).kind(), AgentBackendKind::Replay);
+    }
+
+    #[test]
+    fn extract_openai_sse_text_reads_delta_content() {
+        let line = r#"data: {"choices":[{"delta":{"content":"Hello"}}]}"#;
+        assert_eq!(extract_openai_sse_text(line), Some("Hello".to_string()));
+        assert_eq!(extract_openai_sse_text("data: [DONE]"), None);
+        assert_eq!(extract_openai_sse_text(": keep-alive"), None);
+        let role_only = r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#;
+        assert_eq!(extract_openai_sse_text(role_only), None);
+    }
+
+    #[test]
+    fn extract_openai_sse_text_surfaces_errors() {
+        let line = r#"data: {"error":{"message":"503 overloaded"}}"#;
+        assert_eq!(
+            extract_openai_sse_text(line),
+            Some("API Error: 503 overloaded\n".to_string())
+        );
+    }
+
+    #[test]
+    fn extract_openai_sse_usage_reads_final_chunk() {
+        let line = r#"data: {"choices":[],"usage":{"prompt_tokens":120,"completion_tokens":30,"prompt_tokens_details":{"cached_tokens":100}}}"#;
+        let usage = extract_openai_sse_usage(line).unwrap();
+        assert_eq!(usage.input_tokens, 20);
+        assert_eq!(usage.output_tokens, 30);
+        assert_eq!(usage.cache_read_input_tokens, 100);
+
+        let delta = r#"data: {"choices":[{"delta":{"content":"Hi"}}],"usage":null}"#;
+        assert!(extract_openai_sse_usage(delta).is_none());
+    }
+
+    #[test]
+    fn run_oneshot_uses_command_backend() {
+        let config = BackendConfig {
+            kind: AgentBackendKind::Command,
+            command: Some("tr a-z A-Z".to_string()),
+            ..BackendConfig::default()
+        };
+        assert!(is_oneshot_available(&config));
+        let output = run_oneshot(&config, "m", "swift-owl", None, &[])
+            .unwrap()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "SWIFT-OWL");
+    }
+
+    #[test]
+    fn run_oneshot_streams_large_prompts() {
+        let config = BackendConfig {
+            kind: AgentBackendKind::Command,
+            command: Some("cat".to_string()),
+            ..BackendConfig::default()
+        };
+        // Larger than a pipe buffer in both directions.
+        let prompt = "x".repeat(1 << 20);
+        let output = run_oneshot(&config, "m", &prompt, None, &[])
+            .unwrap()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), prompt.len());
+    }
+
+    #[test]
+    fn run_oneshot_skips_openai_backend() {
+        let config = BackendConfig {
+            kind: AgentBackendKind::Openai,
+            ..BackendConfig::default()
+        };
+        assert!(!is_oneshot_available(&config));
+        assert!(run_oneshot(&config, "m", "p", None, &[]).unwrap().is_none());
+
+        let config = BackendConfig {
+            kind: AgentBackendKind::Replay,
+            ..BackendConfig::default()
+        };
+        assert!(!is_oneshot_available(&config));
+        assert!(run_oneshot(&config, "m", "p", None, &[]).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn claude_backend_reports_missing_binary_at_any_path() {
+        let dir = TempDir::new().unwrap();
+        let backend = ClaudeCliBackend {
+            bin: dir.path().join("bin/claude"),
+        };
+        let request = AgentRequest {
+            phase: StepPhase::Implementation,
+            prompt: "hi",
+            model: "m",
+            working_dir: dir.path(),
+            permissions: None,
+            sandbox: None,
+            limits: None,
+            resume_session: None,
+        };
+        assert!(matches!(
+            backend.spawn(&request),
+            Err(RunnerError::ClaudeNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn command_backend_requires_command() {
+        let dir = TempDir::new().unwrap();
+        let backend = CommandBackend {
+            command: String::new(),
+        };
+        let request = AgentRequest {
+            phase: StepPhase::Implementation,
+            prompt: "hi",
+            model: "m",
+            working_dir: dir.path(),
+            permissions: None,
+            sandbox: None,
+            limits: None,
+            resume_session: None,
+        };
+        assert!(matches!(
+            backend.spawn(&request),
+            Err(RunnerError::BackendUnavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn command_backend_pipes_prompt_to_stdin() {
+        let dir = TempDir::new().unwrap();
+        let backend = CommandBackend {
+            command: "printf '%s:' \"$LOOP_MODEL\"; cat".to_string(),
+        };
+        let request = AgentRequest {
+            phase: StepPhase::Implementation,
+            prompt: "do the thing",
+            model: "local-model",
+            working_dir: dir.path(),
+            permissions: None,
+            sandbox: None,
+            limits: None,
+            resume_session: None,
+        };
+        let mut invocation = backend.spawn(&request).unwrap();
+        let mut out = String::new();
+        invocation
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut out)
+            .await
+            .unwrap();
+        assert_eq!(invocation.handle.wait().await.unwrap(), 0);
+        assert_eq!(out, "local-model:do the thing");
+        assert_eq!(invocation.format, AgentOutputFormat::Text);
+    }
+}
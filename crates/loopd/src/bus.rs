@@ -0,0 +1,122 @@
+//! In-process broadcast bus for run events and step output.
+//!
+//! [`Storage`](crate::storage::Storage) publishes every event after it is
+//! persisted, and the runner publishes output chunks as it writes them to the
+//! iteration log. SSE handlers subscribe here instead of polling `SQLite`.
+//!
+//! Delivery is best-effort: a subscriber that falls more than the channel
+//! capacity behind receives `RecvError::Lagged` and is expected to catch up
+//! from storage (events) or tolerate the gap reported by chunk offsets (output).
+
+use loop_core::{Event, Id};
+use tokio::sync::broadcast;
+
+/// Buffered events per subscriber before it starts lagging.
+pub const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Buffered output chunks per subscriber before it starts lagging.
+pub const OUTPUT_BUS_CAPACITY: usize = 4096;
+
+/// A piece of step output as it was appended to the iteration log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputChunk {
+    pub run_id: Id,
+    pub step_id: Id,
+    /// Byte offset of `content` within the step's log file.
+    pub offset: u64,
+    pub content: String,
+}
+
+/// Broadcast channels shared by storage, the runner, and the HTTP server.
+///
+/// Cloning is cheap; all clones publish to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    events: broadcast::Sender<Event>,
+    output: broadcast::Sender<OutputChunk>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        let (output, _) = broadcast::channel(OUTPUT_BUS_CAPACITY);
+        Self { events, output }
+    }
+
+    /// Publish a persisted event. A no-op when nobody is subscribed.
+    pub fn publish_event(&self, event: &Event) {
+        if self.events.receiver_count() > 0 {
+            let _ = self.events.send(event.clone());
+        }
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Publish an output chunk. A no-op when nobody is subscribed.
+    pub fn publish_output(&self, chunk: OutputChunk) {
+        if self.output.receiver_count() > 0 {
+            let _ = self.output.send(chunk);
+        }
+    }
+
+    pub fn subscribe_output(&self) -> broadcast::Receiver<OutputChunk> {
+        self.output.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn event(run_id: &Id) -> Event {
+        Event {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            step_id: None,
+            event_type: "RUN_STARTED".to_string(),
+            timestamp: Utc::now(),
+            payload_json: "{}".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_events_published_after_subscribing() {
+        let bus = EventBus::new();
+        let run_id = Id::new();
+        bus.publish_event(&event(&run_id));
+
+        let mut rx = bus.subscribe_events();
+        let published = event(&run_id);
+        bus.clone().publish_event(&published);
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.id, published.id);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn output_chunks_fan_out_to_all_subscribers() {
+        let bus = EventBus::new();
+        let mut a = bus.subscribe_output();
+        let mut b = bus.subscribe_output();
+        let chunk = OutputChunk {
+            run_id: Id::new(),
+            step_id: Id::new(),
+            offset: 12,
+            content: "hello\n".to_string(),
+        };
+        bus.publish_output(chunk.clone());
+
+        assert_eq!(a.recv().await.unwrap(), chunk);
+        assert_eq!(b.recv().await.unwrap(), chunk);
+    }
+}
@@ -0,0 +1,573 @@
+//! Run dependencies: a pending run waits until the runs it depends on reach a
+//! condition.
+//!
+//! Dependencies are recorded in `run_dependencies` when the run is created.
+//! The scheduler checks them on every claim: a run with an unmet dependency
+//! stays pending and shows as blocked in `GET /queue`. A run whose dependency
+//! can no longer be met (failed, canceled, scrapped, deleted) is canceled with
+//! a `RUN_DEPENDENCY_FAILED` event. Since a canceled run is itself a failed
+//! dependency, cancellation cascades down the whole chain in one pass.
+//! [`DependencyWatcher`] does this on every run status change, so it does not
+//! wait for a free run slot.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use loop_core::events::{EventPayload, EventType, RunDependencyFailedPayload};
+use loop_core::{
+    Config, DependencyCondition, Id, MergeStrategy, ReviewStatus, Run, RunDependency, RunStatus,
+    StepPhase, StepStatus,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::scheduler::Scheduler;
+use crate::storage::{StorageBackend, StorageError};
+
+/// How often [`DependencyWatcher`] re-checks when no run changes status
+/// (review status changes, such as a scrapped dependency, publish nothing).
+pub const DEPENDENCY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where a single dependency stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyState {
+    Met,
+    Waiting,
+    /// The condition can never hold; the reason is for the event.
+    Failed(String),
+}
+
+/// Evaluate `condition` against the dependency run.
+///
+/// `verified` is whether any of its verification steps succeeded.
+pub fn dependency_state(
+    dependency: &Run,
+    condition: DependencyCondition,
+    verified: bool,
+) -> DependencyState {
+    let ended = |outcome: &str| DependencyState::Failed(format!("run {} {outcome}", dependency.id));
+    match dependency.status {
+        RunStatus::Failed => return ended("failed"),
+        RunStatus::Canceled => return ended("was canceled"),
+        _ => {}
+    }
+
+    match condition {
+        DependencyCondition::Completed if dependency.status == RunStatus::Completed => {
+            DependencyState::Met
+        }
+        DependencyCondition::Merged if is_merged(dependency) => DependencyState::Met,
+        DependencyCondition::Merged if dependency.review_status == ReviewStatus::Scrapped => {
+            ended("was scrapped")
+        }
+        DependencyCondition::SucceededVerification if verified => DependencyState::Met,
+        DependencyCondition::SucceededVerification if dependency.status == RunStatus::Completed => {
+            ended("completed without passing verification")
+        }
+        _ => DependencyState::Waiting,
+    }
+}
+
+/// Whether a run's branch was merged, by the review API or by auto-merge on completion.
+pub(crate) fn is_merged(run: &Run) -> bool {
+    run.review_status == ReviewStatus::Merged
+        || (run.status == RunStatus::Completed
+            && run.worktree.as_ref().is_some_and(|wt| {
+                wt.merge_target_branch.is_some() && wt.merge_strategy != MergeStrategy::None
+            }))
+}
+
+/// Branch a dependent run starts from once `dependency` meets `condition`.
+///
+/// Once merged, that is the branch it was merged into; otherwise its run branch.
+pub fn dependency_branch(dependency: &Run, condition: DependencyCondition) -> Option<String> {
+    let worktree = dependency.worktree.as_ref()?;
+    let branch = match condition {
+        DependencyCondition::Merged => worktree
+            .merge_target_branch
+            .as_ref()
+            .unwrap_or(&worktree.base_branch),
+        _ => &worktree.run_branch,
+    };
+    Some(branch.clone())
+}
+
+/// Unmet dependencies of the pending runs.
+#[derive(Debug, Default)]
+pub struct PendingDependencies {
+    /// Runs still waiting, with their first unmet dependency.
+    pub waiting: HashMap<Id, RunDependency>,
+    /// Runs with a dependency that can never be met, with the reason.
+    pub failed: HashMap<Id, (RunDependency, String)>,
+}
+
+impl PendingDependencies {
+    /// The dependency holding `run_id` back, if any.
+    pub fn blocking(&self, run_id: &Id) -> Option<&RunDependency> {
+        self.waiting
+            .get(run_id)
+            .or_else(|| self.failed.get(run_id).map(|(dependency, _)| dependency))
+    }
+}
+
+/// Check the dependencies of every pending run.
+pub async fn check_pending(
+    storage: &dyn StorageBackend,
+) -> Result<PendingDependencies, StorageError> {
+    let mut pending = PendingDependencies::default();
+    let mut states: HashMap<(Id, DependencyCondition), DependencyState> = HashMap::new();
+    for (run_id, dependencies) in storage.list_pending_run_dependencies().await? {
+        let mut waiting = None;
+        for dependency in dependencies {
+            let key = (dependency.run_id.clone(), dependency.condition);
+            let state = match states.get(&key) {
+                Some(state) => state.clone(),
+                None => {
+                    let state = evaluate(storage, &dependency).await?;
+                    states.insert(key, state.clone());
+                    state
+                }
+            };
+            match state {
+                DependencyState::Met => {}
+                DependencyState::Waiting => {
+                    waiting.get_or_insert(dependency);
+                }
+                DependencyState::Failed(reason) => {
+                    pending.failed.insert(run_id.clone(), (dependency, reason));
+                    waiting = None;
+                    break;
+                }
+            }
+        }
+        if let Some(dependency) = waiting {
+            pending.waiting.insert(run_id, dependency);
+        }
+    }
+    Ok(pending)
+}
+
+async fn evaluate(
+    storage: &dyn StorageBackend,
+    dependency: &RunDependency,
+) -> Result<DependencyState, StorageError> {
+    let run = match storage.get_run(&dependency.run_id).await {
+        Ok(run) => run,
+        Err(StorageError::RunNotFound(_)) => {
+            return Ok(DependencyState::Failed(format!(
+                "run {} not found",
+                dependency.run_id
+            )))
+        }
+        Err(e) => return Err(e),
+    };
+    let verified = dependency.condition == DependencyCondition::SucceededVerification
+        && storage.list_steps(&run.id).await?.iter().any(|step| {
+            step.phase == StepPhase::Verification && step.status == StepStatus::Succeeded
+        });
+    Ok(dependency_state(&run, dependency.condition, verified))
+}
+
+/// Cancel the runs whose dependencies failed, recording why on each.
+pub async fn cancel_failed(
+    storage: &dyn StorageBackend,
+    pending: &PendingDependencies,
+) -> Result<(), StorageError> {
+    for (run_id, (dependency, reason)) in &pending.failed {
+        info!(
+            run_id = %run_id,
+            dependency = %dependency.run_id,
+            condition = dependency.condition.as_str(),
+            reason = %reason,
+            "dependency failed; canceling run"
+        );
+        let payload = EventPayload::RunDependencyFailed(RunDependencyFailedPayload {
+            run_id: run_id.clone(),
+            dependency_run_id: dependency.run_id.clone(),
+            condition: dependency.condition,
+            reason: reason.clone(),
+        });
+        storage.append_event(run_id, None, &payload).await?;
+        storage
+            .update_run_status(run_id, RunStatus::Canceled)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Cancel every pending run whose dependencies can no longer be met, down
+/// the whole chain, and return the dependencies still pending afterwards.
+///
+/// Each round cancels at least one run, which may fail the dependencies of
+/// the next level, so this stops once a round finds nothing left to cancel.
+pub async fn cancel_unmeetable(
+    storage: &dyn StorageBackend,
+) -> Result<PendingDependencies, StorageError> {
+    loop {
+        let pending = check_pending(storage).await?;
+        if pending.failed.is_empty() {
+            return Ok(pending);
+        }
+        cancel_failed(storage, &pending).await?;
+    }
+}
+
+/// Cancels runs with unmeetable dependencies as soon as a run changes status.
+#[derive(Debug, Clone)]
+pub struct DependencyWatcher {
+    scheduler: Arc<Scheduler>,
+}
+
+impl DependencyWatcher {
+    pub fn new(scheduler: Arc<Scheduler>) -> Self {
+        Self { scheduler }
+    }
+
+    /// Check on every run status change, and every
+    /// [`DEPENDENCY_CHECK_INTERVAL`], until `cancel` fires.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        let mut events = self.scheduler.storage().bus().subscribe_events();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.scheduler.cancel_unmeetable_runs().await {
+                    warn!("dependency check failed: {}", e);
+                }
+                let status_change = async {
+                    loop {
+                        match events.recv().await {
+                            Ok(event)
+                                if event.event_type == EventType::RunStatusChanged.as_str() =>
+                            {
+                                return;
+                            }
+                            Ok(_) => {}
+                            Err(RecvError::Lagged(_)) => return,
+                            Err(RecvError::Closed) => std::future::pending::<()>().await,
+                        }
+                    }
+                };
+                tokio::select! {
+                    () = cancel.cancelled() => break,
+                    () = status_change => {}
+                    () = tokio::time::sleep(DEPENDENCY_CHECK_INTERVAL) => {}
+                }
+            }
+        })
+    }
+}
+
+/// Point a run's base branch at its `branch_from` dependency's branch.
+///
+/// Called when the run is claimed, after its dependencies are met.
+pub async fn apply_branch_from(
+    storage: &dyn StorageBackend,
+    run: &Run,
+) -> Result<(), StorageError> {
+    let dependencies = storage.list_run_dependencies(&run.id).await?;
+    let Some(dependency) = dependencies.iter().find(|d| d.branch_from) else {
+        return Ok(());
+    };
+    let dependency_run = storage.get_run(&dependency.run_id).await?;
+    let Some(branch) = dependency_branch(&dependency_run, dependency.condition) else {
+        warn!(
+            run_id = %run.id,
+            dependency = %dependency.run_id,
+            "dependency has no worktree; keeping configured base branch"
+        );
+        return Ok(());
+    };
+    let Some(mut config) = run
+        .config_json
+        .as_deref()
+        .and_then(|json| serde_json::from_str::<Config>(json).ok())
+    else {
+        return Ok(());
+    };
+
+    info!(run_id = %run.id, base_branch = %branch, "basing run on dependency branch");
+    config.base_branch = Some(branch);
+    storage
+        .update_run_config(&run.id, &serde_json::to_string(&config)?)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+    use chrono::Utc;
+    use loop_core::{RunNameSource, RunWorktree, WorktreeProvider};
+    use tempfile::TempDir;
+
+    fn run(status: RunStatus) -> Run {
+        let now = Utc::now();
+        Run {
+            id: Id::new(),
+            name: "dep".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status,
+            workspace_root: "/workspace".to_string(),
+            spec_path: "/workspace/spec.md".to_string(),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: Some(serde_json::to_string(&Config::default()).unwrap()),
+            created_at: now,
+            updated_at: now,
+            review_status: ReviewStatus::Pending,
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        }
+    }
+
+    fn worktree(merge_target_branch: Option<&str>) -> RunWorktree {
+        RunWorktree {
+            base_branch: "main".to_string(),
+            run_branch: "run/dep".to_string(),
+            merge_target_branch: merge_target_branch.map(str::to_string),
+            merge_strategy: MergeStrategy::Squash,
+            worktree_path: "/tmp/dep".into(),
+            provider: WorktreeProvider::Git,
+        }
+    }
+
+    #[test]
+    fn completed_condition_waits_then_meets_or_fails() {
+        use DependencyCondition::Completed;
+        let state = |status| dependency_state(&run(status), Completed, false);
+        assert_eq!(state(RunStatus::Pending), DependencyState::Waiting);
+        assert_eq!(state(RunStatus::Paused), DependencyState::Waiting);
+        assert_eq!(state(RunStatus::Completed), DependencyState::Met);
+        assert!(
+            matches!(state(RunStatus::Failed), DependencyState::Failed(r) if r.ends_with("failed"))
+        );
+        assert!(matches!(
+            state(RunStatus::Canceled),
+            DependencyState::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn merged_condition_accepts_review_merge_and_auto_merge() {
+        use DependencyCondition::Merged;
+        let mut dep = run(RunStatus::Completed);
+        dep.worktree = Some(worktree(None));
+        assert_eq!(
+            dependency_state(&dep, Merged, false),
+            DependencyState::Waiting
+        );
+
+        dep.review_status = ReviewStatus::Merged;
+        assert_eq!(dependency_state(&dep, Merged, false), DependencyState::Met);
+
+        dep.review_status = ReviewStatus::Scrapped;
+        assert!(matches!(
+            dependency_state(&dep, Merged, false),
+            DependencyState::Failed(r) if r.ends_with("was scrapped")
+        ));
+
+        let mut auto = run(RunStatus::Completed);
+        auto.worktree = Some(worktree(Some("develop")));
+        assert_eq!(dependency_state(&auto, Merged, false), DependencyState::Met);
+    }
+
+    #[test]
+    fn verification_condition_needs_a_passing_verification() {
+        use DependencyCondition::SucceededVerification;
+        let running = run(RunStatus::Running);
+        assert_eq!(
+            dependency_state(&running, SucceededVerification, false),
+            DependencyState::Waiting
+        );
+        assert_eq!(
+            dependency_state(&running, SucceededVerification, true),
+            DependencyState::Met
+        );
+        assert!(matches!(
+            dependency_state(&run(RunStatus::Completed), SucceededVerification, false),
+            DependencyState::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn dependency_branch_follows_merge_target_once_merged() {
+        let mut dep = run(RunStatus::Completed);
+        assert_eq!(
+            dependency_branch(&dep, DependencyCondition::Completed),
+            None
+        );
+
+        dep.worktree = Some(worktree(None));
+        assert_eq!(
+            dependency_branch(&dep, DependencyCondition::Completed).as_deref(),
+            Some("run/dep")
+        );
+        assert_eq!(
+            dependency_branch(&dep, DependencyCondition::Merged).as_deref(),
+            Some("main")
+        );
+        dep.worktree = Some(worktree(Some("develop")));
+        assert_eq!(
+            dependency_branch(&dep, DependencyCondition::Merged).as_deref(),
+            Some("develop")
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_dependency_cancels_dependent_with_event() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+
+        let first = run(RunStatus::Running);
+        storage.insert_run(&first).await.unwrap();
+        let second = run(RunStatus::Pending);
+        let dependency = RunDependency {
+            run_id: first.id.clone(),
+            condition: DependencyCondition::Completed,
+            branch_from: false,
+        };
+        storage
+            .insert_run_with_dependencies(&second, std::slice::from_ref(&dependency))
+            .await
+            .unwrap();
+
+        let pending = check_pending(&storage).await.unwrap();
+        assert_eq!(pending.waiting.get(&second.id), Some(&dependency));
+        assert_eq!(pending.blocking(&second.id), Some(&dependency));
+
+        storage
+            .update_run_status(&first.id, RunStatus::Failed)
+            .await
+            .unwrap();
+        let pending = check_pending(&storage).await.unwrap();
+        assert!(pending.waiting.is_empty());
+        assert!(pending.failed.contains_key(&second.id));
+
+        cancel_failed(&storage, &pending).await.unwrap();
+        let canceled = storage.get_run(&second.id).await.unwrap();
+        assert_eq!(canceled.status, RunStatus::Canceled);
+        let events = storage.list_events(&second.id).await.unwrap();
+        assert!(events
+            .iter()
+            .any(|e| e.event_type == "RUN_DEPENDENCY_FAILED"));
+        assert!(check_pending(&storage).await.unwrap().failed.is_empty());
+    }
+
+    async fn insert_dependent(storage: &dyn StorageBackend, on: &Run) -> Run {
+        let dependent = run(RunStatus::Pending);
+        storage
+            .insert_run_with_dependencies(
+                &dependent,
+                &[RunDependency {
+                    run_id: on.id.clone(),
+                    condition: DependencyCondition::Completed,
+                    branch_from: false,
+                }],
+            )
+            .await
+            .unwrap();
+        dependent
+    }
+
+    #[tokio::test]
+    async fn cancel_unmeetable_cascades_down_the_whole_chain() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+
+        let first = run(RunStatus::Failed);
+        storage.insert_run(&first).await.unwrap();
+        let second = insert_dependent(&storage, &first).await;
+        let third = insert_dependent(&storage, &second).await;
+        let fourth = insert_dependent(&storage, &third).await;
+
+        let pending = cancel_unmeetable(&storage).await.unwrap();
+        assert!(pending.failed.is_empty());
+        assert!(pending.waiting.is_empty());
+        for dependent in [&second, &third, &fourth] {
+            let canceled = storage.get_run(&dependent.id).await.unwrap();
+            assert_eq!(canceled.status, RunStatus::Canceled);
+        }
+    }
+
+    #[tokio::test]
+    async fn watcher_cancels_dependents_while_all_slots_are_busy() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+        let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 1));
+
+        // The only slot is held by a long-running run.
+        let busy = run(RunStatus::Pending);
+        storage.insert_run(&busy).await.unwrap();
+        let claimed = scheduler.claim_next_run().await.unwrap().unwrap();
+        assert!(!scheduler.has_capacity());
+        let dependent = insert_dependent(&*storage, &claimed).await;
+        let chained = insert_dependent(&*storage, &dependent).await;
+
+        let cancel = CancellationToken::new();
+        let handle = DependencyWatcher::new(Arc::clone(&scheduler)).spawn(cancel.clone());
+        storage
+            .update_run_status(&claimed.id, RunStatus::Failed)
+            .await
+            .unwrap();
+
+        let mut status = RunStatus::Pending;
+        for _ in 0..100 {
+            status = storage.get_run(&chained.id).await.unwrap().status;
+            if status == RunStatus::Canceled {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(status, RunStatus::Canceled);
+        let dependent = storage.get_run(&dependent.id).await.unwrap();
+        assert_eq!(dependent.status, RunStatus::Canceled);
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn branch_from_rewrites_base_branch() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+
+        let mut first = run(RunStatus::Completed);
+        first.worktree = Some(worktree(None));
+        storage.insert_run(&first).await.unwrap();
+        let second = run(RunStatus::Pending);
+        storage
+            .insert_run_with_dependencies(
+                &second,
+                &[RunDependency {
+                    run_id: first.id.clone(),
+                    condition: DependencyCondition::Completed,
+                    branch_from: true,
+                }],
+            )
+            .await
+            .unwrap();
+
+        apply_branch_from(&storage, &second).await.unwrap();
+        let updated = storage.get_run(&second.id).await.unwrap();
+        let config: Config = serde_json::from_str(updated.config_json.as_deref().unwrap()).unwrap();
+        assert_eq!(config.base_branch.as_deref(), Some("run/dep"));
+    }
+}
@@ -0,0 +1,501 @@
+//! Retention policy enforcement for finished runs.
+//!
+//! A [`GarbageCollector`] applies the daemon's [`RetentionPolicy`] to runs that
+//! completed, failed or were canceled and have not been pruned yet. For each
+//! selected run it deletes the workspace and global log directories, then
+//! removes the run's artifact rows, transcripts, audited actions and
+//! step-level events and records the prune in `gc_log`. Run rows, steps and
+//! usage are kept, so run history and cost reports stay intact.
+//!
+//! Every pass also drops artifact rows of kept runs whose files are gone, so
+//! the `artifacts` table never points at missing files.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use loop_core::{
+    global_run_dir, workspace_run_dir, Config, GcReport, PruneReason, PrunedRun, RetentionPolicy,
+    ReviewStatus, Run, RunStatus,
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::dependencies::is_merged;
+use crate::storage::{StorageBackend, StorageError};
+
+/// How often the retention policy is applied.
+pub const GC_INTERVAL: Duration = Duration::from_hours(1);
+
+/// Finished runs to prune under `policy` at `now`, with the limit that selected each.
+///
+/// `runs` must be unpruned finished runs, newest first. Protected runs are
+/// never selected but still count toward `max_runs_per_workspace`.
+pub fn select_prunable<'a>(
+    policy: &RetentionPolicy,
+    runs: &'a [Run],
+    now: DateTime<Utc>,
+) -> Vec<(&'a Run, PruneReason)> {
+    let mut kept_per_workspace: HashMap<&str, u32> = HashMap::new();
+    let mut selected = Vec::new();
+    for run in runs {
+        let rank = kept_per_workspace
+            .entry(run.workspace_root.as_str())
+            .or_default();
+        *rank += 1;
+        let reason = if policy
+            .max_age_days
+            .is_some_and(|days| older_than(run.updated_at, days, now))
+        {
+            PruneReason::MaxAge
+        } else if policy.max_runs_per_workspace.is_some_and(|max| *rank > max) {
+            PruneReason::MaxRunsPerWorkspace
+        } else {
+            continue;
+        };
+        if !is_protected(policy, run, now) {
+            selected.push((run, reason));
+        }
+    }
+    selected
+}
+
+/// Whether `policy` keeps `run` regardless of its age or rank.
+fn is_protected(policy: &RetentionPolicy, run: &Run, now: DateTime<Utc>) -> bool {
+    let unmerged = run.status == RunStatus::Completed
+        && run.worktree.is_some()
+        && run.review_status != ReviewStatus::Scrapped
+        && !is_merged(run);
+    let recent_failure = run.status == RunStatus::Failed
+        && policy
+            .keep_failed_days
+            .is_some_and(|days| !older_than(run.updated_at, days, now));
+    (policy.keep_unmerged && unmerged) || recent_failure
+}
+
+/// Whether `at` is more than `days` days before `now`.
+fn older_than(at: DateTime<Utc>, days: u32, now: DateTime<Utc>) -> bool {
+    now.checked_sub_signed(chrono::Duration::days(i64::from(days)))
+        .is_some_and(|cutoff| at < cutoff)
+}
+
+/// Log directories of a run: the workspace copy and the global mirror.
+fn run_dirs(run: &Run) -> Vec<PathBuf> {
+    let config = run
+        .config_json
+        .as_deref()
+        .and_then(|json| serde_json::from_str::<Config>(json).ok())
+        .unwrap_or_default();
+    let workspace = workspace_run_dir(Path::new(&run.workspace_root), &run.id);
+    let global = global_run_dir(&config.global_log_dir, &run.id);
+    if workspace == global {
+        vec![workspace]
+    } else {
+        vec![workspace, global]
+    }
+}
+
+/// Total size of the files under `path`, not following symlinks.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map_or(0, |metadata| metadata.len()),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn remove_dir(path: &Path) -> io::Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Background task that prunes finished runs under a retention policy.
+#[derive(Debug)]
+pub struct GarbageCollector {
+    storage: Arc<dyn StorageBackend>,
+    policy: RetentionPolicy,
+}
+
+impl GarbageCollector {
+    pub fn new(storage: Arc<dyn StorageBackend>, policy: RetentionPolicy) -> Self {
+        Self { storage, policy }
+    }
+
+    /// Apply the policy every [`GC_INTERVAL`] until `cancel` fires.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.collect(Utc::now(), false).await {
+                    Ok(report) if !report.runs.is_empty() => info!(
+                        "pruned {} run(s), freed {} bytes",
+                        report.runs.len(),
+                        report.bytes_freed
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!("garbage collection failed: {}", e),
+                }
+                tokio::select! {
+                    () = cancel.cancelled() => break,
+                    () = tokio::time::sleep(GC_INTERVAL) => {}
+                }
+            }
+        })
+    }
+
+    /// Prune the runs the policy selects at `now` and drop artifact rows
+    /// whose files are missing. With `dry_run` nothing is deleted and the
+    /// report describes what a real pass would remove.
+    pub async fn collect(
+        &self,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<GcReport, StorageError> {
+        let mut report = GcReport {
+            dry_run,
+            policy: self.policy.clone(),
+            ..GcReport::default()
+        };
+        let candidates = self.storage.list_gc_candidates().await?;
+        let mut kept: Vec<&Run> = candidates.iter().collect();
+
+        for (run, reason) in select_prunable(&self.policy, &candidates, now) {
+            let dirs = run_dirs(run);
+            let bytes = dirs.iter().map(|dir| dir_size(dir)).sum();
+            if !dry_run {
+                // Rows are only removed once their files are, so a failed
+                // removal leaves the run for the next pass.
+                if let Err(e) = dirs.iter().try_for_each(|dir| remove_dir(dir)) {
+                    warn!(run_id = %run.id, error = %e, "failed to remove run logs; not pruning");
+                    continue;
+                }
+            }
+            let pruned = PrunedRun {
+                run_id: run.id.clone(),
+                name: run.name.clone(),
+                workspace_root: run.workspace_root.clone(),
+                status: run.status,
+                reason,
+                bytes,
+            };
+            let rows = self.storage.prune_run(&pruned, now, dry_run).await?;
+            report.events_removed += rows.events;
+            report.artifacts_removed += rows.artifacts;
+            report.bytes_freed += bytes;
+            report.runs.push(pruned);
+            kept.retain(|candidate| candidate.id != run.id);
+        }
+
+        for run in kept {
+            for artifact in self.storage.list_artifacts(&run.id).await? {
+                if Path::new(&artifact.path).exists() {
+                    continue;
+                }
+                if !dry_run {
+                    self.storage.delete_artifact(&artifact.id).await?;
+                }
+                report.artifacts_removed += 1;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+    use loop_core::events::{EventPayload, RunCreatedPayload, StepStartedPayload};
+    use loop_core::{
+        Artifact, ArtifactLocation, Id, MergeStrategy, RunNameSource, RunWorktree, Step, StepPhase,
+        StepStatus, WorktreeProvider,
+    };
+    use tempfile::TempDir;
+
+    fn run(workspace: &str, status: RunStatus, age_days: i64) -> Run {
+        let at = Utc::now() - chrono::Duration::days(age_days);
+        Run {
+            id: Id::new(),
+            name: "gc".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status,
+            workspace_root: workspace.to_string(),
+            spec_path: format!("{workspace}/spec.md"),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: at,
+            updated_at: at,
+            review_status: ReviewStatus::Pending,
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        }
+    }
+
+    fn unmerged_worktree() -> RunWorktree {
+        RunWorktree {
+            base_branch: "main".to_string(),
+            run_branch: "run/gc".to_string(),
+            merge_target_branch: None,
+            merge_strategy: MergeStrategy::None,
+            worktree_path: "/tmp/gc".into(),
+            provider: WorktreeProvider::Git,
+        }
+    }
+
+    fn selected_ids(selected: &[(&Run, PruneReason)]) -> Vec<(Id, PruneReason)> {
+        selected
+            .iter()
+            .map(|(run, reason)| (run.id.clone(), *reason))
+            .collect()
+    }
+
+    #[test]
+    fn select_prunable_applies_age_and_count_limits() {
+        let runs = vec![
+            run("/a", RunStatus::Completed, 1),
+            run("/b", RunStatus::Completed, 2),
+            run("/a", RunStatus::Canceled, 3),
+            run("/a", RunStatus::Failed, 40),
+        ];
+        let now = Utc::now();
+
+        assert!(select_prunable(&RetentionPolicy::default(), &runs, now).is_empty());
+
+        let by_age = RetentionPolicy {
+            max_age_days: Some(30),
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(
+            selected_ids(&select_prunable(&by_age, &runs, now)),
+            vec![(runs[3].id.clone(), PruneReason::MaxAge)]
+        );
+
+        let by_count = RetentionPolicy {
+            max_runs_per_workspace: Some(1),
+            ..by_age
+        };
+        assert_eq!(
+            selected_ids(&select_prunable(&by_count, &runs, now)),
+            vec![
+                (runs[2].id.clone(), PruneReason::MaxRunsPerWorkspace),
+                (runs[3].id.clone(), PruneReason::MaxAge),
+            ]
+        );
+    }
+
+    #[test]
+    fn select_prunable_keeps_unmerged_runs_and_recent_failures() {
+        let mut unmerged = run("/a", RunStatus::Completed, 50);
+        unmerged.worktree = Some(unmerged_worktree());
+        let mut scrapped = unmerged.clone();
+        scrapped.id = Id::new();
+        scrapped.review_status = ReviewStatus::Scrapped;
+        let runs = vec![
+            unmerged,
+            scrapped,
+            run("/a", RunStatus::Failed, 5),
+            run("/a", RunStatus::Failed, 20),
+        ];
+        let now = Utc::now();
+        let policy = RetentionPolicy {
+            max_runs_per_workspace: Some(0),
+            keep_failed_days: Some(10),
+            ..RetentionPolicy::default()
+        };
+
+        assert_eq!(
+            selected_ids(&select_prunable(&policy, &runs, now)),
+            vec![
+                (runs[1].id.clone(), PruneReason::MaxRunsPerWorkspace),
+                (runs[3].id.clone(), PruneReason::MaxRunsPerWorkspace),
+            ]
+        );
+
+        let policy = RetentionPolicy {
+            keep_unmerged: false,
+            keep_failed_days: None,
+            ..policy
+        };
+        assert_eq!(select_prunable(&policy, &runs, now).len(), 4);
+    }
+
+    /// Insert a finished run with one log file, one artifact row and a
+    /// run-level and a step-level event.
+    async fn insert_run_with_logs(
+        storage: &dyn StorageBackend,
+        workspace: &Path,
+        age_days: i64,
+    ) -> Run {
+        let mut run = run(&workspace.to_string_lossy(), RunStatus::Completed, age_days);
+        let config = Config {
+            global_log_dir: workspace.join("global"),
+            ..Config::default()
+        };
+        run.config_json = Some(serde_json::to_string(&config).unwrap());
+        storage.insert_run(&run).await.unwrap();
+
+        let dir = workspace_run_dir(workspace, &run.id);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("output.log");
+        std::fs::write(&output, "0123456789").unwrap();
+        storage
+            .insert_artifact(&Artifact {
+                id: Id::new(),
+                run_id: run.id.clone(),
+                kind: "output".to_string(),
+                location: ArtifactLocation::Workspace,
+                path: output.to_string_lossy().to_string(),
+                checksum: None,
+            })
+            .await
+            .unwrap();
+
+        let step = Step {
+            id: Id::new(),
+            run_id: run.id.clone(),
+            phase: StepPhase::Implementation,
+            status: StepStatus::Succeeded,
+            attempt: 1,
+            started_at: None,
+            ended_at: None,
+            exit_code: Some(0),
+            prompt_path: None,
+            output_path: Some(output.to_string_lossy().to_string()),
+            session_id: None,
+        };
+        storage.insert_step(&step).await.unwrap();
+        storage
+            .append_event(
+                &run.id,
+                None,
+                &EventPayload::RunCreated(RunCreatedPayload {
+                    run_id: run.id.clone(),
+                    name: run.name.clone(),
+                    name_source: run.name_source,
+                    spec_path: run.spec_path.clone(),
+                    plan_path: None,
+                }),
+            )
+            .await
+            .unwrap();
+        storage
+            .append_event(
+                &run.id,
+                Some(&step.id),
+                &EventPayload::StepStarted(StepStartedPayload {
+                    step_id: step.id.clone(),
+                    phase: "implementation".to_string(),
+                    attempt: 1,
+                }),
+            )
+            .await
+            .unwrap();
+        run
+    }
+
+    #[tokio::test]
+    async fn collect_previews_then_prunes_logs_rows_and_records_it() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+        let old = insert_run_with_logs(&*storage, dir.path(), 40).await;
+        let recent = insert_run_with_logs(&*storage, dir.path(), 1).await;
+        let gc = GarbageCollector::new(
+            Arc::clone(&storage),
+            RetentionPolicy {
+                max_age_days: Some(30),
+                ..RetentionPolicy::default()
+            },
+        );
+
+        let preview = gc.collect(Utc::now(), true).await.unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.runs.len(), 1);
+        assert_eq!(preview.runs[0].run_id, old.id);
+        assert_eq!(preview.runs[0].reason, PruneReason::MaxAge);
+        assert_eq!(
+            (
+                preview.events_removed,
+                preview.artifacts_removed,
+                preview.bytes_freed
+            ),
+            (1, 1, 10)
+        );
+        assert!(workspace_run_dir(dir.path(), &old.id).exists());
+        assert_eq!(storage.list_artifacts(&old.id).await.unwrap().len(), 1);
+        assert_eq!(storage.list_events(&old.id).await.unwrap().len(), 2);
+
+        let report = gc.collect(Utc::now(), false).await.unwrap();
+        assert_eq!(
+            report
+                .runs
+                .iter()
+                .map(|r| r.run_id.clone())
+                .collect::<Vec<_>>(),
+            vec![old.id.clone()]
+        );
+        assert_eq!(report.bytes_freed, 10);
+        assert!(!workspace_run_dir(dir.path(), &old.id).exists());
+        assert!(storage.list_artifacts(&old.id).await.unwrap().is_empty());
+        let events = storage.list_events(&old.id).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].step_id.is_none());
+        let steps = storage.list_steps(&old.id).await.unwrap();
+        assert_eq!(steps.len(), 1);
+        assert!(steps[0].output_path.is_none());
+        assert_eq!(storage.get_run(&old.id).await.unwrap().id, old.id);
+
+        // The recent run is untouched and the pruned one is not selected again.
+        assert!(workspace_run_dir(dir.path(), &recent.id).exists());
+        assert_eq!(storage.list_artifacts(&recent.id).await.unwrap().len(), 1);
+        let candidates = storage.list_gc_candidates().await.unwrap();
+        assert_eq!(
+            candidates.iter().map(|r| r.id.clone()).collect::<Vec<_>>(),
+            vec![recent.id.clone()]
+        );
+        assert!(gc.collect(Utc::now(), false).await.unwrap().runs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn collect_drops_artifact_rows_for_missing_files() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+        let run = insert_run_with_logs(&*storage, dir.path(), 1).await;
+        std::fs::remove_dir_all(workspace_run_dir(dir.path(), &run.id)).unwrap();
+        let gc = GarbageCollector::new(Arc::clone(&storage), RetentionPolicy::default());
+
+        let preview = gc.collect(Utc::now(), true).await.unwrap();
+        assert!(preview.runs.is_empty());
+        assert_eq!(preview.artifacts_removed, 1);
+        assert_eq!(storage.list_artifacts(&run.id).await.unwrap().len(), 1);
+
+        let report = gc.collect(Utc::now(), false).await.unwrap();
+        assert_eq!(report.artifacts_removed, 1);
+        assert!(storage.list_artifacts(&run.id).await.unwrap().is_empty());
+        assert_eq!(storage.list_events(&run.id).await.unwrap().len(), 2);
+    }
+}
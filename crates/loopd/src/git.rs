@@ -547,6 +547,60 @@ pub fn get_head_commit(workspace_root: &Path) -> Result<String> {
     Ok(stdout.trim().to_string())
 }
 
+/// Add `pattern` to the repository's `info/exclude` unless already present.
+///
+/// Keeps daemon-written files out of `git status` and agent commits without
+/// touching the tracked `.gitignore`. Worktrees share the main repository's
+/// exclude file.
+pub fn exclude_locally(workspace_root: &Path, pattern: &str) -> Result<()> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "info/exclude"])
+        .current_dir(workspace_root)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitError::NotARepo(stderr.trim().to_string()));
+    }
+
+    let stdout = String::from_utf8(output.stdout).map_err(|_| GitError::InvalidUtf8)?;
+    let exclude_path = workspace_root.join(stdout.trim());
+    let existing = std::fs::read_to_string(&exclude_path).unwrap_or_default();
+    if existing.lines().any(|line| line.trim() == pattern) {
+        return Ok(());
+    }
+
+    if let Some(parent) = exclude_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let separator = if existing.is_empty() || existing.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    std::fs::write(&exclude_path, format!("{existing}{separator}{pattern}\n"))?;
+    Ok(())
+}
+
+/// Resolve the repository's shared git directory.
+///
+/// For a linked worktree this is the main repository's `.git`, where commits
+/// made in the worktree store their objects and refs.
+pub fn common_dir(workspace_root: &Path) -> Result<std::path::PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-common-dir"])
+        .current_dir(workspace_root)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitError::NotARepo(stderr.trim().to_string()));
+    }
+
+    let stdout = String::from_utf8(output.stdout).map_err(|_| GitError::InvalidUtf8)?;
+    Ok(workspace_root.join(stdout.trim()))
+}
+
 /// Check if the working tree is clean (no uncommitted changes).
 pub fn is_working_tree_clean(workspace_root: &Path) -> Result<bool> {
     let output = Command::new("git")
@@ -563,6 +617,69 @@ pub fn is_working_tree_clean(workspace_root: &Path) -> Result<bool> {
     Ok(stdout.trim().is_empty())
 }
 
+/// List paths that differ from `base`, committed or not, plus untracked files.
+pub fn changed_paths_since(workspace_root: &Path, base: &str) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for args in [
+        vec!["diff", "--name-only", "--no-renames", base],
+        vec!["ls-files", "--others", "--exclude-standard"],
+    ] {
+        let output = Command::new("git")
+            .args(&args)
+            .current_dir(workspace_root)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(format!(
+                "git {}: {stderr}",
+                args.join(" ")
+            )));
+        }
+
+        let stdout = String::from_utf8(output.stdout).map_err(|_| GitError::InvalidUtf8)?;
+        paths.extend(stdout.lines().map(str::to_string));
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Get the full message of the HEAD commit.
+pub fn head_commit_message(workspace_root: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%B"])
+        .current_dir(workspace_root)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitError::CommandFailed(format!("git log: {stderr}")));
+    }
+
+    let stdout = String::from_utf8(output.stdout).map_err(|_| GitError::InvalidUtf8)?;
+    Ok(stdout.trim().to_string())
+}
+
+/// Stage all changes in the working tree and commit them.
+pub fn commit_all(workspace_root: &Path, message: &str) -> Result<()> {
+    for args in [vec!["add", "-A"], vec!["commit", "-m", message]] {
+        let output = Command::new("git")
+            .args(&args)
+            .current_dir(workspace_root)
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitError::CommandFailed(format!(
+                "git {}: {stderr}",
+                args[0]
+            )));
+        }
+    }
+    Ok(())
+}
+
 /// Checkout a branch in the workspace.
 pub fn checkout_branch(workspace_root: &Path, branch: &str) -> Result<()> {
     let output = Command::new("git")
@@ -758,6 +875,34 @@ mod tests {
         dir
     }
 
+    #[test]
+    fn exclude_locally_hides_files_once() {
+        let dir = setup_test_repo();
+        std::fs::create_dir_all(dir.path().join(".loop")).unwrap();
+        std::fs::write(dir.path().join(".loop/claude-settings-1.json"), "{}").unwrap();
+
+        exclude_locally(dir.path(), ".loop/claude-settings-*.json").unwrap();
+        exclude_locally(dir.path(), ".loop/claude-settings-*.json").unwrap();
+
+        assert!(is_working_tree_clean(dir.path()).unwrap());
+        let exclude = std::fs::read_to_string(dir.path().join(".git/info/exclude")).unwrap();
+        assert_eq!(exclude.matches("claude-settings").count(), 1);
+    }
+
+    #[test]
+    fn common_dir_resolves_main_repo_from_worktree() {
+        let dir = setup_test_repo();
+        let branch = get_current_branch(dir.path()).unwrap();
+        let worktree = dir.path().join("wt");
+        create_worktree(dir.path(), &worktree, "run/common-dir", &branch).unwrap();
+
+        let common = common_dir(&worktree).unwrap();
+        assert_eq!(
+            common.canonicalize().unwrap(),
+            dir.path().join(".git").canonicalize().unwrap()
+        );
+    }
+
     #[test]
     fn test_repo_name() {
         assert_eq!(repo_name(Path::new("/home/user/my-project")), "my-project");
@@ -1053,4 +1198,24 @@ mod tests {
         let result = verify_worktree_branch(dir.path(), "main");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn changed_paths_since_covers_commits_edits_and_untracked() {
+        let dir = setup_test_repo();
+        let base = get_head_commit(dir.path()).unwrap();
+
+        std::fs::write(dir.path().join("committed.txt"), "one").unwrap();
+        commit_all(dir.path(), "Add committed file").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Changed").unwrap();
+        std::fs::write(dir.path().join("untracked.txt"), "two").unwrap();
+
+        assert_eq!(
+            changed_paths_since(dir.path(), &base).unwrap(),
+            vec!["README.md", "committed.txt", "untracked.txt"]
+        );
+        assert_eq!(
+            head_commit_message(dir.path()).unwrap(),
+            "Add committed file"
+        );
+    }
 }
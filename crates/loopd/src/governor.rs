@@ -0,0 +1,742 @@
+//! Daemon-wide API rate-limit governor.
+//!
+//! Runs share one circuit breaker per model. A step that sees a 429 or
+//! overloaded error in its stream opens the breaker; every run then holds new
+//! step launches for that model until the backoff elapses. After the cooldown
+//! a single probe step is let through. Throttling shows up within the first
+//! API calls, so once the probe has run for the probe window without being
+//! throttled the waiting launches resume; the breaker itself closes when the
+//! probe completes. A throttled probe doubles the backoff (or follows the
+//! API's retry-after hint) and the cycle repeats. Openings and closings are
+//! recorded as `API_BACKOFF_STARTED` / `API_BACKOFF_ENDED` events on the run
+//! that observed them.
+
+use chrono::{DateTime, Utc};
+use loop_core::events::{ApiBackoffEndedPayload, ApiBackoffStartedPayload, EventPayload};
+use loop_core::types::RateLimitKind;
+use loop_core::Step;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::storage::StorageBackend;
+
+/// Backoff after the first throttled attempt when the API gives no hint.
+pub const DEFAULT_BASE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Upper bound on a single backoff, including retry-after hints.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_mins(10);
+
+/// How long a probe must run unthrottled before other launches resume.
+pub const DEFAULT_PROBE_WINDOW: Duration = Duration::from_secs(30);
+
+/// A throttling error observed in an agent's output stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitSignal {
+    pub kind: RateLimitKind,
+    /// Retry-after hint carried by the error, if any.
+    pub retry_after: Option<Duration>,
+}
+
+/// Detect a 429 / overloaded error in a line of agent output.
+///
+/// Accepts both the synthetic `API Error: <status> ...` text emitted by the
+/// Claude CLI and the OpenAI backend, and raw Anthropic error bodies.
+pub fn detect_rate_limit(text: &str) -> Option<RateLimitSignal> {
+    let lower = text.to_ascii_lowercase();
+    let kind = if lower.contains("api error: 429")
+        || lower.contains("rate_limit_error")
+        || lower.contains("usage limit reached")
+    {
+        RateLimitKind::RateLimited
+    } else if lower.contains("api error: 529") || lower.contains("overloaded") {
+        RateLimitKind::Overloaded
+    } else {
+        return None;
+    };
+    Some(RateLimitSignal {
+        kind,
+        retry_after: parse_retry_after(&lower).or_else(|| usage_limit_reset(text)),
+    })
+}
+
+/// Detect a throttling error in a structured Claude stream-json event.
+///
+/// Only `error` events and failed `result` events are considered, so tool
+/// output that merely mentions rate limits does not trip the breaker.
+pub fn detect_rate_limit_event(event: &serde_json::Value) -> Option<RateLimitSignal> {
+    let is_error = match event.get("type").and_then(|t| t.as_str()) {
+        Some("error") => true,
+        Some("result") => event.get("is_error").and_then(serde_json::Value::as_bool) == Some(true),
+        _ => false,
+    };
+    if !is_error {
+        return None;
+    }
+    detect_rate_limit(&event.to_string())
+}
+
+/// Parse a retry-after hint (`retry-after: 20`, `"retry_after_ms": 1500`, ...).
+fn parse_retry_after(lower: &str) -> Option<Duration> {
+    const KEYS: [&str; 3] = ["retry-after", "retry_after", "retry after"];
+    KEYS.iter().find_map(|key| {
+        let start = lower.find(key)? + key.len();
+        let rest = &lower[start..];
+        let (millis, rest) = match rest
+            .strip_prefix("-ms")
+            .or_else(|| rest.strip_prefix("_ms"))
+        {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let rest = rest.trim_start_matches(['"', '\'', ':', '=', ' ']);
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..end].parse().ok()?;
+        let millis = millis || rest[end..].starts_with("ms");
+        let secs = if millis { value / 1000.0 } else { value };
+        (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+    })
+}
+
+/// Reset time from the Claude CLI's `Claude AI usage limit reached|<epoch>`.
+fn usage_limit_reset(text: &str) -> Option<Duration> {
+    let (_, rest) = text.split_once("limit reached|")?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let reset: i64 = rest[..end].parse().ok()?;
+    let secs = reset - Utc::now().timestamp();
+    Some(Duration::from_secs(secs.max(0) as u64))
+}
+
+/// How a governed attempt ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptOutcome {
+    /// The API throttled the attempt.
+    Throttled(RateLimitSignal),
+    /// The agent ran to completion without being throttled.
+    Completed,
+}
+
+/// Breaker state for one model, as reported by `GET /health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackoffStatus {
+    pub model: String,
+    /// `open` while cooling down, `half_open` once a probe may launch.
+    pub state: &'static str,
+    pub signal: RateLimitKind,
+    pub trips: u32,
+    pub opened_at: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    signal: RateLimitKind,
+    trips: u32,
+    opened_at: Instant,
+    opened_at_utc: DateTime<Utc>,
+    until: Instant,
+    until_utc: DateTime<Utc>,
+    /// When the in-flight probe step launched. Other launches wait for its
+    /// result or for the probe window to pass.
+    probe_started: Option<Instant>,
+}
+
+/// Shared circuit breakers keyed by model.
+pub struct RateLimitGovernor {
+    /// Event sink; `None` keeps the governor silent (tests).
+    storage: Option<Arc<dyn StorageBackend>>,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    probe_window: Duration,
+    breakers: Mutex<HashMap<String, Breaker>>,
+    /// Woken whenever a breaker closes, re-opens or releases its probe.
+    changed: Notify,
+}
+
+impl std::fmt::Debug for RateLimitGovernor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitGovernor")
+            .field("base_backoff", &self.base_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("probe_window", &self.probe_window)
+            .field("breakers", &self.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RateLimitGovernor {
+    /// Create a governor that records backoff events through `storage`.
+    pub fn new(storage: Option<Arc<dyn StorageBackend>>) -> Self {
+        Self {
+            storage,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            probe_window: DEFAULT_PROBE_WINDOW,
+            breakers: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Override the base and maximum backoff.
+    #[must_use]
+    pub fn with_backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_backoff = base;
+        self.max_backoff = max.max(base);
+        self
+    }
+
+    /// Override how long a probe must run unthrottled before launches resume.
+    #[must_use]
+    pub fn with_probe_window(mut self, window: Duration) -> Self {
+        self.probe_window = window;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Breaker>> {
+        self.breakers
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Wait until a step for `model` may launch.
+    ///
+    /// Returns `None` if `cancel` fires while waiting. The returned permit must
+    /// be finished with the attempt's outcome; dropping it unfinished (spawn
+    /// failure, timeout, cancellation) just releases a held probe slot.
+    pub async fn acquire<'a>(
+        &'a self,
+        model: &str,
+        cancel: &CancellationToken,
+    ) -> Option<BackoffPermit<'a>> {
+        let mut announced = false;
+        loop {
+            // Register for wake-ups before inspecting state so a release
+            // between the check and the wait is not missed.
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let wait = {
+                let mut breakers = self.lock();
+                let Some(breaker) = breakers.get_mut(model) else {
+                    return Some(BackoffPermit::new(self, model, false));
+                };
+                let now = Instant::now();
+                match breaker.probe_started {
+                    _ if now < breaker.until => Some(breaker.until - now),
+                    None => {
+                        breaker.probe_started = Some(now);
+                        info!(
+                            model,
+                            trips = breaker.trips,
+                            "API backoff elapsed, launching probe step"
+                        );
+                        return Some(BackoffPermit::new(self, model, true));
+                    }
+                    // The probe got past its first API calls unthrottled.
+                    Some(started) if now >= started + self.probe_window => {
+                        return Some(BackoffPermit::new(self, model, false));
+                    }
+                    Some(started) => Some(started + self.probe_window - now),
+                }
+            };
+
+            if !announced {
+                info!(model, "step launch paused by API backoff");
+                announced = true;
+            }
+            tokio::select! {
+                () = cancel.cancelled() => return None,
+                () = &mut notified => {}
+                () = sleep_for(wait) => {}
+            }
+        }
+    }
+
+    /// Whether launches for `model` are currently held back.
+    pub fn is_open(&self, model: &str) -> bool {
+        self.lock().contains_key(model)
+    }
+
+    /// Current breakers, sorted by model.
+    pub fn snapshot(&self) -> Vec<BackoffStatus> {
+        let now = Instant::now();
+        let mut states: Vec<BackoffStatus> = self
+            .lock()
+            .iter()
+            .map(|(model, breaker)| BackoffStatus {
+                model: model.clone(),
+                state: if now < breaker.until {
+                    "open"
+                } else {
+                    "half_open"
+                },
+                signal: breaker.signal,
+                trips: breaker.trips,
+                opened_at: breaker.opened_at_utc,
+                until: breaker.until_utc,
+            })
+            .collect();
+        states.sort_by(|a, b| a.model.cmp(&b.model));
+        states
+    }
+
+    fn backoff_for(&self, trips: u32, signal: &RateLimitSignal) -> Duration {
+        let exponential = self
+            .base_backoff
+            .saturating_mul(2u32.saturating_pow(trips.saturating_sub(1)));
+        signal
+            .retry_after
+            .unwrap_or(exponential)
+            .min(self.max_backoff)
+    }
+
+    async fn finish(&self, model: &str, outcome: AttemptOutcome, step: &Step) {
+        let payload = match outcome {
+            AttemptOutcome::Throttled(signal) => self.trip(model, signal, step),
+            AttemptOutcome::Completed => self.close(model, step),
+        };
+        self.changed.notify_waiters();
+
+        let (Some(storage), Some(payload)) = (&self.storage, payload) else {
+            return;
+        };
+        if let Err(e) = storage
+            .append_event(&step.run_id, Some(&step.id), &payload)
+            .await
+        {
+            warn!(
+                run_id = %step.run_id,
+                error = %e,
+                event = payload.event_type().as_str(),
+                "failed to emit API backoff event"
+            );
+        }
+    }
+
+    /// Open or extend the breaker. Returns the event for a newly opened breaker.
+    fn trip(&self, model: &str, signal: RateLimitSignal, step: &Step) -> Option<EventPayload> {
+        let now = Instant::now();
+        let now_utc = Utc::now();
+        let mut breakers = self.lock();
+        let opened = !breakers.contains_key(model);
+        let breaker = breakers
+            .entry(model.to_string())
+            .or_insert_with(|| Breaker {
+                signal: signal.kind,
+                trips: 0,
+                opened_at: now,
+                opened_at_utc: now_utc,
+                until: now,
+                until_utc: now_utc,
+                probe_started: None,
+            });
+        breaker.trips += 1;
+        breaker.signal = signal.kind;
+        breaker.probe_started = None;
+        let backoff = self.backoff_for(breaker.trips, &signal);
+        if now + backoff > breaker.until {
+            breaker.until = now + backoff;
+            breaker.until_utc = now_utc + chrono::Duration::from_std(backoff).unwrap_or_default();
+        }
+        warn!(
+            model,
+            run_id = %step.run_id,
+            signal = signal.kind.as_str(),
+            trips = breaker.trips,
+            backoff_sec = backoff.as_secs(),
+            "API throttled, pausing step launches"
+        );
+
+        opened.then(|| {
+            EventPayload::ApiBackoffStarted(ApiBackoffStartedPayload {
+                run_id: step.run_id.clone(),
+                step_id: step.id.clone(),
+                model: model.to_string(),
+                signal: signal.kind,
+                retry_after_sec: signal.retry_after.map(|d| d.as_secs()),
+                backoff_sec: backoff.as_secs(),
+                until: breaker.until_utc,
+            })
+        })
+    }
+
+    /// Close the breaker once its cooldown is over. Returns the closing event.
+    fn close(&self, model: &str, step: &Step) -> Option<EventPayload> {
+        let mut breakers = self.lock();
+        // Success while still cooling down comes from a step launched before
+        // the breaker opened and says nothing about whether the API recovered.
+        if Instant::now() < breakers.get(model)?.until {
+            return None;
+        }
+        let breaker = breakers.remove(model)?;
+        let paused = breaker.opened_at.elapsed();
+        info!(
+            model,
+            run_id = %step.run_id,
+            trips = breaker.trips,
+            paused_sec = paused.as_secs(),
+            "API backoff ended, resuming step launches"
+        );
+        Some(EventPayload::ApiBackoffEnded(ApiBackoffEndedPayload {
+            run_id: step.run_id.clone(),
+            step_id: step.id.clone(),
+            model: model.to_string(),
+            trips: breaker.trips,
+            paused_sec: paused.as_secs(),
+        }))
+    }
+
+    fn release_probe(&self, model: &str) {
+        if let Some(breaker) = self.lock().get_mut(model) {
+            breaker.probe_started = None;
+        }
+        self.changed.notify_waiters();
+    }
+}
+
+/// Sleep for `wait`, or forever when there is no deadline.
+async fn sleep_for(wait: Option<Duration>) {
+    match wait {
+        Some(wait) => tokio::time::sleep(wait).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Permission to launch one agent attempt, returned by [`RateLimitGovernor::acquire`].
+#[derive(Debug)]
+pub struct BackoffPermit<'a> {
+    governor: &'a RateLimitGovernor,
+    model: String,
+    /// This attempt is the half-open probe.
+    probe: bool,
+}
+
+impl<'a> BackoffPermit<'a> {
+    fn new(governor: &'a RateLimitGovernor, model: &str, probe: bool) -> Self {
+        Self {
+            governor,
+            model: model.to_string(),
+            probe,
+        }
+    }
+
+    /// Report the attempt's outcome to the governor.
+    pub async fn finish(mut self, outcome: AttemptOutcome, step: &Step) {
+        self.probe = false;
+        self.governor.finish(&self.model, outcome, step).await;
+    }
+}
+
+impl Drop for BackoffPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.governor.release_probe(&self.model);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+    use loop_core::{Id, ReviewStatus, Run, RunNameSource, RunStatus, StepPhase, StepStatus};
+    use tempfile::TempDir;
+
+    fn step() -> Step {
+        step_for(Id::new())
+    }
+
+    fn step_for(run_id: Id) -> Step {
+        Step {
+            id: Id::new(),
+            run_id,
+            phase: StepPhase::Implementation,
+            status: StepStatus::InProgress,
+            attempt: 1,
+            started_at: None,
+            ended_at: None,
+            exit_code: None,
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        }
+    }
+
+    fn throttled(retry_after: Option<Duration>) -> AttemptOutcome {
+        AttemptOutcome::Throttled(RateLimitSignal {
+            kind: RateLimitKind::RateLimited,
+            retry_after,
+        })
+    }
+
+    #[test]
+    fn detects_rate_limit_and_overload_text() {
+        let signal = detect_rate_limit("API Error: 429 {\"type\":\"error\"}").unwrap();
+        assert_eq!(signal.kind, RateLimitKind::RateLimited);
+        assert_eq!(signal.retry_after, None);
+
+        let signal = detect_rate_limit("API Error: 529 Overloaded").unwrap();
+        assert_eq!(signal.kind, RateLimitKind::Overloaded);
+
+        assert!(detect_rate_limit("API Error: 500 internal").is_none());
+        assert!(detect_rate_limit("all tests passed").is_none());
+    }
+
+    #[test]
+    fn parses_retry_after_hints() {
+        let signal =
+            detect_rate_limit("API Error: 429 rate_limit_error (retry-after: 20)").unwrap();
+        assert_eq!(signal.retry_after, Some(Duration::from_secs(20)));
+
+        let signal =
+            detect_rate_limit(r#"{"type":"overloaded_error","retry_after_ms":1500}"#).unwrap();
+        assert_eq!(signal.retry_after, Some(Duration::from_millis(1500)));
+
+        let signal = detect_rate_limit("API Error: 429 please retry after 7s").unwrap();
+        assert_eq!(signal.retry_after, Some(Duration::from_secs(7)));
+
+        let reset = Utc::now().timestamp() + 120;
+        let signal = detect_rate_limit(&format!("Claude AI usage limit reached|{reset}")).unwrap();
+        assert_eq!(signal.kind, RateLimitKind::RateLimited);
+        let retry = signal.retry_after.unwrap().as_secs();
+        assert!((118..=120).contains(&retry), "retry {retry}");
+    }
+
+    #[test]
+    fn event_detection_ignores_non_error_events() {
+        let error = serde_json::json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        });
+        assert_eq!(
+            detect_rate_limit_event(&error).unwrap().kind,
+            RateLimitKind::Overloaded
+        );
+
+        let tool_output = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": "handle rate_limit_error"}]}
+        });
+        assert!(detect_rate_limit_event(&tool_output).is_none());
+    }
+
+    #[test]
+    fn backoff_doubles_and_respects_hint_and_cap() {
+        let governor = RateLimitGovernor::new(None)
+            .with_backoff(Duration::from_secs(10), Duration::from_secs(50));
+        let signal = |retry_after| RateLimitSignal {
+            kind: RateLimitKind::Overloaded,
+            retry_after,
+        };
+        assert_eq!(
+            governor.backoff_for(1, &signal(None)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            governor.backoff_for(3, &signal(None)),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            governor.backoff_for(9, &signal(None)),
+            Duration::from_secs(50)
+        );
+        assert_eq!(
+            governor.backoff_for(3, &signal(Some(Duration::from_secs(2)))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            governor.backoff_for(1, &signal(Some(Duration::from_secs(500)))),
+            Duration::from_secs(50)
+        );
+    }
+
+    #[tokio::test]
+    async fn throttle_pauses_launches_until_probe_succeeds() {
+        let governor = Arc::new(
+            RateLimitGovernor::new(None)
+                .with_backoff(Duration::from_millis(50), Duration::from_secs(1)),
+        );
+        let cancel = CancellationToken::new();
+        let step = step();
+
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit.finish(throttled(None), &step).await;
+        assert!(governor.is_open("opus"));
+        assert!(!governor.is_open("sonnet"));
+        assert_eq!(governor.snapshot()[0].state, "open");
+
+        // Other models are unaffected.
+        assert!(governor.acquire("sonnet", &cancel).await.is_some());
+
+        // The first waiter becomes the probe; the second waits for its result.
+        let started = Instant::now();
+        let probe = governor.acquire("opus", &cancel).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(40));
+        assert!(probe.probe);
+
+        let waiter = {
+            let governor = Arc::clone(&governor);
+            let cancel = cancel.clone();
+            tokio::spawn(async move { governor.acquire("opus", &cancel).await.map(|p| p.probe) })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(governor.snapshot()[0].state, "half_open");
+
+        probe.finish(AttemptOutcome::Completed, &step).await;
+        assert_eq!(waiter.await.unwrap(), Some(false));
+        assert!(!governor.is_open("opus"));
+        assert!(governor.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn long_running_probe_releases_waiters_after_probe_window() {
+        let governor = Arc::new(
+            RateLimitGovernor::new(None)
+                .with_backoff(Duration::from_millis(10), Duration::from_secs(1))
+                .with_probe_window(Duration::from_millis(100)),
+        );
+        let cancel = CancellationToken::new();
+        let step = step();
+
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit.finish(throttled(None), &step).await;
+        // The probe keeps running (a long agent step) and never finishes here.
+        let probe = governor.acquire("opus", &cancel).await.unwrap();
+        let probe_started = Instant::now();
+        assert!(probe.probe);
+
+        let waiter = {
+            let governor = Arc::clone(&governor);
+            let cancel = cancel.clone();
+            tokio::spawn(async move { governor.acquire("opus", &cancel).await.map(|p| p.probe) })
+        };
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(!waiter.is_finished());
+
+        assert_eq!(waiter.await.unwrap(), Some(false));
+        assert!(probe_started.elapsed() >= Duration::from_millis(100));
+        assert!(probe_started.elapsed() < Duration::from_secs(1));
+
+        probe.finish(AttemptOutcome::Completed, &step).await;
+        assert!(!governor.is_open("opus"));
+    }
+
+    #[tokio::test]
+    async fn failed_probe_reopens_with_longer_backoff() {
+        let governor = RateLimitGovernor::new(None)
+            .with_backoff(Duration::from_millis(20), Duration::from_secs(1));
+        let cancel = CancellationToken::new();
+        let step = step();
+
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit.finish(throttled(None), &step).await;
+        let probe = governor.acquire("opus", &cancel).await.unwrap();
+        probe.finish(throttled(None), &step).await;
+
+        let status = &governor.snapshot()[0];
+        assert_eq!(status.trips, 2);
+        assert_eq!(status.state, "open");
+        assert!(status.until - status.opened_at >= chrono::Duration::milliseconds(40));
+    }
+
+    #[tokio::test]
+    async fn stale_success_keeps_breaker_open() {
+        let governor = RateLimitGovernor::new(None)
+            .with_backoff(Duration::from_millis(20), Duration::from_secs(1));
+        let cancel = CancellationToken::new();
+        let step = step();
+
+        // Launched before the throttle: its success must not close the breaker.
+        let in_flight = governor.acquire("opus", &cancel).await.unwrap();
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit
+            .finish(throttled(Some(Duration::from_secs(5))), &step)
+            .await;
+        in_flight.finish(AttemptOutcome::Completed, &step).await;
+        assert!(governor.is_open("opus"));
+    }
+
+    #[tokio::test]
+    async fn dropped_probe_lets_next_waiter_probe() {
+        let governor = RateLimitGovernor::new(None)
+            .with_backoff(Duration::from_millis(10), Duration::from_secs(1));
+        let cancel = CancellationToken::new();
+        let step = step();
+
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit.finish(throttled(None), &step).await;
+        let probe = governor.acquire("opus", &cancel).await.unwrap();
+        drop(probe);
+        let next = governor.acquire("opus", &cancel).await.unwrap();
+        assert!(next.probe);
+    }
+
+    #[tokio::test]
+    async fn acquire_returns_none_when_cancelled() {
+        let governor = RateLimitGovernor::new(None);
+        let cancel = CancellationToken::new();
+        let step = step();
+
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit.finish(throttled(None), &step).await;
+        cancel.cancel();
+        assert!(governor.acquire("opus", &cancel).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn backoff_events_are_recorded_on_the_run() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), 3).await.unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+        let now = Utc::now();
+        let run = Run {
+            id: Id::new(),
+            name: "throttled".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Running,
+            workspace_root: "/workspace".to_string(),
+            spec_path: "/workspace/spec.md".to_string(),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: now,
+            updated_at: now,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        };
+        storage.insert_run(&run).await.unwrap();
+        let step = step_for(run.id.clone());
+        storage.insert_step(&step).await.unwrap();
+
+        let governor = RateLimitGovernor::new(Some(Arc::clone(&storage)))
+            .with_backoff(Duration::from_millis(10), Duration::from_secs(1));
+        let cancel = CancellationToken::new();
+
+        let permit = governor.acquire("opus", &cancel).await.unwrap();
+        permit
+            .finish(throttled(Some(Duration::from_millis(10))), &step)
+            .await;
+        let probe = governor.acquire("opus", &cancel).await.unwrap();
+        probe.finish(AttemptOutcome::Completed, &step).await;
+
+        let events = storage.list_events(&step.run_id).await.unwrap();
+        let types: Vec<_> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, ["API_BACKOFF_STARTED", "API_BACKOFF_ENDED"]);
+    }
+}
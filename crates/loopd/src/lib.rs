@@ -3,20 +3,36 @@
 //! Library components for the daemon process.
 //! See spec: specs/orchestrator-daemon.md
 
+pub mod backend;
+pub mod bus;
+pub mod dependencies;
+pub mod gc;
 pub mod git;
+pub mod governor;
 pub mod handlers;
+pub mod metrics;
 pub mod naming;
+pub mod notifications;
 pub mod postmortem;
+pub mod replay;
+pub mod resources;
 pub mod runner;
+pub mod sandbox;
 pub mod scheduler;
+pub mod schedules;
 pub mod server;
+pub mod session;
 pub mod skills;
 pub mod storage;
+pub mod storage_postgres;
+pub mod subruns;
 pub mod verifier;
 pub mod watchdog;
+pub mod workers;
 pub mod worktree;
 pub mod worktree_worktrunk;
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
@@ -40,33 +56,45 @@ const MAX_REVIEW_SNAPSHOT_BYTES: usize = 50 * 1024 * 1024;
 
 use crate::handlers::review::build_run_diff_snapshot;
 use chrono::Utc;
+use dependencies::DependencyWatcher;
+use gc::GarbageCollector;
 use loop_core::completion::check_completion;
 use loop_core::events::{
-    EventPayload, PostmortemEndPayload, PostmortemStartPayload, RunCompletedPayload,
-    RunFailedPayload, SelectedSkillPayload, SkillsDiscoveredPayload, SkillsLoadFailedPayload,
-    SkillsSelectedPayload, SkillsTruncatedPayload, StepFinishedPayload, StepStartedPayload,
+    BudgetExceededPayload, EventPayload, ParallelTasksSerialPayload, PostmortemEndPayload,
+    PostmortemStartPayload, RunCompletedPayload, RunFailedPayload, SandboxViolationPayload,
+    SelectedSkillPayload, SkillsDiscoveredPayload, SkillsLoadFailedPayload, SkillsSelectedPayload,
+    SkillsTruncatedPayload, StepFinishedPayload, StepStartedPayload, ToolPermissionDeniedPayload,
     WatchdogRewritePayload, WorktreeCreatedPayload, WorktreeProviderSelectedPayload,
     WorktreeRemovedPayload,
 };
-use loop_core::plan::{select_task, TaskSelection};
+use loop_core::logs;
+use loop_core::plan::{count_pending_tasks, select_task, select_task_by_id, TaskSelection};
+use loop_core::prompt::spec_slug;
 use loop_core::skills::SkillMetadata;
-use loop_core::types::{MergeStrategy, WorktreeProvider};
+use loop_core::types::{MergeStrategy, QueuePolicy, WorktreeProvider};
 use loop_core::{
-    mirror_artifact, write_and_mirror_artifact, Artifact, Config, Id, ReviewStatus, Run, StepPhase,
-    StepStatus,
+    global_run_dir, mirror_artifact, write_and_mirror_artifact, Artifact, ArtifactMode, Config, Id,
+    RetentionPolicy, ReviewStatus, Run, SandboxMode, StepPhase, StepStatus, TokenUsage,
 };
+use notifications::{NotificationsConfig, Notifier};
 use postmortem::ExitReason;
+use replay::FixtureRecorder;
 use runner::{Runner, RunnerConfig, RunnerError};
+use sandbox::{SandboxViolation, SandboxedProcess};
 use scheduler::Scheduler;
+use schedules::ScheduleRunner;
+use session::{SessionReset, SessionTracker};
 use skills::{
     load_skill_body, render_available_skills, select_skills, LoadFailureEvent, SkillSelection,
     SkillsMetrics, StepKind, TruncationEvent,
 };
-use storage::Storage;
+use storage::{open_backend, Storage, StorageBackend};
+use subruns::{FanOut, SupervisionOutcome};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 use verifier::{Verifier, VerifierConfig};
 use watchdog::{Watchdog, WatchdogAction};
+use workers::{LeaseReaper, StepDispatch};
 
 /// Type alias for application-level errors with context and backtraces.
 pub type AppResult<T> = eyre::Result<T>;
@@ -165,30 +193,126 @@ impl ConsecutiveFailures {
     }
 }
 
+// --- Budget Limits ---
+
+/// A per-run budget or the daily spend cap that has been exhausted.
+#[derive(Debug, Clone, PartialEq)]
+struct BudgetBreach {
+    /// Limit name reported in `BUDGET_EXCEEDED` and the `RUN_FAILED` reason.
+    limit: &'static str,
+    used: f64,
+    max: f64,
+}
+
+/// Check the per-run budget limits from the run config.
+///
+/// Returns the first exhausted limit. A limit of 0 disables that check.
+fn check_run_budget(config: &Config, usage: &TokenUsage, elapsed_sec: u64) -> Option<BudgetBreach> {
+    if config.max_run_cost_usd > 0.0 && usage.cost_usd >= config.max_run_cost_usd {
+        return Some(BudgetBreach {
+            limit: "cost_usd",
+            used: usage.cost_usd,
+            max: config.max_run_cost_usd,
+        });
+    }
+    if config.max_run_tokens > 0 && usage.total_tokens() >= config.max_run_tokens {
+        return Some(BudgetBreach {
+            limit: "tokens",
+            used: usage.total_tokens() as f64,
+            max: config.max_run_tokens as f64,
+        });
+    }
+    if config.max_run_wall_clock_sec > 0 && elapsed_sec >= config.max_run_wall_clock_sec {
+        return Some(BudgetBreach {
+            limit: "wall_clock_sec",
+            used: elapsed_sec as f64,
+            max: config.max_run_wall_clock_sec as f64,
+        });
+    }
+    None
+}
+
+/// Check the run's budget limits, then the daemon-wide daily spend cap.
+///
+/// Wall-clock time is measured from `started_at` (the run's first step).
+async fn find_budget_breach(
+    storage: &dyn StorageBackend,
+    scheduler: &Scheduler,
+    run_id: &Id,
+    config: &Config,
+    started_at: chrono::DateTime<Utc>,
+) -> AppResult<Option<BudgetBreach>> {
+    let usage = if config.max_run_cost_usd > 0.0 || config.max_run_tokens > 0 {
+        storage.get_run_usage(run_id).await?
+    } else {
+        TokenUsage::default()
+    };
+    let elapsed_sec = u64::try_from((Utc::now() - started_at).num_seconds()).unwrap_or(0);
+    if let Some(breach) = check_run_budget(config, &usage, elapsed_sec) {
+        return Ok(Some(breach));
+    }
+
+    Ok(scheduler
+        .daily_budget_exceeded()
+        .await?
+        .map(|(used, max)| BudgetBreach {
+            limit: "daily_cost_usd",
+            used,
+            max,
+        }))
+}
+
 /// Daemon configuration.
 #[derive(Debug, Clone)]
 pub struct DaemonConfig {
     /// Path to the `SQLite` database.
     pub db_path: PathBuf,
+    /// Database URL (`sqlite://<path>` or `postgres://...`); overrides `db_path`.
+    pub database_url: Option<String>,
     /// Maximum concurrent runs (default: 3).
     pub max_concurrent_runs: usize,
     /// Maximum concurrent runs per workspace (optional).
     /// See spec Section 4.2, 5.3: per-workspace cap enforcement.
     pub max_runs_per_workspace: Option<usize>,
+    /// Order in which pending runs are claimed (default: fifo).
+    pub queue_policy: QueuePolicy,
+    /// Fair-share weight per workspace root; unlisted workspaces weigh 1.
+    pub workspace_weights: HashMap<String, u32>,
+    /// Seconds a pending run waits per priority level gained; 0 disables aging.
+    pub queue_aging_sec: u64,
     /// HTTP server port (default: 7700).
     pub port: u16,
     /// Auth token for HTTP API (optional, Section 8.1).
     pub auth_token: Option<String>,
+    /// Daemon-wide spend cap (USD) per UTC day across all workspaces (optional).
+    pub max_daily_cost_usd: Option<f64>,
+    /// TOML file with webhook notification rules (optional).
+    pub notifications_config: Option<PathBuf>,
+    /// Directory that receives a replayable fixture bundle per run (optional).
+    pub record_fixtures: Option<PathBuf>,
+    /// Hand steps to registered workers instead of running them in-process.
+    pub distributed: bool,
+    /// Which finished runs are pruned; the collector runs only when a limit is set.
+    pub retention: RetentionPolicy,
 }
 
 impl Default for DaemonConfig {
     fn default() -> Self {
         Self {
             db_path: default_db_path(),
+            database_url: None,
             max_concurrent_runs: scheduler::DEFAULT_MAX_CONCURRENT_RUNS,
             max_runs_per_workspace: Some(1),
+            queue_policy: QueuePolicy::Fifo,
+            workspace_weights: HashMap::new(),
+            queue_aging_sec: scheduler::DEFAULT_QUEUE_AGING_SEC,
             port: 7700,
             auth_token: std::env::var("LOOPD_AUTH_TOKEN").ok(),
+            max_daily_cost_usd: None,
+            notifications_config: None,
+            record_fixtures: None,
+            distributed: false,
+            retention: RetentionPolicy::default(),
         }
     }
 }
@@ -209,39 +333,57 @@ fn default_db_path() -> PathBuf {
 #[derive(Debug)]
 pub struct Daemon {
     config: DaemonConfig,
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     scheduler: Arc<Scheduler>,
     /// Skills metrics per open-skills-orchestration.md Section 7.2.
     skills_metrics: Arc<SkillsMetrics>,
+    /// Webhook routing rules; empty when notifications are not configured.
+    notifications: Arc<NotificationsConfig>,
 }
 
 impl Daemon {
     /// Create a new daemon with the given configuration.
     pub async fn new(config: DaemonConfig) -> AppResult<Self> {
-        let storage = Storage::new(&config.db_path, config.max_concurrent_runs).await?;
-        storage.migrate_embedded().await?;
-        let storage = Arc::new(storage);
+        let storage: Arc<dyn StorageBackend> = match &config.database_url {
+            Some(url) => open_backend(url, config.max_concurrent_runs).await?,
+            None => {
+                let storage = Storage::new(&config.db_path, config.max_concurrent_runs).await?;
+                storage.migrate_embedded().await?;
+                Arc::new(storage)
+            }
+        };
 
-        // Create scheduler with optional per-workspace cap (spec Section 4.2, 5.3).
-        let scheduler = Arc::new(match config.max_runs_per_workspace {
-            Some(max_per_ws) => Scheduler::new_with_workspace_cap(
+        // Create scheduler with queue policy and optional per-workspace cap (spec Section 4.2, 5.3).
+        let scheduler = Arc::new(
+            Scheduler::new_with_policy(
                 Arc::clone(&storage),
                 config.max_concurrent_runs,
-                max_per_ws,
-            ),
-            None => Scheduler::new(Arc::clone(&storage), config.max_concurrent_runs),
-        });
+                config.max_runs_per_workspace,
+                config.queue_policy,
+            )
+            .with_workspace_weights(config.workspace_weights.clone())
+            .with_queue_aging(Some(Duration::from_secs(config.queue_aging_sec)))
+            .with_daily_cost_cap(config.max_daily_cost_usd)
+            .with_distributed(config.distributed)
+            .with_retention(config.retention.clone()),
+        );
+
+        let notifications = match &config.notifications_config {
+            Some(path) => NotificationsConfig::load(path)?,
+            None => NotificationsConfig::default(),
+        };
 
         Ok(Self {
             config,
             storage,
             scheduler,
             skills_metrics: Arc::new(SkillsMetrics::new()),
+            notifications: Arc::new(notifications),
         })
     }
 
     /// Get a reference to the storage backend.
-    pub fn storage(&self) -> &Arc<Storage> {
+    pub fn storage(&self) -> &Arc<dyn StorageBackend> {
         &self.storage
     }
 
@@ -258,16 +400,47 @@ impl Daemon {
     /// Run the daemon main loop.
     pub async fn run(&self) -> AppResult<()> {
         info!("loopd starting on port {}", self.config.port);
-        info!("database: {}", self.config.db_path.display());
+        match &self.config.database_url {
+            Some(url) => info!("database: {}", url),
+            None => info!("database: {}", self.config.db_path.display()),
+        }
         info!("max concurrent runs: {}", self.config.max_concurrent_runs);
+        if let Some(cap) = self.config.max_daily_cost_usd {
+            info!("max daily cost: ${:.2}", cap);
+        }
         if let Some(limit) = self.config.max_runs_per_workspace {
-            info!("max runs per workspace: {}", limit)
+            info!("max runs per workspace: {}", limit);
         } else {
-            info!("max runs per workspace: unbounded")
+            info!("max runs per workspace: unbounded");
         }
         if self.config.auth_token.is_some() {
             info!("auth token: enabled");
         }
+        if !self.notifications.webhooks.is_empty() {
+            info!(
+                "notifications: {} webhook(s)",
+                self.notifications.webhooks.len()
+            );
+            Notifier::new(Arc::clone(&self.storage), Arc::clone(&self.notifications))
+                .spawn(self.scheduler.cancel_token());
+        }
+        ScheduleRunner::new(Arc::clone(&self.storage)).spawn(self.scheduler.cancel_token());
+        DependencyWatcher::new(Arc::clone(&self.scheduler)).spawn(self.scheduler.cancel_token());
+        if self.config.distributed {
+            info!("distributed mode: steps run on registered workers");
+            // Offers from before a restart have no run waiting on them any more.
+            match self.storage.clear_leases().await {
+                Ok(0) => {}
+                Ok(cleared) => info!("withdrew {} stale step offer(s)", cleared),
+                Err(e) => warn!("failed to clear stale leases: {}", e),
+            }
+            LeaseReaper::new(Arc::clone(&self.storage)).spawn(self.scheduler.cancel_token());
+        }
+        if self.config.retention.is_enabled() {
+            info!("retention: {:?}", self.config.retention);
+            GarbageCollector::new(Arc::clone(&self.storage), self.config.retention.clone())
+                .spawn(self.scheduler.cancel_token());
+        }
 
         // Resume any runs that were interrupted by a previous crash.
         match self.scheduler.resume_interrupted_runs().await {
@@ -282,14 +455,21 @@ impl Daemon {
                         let scheduler = Arc::clone(&self.scheduler);
                         let storage = Arc::clone(&self.storage);
                         let skills_metrics = Arc::clone(&self.skills_metrics);
+                        let record_fixtures = self.config.record_fixtures.clone();
                         let run_id = run.id.clone();
                         let cancel_token = scheduler.cancel_token();
                         tokio::spawn(async move {
                             let scheduler_for_error = Arc::clone(&scheduler);
                             let storage_for_error = Arc::clone(&storage);
-                            if let Err(e) =
-                                process_run(scheduler, storage, run, cancel_token, skills_metrics)
-                                    .await
+                            if let Err(e) = process_run(
+                                scheduler,
+                                storage,
+                                run,
+                                cancel_token,
+                                skills_metrics,
+                                record_fixtures,
+                            )
+                            .await
                             {
                                 let error_message = e.to_string();
                                 error!("resumed run processing failed: {}", error_message);
@@ -333,11 +513,18 @@ impl Daemon {
         // Start HTTP server in background task.
         let http_storage = Arc::clone(&self.storage);
         let http_scheduler = Arc::clone(&self.scheduler);
+        let http_skills_metrics = Arc::clone(&self.skills_metrics);
         let http_port = self.config.port;
         let http_token = self.config.auth_token.clone();
         let http_handle = tokio::spawn(async move {
-            if let Err(e) =
-                server::start_server(http_storage, http_scheduler, http_port, http_token).await
+            if let Err(e) = server::start_server(
+                http_storage,
+                http_scheduler,
+                http_skills_metrics,
+                http_port,
+                http_token,
+            )
+            .await
             {
                 error!("HTTP server error: {}", e);
             }
@@ -364,13 +551,21 @@ impl Daemon {
                     let scheduler = Arc::clone(&self.scheduler);
                     let storage = Arc::clone(&self.storage);
                     let skills_metrics = Arc::clone(&self.skills_metrics);
+                    let record_fixtures = self.config.record_fixtures.clone();
                     let run_id = run.id.clone();
                     let cancel_token = scheduler.cancel_token();
                     tokio::spawn(async move {
                         let scheduler_for_error = Arc::clone(&scheduler);
                         let storage_for_error = Arc::clone(&storage);
-                        if let Err(e) =
-                            process_run(scheduler, storage, run, cancel_token, skills_metrics).await
+                        if let Err(e) = process_run(
+                            scheduler,
+                            storage,
+                            run,
+                            cancel_token,
+                            skills_metrics,
+                            record_fixtures,
+                        )
+                        .await
                         {
                             let error_message = e.to_string();
                             error!("run processing failed: {}", error_message);
@@ -528,15 +723,88 @@ fn build_worktree_config_for_provider(
 /// main working tree and the worktree checkout. The agent must read/write the
 /// worktree copy so that changes stay on the run branch instead of leaking as
 /// uncommitted modifications in the main working tree.
-fn remap_to_worktree(path: &str, workspace_root: &str, worktree_path: &str) -> String {
-    if let Some(rel) = path.strip_prefix(workspace_root) {
-        let rel = rel.strip_prefix('/').unwrap_or(rel);
-        format!("{worktree_path}/{rel}")
-    } else {
-        path.to_string()
+///
+/// Paths already inside the worktree (e.g. a child run's plan copy) are left
+/// as is, and only whole path components are matched, so a sibling directory
+/// such as `/x/project.run-1` is not mistaken for a child of `/x/project`.
+pub(crate) fn remap_to_worktree(path: &str, workspace_root: &str, worktree_path: &str) -> String {
+    let candidate = Path::new(path);
+    if candidate.starts_with(worktree_path) {
+        return path.to_string();
+    }
+    match candidate.strip_prefix(workspace_root) {
+        Ok(rel) => Path::new(worktree_path).join(rel).display().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Select the plan task to inject into a prompt.
+///
+/// Child runs always get their own task by ID; other runs get the next pending
+/// task. Returns `None` if the plan cannot be read.
+fn select_prompt_task(plan_file: &Path, subrun_task: Option<&str>) -> Option<TaskSelection> {
+    match subrun_task {
+        Some(task_id) => std::fs::read_to_string(plan_file)
+            .ok()
+            .and_then(|content| select_task_by_id(&content, task_id)),
+        None => select_task(plan_file).ok().flatten(),
     }
 }
 
+/// Spec alignment rules shared by the serial and sub-run implementation prompts.
+const SPEC_ALIGNMENT_GUARDRAILS: &str = "\
+Spec alignment guardrails (must follow):
+- Before coding, identify the exact spec section(s) you are implementing and list the required
+  behavior, constraints, and any data shapes.
+- If the spec defines a schema/event payload/API contract, match it exactly (field names,
+  nesting, nullability, ordering). Keep types in sync.
+- Do not use placeholder values for required behavior. Implement the real behavior or leave the
+  task unchecked.
+- If any spec detail is ambiguous, do not guess. Choose the safest minimal interpretation,
+  document the assumption in your response, and limit changes to what is unambiguous.";
+
+/// Base prompt for a child run implementing one plan task (see subruns.rs).
+///
+/// The parent tracks task state and merges the child's branch, so the child
+/// neither checks off tasks nor waits for the rest of the plan.
+fn subrun_prompt(refs: &str, task_id: &str, completion_note: &str) -> String {
+    format!(
+        r#"{refs}
+
+You are an implementation agent working on one task of a plan that several runs implement in
+parallel. Read the spec and the plan.
+
+IMPORTANT: Before starting work, check:
+1. The LEARNINGS.md file for repo-wide patterns and common mistakes
+2. The ## Learnings section at the bottom of the plan for task-specific corrections
+Avoid repeating past mistakes - these learnings exist because previous implementations got it wrong.
+
+Task:
+1. Implement only plan task `{task_id}` (shown under "Selected Task" below). Other runs implement
+   the remaining tasks; do not work on them, even if they look unfinished.
+2. Run verification relevant to that task. If the plan lists a verification checklist, run what
+   applies.
+3. Do not edit the plan file. The parent run tracks task state.
+4. Make exactly one git commit for your changes using `gritty commit --accept`.
+5. When task `{task_id}` is implemented and committed, respond with:
+<promise>COMPLETE</promise>
+
+{SPEC_ALIGNMENT_GUARDRAILS}
+
+Response format (strict):
+- Task `{task_id}` implemented and committed: output `<promise>COMPLETE</promise>`.
+  If the runner requires exact output, print only the token; otherwise ensure it's the final non-empty line.
+- Otherwise: ONE sentence saying what remains or what blocks the task.
+
+Constraints:
+- Do not modify files under `reference/`.
+- Do not work on any other plan task.
+- If no changes were made, do not commit.
+
+{completion_note}"#
+    )
+}
+
 /// Build the implementation prompt with context file references.
 /// Matches bin/loop behavior: @spec @plan @runner-notes @LEARNINGS.md + `context_files`.
 ///
@@ -553,6 +821,7 @@ fn build_implementation_prompt(
     run_dir: &Path,
     config: &Config,
     available_skills: &[SkillMetadata],
+    subrun_task: Option<&str>,
 ) -> (
     String,
     Option<SkillSelection>,
@@ -594,7 +863,11 @@ fn build_implementation_prompt(
         refs.push_str(&format!(" @{}", remap(&learnings_path.display().to_string())));
     }
 
-    let custom_prompt = if let Some(prompt_file) = config.prompt_file.as_ref() {
+    // Child runs always use the sub-run prompt; custom prompts assume the
+    // serial rules (check off tasks, complete when the plan is done).
+    let custom_prompt = if subrun_task.is_some() {
+        None
+    } else if let Some(prompt_file) = config.prompt_file.as_ref() {
         prompt_file.exists().then(|| prompt_file.clone())
     } else {
         let default_prompt = workspace_root.join(".loop/prompt.txt");
@@ -627,7 +900,9 @@ fn build_implementation_prompt(
         String::new()
     };
 
-    if prompt.trim().is_empty() {
+    if let Some(task_id) = subrun_task {
+        prompt = subrun_prompt(&refs, task_id, completion_note);
+    } else if prompt.trim().is_empty() {
         prompt = format!(
             r#"{refs}
 
@@ -658,15 +933,7 @@ Checkbox legend:
 - `[R]`: reviewed/verified (non-blocking)
 - `[ ]?`: manual QA only (ignored)
 
-Spec alignment guardrails (must follow):
-- Before coding, identify the exact spec section(s) you are implementing and list the required
-  behavior, constraints, and any data shapes.
-- If the spec defines a schema/event payload/API contract, match it exactly (field names,
-  nesting, nullability, ordering). Keep types in sync.
-- Do not use placeholder values for required behavior. Implement the real behavior or leave the
-  task unchecked.
-- If any spec detail is ambiguous, do not guess. Choose the safest minimal interpretation,
-  document the assumption in your response, and limit changes to what is unambiguous.
+{SPEC_ALIGNMENT_GUARDRAILS}
 
 Response format (strict):
 - ALL `[ ]` tasks complete: output `<promise>COMPLETE</promise>`.
@@ -686,7 +953,7 @@ Constraints:
         );
     }
 
-    let plan_placeholder = run.plan_path.as_deref().map(|p| remap(p)).unwrap_or_default();
+    let plan_placeholder = run.plan_path.as_deref().map(&remap).unwrap_or_default();
     prompt = prompt
         .replace("SPEC_PATH", &remap(&run.spec_path))
         .replace("PLAN_PATH", &plan_placeholder);
@@ -696,9 +963,7 @@ Constraints:
     let mut selected_task: Option<TaskSelection> = None;
     if let Some(plan_path) = &run.plan_path {
         let plan_file = PathBuf::from(remap(plan_path));
-        if let Ok(Some(task)) = select_task(&plan_file) {
-            selected_task = Some(task);
-        }
+        selected_task = select_prompt_task(&plan_file, subrun_task);
     }
 
     // Inject selected task text into prompt (Section 5.1).
@@ -814,6 +1079,7 @@ fn build_review_prompt(
     run: &loop_core::Run,
     config: &Config,
     available_skills: &[SkillMetadata],
+    subrun_task: Option<&str>,
 ) -> (
     String,
     Option<SkillSelection>,
@@ -862,9 +1128,7 @@ If changes are needed:
     let mut selected_task: Option<TaskSelection> = None;
     if let Some(plan_path) = &run.plan_path {
         let plan_file = PathBuf::from(remap(plan_path));
-        if let Ok(Some(task)) = select_task(&plan_file) {
-            selected_task = Some(task);
-        }
+        selected_task = select_prompt_task(&plan_file, subrun_task);
     }
 
     // Inject selected task text into prompt (Section 5.1).
@@ -965,10 +1229,58 @@ If changes are needed:
     )
 }
 
+/// Path of the plan generated by the planning phase: `<plans_dir>/<spec-slug>-plan.md`.
+///
+/// `config.plans_dir` must already be resolved against the workspace root.
+fn generated_plan_path(run: &loop_core::Run, config: &Config) -> PathBuf {
+    let slug = spec_slug(Path::new(&run.spec_path));
+    config.plans_dir.join(format!("{slug}-plan.md"))
+}
+
+/// Build the planning prompt that asks the agent to decompose the spec into a
+/// checkbox plan at `plan_file` (already remapped into the worktree if any).
+fn build_planning_prompt(run: &loop_core::Run, plan_file: &Path) -> String {
+    let spec_ref = if let Some(wt) = run.worktree.as_ref() {
+        remap_to_worktree(&run.spec_path, &run.workspace_root, &wt.worktree_path)
+    } else {
+        run.spec_path.clone()
+    };
+    let plan_file = plan_file.display();
+
+    format!(
+        r"@{spec_ref}
+
+You are a planning agent. Read the spec and decompose it into an implementation plan.
+
+Task:
+1. Write the plan to {plan_file} (create parent directories if needed).
+2. Group tasks under `## Phase N: <name>` headings, in the order they should be implemented.
+3. Write each task as one unchecked checkbox line: `- [ ] <task>`. Keep tasks small enough
+   to implement and verify in a single commit, and cite the spec section they implement.
+4. Put manual-only checks under a `## Verification Checklist` heading, or mark them `- [ ]?`.
+   They are not tasks.
+5. Do not implement anything and do not modify other files.
+
+Response format (strict): ONE sentence stating how many tasks the plan contains."
+    )
+}
+
+/// Check that the planning phase produced a plan the implementation loop can use.
+///
+/// Returns the number of pending tasks, or a reason suitable for `RUN_FAILED`.
+fn validate_generated_plan(plan_file: &Path) -> Result<usize, String> {
+    let content = std::fs::read_to_string(plan_file)
+        .map_err(|e| format!("plan_not_written:{}: {e}", plan_file.display()))?;
+    match count_pending_tasks(&content) {
+        0 => Err(format!("plan_has_no_tasks:{}", plan_file.display())),
+        pending => Ok(pending),
+    }
+}
+
 /// Write summary.json for a run if enabled in config.
 ///
 /// Implements postmortem-analysis.md Section 5.1 step 2.
-async fn maybe_export_report(storage: &Storage, run: &Run, config: &Config) {
+async fn maybe_export_report(storage: &dyn StorageBackend, run: &Run, config: &Config) {
     let workspace_root = Path::new(&run.workspace_root);
     let run_dir = loop_core::workspace_run_dir(workspace_root, &run.id);
     let report_path = run_dir.join("report.tsv");
@@ -1021,7 +1333,7 @@ async fn maybe_export_report(storage: &Storage, run: &Run, config: &Config) {
 }
 
 async fn maybe_write_summary(
-    storage: &Storage,
+    storage: &dyn StorageBackend,
     run: &Run,
     config: &Config,
     exit_reason: ExitReason,
@@ -1065,7 +1377,7 @@ async fn maybe_write_summary(
 }
 
 async fn maybe_write_review_snapshot(
-    storage: &Storage,
+    storage: &dyn StorageBackend,
     run: &Run,
     config: &Config,
     exit_reason: ExitReason,
@@ -1145,7 +1457,7 @@ async fn maybe_write_review_snapshot(
 }
 
 async fn finalize_run_artifacts(
-    storage: &Storage,
+    storage: &dyn StorageBackend,
     run: &Run,
     config: &Config,
     exit_reason: ExitReason,
@@ -1170,7 +1482,7 @@ async fn finalize_run_artifacts(
 /// Implements postmortem-analysis.md Section 5.1 steps 3-5.
 /// Emits `POSTMORTEM_START` and `POSTMORTEM_END` events.
 async fn maybe_run_postmortem(
-    storage: &Storage,
+    storage: &dyn StorageBackend,
     run: &Run,
     config: &Config,
     iterations_run: u32,
@@ -1181,11 +1493,12 @@ async fn maybe_run_postmortem(
         return;
     }
 
-    // Check if claude is available (spec Section 5.1 step 3)
-    if !postmortem::is_claude_available() {
+    // Check if the agent backend is available (spec Section 5.1 step 3)
+    if !postmortem::is_agent_available(config) {
         warn!(
             run_id = %run.id,
-            "claude CLI not found; skipping postmortem analysis"
+            backend = config.agent_backend.as_str(),
+            "agent backend unavailable for analysis; skipping postmortem analysis"
         );
         return;
     }
@@ -1248,10 +1561,11 @@ async fn maybe_run_postmortem(
 /// individually cancelled or the global shutdown token fires.
 async fn process_run(
     scheduler: Arc<Scheduler>,
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     run: loop_core::Run,
     _cancel_token: tokio_util::sync::CancellationToken,
     skills_metrics: Arc<SkillsMetrics>,
+    record_fixtures: Option<PathBuf>,
 ) -> AppResult<()> {
     // Register a per-run cancellation token (child of global shutdown token).
     // This allows cancel_run() to kill just this run's in-flight process.
@@ -1263,6 +1577,7 @@ async fn process_run(
         run.clone(),
         cancel_token,
         skills_metrics,
+        record_fixtures,
     )
     .await;
 
@@ -1272,14 +1587,87 @@ async fn process_run(
     result
 }
 
+/// Hand the run's plan to parallel child runs when `parallel_tasks` allows it.
+///
+/// Returns `None` when the run should implement its plan itself: fan-out is
+/// off, the run is a child, there is no plan or worktree, the plan is not a
+/// task graph, or the scheduler has no room for children next to the parent.
+async fn supervise_subruns(
+    scheduler: &Scheduler,
+    storage: &dyn StorageBackend,
+    run: &loop_core::Run,
+    config: &Config,
+    is_child: bool,
+    working_dir: &Path,
+    cancel_token: &tokio_util::sync::CancellationToken,
+) -> AppResult<Option<SupervisionOutcome>> {
+    if !config.parallel_tasks || is_child {
+        return Ok(None);
+    }
+    let (Some(plan_path), Some(worktree)) = (run.plan_path.as_deref(), run.worktree.as_ref())
+    else {
+        return Ok(None);
+    };
+    let plan_file = PathBuf::from(remap_to_worktree(
+        plan_path,
+        &run.workspace_root,
+        &worktree.worktree_path,
+    ));
+    let fan_out = match subruns::fan_out_graph(config, &plan_file) {
+        FanOut::Graph(_) if !subruns::has_room_for_children(scheduler) => FanOut::Serial {
+            cause: "no_run_slots",
+            detail: format!(
+                "parallel_tasks needs room for at least two concurrent runs \
+                 (max_concurrent_runs={}, max_runs_per_workspace={})",
+                scheduler.max_concurrent(),
+                scheduler
+                    .max_runs_per_workspace()
+                    .map_or_else(|| "unlimited".to_string(), |cap| cap.to_string()),
+            ),
+        },
+        fan_out => fan_out,
+    };
+    let graph = match fan_out {
+        FanOut::Graph(graph) => graph,
+        FanOut::Off => return Ok(None),
+        FanOut::Serial { cause, detail } => {
+            warn!(run_id = %run.id, cause, detail = %detail, "running plan tasks serially");
+            let payload = EventPayload::ParallelTasksSerial(ParallelTasksSerialPayload {
+                run_id: run.id.clone(),
+                cause: cause.to_string(),
+                detail,
+            });
+            if let Err(e) = storage.append_event(&run.id, None, &payload).await {
+                warn!(run_id = %run.id, error = %e, "failed to emit PARALLEL_TASKS_SERIAL event");
+            }
+            return Ok(None);
+        }
+    };
+
+    info!(
+        run_id = %run.id,
+        tasks = graph.unfinished().count(),
+        "fanning plan tasks out into child runs"
+    );
+    let parent = subruns::Parent {
+        run,
+        worktree,
+        working_dir,
+        config,
+    };
+    let outcome = subruns::supervise(scheduler, storage, &parent, &graph, cancel_token).await?;
+    Ok(Some(outcome))
+}
+
 /// Inner implementation of process_run, separated so the per-run token
 /// cleanup in the outer function runs on all exit paths.
 async fn process_run_inner(
     scheduler: Arc<Scheduler>,
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     run: loop_core::Run,
     cancel_token: tokio_util::sync::CancellationToken,
     skills_metrics: Arc<SkillsMetrics>,
+    record_fixtures: Option<PathBuf>,
 ) -> AppResult<()> {
     info!(
         run_id = %run.id,
@@ -1300,6 +1688,16 @@ async fn process_run_inner(
     // This handles daemon restarts by rebuilding state from persisted steps.
     let steps = storage.list_steps(&run.id).await?;
     let mut consecutive_failures = ConsecutiveFailures::from_steps(&steps);
+    // Agent session continued by implementation iterations (session_resume).
+    let mut sessions = SessionTracker::from_config(&config);
+    sessions.restore(&steps);
+    let mut review_feedback: Option<String> = None;
+    // Wall-clock budget counts from the first step, so it survives daemon restarts.
+    let run_started_at = steps
+        .iter()
+        .filter_map(|step| step.started_at)
+        .min()
+        .unwrap_or_else(Utc::now);
     info!(
         run_id = %run.id,
         verification = consecutive_failures.verification,
@@ -1490,9 +1888,44 @@ async fn process_run_inner(
 
     // Create runners and verifier from config.
     // Implementation and review may use different models (review_model config key).
-    let runner = Runner::new(RunnerConfig::from_config(&config));
-    let review_runner = Runner::new(RunnerConfig::from_config_for_review(&config));
-    let verifier = Verifier::new(VerifierConfig::from_config(&config));
+    // Both publish output to the storage bus so SSE clients can tail live steps,
+    // and both launch through the daemon-wide API rate-limit governor.
+    let mut runner = Runner::new(RunnerConfig::from_config(&config))
+        .with_output_bus(storage.bus().clone())
+        .with_governor(Arc::clone(scheduler.governor()));
+    let mut review_runner = Runner::new(RunnerConfig::from_config_for_review(&config))
+        .with_output_bus(storage.bus().clone())
+        .with_governor(Arc::clone(scheduler.governor()));
+    // Both runners share one bundle so it keeps the order steps actually ran in.
+    if let Some(dir) = record_fixtures {
+        let dir = dir.join(&run.id.0);
+        match FixtureRecorder::new(dir.clone()) {
+            Ok(recorder) => {
+                info!(run_id = %run.id, fixture = %dir.display(), "recording agent invocations");
+                let recorder = Arc::new(recorder);
+                runner = runner.with_recorder(Arc::clone(&recorder));
+                review_runner = review_runner.with_recorder(recorder);
+            }
+            Err(e) => warn!(
+                run_id = %run.id,
+                fixture = %dir.display(),
+                error = %e,
+                "failed to start fixture recording"
+            ),
+        }
+    }
+    let mut verifier = Verifier::new(VerifierConfig::from_config(&config));
+    // In distributed mode all three hand their steps to workers instead.
+    if scheduler.is_distributed() {
+        let dispatch = Arc::new(StepDispatch::new(
+            Arc::clone(&storage),
+            config.clone(),
+            cancel_token.clone(),
+        ));
+        runner = runner.with_dispatch(Arc::clone(&dispatch));
+        review_runner = review_runner.with_dispatch(Arc::clone(&dispatch));
+        verifier = verifier.with_dispatch(dispatch);
+    }
     let watchdog = Watchdog::with_defaults();
 
     let mut previous_outputs: Vec<String> = Vec::new();
@@ -1510,6 +1943,17 @@ async fn process_run_inner(
     // Track last exit code for summary.json (postmortem-analysis.md Section 3).
     let mut last_exit_code: i32 = 0;
 
+    // Child runs implement a single plan task from their own plan copy and
+    // never fan out themselves.
+    let subrun_task_id = match storage.get_run_child(&run.id).await? {
+        Some(link) => {
+            subruns::seed_child_plan(&*storage, &link, &run).await?;
+            Some(link.task_id)
+        }
+        None => None,
+    };
+    let mut fan_out_checked = false;
+
     // Main phase loop.
     loop {
         // Check iteration limit.
@@ -1522,7 +1966,7 @@ async fn process_run_inner(
             );
             // Write report + summary.json before emitting events (postmortem-analysis.md Section 5.1).
             finalize_run_artifacts(
-                &storage,
+                &*storage,
                 &run,
                 &config,
                 ExitReason::IterationsExhausted,
@@ -1541,7 +1985,7 @@ async fn process_run_inner(
                 .await?;
             // Run postmortem analysis (postmortem-analysis.md Section 5.1).
             maybe_run_postmortem(
-                &storage,
+                &*storage,
                 &run,
                 &config,
                 iteration_count,
@@ -1552,6 +1996,52 @@ async fn process_run_inner(
             break;
         }
 
+        // Check budget limits between steps.
+        if let Some(breach) =
+            find_budget_breach(&*storage, &scheduler, &run.id, &config, run_started_at).await?
+        {
+            warn!(
+                run_id = %run.id,
+                limit = breach.limit,
+                used = breach.used,
+                max = breach.max,
+                "budget exceeded"
+            );
+            finalize_run_artifacts(
+                &*storage,
+                &run,
+                &config,
+                ExitReason::Failed,
+                last_exit_code,
+                Some(config.completion_mode.as_str()),
+            )
+            .await;
+            let budget_payload = EventPayload::BudgetExceeded(BudgetExceededPayload {
+                run_id: run.id.clone(),
+                limit: breach.limit.to_string(),
+                used: breach.used,
+                max: breach.max,
+            });
+            storage.append_event(&run.id, None, &budget_payload).await?;
+            let event_payload = EventPayload::RunFailed(RunFailedPayload {
+                run_id: run.id.clone(),
+                reason: format!("budget_exceeded:{}", breach.limit),
+            });
+            scheduler
+                .complete_run(&run.id, loop_core::RunStatus::Failed, &event_payload)
+                .await?;
+            maybe_run_postmortem(
+                &*storage,
+                &run,
+                &config,
+                iteration_count,
+                None,
+                "budget_exceeded",
+            )
+            .await;
+            break;
+        }
+
         // Determine the next phase.
         let next_phase = scheduler.determine_next_phase(&run.id).await?;
 
@@ -1560,7 +2050,7 @@ async fn process_run_inner(
             info!("run complete: {}", run.id);
             // Write report + summary.json before emitting events (postmortem-analysis.md Section 5.1).
             finalize_run_artifacts(
-                &storage,
+                &*storage,
                 &run,
                 &config,
                 ExitReason::CompletePlan,
@@ -1579,7 +2069,7 @@ async fn process_run_inner(
                 .await?;
             // Run postmortem analysis (postmortem-analysis.md Section 5.1).
             maybe_run_postmortem(
-                &storage,
+                &*storage,
                 &run,
                 &config,
                 iteration_count,
@@ -1595,7 +2085,7 @@ async fn process_run_inner(
         if current_run.status == loop_core::RunStatus::Canceled {
             info!(run_id = %run.id, "run canceled; stopping execution");
             finalize_run_artifacts(
-                &storage,
+                &*storage,
                 &run,
                 &config,
                 ExitReason::Canceled,
@@ -1604,7 +2094,7 @@ async fn process_run_inner(
             )
             .await;
             maybe_run_postmortem(
-                &storage,
+                &*storage,
                 &run,
                 &config,
                 iteration_count,
@@ -1623,6 +2113,148 @@ async fn process_run_inner(
             break;
         }
 
+        // Fan independent plan tasks out into child runs instead of implementing
+        // them here. Decided once, when the run first reaches implementation.
+        if phase == StepPhase::Implementation && !fan_out_checked {
+            fan_out_checked = true;
+            let outcome = supervise_subruns(
+                &scheduler,
+                &*storage,
+                &run,
+                &config,
+                subrun_task_id.is_some(),
+                &working_dir,
+                &cancel_token,
+            )
+            .await?;
+            if let Some(outcome) = outcome {
+                match outcome {
+                    SupervisionOutcome::Completed { merged } => {
+                        info!(run_id = %run.id, merged, "all child runs merged");
+                        let needs_merge = run.worktree.as_ref().is_some_and(|wt| {
+                            wt.merge_target_branch.is_some()
+                                && wt.merge_strategy != MergeStrategy::None
+                        });
+                        if needs_merge {
+                            if let Err(e) = execute_merge(&run, &workspace_root) {
+                                error!(run_id = %run.id, error = %e, "merge failed");
+                                finalize_run_artifacts(
+                                    &*storage,
+                                    &run,
+                                    &config,
+                                    ExitReason::Failed,
+                                    last_exit_code,
+                                    Some(config.completion_mode.as_str()),
+                                )
+                                .await;
+                                let event_payload = EventPayload::RunFailed(RunFailedPayload {
+                                    run_id: run.id.clone(),
+                                    reason: format!("merge_failed:{e}"),
+                                });
+                                scheduler
+                                    .complete_run(
+                                        &run.id,
+                                        loop_core::RunStatus::Failed,
+                                        &event_payload,
+                                    )
+                                    .await?;
+                                maybe_run_postmortem(
+                                    &*storage,
+                                    &run,
+                                    &config,
+                                    iteration_count,
+                                    None,
+                                    "merge_failed",
+                                )
+                                .await;
+                                break;
+                            }
+                        }
+                        finalize_run_artifacts(
+                            &*storage,
+                            &run,
+                            &config,
+                            ExitReason::CompletePlan,
+                            last_exit_code,
+                            Some(config.completion_mode.as_str()),
+                        )
+                        .await;
+                        let mode = if needs_merge {
+                            "merge"
+                        } else {
+                            "parallel_tasks"
+                        };
+                        let event_payload = EventPayload::RunCompleted(RunCompletedPayload {
+                            run_id: run.id.clone(),
+                            mode: mode.to_string(),
+                        });
+                        scheduler
+                            .complete_run(&run.id, loop_core::RunStatus::Completed, &event_payload)
+                            .await?;
+                        maybe_run_postmortem(
+                            &*storage,
+                            &run,
+                            &config,
+                            iteration_count,
+                            Some(iteration_count),
+                            "run_completed",
+                        )
+                        .await;
+                    }
+                    SupervisionOutcome::Failed(reason) => {
+                        warn!(run_id = %run.id, reason = %reason, "child runs failed");
+                        finalize_run_artifacts(
+                            &*storage,
+                            &run,
+                            &config,
+                            ExitReason::Failed,
+                            last_exit_code,
+                            Some(config.completion_mode.as_str()),
+                        )
+                        .await;
+                        let event_payload = EventPayload::RunFailed(RunFailedPayload {
+                            run_id: run.id.clone(),
+                            reason,
+                        });
+                        scheduler
+                            .complete_run(&run.id, loop_core::RunStatus::Failed, &event_payload)
+                            .await?;
+                        maybe_run_postmortem(
+                            &*storage,
+                            &run,
+                            &config,
+                            iteration_count,
+                            None,
+                            "subrun_failed",
+                        )
+                        .await;
+                    }
+                    SupervisionOutcome::Canceled => {
+                        info!(run_id = %run.id, "run canceled while supervising child runs");
+                        finalize_run_artifacts(
+                            &*storage,
+                            &run,
+                            &config,
+                            ExitReason::Canceled,
+                            last_exit_code,
+                            Some(config.completion_mode.as_str()),
+                        )
+                        .await;
+                        maybe_run_postmortem(
+                            &*storage,
+                            &run,
+                            &config,
+                            iteration_count,
+                            None,
+                            "run_canceled",
+                        )
+                        .await;
+                    }
+                }
+                break;
+            }
+        }
+
         let step = scheduler.enqueue_step(&run.id, phase).await?;
         info!(
             run_name = %run.name,
@@ -1645,15 +2277,191 @@ async fn process_run_inner(
             .await?;
 
         match phase {
+            StepPhase::Planning => {
+                // The plan path is stored in workspace form like a user-supplied
+                // plan; the agent writes the worktree copy.
+                let plan_path = generated_plan_path(&run, &config);
+                let plan_path_str = plan_path.to_string_lossy().to_string();
+                let plan_file = run.worktree.as_ref().map_or_else(
+                    || plan_path.clone(),
+                    |wt| {
+                        PathBuf::from(remap_to_worktree(
+                            &plan_path_str,
+                            &run.workspace_root,
+                            &wt.worktree_path,
+                        ))
+                    },
+                );
+
+                let prompt = build_planning_prompt(&run, &plan_file);
+                std::fs::write(run_dir.join("planning-prompt.txt"), &prompt)?;
+                info!(
+                    step_id = %step.id,
+                    plan = %plan_file.display(),
+                    "planning phase generating plan"
+                );
+
+                let step_outcome = runner
+                    .execute_step(&step, &prompt, &run_dir, &working_dir, cancel_token.clone())
+                    .await;
+                record_step_usage(&*storage, &runner, &run.id, &step.id).await;
+                record_step_transcript(&*storage, &runner, &run.id, &step.id).await;
+                record_permission_denials(&*storage, &runner, &run.id, &step.id).await;
+                let violations = runner.take_sandbox_violations();
+                record_sandbox_violations(&*storage, &run.id, &step.id, config.sandbox, violations)
+                    .await;
+                let failure = match step_outcome {
+                    Ok(result) => {
+                        last_exit_code = result.exit_code;
+                        mirror_step_log(
+                            &*storage,
+                            &config,
+                            &run.id,
+                            "planning_output",
+                            &result.output_path,
+                        )
+                        .await?;
+
+                        match validate_generated_plan(&plan_file) {
+                            Ok(pending) => {
+                                scheduler
+                                    .complete_step(
+                                        &step.id,
+                                        StepStatus::Succeeded,
+                                        Some(result.exit_code),
+                                        Some(result.output_path.to_string_lossy().as_ref()),
+                                    )
+                                    .await?;
+                                let event_payload =
+                                    EventPayload::StepFinished(StepFinishedPayload {
+                                        step_id: step.id.clone(),
+                                        exit_code: result.exit_code,
+                                        duration_ms: result.duration_ms,
+                                        output_path: result
+                                            .output_path
+                                            .to_string_lossy()
+                                            .to_string(),
+                                    });
+                                storage
+                                    .append_event(&run.id, Some(&step.id), &event_payload)
+                                    .await?;
+
+                                let plan_artifacts = mirror_artifact(
+                                    &run.id,
+                                    "plan",
+                                    &plan_file,
+                                    &config.global_log_dir,
+                                    config.artifact_mode,
+                                )?;
+                                insert_artifacts(&*storage, plan_artifacts).await?;
+                                storage
+                                    .update_run_plan_path(&run.id, &plan_path_str)
+                                    .await?;
+                                run.plan_path = Some(plan_path_str);
+                                info!(run_id = %run.id, pending, "generated plan accepted");
+                                None
+                            }
+                            Err(reason) => {
+                                scheduler
+                                    .complete_step(
+                                        &step.id,
+                                        StepStatus::Failed,
+                                        Some(result.exit_code),
+                                        Some(result.output_path.to_string_lossy().as_ref()),
+                                    )
+                                    .await?;
+                                Some(reason)
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let fail_exit_code = match &e {
+                            RunnerError::ExitCode { code, .. }
+                            | RunnerError::TransientApiError { code, .. }
+                            | RunnerError::ResourceLimit { code, .. } => Some(*code),
+                            _ => None,
+                        };
+                        note_resource_limit(&run_dir, &e);
+                        scheduler
+                            .complete_step(&step.id, StepStatus::Failed, fail_exit_code, None)
+                            .await?;
+                        if let Some(code) = fail_exit_code {
+                            last_exit_code = code;
+                        }
+                        Some(format!("runner_execution_failed:{e}"))
+                    }
+                };
+
+                if let Some(reason) = failure {
+                    error!(run_id = %run.id, reason = %reason, "planning phase failed");
+                    finalize_run_artifacts(
+                        &*storage,
+                        &run,
+                        &config,
+                        ExitReason::Failed,
+                        last_exit_code,
+                        Some(config.completion_mode.as_str()),
+                    )
+                    .await;
+                    let event_payload = EventPayload::RunFailed(RunFailedPayload {
+                        run_id: run.id.clone(),
+                        reason: format!("planning_failed:{reason}"),
+                    });
+                    scheduler
+                        .complete_run(&run.id, loop_core::RunStatus::Failed, &event_payload)
+                        .await?;
+                    maybe_run_postmortem(
+                        &*storage,
+                        &run,
+                        &config,
+                        iteration_count,
+                        None,
+                        "planning_failed",
+                    )
+                    .await;
+                    break;
+                }
+            }
+
             StepPhase::Implementation => {
                 iteration_count += 1;
 
+                // Continue the previous iteration's session when enabled.
+                let resume_session = sessions.begin_iteration();
+                runner.set_resume_session(resume_session.clone());
+                // Only a freshly built prompt selects skills; resumed and
+                // rewritten prompts must not inherit the last step's tools.
+                runner.set_step_allowed_tools(Vec::new());
+                let feedback = review_feedback.take();
+
                 // Build and write prompt.
                 let (prompt, prompt_path) = if let Some(rewrite) = pending_rewrite.take() {
                     (rewrite.content.clone(), rewrite.prompt_after.clone())
+                } else if let Some(session_id) = &resume_session {
+                    let runner_notes = std::fs::read_to_string(run_dir.join("runner-notes.txt"))
+                        .unwrap_or_default();
+                    let prompt = session::build_resume_prompt(
+                        &runner_notes,
+                        feedback.as_deref(),
+                        subrun_task_id.as_deref(),
+                    );
+                    let prompt_path = run_dir.join("resume-prompt.txt");
+                    std::fs::write(&prompt_path, &prompt)?;
+                    info!(step_id = %step.id, session_id = %session_id, "resuming agent session");
+                    (prompt, prompt_path)
                 } else {
                     let (prompt, skill_selection, truncation_events, load_failure_events) =
-                        build_implementation_prompt(&run, &run_dir, &config, &discovered_skills);
+                        build_implementation_prompt(
+                            &run,
+                            &run_dir,
+                            &config,
+                            &discovered_skills,
+                            subrun_task_id.as_deref(),
+                        );
+                    runner.set_step_allowed_tools(selected_skill_tools(
+                        skill_selection.as_ref(),
+                        &discovered_skills,
+                    ));
 
                     // Emit SKILLS_LOAD_FAILED events and increment metrics per Section 4.3 / 7.2.
                     for failure in &load_failure_events {
@@ -1760,11 +2568,14 @@ async fn process_run_inner(
                         &config.global_log_dir,
                         config.artifact_mode,
                     )?;
-                    insert_artifacts(&storage, artifacts).await?;
+                    insert_artifacts(&*storage, artifacts).await?;
                     (prompt, run_dir.join("prompt.txt"))
                 };
 
-                last_prompt = Some(prompt.clone());
+                // Watchdog rewrites start from the last full prompt, not a delta.
+                if resume_session.is_none() {
+                    last_prompt = Some(prompt.clone());
+                }
 
                 info!(
                     step_id = %step.id,
@@ -1776,10 +2587,26 @@ async fn process_run_inner(
                 let head_before = git::get_head_commit(&working_dir).ok();
 
                 // Execute via runner.
-                match runner
+                let step_outcome = runner
                     .execute_step(&step, &prompt, &run_dir, &working_dir, cancel_token.clone())
-                    .await
-                {
+                    .await;
+                record_step_usage(&*storage, &runner, &run.id, &step.id).await;
+                record_step_transcript(&*storage, &runner, &run.id, &step.id).await;
+                record_permission_denials(&*storage, &runner, &run.id, &step.id).await;
+                let violations = runner.take_sandbox_violations();
+                record_sandbox_violations(&*storage, &run.id, &step.id, config.sandbox, violations)
+                    .await;
+                let session_id = step_outcome
+                    .as_ref()
+                    .ok()
+                    .and_then(|result| result.session_id.clone());
+                if let Some(session_id) = &session_id {
+                    if let Err(e) = storage.set_step_session(&step.id, session_id).await {
+                        warn!(step_id = %step.id, error = %e, "failed to record agent session");
+                    }
+                }
+                sessions.finish_iteration(session_id, step_outcome.is_ok());
+                match step_outcome {
                     Ok(result) => {
                         // Track last exit code for summary.json.
                         last_exit_code = result.exit_code;
@@ -1819,14 +2646,14 @@ async fn process_run_inner(
                             .await?;
 
                         // Persist output artifacts (mirror if configured).
-                        let output_artifacts = mirror_artifact(
+                        mirror_step_log(
+                            &*storage,
+                            &config,
                             &run.id,
                             "implementation_output",
                             &result.output_path,
-                            &config.global_log_dir,
-                            config.artifact_mode,
-                        )?;
-                        insert_artifacts(&storage, output_artifacts).await?;
+                        )
+                        .await?;
 
                         let tail_artifacts = mirror_artifact(
                             &run.id,
@@ -1835,7 +2662,7 @@ async fn process_run_inner(
                             &config.global_log_dir,
                             config.artifact_mode,
                         )?;
-                        insert_artifacts(&storage, tail_artifacts).await?;
+                        insert_artifacts(&*storage, tail_artifacts).await?;
 
                         // Track output for watchdog evaluation after verification.
                         last_output = Some(result.output.clone());
@@ -1874,7 +2701,7 @@ async fn process_run_inner(
                                     );
                                     // Write report + summary.json before emitting events.
                                     finalize_run_artifacts(
-                                        &storage,
+                                        &*storage,
                                         &run,
                                         &config,
                                         ExitReason::Failed,
@@ -1895,7 +2722,7 @@ async fn process_run_inner(
                                         .await?;
                                     // Run postmortem analysis (postmortem-analysis.md Section 5.1).
                                     maybe_run_postmortem(
-                                        &storage,
+                                        &*storage,
                                         &run,
                                         &config,
                                         iteration_count,
@@ -1913,7 +2740,7 @@ async fn process_run_inner(
 
                             // Write report + summary.json before emitting events (postmortem-analysis.md Section 5.1).
                             finalize_run_artifacts(
-                                &storage,
+                                &*storage,
                                 &run,
                                 &config,
                                 ExitReason::CompletePlan,
@@ -1941,7 +2768,7 @@ async fn process_run_inner(
                                 .await?;
                             // Run postmortem analysis (postmortem-analysis.md Section 5.1).
                             maybe_run_postmortem(
-                                &storage,
+                                &*storage,
                                 &run,
                                 &config,
                                 iteration_count,
@@ -1963,8 +2790,12 @@ async fn process_run_inner(
                             RunnerError::TransientApiError { code, output_tail } => {
                                 (Some(*code), Some(output_tail.as_str()))
                             }
+                            RunnerError::ResourceLimit {
+                                code, output_tail, ..
+                            } => (Some(*code), Some(output_tail.as_str())),
                             _ => (None, None),
                         };
+                        note_resource_limit(&run_dir, &e);
 
                         error!(
                             step_id = %step.id,
@@ -1983,7 +2814,7 @@ async fn process_run_inner(
 
                         // Write report + summary.json before emitting events.
                         finalize_run_artifacts(
-                            &storage,
+                            &*storage,
                             &run,
                             &config,
                             ExitReason::ClaudeFailed,
@@ -2002,7 +2833,7 @@ async fn process_run_inner(
                             .await?;
                         // Run postmortem analysis (postmortem-analysis.md Section 5.1).
                         maybe_run_postmortem(
-                            &storage,
+                            &*storage,
                             &run,
                             &config,
                             iteration_count,
@@ -2018,7 +2849,16 @@ async fn process_run_inner(
             StepPhase::Review => {
                 // Build review prompt.
                 let (prompt, skill_selection, truncation_events, load_failure_events) =
-                    build_review_prompt(&run, &config, &discovered_skills);
+                    build_review_prompt(
+                        &run,
+                        &config,
+                        &discovered_skills,
+                        subrun_task_id.as_deref(),
+                    );
+                review_runner.set_step_allowed_tools(selected_skill_tools(
+                    skill_selection.as_ref(),
+                    &discovered_skills,
+                ));
 
                 // Emit SKILLS_LOAD_FAILED events and increment metrics per Section 4.3 / 7.2.
                 for failure in &load_failure_events {
@@ -2123,10 +2963,16 @@ async fn process_run_inner(
                 let head_before = git::get_head_commit(&working_dir).ok();
 
                 // Execute via review runner (may use a different model).
-                match review_runner
+                let step_outcome = review_runner
                     .execute_step(&step, &prompt, &run_dir, &working_dir, cancel_token.clone())
-                    .await
-                {
+                    .await;
+                record_step_usage(&*storage, &review_runner, &run.id, &step.id).await;
+                record_step_transcript(&*storage, &review_runner, &run.id, &step.id).await;
+                record_permission_denials(&*storage, &review_runner, &run.id, &step.id).await;
+                let violations = review_runner.take_sandbox_violations();
+                record_sandbox_violations(&*storage, &run.id, &step.id, config.sandbox, violations)
+                    .await;
+                match step_outcome {
                     Ok(result) => {
                         // Log diff stats for this review iteration.
                         if let Some(ref before) = head_before {
@@ -2161,15 +3007,18 @@ async fn process_run_inner(
                             .append_event(&run.id, Some(&step.id), &event_payload)
                             .await?;
 
+                        // Passed on to the next iteration when it resumes the session.
+                        review_feedback = session::review_feedback(&result.output);
+
                         // Persist review output artifacts (mirror if configured).
-                        let output_artifacts = mirror_artifact(
+                        mirror_step_log(
+                            &*storage,
+                            &config,
                             &run.id,
                             "review_output",
                             &result.output_path,
-                            &config.global_log_dir,
-                            config.artifact_mode,
-                        )?;
-                        insert_artifacts(&storage, output_artifacts).await?;
+                        )
+                        .await?;
 
                         let tail_artifacts = mirror_artifact(
                             &run.id,
@@ -2178,7 +3027,7 @@ async fn process_run_inner(
                             &config.global_log_dir,
                             config.artifact_mode,
                         )?;
-                        insert_artifacts(&storage, tail_artifacts).await?;
+                        insert_artifacts(&*storage, tail_artifacts).await?;
 
                         // Update consecutive failure counter (reset on success).
                         consecutive_failures.update(StepPhase::Review, StepStatus::Succeeded);
@@ -2188,9 +3037,11 @@ async fn process_run_inner(
                     Err(e) => {
                         let fail_exit_code = match &e {
                             RunnerError::ExitCode { code, .. }
-                            | RunnerError::TransientApiError { code, .. } => Some(*code),
+                            | RunnerError::TransientApiError { code, .. }
+                            | RunnerError::ResourceLimit { code, .. } => Some(*code),
                             _ => None,
                         };
+                        note_resource_limit(&run_dir, &e);
 
                         error!(
                             step_id = %step.id,
@@ -2217,7 +3068,7 @@ async fn process_run_inner(
                                 "consecutive failure threshold reached"
                             );
                             finalize_run_artifacts(
-                                &storage,
+                                &*storage,
                                 &run,
                                 &config,
                                 ExitReason::Failed,
@@ -2235,7 +3086,7 @@ async fn process_run_inner(
                                 .complete_run(&run.id, loop_core::RunStatus::Failed, &event_payload)
                                 .await?;
                             maybe_run_postmortem(
-                                &storage,
+                                &*storage,
                                 &run,
                                 &config,
                                 iteration_count,
@@ -2280,6 +3131,27 @@ async fn process_run_inner(
                             .append_event(&run.id, Some(&step.id), &event_payload)
                             .await?;
 
+                        let violations = result
+                            .commands
+                            .iter()
+                            .filter_map(|command| {
+                                Some(SandboxViolation {
+                                    process: SandboxedProcess::Verification,
+                                    command: Some(command.cmd.clone()),
+                                    exit_code: Some(command.exit_code),
+                                    detail: command.sandbox_violation.clone()?,
+                                })
+                            })
+                            .collect();
+                        record_sandbox_violations(
+                            &*storage,
+                            &run.id,
+                            &step.id,
+                            config.sandbox,
+                            violations,
+                        )
+                        .await;
+
                         // Mirror runner notes artifact if present.
                         if let Some(notes_path) = result.runner_notes_path.as_ref() {
                             let note_artifacts = mirror_artifact(
@@ -2289,7 +3161,7 @@ async fn process_run_inner(
                                 &config.global_log_dir,
                                 config.artifact_mode,
                             )?;
-                            insert_artifacts(&storage, note_artifacts).await?;
+                            insert_artifacts(&*storage, note_artifacts).await?;
                         }
 
                         if result.passed {
@@ -2326,7 +3198,7 @@ async fn process_run_inner(
                                 );
                                 // Write report + summary.json before emitting events.
                                 finalize_run_artifacts(
-                                    &storage,
+                                    &*storage,
                                     &run,
                                     &config,
                                     ExitReason::Failed,
@@ -2353,7 +3225,7 @@ async fn process_run_inner(
                                     .await?;
                                 // Run postmortem analysis (postmortem-analysis.md Section 5.1).
                                 maybe_run_postmortem(
-                                    &storage,
+                                    &*storage,
                                     &run,
                                     &config,
                                     iteration_count,
@@ -2398,9 +3270,10 @@ async fn process_run_inner(
                                         &config.global_log_dir,
                                         config.artifact_mode,
                                     )?;
-                                    insert_artifacts(&storage, rewrite_artifacts).await?;
+                                    insert_artifacts(&*storage, rewrite_artifacts).await?;
 
                                     // Emit WATCHDOG_REWRITE event.
+                                    scheduler.metrics().inc_watchdog_rewrite(decision.signal);
                                     let payload =
                                         EventPayload::WatchdogRewrite(WatchdogRewritePayload {
                                             step_id: step.id.clone(),
@@ -2419,10 +3292,11 @@ async fn process_run_inner(
                                         .await?;
 
                                     pending_rewrite = Some(rewrite);
+                                    sessions.reset(SessionReset::WatchdogRewrite);
                                 } else if decision.action == WatchdogAction::Fail.as_str() {
                                     // Write report + summary.json before emitting events.
                                     finalize_run_artifacts(
-                                        &storage,
+                                        &*storage,
                                         &run,
                                         &config,
                                         ExitReason::Failed,
@@ -2444,7 +3318,7 @@ async fn process_run_inner(
                                         .await?;
                                     // Run postmortem analysis (postmortem-analysis.md Section 5.1).
                                     maybe_run_postmortem(
-                                        &storage,
+                                        &*storage,
                                         &run,
                                         &config,
                                         iteration_count,
@@ -2520,7 +3394,7 @@ async fn process_run_inner(
                             .await?;
                         // Write report + summary.json before emitting events.
                         finalize_run_artifacts(
-                            &storage,
+                            &*storage,
                             &run,
                             &config,
                             ExitReason::Failed,
@@ -2538,7 +3412,7 @@ async fn process_run_inner(
                             .await?;
                         // Run postmortem analysis (postmortem-analysis.md Section 5.1).
                         maybe_run_postmortem(
-                            &storage,
+                            &*storage,
                             &run,
                             &config,
                             iteration_count,
@@ -2711,16 +3585,204 @@ fn execute_merge(run: &loop_core::Run, workspace_root: &Path) -> Result<(), git:
     )
 }
 
-async fn insert_artifacts(storage: &Storage, artifacts: Vec<Artifact>) -> AppResult<()> {
+/// Persist token usage the runner accumulated for a step.
+///
+/// Failures are logged rather than propagated: accounting must not fail a run.
+async fn record_step_usage(
+    storage: &dyn StorageBackend,
+    runner: &Runner,
+    run_id: &Id,
+    step_id: &Id,
+) {
+    let usage = runner.take_usage();
+    if usage.is_empty() {
+        return;
+    }
+    if let Err(e) = storage
+        .record_step_usage(run_id, step_id, Some(runner.model()), &usage)
+        .await
+    {
+        warn!(
+            run_id = %run_id,
+            step_id = %step_id,
+            error = %e,
+            "failed to record step usage"
+        );
+    }
+}
+
+/// Persist the transcript the runner built for a step, whatever its outcome,
+/// along with the audited agent actions derived from it.
+async fn record_step_transcript(
+    storage: &dyn StorageBackend,
+    runner: &Runner,
+    run_id: &Id,
+    step_id: &Id,
+) {
+    let entries = runner.take_transcript();
+    if entries.is_empty() {
+        return;
+    }
+    if let Err(e) = storage
+        .append_transcript_entries(run_id, step_id, &entries)
+        .await
+    {
+        warn!(
+            run_id = %run_id,
+            step_id = %step_id,
+            error = %e,
+            "failed to record step transcript"
+        );
+    }
+
+    let actions = loop_core::audit::actions_from_transcript(run_id, step_id, &entries);
+    if actions.is_empty() {
+        return;
+    }
+    if let Err(e) = storage.record_agent_actions(&actions).await {
+        warn!(
+            run_id = %run_id,
+            step_id = %step_id,
+            error = %e,
+            "failed to record agent actions"
+        );
+    }
+}
+
+/// Tool patterns from the `allowed_tools` of the skills selected for a step.
+fn selected_skill_tools(
+    selection: Option<&SkillSelection>,
+    available_skills: &[SkillMetadata],
+) -> Vec<String> {
+    let Some(selection) = selection else {
+        return Vec::new();
+    };
+    selection
+        .skills
+        .iter()
+        .filter_map(|selected| available_skills.iter().find(|s| s.name == selected.name))
+        .flat_map(|skill| skill.allowed_tools.iter().cloned())
+        .collect()
+}
+
+/// Emit `TOOL_PERMISSION_DENIED` for each tool call the policy denied during
+/// a step, with the tool input redacted like the action audit log.
+async fn record_permission_denials(
+    storage: &dyn StorageBackend,
+    runner: &Runner,
+    run_id: &Id,
+    step_id: &Id,
+) {
+    for denial in runner.take_permission_denials() {
+        warn!(
+            run_id = %run_id,
+            step_id = %step_id,
+            tool = %denial.tool_name,
+            "tool call denied by tool policy"
+        );
+        let payload = EventPayload::ToolPermissionDenied(ToolPermissionDeniedPayload {
+            run_id: run_id.clone(),
+            step_id: step_id.clone(),
+            tool_input: loop_core::audit::redact_arguments(&denial.tool_name, &denial.tool_input),
+            tool_name: denial.tool_name,
+            tool_use_id: denial.tool_use_id,
+        });
+        if let Err(e) = storage.append_event(run_id, Some(step_id), &payload).await {
+            warn!(
+                run_id = %run_id,
+                error = %e,
+                "failed to emit TOOL_PERMISSION_DENIED event"
+            );
+        }
+    }
+}
+
+/// Explain a resource limit hit in runner notes so the next attempt can adapt.
+fn note_resource_limit(run_dir: &Path, error: &RunnerError) {
+    if let RunnerError::ResourceLimit { limit, .. } = error {
+        if let Err(e) = Verifier::write_runner_notes(run_dir, &limit.runner_note()) {
+            warn!(error = %e, "failed to write runner notes for resource limit");
+        }
+    }
+}
+
+/// Emit `SANDBOX_VIOLATION` events for accesses the sandbox denied during a step.
+async fn record_sandbox_violations(
+    storage: &dyn StorageBackend,
+    run_id: &Id,
+    step_id: &Id,
+    sandbox: SandboxMode,
+    violations: Vec<SandboxViolation>,
+) {
+    for violation in violations {
+        warn!(
+            run_id = %run_id,
+            step_id = %step_id,
+            process = violation.process.as_str(),
+            detail = %violation.detail,
+            "sandbox denied access"
+        );
+        let payload = EventPayload::SandboxViolation(SandboxViolationPayload {
+            run_id: run_id.clone(),
+            step_id: step_id.clone(),
+            process: violation.process.as_str().to_string(),
+            sandbox,
+            command: violation.command,
+            exit_code: violation.exit_code,
+            detail: violation.detail,
+        });
+        if let Err(e) = storage.append_event(run_id, Some(step_id), &payload).await {
+            warn!(
+                run_id = %run_id,
+                error = %e,
+                "failed to emit SANDBOX_VIOLATION event"
+            );
+        }
+    }
+}
+
+async fn insert_artifacts(storage: &dyn StorageBackend, artifacts: Vec<Artifact>) -> AppResult<()> {
     for artifact in artifacts {
         storage.insert_artifact(&artifact).await?;
     }
     Ok(())
 }
 
+/// Mirror a finished step log, compressed or not, and cap the global copy.
+///
+/// The runner has already compressed the workspace log and applied the run's
+/// log cap there; the global mirror gets the same cap.
+async fn mirror_step_log(
+    storage: &dyn StorageBackend,
+    config: &Config,
+    run_id: &Id,
+    kind: &str,
+    output_path: &Path,
+) -> AppResult<()> {
+    let log = logs::resolve(output_path).unwrap_or_else(|| output_path.to_path_buf());
+    let artifacts = mirror_artifact(
+        run_id,
+        kind,
+        &log,
+        &config.global_log_dir,
+        config.artifact_mode,
+    )?;
+    insert_artifacts(storage, artifacts).await?;
+
+    if config.max_run_log_mb > 0 && config.artifact_mode != ArtifactMode::Workspace {
+        let global_dir = global_run_dir(&config.global_log_dir, run_id);
+        let max_bytes = config.max_run_log_mb.saturating_mul(1024 * 1024);
+        if let Err(e) = logs::enforce_size_cap(&global_dir, max_bytes) {
+            warn!(run_id = %run_id, error = %e, "failed to enforce run log cap on global mirror");
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::testing::TestPostgres;
     use loop_core::Step;
 
     fn make_step(phase: StepPhase, status: StepStatus) -> Step {
@@ -2732,9 +3794,10 @@ mod tests {
             attempt: 1,
             started_at: Some(Utc::now()),
             ended_at: Some(Utc::now()),
-            exit_code: Some(if status == StepStatus::Failed { 1 } else { 0 }),
+            exit_code: Some(i32::from(status == StepStatus::Failed)),
             prompt_path: None,
             output_path: None,
+            session_id: None,
         }
     }
 
@@ -2872,6 +3935,62 @@ mod tests {
         assert_eq!(limit, 2);
     }
 
+    #[test]
+    fn run_budget_disabled_by_default() {
+        let config = Config::default();
+        let usage = TokenUsage {
+            input_tokens: 10_000_000,
+            cost_usd: 1_000.0,
+            ..TokenUsage::default()
+        };
+        assert!(check_run_budget(&config, &usage, 1_000_000).is_none());
+    }
+
+    #[test]
+    fn run_budget_cost_exceeded() {
+        let mut config = Config::default();
+        config.max_run_cost_usd = 5.0;
+        config.max_run_tokens = 1_000_000;
+
+        let mut usage = TokenUsage {
+            input_tokens: 1000,
+            cost_usd: 4.99,
+            ..TokenUsage::default()
+        };
+        assert!(check_run_budget(&config, &usage, 0).is_none());
+
+        usage.cost_usd = 5.0;
+        let breach = check_run_budget(&config, &usage, 0).unwrap();
+        assert_eq!(breach.limit, "cost_usd");
+        assert!((breach.max - 5.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn run_budget_tokens_include_cache() {
+        let mut config = Config::default();
+        config.max_run_tokens = 1000;
+
+        let usage = TokenUsage {
+            input_tokens: 400,
+            output_tokens: 100,
+            cache_read_input_tokens: 500,
+            ..TokenUsage::default()
+        };
+        let breach = check_run_budget(&config, &usage, 0).unwrap();
+        assert_eq!(breach.limit, "tokens");
+        assert!((breach.used - 1000.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn run_budget_wall_clock_exceeded() {
+        let mut config = Config::default();
+        config.max_run_wall_clock_sec = 3600;
+
+        assert!(check_run_budget(&config, &TokenUsage::default(), 3599).is_none());
+        let breach = check_run_budget(&config, &TokenUsage::default(), 3600).unwrap();
+        assert_eq!(breach.limit, "wall_clock_sec");
+    }
+
     #[test]
     fn remap_to_worktree_rewrites_workspace_path() {
         let result = remap_to_worktree(
@@ -2901,4 +4020,317 @@ mod tests {
         );
         assert_eq!(result, "/home/user/project.run-abc/specs/plan.md");
     }
+
+    #[test]
+    fn remap_to_worktree_keeps_paths_already_in_a_sibling_worktree() {
+        let result = remap_to_worktree(
+            "/home/user/project.run-abc/.loop/subrun-plan.md",
+            "/home/user/project",
+            "/home/user/project.run-abc",
+        );
+        assert_eq!(result, "/home/user/project.run-abc/.loop/subrun-plan.md");
+    }
+
+    #[test]
+    fn child_run_prompt_is_scoped_to_its_task() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let plan_file = dir.path().join("plan.md");
+        std::fs::write(
+            &plan_file,
+            "- [ ] [types] Add types\n- [ ] [api] Add API (depends-on: types)\n",
+        )
+        .unwrap();
+        let mut run = planning_run(dir.path());
+        run.plan_path = Some(plan_file.to_string_lossy().to_string());
+
+        let (prompt, ..) =
+            build_implementation_prompt(&run, dir.path(), &Config::default(), &[], Some("api"));
+
+        assert!(prompt.contains("Implement only plan task `api`"));
+        assert!(prompt.contains("Do not edit the plan file"));
+        assert!(prompt.contains("> Add API"));
+        assert!(!prompt.contains("mark only the task(s) you completed with [x]"));
+        assert!(!prompt.contains("If (and only if) all"));
+
+        let (serial, ..) =
+            build_implementation_prompt(&run, dir.path(), &Config::default(), &[], None);
+        assert!(serial.contains("If (and only if) all"));
+        assert!(serial.contains("Spec alignment guardrails"));
+    }
+
+    fn planning_run(workspace_root: &Path) -> Run {
+        let now = Utc::now();
+        Run {
+            id: Id::new(),
+            name: "planning".to_string(),
+            name_source: loop_core::RunNameSource::SpecSlug,
+            status: loop_core::RunStatus::Running,
+            workspace_root: workspace_root.display().to_string(),
+            spec_path: workspace_root
+                .join("specs/Rate Limits.md")
+                .display()
+                .to_string(),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: now,
+            updated_at: now,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        }
+    }
+
+    #[test]
+    fn select_prompt_task_scopes_child_runs_to_their_task() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let plan_file = dir.path().join("plan.md");
+        std::fs::write(
+            &plan_file,
+            "- [ ] [types] Add types\n- [ ] [api] Add API (depends-on: types)\n",
+        )
+        .unwrap();
+
+        let serial = select_prompt_task(&plan_file, None).unwrap();
+        assert_eq!(serial.label, "Add types");
+        let child = select_prompt_task(&plan_file, Some("api")).unwrap();
+        assert_eq!(child.label, "Add API");
+        assert!(select_prompt_task(&plan_file, Some("missing")).is_none());
+    }
+
+    #[test]
+    fn generated_plan_path_uses_plans_dir_and_spec_slug() {
+        let workspace = Path::new("/repo");
+        let mut config = Config::default();
+        config.resolve_paths(workspace);
+        let path = generated_plan_path(&planning_run(workspace), &config);
+        assert_eq!(
+            path,
+            PathBuf::from("/repo/specs/planning/rate-limits-plan.md")
+        );
+    }
+
+    #[test]
+    fn planning_prompt_references_spec_and_plan_file() {
+        let run = planning_run(Path::new("/repo"));
+        let prompt = build_planning_prompt(&run, Path::new("/repo/specs/planning/x-plan.md"));
+        assert!(prompt.starts_with("@/repo/specs/Rate Limits.md"));
+        assert!(prompt.contains("Write the plan to /repo/specs/planning/x-plan.md"));
+        assert!(prompt.contains("`- [ ] <task>`"));
+    }
+
+    #[test]
+    fn validate_generated_plan_requires_pending_tasks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let plan = dir.path().join("plan.md");
+
+        let missing = validate_generated_plan(&plan).unwrap_err();
+        assert!(missing.starts_with("plan_not_written:"));
+
+        std::fs::write(&plan, "## Verification Checklist\n- [ ] run tests\n").unwrap();
+        let empty = validate_generated_plan(&plan).unwrap_err();
+        assert!(empty.starts_with("plan_has_no_tasks:"));
+
+        std::fs::write(
+            &plan,
+            "## Phase 1: Core\n- [ ] Add types\n- [ ] Add storage\n- [ ]? Manual check\n",
+        )
+        .unwrap();
+        assert_eq!(validate_generated_plan(&plan), Ok(2));
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Write a fixture invocation whose stream is a single assistant message.
+    fn scripted_invocation(
+        fixture: &Path,
+        phase: StepPhase,
+        text: &str,
+        files: &[(&str, &str)],
+        commit: Option<&str>,
+    ) -> replay::FixtureInvocation {
+        let index = std::fs::read_dir(fixture).unwrap().count();
+        let stream = PathBuf::from(format!("{index:03}-{}.jsonl", phase.slug()));
+        let event = serde_json::json!({
+            "type": "assistant",
+            "message": { "content": [{ "type": "text", "text": text }] },
+        });
+        std::fs::write(fixture.join(&stream), format!("{event}\n")).unwrap();
+        replay::FixtureInvocation {
+            phase,
+            stream,
+            format: backend::AgentOutputFormat::ClaudeStreamJson,
+            exit_code: 0,
+            files: files
+                .iter()
+                .map(|(path, content)| replay::FileMutation {
+                    path: PathBuf::from(path),
+                    content: Some((*content).to_string()),
+                })
+                .collect(),
+            commit: commit.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn daemon_opens_database_urls_and_rejects_unknown_schemes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("from-url.db");
+        let config = DaemonConfig {
+            database_url: Some(format!("sqlite://{}", db_path.display())),
+            ..Default::default()
+        };
+        Daemon::new(config).await.unwrap();
+        assert!(db_path.exists());
+
+        if let Some(postgres) = TestPostgres::start().await {
+            let config = DaemonConfig {
+                db_path: dir.path().join("unused.db"),
+                database_url: Some(postgres.url.clone()),
+                ..Default::default()
+            };
+            let daemon = Daemon::new(config).await.unwrap();
+            assert!(daemon.storage().list_runs(None).await.unwrap().is_empty());
+        }
+
+        let config = DaemonConfig {
+            db_path: dir.path().join("unused.db"),
+            database_url: Some("mysql://loopd@localhost/loopd".to_string()),
+            ..Default::default()
+        };
+        let err = Daemon::new(config).await.unwrap_err();
+        assert!(err.to_string().contains("mysql"));
+        assert!(!dir.path().join("unused.db").exists());
+    }
+
+    #[tokio::test]
+    async fn replayed_run_goes_through_full_lifecycle_and_merges() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let workspace = dir.path().join("repo");
+        let fixture = dir.path().join("fixture");
+        std::fs::create_dir_all(workspace.join("specs")).unwrap();
+        std::fs::create_dir_all(&fixture).unwrap();
+        git(&workspace, &["init", "-q", "-b", "main"]);
+        git(&workspace, &["config", "user.email", "test@test.com"]);
+        git(&workspace, &["config", "user.name", "Test"]);
+        std::fs::write(workspace.join(".gitignore"), "logs/\n").unwrap();
+        std::fs::write(workspace.join("specs/greeting.md"), "# Greeting\n").unwrap();
+        git(&workspace, &["add", "."]);
+        git(&workspace, &["commit", "-q", "-m", "Add spec"]);
+
+        // Iteration 1 forgets the file and fails verification; iteration 2
+        // adds it; iteration 3 declares completion.
+        let mut invocations = Vec::new();
+        for (phase, text, files, commit) in [
+            (StepPhase::Implementation, "Looked around.", &[][..], None),
+            (StepPhase::Review, "APPROVED", &[][..], None),
+            (
+                StepPhase::Implementation,
+                "Added the greeting.",
+                &[("greeting.txt", "hello\n")][..],
+                Some("Add greeting"),
+            ),
+            (StepPhase::Review, "APPROVED", &[][..], None),
+            (
+                StepPhase::Implementation,
+                "<promise>COMPLETE</promise>",
+                &[][..],
+                None,
+            ),
+        ] {
+            invocations.push(scripted_invocation(&fixture, phase, text, files, commit));
+        }
+        replay::FixtureManifest { invocations }
+            .save(&fixture)
+            .unwrap();
+
+        let config = Config {
+            agent_backend: loop_core::AgentBackendKind::Replay,
+            replay_fixture: Some(fixture.clone()),
+            verify_cmds: vec!["test -f greeting.txt".to_string()],
+            worktree_provider: WorktreeProvider::Git,
+            worktree_path_template: dir.path().join("worktree").display().to_string(),
+            merge_target_branch: Some("main".to_string()),
+            merge_strategy: MergeStrategy::Squash,
+            iterations: 10,
+            postmortem: false,
+            ..Config::default()
+        };
+        let storage = Storage::new(&dir.path().join("loopd.db"), 2).await.unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+        let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 2));
+        let mut run = planning_run(&workspace);
+        run.status = loop_core::RunStatus::Pending;
+        run.name = "greeting".to_string();
+        run.spec_path = workspace.join("specs/greeting.md").display().to_string();
+        run.config_json = Some(serde_json::to_string(&config).unwrap());
+        storage.insert_run(&run).await.unwrap();
+        let run = scheduler.claim_next_run().await.unwrap().unwrap();
+
+        let record = dir.path().join("recorded");
+        process_run(
+            Arc::clone(&scheduler),
+            Arc::clone(&storage),
+            run.clone(),
+            scheduler.cancel_token(),
+            Arc::new(SkillsMetrics::new()),
+            Some(record.clone()),
+        )
+        .await
+        .unwrap();
+
+        let finished = storage.get_run(&run.id).await.unwrap();
+        assert_eq!(finished.status, loop_core::RunStatus::Completed);
+        let phases: Vec<(StepPhase, StepStatus)> = storage
+            .list_steps(&run.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|step| (step.phase, step.status))
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                (StepPhase::Implementation, StepStatus::Succeeded),
+                (StepPhase::Review, StepStatus::Succeeded),
+                (StepPhase::Verification, StepStatus::Failed),
+                (StepPhase::Implementation, StepStatus::Succeeded),
+                (StepPhase::Review, StepStatus::Succeeded),
+                (StepPhase::Verification, StepStatus::Succeeded),
+                (StepPhase::Implementation, StepStatus::Succeeded),
+            ]
+        );
+        // The squash merge landed the agent's change on the target branch.
+        git(&workspace, &["checkout", "-q", "main"]);
+        assert_eq!(
+            std::fs::read_to_string(workspace.join("greeting.txt")).unwrap(),
+            "hello\n"
+        );
+
+        // Recording the replayed run reproduces the script.
+        let recorded = replay::FixtureManifest::load(&record.join(&run.id.0)).unwrap();
+        let script = replay::FixtureManifest::load(&fixture).unwrap();
+        assert_eq!(recorded.invocations.len(), script.invocations.len());
+        for (recorded, scripted) in recorded.invocations.iter().zip(&script.invocations) {
+            assert_eq!(recorded.phase, scripted.phase);
+            assert_eq!(recorded.files, scripted.files);
+            assert_eq!(recorded.commit, scripted.commit);
+        }
+    }
 }
@@ -8,8 +8,13 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+use std::path::PathBuf;
+
 use clap::Parser;
+use loop_core::{QueuePolicy, RetentionPolicy};
+use loopd::workers::{self, Worker, WorkerConfig};
 use loopd::{Daemon, DaemonConfig};
+use tokio_util::sync::CancellationToken;
 use tracing::error;
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -19,6 +24,79 @@ struct Cli {
     /// Port to listen on
     #[arg(short, long, default_value = "7700")]
     port: u16,
+
+    /// Database URL: sqlite://<path>, a bare path, or postgres://... (default: ~/.local/share/loopd/loopd.db)
+    #[arg(long, env = "LOOPD_DATABASE_URL")]
+    database_url: Option<String>,
+
+    /// Daily spend cap in USD across all workspaces (resets at UTC midnight)
+    #[arg(long, env = "LOOPD_MAX_DAILY_COST_USD")]
+    max_daily_cost_usd: Option<f64>,
+
+    /// TOML file with webhook notification rules
+    #[arg(long, env = "LOOPD_NOTIFICATIONS_CONFIG")]
+    notifications_config: Option<PathBuf>,
+
+    /// Save a replayable fixture bundle for every run under this directory
+    #[arg(long, env = "LOOPD_RECORD_FIXTURES")]
+    record_fixtures: Option<PathBuf>,
+
+    /// Order pending runs are claimed in: fifo, newest_first, or fair_share
+    #[arg(long, env = "LOOPD_QUEUE_POLICY", default_value = "fifo", value_parser = parse_queue_policy)]
+    queue_policy: QueuePolicy,
+
+    /// Fair-share weight for a workspace, as <workspace_root>=<weight> (repeatable)
+    #[arg(long = "workspace-weight", value_parser = parse_workspace_weight)]
+    workspace_weights: Vec<(String, u32)>,
+
+    /// Seconds a pending run waits per priority level gained (0 disables aging)
+    #[arg(long, env = "LOOPD_QUEUE_AGING_SEC", default_value_t = loopd::scheduler::DEFAULT_QUEUE_AGING_SEC)]
+    queue_aging_sec: u64,
+
+    /// Hand steps to registered workers instead of running them in-process
+    #[arg(long, env = "LOOPD_DISTRIBUTED", conflicts_with = "controller")]
+    distributed: bool,
+
+    /// Run as a worker that claims steps from the controller at this URL
+    #[arg(long, env = "LOOPD_CONTROLLER")]
+    controller: Option<String>,
+
+    /// Prune finished runs not updated for this many days
+    #[arg(long, env = "LOOPD_RETENTION_MAX_AGE_DAYS")]
+    retention_max_age_days: Option<u32>,
+
+    /// Keep at most this many finished runs per workspace; older ones are pruned
+    #[arg(long, env = "LOOPD_RETENTION_MAX_RUNS_PER_WORKSPACE")]
+    retention_max_runs_per_workspace: Option<u32>,
+
+    /// Keep completed runs whose branch is neither merged nor scrapped
+    #[arg(long, env = "LOOPD_RETENTION_KEEP_UNMERGED", default_value_t = true, action = clap::ArgAction::Set)]
+    retention_keep_unmerged: bool,
+
+    /// Keep failed runs for at least this many days
+    #[arg(long, env = "LOOPD_RETENTION_KEEP_FAILED_DAYS")]
+    retention_keep_failed_days: Option<u32>,
+}
+
+fn parse_queue_policy(value: &str) -> Result<QueuePolicy, String> {
+    match value {
+        "fifo" => Ok(QueuePolicy::Fifo),
+        "newest_first" => Ok(QueuePolicy::NewestFirst),
+        "fair_share" => Ok(QueuePolicy::FairShare),
+        _ => Err(format!(
+            "expected fifo, newest_first, or fair_share, got '{value}'"
+        )),
+    }
+}
+
+fn parse_workspace_weight(value: &str) -> Result<(String, u32), String> {
+    let (workspace, weight) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected <workspace_root>=<weight>, got '{value}'"))?;
+    match weight.parse() {
+        Ok(weight) if weight > 0 => Ok((workspace.to_string(), weight)),
+        _ => Err(format!("weight must be a positive integer, got '{weight}'")),
+    }
 }
 
 fn main() {
@@ -31,8 +109,27 @@ fn main() {
         )
         .init();
 
+    if let Some(controller_url) = cli.controller {
+        run_worker(controller_url);
+        return;
+    }
+
     let config = DaemonConfig {
         port: cli.port,
+        database_url: cli.database_url,
+        max_daily_cost_usd: cli.max_daily_cost_usd,
+        notifications_config: cli.notifications_config,
+        record_fixtures: cli.record_fixtures,
+        queue_policy: cli.queue_policy,
+        workspace_weights: cli.workspace_weights.into_iter().collect(),
+        queue_aging_sec: cli.queue_aging_sec,
+        distributed: cli.distributed,
+        retention: RetentionPolicy {
+            max_age_days: cli.retention_max_age_days,
+            max_runs_per_workspace: cli.retention_max_runs_per_workspace,
+            keep_unmerged: cli.retention_keep_unmerged,
+            keep_failed_days: cli.retention_keep_failed_days,
+        },
         ..Default::default()
     };
 
@@ -95,3 +192,44 @@ fn main() {
         }
     });
 }
+
+/// Worker mode: claim and execute steps until SIGINT/SIGTERM.
+fn run_worker(controller_url: String) {
+    let worker = Worker::new(WorkerConfig {
+        controller_url,
+        auth_token: std::env::var("LOOPD_AUTH_TOKEN").ok(),
+        hostname: workers::local_hostname(),
+        capabilities: vec!["shared_fs".to_string()],
+    });
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to create tokio runtime");
+
+    runtime.block_on(async {
+        let cancel = CancellationToken::new();
+        let shutdown = cancel.clone();
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            {
+                use tokio::signal::unix::{signal, SignalKind};
+                let mut sigterm =
+                    signal(SignalKind::terminate()).expect("failed to register SIGTERM handler");
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            #[cfg(not(unix))]
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("shutting down worker");
+            shutdown.cancel();
+        });
+
+        if let Err(e) = worker.run(cancel).await {
+            error!("worker error: {}", e);
+            std::process::exit(1);
+        }
+    });
+}
@@ -0,0 +1,408 @@
+//! Prometheus metrics for the daemon.
+//!
+//! Counters and histograms are plain atomics, following the scheduler and
+//! `SkillsMetrics` pattern. `render` writes them, together with point-in-time
+//! scheduler gauges, in the Prometheus text exposition format for `GET /metrics`.
+
+use loop_core::types::{StepPhase, StepStatus, WatchdogSignal, WorktreeProvider};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::skills::SkillsMetrics;
+
+/// Maximum number of finite buckets a histogram can have.
+const MAX_BUCKETS: usize = 16;
+
+/// Step duration bucket bounds (milliseconds): 1s up to 1h.
+const STEP_DURATION_BUCKETS_MS: &[u64] = &[
+    1_000, 5_000, 15_000, 30_000, 60_000, 120_000, 300_000, 600_000, 1_200_000, 1_800_000,
+    3_600_000,
+];
+
+/// Worktree create bucket bounds (milliseconds): 50ms up to 30s.
+const WORKTREE_CREATE_BUCKETS_MS: &[u64] =
+    &[50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+/// Step phases in exposition order.
+const STEP_PHASES: [StepPhase; 6] = [
+    StepPhase::Planning,
+    StepPhase::Implementation,
+    StepPhase::Review,
+    StepPhase::Verification,
+    StepPhase::Watchdog,
+    StepPhase::Merge,
+];
+
+/// Watchdog signals in exposition order.
+const WATCHDOG_SIGNALS: [WatchdogSignal; 4] = [
+    WatchdogSignal::RepeatedTask,
+    WatchdogSignal::VerificationFailed,
+    WatchdogSignal::NoProgress,
+    WatchdogSignal::MalformedComplete,
+];
+
+/// Worktree create latency, recorded by `worktree::create`.
+pub static WORKTREE_CREATE_DURATION: Histogram = Histogram::new(WORKTREE_CREATE_BUCKETS_MS);
+
+/// Fixed-bucket histogram of millisecond observations, exported in seconds.
+#[derive(Debug)]
+pub struct Histogram {
+    bounds_ms: &'static [u64],
+    /// Non-cumulative counts per finite bucket; cumulated at render time.
+    buckets: [AtomicU64; MAX_BUCKETS],
+    count: AtomicU64,
+    sum_ms: AtomicU64,
+}
+
+impl Histogram {
+    /// Create a histogram with the given ascending bucket bounds (milliseconds).
+    pub const fn new(bounds_ms: &'static [u64]) -> Self {
+        assert!(bounds_ms.len() <= MAX_BUCKETS, "too many histogram buckets");
+        Self {
+            bounds_ms,
+            buckets: [const { AtomicU64::new(0) }; MAX_BUCKETS],
+            count: AtomicU64::new(0),
+            sum_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Record one observation.
+    pub fn observe_ms(&self, ms: u64) {
+        if let Some(idx) = self.bounds_ms.iter().position(|bound| ms <= *bound) {
+            self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ms.fetch_add(ms, Ordering::Relaxed);
+    }
+
+    /// Number of observations recorded.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Write `_bucket`, `_sum` and `_count` series. `labels` is either empty or
+    /// a comma-terminated label list such as `phase="review",`.
+    fn write_series(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, bucket) in self.bounds_ms.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let le = *bound as f64 / 1000.0;
+            let _ = writeln!(out, "{name}_bucket{{{labels}le=\"{le}\"}} {cumulative}");
+        }
+        let count = self.count();
+        let sum = self.sum_ms.load(Ordering::Relaxed) as f64 / 1000.0;
+        let _ = writeln!(out, "{name}_bucket{{{labels}le=\"+Inf\"}} {count}");
+        let labels = labels.trim_end_matches(',');
+        if labels.is_empty() {
+            let _ = writeln!(out, "{name}_sum {sum}");
+            let _ = writeln!(out, "{name}_count {count}");
+        } else {
+            let _ = writeln!(out, "{name}_sum{{{labels}}} {sum}");
+            let _ = writeln!(out, "{name}_count{{{labels}}} {count}");
+        }
+    }
+}
+
+/// Step, verification and watchdog metrics recorded while runs execute.
+#[derive(Debug)]
+pub struct DaemonMetrics {
+    /// Step duration per phase, indexed like `STEP_PHASES`.
+    step_duration: [Histogram; STEP_PHASES.len()],
+    verification_passed: AtomicU64,
+    verification_failed: AtomicU64,
+    /// Watchdog rewrites per signal, indexed like `WATCHDOG_SIGNALS`.
+    watchdog_rewrites: [AtomicU64; WATCHDOG_SIGNALS.len()],
+}
+
+impl Default for DaemonMetrics {
+    fn default() -> Self {
+        Self {
+            step_duration: std::array::from_fn(|_| Histogram::new(STEP_DURATION_BUCKETS_MS)),
+            verification_passed: AtomicU64::new(0),
+            verification_failed: AtomicU64::new(0),
+            watchdog_rewrites: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+impl DaemonMetrics {
+    /// Create a new metrics instance with all counters at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finished step. Verification steps also count as pass or fail.
+    pub fn observe_step(&self, phase: StepPhase, status: StepStatus, duration_ms: u64) {
+        self.step_duration[phase_index(phase)].observe_ms(duration_ms);
+        if phase == StepPhase::Verification {
+            match status {
+                StepStatus::Succeeded => {
+                    self.verification_passed.fetch_add(1, Ordering::Relaxed);
+                }
+                StepStatus::Failed => {
+                    self.verification_failed.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Increment the watchdog rewrite counter for a signal.
+    pub fn inc_watchdog_rewrite(&self, signal: WatchdogSignal) {
+        self.watchdog_rewrites[signal_index(signal)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of steps recorded for a phase.
+    pub fn step_count(&self, phase: StepPhase) -> u64 {
+        self.step_duration[phase_index(phase)].count()
+    }
+
+    /// Get current verification (passed, failed) counts.
+    pub fn verification_counts(&self) -> (u64, u64) {
+        (
+            self.verification_passed.load(Ordering::Relaxed),
+            self.verification_failed.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Get current watchdog rewrite count for a signal.
+    pub fn watchdog_rewrites(&self, signal: WatchdogSignal) -> u64 {
+        self.watchdog_rewrites[signal_index(signal)].load(Ordering::Relaxed)
+    }
+}
+
+fn phase_index(phase: StepPhase) -> usize {
+    match phase {
+        StepPhase::Planning => 0,
+        StepPhase::Implementation => 1,
+        StepPhase::Review => 2,
+        StepPhase::Verification => 3,
+        StepPhase::Watchdog => 4,
+        StepPhase::Merge => 5,
+    }
+}
+
+fn signal_index(signal: WatchdogSignal) -> usize {
+    match signal {
+        WatchdogSignal::RepeatedTask => 0,
+        WatchdogSignal::VerificationFailed => 1,
+        WatchdogSignal::NoProgress => 2,
+        WatchdogSignal::MalformedComplete => 3,
+    }
+}
+
+/// Point-in-time scheduler state for the gauges.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SchedulerGauges {
+    /// Runs in PENDING status.
+    pub queue_depth: usize,
+    /// Runs currently holding a concurrency slot.
+    pub active_runs: usize,
+    /// Configured concurrency limit.
+    pub max_concurrent: usize,
+    /// Times a pending run was skipped by the per-workspace cap.
+    pub queue_blocked_workspace: usize,
+}
+
+/// Render all metrics in the Prometheus text exposition format (version 0.0.4).
+pub fn render(gauges: &SchedulerGauges, metrics: &DaemonMetrics, skills: &SkillsMetrics) -> String {
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "loopd_queue_depth",
+        "gauge",
+        "Runs waiting to be claimed.",
+    );
+    let _ = writeln!(out, "loopd_queue_depth {}", gauges.queue_depth);
+    write_header(
+        &mut out,
+        "loopd_active_runs",
+        "gauge",
+        "Runs currently executing.",
+    );
+    let _ = writeln!(out, "loopd_active_runs {}", gauges.active_runs);
+    write_header(
+        &mut out,
+        "loopd_max_concurrent_runs",
+        "gauge",
+        "Configured concurrency limit.",
+    );
+    let _ = writeln!(out, "loopd_max_concurrent_runs {}", gauges.max_concurrent);
+    write_header(
+        &mut out,
+        "loopd_queue_blocked_workspace_total",
+        "counter",
+        "Pending runs skipped because their workspace was at its cap.",
+    );
+    let _ = writeln!(
+        out,
+        "loopd_queue_blocked_workspace_total {}",
+        gauges.queue_blocked_workspace
+    );
+
+    write_header(
+        &mut out,
+        "loopd_step_duration_seconds",
+        "histogram",
+        "Step duration by phase.",
+    );
+    for (phase, histogram) in STEP_PHASES.iter().zip(&metrics.step_duration) {
+        let labels = format!("phase=\"{}\",", phase.as_str());
+        histogram.write_series(&mut out, "loopd_step_duration_seconds", &labels);
+    }
+
+    let (passed, failed) = metrics.verification_counts();
+    write_header(
+        &mut out,
+        "loopd_verification_total",
+        "counter",
+        "Verification steps by result.",
+    );
+    let _ = writeln!(out, "loopd_verification_total{{result=\"pass\"}} {passed}");
+    let _ = writeln!(out, "loopd_verification_total{{result=\"fail\"}} {failed}");
+
+    write_header(
+        &mut out,
+        "loopd_watchdog_rewrites_total",
+        "counter",
+        "Prompt rewrites by watchdog signal.",
+    );
+    for signal in WATCHDOG_SIGNALS {
+        let _ = writeln!(
+            out,
+            "loopd_watchdog_rewrites_total{{signal=\"{}\"}} {}",
+            signal.as_str(),
+            metrics.watchdog_rewrites(signal)
+        );
+    }
+
+    write_header(
+        &mut out,
+        "loopd_worktree_create_duration_seconds",
+        "histogram",
+        "Worktree creation latency.",
+    );
+    WORKTREE_CREATE_DURATION.write_series(&mut out, "loopd_worktree_create_duration_seconds", "");
+    write_header(
+        &mut out,
+        "loopd_worktrees_created_total",
+        "counter",
+        "Worktrees created by provider.",
+    );
+    for provider in [
+        WorktreeProvider::Git,
+        WorktreeProvider::Worktrunk,
+        WorktreeProvider::Auto,
+    ] {
+        let _ = writeln!(
+            out,
+            "loopd_worktrees_created_total{{provider=\"{}\"}} {}",
+            provider.as_str(),
+            crate::worktree::created_count(provider)
+        );
+    }
+
+    for (name, help, value) in [
+        (
+            "loopd_skills_discovered_total",
+            "Skills discovered across all runs.",
+            skills.get_discovered(),
+        ),
+        (
+            "loopd_skills_selected_total",
+            "Skills selected across all runs.",
+            skills.get_selected(),
+        ),
+        (
+            "loopd_skills_load_failed_total",
+            "Skill load failures.",
+            skills.get_load_failed(),
+        ),
+        (
+            "loopd_skills_truncated_total",
+            "Skill bodies truncated.",
+            skills.get_truncated(),
+        ),
+    ] {
+        write_header(&mut out, name, "counter", help);
+        let _ = writeln!(out, "{name} {value}");
+    }
+
+    out
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        static BOUNDS: &[u64] = &[100, 1_000];
+        let histogram = Histogram::new(BOUNDS);
+        histogram.observe_ms(50);
+        histogram.observe_ms(500);
+        histogram.observe_ms(5_000);
+
+        let mut out = String::new();
+        histogram.write_series(&mut out, "test_seconds", "phase=\"review\",");
+        assert!(out.contains("test_seconds_bucket{phase=\"review\",le=\"0.1\"} 1\n"));
+        assert!(out.contains("test_seconds_bucket{phase=\"review\",le=\"1\"} 2\n"));
+        assert!(out.contains("test_seconds_bucket{phase=\"review\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("test_seconds_sum{phase=\"review\"} 5.55\n"));
+        assert!(out.contains("test_seconds_count{phase=\"review\"} 3\n"));
+    }
+
+    #[test]
+    fn observe_step_counts_verification_results() {
+        let metrics = DaemonMetrics::new();
+        metrics.observe_step(StepPhase::Verification, StepStatus::Succeeded, 1_000);
+        metrics.observe_step(StepPhase::Verification, StepStatus::Failed, 2_000);
+        metrics.observe_step(StepPhase::Verification, StepStatus::Failed, 2_000);
+        metrics.observe_step(StepPhase::Review, StepStatus::Failed, 2_000);
+
+        assert_eq!(metrics.verification_counts(), (1, 2));
+        assert_eq!(metrics.step_count(StepPhase::Verification), 3);
+        assert_eq!(metrics.step_count(StepPhase::Review), 1);
+        assert_eq!(metrics.step_count(StepPhase::Merge), 0);
+    }
+
+    #[test]
+    fn render_includes_all_families() {
+        let metrics = DaemonMetrics::new();
+        metrics.observe_step(StepPhase::Implementation, StepStatus::Succeeded, 42_000);
+        metrics.inc_watchdog_rewrite(WatchdogSignal::NoProgress);
+        let skills = SkillsMetrics::new();
+        skills.inc_discovered(4);
+        let gauges = SchedulerGauges {
+            queue_depth: 2,
+            active_runs: 1,
+            max_concurrent: 3,
+            queue_blocked_workspace: 5,
+        };
+
+        let out = render(&gauges, &metrics, &skills);
+        assert!(out.contains("# TYPE loopd_queue_depth gauge\nloopd_queue_depth 2\n"));
+        assert!(out.contains("loopd_active_runs 1\n"));
+        assert!(out.contains("loopd_queue_blocked_workspace_total 5\n"));
+        assert!(out.contains(
+            "loopd_step_duration_seconds_bucket{phase=\"implementation\",le=\"60\"} 1\n"
+        ));
+        assert!(out.contains("loopd_step_duration_seconds_count{phase=\"merge\"} 0\n"));
+        assert!(out.contains("loopd_verification_total{result=\"pass\"} 0\n"));
+        assert!(out.contains("loopd_watchdog_rewrites_total{signal=\"no_progress\"} 1\n"));
+        assert!(out.contains("# TYPE loopd_worktree_create_duration_seconds histogram\n"));
+        assert!(out.contains("loopd_worktrees_created_total{provider=\"git\"}"));
+        assert!(out.contains("loopd_skills_discovered_total 4\n"));
+
+        // Every family has HELP and TYPE lines.
+        let helps = out.lines().filter(|l| l.starts_with("# HELP")).count();
+        let types = out.lines().filter(|l| l.starts_with("# TYPE")).count();
+        assert_eq!(helps, types);
+    }
+}
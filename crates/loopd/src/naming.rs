@@ -1,16 +1,18 @@
 //! Run naming utilities.
 //!
 //! Implements run name generation per spec Section 3 and Section 4.1:
-//! - `name_source=haiku`: Use Claude haiku model to generate a short label
+//! - `name_source=haiku`: Use the configured agent backend (Claude haiku by default)
+//!   to generate a short label
 //! - `name_source=spec_slug`: Use the spec filename or title
 //! - Names are ASCII, max 64 chars; daemon truncates if needed
 //! - If haiku generation fails, fall back to `spec_slug`
 
 use loop_core::{prompt::spec_slug, RunNameSource};
 use std::path::Path;
-use std::process::Command;
 use thiserror::Error;
 
+use crate::backend::{run_oneshot, BackendConfig};
+
 /// Maximum length for run names.
 pub const MAX_NAME_LENGTH: usize = 64;
 
@@ -18,8 +20,8 @@ pub const MAX_NAME_LENGTH: usize = 64;
 pub enum NamingError {
     #[error("haiku generation failed: {0}")]
     HaikuFailed(String),
-    #[error("claude CLI not available")]
-    ClaudeNotAvailable,
+    #[error("agent backend not available for naming")]
+    BackendNotAvailable,
 }
 
 /// Result of name generation.
@@ -31,12 +33,18 @@ pub struct NameResult {
 
 /// Generate a run name based on the configured source.
 ///
-/// If `source` is `Haiku`, attempts to generate a name using Claude haiku model.
-/// Falls back to `SpecSlug` if haiku generation fails.
-pub fn generate_name(spec_path: &Path, source: RunNameSource, haiku_model: &str) -> NameResult {
+/// If `source` is `Haiku`, attempts to generate a name through the agent backend
+/// using `haiku_model`. Falls back to `SpecSlug` if haiku generation fails or the
+/// backend has no one-shot form.
+pub fn generate_name(
+    spec_path: &Path,
+    source: RunNameSource,
+    haiku_model: &str,
+    backend: &BackendConfig,
+) -> NameResult {
     match source {
         RunNameSource::Haiku => {
-            if let Ok(name) = generate_haiku_name(spec_path, haiku_model) {
+            if let Ok(name) = generate_haiku_name(spec_path, haiku_model, backend) {
                 NameResult {
                     name: sanitize_name(&name),
                     source: RunNameSource::Haiku,
@@ -60,13 +68,12 @@ pub fn generate_name(spec_path: &Path, source: RunNameSource, haiku_model: &str)
     }
 }
 
-/// Generate a haiku-style name using Claude.
-fn generate_haiku_name(spec_path: &Path, model: &str) -> Result<String, NamingError> {
-    // Check if claude CLI is available
-    if Command::new("claude").arg("--version").output().is_err() {
-        return Err(NamingError::ClaudeNotAvailable);
-    }
-
+/// Generate a haiku-style name using the agent backend.
+fn generate_haiku_name(
+    spec_path: &Path,
+    model: &str,
+    backend: &BackendConfig,
+) -> Result<String, NamingError> {
     let spec_name = spec_path
         .file_stem()
         .and_then(|s| s.to_str())
@@ -77,10 +84,9 @@ fn generate_haiku_name(spec_path: &Path, model: &str) -> Result<String, NamingEr
          Output ONLY the name, nothing else. Examples: 'swift-owl', 'cosmic-garden', 'quiet-thunder'."
     );
 
-    let output = Command::new("claude")
-        .args(["--model", model, "--print", "-p", &prompt])
-        .output()
-        .map_err(|e| NamingError::HaikuFailed(e.to_string()))?;
+    let output = run_oneshot(backend, model, &prompt, None, &["--print", "-p"])
+        .map_err(|e| NamingError::HaikuFailed(e.to_string()))?
+        .ok_or(NamingError::BackendNotAvailable)?;
 
     if !output.status.success() {
         return Err(NamingError::HaikuFailed(
@@ -141,6 +147,7 @@ mod tests {
             Path::new("specs/my-feature.md"),
             RunNameSource::SpecSlug,
             "haiku",
+            &BackendConfig::default(),
         );
         assert_eq!(result.name, "my-feature");
         assert_eq!(result.source, RunNameSource::SpecSlug);
@@ -153,6 +160,7 @@ mod tests {
             Path::new("specs/orchestrator-daemon.md"),
             RunNameSource::Haiku,
             "haiku",
+            &BackendConfig::default(),
         );
         // Will fall back because claude CLI likely not in test env
         assert!(!result.name.is_empty());
@@ -0,0 +1,855 @@
+//! Webhook and Slack notifications driven by run events.
+//!
+//! The [`Notifier`] subscribes to the storage event bus, turns notable events
+//! into a [`Notification`] enriched with run context (name, branch, iteration
+//! count, diff stats), routes it to every webhook whose rules match, and posts
+//! it with retries. The final outcome of each delivery is written to the
+//! `notification_deliveries` table so failures are visible.
+//!
+//! Rules are read from a TOML file (`loopd --notifications-config <path>`):
+//!
+//! ```toml
+//! [[webhook]]
+//! name = "team-slack"
+//! url = "https://hooks.slack.com/services/..."
+//! format = "slack"              # or "json" (default)
+//! min_severity = "warning"      # info (default) | warning | critical
+//! workspaces = ["/home/me/app"] # empty: every workspace
+//! events = ["RUN_FAILED"]       # empty: every notifiable event
+//! max_attempts = 3
+//! retry_backoff_ms = 1000       # doubled after each failed attempt
+//! ```
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use loop_core::events::{
+    BudgetExceededPayload, EventType, RunCompletedPayload, RunFailedPayload, WatchdogRewritePayload,
+};
+use loop_core::{Event, Id, StepPhase};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::git::{self, DiffStats};
+use crate::storage::{DeliveryStatus, NotificationDelivery, StorageBackend};
+
+/// Default attempts per delivery, including the first.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry.
+pub const DEFAULT_RETRY_BACKOFF_MS: u64 = 1000;
+
+/// Per-request timeout for webhook POSTs.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum NotificationError {
+    #[error("failed to read notifications config {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid notifications config {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("invalid webhook {name:?}: {message}")]
+    InvalidWebhook { name: String, message: String },
+}
+
+/// How urgent a notification is; webhooks filter with `min_severity`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Request body shape for a webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// The [`Notification`] serialized as JSON.
+    #[default]
+    Json,
+    /// Slack incoming-webhook `{"text": ...}` with mrkdwn.
+    Slack,
+}
+
+fn default_max_attempts() -> u32 {
+    DEFAULT_MAX_ATTEMPTS
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    DEFAULT_RETRY_BACKOFF_MS
+}
+
+/// One `[[webhook]]` entry: destination plus routing rules.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    #[serde(default)]
+    pub min_severity: Severity,
+    /// Workspace roots to notify for; empty matches every workspace.
+    #[serde(default)]
+    pub workspaces: Vec<String>,
+    /// Event types to notify for; empty matches every notifiable event.
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl WebhookConfig {
+    /// Whether this webhook's routing rules accept `notification`.
+    pub fn matches(&self, notification: &Notification) -> bool {
+        notification.severity >= self.min_severity
+            && (self.workspaces.is_empty()
+                || self
+                    .workspaces
+                    .iter()
+                    .any(|w| w == &notification.workspace_root))
+            && (self.events.is_empty() || self.events.iter().any(|e| e == &notification.event_type))
+    }
+
+    fn validate(&self) -> Result<(), NotificationError> {
+        let invalid = |message: String| NotificationError::InvalidWebhook {
+            name: self.name.clone(),
+            message,
+        };
+        if self.name.trim().is_empty() {
+            return Err(invalid("name must not be empty".to_string()));
+        }
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(invalid(format!("url must be http(s): {}", self.url)));
+        }
+        if self.max_attempts == 0 {
+            return Err(invalid("max_attempts must be at least 1".to_string()));
+        }
+        if let Some(unknown) = self.events.iter().find(|e| !is_notifiable(e)) {
+            return Err(invalid(format!("event {unknown} is not notifiable")));
+        }
+        Ok(())
+    }
+}
+
+/// Parsed notifications config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    #[serde(default, rename = "webhook")]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+impl NotificationsConfig {
+    /// Read and validate a TOML config file.
+    pub fn load(path: &Path) -> Result<Self, NotificationError> {
+        let content = std::fs::read_to_string(path).map_err(|source| NotificationError::Read {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::parse(&content).map_err(|e| match e {
+            NotificationError::Parse { message, .. } => NotificationError::Parse {
+                path: path.display().to_string(),
+                message,
+            },
+            other => other,
+        })
+    }
+
+    /// Parse and validate TOML config content.
+    pub fn parse(content: &str) -> Result<Self, NotificationError> {
+        let config: Self = toml::from_str(content).map_err(|e| NotificationError::Parse {
+            path: "<inline>".to_string(),
+            message: e.to_string(),
+        })?;
+        for (i, webhook) in config.webhooks.iter().enumerate() {
+            webhook.validate()?;
+            if config.webhooks[..i].iter().any(|w| w.name == webhook.name) {
+                return Err(NotificationError::InvalidWebhook {
+                    name: webhook.name.clone(),
+                    message: "duplicate webhook name".to_string(),
+                });
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Event types that can produce a notification.
+const NOTIFIABLE_EVENTS: [EventType; 4] = [
+    EventType::RunCompleted,
+    EventType::RunFailed,
+    EventType::WatchdogRewrite,
+    EventType::BudgetExceeded,
+];
+
+fn is_notifiable(event_type: &str) -> bool {
+    NOTIFIABLE_EVENTS.iter().any(|t| t.as_str() == event_type)
+}
+
+/// Diff stats of the run branch against its base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiffSummary {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl From<DiffStats> for DiffSummary {
+    fn from(stats: DiffStats) -> Self {
+        Self {
+            files_changed: stats.files_changed,
+            insertions: stats.insertions,
+            deletions: stats.deletions,
+        }
+    }
+}
+
+/// A notable event with the run context a human needs to act on it.
+///
+/// This is also the body posted to `json` webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub event_id: Id,
+    pub event_type: String,
+    /// Finer-grained cause, e.g. `consecutive_failures` for that kind of `RUN_FAILED`.
+    pub kind: String,
+    pub severity: Severity,
+    pub run_id: Id,
+    pub run_name: String,
+    pub workspace_root: String,
+    pub branch: Option<String>,
+    /// Implementation steps run so far.
+    pub iterations: u32,
+    pub diff: Option<DiffSummary>,
+    pub detail: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Kind, severity and detail for a notifiable event, or `None` to skip it.
+fn classify(event: &Event) -> Option<(&'static str, Severity, String)> {
+    let payload = event.payload_json.as_str();
+    match event.event_type.as_str() {
+        t if t == EventType::RunCompleted.as_str() => {
+            let p: RunCompletedPayload = serde_json::from_str(payload).ok()?;
+            Some((
+                "run_completed",
+                Severity::Info,
+                format!("completed ({})", p.mode),
+            ))
+        }
+        t if t == EventType::RunFailed.as_str() => {
+            let p: RunFailedPayload = serde_json::from_str(payload).ok()?;
+            let kind = if p.reason.starts_with("max_consecutive_failures") {
+                "consecutive_failures"
+            } else {
+                "run_failed"
+            };
+            Some((kind, Severity::Critical, p.reason))
+        }
+        t if t == EventType::WatchdogRewrite.as_str() => {
+            let p: WatchdogRewritePayload = serde_json::from_str(payload).ok()?;
+            Some((
+                "watchdog_rewrite",
+                Severity::Warning,
+                format!("prompt rewritten after {} signal", p.signal.as_str()),
+            ))
+        }
+        t if t == EventType::BudgetExceeded.as_str() => {
+            let p: BudgetExceededPayload = serde_json::from_str(payload).ok()?;
+            Some((
+                "budget_exceeded",
+                Severity::Warning,
+                format!(
+                    "{} budget exhausted ({:.2} of {:.2})",
+                    p.limit, p.used, p.max
+                ),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Build the notification for `event`, or `None` if it is not notifiable.
+pub async fn build_notification(
+    storage: &dyn StorageBackend,
+    event: &Event,
+) -> Option<Notification> {
+    let (kind, severity, detail) = classify(event)?;
+    let run = storage.get_run(&event.run_id).await.ok()?;
+    let iterations = storage
+        .list_steps(&run.id)
+        .await
+        .map(|steps| {
+            steps
+                .iter()
+                .filter(|s| s.phase == StepPhase::Implementation)
+                .count() as u32
+        })
+        .unwrap_or(0);
+
+    let diff = match &run.worktree {
+        Some(wt) => {
+            let workspace_root = run.workspace_root.clone();
+            let (base, branch) = (wt.base_branch.clone(), wt.run_branch.clone());
+            tokio::task::spawn_blocking(move || {
+                git::diff_stats_between(Path::new(&workspace_root), &base, &branch)
+            })
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .map(DiffSummary::from)
+        }
+        None => None,
+    };
+
+    Some(Notification {
+        event_id: event.id.clone(),
+        event_type: event.event_type.clone(),
+        kind: kind.to_string(),
+        severity,
+        run_id: run.id,
+        run_name: run.name,
+        workspace_root: run.workspace_root,
+        branch: run.worktree.map(|wt| wt.run_branch),
+        iterations,
+        diff,
+        detail,
+        timestamp: event.timestamp,
+    })
+}
+
+/// Escape the characters Slack treats as control sequences in mrkdwn.
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Render a notification as Slack mrkdwn.
+pub fn slack_text(n: &Notification) -> String {
+    let icon = match n.severity {
+        Severity::Info => ":white_check_mark:",
+        Severity::Warning => ":warning:",
+        Severity::Critical => ":x:",
+    };
+    let mut facts = Vec::new();
+    if let Some(branch) = &n.branch {
+        facts.push(format!("Branch: `{}`", slack_escape(branch)));
+    }
+    facts.push(format!("Iterations: {}", n.iterations));
+    if let Some(diff) = &n.diff {
+        facts.push(format!(
+            "Diff: {} files, +{}/-{}",
+            diff.files_changed, diff.insertions, diff.deletions
+        ));
+    }
+    format!(
+        "{icon} *{}* {}: {}\n{}\nWorkspace: {}",
+        slack_escape(&n.run_name),
+        n.kind.replace('_', " "),
+        slack_escape(&n.detail),
+        facts.join(" · "),
+        slack_escape(&n.workspace_root),
+    )
+}
+
+/// Request body for `webhook`.
+pub fn webhook_body(webhook: &WebhookConfig, n: &Notification) -> serde_json::Value {
+    match webhook.format {
+        WebhookFormat::Json => serde_json::to_value(n).unwrap_or(serde_json::Value::Null),
+        WebhookFormat::Slack => serde_json::json!({ "text": slack_text(n) }),
+    }
+}
+
+/// Result of posting to one webhook, retries included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryOutcome {
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub response_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+/// Timeouts, rate limiting and server errors are worth retrying; other
+/// client errors will fail the same way again.
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+}
+
+/// POST `body` to the webhook, retrying with exponential backoff.
+pub async fn deliver(
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+    body: &serde_json::Value,
+) -> DeliveryOutcome {
+    let mut response_code = None;
+    let mut error = None;
+    let mut attempts = 0;
+    let mut backoff = Duration::from_millis(webhook.retry_backoff_ms);
+
+    while attempts < webhook.max_attempts {
+        if attempts > 0 {
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2);
+        }
+        attempts += 1;
+
+        match client
+            .post(&webhook.url)
+            .timeout(REQUEST_TIMEOUT)
+            .json(body)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => {
+                return DeliveryOutcome {
+                    status: DeliveryStatus::Delivered,
+                    attempts,
+                    response_code: Some(response.status().as_u16()),
+                    error: None,
+                };
+            }
+            Ok(response) => {
+                let status = response.status();
+                response_code = Some(status.as_u16());
+                error = Some(format!("HTTP {status}"));
+                if !is_retryable(status) {
+                    break;
+                }
+            }
+            Err(e) => {
+                response_code = None;
+                error = Some(e.to_string());
+            }
+        }
+    }
+
+    DeliveryOutcome {
+        status: DeliveryStatus::Failed,
+        attempts,
+        response_code,
+        error,
+    }
+}
+
+/// Delivers notifications for events published on the storage bus.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    storage: Arc<dyn StorageBackend>,
+    config: Arc<NotificationsConfig>,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(storage: Arc<dyn StorageBackend>, config: Arc<NotificationsConfig>) -> Self {
+        Self {
+            storage,
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Subscribe to the bus and notify until `cancel` fires.
+    ///
+    /// Each event is handled on its own task so a slow or retrying webhook
+    /// does not hold up later events.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        let mut rx = self.storage.bus().subscribe_events();
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    () = cancel.cancelled() => break,
+                    received = rx.recv() => match received {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!(skipped, "notifier lagged; events were not notified");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+                if !is_notifiable(&event.event_type) {
+                    continue;
+                }
+                let notifier = self.clone();
+                tokio::spawn(async move { notifier.notify(&event).await });
+            }
+        })
+    }
+
+    /// Deliver `event` to every matching webhook and log the outcomes.
+    pub async fn notify(&self, event: &Event) {
+        let Some(notification) = build_notification(&*self.storage, event).await else {
+            return;
+        };
+        let deliveries = self
+            .config
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.matches(&notification))
+            .map(|webhook| self.deliver_and_record(webhook, &notification));
+        futures_util::future::join_all(deliveries).await;
+    }
+
+    async fn deliver_and_record(&self, webhook: &WebhookConfig, notification: &Notification) {
+        let body = webhook_body(webhook, notification);
+        let outcome = deliver(&self.client, webhook, &body).await;
+        match outcome.status {
+            DeliveryStatus::Delivered => info!(
+                webhook = %webhook.name,
+                run_id = %notification.run_id,
+                event_type = %notification.event_type,
+                attempts = outcome.attempts,
+                "notification delivered"
+            ),
+            DeliveryStatus::Failed => warn!(
+                webhook = %webhook.name,
+                run_id = %notification.run_id,
+                event_type = %notification.event_type,
+                attempts = outcome.attempts,
+                error = outcome.error.as_deref().unwrap_or(""),
+                "notification delivery failed"
+            ),
+        }
+
+        let delivery = NotificationDelivery {
+            id: Id::new(),
+            run_id: notification.run_id.clone(),
+            event_id: notification.event_id.clone(),
+            event_type: notification.event_type.clone(),
+            webhook: webhook.name.clone(),
+            status: outcome.status,
+            attempts: outcome.attempts,
+            response_code: outcome.response_code,
+            error: outcome.error,
+            created_at: Utc::now(),
+        };
+        if let Err(e) = self.storage.record_notification_delivery(&delivery).await {
+            warn!(webhook = %webhook.name, error = %e, "failed to record notification delivery");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use loop_core::events::EventPayload;
+    use loop_core::{ReviewStatus, Run, RunNameSource, RunStatus, WatchdogSignal};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Local webhook receiver that fails the first `failures` requests with 503.
+    #[derive(Default)]
+    struct Stub {
+        failures: usize,
+        hits: AtomicUsize,
+        bodies: Mutex<Vec<serde_json::Value>>,
+    }
+
+    async fn stub_handler(
+        State(stub): State<Arc<Stub>>,
+        Json(body): Json<serde_json::Value>,
+    ) -> StatusCode {
+        let hit = stub.hits.fetch_add(1, Ordering::SeqCst);
+        if hit < stub.failures {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        stub.bodies.lock().unwrap().push(body);
+        StatusCode::OK
+    }
+
+    async fn start_stub(failures: usize) -> (String, Arc<Stub>) {
+        let stub = Arc::new(Stub {
+            failures,
+            ..Stub::default()
+        });
+        let app = Router::new()
+            .route("/hook", post(stub_handler))
+            .with_state(Arc::clone(&stub));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/hook"), stub)
+    }
+
+    fn webhook(url: &str) -> WebhookConfig {
+        WebhookConfig {
+            name: "stub".to_string(),
+            url: url.to_string(),
+            format: WebhookFormat::Json,
+            min_severity: Severity::Info,
+            workspaces: Vec::new(),
+            events: Vec::new(),
+            max_attempts: 3,
+            retry_backoff_ms: 1,
+        }
+    }
+
+    fn notification(severity: Severity, workspace_root: &str) -> Notification {
+        Notification {
+            event_id: Id::new(),
+            event_type: "RUN_FAILED".to_string(),
+            kind: "consecutive_failures".to_string(),
+            severity,
+            run_id: Id::new(),
+            run_name: "brave-fox".to_string(),
+            workspace_root: workspace_root.to_string(),
+            branch: Some("run/brave-fox".to_string()),
+            iterations: 4,
+            diff: Some(DiffSummary {
+                files_changed: 3,
+                insertions: 45,
+                deletions: 12,
+            }),
+            detail: "max_consecutive_failures:verification:3".to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn event(run_id: &Id, payload: &EventPayload) -> Event {
+        Event {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            step_id: None,
+            event_type: payload.event_type().as_str().to_string(),
+            timestamp: Utc::now(),
+            payload_json: payload.to_json().unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse_config_applies_defaults_and_validates() {
+        let config = NotificationsConfig::parse(
+            r#"
+            [[webhook]]
+            name = "slack"
+            url = "https://hooks.slack.com/services/x"
+            format = "slack"
+            min_severity = "warning"
+            workspaces = ["/ws"]
+
+            [[webhook]]
+            name = "audit"
+            url = "http://127.0.0.1:9000/events"
+            events = ["RUN_COMPLETED"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.webhooks.len(), 2);
+        assert_eq!(config.webhooks[0].format, WebhookFormat::Slack);
+        assert_eq!(config.webhooks[0].min_severity, Severity::Warning);
+        assert_eq!(config.webhooks[1].format, WebhookFormat::Json);
+        assert_eq!(config.webhooks[1].max_attempts, DEFAULT_MAX_ATTEMPTS);
+
+        for bad in [
+            "[[webhook]]\nname = \"a\"\nurl = \"ftp://x\"",
+            "[[webhook]]\nname = \"a\"\nurl = \"http://x\"\nevents = [\"STEP_STARTED\"]",
+            "[[webhook]]\nname = \"a\"\nurl = \"http://x\"\nmax_attempts = 0",
+            "[[webhook]]\nname = \"a\"\nurl = \"http://x\"\n[[webhook]]\nname = \"a\"\nurl = \"http://y\"",
+            "[[webhook]]\nname = \"a\"\nurl = \"http://x\"\nseverity = \"info\"",
+        ] {
+            assert!(NotificationsConfig::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn webhook_routing_applies_severity_workspace_and_event_filters() {
+        let mut hook = webhook("http://x");
+        hook.min_severity = Severity::Warning;
+        hook.workspaces = vec!["/ws-a".to_string()];
+
+        assert!(hook.matches(&notification(Severity::Critical, "/ws-a")));
+        assert!(!hook.matches(&notification(Severity::Info, "/ws-a")));
+        assert!(!hook.matches(&notification(Severity::Critical, "/ws-b")));
+
+        hook.events = vec!["RUN_COMPLETED".to_string()];
+        assert!(!hook.matches(&notification(Severity::Critical, "/ws-a")));
+    }
+
+    #[test]
+    fn classify_distinguishes_consecutive_failure_aborts() {
+        let run_id = Id::new();
+        let failed = |reason: &str| {
+            event(
+                &run_id,
+                &EventPayload::RunFailed(RunFailedPayload {
+                    run_id: run_id.clone(),
+                    reason: reason.to_string(),
+                }),
+            )
+        };
+        let (kind, severity, _) = classify(&failed("max_consecutive_failures:review:2")).unwrap();
+        assert_eq!(
+            (kind, severity),
+            ("consecutive_failures", Severity::Critical)
+        );
+        let (kind, _, detail) = classify(&failed("run_error:boom")).unwrap();
+        assert_eq!((kind, detail.as_str()), ("run_failed", "run_error:boom"));
+
+        let rewrite = event(
+            &run_id,
+            &EventPayload::WatchdogRewrite(WatchdogRewritePayload {
+                step_id: Id::new(),
+                signal: WatchdogSignal::RepeatedTask,
+                prompt_before: String::new(),
+                prompt_after: String::new(),
+            }),
+        );
+        assert_eq!(classify(&rewrite).unwrap().1, Severity::Warning);
+
+        let started = event(
+            &run_id,
+            &EventPayload::RunStarted(loop_core::events::RunStartedPayload {
+                run_id: run_id.clone(),
+                worker_id: "w".to_string(),
+            }),
+        );
+        assert!(classify(&started).is_none());
+    }
+
+    #[test]
+    fn slack_body_includes_branch_iterations_and_diff() {
+        let mut hook = webhook("http://x");
+        hook.format = WebhookFormat::Slack;
+        let mut n = notification(Severity::Critical, "/ws");
+        n.run_name = "a<b>".to_string();
+
+        let body = webhook_body(&hook, &n);
+        let text = body["text"].as_str().unwrap();
+        assert!(text.starts_with(":x: *a&lt;b&gt;* consecutive failures:"));
+        assert!(text.contains("Branch: `run/brave-fox`"));
+        assert!(text.contains("Iterations: 4"));
+        assert!(text.contains("Diff: 3 files, +45/-12"));
+    }
+
+    #[tokio::test]
+    async fn deliver_retries_server_errors_until_success() {
+        let (url, stub) = start_stub(2).await;
+        let body = serde_json::json!({ "hello": "world" });
+
+        let outcome = deliver(&reqwest::Client::new(), &webhook(&url), &body).await;
+
+        assert_eq!(outcome.status, DeliveryStatus::Delivered);
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(stub.bodies.lock().unwrap().as_slice(), &[body]);
+    }
+
+    #[tokio::test]
+    async fn deliver_gives_up_after_max_attempts() {
+        let (url, stub) = start_stub(usize::MAX).await;
+        let mut hook = webhook(&url);
+        hook.max_attempts = 2;
+
+        let outcome = deliver(&reqwest::Client::new(), &hook, &serde_json::json!({})).await;
+
+        assert_eq!(outcome.status, DeliveryStatus::Failed);
+        assert_eq!(outcome.attempts, 2);
+        assert_eq!(outcome.response_code, Some(503));
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn notifier_posts_run_failures_and_logs_deliveries() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+
+        let run = Run {
+            id: Id::new(),
+            name: "brave-fox".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Running,
+            workspace_root: "/ws".to_string(),
+            spec_path: "/ws/spec.md".to_string(),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        };
+        storage.insert_run(&run).await.unwrap();
+
+        let (ok_url, stub) = start_stub(0).await;
+        let mut down = webhook("http://127.0.0.1:1/unreachable");
+        down.name = "down".to_string();
+        down.max_attempts = 1;
+        let mut other_workspace = webhook(&ok_url);
+        other_workspace.name = "elsewhere".to_string();
+        other_workspace.workspaces = vec!["/other".to_string()];
+        let config = NotificationsConfig {
+            webhooks: vec![webhook(&ok_url), down, other_workspace],
+        };
+
+        let cancel = CancellationToken::new();
+        let handle = Notifier::new(Arc::clone(&storage), Arc::new(config)).spawn(cancel.clone());
+
+        let payload = EventPayload::RunFailed(RunFailedPayload {
+            run_id: run.id.clone(),
+            reason: "max_consecutive_failures:verification:3".to_string(),
+        });
+        storage
+            .complete_run_atomically(&run.id, &payload, RunStatus::Failed)
+            .await
+            .unwrap();
+
+        let mut deliveries = Vec::new();
+        for _ in 0..100 {
+            deliveries = storage
+                .list_notification_deliveries(None, 10)
+                .await
+                .unwrap();
+            if deliveries.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        cancel.cancel();
+        handle.await.unwrap();
+
+        assert_eq!(deliveries.len(), 2);
+        let status_of = |name: &str| {
+            deliveries
+                .iter()
+                .find(|d| d.webhook == name)
+                .map(|d| d.status)
+        };
+        assert_eq!(status_of("stub"), Some(DeliveryStatus::Delivered));
+        assert_eq!(status_of("down"), Some(DeliveryStatus::Failed));
+
+        let bodies = stub.bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        assert_eq!(bodies[0]["run_name"], "brave-fox");
+        assert_eq!(bodies[0]["kind"], "consecutive_failures");
+        assert_eq!(bodies[0]["severity"], "critical");
+        assert_eq!(bodies[0]["iterations"], 0);
+    }
+}
@@ -4,14 +4,15 @@
 //! See spec: specs/postmortem-analysis.md
 
 use loop_core::artifacts::ArtifactError;
-use loop_core::{write_and_mirror_artifact, Artifact, Config, Run, Step};
+use loop_core::{logs, write_and_mirror_artifact, Artifact, Config, Run, Step, TokenUsage};
 use serde::Serialize;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use thiserror::Error;
 use tracing::warn;
 
-use crate::storage::Storage;
+use crate::backend::{is_oneshot_available, run_oneshot, BackendConfig};
+use crate::storage::StorageBackend;
 
 #[derive(Debug, Error)]
 pub enum PostmortemError {
@@ -23,6 +24,8 @@ pub enum PostmortemError {
     Io(#[from] std::io::Error),
     #[error("artifact error: {0}")]
     Artifact(#[from] ArtifactError),
+    #[error("agent backend '{0}' does not support postmortem analysis")]
+    BackendUnsupported(String),
 }
 
 pub type Result<T> = std::result::Result<T, PostmortemError>;
@@ -52,6 +55,8 @@ pub struct RunSummary {
     pub last_iteration_tail: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_iteration_log: Option<String>,
+    /// Token usage and reported cost summed over all steps.
+    pub usage: TokenUsage,
 }
 
 /// Exit reasons matching legacy bin/loop behavior.
@@ -99,7 +104,7 @@ impl ExitReason {
 /// Implements spec Section 5.1 step 2: write summary.json to run directory
 /// and mirror to global artifacts if configured.
 pub async fn write_summary_json(
-    storage: &Storage,
+    storage: &dyn StorageBackend,
     run: &Run,
     config: &Config,
     exit_reason: ExitReason,
@@ -117,6 +122,7 @@ pub async fn write_summary_json(
         .collect();
 
     let iterations_run = implementation_steps.len() as u32;
+    let usage = storage.get_run_usage(&run.id).await?;
 
     // Find completed iteration (the iteration where completion was detected).
     let completed_iteration = (exit_reason == ExitReason::CompletePlan
@@ -171,6 +177,7 @@ pub async fn write_summary_json(
         prompt_snapshot,
         last_iteration_tail,
         last_iteration_log,
+        usage,
     };
 
     // Serialize with pretty printing for readability.
@@ -230,9 +237,9 @@ impl AnalysisContext {
 
         let last_iter = (iterations_run > 0).then_some(iterations_run);
 
-        // Derive iteration file paths from iteration count
+        // Derive iteration file paths from iteration count (runner naming: iter-XX-impl.*)
         let (last_iter_tail, last_iter_log) = if let Some(iter) = last_iter {
-            let iter_slug = format!("{iter:02}");
+            let iter_slug = format!("{iter:02}-impl");
             (
                 Some(
                     run_dir
@@ -481,6 +488,19 @@ pub fn capture_git_snapshot(workspace_root: &Path, analysis_dir: &Path) -> Resul
 pub fn write_analysis_prompts(ctx: &AnalysisContext) -> Result<AnalysisPrompts> {
     std::fs::create_dir_all(&ctx.analysis_dir)?;
 
+    // Agents cannot read zstd: point the prompts at a decompressed copy.
+    let expanded;
+    let ctx = match expand_compressed_log(ctx.last_iter_log.as_deref(), &ctx.analysis_dir)? {
+        Some(path) => {
+            expanded = AnalysisContext {
+                last_iter_log: Some(path),
+                ..ctx.clone()
+            };
+            &expanded
+        }
+        None => ctx,
+    };
+
     let run_quality_prompt = build_run_quality_prompt(ctx);
     let spec_compliance_prompt = build_spec_compliance_prompt(ctx);
     let summary_prompt = build_summary_prompt(ctx);
@@ -512,6 +532,21 @@ pub fn write_analysis_prompts(ctx: &AnalysisContext) -> Result<AnalysisPrompts>
     })
 }
 
+/// Decompress a compressed iteration log into `analysis_dir`.
+///
+/// Returns the copy's path, or `None` when the log is uncompressed or gone.
+fn expand_compressed_log(log: Option<&str>, analysis_dir: &Path) -> Result<Option<String>> {
+    let Some(log) = log.map(Path::new) else {
+        return Ok(None);
+    };
+    if logs::resolve(log).is_none_or(|resolved| resolved == log) {
+        return Ok(None);
+    }
+    let copy = analysis_dir.join(log.file_name().unwrap_or_default());
+    std::io::copy(&mut logs::open(log)?, &mut std::fs::File::create(&copy)?)?;
+    Ok(Some(copy.to_string_lossy().to_string()))
+}
+
 /// A single analysis prompt with its paths.
 #[derive(Debug, Clone)]
 pub struct AnalysisPrompt {
@@ -574,28 +609,40 @@ pub fn is_claude_available() -> bool {
         .unwrap_or(false)
 }
 
-/// Execute a single analysis step using the claude CLI.
+/// Check if the run's agent backend can execute postmortem analysis.
+///
+/// Claude requires the CLI on PATH (or `claude_bin`); the command backend
+/// requires `agent_command`. HTTP backends are not supported.
+pub fn is_agent_available(config: &Config) -> bool {
+    is_oneshot_available(&BackendConfig::from_config(config))
+}
+
+/// Execute a single analysis step using the run's agent backend.
 ///
 /// Implements spec Section 5.1 step 4: Execute each prompt using
-/// `claude -p --dangerously-skip-permissions --model <model>` and write outputs.
+/// `claude -p --dangerously-skip-permissions --model <model>` (or the configured
+/// command backend) and write outputs.
 ///
 /// Returns Ok with the result, or Err if execution failed catastrophically.
 pub fn execute_analysis_step(
     prompt: &AnalysisPrompt,
     model: &str,
     working_dir: &Path,
+    backend: &BackendConfig,
 ) -> Result<AnalysisStepResult> {
-    let output = Command::new("claude")
-        .arg("-p")
-        .arg("--dangerously-skip-permissions")
-        .arg("--model")
-        .arg(model)
-        .arg(&prompt.prompt)
-        .current_dir(working_dir)
-        .output();
+    let output = run_oneshot(
+        backend,
+        model,
+        &prompt.prompt,
+        Some(working_dir),
+        &["-p", "--dangerously-skip-permissions"],
+    );
 
     match output {
-        Ok(result) => {
+        Ok(None) => Err(PostmortemError::BackendUnsupported(
+            backend.kind.as_str().to_string(),
+        )),
+        Ok(Some(result)) => {
             let exit_code = result.status.code().unwrap_or(-1);
             let success = result.status.success();
 
@@ -664,54 +711,63 @@ pub fn run_postmortem_analysis(
 
     // Step 2: Write analysis prompts
     let prompts = write_analysis_prompts(&ctx)?;
+    let backend = BackendConfig::from_config(config);
 
     // Step 3: Execute analysis steps
     // Order matters: spec-compliance and run-quality first, then summary (which references them)
-    let spec_compliance =
-        match execute_analysis_step(&prompts.spec_compliance, &config.model, workspace_root) {
-            Ok(result) => {
-                tracing::info!(
-                    run_id = %run.id,
-                    success = result.success,
-                    exit_code = result.exit_code,
-                    "spec compliance analysis complete"
-                );
-                Some(result)
-            }
-            Err(e) => {
-                warn!(
-                    run_id = %run.id,
-                    error = %e,
-                    "spec compliance analysis failed"
-                );
-                None
-            }
-        };
+    let spec_compliance = match execute_analysis_step(
+        &prompts.spec_compliance,
+        &config.model,
+        workspace_root,
+        &backend,
+    ) {
+        Ok(result) => {
+            tracing::info!(
+                run_id = %run.id,
+                success = result.success,
+                exit_code = result.exit_code,
+                "spec compliance analysis complete"
+            );
+            Some(result)
+        }
+        Err(e) => {
+            warn!(
+                run_id = %run.id,
+                error = %e,
+                "spec compliance analysis failed"
+            );
+            None
+        }
+    };
 
-    let run_quality =
-        match execute_analysis_step(&prompts.run_quality, &config.model, workspace_root) {
-            Ok(result) => {
-                tracing::info!(
-                    run_id = %run.id,
-                    success = result.success,
-                    exit_code = result.exit_code,
-                    "run quality analysis complete"
-                );
-                Some(result)
-            }
-            Err(e) => {
-                warn!(
-                    run_id = %run.id,
-                    error = %e,
-                    "run quality analysis failed"
-                );
-                None
-            }
-        };
+    let run_quality = match execute_analysis_step(
+        &prompts.run_quality,
+        &config.model,
+        workspace_root,
+        &backend,
+    ) {
+        Ok(result) => {
+            tracing::info!(
+                run_id = %run.id,
+                success = result.success,
+                exit_code = result.exit_code,
+                "run quality analysis complete"
+            );
+            Some(result)
+        }
+        Err(e) => {
+            warn!(
+                run_id = %run.id,
+                error = %e,
+                "run quality analysis failed"
+            );
+            None
+        }
+    };
 
     // Summary depends on the other two reports existing
     let summary = if spec_compliance.is_some() && run_quality.is_some() {
-        match execute_analysis_step(&prompts.summary, &config.model, workspace_root) {
+        match execute_analysis_step(&prompts.summary, &config.model, workspace_root, &backend) {
             Ok(result) => {
                 tracing::info!(
                     run_id = %run.id,
@@ -809,6 +865,12 @@ mod tests {
             prompt_snapshot: "/path/prompt.txt".to_string(),
             last_iteration_tail: Some("/path/iter-11.tail.txt".to_string()),
             last_iteration_log: Some("/path/iter-11.log".to_string()),
+            usage: TokenUsage {
+                input_tokens: 1200,
+                output_tokens: 300,
+                cost_usd: 0.42,
+                ..TokenUsage::default()
+            },
         };
 
         let json = serde_json::to_string(&summary).unwrap();
@@ -816,6 +878,8 @@ mod tests {
         assert!(json.contains("\"start_ms\":1738218455000"));
         assert!(json.contains("\"exit_reason\":\"complete_plan\""));
         assert!(json.contains("\"completed_iteration\":11"));
+        assert!(json.contains("\"input_tokens\":1200"));
+        assert!(json.contains("\"cost_usd\":0.42"));
     }
 
     #[test]
@@ -832,11 +896,12 @@ mod tests {
             completion_mode: None,
             model: "opus".to_string(),
             exit_reason: "failed".to_string(),
-            run_log: "".to_string(),
-            run_report: "".to_string(),
-            prompt_snapshot: "".to_string(),
+            run_log: String::new(),
+            run_report: String::new(),
+            prompt_snapshot: String::new(),
             last_iteration_tail: None,
             last_iteration_log: None,
+            usage: TokenUsage::default(),
         };
 
         let json = serde_json::to_string(&summary).unwrap();
@@ -1027,6 +1092,30 @@ mod tests {
         assert_eq!(written_content, prompts.run_quality.prompt);
     }
 
+    #[test]
+    fn write_analysis_prompts_expands_compressed_last_log() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let run_dir = temp_dir.path().join("run-test");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let log = run_dir.join("iter-03-impl.log");
+        std::fs::write(&log, "agent output\n").unwrap();
+        logs::compress(&log).unwrap();
+
+        let mut ctx = create_test_context();
+        ctx.analysis_dir = run_dir.join("analysis");
+        ctx.last_iter_log = Some(log.to_string_lossy().to_string());
+
+        let prompts = write_analysis_prompts(&ctx).unwrap();
+        let copy = ctx.analysis_dir.join("iter-03-impl.log");
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "agent output\n");
+        assert!(prompts
+            .run_quality
+            .prompt
+            .contains(copy.to_string_lossy().as_ref()));
+    }
+
     #[test]
     fn capture_git_snapshot_creates_files_in_git_repo() {
         use tempfile::TempDir;
@@ -1170,6 +1259,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_comparison)]
     fn is_claude_available_returns_bool() {
         // This just tests that the function doesn't panic
         // It will return true if claude is installed, false otherwise
@@ -0,0 +1,584 @@
+//! Record-and-replay agent backend for deterministic end-to-end runs.
+//!
+//! A fixture bundle is a directory holding `manifest.json` and one output
+//! stream file per agent invocation. The manifest lists the invocations of a
+//! run in order; each one names its phase, the stream it produced, its exit
+//! code, and the files it left changed in the working directory.
+//!
+//! The `replay` backend (`agent_backend=replay`, `replay_fixture=<dir>`) plays
+//! a bundle back through the real runner: each invocation writes its recorded
+//! files (committing them when the recording saw a commit), then streams the
+//! recorded output and exits with the recorded code. Invocations are consumed
+//! per phase, so the implementation and review runners each follow their own
+//! part of the script. No model or network is involved, which lets the full
+//! implementation → review → verification → watchdog → merge lifecycle run in CI.
+//!
+//! Bundles are written by [`FixtureRecorder`] when loopd runs with
+//! `--record-fixtures <dir>`, or by hand for scripted scenarios.
+
+use loop_core::{AgentBackendKind, StepPhase};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::backend::{AgentBackend, AgentHandle, AgentInvocation, AgentOutputFormat, AgentRequest};
+use crate::git::{self, GitError};
+use crate::runner::{Result, RunnerError};
+
+/// Manifest file name inside a fixture bundle.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    #[error("fixture I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid fixture manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("fixture path must be relative and stay inside the working directory: {0}")]
+    InvalidPath(PathBuf),
+    #[error("fixture git operation failed: {0}")]
+    Git(#[from] GitError),
+}
+
+/// Ordered agent invocations of a recorded run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureManifest {
+    pub invocations: Vec<FixtureInvocation>,
+}
+
+/// One recorded agent invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureInvocation {
+    pub phase: StepPhase,
+    /// Output stream file, relative to the bundle directory.
+    pub stream: PathBuf,
+    #[serde(default = "default_format")]
+    pub format: AgentOutputFormat,
+    #[serde(default)]
+    pub exit_code: i32,
+    /// Files to write or delete before the output is streamed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileMutation>,
+    /// Commit all changes with this message after writing `files`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+/// Contents of one file after an invocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMutation {
+    /// Path relative to the working directory.
+    pub path: PathBuf,
+    /// New contents; `None` deletes the file.
+    pub content: Option<String>,
+}
+
+fn default_format() -> AgentOutputFormat {
+    AgentOutputFormat::ClaudeStreamJson
+}
+
+impl FixtureManifest {
+    /// Load `manifest.json` from a bundle directory.
+    pub fn load(dir: &Path) -> std::result::Result<Self, FixtureError> {
+        let content = std::fs::read_to_string(dir.join(MANIFEST_FILE))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Write `manifest.json` into a bundle directory.
+    pub fn save(&self, dir: &Path) -> std::result::Result<(), FixtureError> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(MANIFEST_FILE), content)?;
+        Ok(())
+    }
+}
+
+impl FileMutation {
+    /// Write or delete the file under `working_dir`.
+    pub fn apply(&self, working_dir: &Path) -> std::result::Result<(), FixtureError> {
+        let escapes = self
+            .path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        if escapes || self.path.as_os_str().is_empty() {
+            return Err(FixtureError::InvalidPath(self.path.clone()));
+        }
+        let target = working_dir.join(&self.path);
+        match &self.content {
+            Some(content) => {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&target, content)?;
+            }
+            None => match std::fs::remove_file(&target) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Backend that plays back a fixture bundle instead of running an agent.
+#[derive(Debug)]
+pub struct ReplayBackend {
+    dir: PathBuf,
+    /// Invocations already consumed, per phase.
+    cursors: Mutex<HashMap<&'static str, usize>>,
+}
+
+impl ReplayBackend {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn unavailable(&self, detail: impl std::fmt::Display) -> RunnerError {
+        RunnerError::BackendUnavailable(format!("replay fixture {}: {detail}", self.dir.display()))
+    }
+
+    /// Take the next recorded invocation for `phase`.
+    fn next_invocation(&self, phase: StepPhase) -> Result<FixtureInvocation> {
+        let manifest = FixtureManifest::load(&self.dir).map_err(|err| self.unavailable(err))?;
+        let mut cursors = self
+            .cursors
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let cursor = cursors.entry(phase.as_str()).or_insert(0);
+        let invocation = manifest
+            .invocations
+            .into_iter()
+            .filter(|invocation| invocation.phase == phase)
+            .nth(*cursor)
+            .ok_or_else(|| {
+                self.unavailable(format!("no more recorded {} invocations", phase.as_str()))
+            })?;
+        *cursor += 1;
+        Ok(invocation)
+    }
+}
+
+impl AgentBackend for ReplayBackend {
+    fn spawn(&self, request: &AgentRequest<'_>) -> Result<AgentInvocation> {
+        let invocation = self.next_invocation(request.phase)?;
+        debug!(
+            fixture = %self.dir.display(),
+            phase = request.phase.as_str(),
+            stream = %invocation.stream.display(),
+            "replaying recorded agent invocation"
+        );
+
+        for file in &invocation.files {
+            file.apply(request.working_dir)
+                .map_err(|err| self.unavailable(err))?;
+        }
+        if let Some(message) = &invocation.commit {
+            git::commit_all(request.working_dir, message).map_err(|err| self.unavailable(err))?;
+        }
+
+        let stream = std::fs::read(self.dir.join(&invocation.stream))
+            .map_err(|err| self.unavailable(format!("{}: {err}", invocation.stream.display())))?;
+        let exit_code = invocation.exit_code;
+
+        Ok(AgentInvocation {
+            stdout: Some(Box::new(std::io::Cursor::new(stream))),
+            stderr: None,
+            handle: AgentHandle::Task(tokio::spawn(async move { Ok(exit_code) })),
+            format: invocation.format,
+        })
+    }
+
+    fn kind(&self) -> AgentBackendKind {
+        AgentBackendKind::Replay
+    }
+}
+
+/// Records every agent invocation of a run into a fixture bundle.
+///
+/// Shared by the implementation and review runners of a run, so the manifest
+/// keeps the order the invocations actually ran in.
+#[derive(Debug)]
+pub struct FixtureRecorder {
+    dir: PathBuf,
+    manifest: Mutex<FixtureManifest>,
+}
+
+impl FixtureRecorder {
+    /// Record into `dir`, appending to a bundle already there (e.g. a resumed run).
+    pub fn new(dir: PathBuf) -> std::result::Result<Self, FixtureError> {
+        std::fs::create_dir_all(&dir)?;
+        let manifest = if dir.join(MANIFEST_FILE).exists() {
+            FixtureManifest::load(&dir)?
+        } else {
+            FixtureManifest::default()
+        };
+        Ok(Self {
+            dir,
+            manifest: Mutex::new(manifest),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Commit the working directory is at before an invocation, if it is a git checkout.
+    pub fn snapshot(working_dir: &Path) -> Option<String> {
+        git::get_head_commit(working_dir).ok()
+    }
+
+    /// Append an invocation to the bundle.
+    ///
+    /// `output` is the raw stream the runner saved; `base` the snapshot taken
+    /// before the invocation. Every file that differs from `base` afterwards is
+    /// recorded with its full contents, and a moved HEAD is recorded as one
+    /// commit carrying the latest commit message.
+    pub fn record(
+        &self,
+        phase: StepPhase,
+        format: AgentOutputFormat,
+        exit_code: i32,
+        output: &Path,
+        working_dir: &Path,
+        base: Option<&str>,
+    ) -> std::result::Result<(), FixtureError> {
+        let mut files = Vec::new();
+        let mut commit = None;
+        if let Some(base) = base {
+            for path in git::changed_paths_since(working_dir, base)? {
+                let content = match std::fs::read_to_string(working_dir.join(&path)) {
+                    Ok(content) => Some(content),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(err) => {
+                        warn!(path = %path, error = %err, "skipping unrecordable file");
+                        continue;
+                    }
+                };
+                files.push(FileMutation {
+                    path: PathBuf::from(path),
+                    content,
+                });
+            }
+            if Self::snapshot(working_dir).as_deref() != Some(base) {
+                commit = Some(git::head_commit_message(working_dir)?);
+            }
+        }
+
+        let mut manifest = self
+            .manifest
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let extension = if format.is_structured() {
+            "jsonl"
+        } else {
+            "log"
+        };
+        let stream = PathBuf::from(format!(
+            "{:03}-{}.{extension}",
+            manifest.invocations.len() + 1,
+            phase.slug()
+        ));
+        match std::fs::copy(output, self.dir.join(&stream)) {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                std::fs::write(self.dir.join(&stream), "")?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        manifest.invocations.push(FixtureInvocation {
+            phase,
+            stream,
+            format,
+            exit_code,
+            files,
+            commit,
+        });
+        manifest.save(&self.dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendConfig;
+    use crate::runner::{Runner, RunnerConfig};
+    use chrono::Utc;
+    use loop_core::{Id, Step, StepStatus};
+    use std::process::Command;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio_util::sync::CancellationToken;
+
+    fn step(phase: StepPhase, attempt: u32) -> Step {
+        Step {
+            id: Id::new(),
+            run_id: Id::new(),
+            phase,
+            status: StepStatus::InProgress,
+            attempt,
+            started_at: Some(Utc::now()),
+            ended_at: None,
+            exit_code: None,
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    fn init_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.email", "test@test.com"]);
+        git(dir.path(), &["config", "user.name", "Test"]);
+        std::fs::write(dir.path().join("README.md"), "# Test\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "Initial commit"]);
+        dir
+    }
+
+    fn assistant_line(text: &str) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "message": { "content": [{ "type": "text", "text": text }] },
+        })
+        .to_string()
+            + "\n"
+    }
+
+    fn replay_runner(fixture: &Path) -> Runner {
+        Runner::new(RunnerConfig {
+            backend: BackendConfig {
+                kind: AgentBackendKind::Replay,
+                replay_fixture: Some(fixture.to_path_buf()),
+                ..BackendConfig::default()
+            },
+            ..RunnerConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn replay_streams_recording_and_applies_files_per_phase() {
+        let fixture = TempDir::new().unwrap();
+        let work = init_repo();
+        let run_dir = TempDir::new().unwrap();
+        std::fs::write(
+            fixture.path().join("impl.jsonl"),
+            assistant_line("Implemented the greeting."),
+        )
+        .unwrap();
+        std::fs::write(
+            fixture.path().join("review.jsonl"),
+            assistant_line("APPROVED"),
+        )
+        .unwrap();
+        FixtureManifest {
+            invocations: vec![
+                FixtureInvocation {
+                    phase: StepPhase::Implementation,
+                    stream: PathBuf::from("impl.jsonl"),
+                    format: AgentOutputFormat::ClaudeStreamJson,
+                    exit_code: 0,
+                    files: vec![
+                        FileMutation {
+                            path: PathBuf::from("src/hello.txt"),
+                            content: Some("hello\n".to_string()),
+                        },
+                        FileMutation {
+                            path: PathBuf::from("README.md"),
+                            content: None,
+                        },
+                    ],
+                    commit: Some("Add greeting".to_string()),
+                },
+                FixtureInvocation {
+                    phase: StepPhase::Review,
+                    stream: PathBuf::from("review.jsonl"),
+                    format: AgentOutputFormat::ClaudeStreamJson,
+                    exit_code: 0,
+                    files: Vec::new(),
+                    commit: None,
+                },
+            ],
+        }
+        .save(fixture.path())
+        .unwrap();
+
+        // Review comes first here: each phase follows its own cursor.
+        let review_runner = replay_runner(fixture.path());
+        let review = review_runner
+            .execute_step(
+                &step(StepPhase::Review, 1),
+                "review prompt",
+                run_dir.path(),
+                work.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(review.output.trim(), "APPROVED");
+
+        let runner = replay_runner(fixture.path());
+        let result = runner
+            .execute_step(
+                &step(StepPhase::Implementation, 1),
+                "prompt",
+                run_dir.path(),
+                work.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.output.trim(), "Implemented the greeting.");
+        assert_eq!(
+            std::fs::read_to_string(work.path().join("src/hello.txt")).unwrap(),
+            "hello\n"
+        );
+        assert!(!work.path().join("README.md").exists());
+        assert_eq!(
+            git::head_commit_message(work.path()).unwrap(),
+            "Add greeting"
+        );
+
+        // The script has a single implementation invocation.
+        let err = runner
+            .execute_step(
+                &step(StepPhase::Implementation, 2),
+                "prompt",
+                run_dir.path(),
+                work.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RunnerError::BackendUnavailable(_)));
+    }
+
+    #[tokio::test]
+    async fn replay_reports_recorded_exit_code() {
+        let fixture = TempDir::new().unwrap();
+        let work = TempDir::new().unwrap();
+        let run_dir = TempDir::new().unwrap();
+        std::fs::write(fixture.path().join("001-impl.log"), "compile error\n").unwrap();
+        std::fs::write(
+            fixture.path().join(MANIFEST_FILE),
+            r#"{"invocations":[{"phase":"implementation","stream":"001-impl.log","format":"text","exit_code":2}]}"#,
+        )
+        .unwrap();
+
+        let err = replay_runner(fixture.path())
+            .execute_step(
+                &step(StepPhase::Implementation, 1),
+                "prompt",
+                run_dir.path(),
+                work.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RunnerError::ExitCode { code: 2, .. }));
+    }
+
+    #[test]
+    fn file_mutations_stay_inside_working_dir() {
+        let work = TempDir::new().unwrap();
+        for path in ["../escape.txt", "/etc/passwd", ""] {
+            let mutation = FileMutation {
+                path: PathBuf::from(path),
+                content: Some("x".to_string()),
+            };
+            assert!(matches!(
+                mutation.apply(work.path()),
+                Err(FixtureError::InvalidPath(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn recorded_run_replays_identically() {
+        let bundle = TempDir::new().unwrap();
+        let recorded = init_repo();
+        let run_dir = TempDir::new().unwrap();
+        let recorder = Arc::new(FixtureRecorder::new(bundle.path().to_path_buf()).unwrap());
+        let runner = Runner::new(RunnerConfig {
+            backend: BackendConfig {
+                kind: AgentBackendKind::Command,
+                command: Some(
+                    "mkdir -p docs && echo notes > docs/notes.md && rm README.md \
+                     && git add -A && git commit -qm 'Write notes' && echo 'Wrote notes.'"
+                        .to_string(),
+                ),
+                ..BackendConfig::default()
+            },
+            ..RunnerConfig::default()
+        })
+        .with_recorder(Arc::clone(&recorder));
+        let result = runner
+            .execute_step(
+                &step(StepPhase::Implementation, 1),
+                "prompt",
+                run_dir.path(),
+                recorded.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let manifest = FixtureManifest::load(bundle.path()).unwrap();
+        assert_eq!(manifest.invocations.len(), 1);
+        let invocation = &manifest.invocations[0];
+        assert_eq!(invocation.stream, PathBuf::from("001-impl.log"));
+        assert_eq!(invocation.format, AgentOutputFormat::Text);
+        assert_eq!(invocation.commit.as_deref(), Some("Write notes"));
+        assert_eq!(
+            invocation.files,
+            vec![
+                FileMutation {
+                    path: PathBuf::from("README.md"),
+                    content: None,
+                },
+                FileMutation {
+                    path: PathBuf::from("docs/notes.md"),
+                    content: Some("notes\n".to_string()),
+                },
+            ]
+        );
+
+        let replayed = init_repo();
+        let replay = replay_runner(bundle.path())
+            .execute_step(
+                &step(StepPhase::Implementation, 1),
+                "prompt",
+                run_dir.path(),
+                replayed.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(replay.output, result.output);
+        assert_eq!(
+            std::fs::read_to_string(replayed.path().join("docs/notes.md")).unwrap(),
+            "notes\n"
+        );
+        assert!(!replayed.path().join("README.md").exists());
+        assert_eq!(
+            git::head_commit_message(replayed.path()).unwrap(),
+            "Write notes"
+        );
+    }
+}
@@ -0,0 +1,551 @@
+//! Resource limits for agent and verification processes.
+//!
+//! A [`ResourceGuard`] is prepared per spawned process from the phase's
+//! [`ResourceLimits`]:
+//! - Memory and process count go into a fresh cgroup v2 child of
+//!   `limits.cgroup_parent` when it is usable, so they cover the whole process
+//!   tree. Otherwise they fall back to `RLIMIT_AS` (address space, stricter
+//!   than resident memory) and `RLIMIT_NPROC` (counted per user, not per tree).
+//! - CPU time uses `RLIMIT_CPU`, so it applies to each process separately.
+//!   A process that ignores `SIGXCPU` is killed at the hard limit; the CPU
+//!   time sampled by [`ResourceGuard::watch_cpu`] tells that kill apart.
+//! - Output size is enforced by [`CappedReader`]; the caller kills the process
+//!   once the cap is hit.
+//!
+//! After a failed exit, [`ResourceGuard::limit_hit`] reports which limit
+//! tripped so it can be surfaced instead of a bare exit code.
+
+use loop_core::{ResourceLimitHit, ResourceLimitKind, ResourceLimits};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Extra seconds between the soft CPU limit (`SIGXCPU`) and the hard one (`SIGKILL`).
+const CPU_HARD_LIMIT_GRACE_SEC: u64 = 5;
+
+/// How often [`ResourceGuard::watch_cpu`] samples a process's CPU time.
+const CPU_SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// `setrlimit`'s resource type: an enum on glibc, a plain `int` elsewhere (musl).
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(target_os = "linux", not(target_env = "gnu")))]
+type RlimitResource = libc::c_int;
+
+/// Output that indicates a failed allocation under `RLIMIT_AS`.
+const MEMORY_EXHAUSTED_MARKERS: [&str; 6] = [
+    "Cannot allocate memory",
+    "out of memory",
+    "Out of memory",
+    "memory allocation of",
+    "MemoryError",
+    "bad_alloc",
+];
+
+/// Output that indicates a failed fork under `RLIMIT_NPROC`.
+const FORK_FAILED_MARKERS: [&str; 3] = [
+    "Resource temporarily unavailable",
+    "fork: retry",
+    "Cannot fork",
+];
+
+/// Limits prepared for one process.
+#[derive(Debug)]
+pub struct ResourceGuard {
+    limits: ResourceLimits,
+    cgroup: Option<Cgroup>,
+    /// Most CPU time seen for the process, in milliseconds.
+    cpu_used_ms: Arc<AtomicU64>,
+}
+
+impl ResourceGuard {
+    /// Prepare `limits` for one process; `None` when no limit is set.
+    ///
+    /// Falls back to rlimits (with a warning) when the cgroup cannot be created.
+    pub fn new(limits: ResourceLimits, cgroup_parent: Option<&Path>) -> Option<Self> {
+        if limits.is_empty() {
+            return None;
+        }
+        let needs_cgroup = limits.memory_mb.is_some() || limits.max_procs.is_some();
+        let cgroup =
+            cgroup_parent.filter(|_| needs_cgroup).and_then(|parent| {
+                match Cgroup::create(parent, &limits) {
+                    Ok(cgroup) => Some(cgroup),
+                    Err(e) => {
+                        warn!(
+                            cgroup_parent = %parent.display(),
+                            error = %e,
+                            "cannot create cgroup; falling back to rlimits"
+                        );
+                        None
+                    }
+                }
+            });
+        Some(Self {
+            limits,
+            cgroup,
+            cpu_used_ms: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub fn limits(&self) -> ResourceLimits {
+        self.limits
+    }
+
+    /// Whether memory and process limits are enforced by a cgroup.
+    pub fn uses_cgroup(&self) -> bool {
+        self.cgroup.is_some()
+    }
+
+    /// Output cap for each of stdout and stderr, in bytes.
+    pub fn max_output_bytes(&self) -> Option<u64> {
+        self.limits.max_output_mb.map(|mb| mb * BYTES_PER_MB)
+    }
+
+    /// Wrap an output stream so it ends at the output cap, cancelling `exceeded`.
+    pub fn cap_output<R>(&self, reader: R, exceeded: &CancellationToken) -> CappedReader<R> {
+        CappedReader {
+            inner: reader,
+            remaining: self.max_output_bytes(),
+            exceeded: exceeded.clone(),
+        }
+    }
+
+    /// Apply the limits to `cmd` once it is spawned.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cmd: &mut Command) -> std::io::Result<()> {
+        use std::os::fd::{AsRawFd, OwnedFd};
+
+        let mut rlimits: Vec<(RlimitResource, u64, u64)> = Vec::new();
+        if let Some(cpu_sec) = self.limits.cpu_sec {
+            rlimits.push((
+                libc::RLIMIT_CPU,
+                cpu_sec,
+                cpu_sec + CPU_HARD_LIMIT_GRACE_SEC,
+            ));
+        }
+        if self.cgroup.is_none() {
+            if let Some(memory_mb) = self.limits.memory_mb {
+                let bytes = memory_mb * BYTES_PER_MB;
+                rlimits.push((libc::RLIMIT_AS, bytes, bytes));
+            }
+            if let Some(max_procs) = self.limits.max_procs {
+                rlimits.push((libc::RLIMIT_NPROC, max_procs, max_procs));
+            }
+        }
+        // Unprivileged processes cannot raise their hard limits.
+        let rlimits: Vec<(RlimitResource, libc::rlimit)> = rlimits
+            .into_iter()
+            .map(|(resource, soft, hard)| {
+                let current_hard = current_hard_limit(resource);
+                let hard = hard.min(current_hard);
+                let limit = libc::rlimit {
+                    rlim_cur: soft.min(hard),
+                    rlim_max: hard,
+                };
+                (resource, limit)
+            })
+            .collect();
+        let cgroup_procs: Option<OwnedFd> = match &self.cgroup {
+            Some(cgroup) => Some(cgroup.procs.try_clone()?.into()),
+            None => None,
+        };
+
+        // SAFETY: the hook runs in the forked child before exec and only makes
+        // async-signal-safe syscalls on data prepared by the parent.
+        unsafe {
+            cmd.pre_exec(move || {
+                if let Some(fd) = &cgroup_procs {
+                    // "0" moves the writing process into the cgroup.
+                    if libc::write(fd.as_raw_fd(), b"0".as_ptr().cast(), 1) != 1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                for (resource, limit) in &rlimits {
+                    if libc::setrlimit(*resource, limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Apply the limits to `cmd` once it is spawned.
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _cmd: &mut Command) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "resource limits require Linux",
+        ))
+    }
+
+    /// Sample the CPU time of the spawned process `pid` until it is reaped.
+    ///
+    /// Needs a Tokio runtime; does nothing without a CPU limit.
+    #[cfg(target_os = "linux")]
+    pub fn watch_cpu(&self, pid: Option<u32>) {
+        let (Some(pid), Some(_)) = (pid, self.limits.cpu_sec) else {
+            return;
+        };
+        let used = Arc::clone(&self.cpu_used_ms);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CPU_SAMPLE_INTERVAL);
+            // Exited processes stay readable until reaped, so the last
+            // sample covers the whole run.
+            while let Some(ms) = process_cpu_ms(pid) {
+                used.fetch_max(ms, Ordering::Relaxed);
+                interval.tick().await;
+            }
+        });
+    }
+
+    /// Sample the CPU time of the spawned process `pid` until it is reaped.
+    #[cfg(not(target_os = "linux"))]
+    pub fn watch_cpu(&self, _pid: Option<u32>) {}
+
+    /// The limit a failed process ran into, if any.
+    ///
+    /// `signal` is the signal that terminated the process; `output` is its
+    /// captured output, used to recognize rlimit failures.
+    pub fn limit_hit(&self, signal: Option<i32>, output: &str) -> Option<ResourceLimitHit> {
+        if let Some(cgroup) = &self.cgroup {
+            if cgroup.event_count("memory.events", "oom_kill") > 0 {
+                return self.limits.hit(ResourceLimitKind::Memory);
+            }
+            if cgroup.event_count("pids.events", "max") > 0 {
+                return self.limits.hit(ResourceLimitKind::Processes);
+            }
+        }
+        if signal.is_some_and(|signal| self.is_cpu_limit_signal(signal)) {
+            return self.limits.hit(ResourceLimitKind::CpuTime);
+        }
+        if self.cgroup.is_none() {
+            let mentions = |markers: &[&str]| markers.iter().any(|m| output.contains(m));
+            if mentions(&MEMORY_EXHAUSTED_MARKERS) {
+                return self.limits.hit(ResourceLimitKind::Memory);
+            }
+            if mentions(&FORK_FAILED_MARKERS) {
+                return self.limits.hit(ResourceLimitKind::Processes);
+            }
+        }
+        None
+    }
+
+    /// Whether `signal` came from `RLIMIT_CPU`: `SIGXCPU` at the soft limit,
+    /// or `SIGKILL` at the hard limit once the process used up the soft one.
+    #[cfg(target_os = "linux")]
+    fn is_cpu_limit_signal(&self, signal: i32) -> bool {
+        let Some(cpu_sec) = self.limits.cpu_sec else {
+            return false;
+        };
+        signal == libc::SIGXCPU
+            || (signal == libc::SIGKILL
+                && self.cpu_used_ms.load(Ordering::Relaxed) >= cpu_sec * 1000)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn is_cpu_limit_signal(&self, _signal: i32) -> bool {
+        false
+    }
+}
+
+/// Signal that terminated a process, if any.
+pub fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        std::os::unix::process::ExitStatusExt::signal(status)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn current_hard_limit(resource: RlimitResource) -> u64 {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the provided struct.
+    if unsafe { libc::getrlimit(resource, &raw mut limit) } == 0 {
+        limit.rlim_max
+    } else {
+        libc::RLIM_INFINITY
+    }
+}
+
+/// User plus system CPU time of `pid` from `/proc/<pid>/stat`, in milliseconds.
+#[cfg(target_os = "linux")]
+fn process_cpu_ms(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name may contain spaces, so count fields from its ')'.
+    // utime and stime are fields 14 and 15, i.e. 12 and 13 past the name.
+    let mut fields = stat.get(stat.rfind(')')? + 2..)?.split(' ');
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    // SAFETY: sysconf has no memory-safety preconditions.
+    let ticks_per_sec = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) })
+        .ok()
+        .filter(|&ticks| ticks > 0)?;
+    Some((utime + stime) * 1000 / ticks_per_sec)
+}
+
+/// A per-process cgroup, removed (with anything still in it) on drop.
+#[derive(Debug)]
+struct Cgroup {
+    path: PathBuf,
+    /// `cgroup.procs`, opened by the parent so the child only has to write.
+    procs: std::fs::File,
+}
+
+impl Cgroup {
+    fn create(parent: &Path, limits: &ResourceLimits) -> std::io::Result<Self> {
+        let path = parent.join(format!("loop-{}", Uuid::now_v7()));
+        std::fs::create_dir(&path)?;
+        let setup = || -> std::io::Result<std::fs::File> {
+            if let Some(memory_mb) = limits.memory_mb {
+                std::fs::write(
+                    path.join("memory.max"),
+                    (memory_mb * BYTES_PER_MB).to_string(),
+                )?;
+                // Without swap accounting the file is missing; memory.max still holds.
+                let _ = std::fs::write(path.join("memory.swap.max"), "0");
+            }
+            if let Some(max_procs) = limits.max_procs {
+                std::fs::write(path.join("pids.max"), max_procs.to_string())?;
+            }
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(path.join("cgroup.procs"))
+        };
+        match setup() {
+            Ok(procs) => Ok(Self { path, procs }),
+            Err(e) => {
+                let _ = std::fs::remove_dir(&path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Counter from a flat-keyed events file such as `memory.events`.
+    fn event_count(&self, file: &str, key: &str) -> u64 {
+        std::fs::read_to_string(self.path.join(file))
+            .unwrap_or_default()
+            .lines()
+            .find_map(|line| {
+                let (name, count) = line.split_once(' ')?;
+                (name == key).then(|| count.trim().parse().ok())?
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Kill leftovers (e.g. daemonized grandchildren) so the cgroup can go.
+        let _ = std::fs::write(self.path.join("cgroup.kill"), "1");
+        for _ in 0..50 {
+            match std::fs::remove_dir(&self.path) {
+                Ok(()) => return,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        }
+        debug!(path = %self.path.display(), "could not remove process cgroup");
+    }
+}
+
+/// Reader that reports end of stream once its byte budget is used up.
+#[derive(Debug)]
+pub struct CappedReader<R> {
+    inner: R,
+    /// Bytes still allowed; `None` means unlimited.
+    remaining: Option<u64>,
+    exceeded: CancellationToken,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CappedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let Some(remaining) = self.remaining else {
+            return Pin::new(&mut self.inner).poll_read(cx, buf);
+        };
+        if remaining == 0 && self.exceeded.is_cancelled() {
+            return Poll::Ready(Ok(()));
+        }
+
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let read = (buf.filled().len() - before) as u64;
+            if read > remaining {
+                // Keep output up to the cap and end the stream there.
+                buf.set_filled(before + remaining as usize);
+                self.remaining = Some(0);
+                self.exceeded.cancel();
+            } else {
+                self.remaining = Some(remaining - read);
+            }
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn capped_reader_stops_at_limit() {
+        let limits = ResourceLimits {
+            max_output_mb: Some(1),
+            ..ResourceLimits::default()
+        };
+        let guard = ResourceGuard::new(limits, None).unwrap();
+        let exceeded = CancellationToken::new();
+        let input = vec![b'x'; 3 * 1024 * 1024];
+        let mut reader = guard.cap_output(std::io::Cursor::new(input), &exceeded);
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out.len() as u64, BYTES_PER_MB);
+        assert!(exceeded.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn capped_reader_passes_small_output() {
+        let guard = ResourceGuard::new(
+            ResourceLimits {
+                max_output_mb: Some(1),
+                ..ResourceLimits::default()
+            },
+            None,
+        )
+        .unwrap();
+        let exceeded = CancellationToken::new();
+        let mut reader = guard.cap_output(std::io::Cursor::new(b"hello".to_vec()), &exceeded);
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"hello");
+        assert!(!exceeded.is_cancelled());
+    }
+
+    #[test]
+    fn no_limits_means_no_guard() {
+        assert!(ResourceGuard::new(ResourceLimits::default(), None).is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn limit_hit_recognizes_rlimit_failures() {
+        let guard = ResourceGuard::new(
+            ResourceLimits {
+                memory_mb: Some(256),
+                cpu_sec: Some(10),
+                ..ResourceLimits::default()
+            },
+            None,
+        )
+        .unwrap();
+
+        let hit = guard
+            .limit_hit(None, "memory allocation of 1048576 bytes failed")
+            .unwrap();
+        assert_eq!(hit.kind, ResourceLimitKind::Memory);
+        assert_eq!(hit.limit, 256);
+        let hit = guard.limit_hit(Some(libc::SIGXCPU), "").unwrap();
+        assert_eq!(hit.kind, ResourceLimitKind::CpuTime);
+        // SIGKILL is a CPU limit only once the soft limit was used up.
+        assert_eq!(guard.limit_hit(Some(libc::SIGKILL), ""), None);
+        guard.cpu_used_ms.store(10_000, Ordering::Relaxed);
+        let hit = guard.limit_hit(Some(libc::SIGKILL), "").unwrap();
+        assert_eq!(hit.kind, ResourceLimitKind::CpuTime);
+        // Fork failures only count when a process limit is configured.
+        assert_eq!(
+            guard.limit_hit(None, "fork: retry: Resource temporarily unavailable"),
+            None
+        );
+        assert_eq!(guard.limit_hit(None, "test failed"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn cpu_limit_stops_busy_process() {
+        let guard = ResourceGuard::new(
+            ResourceLimits {
+                cpu_sec: Some(1),
+                ..ResourceLimits::default()
+            },
+            None,
+        )
+        .unwrap();
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("while :; do :; done")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        guard.apply(&mut cmd).unwrap();
+
+        let status = tokio::time::timeout(std::time::Duration::from_secs(30), cmd.status())
+            .await
+            .unwrap()
+            .unwrap();
+        let signal = exit_signal(&status);
+        assert_eq!(
+            guard.limit_hit(signal, "").map(|hit| hit.kind),
+            Some(ResourceLimitKind::CpuTime)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn cpu_limit_reports_process_killed_at_hard_limit() {
+        let guard = ResourceGuard::new(
+            ResourceLimits {
+                cpu_sec: Some(1),
+                ..ResourceLimits::default()
+            },
+            None,
+        )
+        .unwrap();
+        // Ignoring SIGXCPU keeps the process running until the hard limit.
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("trap '' XCPU; while :; do :; done")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        guard.apply(&mut cmd).unwrap();
+        let mut child = cmd.spawn().unwrap();
+        guard.watch_cpu(child.id());
+
+        let status = tokio::time::timeout(std::time::Duration::from_secs(30), child.wait())
+            .await
+            .unwrap()
+            .unwrap();
+        let signal = exit_signal(&status);
+        assert_eq!(signal, Some(libc::SIGKILL));
+        assert_eq!(
+            guard.limit_hit(signal, "").map(|hit| hit.kind),
+            Some(ResourceLimitKind::CpuTime)
+        );
+    }
+}
@@ -1,25 +1,45 @@
-//! Runner module for executing Claude CLI with retries and timeouts.
+//! Runner module for executing agent backends with retries and timeouts.
 //!
-//! Implements step execution via the Claude CLI (spec Section 4.2, 5.3, 7.1).
+//! Implements step execution (spec Section 4.2, 5.3, 7.1). The agent itself is
+//! pluggable via [`crate::backend::AgentBackend`]; Claude CLI is the default.
 //! Key responsibilities:
-//! - Execute Claude CLI with configurable timeout
+//! - Execute the configured agent backend with configurable timeout
 //! - Retry on failure with exponential backoff
 //! - Write artifacts (iter-XX.log, iter-XX.tail.txt)
 //! - Track step timing and exit codes
 
 use chrono::Utc;
-use loop_core::{Id, Step};
+use loop_core::logs;
+use loop_core::tool_policy::permission_denials;
+use loop_core::{
+    AgentBackendKind, Id, PermissionDenial, ResourceLimitHit, ResourceLimitKind,
+    ResourceLimitsConfig, Step, TokenUsage, ToolPolicy, TranscriptBuilder, TranscriptEntry,
+};
+use serde::{Deserialize, Serialize};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
-use tokio::process::Command;
 use tokio::time::timeout;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
+use crate::backend::{
+    create_backend, extract_openai_sse_text, extract_openai_sse_usage, AgentBackend,
+    AgentOutputFormat, AgentPermissions, AgentRequest, BackendConfig,
+};
+use crate::bus::{EventBus, OutputChunk};
+use crate::git;
+use crate::governor::{
+    detect_rate_limit, detect_rate_limit_event, AttemptOutcome, RateLimitGovernor, RateLimitSignal,
+};
+use crate::replay::FixtureRecorder;
+use crate::resources::ResourceGuard;
+use crate::sandbox::{SandboxConfig, SandboxError, SandboxViolation, SandboxedProcess};
+use crate::workers::{DispatchError, StepDispatch, StepReport};
+
 /// Interval between heartbeat log messages during long-running Claude executions.
 ///
 /// Helps operators monitor progress and identify stuck processes.
@@ -43,6 +63,10 @@ const MAX_OUTPUT_BYTES: usize = 50 * 1024 * 1024;
 /// loading the entire log. 200 lines balances context with file size.
 const TAIL_LINES: usize = 200;
 
+/// Per-run Claude settings files written into the working directory, as an
+/// `info/exclude` pattern so agents never commit them.
+const CLAUDE_SETTINGS_EXCLUDE: &str = ".loop/claude-settings-*.json";
+
 /// Read from an async reader with a maximum byte limit.
 ///
 /// Returns the buffer truncated at `max_bytes`. Logs a warning if truncated.
@@ -104,18 +128,202 @@ struct StreamResult {
     text: Vec<u8>,
     /// Whether a transient API error (5xx / overloaded) was detected in the stream.
     transient_api_error: bool,
+    /// Rate-limit / overload error reported to the governor.
+    rate_limit: Option<RateLimitSignal>,
+    /// Token usage reported in the stream (zero for plain-text backends).
+    usage: TokenUsage,
+    /// Typed transcript entries (empty for non-Claude backends).
+    transcript: Vec<TranscriptEntry>,
+    /// Tool calls denied by the permission policy (Claude `result` event).
+    permission_denials: Vec<PermissionDenial>,
+    /// Session id from the Claude `system`/`init` event.
+    session_id: Option<String>,
+}
+
+/// Read the Anthropic-style usage fields from a `usage` object.
+fn usage_from_json(usage: &serde_json::Value) -> TokenUsage {
+    let field = |name: &str| {
+        usage
+            .get(name)
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0)
+    };
+    TokenUsage {
+        input_tokens: field("input_tokens"),
+        output_tokens: field("output_tokens"),
+        cache_creation_input_tokens: field("cache_creation_input_tokens"),
+        cache_read_input_tokens: field("cache_read_input_tokens"),
+        cost_usd: 0.0,
+    }
+}
+
+/// Accumulates token usage from stream events.
+///
+/// Each API message reports input/cache tokens in `message_start` and a
+/// cumulative `output_tokens` count in `message_delta`. Claude Code's final
+/// `result` event carries run-wide totals and `total_cost_usd`; when present
+/// it replaces the per-message sum.
+#[derive(Default)]
+struct UsageTracker {
+    /// Usage of finished messages.
+    completed: TokenUsage,
+    /// Usage of the message currently streaming.
+    current: TokenUsage,
+    /// Authoritative totals from a `result` or OpenAI final chunk.
+    reported: Option<TokenUsage>,
+}
+
+impl UsageTracker {
+    fn observe_claude_event(&mut self, event: &serde_json::Value) {
+        // Unwrap `stream_event` envelopes to the raw API event.
+        let event = match event.get("type").and_then(|t| t.as_str()) {
+            Some("stream_event") => match event.get("event") {
+                Some(inner) => inner,
+                None => return,
+            },
+            _ => event,
+        };
+
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => {
+                if let Some(usage) = event.get("message").and_then(|m| m.get("usage")) {
+                    self.completed.add(&self.current);
+                    self.current = usage_from_json(usage);
+                }
+            }
+            Some("message_delta") => {
+                if let Some(usage) = event.get("usage") {
+                    let delta = usage_from_json(usage);
+                    // Counts in message_delta are cumulative for the message.
+                    self.current.output_tokens =
+                        self.current.output_tokens.max(delta.output_tokens);
+                    self.current.input_tokens = self.current.input_tokens.max(delta.input_tokens);
+                }
+            }
+            Some("result") => {
+                let mut reported = event.get("usage").map(usage_from_json).unwrap_or_default();
+                reported.cost_usd = event
+                    .get("total_cost_usd")
+                    .and_then(serde_json::Value::as_f64)
+                    .unwrap_or(0.0);
+                if !reported.is_empty() {
+                    self.reported = Some(reported);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn observe_openai_line(&mut self, line: &str) {
+        if let Some(usage) = extract_openai_sse_usage(line) {
+            self.reported = Some(usage);
+        }
+    }
+
+    fn finish(self) -> TokenUsage {
+        self.reported.unwrap_or_else(|| {
+            let mut total = self.completed;
+            total.add(&self.current);
+            total
+        })
+    }
+}
+
+/// Session id announced by Claude Code's `system`/`init` event.
+fn claude_session_id(event: &serde_json::Value) -> Option<String> {
+    let is_init = event.get("type").and_then(|t| t.as_str()) == Some("system")
+        && event.get("subtype").and_then(|t| t.as_str()) == Some("init");
+    if !is_init {
+        return None;
+    }
+    event
+        .get("session_id")
+        .and_then(|id| id.as_str())
+        .map(str::to_string)
 }
 
-/// Read Claude `--output-format stream-json` output, extract text, stream to disk.
+/// Extract human-readable text from a single Claude stream-json event.
 ///
-/// Claude's stream-json format produces newline-delimited JSON events following the
-/// Anthropic API streaming protocol. We extract text from `content_block_delta` events
-/// (where `delta.type == "text_delta"`) and write it to the log file as each chunk arrives.
-/// Returns the accumulated plain-text content and whether a transient API error was detected.
-async fn stream_claude_json<R: tokio::io::AsyncRead + Unpin>(
+/// Claude Code emits several event formats:
+///
+/// 1. Raw API streaming: {"type":"content_block_delta","delta":{"type":"text_delta","text":"..."}}
+/// 2. Wrapped API streaming: {"type":"stream_event","event":{"type":"content_block_delta",...}}
+/// 3. Claude Code protocol: {"type":"assistant","message":{"content":[{"type":"text","text":"..."}]}}
+///
+/// We extract text from all three so the .log file has readable output.
+fn extract_claude_event_text(event: &serde_json::Value) -> Option<String> {
+    let event_type = event
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("unknown");
+
+    match event_type {
+        // Claude Code protocol: assistant messages with content blocks.
+        "assistant" => event
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter_map(|b| {
+                        if b.get("type").and_then(|t| t.as_str()) == Some("text") {
+                            b.get("text").and_then(|t| t.as_str())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("")
+            })
+            .filter(|s| !s.is_empty()),
+        // Wrapped API streaming events.
+        "stream_event" => event
+            .get("event")
+            .and_then(|ev| extract_text_delta(ev))
+            .map(std::string::ToString::to_string),
+        // Raw API streaming events (content_block_delta, etc).
+        _ => extract_text_delta(event).map(std::string::ToString::to_string),
+    }
+}
+
+/// Publishes log text to the output bus as it is appended to the log file.
+struct OutputSink {
+    bus: EventBus,
+    run_id: Id,
+    step_id: Id,
+    /// Bytes written to the log file so far.
+    offset: u64,
+}
+
+impl OutputSink {
+    fn publish(&mut self, text: &str) {
+        self.bus.publish_output(OutputChunk {
+            run_id: self.run_id.clone(),
+            step_id: self.step_id.clone(),
+            offset: self.offset,
+            content: text.to_string(),
+        });
+        self.offset += text.len() as u64;
+    }
+}
+
+/// Read an agent backend's output stream, extract text, stream to disk.
+///
+/// For Claude `--output-format stream-json`, the stream is newline-delimited JSON
+/// events following the Anthropic API streaming protocol; OpenAI-compatible
+/// backends produce `data: {...}` SSE lines; command backends produce plain text.
+/// Text is written to the log file as each chunk arrives, and structured formats
+/// also get their raw lines dumped to a sibling `.jsonl` file. When a sink is
+/// given, each chunk is also published to the output bus once it is on disk.
+/// Returns the accumulated plain-text content, whether a transient API error was
+/// detected, and any token usage the stream reported.
+async fn stream_agent_output<R: tokio::io::AsyncRead + Unpin>(
     reader: R,
+    format: AgentOutputFormat,
     max_bytes: usize,
     path: PathBuf,
+    mut sink: Option<OutputSink>,
 ) -> std::io::Result<StreamResult> {
     let mut file = tokio::fs::OpenOptions::new()
         .create(true)
@@ -125,13 +333,19 @@ async fn stream_claude_json<R: tokio::io::AsyncRead + Unpin>(
         .await?;
 
     // Write raw JSON events to a sibling .jsonl file for debugging.
-    let raw_path = path.with_extension("jsonl");
-    let mut raw_file = tokio::fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&raw_path)
-        .await?;
+    let mut raw_file = if format.is_structured() {
+        let raw_path = path.with_extension("jsonl");
+        Some(
+            tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&raw_path)
+                .await?,
+        )
+    } else {
+        None
+    };
 
     let mut buf_reader = tokio::io::BufReader::new(reader);
     let mut line = String::new();
@@ -139,6 +353,11 @@ async fn stream_claude_json<R: tokio::io::AsyncRead + Unpin>(
     let mut truncated = false;
     let mut line_count: u64 = 0;
     let mut transient_api_error = false;
+    let mut rate_limit = None;
+    let mut usage = UsageTracker::default();
+    let mut transcript = TranscriptBuilder::new();
+    let mut denials = Vec::new();
+    let mut session_id = None;
 
     loop {
         line.clear();
@@ -147,68 +366,53 @@ async fn stream_claude_json<R: tokio::io::AsyncRead + Unpin>(
             break;
         }
 
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        // Dump raw line to .jsonl file.
-        raw_file.write_all(trimmed.as_bytes()).await?;
-        raw_file.write_all(b"\n").await?;
-        line_count += 1;
+        let text = if let Some(raw_file) = raw_file.as_mut() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
 
-        // Log first few events to help debug format issues.
-        if line_count <= 5 {
-            tracing::info!(line_count, line = &trimmed[..trimmed.len().min(300)], "stream-json event");
-        }
+            // Dump raw line to .jsonl file.
+            raw_file.write_all(trimmed.as_bytes()).await?;
+            raw_file.write_all(b"\n").await?;
+            line_count += 1;
+
+            // Log first few events to help debug format issues.
+            if line_count <= 5 {
+                tracing::info!(
+                    line_count,
+                    line = &trimmed[..trimmed.len().min(300)],
+                    "stream-json event"
+                );
+            }
 
-        // Parse JSON event and extract human-readable text.
-        //
-        // Claude Code emits several event formats:
-        //
-        // 1. Raw API streaming: {"type":"content_block_delta","delta":{"type":"text_delta","text":"..."}}
-        // 2. Wrapped API streaming: {"type":"stream_event","event":{"type":"content_block_delta",...}}
-        // 3. Claude Code protocol: {"type":"assistant","message":{"content":[{"type":"text","text":"..."}]}}
-        //
-        // We extract text from all three so the .log file has readable output.
-        let text = match serde_json::from_str::<serde_json::Value>(trimmed) {
-            Ok(event) => {
-                let event_type = event.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
-
-                match event_type {
-                    // Claude Code protocol: assistant messages with content blocks.
-                    "assistant" => {
-                        event.get("message")
-                            .and_then(|m| m.get("content"))
-                            .and_then(|c| c.as_array())
-                            .map(|blocks| {
-                                blocks.iter()
-                                    .filter_map(|b| {
-                                        if b.get("type").and_then(|t| t.as_str()) == Some("text") {
-                                            b.get("text").and_then(|t| t.as_str())
-                                        } else {
-                                            None
-                                        }
-                                    })
-                                    .collect::<Vec<_>>()
-                                    .join("")
-                            })
-                            .filter(|s| !s.is_empty())
-                    }
-                    // Wrapped API streaming events.
-                    "stream_event" => {
-                        event.get("event").and_then(|ev| extract_text_delta(ev)).map(|s| s.to_string())
+            match format {
+                AgentOutputFormat::OpenAiSse => {
+                    usage.observe_openai_line(trimmed);
+                    extract_openai_sse_text(trimmed)
+                }
+                _ => match serde_json::from_str::<serde_json::Value>(trimmed) {
+                    Ok(event) => {
+                        usage.observe_claude_event(&event);
+                        transcript.observe(&event, Utc::now());
+                        denials.extend(permission_denials(&event));
+                        if let Some(id) = claude_session_id(&event) {
+                            session_id = Some(id);
+                        }
+                        if rate_limit.is_none() {
+                            rate_limit = detect_rate_limit_event(&event);
+                        }
+                        extract_claude_event_text(&event)
                     }
-                    // Raw API streaming events (content_block_delta, etc).
-                    _ => {
-                        extract_text_delta(&event).map(|s| s.to_string())
+                    Err(err) => {
+                        tracing::warn!(line = &trimmed[..trimmed.len().min(200)], error = %err, "ignoring unparseable stream-json line");
+                        None
                     }
-                }
-            }
-            Err(err) => {
-                tracing::warn!(line = &trimmed[..trimmed.len().min(200)], error = %err, "ignoring unparseable stream-json line");
-                None
+                },
             }
+        } else {
+            // Plain text: pass lines through untouched.
+            Some(line.clone())
         };
 
         if let Some(text) = text {
@@ -219,11 +423,19 @@ async fn stream_claude_json<R: tokio::io::AsyncRead + Unpin>(
                 transient_api_error = true;
                 tracing::warn!(text = &text[..text.len().min(200)], "transient API error detected in stream");
             }
+            // Only the synthetic error lines count: assistant prose that
+            // mentions "overloaded" must not trip the breaker.
+            if rate_limit.is_none() && text.starts_with("API Error:") {
+                rate_limit = detect_rate_limit(&text);
+            }
 
             let bytes = text.as_bytes();
 
             // Write to disk immediately so partial output survives timeouts.
             file.write_all(bytes).await?;
+            if let Some(sink) = sink.as_mut() {
+                sink.publish(&text);
+            }
 
             // Accumulate in memory up to the limit.
             if !truncated {
@@ -245,15 +457,22 @@ async fn stream_claude_json<R: tokio::io::AsyncRead + Unpin>(
     file.flush().await?;
     Ok(StreamResult {
         text: text_buf,
-        transient_api_error,
+        transient_api_error: transient_api_error || rate_limit.is_some(),
+        rate_limit,
+        usage: usage.finish(),
+        transcript: transcript.finish(),
+        permission_denials: denials,
+        session_id,
     })
 }
 
 /// How the process wait loop terminated.
 enum ProcessOutcome {
-    Completed(std::process::ExitStatus),
+    Completed(i32),
     TimedOut,
     Cancelled,
+    /// Killed after exceeding the output size limit.
+    OutputLimit,
 }
 
 /// Maximum transient-error retries, independent of configured `retries`.
@@ -265,6 +484,8 @@ pub enum RunnerError {
     Io(#[from] std::io::Error),
     #[error("claude CLI not found")]
     ClaudeNotFound,
+    #[error("agent backend unavailable: {0}")]
+    BackendUnavailable(String),
     #[error("timeout after {0} seconds")]
     Timeout(u32),
     #[error("process failed with exit code {code}")]
@@ -275,12 +496,22 @@ pub enum RunnerError {
     RetriesExhausted,
     #[error("cancelled")]
     Cancelled,
+    #[error("sandbox error: {0}")]
+    Sandbox(#[from] SandboxError),
+    #[error("{limit} (exit code {code})")]
+    ResourceLimit {
+        limit: ResourceLimitHit,
+        code: i32,
+        output_tail: String,
+    },
+    #[error("worker error: {0}")]
+    Worker(String),
 }
 
 pub type Result<T> = std::result::Result<T, RunnerError>;
 
 /// Result of executing a step.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepResult {
     /// Exit code from Claude CLI.
     pub exit_code: i32,
@@ -294,6 +525,8 @@ pub struct StepResult {
     pub output: String,
     /// Number of retry attempts made.
     pub attempts: u32,
+    /// Agent session the step ran in, if the backend reported one.
+    pub session_id: Option<String>,
 }
 
 /// Runner configuration.
@@ -307,6 +540,18 @@ pub struct RunnerConfig {
     pub retries: u32,
     /// Backoff between retries in seconds.
     pub retry_backoff_sec: u32,
+    /// Agent backend selection and settings.
+    pub backend: BackendConfig,
+    /// Workspace tool permission policy (empty = all permissions).
+    pub tool_policy: ToolPolicy,
+    /// Process sandbox for agent backends that spawn a process.
+    pub sandbox: SandboxConfig,
+    /// Per-phase resource limits for agent processes.
+    pub resource_limits: ResourceLimitsConfig,
+    /// Compress step logs with zstd once the invocation finishes.
+    pub log_compression: bool,
+    /// Cap on the run directory's step logs in bytes (0 = unlimited).
+    pub max_run_log_bytes: u64,
 }
 
 impl Default for RunnerConfig {
@@ -316,6 +561,12 @@ impl Default for RunnerConfig {
             timeout_sec: 600,
             retries: 0,
             retry_backoff_sec: 5,
+            backend: BackendConfig::default(),
+            tool_policy: ToolPolicy::default(),
+            sandbox: SandboxConfig::default(),
+            resource_limits: ResourceLimitsConfig::default(),
+            log_compression: true,
+            max_run_log_bytes: 0,
         }
     }
 }
@@ -328,6 +579,12 @@ impl RunnerConfig {
             timeout_sec: config.claude_timeout_sec,
             retries: config.claude_retries,
             retry_backoff_sec: config.claude_retry_backoff_sec,
+            backend: BackendConfig::from_config(config),
+            tool_policy: config.tool_policy.clone(),
+            sandbox: SandboxConfig::from_config(config),
+            resource_limits: config.resource_limits.clone(),
+            log_compression: config.log_compression,
+            max_run_log_bytes: config.max_run_log_mb.saturating_mul(1024 * 1024),
         }
     }
 
@@ -343,14 +600,41 @@ impl RunnerConfig {
             timeout_sec: config.claude_timeout_sec,
             retries: config.claude_retries,
             retry_backoff_sec: config.claude_retry_backoff_sec,
+            backend: BackendConfig::from_config(config),
+            tool_policy: config.tool_policy.clone(),
+            sandbox: SandboxConfig::from_config(config),
+            resource_limits: config.resource_limits.clone(),
+            log_compression: config.log_compression,
+            max_run_log_bytes: config.max_run_log_mb.saturating_mul(1024 * 1024),
         }
     }
 }
 
-/// Runner for executing Claude CLI commands.
+/// Runner for executing agent backend invocations.
 #[derive(Debug)]
 pub struct Runner {
     config: RunnerConfig,
+    backend: Box<dyn AgentBackend>,
+    /// Usage accumulated across invocations since the last `take_usage`.
+    usage: Mutex<TokenUsage>,
+    /// Transcript entries accumulated since the last `take_transcript`.
+    transcript: Mutex<Vec<TranscriptEntry>>,
+    /// Skill `allowed_tools` merged into the tool policy for the next steps.
+    step_allowed_tools: Mutex<Vec<String>>,
+    /// Permission denials accumulated since the last `take_permission_denials`.
+    permission_denials: Mutex<Vec<PermissionDenial>>,
+    /// Sandbox violations accumulated since the last `take_sandbox_violations`.
+    sandbox_violations: Mutex<Vec<SandboxViolation>>,
+    /// Agent session the next steps continue; `None` starts a new one.
+    resume_session: Mutex<Option<String>>,
+    /// Receives output chunks as they are written to iteration logs.
+    output_bus: Option<EventBus>,
+    /// Saves each invocation into a replayable fixture bundle.
+    recorder: Option<Arc<FixtureRecorder>>,
+    /// Daemon-wide rate-limit backoff gating every agent launch.
+    governor: Option<Arc<RateLimitGovernor>>,
+    /// Hands steps to workers instead of executing them (distributed mode).
+    dispatch: Option<Arc<StepDispatch>>,
 }
 
 /// Truncate a string for logging, adding "..." if truncated.
@@ -365,7 +649,49 @@ fn truncate_str(s: &str, max_len: usize) -> String {
 impl Runner {
     /// Create a new runner with the given configuration.
     pub fn new(config: RunnerConfig) -> Self {
-        Self { config }
+        let backend = create_backend(&config.backend);
+        Self {
+            config,
+            backend,
+            usage: Mutex::new(TokenUsage::default()),
+            transcript: Mutex::new(Vec::new()),
+            step_allowed_tools: Mutex::new(Vec::new()),
+            permission_denials: Mutex::new(Vec::new()),
+            sandbox_violations: Mutex::new(Vec::new()),
+            resume_session: Mutex::new(None),
+            output_bus: None,
+            recorder: None,
+            governor: None,
+            dispatch: None,
+        }
+    }
+
+    /// Publish step output to `bus` while it is being written.
+    #[must_use]
+    pub fn with_output_bus(mut self, bus: EventBus) -> Self {
+        self.output_bus = Some(bus);
+        self
+    }
+
+    /// Record every invocation into a fixture bundle for the `replay` backend.
+    #[must_use]
+    pub fn with_recorder(mut self, recorder: Arc<FixtureRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Hold agent launches while the shared API backoff for the model is open.
+    #[must_use]
+    pub fn with_governor(mut self, governor: Arc<RateLimitGovernor>) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
+    /// Execute steps on workers through `dispatch` rather than in-process.
+    #[must_use]
+    pub fn with_dispatch(mut self, dispatch: Arc<StepDispatch>) -> Self {
+        self.dispatch = Some(dispatch);
+        self
     }
 
     /// Create a runner with default configuration.
@@ -373,6 +699,120 @@ impl Runner {
         Self::new(RunnerConfig::default())
     }
 
+    /// Model passed to the agent backend.
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// Drain token usage accumulated since the last call.
+    ///
+    /// Every invocation contributes, including retries, timeouts and failures,
+    /// so callers should drain after `execute_step` whatever its outcome.
+    pub fn take_usage(&self) -> TokenUsage {
+        let mut usage = self
+            .usage
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::mem::take(&mut *usage)
+    }
+
+    /// Drain transcript entries accumulated since the last call.
+    ///
+    /// Like [`Runner::take_usage`], every invocation of a step contributes, so
+    /// callers should drain after `execute_step` whatever its outcome.
+    pub fn take_transcript(&self) -> Vec<TranscriptEntry> {
+        let mut transcript = self
+            .transcript
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::mem::take(&mut *transcript)
+    }
+
+    /// Set the skill `allowed_tools` merged into the tool policy for the steps
+    /// executed from now on; pass an empty list for steps without skills.
+    pub fn set_step_allowed_tools(&self, tools: Vec<String>) {
+        *self
+            .step_allowed_tools
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = tools;
+    }
+
+    /// Set the agent session the steps executed from now on continue; pass
+    /// `None` to start each step in a new session.
+    pub fn set_resume_session(&self, session_id: Option<String>) {
+        *self
+            .resume_session
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = session_id;
+    }
+
+    /// Drain tool calls denied by the tool policy since the last call.
+    pub fn take_permission_denials(&self) -> Vec<PermissionDenial> {
+        let mut denials = self
+            .permission_denials
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::mem::take(&mut *denials)
+    }
+
+    /// Drain sandbox violations reported since the last call.
+    pub fn take_sandbox_violations(&self) -> Vec<SandboxViolation> {
+        let mut violations = self
+            .sandbox_violations
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::mem::take(&mut *violations)
+    }
+
+    /// Tool policy for the current step: the workspace policy plus the
+    /// selected skills' allowed tools.
+    fn step_tool_policy(&self) -> ToolPolicy {
+        let tools = self
+            .step_allowed_tools
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        self.config
+            .tool_policy
+            .with_allowed(tools.iter().map(String::as_str))
+    }
+
+    /// Write the step's tool policy to the run's Claude settings file in the
+    /// working directory.
+    fn write_claude_settings(
+        working_dir: &Path,
+        run_id: &Id,
+        policy: &ToolPolicy,
+    ) -> Result<PathBuf> {
+        let settings_dir = working_dir.join(".loop");
+        std::fs::create_dir_all(&settings_dir)?;
+        let settings_path = settings_dir.join(format!("claude-settings-{run_id}.json"));
+        let settings = serde_json::to_vec_pretty(&policy.to_claude_settings())
+            .map_err(std::io::Error::other)?;
+        std::fs::write(&settings_path, settings)?;
+
+        if let Err(e) = git::exclude_locally(working_dir, CLAUDE_SETTINGS_EXCLUDE) {
+            debug!(
+                working_dir = %working_dir.display(),
+                error = %e,
+                "could not exclude claude settings file from git"
+            );
+        }
+        Ok(settings_path)
+    }
+
+    /// Append one invocation's entries; retries continue the step's numbering.
+    fn append_transcript(&self, entries: Vec<TranscriptEntry>) {
+        let mut transcript = self
+            .transcript
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let offset = transcript.len() as u32;
+        transcript.extend(entries.into_iter().map(|mut entry| {
+            entry.seq += offset;
+            entry
+        }));
+    }
+
     /// Get the run directory for artifacts.
     ///
     /// Follows spec Section 3.2: `<workspace_root>/logs/loop/run-<run_id>/`
@@ -415,6 +855,61 @@ impl Runner {
         ))
     }
 
+    /// Execute a step on a worker, folding what it reports into this
+    /// runner's usage, transcript, denials and violations.
+    async fn execute_on_worker(
+        &self,
+        dispatch: &StepDispatch,
+        step: &Step,
+        prompt: &str,
+        run_dir: &Path,
+        working_dir: &Path,
+    ) -> Result<StepResult> {
+        let mut request = dispatch.request(step, run_dir, working_dir);
+        request.prompt = Some(prompt.to_string());
+        request.resume_session.clone_from(
+            &self
+                .resume_session
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        request.allowed_tools.clone_from(
+            &self
+                .step_allowed_tools
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+
+        let report = match dispatch.dispatch(&request).await {
+            Ok(StepReport::Agent(report)) => report,
+            Ok(StepReport::Verification { .. }) => {
+                return Err(RunnerError::Worker(
+                    "worker sent a verification report for an agent step".to_string(),
+                ))
+            }
+            Err(DispatchError::Cancelled) => return Err(RunnerError::Cancelled),
+            Err(e) => return Err(RunnerError::Worker(e.to_string())),
+        };
+
+        self.usage
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .add(&report.usage);
+        self.transcript
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(report.transcript);
+        self.permission_denials
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(report.permission_denials);
+        self.sandbox_violations
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(report.sandbox_violations);
+        report.outcome.map_err(RunnerError::from)
+    }
+
     /// Execute a step with retries.
     ///
     /// Implements spec Section 4.2: `execute_step(step, prompt) -> StepResult`
@@ -423,7 +918,9 @@ impl Runner {
     /// Transient API errors (5xx, overloaded) are always retried up to
     /// `MAX_TRANSIENT_RETRIES` with exponential backoff, independent of
     /// the configured `retries` count. Non-transient `ExitCode` errors
-    /// respect the configured retry budget with flat backoff.
+    /// respect the configured retry budget with flat backoff. When a governor
+    /// is attached and the API throttled the model, the retry waits on the
+    /// shared backoff instead of sleeping locally.
     ///
     /// If `cancel_token` is cancelled, the step will be aborted and return `Cancelled`.
     pub async fn execute_step(
@@ -434,6 +931,12 @@ impl Runner {
         working_dir: &Path,
         cancel_token: CancellationToken,
     ) -> Result<StepResult> {
+        if let Some(dispatch) = &self.dispatch {
+            return self
+                .execute_on_worker(dispatch, step, prompt, run_dir, working_dir)
+                .await;
+        }
+
         let max_config_attempts = self.config.retries + 1;
         let mut config_attempt: u32 = 0;
         let mut transient_attempt: u32 = 0;
@@ -464,8 +967,11 @@ impl Runner {
                 )
                 .await;
 
-            // Don't retry if cancelled
-            if matches!(result, Err(RunnerError::Cancelled)) {
+            // Don't retry if cancelled or stopped by a resource limit (it would trip again)
+            if matches!(
+                result,
+                Err(RunnerError::Cancelled | RunnerError::ResourceLimit { .. })
+            ) {
                 return result;
             }
 
@@ -489,6 +995,19 @@ impl Runner {
                         return Err(e);
                     }
 
+                    if self
+                        .governor
+                        .as_ref()
+                        .is_some_and(|governor| governor.is_open(&self.config.model))
+                    {
+                        info!(
+                            step_id = %step.id,
+                            model = %self.config.model,
+                            "retrying transient error after shared API backoff"
+                        );
+                        continue;
+                    }
+
                     // Exponential backoff: 5s, 10s, 20s, 40s, 80s (capped at 120s)
                     let backoff_sec = std::cmp::min(
                         u64::from(self.config.retry_backoff_sec)
@@ -529,6 +1048,43 @@ impl Runner {
         }
     }
 
+    /// Compress a finished invocation's logs and evict old iterations over the cap.
+    ///
+    /// Readers go through `loop_core::logs`, which resolves the `.zst` form.
+    fn finish_logs(&self, step: &Step, run_dir: &Path, output_path: &Path) {
+        if self.config.log_compression {
+            for path in [
+                output_path.to_path_buf(),
+                output_path.with_extension("jsonl"),
+            ] {
+                if let Err(err) = logs::compress(&path) {
+                    warn!(
+                        step_id = %step.id,
+                        path = %path.display(),
+                        error = %err,
+                        "failed to compress step log"
+                    );
+                }
+            }
+        }
+        if self.config.max_run_log_bytes > 0 {
+            match logs::enforce_size_cap(run_dir, self.config.max_run_log_bytes) {
+                Ok(evicted) if !evicted.is_empty() => info!(
+                    step_id = %step.id,
+                    evicted = evicted.len(),
+                    max_bytes = self.config.max_run_log_bytes,
+                    "evicted oldest iteration logs over the run log cap"
+                ),
+                Ok(_) => {}
+                Err(err) => warn!(
+                    step_id = %step.id,
+                    error = %err,
+                    "failed to enforce run log cap"
+                ),
+            }
+        }
+    }
+
     /// Execute a single retry of a step.
     ///
     /// Stdout is streamed to the log file as it arrives so partial output
@@ -547,48 +1103,94 @@ impl Runner {
         let output_path = Self::iter_log_path(run_dir, step);
         let tail_path = Self::iter_tail_path(run_dir, step);
 
-        let start = Utc::now();
+        let policy = self.step_tool_policy();
+        let settings_path = if policy.is_empty() || self.backend.kind() != AgentBackendKind::Claude
+        {
+            None
+        } else {
+            Some(Self::write_claude_settings(
+                working_dir,
+                &step.run_id,
+                &policy,
+            )?)
+        };
 
-        // Build claude command
-        let mut cmd = Command::new("claude");
-        cmd.arg("-p")
-            .arg("--verbose")
-            .arg("--dangerously-skip-permissions")
-            .arg("--output-format")
-            .arg("stream-json")
-            .arg("--model")
-            .arg(&self.config.model)
-            .arg(prompt)
-            .current_dir(working_dir)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        debug!(
-            step_id = %step.id,
-            model = %self.config.model,
-            working_dir = %working_dir.display(),
-            "spawning claude process"
+        let sandbox = self.config.sandbox.for_run(working_dir, run_dir);
+        let limits = ResourceGuard::new(
+            self.config.resource_limits.for_phase(step.phase),
+            self.config.resource_limits.cgroup_parent.as_deref(),
         );
 
-        let mut child = cmd.spawn().map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                RunnerError::ClaudeNotFound
-            } else {
-                RunnerError::Io(e)
-            }
+        let resume_session = self
+            .resume_session
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+
+        let permit = match &self.governor {
+            Some(governor) => match governor.acquire(&self.config.model, &cancel_token).await {
+                Some(permit) => Some(permit),
+                None => return Err(RunnerError::Cancelled),
+            },
+            None => None,
+        };
+
+        let recording_base = self
+            .recorder
+            .as_ref()
+            .and_then(|_| FixtureRecorder::snapshot(working_dir));
+
+        let start = Utc::now();
+
+        let mut invocation = self.backend.spawn(&AgentRequest {
+            phase: step.phase,
+            prompt,
+            model: &self.config.model,
+            working_dir,
+            permissions: settings_path
+                .as_deref()
+                .map(|settings_path| AgentPermissions {
+                    settings_path,
+                    allowlist: policy.is_allowlist(),
+                }),
+            sandbox: sandbox.as_ref(),
+            limits: limits.as_ref(),
+            resume_session: resume_session.as_deref(),
         })?;
 
-        // Claude CLI with --output-format stream-json sends JSON events to stdout.
-        // With --verbose, debug/progress output goes to stderr.
-        let stdout_task = child
-            .stdout
-            .take()
-            .map(|stdout| tokio::spawn(stream_claude_json(stdout, MAX_OUTPUT_BYTES, output_path.clone())));
-        let stderr_task = child
+        // Cancelled by a capped stream once the output size limit is exceeded.
+        let output_exceeded = CancellationToken::new();
+        if let Some(limits) = &limits {
+            invocation.stdout = invocation.stdout.take().map(|stdout| {
+                Box::new(limits.cap_output(stdout, &output_exceeded))
+                    as Box<dyn tokio::io::AsyncRead + Send + Unpin>
+            });
+            invocation.stderr = invocation.stderr.take().map(|stderr| {
+                Box::new(limits.cap_output(stderr, &output_exceeded))
+                    as Box<dyn tokio::io::AsyncRead + Send + Unpin>
+            });
+        }
+
+        let format = invocation.format;
+        let stdout_task = invocation.stdout.take().map(|stdout| {
+            tokio::spawn(stream_agent_output(
+                stdout,
+                format,
+                MAX_OUTPUT_BYTES,
+                output_path.clone(),
+                self.output_bus.clone().map(|bus| OutputSink {
+                    bus,
+                    run_id: step.run_id.clone(),
+                    step_id: step.id.clone(),
+                    offset: 0,
+                }),
+            ))
+        });
+        let stderr_task = invocation
             .stderr
             .take()
             .map(|stderr| tokio::spawn(read_bounded(stderr, MAX_OUTPUT_BYTES)));
+        let child = &mut invocation.handle;
 
         // Wait for process with periodic progress logging.
         let started = Instant::now();
@@ -611,7 +1213,6 @@ impl Runner {
                         "failed to kill timed-out process"
                     );
                 }
-                let _ = child.wait().await;
                 break ProcessOutcome::TimedOut;
             }
 
@@ -626,7 +1227,7 @@ impl Runner {
             tokio::select! {
                 result = child.wait() => {
                     match result {
-                        Ok(status) => break ProcessOutcome::Completed(status),
+                        Ok(code) => break ProcessOutcome::Completed(code),
                         Err(e) => return Err(RunnerError::Io(e)),
                     }
                 }
@@ -642,9 +1243,22 @@ impl Runner {
                             "failed to kill cancelled process"
                         );
                     }
-                    let _ = child.wait().await;
                     break ProcessOutcome::Cancelled;
                 }
+                () = output_exceeded.cancelled() => {
+                    warn!(
+                        step_id = %step.id,
+                        "output size limit exceeded; killing process"
+                    );
+                    if let Err(err) = child.kill().await {
+                        warn!(
+                            step_id = %step.id,
+                            error = %err,
+                            "failed to kill process over output limit"
+                        );
+                    }
+                    break ProcessOutcome::OutputLimit;
+                }
                 () = tokio::time::sleep(sleep_duration) => {
                     let elapsed_secs = started.elapsed().as_secs();
                     info!(
@@ -653,16 +1267,29 @@ impl Runner {
                         elapsed_sec = elapsed_secs,
                         timeout_sec = self.config.timeout_sec,
                         working_dir = %working_dir.display(),
-                        "claude still running"
+                        "agent still running"
                     );
                 }
             }
         };
 
+        // The process may exit on its own (e.g. SIGPIPE) once its output is capped.
+        let outcome = match outcome {
+            ProcessOutcome::Completed(_) if output_exceeded.is_cancelled() => {
+                ProcessOutcome::OutputLimit
+            }
+            outcome => outcome,
+        };
+
         // Always capture remaining output (pipe closes after kill, tasks finish quickly).
         let default_stream_result = StreamResult {
             text: Vec::new(),
             transient_api_error: false,
+            rate_limit: None,
+            usage: TokenUsage::default(),
+            transcript: Vec::new(),
+            permission_denials: Vec::new(),
+            session_id: None,
         };
         let stream_result = match stdout_task {
             Some(task) => match timeout(IO_CAPTURE_TIMEOUT, task).await {
@@ -704,6 +1331,73 @@ impl Runner {
         let end = Utc::now();
         let duration_ms = (end - start).num_milliseconds() as u64;
 
+        if let (Some(recorder), ProcessOutcome::Completed(exit_code)) = (&self.recorder, &outcome) {
+            let raw_path = if format.is_structured() {
+                output_path.with_extension("jsonl")
+            } else {
+                output_path.clone()
+            };
+            if let Err(err) = recorder.record(
+                step.phase,
+                format,
+                *exit_code,
+                &raw_path,
+                working_dir,
+                recording_base.as_deref(),
+            ) {
+                warn!(
+                    step_id = %step.id,
+                    fixture = %recorder.dir().display(),
+                    error = %err,
+                    "failed to record agent invocation"
+                );
+            }
+        }
+
+        // Timeouts and cancellations drop the permit without a verdict.
+        if let (Some(permit), ProcessOutcome::Completed(exit_code)) = (permit, &outcome) {
+            let attempt = match stream_result.rate_limit {
+                Some(signal) if *exit_code != 0 => AttemptOutcome::Throttled(signal),
+                _ => AttemptOutcome::Completed,
+            };
+            permit.finish(attempt, step).await;
+        }
+
+        if !stream_result.usage.is_empty() {
+            self.usage
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .add(&stream_result.usage);
+        }
+        if let Some(sandbox) = &sandbox {
+            let mut violations = sandbox.transcript_violations(&stream_result.transcript);
+            if let ProcessOutcome::Completed(exit_code) = outcome {
+                if exit_code != 0 && violations.is_empty() {
+                    if let Some(detail) = sandbox
+                        .find_violation(&String::from_utf8_lossy(&stderr), SandboxedProcess::Agent)
+                    {
+                        violations.push(SandboxViolation {
+                            process: SandboxedProcess::Agent,
+                            command: None,
+                            exit_code: Some(exit_code),
+                            detail,
+                        });
+                    }
+                }
+            }
+            self.sandbox_violations
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .extend(violations);
+        }
+        self.append_transcript(stream_result.transcript);
+        if !stream_result.permission_denials.is_empty() {
+            self.permission_denials
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .extend(stream_result.permission_denials);
+        }
+
         // Build full output string.
         // stream_result.text = extracted text from stream-json events (already written to log file).
         // stderr = verbose debug output (not used for run output).
@@ -719,6 +1413,8 @@ impl Runner {
             }
         }
 
+        self.finish_logs(step, run_dir, &output_path);
+
         // Log completion with output preview.
         let output_preview = {
             let lines: Vec<&str> = full_output.lines().collect();
@@ -747,9 +1443,25 @@ impl Runner {
                 );
                 Err(RunnerError::Cancelled)
             }
-            ProcessOutcome::Completed(exit_status) => {
-                let exit_code = exit_status.code().unwrap_or(-1);
-
+            ProcessOutcome::OutputLimit => {
+                let limit = limits
+                    .as_ref()
+                    .and_then(|limits| limits.limits().hit(ResourceLimitKind::OutputSize))
+                    .expect("output limit outcome requires an output limit");
+                info!(
+                    step_id = %step.id,
+                    phase = ?step.phase,
+                    duration_ms = duration_ms,
+                    output_bytes = full_output.len(),
+                    "step stopped by output size limit (partial output saved)"
+                );
+                Err(RunnerError::ResourceLimit {
+                    limit,
+                    code: child.wait().await.unwrap_or(-1),
+                    output_tail: output_preview,
+                })
+            }
+            ProcessOutcome::Completed(exit_code) => {
                 info!(
                     step_id = %step.id,
                     phase = ?step.phase,
@@ -783,7 +1495,18 @@ impl Runner {
 
                     let output_tail = output_preview.clone();
 
-                    return if stream_result.transient_api_error {
+                    let limit = limits.as_ref().and_then(|limits| {
+                        let output = format!("{full_output}\n{}", String::from_utf8_lossy(&stderr));
+                        limits.limit_hit(child.signal(), &output)
+                    });
+                    return if let Some(limit) = limit {
+                        warn!(step_id = %step.id, %limit, "agent process hit a resource limit");
+                        Err(RunnerError::ResourceLimit {
+                            limit,
+                            code: exit_code,
+                            output_tail,
+                        })
+                    } else if stream_result.transient_api_error {
                         Err(RunnerError::TransientApiError {
                             code: exit_code,
                             output_tail,
@@ -803,6 +1526,7 @@ impl Runner {
                     tail_path,
                     output: full_output,
                     attempts: retry,
+                    session_id: stream_result.session_id,
                 })
             }
         }
@@ -812,9 +1536,10 @@ impl Runner {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use loop_core::{Id, StepPhase, StepStatus};
+    use loop_core::{StepPhase, StepStatus, TranscriptEntryKind};
+    use std::process::Stdio;
     use tempfile::TempDir;
-    use tokio::time::timeout;
+    use tokio::process::Command;
 
     fn create_test_step(attempt: u32) -> Step {
         Step {
@@ -828,6 +1553,7 @@ mod tests {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         }
     }
 
@@ -971,7 +1697,7 @@ mod tests {
                         last_error = Some(e);
                         if attempt < max_attempts {
                             let backoff = Duration::from_millis(
-                                (self.config.retry_backoff_sec * 10) as u64, // 10ms per "second" for fast tests
+                                u64::from(self.config.retry_backoff_sec * 10), // 10ms per "second" for fast tests
                             );
                             tokio::time::sleep(backoff).await;
                         }
@@ -1015,7 +1741,7 @@ mod tests {
 
             let (exit_code, stdout, stderr) = if self.config.timeout_sec > 0 {
                 // Use milliseconds for timeout in tests (timeout_sec treated as ms)
-                let timeout_duration = Duration::from_millis(self.config.timeout_sec as u64);
+                let timeout_duration = Duration::from_millis(u64::from(self.config.timeout_sec));
 
                 match timeout(timeout_duration, child.wait_with_output()).await {
                     Ok(result) => {
@@ -1078,6 +1804,7 @@ mod tests {
                 tail_path,
                 output: full_output,
                 attempts: attempt,
+                session_id: None,
             })
         }
     }
@@ -1407,6 +2134,524 @@ exit 0
         }
     }
 
+    #[tokio::test]
+    async fn execute_step_with_command_backend_writes_log_contract() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run-test");
+        let step = create_test_step(2);
+
+        let config = RunnerConfig {
+            backend: BackendConfig {
+                kind: loop_core::AgentBackendKind::Command,
+                command: Some("cat; echo '<promise>COMPLETE</promise>'".to_string()),
+                ..BackendConfig::default()
+            },
+            ..Default::default()
+        };
+        let runner = Runner::new(config);
+
+        let result = runner
+            .execute_step(
+                &step,
+                "line one\nline two\n",
+                &run_dir,
+                dir.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.exit_code, 0);
+        assert_eq!(result.output_path, run_dir.join("iter-02-impl.log"));
+        assert_eq!(
+            result.output,
+            "line one\nline two\n<promise>COMPLETE</promise>\n"
+        );
+        // Finished logs are compressed; the reader serves them transparently.
+        assert!(!result.output_path.exists());
+        assert!(run_dir.join("iter-02-impl.log.zst").exists());
+        let log = logs::read_from(&result.output_path, 0).unwrap();
+        assert_eq!(String::from_utf8(log).unwrap(), result.output);
+        assert!(result.tail_path.exists());
+        // Plain-text backends have no raw event stream to dump.
+        assert!(!run_dir.join("iter-02-impl.jsonl").exists());
+    }
+
+    #[tokio::test]
+    async fn sandboxed_agent_reports_denied_writes() {
+        if !crate::sandbox::is_supported() {
+            eprintln!("skipping: landlock unavailable");
+            return;
+        }
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run-test");
+        let working_dir = dir.path().join("worktree");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&working_dir).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let step = create_test_step(1);
+
+        let runner = Runner::new(RunnerConfig {
+            backend: BackendConfig {
+                kind: loop_core::AgentBackendKind::Command,
+                command: Some(format!(
+                    "cat >/dev/null; touch inside && touch {}/escaped",
+                    outside.display()
+                )),
+                ..BackendConfig::default()
+            },
+            sandbox: SandboxConfig {
+                mode: loop_core::SandboxMode::Fs,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let result = runner
+            .execute_step(
+                &step,
+                "prompt",
+                &run_dir,
+                &working_dir,
+                CancellationToken::new(),
+            )
+            .await;
+
+        assert!(matches!(result, Err(RunnerError::ExitCode { .. })));
+        assert!(working_dir.join("inside").exists());
+        assert!(!outside.join("escaped").exists());
+        let violations = runner.take_sandbox_violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].process, SandboxedProcess::Agent);
+        assert_eq!(violations[0].exit_code, Some(1));
+        assert!(violations[0].detail.contains("Permission denied"));
+        assert!(runner.take_sandbox_violations().is_empty());
+    }
+
+    #[tokio::test]
+    async fn output_limit_stops_agent_without_retry() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run-test");
+        let step = create_test_step(1);
+        let mut resource_limits = ResourceLimitsConfig::default();
+        resource_limits.apply_key("max_output_mb", "1").unwrap();
+
+        let runner = Runner::new(RunnerConfig {
+            retries: 2,
+            retry_backoff_sec: 0,
+            backend: BackendConfig {
+                kind: loop_core::AgentBackendKind::Command,
+                command: Some("cat >/dev/null; yes \"$(printf '%01000d' 0)\"".to_string()),
+                ..BackendConfig::default()
+            },
+            resource_limits,
+            ..Default::default()
+        });
+
+        let result = runner
+            .execute_step(
+                &step,
+                "prompt",
+                &run_dir,
+                dir.path(),
+                CancellationToken::new(),
+            )
+            .await;
+
+        match result {
+            Err(RunnerError::ResourceLimit { limit, .. }) => {
+                assert_eq!(limit.kind, ResourceLimitKind::OutputSize);
+                assert_eq!(limit.limit, 1);
+            }
+            other => panic!("expected resource limit error, got {other:?}"),
+        }
+        let log = logs::read_from(&Runner::iter_log_path(&run_dir, &step), 0).unwrap();
+        assert!(log.len() <= 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn stream_agent_output_extracts_openai_sse_deltas() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("test.log");
+        let input = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let reader = std::io::Cursor::new(input.as_bytes().to_vec());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::OpenAiSse,
+            MAX_OUTPUT_BYTES,
+            log_path.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&result.text), "Hello world");
+        assert!(!result.transient_api_error);
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "Hello world");
+    }
+
+    #[tokio::test]
+    async fn stream_agent_output_flags_openai_5xx_as_transient() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("test.log");
+        let input = "data: {\"error\":{\"message\":\"503 upstream unavailable\"}}\n\n";
+        let reader = std::io::Cursor::new(input.as_bytes().to_vec());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::OpenAiSse,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(result.transient_api_error);
+    }
+
+    #[tokio::test]
+    async fn stream_agent_output_publishes_chunks_with_log_offsets() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("test.log");
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe_output();
+        let sink = OutputSink {
+            bus,
+            run_id: Id::new(),
+            step_id: Id::new(),
+            offset: 0,
+        };
+        let reader = std::io::Cursor::new(b"first\nsecond line\n".to_vec());
+        stream_agent_output(
+            reader,
+            AgentOutputFormat::Text,
+            MAX_OUTPUT_BYTES,
+            log_path.clone(),
+            Some(sink),
+        )
+        .await
+        .unwrap();
+
+        let first = rx.recv().await.unwrap();
+        assert_eq!((first.offset, first.content.as_str()), (0, "first\n"));
+        let second = rx.recv().await.unwrap();
+        assert_eq!(
+            (second.offset, second.content.as_str()),
+            (6, "second line\n")
+        );
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(&log[second.offset as usize..], "second line\n");
+    }
+
+    #[tokio::test]
+    async fn stream_claude_json_sums_message_usage() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("output.log");
+
+        // Two API messages wrapped in stream_event envelopes, no final result event.
+        let stream_data = concat!(
+            r#"{"type":"stream_event","event":{"type":"message_start","message":{"usage":{"input_tokens":100,"cache_creation_input_tokens":20,"cache_read_input_tokens":300,"output_tokens":1}}}}"#,
+            "\n",
+            r#"{"type":"stream_event","event":{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":40}}}"#,
+            "\n",
+            r#"{"type":"stream_event","event":{"type":"message_start","message":{"usage":{"input_tokens":10,"output_tokens":1}}}}"#,
+            "\n",
+            r#"{"type":"stream_event","event":{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":5}}}"#,
+            "\n",
+        );
+
+        let reader = tokio::io::BufReader::new(stream_data.as_bytes());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.usage.input_tokens, 110);
+        assert_eq!(result.usage.output_tokens, 45);
+        assert_eq!(result.usage.cache_creation_input_tokens, 20);
+        assert_eq!(result.usage.cache_read_input_tokens, 300);
+        assert!(result.usage.cost_usd.abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn stream_claude_json_prefers_result_event_usage() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("output.log");
+
+        let stream_data = concat!(
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":5,"output_tokens":1}}}"#,
+            "\n",
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#,
+            "\n",
+            r#"{"type":"result","subtype":"success","total_cost_usd":0.0421,"usage":{"input_tokens":50,"output_tokens":70,"cache_read_input_tokens":1000}}"#,
+            "\n",
+        );
+
+        let reader = tokio::io::BufReader::new(stream_data.as_bytes());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.usage.input_tokens, 50);
+        assert_eq!(result.usage.output_tokens, 70);
+        assert_eq!(result.usage.cache_read_input_tokens, 1000);
+        assert!((result.usage.cost_usd - 0.0421).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn stream_agent_output_reads_openai_usage_chunk() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("test.log");
+        let input = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}],\"usage\":null}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":3}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let reader = std::io::Cursor::new(input.as_bytes().to_vec());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::OpenAiSse,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&result.text), "Hi");
+        assert_eq!(result.usage.input_tokens, 12);
+        assert_eq!(result.usage.output_tokens, 3);
+    }
+
+    #[test]
+    fn take_usage_drains_accumulated_usage() {
+        let runner = Runner::with_defaults();
+        assert!(runner.take_usage().is_empty());
+
+        runner.usage.lock().unwrap().add(&TokenUsage {
+            input_tokens: 3,
+            ..TokenUsage::default()
+        });
+        assert_eq!(runner.take_usage().input_tokens, 3);
+        assert!(runner.take_usage().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tool_policy_writes_settings_and_collects_denials() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run-test");
+        let args_path = dir.path().join("args.txt");
+        let script_path = dir.path().join("fake-claude.sh");
+        std::fs::write(
+            &script_path,
+            format!(
+                r#"#!/bin/sh
+printf '%s\n' "$@" > "{args}"
+echo '{{"type":"result","subtype":"success","permission_denials":[{{"tool_name":"Bash","tool_use_id":"t1","tool_input":{{"command":"git push"}}}}]}}'
+"#,
+                args = args_path.display(),
+            ),
+        )
+        .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&script_path).unwrap().permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(&script_path, perms).unwrap();
+        }
+
+        let runner = Runner::new(RunnerConfig {
+            backend: BackendConfig {
+                claude_bin: script_path,
+                ..BackendConfig::default()
+            },
+            tool_policy: ToolPolicy {
+                allow: vec!["Edit".to_string()],
+                deny: vec!["Bash(git push:*)".to_string()],
+            },
+            ..Default::default()
+        });
+        runner.set_step_allowed_tools(vec!["Bash(cargo:*)".to_string()]);
+        let step = create_test_step(1);
+
+        runner
+            .execute_step(
+                &step,
+                "prompt",
+                &run_dir,
+                dir.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let settings_path = dir
+            .path()
+            .join(format!(".loop/claude-settings-{}.json", step.run_id));
+        let args = std::fs::read_to_string(&args_path).unwrap();
+        assert!(!args.contains("--dangerously-skip-permissions"));
+        assert!(args.contains(&format!(
+            "--permission-mode\ndefault\n--settings\n{}\n",
+            settings_path.display()
+        )));
+
+        let settings: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&settings_path).unwrap()).unwrap();
+        assert_eq!(
+            settings["permissions"]["allow"],
+            serde_json::json!(["Edit", "Bash(cargo:*)"])
+        );
+        assert_eq!(
+            settings["permissions"]["deny"],
+            serde_json::json!(["Bash(git push:*)"])
+        );
+
+        let denials = runner.take_permission_denials();
+        assert_eq!(denials.len(), 1);
+        assert_eq!(denials[0].tool_input["command"], "git push");
+        assert!(runner.take_permission_denials().is_empty());
+    }
+
+    #[tokio::test]
+    async fn no_tool_policy_keeps_full_permissions() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run-test");
+        let args_path = dir.path().join("args.txt");
+        let script_path = dir.path().join("fake-claude.sh");
+        std::fs::write(
+            &script_path,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"{}\"\n",
+                args_path.display()
+            ),
+        )
+        .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&script_path).unwrap().permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(&script_path, perms).unwrap();
+        }
+
+        let runner = Runner::new(RunnerConfig {
+            backend: BackendConfig {
+                claude_bin: script_path,
+                ..BackendConfig::default()
+            },
+            ..Default::default()
+        });
+        // Skill tools do not restrict a step when no policy is configured.
+        runner.set_step_allowed_tools(vec!["Bash(git:*)".to_string()]);
+        let step = create_test_step(1);
+
+        runner
+            .execute_step(
+                &step,
+                "prompt",
+                &run_dir,
+                dir.path(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let args = std::fs::read_to_string(&args_path).unwrap();
+        assert!(args.contains("--dangerously-skip-permissions"));
+        assert!(!args.contains("--settings"));
+        assert!(!dir.path().join(".loop").exists());
+    }
+
+    #[tokio::test]
+    async fn resume_session_passes_resume_and_reports_session_id() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run-test");
+        let args_path = dir.path().join("args.txt");
+        let script_path = dir.path().join("fake-claude.sh");
+        std::fs::write(
+            &script_path,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"{}\"\n\
+                 echo '{{\"type\":\"system\",\"subtype\":\"init\",\"session_id\":\"sess-2\"}}'\n",
+                args_path.display()
+            ),
+        )
+        .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&script_path).unwrap().permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(&script_path, perms).unwrap();
+        }
+
+        let runner = Runner::new(RunnerConfig {
+            backend: BackendConfig {
+                claude_bin: script_path,
+                ..BackendConfig::default()
+            },
+            ..Default::default()
+        });
+        let step = create_test_step(1);
+        let execute = || {
+            runner.execute_step(
+                &step,
+                "delta",
+                &run_dir,
+                dir.path(),
+                CancellationToken::new(),
+            )
+        };
+
+        let result = execute().await.unwrap();
+        assert_eq!(result.session_id.as_deref(), Some("sess-2"));
+        assert!(!std::fs::read_to_string(&args_path)
+            .unwrap()
+            .contains("--resume"));
+
+        runner.set_resume_session(Some("sess-1".to_string()));
+        execute().await.unwrap();
+        let args = std::fs::read_to_string(&args_path).unwrap();
+        assert!(args.contains("--resume\nsess-1\n"), "{args}");
+    }
+
+    #[test]
+    fn take_transcript_drains_entries_across_invocations() {
+        let runner = Runner::with_defaults();
+        assert!(runner.take_transcript().is_empty());
+
+        let event = serde_json::json!({"type": "assistant", "message": {"content": [
+            {"type": "text", "text": "attempt"}
+        ]}});
+        let mut builder = TranscriptBuilder::new();
+        builder.observe(&event, Utc::now());
+        let entries = builder.finish();
+        runner.append_transcript(entries.clone());
+        runner.append_transcript(entries);
+
+        let drained = runner.take_transcript();
+        assert_eq!(drained.iter().map(|e| e.seq).collect::<Vec<_>>(), [0, 1]);
+        assert!(runner.take_transcript().is_empty());
+    }
+
     #[tokio::test]
     async fn stream_claude_json_extracts_text_deltas() {
         let dir = TempDir::new().unwrap();
@@ -1425,7 +2670,15 @@ exit 0
         );
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
-        let result = stream_claude_json(reader, MAX_OUTPUT_BYTES, log_path.clone()).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path.clone(),
+            None,
+        )
+        .await
+        .unwrap();
 
         let text = String::from_utf8(result.text).unwrap();
         assert_eq!(text, "Hello world!");
@@ -1449,7 +2702,15 @@ exit 0
         );
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
-        let result = stream_claude_json(reader, MAX_OUTPUT_BYTES, log_path.clone()).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path.clone(),
+            None,
+        )
+        .await
+        .unwrap();
 
         assert_eq!(String::from_utf8(result.text).unwrap(), "ok!");
         assert!(!result.transient_api_error);
@@ -1467,7 +2728,15 @@ exit 0
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
         // Limit in-memory to 7 bytes.
-        let result = stream_claude_json(reader, 7, log_path.clone()).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            7,
+            log_path.clone(),
+            None,
+        )
+        .await
+        .unwrap();
 
         // In-memory buffer truncated at 7 bytes.
         assert_eq!(String::from_utf8(result.text).unwrap(), "abcdefg");
@@ -1490,7 +2759,15 @@ exit 0
         );
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
-        let result = stream_claude_json(reader, MAX_OUTPUT_BYTES, log_path.clone()).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path.clone(),
+            None,
+        )
+        .await
+        .unwrap();
 
         assert_eq!(String::from_utf8(result.text).unwrap(), "Hello world");
         assert!(!result.transient_api_error);
@@ -1513,7 +2790,15 @@ exit 0
         );
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
-        let result = stream_claude_json(reader, MAX_OUTPUT_BYTES, log_path.clone()).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path.clone(),
+            None,
+        )
+        .await
+        .unwrap();
 
         let text = String::from_utf8(result.text).unwrap();
         assert_eq!(text, "Hello from assistant and more text");
@@ -1522,6 +2807,44 @@ exit 0
         assert_eq!(on_disk, "Hello from assistant and more text");
     }
 
+    #[tokio::test]
+    async fn stream_claude_json_builds_transcript() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("output.log");
+
+        let stream_data = concat!(
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo build"}}]}}"#,
+            "\n",
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"error: build failed","is_error":true}]}}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Fixing the build."}]}}"#,
+            "\n",
+        );
+
+        let reader = tokio::io::BufReader::new(stream_data.as_bytes());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let kinds: Vec<_> = result.transcript.iter().map(|entry| entry.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TranscriptEntryKind::ToolUse,
+                TranscriptEntryKind::ToolResult,
+                TranscriptEntryKind::AssistantMessage,
+            ]
+        );
+        assert_eq!(result.transcript[1].tool_name.as_deref(), Some("Bash"));
+        assert!(result.transcript[1].is_error);
+    }
+
     #[tokio::test]
     async fn stream_claude_json_detects_transient_api_error() {
         let dir = TempDir::new().unwrap();
@@ -1533,9 +2856,45 @@ exit 0
         );
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
-        let result = stream_claude_json(reader, MAX_OUTPUT_BYTES, log_path).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(result.transient_api_error);
+        assert!(result.rate_limit.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_claude_json_detects_rate_limit_error_event() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("output.log");
+
+        let stream_data = concat!(
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"Too many requests"},"retry_after":12}"#,
+            "\n",
+        );
+
+        let reader = tokio::io::BufReader::new(stream_data.as_bytes());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
 
         assert!(result.transient_api_error);
+        let signal = result.rate_limit.unwrap();
+        assert_eq!(signal.kind, loop_core::RateLimitKind::RateLimited);
+        assert_eq!(signal.retry_after, Some(Duration::from_secs(12)));
     }
 
     #[tokio::test]
@@ -1548,9 +2907,43 @@ exit 0
         );
 
         let reader = tokio::io::BufReader::new(stream_data.as_bytes());
-        let result = stream_claude_json(reader, MAX_OUTPUT_BYTES, log_path).await.unwrap();
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(result.transient_api_error);
+        assert!(result.rate_limit.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_claude_json_detects_synthetic_rate_limit_text() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("output.log");
+
+        let stream_data = concat!(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"API Error: 529 {\"type\":\"overloaded_error\"}"}}"#, "\n",
+        );
+
+        let reader = tokio::io::BufReader::new(stream_data.as_bytes());
+        let result = stream_agent_output(
+            reader,
+            AgentOutputFormat::ClaudeStreamJson,
+            MAX_OUTPUT_BYTES,
+            log_path,
+            None,
+        )
+        .await
+        .unwrap();
 
         assert!(result.transient_api_error);
+        let signal = result.rate_limit.unwrap();
+        assert_eq!(signal.kind, loop_core::RateLimitKind::Overloaded);
     }
 
     #[tokio::test]
@@ -0,0 +1,473 @@
+//! Landlock sandbox for agent and verification processes.
+//!
+//! With `sandbox = fs` a sandboxed process may read anything but only write
+//! beneath its worktree, the repository's shared git directory, a per-run
+//! scratch dir (exported as `TMPDIR`), and any `sandbox_writable` paths. Agent
+//! processes may also write Claude's own state (`~/.claude`, `~/.claude.json`).
+//! `sandbox = fs+net` additionally denies TCP bind/connect, except that the
+//! agent may connect to `sandbox_agent_ports` (default 443) so it can reach its
+//! model API. Network access is therefore restricted, not disabled, for agents.
+//!
+//! The ruleset is built in the daemon before spawning and enforced in the
+//! child just before `exec`, so it also covers every tool the agent runs.
+//! Landlock reports breaches as `EACCES`/`EPERM` rather than killing the
+//! process; [`Sandbox::find_violation`] picks those out of failed command
+//! output, counting only denials this sandbox can have caused.
+
+use loop_core::{Config, SandboxMode, TranscriptEntry, TranscriptEntryKind};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::git;
+
+/// Output fragments that indicate a denied filesystem or network access.
+const VIOLATION_MARKERS: [&str; 4] = [
+    "Permission denied",
+    "Operation not permitted",
+    "EACCES",
+    "EPERM",
+];
+
+/// Scratch directory under the run directory, exported as `TMPDIR`.
+const SCRATCH_DIR: &str = "sandbox-tmp";
+
+/// Maximum length of a reported violation line.
+const MAX_VIOLATION_DETAIL_CHARS: usize = 500;
+
+#[derive(Debug, Error)]
+pub enum SandboxError {
+    #[error("sandbox requires Linux with Landlock enabled")]
+    Unsupported,
+    #[error("landlock ruleset error: {0}")]
+    #[cfg(target_os = "linux")]
+    Ruleset(#[from] landlock::RulesetError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Which kind of process is being sandboxed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxedProcess {
+    /// The agent backend process (and every tool it runs).
+    Agent,
+    /// A `verify_cmds` shell command.
+    Verification,
+}
+
+impl SandboxedProcess {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Agent => "agent",
+            Self::Verification => "verification",
+        }
+    }
+}
+
+/// Sandbox settings extracted from the run config.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxConfig {
+    pub mode: SandboxMode,
+    /// Extra writable paths (`sandbox_writable`).
+    pub writable: Vec<PathBuf>,
+    /// Ports the agent may connect to under `fs+net` (`sandbox_agent_ports`).
+    pub agent_ports: Vec<u16>,
+}
+
+impl SandboxConfig {
+    /// Create from loop-core Config.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            mode: config.sandbox,
+            writable: config.sandbox_writable.clone(),
+            agent_ports: config.sandbox_agent_ports.clone(),
+        }
+    }
+
+    /// Sandbox for a run's processes working in `working_dir`, or `None` when
+    /// disabled.
+    ///
+    /// The scratch dir under `run_dir` is created on [`Sandbox::apply`].
+    pub fn for_run(&self, working_dir: &Path, run_dir: &Path) -> Option<Sandbox> {
+        if !self.mode.restricts_fs() {
+            return None;
+        }
+
+        let scratch_dir = run_dir.join(SCRATCH_DIR);
+        let mut writable = vec![working_dir.to_path_buf(), scratch_dir.clone()];
+        match git::common_dir(working_dir) {
+            Ok(common_dir) => writable.push(common_dir),
+            Err(e) => debug!(
+                working_dir = %working_dir.display(),
+                error = %e,
+                "no git directory to add to the sandbox"
+            ),
+        }
+        writable.push(PathBuf::from("/dev/null"));
+        writable.extend(self.writable.iter().cloned());
+
+        Some(Sandbox {
+            mode: self.mode,
+            writable,
+            scratch_dir,
+            agent_ports: self.agent_ports.clone(),
+        })
+    }
+}
+
+/// A resolved sandbox for one working directory.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    mode: SandboxMode,
+    writable: Vec<PathBuf>,
+    scratch_dir: PathBuf,
+    agent_ports: Vec<u16>,
+}
+
+impl Sandbox {
+    pub fn mode(&self) -> SandboxMode {
+        self.mode
+    }
+
+    /// Paths a sandboxed process may write beneath (missing paths are skipped).
+    pub fn writable_paths(&self, process: SandboxedProcess) -> Vec<PathBuf> {
+        let mut paths = self.writable.clone();
+        if process == SandboxedProcess::Agent {
+            if let Some(home) = dirs::home_dir() {
+                paths.push(home.join(".claude"));
+                paths.push(home.join(".claude.json"));
+            }
+        }
+        paths
+    }
+
+    /// Restrict `cmd` to this sandbox once it is spawned.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cmd: &mut Command, process: SandboxedProcess) -> Result<(), SandboxError> {
+        use landlock::{
+            path_beneath_rules, AccessFs, AccessNet, CompatLevel, Compatible, NetPort, Ruleset,
+            RulesetAttr, RulesetCreatedAttr, ABI,
+        };
+        use std::os::fd::{AsRawFd, OwnedFd};
+
+        std::fs::create_dir_all(&self.scratch_dir)?;
+
+        let fs_access = AccessFs::from_write(ABI::V3);
+        let mut ruleset = Ruleset::default().handle_access(fs_access)?;
+        if self.mode.restricts_net() {
+            // Silently running with network access would defeat `fs+net`.
+            ruleset = ruleset
+                .set_compatibility(CompatLevel::HardRequirement)
+                .handle_access(AccessNet::BindTcp | AccessNet::ConnectTcp)?
+                .set_compatibility(CompatLevel::BestEffort);
+        }
+        let mut created = ruleset
+            .create()?
+            .add_rules(path_beneath_rules(self.writable_paths(process), fs_access))?;
+        if self.mode.restricts_net() && process == SandboxedProcess::Agent {
+            for &port in &self.agent_ports {
+                created = created.add_rule(NetPort::new(port, AccessNet::ConnectTcp))?;
+            }
+        }
+        let ruleset_fd: Option<OwnedFd> = created.into();
+        let ruleset_fd = ruleset_fd.ok_or(SandboxError::Unsupported)?;
+
+        cmd.env("TMPDIR", &self.scratch_dir);
+        // SAFETY: the hook runs in the forked child before exec and only makes
+        // async-signal-safe syscalls on a descriptor opened by the parent.
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if libc::syscall(libc::SYS_landlock_restrict_self, ruleset_fd.as_raw_fd(), 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Restrict `cmd` to this sandbox once it is spawned.
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(
+        &self,
+        _cmd: &mut Command,
+        _process: SandboxedProcess,
+    ) -> Result<(), SandboxError> {
+        Err(SandboxError::Unsupported)
+    }
+}
+
+/// Whether the running kernel can enforce the sandbox.
+#[cfg(target_os = "linux")]
+pub fn is_supported() -> bool {
+    use landlock::{AccessFs, Ruleset, RulesetAttr, ABI};
+    use std::os::fd::OwnedFd;
+
+    Ruleset::default()
+        .handle_access(AccessFs::from_write(ABI::V1))
+        .and_then(Ruleset::create)
+        .is_ok_and(|created| Option::<OwnedFd>::from(created).is_some())
+}
+
+/// Whether the running kernel can enforce the sandbox.
+#[cfg(not(target_os = "linux"))]
+pub fn is_supported() -> bool {
+    false
+}
+
+/// A denied access reported by a sandboxed process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxViolation {
+    pub process: SandboxedProcess,
+    /// Command or tool call that hit the sandbox.
+    pub command: Option<String>,
+    pub exit_code: Option<i32>,
+    /// Output line reporting the denied access.
+    pub detail: String,
+}
+
+impl Sandbox {
+    /// First output line reporting an access this sandbox denied.
+    ///
+    /// A denial only counts when the line names an absolute path outside the
+    /// process's writable set, or, under `fs+net`, a refused connection. Other
+    /// `Permission denied` failures (ssh auth errors, test output, a chmod'd
+    /// file in the worktree) happen without a sandbox too.
+    pub fn find_violation(&self, output: &str, process: SandboxedProcess) -> Option<String> {
+        let writable = self.writable_paths(process);
+        output
+            .lines()
+            .map(str::trim)
+            .filter(|line| VIOLATION_MARKERS.iter().any(|marker| line.contains(marker)))
+            .find(|line| {
+                denied_paths(line).any(|path| !is_beneath_any(path, &writable))
+                    || (self.mode.restricts_net() && line.to_ascii_lowercase().contains("connect"))
+            })
+            .map(|line| line.chars().take(MAX_VIOLATION_DETAIL_CHARS).collect())
+    }
+
+    /// Sandbox denials reported by failed tool calls in an agent transcript.
+    ///
+    /// Commands are redacted the same way as the action audit log.
+    pub fn transcript_violations(&self, entries: &[TranscriptEntry]) -> Vec<SandboxViolation> {
+        let calls: HashMap<&str, &TranscriptEntry> = entries
+            .iter()
+            .filter(|entry| entry.kind == TranscriptEntryKind::ToolUse)
+            .filter_map(|entry| Some((entry.tool_use_id.as_deref()?, entry)))
+            .collect();
+
+        entries
+            .iter()
+            .filter(|entry| entry.kind == TranscriptEntryKind::ToolResult && entry.is_error)
+            .filter_map(|entry| {
+                let detail =
+                    self.find_violation(entry.text.as_deref()?, SandboxedProcess::Agent)?;
+                let call = entry
+                    .tool_use_id
+                    .as_deref()
+                    .and_then(|id| calls.get(id).copied());
+                Some(SandboxViolation {
+                    process: SandboxedProcess::Agent,
+                    command: call.and_then(describe_tool_call),
+                    exit_code: None,
+                    detail,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Absolute paths mentioned in an output line, e.g. `'/etc/x'` or `/etc/x:`.
+fn denied_paths(line: &str) -> impl Iterator<Item = &Path> {
+    line.split(|c: char| c.is_whitespace() || matches!(c, '\'' | '"' | '`'))
+        .map(|token| token.trim_end_matches([':', ',', ';', ')']))
+        .filter(|token| token.starts_with('/') && token.len() > 1)
+        .map(Path::new)
+}
+
+/// Whether `path` lies beneath one of `roots`, as given or canonicalized.
+fn is_beneath_any(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| {
+        path.starts_with(root)
+            || root
+                .canonicalize()
+                .is_ok_and(|canonical| path.starts_with(canonical))
+    })
+}
+
+/// Short, redacted description of a tool call, e.g. `Write src/main.rs`.
+fn describe_tool_call(call: &TranscriptEntry) -> Option<String> {
+    let tool = call.tool_name.as_deref()?;
+    let arguments = call
+        .input
+        .as_ref()
+        .map(|input| loop_core::audit::redact_arguments(tool, input))
+        .unwrap_or_default();
+    let target = ["command", "file_path", "notebook_path", "url"]
+        .iter()
+        .find_map(|key| arguments.get(key).and_then(Value::as_str));
+    Some(match target {
+        Some(target) if tool == "Bash" => target.to_string(),
+        Some(target) => format!("{tool} {target}"),
+        None => tool.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use std::process::Stdio;
+    use tempfile::TempDir;
+
+    fn entry(kind: TranscriptEntryKind, seq: u32) -> TranscriptEntry {
+        TranscriptEntry {
+            seq,
+            kind,
+            text: None,
+            tool_use_id: Some("t1".to_string()),
+            tool_name: Some("Bash".to_string()),
+            input: None,
+            is_error: false,
+            at: Utc::now(),
+            duration_ms: None,
+        }
+    }
+
+    fn fs_sandbox(mode: SandboxMode, worktree: &Path) -> Sandbox {
+        SandboxConfig {
+            mode,
+            ..Default::default()
+        }
+        .for_run(worktree, Path::new("/tmp/loop-run"))
+        .unwrap()
+    }
+
+    #[test]
+    fn find_violation_returns_first_denial_outside_writable_paths() {
+        let sandbox = fs_sandbox(SandboxMode::Fs, Path::new("/work/tree"));
+        let output = "compiling\n\
+            chmod: cannot access '/work/tree/locked': Permission denied\n\
+            touch: cannot touch '/etc/x': Permission denied\n\
+            error: EPERM\n";
+        assert_eq!(
+            sandbox
+                .find_violation(output, SandboxedProcess::Verification)
+                .as_deref(),
+            Some("touch: cannot touch '/etc/x': Permission denied")
+        );
+        assert_eq!(
+            sandbox.find_violation("test result: FAILED", SandboxedProcess::Verification),
+            None
+        );
+    }
+
+    #[test]
+    fn ordinary_permission_failures_are_not_violations() {
+        let sandbox = fs_sandbox(SandboxMode::Fs, Path::new("/work/tree"));
+        for output in [
+            "git@github.com: Permission denied (publickey).",
+            "test io::tests::read_only ... FAILED: Permission denied (os error 13)",
+            "sh: ./run.sh: Permission denied",
+            "rm: cannot remove '/work/tree/target/x': Operation not permitted",
+            "cp: cannot create '/tmp/loop-run/sandbox-tmp/a': EACCES",
+            "curl: (7) Failed to connect to example.com port 80: Permission denied",
+        ] {
+            assert_eq!(
+                sandbox.find_violation(output, SandboxedProcess::Verification),
+                None,
+                "{output}"
+            );
+        }
+
+        // Under `fs+net` a refused connection is the sandbox's doing.
+        let sandbox = fs_sandbox(SandboxMode::FsNet, Path::new("/work/tree"));
+        assert!(sandbox
+            .find_violation(
+                "curl: (7) Failed to connect to example.com port 80: Permission denied",
+                SandboxedProcess::Verification
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn transcript_violations_use_failed_tool_results() {
+        let sandbox = fs_sandbox(SandboxMode::Fs, Path::new("/work/tree"));
+        let mut call = entry(TranscriptEntryKind::ToolUse, 0);
+        call.input = Some(json!({"command": "GITHUB_TOKEN=ghp_abcdefghijkl touch /etc/x"}));
+        let mut result = entry(TranscriptEntryKind::ToolResult, 1);
+        result.is_error = true;
+        result.text = Some("touch: cannot touch '/etc/x': Permission denied".to_string());
+        let mut ok = entry(TranscriptEntryKind::ToolResult, 2);
+        ok.text = Some("Permission denied is mentioned in this file".to_string());
+
+        let violations = sandbox.transcript_violations(&[call, result, ok]);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].process, SandboxedProcess::Agent);
+        let command = violations[0].command.as_deref().unwrap();
+        assert!(command.ends_with("touch /etc/x"), "{command}");
+        assert!(!command.contains("ghp_abcdefghijkl"));
+    }
+
+    #[test]
+    fn disabled_sandbox_resolves_to_none() {
+        let dir = TempDir::new().unwrap();
+        let config = SandboxConfig::default();
+        assert!(config.for_run(dir.path(), dir.path()).is_none());
+    }
+
+    #[tokio::test]
+    async fn fs_sandbox_limits_writes_to_worktree_and_scratch() {
+        if !is_supported() {
+            eprintln!("skipping: landlock unavailable");
+            return;
+        }
+        let dir = TempDir::new().unwrap();
+        let worktree = dir.path().join("worktree");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&worktree).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+
+        let sandbox = SandboxConfig {
+            mode: SandboxMode::Fs,
+            ..Default::default()
+        }
+        .for_run(&worktree, dir.path())
+        .unwrap();
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!(
+                "touch inside && touch \"$TMPDIR/tmp\" && touch {}/escaped",
+                outside.display()
+            ))
+            .current_dir(&worktree)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        sandbox
+            .apply(&mut cmd, SandboxedProcess::Verification)
+            .unwrap();
+        let output = cmd.output().await.unwrap();
+
+        assert!(!output.status.success());
+        assert!(worktree.join("inside").exists());
+        assert!(dir.path().join("sandbox-tmp/tmp").exists());
+        assert!(!outside.join("escaped").exists());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            sandbox
+                .find_violation(&stderr, SandboxedProcess::Verification)
+                .is_some(),
+            "{stderr}"
+        );
+    }
+}
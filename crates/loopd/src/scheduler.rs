@@ -3,22 +3,33 @@
 //! Implements run claiming, step enqueuing, and concurrency control.
 //! See spec Section 2.1, Section 4.2, Section 5.1.
 
-use loop_core::{Config, Id, QueuePolicy, Run, RunStatus, Step, StepPhase, StepStatus};
+use chrono::{DateTime, Utc};
+use loop_core::{
+    Config, Id, QueueBlock, QueueEntry, QueuePolicy, RetentionPolicy, Run, RunStatus, Step,
+    StepPhase, StepStatus,
+};
 
 #[cfg(test)]
 use loop_core::ReviewStatus;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::{Mutex, Semaphore};
 use tokio_util::sync::CancellationToken;
 
-use crate::storage::{Storage, StorageError};
+use crate::dependencies;
+use crate::governor::RateLimitGovernor;
+use crate::metrics::DaemonMetrics;
+use crate::storage::{StorageBackend, StorageError};
 
 /// Default maximum concurrent runs (spec says 2-5, defaulting to 3).
 pub const DEFAULT_MAX_CONCURRENT_RUNS: usize = 3;
 
+/// Default wait per priority level gained by a pending run (10 minutes).
+pub const DEFAULT_QUEUE_AGING_SEC: u64 = 600;
+
 #[derive(Debug, Error)]
 pub enum SchedulerError {
     #[error("storage error: {0}")]
@@ -35,7 +46,7 @@ pub type Result<T> = std::result::Result<T, SchedulerError>;
 
 /// Scheduler state and configuration.
 pub struct Scheduler {
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     /// Semaphore for concurrency limiting (backpressure).
     concurrency_semaphore: Arc<Semaphore>,
     /// Current number of active runs.
@@ -45,9 +56,13 @@ pub struct Scheduler {
     /// Maximum concurrent runs per workspace (optional).
     /// See spec Section 4.2, 5.3: per-workspace cap enforcement.
     max_runs_per_workspace: Option<usize>,
-    /// Queue policy: fifo (oldest first) or `newest_first`.
+    /// Queue policy: fifo (oldest first), `newest_first`, or `fair_share`.
     /// See spec Section 3.2, 5.3.
     queue_policy: QueuePolicy,
+    /// Fair-share weight per workspace root; unlisted workspaces weigh 1.
+    workspace_weights: HashMap<String, u32>,
+    /// A pending run gains one priority level per `queue_aging` waited.
+    queue_aging: Option<Duration>,
     /// Counter for queue blocked events (per-workspace cap).
     /// See extended spec Section 7.2.
     queue_blocked_workspace: AtomicUsize,
@@ -59,6 +74,16 @@ pub struct Scheduler {
     cancel_token: CancellationToken,
     /// Per-run cancellation tokens (child tokens of `cancel_token`).
     run_tokens: Mutex<HashMap<Id, CancellationToken>>,
+    /// Daemon-wide spend cap (USD) per UTC day across all workspaces.
+    daily_cost_cap_usd: Option<f64>,
+    /// Step, verification and watchdog metrics for `GET /metrics`.
+    metrics: DaemonMetrics,
+    /// Daemon-wide API rate-limit backoff shared by every run's runners.
+    governor: Arc<RateLimitGovernor>,
+    /// Whether runs hand their steps to workers (distributed mode).
+    distributed: bool,
+    /// Which finished runs the garbage collector prunes.
+    retention: RetentionPolicy,
 }
 
 impl std::fmt::Debug for Scheduler {
@@ -68,6 +93,11 @@ impl std::fmt::Debug for Scheduler {
             .field("max_concurrent", &self.max_concurrent)
             .field("max_runs_per_workspace", &self.max_runs_per_workspace)
             .field("queue_policy", &self.queue_policy)
+            .field("workspace_weights", &self.workspace_weights)
+            .field("queue_aging", &self.queue_aging)
+            .field("daily_cost_cap_usd", &self.daily_cost_cap_usd)
+            .field("distributed", &self.distributed)
+            .field("retention", &self.retention)
             .field("shutdown", &self.shutdown.load(Ordering::Relaxed))
             .finish_non_exhaustive()
     }
@@ -75,7 +105,8 @@ impl std::fmt::Debug for Scheduler {
 
 impl Scheduler {
     /// Create a new scheduler with the given storage backend.
-    pub fn new(storage: Arc<Storage>, max_concurrent: usize) -> Self {
+    pub fn new(storage: Arc<dyn StorageBackend>, max_concurrent: usize) -> Self {
+        let governor = Arc::new(RateLimitGovernor::new(Some(Arc::clone(&storage))));
         Self {
             storage,
             concurrency_semaphore: Arc::new(Semaphore::new(max_concurrent)),
@@ -83,11 +114,18 @@ impl Scheduler {
             max_concurrent,
             max_runs_per_workspace: None,
             queue_policy: QueuePolicy::Fifo,
+            workspace_weights: HashMap::new(),
+            queue_aging: None,
             queue_blocked_workspace: AtomicUsize::new(0),
             claim_lock: Mutex::new(()),
             shutdown: std::sync::atomic::AtomicBool::new(false),
             cancel_token: CancellationToken::new(),
             run_tokens: Mutex::new(HashMap::new()),
+            daily_cost_cap_usd: None,
+            metrics: DaemonMetrics::new(),
+            governor,
+            distributed: false,
+            retention: RetentionPolicy::default(),
         }
     }
 
@@ -95,10 +133,11 @@ impl Scheduler {
     ///
     /// See spec Section 4.2, 5.3: per-workspace run caps.
     pub fn new_with_workspace_cap(
-        storage: Arc<Storage>,
+        storage: Arc<dyn StorageBackend>,
         max_concurrent: usize,
         max_runs_per_workspace: usize,
     ) -> Self {
+        let governor = Arc::new(RateLimitGovernor::new(Some(Arc::clone(&storage))));
         Self {
             storage,
             concurrency_semaphore: Arc::new(Semaphore::new(max_concurrent)),
@@ -106,11 +145,18 @@ impl Scheduler {
             max_concurrent,
             max_runs_per_workspace: Some(max_runs_per_workspace),
             queue_policy: QueuePolicy::Fifo,
+            workspace_weights: HashMap::new(),
+            queue_aging: None,
             queue_blocked_workspace: AtomicUsize::new(0),
             claim_lock: Mutex::new(()),
             shutdown: std::sync::atomic::AtomicBool::new(false),
             cancel_token: CancellationToken::new(),
             run_tokens: Mutex::new(HashMap::new()),
+            daily_cost_cap_usd: None,
+            metrics: DaemonMetrics::new(),
+            governor,
+            distributed: false,
+            retention: RetentionPolicy::default(),
         }
     }
 
@@ -118,11 +164,12 @@ impl Scheduler {
     ///
     /// See spec Section 3.2, 4.2, 5.3: queue policy and per-workspace caps.
     pub fn new_with_policy(
-        storage: Arc<Storage>,
+        storage: Arc<dyn StorageBackend>,
         max_concurrent: usize,
         max_runs_per_workspace: Option<usize>,
         queue_policy: QueuePolicy,
     ) -> Self {
+        let governor = Arc::new(RateLimitGovernor::new(Some(Arc::clone(&storage))));
         Self {
             storage,
             concurrency_semaphore: Arc::new(Semaphore::new(max_concurrent)),
@@ -130,19 +177,77 @@ impl Scheduler {
             max_concurrent,
             max_runs_per_workspace,
             queue_policy,
+            workspace_weights: HashMap::new(),
+            queue_aging: None,
             queue_blocked_workspace: AtomicUsize::new(0),
             claim_lock: Mutex::new(()),
             shutdown: std::sync::atomic::AtomicBool::new(false),
             cancel_token: CancellationToken::new(),
             run_tokens: Mutex::new(HashMap::new()),
+            daily_cost_cap_usd: None,
+            metrics: DaemonMetrics::new(),
+            governor,
+            distributed: false,
+            retention: RetentionPolicy::default(),
         }
     }
 
     /// Create a scheduler with default concurrency settings.
-    pub fn with_defaults(storage: Arc<Storage>) -> Self {
+    pub fn with_defaults(storage: Arc<dyn StorageBackend>) -> Self {
         Self::new(storage, DEFAULT_MAX_CONCURRENT_RUNS)
     }
 
+    /// Set the daemon-wide daily spend cap (USD). `None` disables the cap.
+    #[must_use]
+    pub fn with_daily_cost_cap(mut self, cap_usd: Option<f64>) -> Self {
+        self.daily_cost_cap_usd = cap_usd.filter(|cap| *cap > 0.0);
+        self
+    }
+
+    /// Hand every run's steps to workers instead of executing them in-process.
+    #[must_use]
+    pub fn with_distributed(mut self, distributed: bool) -> Self {
+        self.distributed = distributed;
+        self
+    }
+
+    /// Set the retention policy applied by the garbage collector.
+    #[must_use]
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// Set fair-share weights by workspace root. Weights below 1 count as 1.
+    #[must_use]
+    pub fn with_workspace_weights(mut self, weights: HashMap<String, u32>) -> Self {
+        self.workspace_weights = weights;
+        self
+    }
+
+    /// Raise a pending run's priority by one level per `aging` waited. `None` disables aging.
+    #[must_use]
+    pub fn with_queue_aging(mut self, aging: Option<Duration>) -> Self {
+        self.queue_aging = aging.filter(|aging| !aging.is_zero());
+        self
+    }
+
+    /// Check the daily spend cap against cost recorded since UTC midnight.
+    ///
+    /// Returns `Some((spent, cap))` once the cap has been reached.
+    pub async fn daily_budget_exceeded(&self) -> Result<Option<(f64, f64)>> {
+        let Some(cap) = self.daily_cost_cap_usd else {
+            return Ok(None);
+        };
+        let midnight = chrono::Utc::now()
+            .date_naive()
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+            .timestamp_millis();
+        let spent = self.storage.cost_since(midnight).await?;
+        Ok((spent >= cap).then_some((spent, cap)))
+    }
+
     /// Get the queue blocked workspace counter value.
     ///
     /// See extended spec Section 7.2: metrics.
@@ -155,11 +260,46 @@ impl Scheduler {
         self.active_runs.load(Ordering::SeqCst)
     }
 
+    /// Get the daemon metrics recorded by this scheduler and the run loop.
+    pub fn metrics(&self) -> &DaemonMetrics {
+        &self.metrics
+    }
+
+    /// Get the API rate-limit governor shared by all runs.
+    pub fn governor(&self) -> &Arc<RateLimitGovernor> {
+        &self.governor
+    }
+
+    /// Get the storage the scheduler claims runs from.
+    pub fn storage(&self) -> &Arc<dyn StorageBackend> {
+        &self.storage
+    }
+
+    /// Whether steps run on workers (distributed mode).
+    pub fn is_distributed(&self) -> bool {
+        self.distributed
+    }
+
+    /// Get the retention policy for finished runs.
+    pub fn retention(&self) -> &RetentionPolicy {
+        &self.retention
+    }
+
     /// Get the maximum concurrent runs.
     pub fn max_concurrent(&self) -> usize {
         self.max_concurrent
     }
 
+    /// Get the queue policy.
+    pub fn queue_policy(&self) -> QueuePolicy {
+        self.queue_policy
+    }
+
+    /// Get the per-workspace run limit, if any.
+    pub fn max_runs_per_workspace(&self) -> Option<usize> {
+        self.max_runs_per_workspace
+    }
+
     /// Check if the scheduler can accept more runs.
     pub fn has_capacity(&self) -> bool {
         self.active_run_count() < self.max_concurrent
@@ -207,6 +347,7 @@ impl Scheduler {
     /// - No pending runs exist
     /// - Concurrency limit reached (blocks until slot available)
     /// - All pending runs are blocked by per-workspace cap
+    /// - The daily spend cap has been reached
     /// - Scheduler is shutting down
     ///
     /// On success, transitions the run to RUNNING status.
@@ -244,30 +385,29 @@ impl Scheduler {
         // Lock to prevent race conditions during claim.
         let _lock = self.claim_lock.lock().await;
 
+        // Runs whose dependencies can no longer be met are canceled; runs
+        // still waiting on one stay pending.
+        let dependencies = dependencies::cancel_unmeetable(&*self.storage).await?;
+
+        // Pending runs stay queued until the daily spend cap resets.
+        if let Some((spent, cap)) = self.daily_budget_exceeded().await? {
+            tracing::debug!(spent, cap, "daily spend cap reached; not claiming runs");
+            drop(_permit);
+            return Ok(None);
+        }
+
         // Find the next pending run based on queue policy (spec Section 5.3).
-        // list_runs returns DESC order (newest first).
-        let runs = self.storage.list_runs(None).await?;
-        let pending_runs: Vec<Run> = runs
-            .into_iter()
-            .filter(|r| r.status == RunStatus::Pending)
-            .collect();
+        let running = self.storage.count_running_runs_by_workspace().await?;
+        let queue = self.ordered_queue(&running).await?;
 
         // Find the first pending run that isn't blocked by workspace cap.
-        // Iterate based on queue_policy:
-        // - Fifo: reverse order (oldest first)
-        // - NewestFirst: forward order (newest first, as returned by list_runs)
         let mut selected_run = None;
-        let run_iter: Box<dyn Iterator<Item = &Run>> = match self.queue_policy {
-            QueuePolicy::Fifo => Box::new(pending_runs.iter().rev()),
-            QueuePolicy::NewestFirst => Box::new(pending_runs.iter()),
-        };
-
-        for run in run_iter {
+        for (run, _) in queue {
+            if dependencies.blocking(&run.id).is_some() {
+                continue;
+            }
             if let Some(max_per_ws) = self.max_runs_per_workspace {
-                let running_in_workspace = self
-                    .storage
-                    .count_running_runs_for_workspace(&run.workspace_root)
-                    .await?;
+                let running_in_workspace = running.get(&run.workspace_root).copied().unwrap_or(0);
 
                 if running_in_workspace >= max_per_ws {
                     // Blocked by per-workspace cap (spec Section 6.2).
@@ -283,7 +423,7 @@ impl Scheduler {
                     continue;
                 }
             }
-            selected_run = Some(run.clone());
+            selected_run = Some(run);
             break;
         }
 
@@ -293,6 +433,8 @@ impl Scheduler {
             return Ok(None);
         };
 
+        dependencies::apply_branch_from(&*self.storage, &run).await?;
+
         // Transition to RUNNING.
         self.storage
             .update_run_status(&run.id, RunStatus::Running)
@@ -309,6 +451,84 @@ impl Scheduler {
         Ok(Some(updated_run))
     }
 
+    /// Cancel pending runs whose dependencies can no longer be met, without
+    /// waiting for a run slot. Serialized with claims so a run is never
+    /// canceled twice.
+    pub async fn cancel_unmeetable_runs(&self) -> Result<()> {
+        let _lock = self.claim_lock.lock().await;
+        dependencies::cancel_unmeetable(&*self.storage).await?;
+        Ok(())
+    }
+
+    /// The pending queue in claim order, with why each run is not claimable yet.
+    ///
+    /// Simulates successive claims against the current running counts, so a
+    /// run behind another from the same capped workspace shows as blocked.
+    pub async fn queue(&self) -> Result<Vec<QueueEntry>> {
+        let dependencies = dependencies::check_pending(&*self.storage).await?;
+        let daily_cap = self.daily_budget_exceeded().await?;
+        let running = self.storage.count_running_runs_by_workspace().await?;
+        let queue = self.ordered_queue(&running).await?;
+
+        let mut claimed = running;
+        let mut free_slots = self.max_concurrent.saturating_sub(self.active_run_count());
+        Ok(queue
+            .into_iter()
+            .map(|(run, effective_priority)| {
+                let in_workspace = claimed.entry(run.workspace_root.clone()).or_default();
+                let blocked = if let Some(dependency) = dependencies.blocking(&run.id) {
+                    Some(QueueBlock::Dependency {
+                        depends_on: dependency.run_id.clone(),
+                        condition: dependency.condition,
+                    })
+                } else if let Some((spent_usd, cap_usd)) = daily_cap {
+                    Some(QueueBlock::DailyCostCap { spent_usd, cap_usd })
+                } else if let Some(cap) = self
+                    .max_runs_per_workspace
+                    .filter(|cap| *in_workspace >= *cap)
+                {
+                    Some(QueueBlock::WorkspaceCap { cap })
+                } else if free_slots == 0 {
+                    Some(QueueBlock::Concurrency {
+                        max: self.max_concurrent,
+                    })
+                } else {
+                    free_slots -= 1;
+                    *in_workspace += 1;
+                    None
+                };
+                QueueEntry {
+                    run_id: run.id,
+                    name: run.name,
+                    workspace_root: run.workspace_root,
+                    priority: run.priority,
+                    effective_priority,
+                    created_at: run.created_at,
+                    blocked,
+                }
+            })
+            .collect())
+    }
+
+    /// Pending runs in claim order, paired with their effective priority.
+    async fn ordered_queue(&self, running: &HashMap<String, usize>) -> Result<Vec<(Run, i64)>> {
+        let pending: Vec<Run> = self
+            .storage
+            .list_runs(None)
+            .await?
+            .into_iter()
+            .filter(|r| r.status == RunStatus::Pending)
+            .collect();
+        Ok(order_queue(
+            pending,
+            self.queue_policy,
+            running,
+            &self.workspace_weights,
+            self.queue_aging,
+            Utc::now(),
+        ))
+    }
+
     /// Resume runs that were RUNNING when the daemon stopped.
     ///
     /// Called at daemon startup to recover from crashes (Section 5.2).
@@ -374,6 +594,7 @@ impl Scheduler {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         };
 
         self.storage.insert_step(&step).await?;
@@ -390,11 +611,8 @@ impl Scheduler {
             ));
         }
 
-        // Update with started_at timestamp.
-        // Note: storage.update_step sets ended_at, so we do a direct update here.
-        self.storage
-            .update_step(step_id, StepStatus::InProgress, None, None)
-            .await?;
+        // Update with started_at timestamp (update_step would set ended_at instead).
+        self.storage.mark_step_started(step_id).await?;
         Ok(())
     }
 
@@ -417,6 +635,12 @@ impl Scheduler {
         self.storage
             .update_step(step_id, status, exit_code, output_path)
             .await?;
+
+        if let Some(started_at) = step.started_at {
+            let duration_ms = (chrono::Utc::now() - started_at).num_milliseconds();
+            self.metrics
+                .observe_step(step.phase, status, u64::try_from(duration_ms).unwrap_or(0));
+        }
         Ok(())
     }
 
@@ -596,6 +820,9 @@ impl Scheduler {
     /// When `reviewer=true` (default): implementation -> review -> verification
     /// When `reviewer=false`: implementation -> verification (skip review)
     ///
+    /// When `planner=true` and the run has no plan yet, a planning step runs
+    /// before the first implementation step.
+    ///
     /// Verification failure handling (Section 5.2):
     /// When verification fails, we requeue implementation (do not advance plan).
     /// Runner notes are written by the verifier module.
@@ -625,12 +852,18 @@ impl Scheduler {
 
         match last_succeeded {
             None => {
-                // No steps completed yet; start with implementation.
-                Ok(Some(StepPhase::Implementation))
+                // No steps completed yet; generate a plan first if configured,
+                // otherwise start with implementation.
+                if run.plan_path.is_none() && Self::is_planner_enabled(&run) {
+                    Ok(Some(StepPhase::Planning))
+                } else {
+                    Ok(Some(StepPhase::Implementation))
+                }
             }
             Some(step) => {
                 // Determine next phase based on last completed.
                 match step.phase {
+                    StepPhase::Planning => Ok(Some(StepPhase::Implementation)),
                     StepPhase::Implementation => {
                         // Skip review if reviewer is disabled.
                         if reviewer_enabled {
@@ -663,48 +896,164 @@ impl Scheduler {
     /// Parses the run's `config_json` to check the `reviewer` field.
     /// Defaults to true per spec Section 4.1 (config.rs default).
     fn is_reviewer_enabled(run: &Run) -> bool {
+        Self::run_config_flag(run, "reviewer", |config| config.reviewer, true)
+    }
+
+    /// Check if the planning phase is enabled for a run (`planner`, default false).
+    fn is_planner_enabled(run: &Run) -> bool {
+        Self::run_config_flag(run, "planner", |config| config.planner, false)
+    }
+
+    /// Read a boolean key from the run's `config_json`, falling back to `default`.
+    fn run_config_flag(
+        run: &Run,
+        key: &str,
+        from_config: fn(&Config) -> bool,
+        default: bool,
+    ) -> bool {
         if let Some(config_json) = &run.config_json {
-            // Try to parse as full Config or just extract reviewer field.
+            // Try to parse as full Config or just extract the field.
             if let Ok(config) = serde_json::from_str::<Config>(config_json) {
-                return config.reviewer;
+                return from_config(&config);
             }
-            // Fallback: try to parse as a partial JSON object with just reviewer.
+            // Fallback: try to parse as a partial JSON object with just the field.
             if let Ok(obj) = serde_json::from_str::<serde_json::Value>(config_json) {
-                if let Some(reviewer) = obj.get("reviewer").and_then(serde_json::Value::as_bool) {
-                    return reviewer;
+                if let Some(value) = obj.get(key).and_then(serde_json::Value::as_bool) {
+                    return value;
                 }
             }
         }
-        // Default: reviewer enabled.
-        true
+        default
+    }
+}
+
+/// A run's priority plus one level per full `aging` period it has waited.
+fn effective_priority(run: &Run, aging: Option<Duration>, now: DateTime<Utc>) -> i64 {
+    let boost = aging.map_or(0, |aging| {
+        let waited = (now - run.created_at).num_seconds().max(0);
+        waited / i64::try_from(aging.as_secs().max(1)).unwrap_or(i64::MAX)
+    });
+    i64::from(run.priority).saturating_add(boost)
+}
+
+/// Order pending runs for claiming.
+///
+/// Runs are ranked by effective priority, then by age as the policy says.
+/// Aging boosts a priority level as a whole: each level keeps the policy's age
+/// order and competes with the other levels through its head run, so under
+/// `NewestFirst` a stale run never jumps ahead of newer runs at its own level.
+/// Under `FairShare` the next run comes from the workspace with the fewest
+/// running and already-ordered runs per unit of weight, so one busy workspace
+/// cannot starve the others; priority orders runs within a workspace and
+/// breaks ties between workspaces.
+fn order_queue(
+    pending: Vec<Run>,
+    policy: QueuePolicy,
+    running: &HashMap<String, usize>,
+    weights: &HashMap<String, u32>,
+    aging: Option<Duration>,
+    now: DateTime<Utc>,
+) -> Vec<(Run, i64)> {
+    // Run IDs are UUIDv7, so they break creation-time ties in creation order.
+    let by_age = |a: &Run, b: &Run| {
+        let oldest_first = a
+            .created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.id.as_ref().cmp(b.id.as_ref()));
+        if policy == QueuePolicy::NewestFirst {
+            oldest_first.reverse()
+        } else {
+            oldest_first
+        }
+    };
+
+    let mut levels: BTreeMap<i32, VecDeque<(Run, i64)>> = BTreeMap::new();
+    for run in pending {
+        let effective = effective_priority(&run, aging, now);
+        levels
+            .entry(run.priority)
+            .or_default()
+            .push_back((run, effective));
+    }
+    for runs in levels.values_mut() {
+        runs.make_contiguous()
+            .sort_by(|(a, _), (b, _)| by_age(a, b));
+    }
+
+    let mut ranked = Vec::new();
+    loop {
+        let next = levels
+            .iter()
+            .filter_map(|(level, runs)| runs.front().map(|head| (level, head)))
+            .min_by(|(_, (run_a, pa)), (_, (run_b, pb))| {
+                pb.cmp(pa).then_with(|| by_age(run_a, run_b))
+            })
+            .map(|(level, _)| *level);
+        let Some(level) = next else {
+            break;
+        };
+        if let Some(entry) = levels.get_mut(&level).and_then(VecDeque::pop_front) {
+            ranked.push(entry);
+        }
+    }
+    if policy != QueuePolicy::FairShare {
+        return ranked;
+    }
+
+    let mut workspaces: BTreeMap<String, VecDeque<(Run, i64)>> = BTreeMap::new();
+    for entry in ranked {
+        workspaces
+            .entry(entry.0.workspace_root.clone())
+            .or_default()
+            .push_back(entry);
+    }
+    let mut load: HashMap<String, u64> = workspaces
+        .keys()
+        .map(|ws| (ws.clone(), running.get(ws).copied().unwrap_or(0) as u64))
+        .collect();
+    let weight = |ws: &str| u64::from(weights.get(ws).copied().unwrap_or(1).max(1));
+
+    let mut ordered = Vec::new();
+    loop {
+        // Lowest load per weight first (compared without division), then the
+        // workspace whose head run ranks higher.
+        let next = workspaces
+            .iter()
+            .filter_map(|(ws, runs)| runs.front().map(|head| (ws, head)))
+            .min_by(|(ws_a, (run_a, pa)), (ws_b, (run_b, pb))| {
+                (load[*ws_a] * weight(ws_b))
+                    .cmp(&(load[*ws_b] * weight(ws_a)))
+                    .then_with(|| pb.cmp(pa))
+                    .then_with(|| run_a.created_at.cmp(&run_b.created_at))
+            })
+            .map(|(ws, _)| ws.clone());
+        let Some(ws) = next else {
+            break;
+        };
+        if let Some(entry) = workspaces.get_mut(&ws).and_then(VecDeque::pop_front) {
+            ordered.push(entry);
+        }
+        *load.entry(ws).or_default() += 1;
     }
+    ordered
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::DEFAULT_MAX_CONCURRENT_RUNS;
-    use chrono::Utc;
+    use crate::storage::testing::{backend_test, open, TestBackend};
     use loop_core::RunNameSource;
-    use tempfile::TempDir;
 
     struct TestScheduler {
         scheduler: Scheduler,
-        _dir: TempDir,
+        _backend: TestBackend,
     }
 
-    async fn create_test_scheduler() -> TestScheduler {
-        let dir = TempDir::new().unwrap();
-        let db_path = dir.path().join("test.db");
-        let storage = Storage::new(&db_path, DEFAULT_MAX_CONCURRENT_RUNS)
-            .await
-            .unwrap();
-        storage.migrate_embedded().await.unwrap();
-        let storage = Arc::new(storage);
-        let scheduler = Scheduler::new(storage, 2);
+    fn create_test_scheduler(backend: TestBackend) -> TestScheduler {
+        let storage = Arc::clone(&backend.storage);
         TestScheduler {
-            scheduler,
-            _dir: dir,
+            scheduler: Scheduler::new(storage, 2),
+            _backend: backend,
         }
     }
 
@@ -728,19 +1077,64 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         }
     }
 
-    #[tokio::test]
-    async fn claim_next_run_returns_none_when_empty() {
-        let ts = create_test_scheduler().await;
+    backend_test!(claim_next_run_returns_none_when_empty, open);
+    async fn claim_next_run_returns_none_when_empty(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let result = ts.scheduler.claim_next_run().await.unwrap();
         assert!(result.is_none());
     }
 
-    #[tokio::test]
-    async fn claim_next_run_transitions_to_running() {
-        let ts = create_test_scheduler().await;
+    backend_test!(claim_skips_runs_until_dependencies_are_met, open);
+    async fn claim_skips_runs_until_dependencies_are_met(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
+        let storage = &ts.scheduler.storage;
+        let first = create_test_run("run-1");
+        storage.insert_run(&first).await.unwrap();
+        let dependent = Run {
+            created_at: first.created_at - chrono::Duration::seconds(1),
+            ..create_test_run("run-2")
+        };
+        storage
+            .insert_run_with_dependencies(
+                &dependent,
+                &[loop_core::RunDependency {
+                    run_id: first.id.clone(),
+                    condition: loop_core::DependencyCondition::Completed,
+                    branch_from: false,
+                }],
+            )
+            .await
+            .unwrap();
+
+        // The dependent run is older but waits on run-1.
+        let queue = ts.scheduler.queue().await.unwrap();
+        assert_eq!(queue[0].run_id, dependent.id);
+        assert_eq!(
+            queue[0].blocked,
+            Some(QueueBlock::Dependency {
+                depends_on: first.id.clone(),
+                condition: loop_core::DependencyCondition::Completed,
+            })
+        );
+        let claimed = ts.scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert!(ts.scheduler.claim_next_run().await.unwrap().is_none());
+
+        ts.scheduler
+            .release_run(&first.id, RunStatus::Completed)
+            .await
+            .unwrap();
+        let claimed = ts.scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(claimed.id, dependent.id);
+    }
+
+    backend_test!(claim_next_run_transitions_to_running, open);
+    async fn claim_next_run_transitions_to_running(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
 
@@ -752,9 +1146,43 @@ mod tests {
         assert_eq!(ts.scheduler.active_run_count(), 1);
     }
 
-    #[tokio::test]
-    async fn respects_concurrency_limit() {
-        let ts = create_test_scheduler().await;
+    backend_test!(daily_cost_cap_holds_pending_runs, open);
+    async fn daily_cost_cap_holds_pending_runs(backend: TestBackend) {
+        let storage = Arc::clone(&backend.storage);
+        let scheduler = Scheduler::new(Arc::clone(&storage), 2).with_daily_cost_cap(Some(1.0));
+
+        // Another run already spent the whole cap today.
+        let mut spent_run = create_test_run("run-spent");
+        spent_run.status = RunStatus::Running;
+        storage.insert_run(&spent_run).await.unwrap();
+        let step = scheduler
+            .enqueue_step(&spent_run.id, StepPhase::Implementation)
+            .await
+            .unwrap();
+        let usage = loop_core::TokenUsage {
+            cost_usd: 1.5,
+            ..loop_core::TokenUsage::default()
+        };
+        storage
+            .record_step_usage(&spent_run.id, &step.id, None, &usage)
+            .await
+            .unwrap();
+
+        storage.insert_run(&create_test_run("run-1")).await.unwrap();
+        let exceeded = scheduler.daily_budget_exceeded().await.unwrap();
+        assert_eq!(exceeded, Some((1.5, 1.0)));
+        assert!(scheduler.claim_next_run().await.unwrap().is_none());
+        assert_eq!(scheduler.active_run_count(), 0);
+
+        // Without a cap the pending run is claimed.
+        let uncapped = Scheduler::new(Arc::clone(&storage), 2).with_daily_cost_cap(None);
+        assert!(uncapped.daily_budget_exceeded().await.unwrap().is_none());
+        assert!(uncapped.claim_next_run().await.unwrap().is_some());
+    }
+
+    backend_test!(respects_concurrency_limit, open);
+    async fn respects_concurrency_limit(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
 
         // Insert 3 runs but limit is 2.
         for i in 0..3 {
@@ -770,9 +1198,9 @@ mod tests {
         assert!(!ts.scheduler.has_capacity());
     }
 
-    #[tokio::test]
-    async fn enqueue_step_creates_queued_step() {
-        let ts = create_test_scheduler().await;
+    backend_test!(enqueue_step_creates_queued_step, open);
+    async fn enqueue_step_creates_queued_step(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
 
@@ -790,9 +1218,9 @@ mod tests {
         assert_eq!(step.attempt, 1);
     }
 
-    #[tokio::test]
-    async fn release_run_frees_capacity() {
-        let ts = create_test_scheduler().await;
+    backend_test!(release_run_frees_capacity, open);
+    async fn release_run_frees_capacity(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
 
@@ -807,9 +1235,9 @@ mod tests {
         assert!(ts.scheduler.has_capacity());
     }
 
-    #[tokio::test]
-    async fn determine_next_phase_starts_with_implementation() {
-        let ts = create_test_scheduler().await;
+    backend_test!(determine_next_phase_starts_with_implementation, open);
+    async fn determine_next_phase_starts_with_implementation(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -818,6 +1246,50 @@ mod tests {
         assert_eq!(phase, Some(StepPhase::Implementation));
     }
 
+    backend_test!(determine_next_phase_plans_first_when_no_plan, open);
+    async fn determine_next_phase_plans_first_when_no_plan(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
+        let mut run = create_test_run("run-1");
+        run.plan_path = None;
+        run.config_json = Some(r#"{"planner": true}"#.to_string());
+        ts.scheduler.storage.insert_run(&run).await.unwrap();
+        ts.scheduler.claim_next_run().await.unwrap();
+
+        let phase = ts.scheduler.determine_next_phase(&run.id).await.unwrap();
+        assert_eq!(phase, Some(StepPhase::Planning));
+
+        let step = ts
+            .scheduler
+            .enqueue_step(&run.id, StepPhase::Planning)
+            .await
+            .unwrap();
+        ts.scheduler.start_step(&step.id).await.unwrap();
+        ts.scheduler
+            .complete_step(&step.id, StepStatus::Succeeded, Some(0), None)
+            .await
+            .unwrap();
+
+        let phase = ts.scheduler.determine_next_phase(&run.id).await.unwrap();
+        assert_eq!(phase, Some(StepPhase::Implementation));
+    }
+
+    backend_test!(
+        determine_next_phase_skips_planning_with_plan_or_when_disabled,
+        open
+    );
+    async fn determine_next_phase_skips_planning_with_plan_or_when_disabled(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
+        let mut with_plan = create_test_run("run-1");
+        with_plan.config_json = Some(r#"{"planner": true}"#.to_string());
+        let mut disabled = create_test_run("run-2");
+        disabled.plan_path = None;
+        for run in [&with_plan, &disabled] {
+            ts.scheduler.storage.insert_run(run).await.unwrap();
+            let phase = ts.scheduler.determine_next_phase(&run.id).await.unwrap();
+            assert_eq!(phase, Some(StepPhase::Implementation));
+        }
+    }
+
     #[test]
     fn is_reviewer_enabled_defaults_to_true() {
         let run = create_test_run("run-1");
@@ -845,6 +1317,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         assert!(!Scheduler::is_reviewer_enabled(&run));
 
@@ -853,9 +1326,9 @@ mod tests {
         assert!(Scheduler::is_reviewer_enabled(&run));
     }
 
-    #[tokio::test]
-    async fn determine_next_phase_goes_to_review_when_enabled() {
-        let ts = create_test_scheduler().await;
+    backend_test!(determine_next_phase_goes_to_review_when_enabled, open);
+    async fn determine_next_phase_goes_to_review_when_enabled(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -877,9 +1350,9 @@ mod tests {
         assert_eq!(phase, Some(StepPhase::Review));
     }
 
-    #[tokio::test]
-    async fn determine_next_phase_skips_review_when_disabled() {
-        let ts = create_test_scheduler().await;
+    backend_test!(determine_next_phase_skips_review_when_disabled, open);
+    async fn determine_next_phase_skips_review_when_disabled(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let now = Utc::now();
         let run = Run {
             id: Id::from_string("run-no-review"),
@@ -899,6 +1372,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -920,9 +1394,9 @@ mod tests {
         assert_eq!(phase, Some(StepPhase::Verification));
     }
 
-    #[tokio::test]
-    async fn determine_next_phase_review_to_verification() {
-        let ts = create_test_scheduler().await;
+    backend_test!(determine_next_phase_review_to_verification, open);
+    async fn determine_next_phase_review_to_verification(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -955,9 +1429,14 @@ mod tests {
         assert_eq!(phase, Some(StepPhase::Verification));
     }
 
-    #[tokio::test]
-    async fn determine_next_phase_verification_failure_requeues_implementation() {
-        let ts = create_test_scheduler().await;
+    backend_test!(
+        determine_next_phase_verification_failure_requeues_implementation,
+        open
+    );
+    async fn determine_next_phase_verification_failure_requeues_implementation(
+        backend: TestBackend,
+    ) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -1003,9 +1482,35 @@ mod tests {
         assert_eq!(phase, Some(StepPhase::Implementation));
     }
 
-    #[tokio::test]
-    async fn determine_next_phase_verification_success_continues() {
-        let ts = create_test_scheduler().await;
+    backend_test!(complete_step_records_metrics, open);
+    async fn complete_step_records_metrics(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
+        let run = create_test_run("run-1");
+        ts.scheduler.storage.insert_run(&run).await.unwrap();
+        ts.scheduler.claim_next_run().await.unwrap();
+
+        for status in [StepStatus::Failed, StepStatus::Succeeded] {
+            let step = ts
+                .scheduler
+                .enqueue_step(&run.id, StepPhase::Verification)
+                .await
+                .unwrap();
+            ts.scheduler.start_step(&step.id).await.unwrap();
+            ts.scheduler
+                .complete_step(&step.id, status, None, None)
+                .await
+                .unwrap();
+        }
+
+        let metrics = ts.scheduler.metrics();
+        assert_eq!(metrics.step_count(StepPhase::Verification), 2);
+        assert_eq!(metrics.verification_counts(), (1, 1));
+        assert_eq!(metrics.step_count(StepPhase::Implementation), 0);
+    }
+
+    backend_test!(determine_next_phase_verification_success_continues, open);
+    async fn determine_next_phase_verification_success_continues(backend: TestBackend) {
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("run-1");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -1053,18 +1558,11 @@ mod tests {
 
     // --- Per-workspace cap tests (spec Section 4.2, 5.3) ---
 
-    async fn create_test_scheduler_with_workspace_cap(cap: usize) -> TestScheduler {
-        let dir = TempDir::new().unwrap();
-        let db_path = dir.path().join("test.db");
-        let storage = Storage::new(&db_path, DEFAULT_MAX_CONCURRENT_RUNS)
-            .await
-            .unwrap();
-        storage.migrate_embedded().await.unwrap();
-        let storage = Arc::new(storage);
-        let scheduler = Scheduler::new_with_workspace_cap(storage, 5, cap);
+    fn create_test_scheduler_with_workspace_cap(backend: TestBackend, cap: usize) -> TestScheduler {
+        let storage = Arc::clone(&backend.storage);
         TestScheduler {
-            scheduler,
-            _dir: dir,
+            scheduler: Scheduler::new_with_workspace_cap(storage, 5, cap),
+            _backend: backend,
         }
     }
 
@@ -1088,12 +1586,13 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         }
     }
 
-    #[tokio::test]
-    async fn workspace_cap_blocks_run_when_at_limit() {
-        let ts = create_test_scheduler_with_workspace_cap(1).await;
+    backend_test!(workspace_cap_blocks_run_when_at_limit, open);
+    async fn workspace_cap_blocks_run_when_at_limit(backend: TestBackend) {
+        let ts = create_test_scheduler_with_workspace_cap(backend, 1);
 
         // Insert two runs in the same workspace.
         let run1 = create_test_run_in_workspace("run-1", "/workspace-a");
@@ -1115,9 +1614,9 @@ mod tests {
         assert!(ts.scheduler.queue_blocked_workspace_count() > 0);
     }
 
-    #[tokio::test]
-    async fn workspace_cap_allows_run_from_different_workspace() {
-        let ts = create_test_scheduler_with_workspace_cap(1).await;
+    backend_test!(workspace_cap_allows_run_from_different_workspace, open);
+    async fn workspace_cap_allows_run_from_different_workspace(backend: TestBackend) {
+        let ts = create_test_scheduler_with_workspace_cap(backend, 1);
 
         // Insert runs in different workspaces.
         let run1 = create_test_run_in_workspace("run-1", "/workspace-a");
@@ -1137,9 +1636,9 @@ mod tests {
         assert_eq!(claimed2.unwrap().workspace_root, "/workspace-b");
     }
 
-    #[tokio::test]
-    async fn workspace_cap_releases_slot_when_run_completes() {
-        let ts = create_test_scheduler_with_workspace_cap(1).await;
+    backend_test!(workspace_cap_releases_slot_when_run_completes, open);
+    async fn workspace_cap_releases_slot_when_run_completes(backend: TestBackend) {
+        let ts = create_test_scheduler_with_workspace_cap(backend, 1);
 
         // Insert two runs in the same workspace.
         let run1 = create_test_run_in_workspace("run-1", "/workspace-a");
@@ -1168,10 +1667,10 @@ mod tests {
         assert_eq!(claimed2.unwrap().id, run2.id);
     }
 
-    #[tokio::test]
-    async fn no_workspace_cap_allows_multiple_runs_same_workspace() {
+    backend_test!(no_workspace_cap_allows_multiple_runs_same_workspace, open);
+    async fn no_workspace_cap_allows_multiple_runs_same_workspace(backend: TestBackend) {
         // Standard scheduler without workspace cap.
-        let ts = create_test_scheduler().await;
+        let ts = create_test_scheduler(backend);
 
         // Insert two runs in the same workspace.
         let run1 = create_test_run("run-1");
@@ -1190,18 +1689,14 @@ mod tests {
 
     // --- Queue policy tests (spec Section 3.2, 5.3) ---
 
-    async fn create_test_scheduler_with_policy(policy: QueuePolicy) -> TestScheduler {
-        let dir = TempDir::new().unwrap();
-        let db_path = dir.path().join("test.db");
-        let storage = Storage::new(&db_path, DEFAULT_MAX_CONCURRENT_RUNS)
-            .await
-            .unwrap();
-        storage.migrate_embedded().await.unwrap();
-        let storage = Arc::new(storage);
-        let scheduler = Scheduler::new_with_policy(storage, 5, None, policy);
+    fn create_test_scheduler_with_policy(
+        backend: TestBackend,
+        policy: QueuePolicy,
+    ) -> TestScheduler {
+        let storage = Arc::clone(&backend.storage);
         TestScheduler {
-            scheduler,
-            _dir: dir,
+            scheduler: Scheduler::new_with_policy(storage, 5, None, policy),
+            _backend: backend,
         }
     }
 
@@ -1228,12 +1723,13 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         }
     }
 
-    #[tokio::test]
-    async fn queue_policy_fifo_claims_oldest_first() {
-        let ts = create_test_scheduler_with_policy(QueuePolicy::Fifo).await;
+    backend_test!(queue_policy_fifo_claims_oldest_first, open);
+    async fn queue_policy_fifo_claims_oldest_first(backend: TestBackend) {
+        let ts = create_test_scheduler_with_policy(backend, QueuePolicy::Fifo);
 
         // Insert runs with explicit ordering (run-1 is oldest).
         let run1 = create_test_run_with_order("run-1", 0);
@@ -1255,9 +1751,9 @@ mod tests {
         assert_eq!(claimed.id, run3.id);
     }
 
-    #[tokio::test]
-    async fn queue_policy_newest_first_claims_newest_first() {
-        let ts = create_test_scheduler_with_policy(QueuePolicy::NewestFirst).await;
+    backend_test!(queue_policy_newest_first_claims_newest_first, open);
+    async fn queue_policy_newest_first_claims_newest_first(backend: TestBackend) {
+        let ts = create_test_scheduler_with_policy(backend, QueuePolicy::NewestFirst);
 
         // Insert runs with explicit ordering (run-3 is newest).
         let run1 = create_test_run_with_order("run-1", 0);
@@ -1282,10 +1778,10 @@ mod tests {
         assert_eq!(claimed.id, run1.id);
     }
 
-    #[tokio::test]
-    async fn default_policy_is_fifo() {
+    backend_test!(default_policy_is_fifo, open);
+    async fn default_policy_is_fifo(backend: TestBackend) {
         // Standard scheduler should use FIFO (default).
-        let ts = create_test_scheduler().await;
+        let ts = create_test_scheduler(backend);
 
         let run1 = create_test_run_with_order("run-1", 0);
         let run2 = create_test_run_with_order("run-2", 100);
@@ -1298,6 +1794,169 @@ mod tests {
         assert_eq!(claimed.id, run1.id, "Default policy should be FIFO");
     }
 
+    // --- Priority, aging and fair-share tests ---
+
+    fn queued_run(id: &str, workspace: &str, order: i64, priority: i32) -> Run {
+        Run {
+            workspace_root: workspace.to_string(),
+            priority,
+            ..create_test_run_with_order(id, order)
+        }
+    }
+
+    fn order_ids(
+        pending: Vec<Run>,
+        policy: QueuePolicy,
+        weights: &HashMap<String, u32>,
+        aging: Option<Duration>,
+        now_order: i64,
+    ) -> Vec<String> {
+        let now = DateTime::from_timestamp(1700000000 + now_order, 0).unwrap();
+        order_queue(pending, policy, &HashMap::new(), weights, aging, now)
+            .into_iter()
+            .map(|(run, _)| run.id.to_string())
+            .collect()
+    }
+
+    backend_test!(higher_priority_is_claimed_before_older_runs, open);
+    async fn higher_priority_is_claimed_before_older_runs(backend: TestBackend) {
+        let ts = create_test_scheduler_with_policy(backend, QueuePolicy::Fifo);
+        let old = queued_run("run-old", "/workspace", 0, 0);
+        let urgent = queued_run("run-urgent", "/workspace", 100, 5);
+        ts.scheduler.storage.insert_run(&old).await.unwrap();
+        ts.scheduler.storage.insert_run(&urgent).await.unwrap();
+
+        let claimed = ts.scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(claimed.id, urgent.id);
+        assert_eq!(claimed.priority, 5);
+    }
+
+    #[test]
+    fn aging_lets_old_low_priority_runs_overtake() {
+        let pending = || {
+            vec![
+                queued_run("low", "/workspace", 0, 0),
+                queued_run("high", "/workspace", 3000, 2),
+            ]
+        };
+        let aging = Some(Duration::from_mins(10));
+        let weights = HashMap::new();
+
+        // Without aging priority always wins.
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::Fifo, &weights, None, 3000),
+            ["high", "low"]
+        );
+        // After 50 minutes the low run has gained 5 levels, the new one none.
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::Fifo, &weights, aging, 3000),
+            ["low", "high"]
+        );
+    }
+
+    #[test]
+    fn aging_keeps_newest_first_order_within_a_level() {
+        let pending = || {
+            vec![
+                queued_run("stale-1", "/workspace", 0, 0),
+                queued_run("stale-2", "/workspace", 100, 0),
+                queued_run("fresh", "/workspace", 3500, 0),
+                queued_run("high", "/workspace", 3550, 1),
+            ]
+        };
+        let aging = Some(Duration::from_mins(10));
+        let weights = HashMap::new();
+
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::NewestFirst, &weights, None, 3600),
+            ["high", "fresh", "stale-2", "stale-1"]
+        );
+        // The stale runs have gained 5-6 levels, but they still wait behind the
+        // newer run at their own level, which has gained none.
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::NewestFirst, &weights, aging, 3600),
+            ["high", "fresh", "stale-2", "stale-1"]
+        );
+    }
+
+    #[test]
+    fn fair_share_interleaves_workspaces_by_weight() {
+        let pending = || {
+            vec![
+                queued_run("a1", "/a", 0, 0),
+                queued_run("a2", "/a", 1, 0),
+                queued_run("a3", "/a", 2, 0),
+                queued_run("b1", "/b", 3, 0),
+                queued_run("b2", "/b", 4, 0),
+            ]
+        };
+
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::Fifo, &HashMap::new(), None, 10),
+            ["a1", "a2", "a3", "b1", "b2"]
+        );
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::FairShare, &HashMap::new(), None, 10),
+            ["a1", "b1", "a2", "b2", "a3"]
+        );
+        let weights = HashMap::from([("/a".to_string(), 2)]);
+        assert_eq!(
+            order_ids(pending(), QueuePolicy::FairShare, &weights, None, 10),
+            ["a1", "b1", "a2", "a3", "b2"]
+        );
+    }
+
+    backend_test!(fair_share_counts_running_runs_against_workspace, open);
+    async fn fair_share_counts_running_runs_against_workspace(backend: TestBackend) {
+        let ts = create_test_scheduler_with_policy(backend, QueuePolicy::FairShare);
+        for (id, workspace, order) in [("a1", "/a", 0), ("a2", "/a", 1), ("b1", "/b", 2)] {
+            let run = queued_run(id, workspace, order, 0);
+            ts.scheduler.storage.insert_run(&run).await.unwrap();
+        }
+
+        let first = ts.scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(first.id.to_string(), "a1");
+        // /a already has a running run, so /b goes next despite being newer.
+        let second = ts.scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(second.id.to_string(), "b1");
+    }
+
+    backend_test!(queue_reports_why_runs_are_blocked, open);
+    async fn queue_reports_why_runs_are_blocked(backend: TestBackend) {
+        let storage = Arc::clone(&backend.storage);
+        let scheduler = Scheduler::new_with_policy(storage, 2, Some(1), QueuePolicy::Fifo);
+        for (id, workspace, order) in [
+            ("a1", "/a", 0),
+            ("a2", "/a", 1),
+            ("b1", "/b", 2),
+            ("c1", "/c", 3),
+        ] {
+            let run = queued_run(id, workspace, order, 0);
+            scheduler.storage.insert_run(&run).await.unwrap();
+        }
+
+        let queue = scheduler.queue().await.unwrap();
+        let summary: Vec<(String, Option<QueueBlock>)> = queue
+            .into_iter()
+            .map(|entry| (entry.run_id.to_string(), entry.blocked))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a1".to_string(), None),
+                ("a2".to_string(), Some(QueueBlock::WorkspaceCap { cap: 1 })),
+                ("b1".to_string(), None),
+                ("c1".to_string(), Some(QueueBlock::Concurrency { max: 2 })),
+            ]
+        );
+
+        // Claims follow the reported order.
+        let claimed = scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(claimed.id.to_string(), "a1");
+        let claimed = scheduler.claim_next_run().await.unwrap().unwrap();
+        assert_eq!(claimed.id.to_string(), "b1");
+    }
+
     // --- Runner pipeline progression tests (spec Section 5.1) ---
 
     /// Helper to advance through a phase: enqueue, start, complete with given status.
@@ -1317,11 +1976,11 @@ mod tests {
         step
     }
 
-    #[tokio::test]
-    async fn pipeline_progression_full_cycle_with_reviewer() {
+    backend_test!(pipeline_progression_full_cycle_with_reviewer, open);
+    async fn pipeline_progression_full_cycle_with_reviewer(backend: TestBackend) {
         // Test spec Section 5.1 main flow with reviewer=true (default):
         // implementation -> review -> verification -> implementation (next iteration)
-        let ts = create_test_scheduler().await;
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("pipeline-run");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -1383,10 +2042,15 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn pipeline_progression_verification_failure_requeues_implementation() {
+    backend_test!(
+        pipeline_progression_verification_failure_requeues_implementation,
+        open
+    );
+    async fn pipeline_progression_verification_failure_requeues_implementation(
+        backend: TestBackend,
+    ) {
         // Test spec Section 5.2: verification failure requeues implementation
-        let ts = create_test_scheduler().await;
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("pipeline-verify-fail");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -1426,11 +2090,11 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn pipeline_progression_without_reviewer() {
+    backend_test!(pipeline_progression_without_reviewer, open);
+    async fn pipeline_progression_without_reviewer(backend: TestBackend) {
         // Test spec Section 5.1 flow with reviewer=false:
         // implementation -> verification (skip review)
-        let ts = create_test_scheduler().await;
+        let ts = create_test_scheduler(backend);
         let now = Utc::now();
         let run = Run {
             id: Id::from_string("no-reviewer-run"),
@@ -1450,6 +2114,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -1475,10 +2140,10 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn pipeline_progression_multiple_iterations() {
+    backend_test!(pipeline_progression_multiple_iterations, open);
+    async fn pipeline_progression_multiple_iterations(backend: TestBackend) {
         // Test multiple complete iterations through the pipeline
-        let ts = create_test_scheduler().await;
+        let ts = create_test_scheduler(backend);
         let run = create_test_run("multi-iter-run");
         ts.scheduler.storage.insert_run(&run).await.unwrap();
         ts.scheduler.claim_next_run().await.unwrap();
@@ -0,0 +1,318 @@
+//! Cron schedules that enqueue recurring runs.
+//!
+//! A [`ScheduleRunner`] polls the `schedules` table and, for each schedule
+//! whose `next_fire_at` has passed, builds a pending run exactly as
+//! `POST /runs` would and inserts it. Two policies shape each fire:
+//!
+//! - Missed fires: a fire more than [`MISSED_FIRE_GRACE`] late (the daemon was
+//!   down) is dropped under `skip`, or enqueued once under `catch_up` however
+//!   many fire times were missed.
+//! - Overlap: under `skip`, no run is enqueued while the schedule's previous
+//!   run is still pending, running or paused.
+//!
+//! Either way the schedule advances to its next fire time after now.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use croner::Cron;
+use loop_core::{MissedFirePolicy, OverlapPolicy, Run, RunStatus, Schedule};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::server::{self, CreateRunRequest};
+use crate::storage::{StorageBackend, StorageError};
+
+/// How often due schedules are checked.
+pub const SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A fire later than this counts as missed rather than merely late.
+pub const MISSED_FIRE_GRACE: Duration = Duration::from_mins(2);
+
+/// Errors from cron evaluation.
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error("invalid cron expression '{expr}': {reason}")]
+    InvalidCron { expr: String, reason: String },
+    #[error("cron expression '{0}' has no upcoming fire time")]
+    NoUpcomingFire(String),
+}
+
+/// Parse a cron expression (five fields, or an alias such as `@daily`).
+pub fn parse_cron(expr: &str) -> Result<Cron, ScheduleError> {
+    Cron::new(expr)
+        .parse()
+        .map_err(|e| ScheduleError::InvalidCron {
+            expr: expr.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+/// The first fire time of `expr` strictly after `after`, in local time.
+pub fn next_fire(expr: &str, after: DateTime<Utc>) -> Result<DateTime<Utc>, ScheduleError> {
+    next_fire_in(expr, after, &Local)
+}
+
+/// The first fire time of `expr` strictly after `after`, evaluated in `tz`.
+pub fn next_fire_in<Tz: TimeZone>(
+    expr: &str,
+    after: DateTime<Utc>,
+    tz: &Tz,
+) -> Result<DateTime<Utc>, ScheduleError> {
+    parse_cron(expr)?
+        .find_next_occurrence(&after.with_timezone(tz), false)
+        .map(|fire| fire.with_timezone(&Utc))
+        .map_err(|_| ScheduleError::NoUpcomingFire(expr.to_string()))
+}
+
+/// What to do with a due schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FireDecision {
+    /// Enqueue a run.
+    Enqueue,
+    /// The fire was missed and the schedule skips missed fires.
+    SkipMissed,
+    /// The previous run is still active and the schedule skips overlaps.
+    SkipOverlap,
+}
+
+/// Decide a due schedule's fire at `now`.
+pub fn decide(schedule: &Schedule, now: DateTime<Utc>, previous_active: bool) -> FireDecision {
+    let missed = (now - schedule.next_fire_at)
+        .to_std()
+        .is_ok_and(|late| late > MISSED_FIRE_GRACE);
+    if missed && schedule.missed_fire_policy == MissedFirePolicy::Skip {
+        FireDecision::SkipMissed
+    } else if previous_active && schedule.overlap_policy == OverlapPolicy::Skip {
+        FireDecision::SkipOverlap
+    } else {
+        FireDecision::Enqueue
+    }
+}
+
+/// Fires due schedules.
+#[derive(Debug, Clone)]
+pub struct ScheduleRunner {
+    storage: Arc<dyn StorageBackend>,
+}
+
+impl ScheduleRunner {
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        Self { storage }
+    }
+
+    /// Poll for due schedules every [`SCHEDULE_POLL_INTERVAL`] until `cancel` fires.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.tick(Utc::now()).await {
+                    warn!("schedule check failed: {}", e);
+                }
+                tokio::select! {
+                    () = cancel.cancelled() => break,
+                    () = tokio::time::sleep(SCHEDULE_POLL_INTERVAL) => {}
+                }
+            }
+        })
+    }
+
+    /// Fire every schedule due at `now`; returns the runs enqueued.
+    pub async fn tick(&self, now: DateTime<Utc>) -> Result<Vec<Run>, StorageError> {
+        let mut enqueued = Vec::new();
+        for schedule in self.storage.list_due_schedules(now).await? {
+            let next_fire_at = match next_fire(&schedule.cron, now) {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!("schedule {} ({}): {}", schedule.name, schedule.id, e);
+                    continue;
+                }
+            };
+            let previous_active = self.previous_run_active(&schedule).await?;
+            let run = match decide(&schedule, now, previous_active) {
+                FireDecision::Enqueue => match build_run(&schedule) {
+                    Ok(run) => Some(run),
+                    Err(e) => {
+                        warn!("schedule {} ({}): {}", schedule.name, schedule.id, e);
+                        None
+                    }
+                },
+                FireDecision::SkipMissed => {
+                    info!(
+                        "schedule {} ({}): skipping missed fire at {}",
+                        schedule.name, schedule.id, schedule.next_fire_at
+                    );
+                    None
+                }
+                FireDecision::SkipOverlap => {
+                    info!(
+                        "schedule {} ({}): previous run still active, skipping fire",
+                        schedule.name, schedule.id
+                    );
+                    None
+                }
+            };
+
+            self.storage
+                .fire_schedule(&schedule.id, run.as_ref(), next_fire_at)
+                .await?;
+            if let Some(run) = run {
+                info!(
+                    "schedule {} ({}): enqueued run {} ({})",
+                    schedule.name, schedule.id, run.name, run.id
+                );
+                enqueued.push(run);
+            }
+        }
+        Ok(enqueued)
+    }
+
+    /// Whether the schedule's last run is still pending, running or paused.
+    async fn previous_run_active(&self, schedule: &Schedule) -> Result<bool, StorageError> {
+        let Some(run_id) = &schedule.last_run_id else {
+            return Ok(false);
+        };
+        match self.storage.get_run(run_id).await {
+            Ok(run) => Ok(matches!(
+                run.status,
+                RunStatus::Pending | RunStatus::Running | RunStatus::Paused
+            )),
+            Err(StorageError::RunNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Build the pending run for a fire, named after the schedule.
+fn build_run(schedule: &Schedule) -> Result<Run, server::NewRunError> {
+    server::new_run(CreateRunRequest {
+        spec_path: schedule.spec_path.clone(),
+        plan_path: schedule.plan_path.clone(),
+        workspace_root: schedule.workspace_root.clone(),
+        config_override: schedule.config_override.clone(),
+        name: Some(schedule.name.clone()),
+        priority: Some(schedule.priority),
+        ..CreateRunRequest::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+    use loop_core::Id;
+    use tempfile::TempDir;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn schedule(workspace_root: &str, next_fire_at: DateTime<Utc>) -> Schedule {
+        Schedule {
+            id: Id::new(),
+            name: "nightly".to_string(),
+            workspace_root: workspace_root.to_string(),
+            spec_path: format!("{workspace_root}/spec.md"),
+            plan_path: None,
+            config_override: None,
+            cron: "*/5 * * * *".to_string(),
+            missed_fire_policy: MissedFirePolicy::Skip,
+            overlap_policy: OverlapPolicy::Skip,
+            priority: 4,
+            next_fire_at,
+            last_fire_at: None,
+            last_run_id: None,
+            created_at: next_fire_at,
+            updated_at: next_fire_at,
+        }
+    }
+
+    #[test]
+    fn parse_cron_accepts_fields_and_aliases() {
+        assert!(parse_cron("*/15 * * * *").is_ok());
+        assert!(parse_cron("@daily").is_ok());
+        let err = parse_cron("every day").unwrap_err();
+        assert!(matches!(err, ScheduleError::InvalidCron { .. }));
+        assert!(err.to_string().contains("every day"));
+    }
+
+    #[test]
+    fn next_fire_is_strictly_after() {
+        let after = at("2026-03-01T03:00:00Z");
+        assert_eq!(
+            next_fire_in("0 2 * * *", after, &Utc).unwrap(),
+            at("2026-03-02T02:00:00Z")
+        );
+        assert_eq!(
+            next_fire_in("0 3 * * *", after, &Utc).unwrap(),
+            at("2026-03-02T03:00:00Z")
+        );
+        assert_eq!(
+            next_fire_in("*/5 * * * *", at("2026-03-01T03:01:30Z"), &Utc).unwrap(),
+            at("2026-03-01T03:05:00Z")
+        );
+    }
+
+    #[test]
+    fn decide_applies_missed_fire_and_overlap_policies() {
+        let fire_at = at("2026-03-01T02:00:00Z");
+        let on_time = fire_at + chrono::Duration::seconds(10);
+        let late = fire_at + chrono::Duration::hours(5);
+        let mut s = schedule("/workspace", fire_at);
+
+        assert_eq!(decide(&s, on_time, false), FireDecision::Enqueue);
+        assert_eq!(decide(&s, late, false), FireDecision::SkipMissed);
+        assert_eq!(decide(&s, on_time, true), FireDecision::SkipOverlap);
+
+        s.missed_fire_policy = MissedFirePolicy::CatchUp;
+        assert_eq!(decide(&s, late, false), FireDecision::Enqueue);
+        assert_eq!(decide(&s, late, true), FireDecision::SkipOverlap);
+
+        s.overlap_policy = OverlapPolicy::Allow;
+        assert_eq!(decide(&s, on_time, true), FireDecision::Enqueue);
+    }
+
+    #[tokio::test]
+    async fn tick_enqueues_once_and_skips_overlapping_fires() {
+        let dir = TempDir::new().unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(
+            Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+                .await
+                .unwrap(),
+        );
+        storage.migrate_embedded().await.unwrap();
+        let runner = ScheduleRunner::new(Arc::clone(&storage));
+
+        let now = Utc::now();
+        let workspace = dir.path().to_string_lossy().to_string();
+        let s = schedule(&workspace, now - chrono::Duration::seconds(30));
+        storage.insert_schedule(&s).await.unwrap();
+
+        let enqueued = runner.tick(now).await.unwrap();
+        assert_eq!(enqueued.len(), 1);
+        let run = storage.get_run(&enqueued[0].id).await.unwrap();
+        assert_eq!(run.name, "nightly");
+        assert_eq!(run.priority, 4);
+        assert_eq!(run.status, RunStatus::Pending);
+        assert!(run.config_json.is_some());
+
+        let fired = storage.list_schedules(None).await.unwrap().remove(0);
+        assert_eq!(fired.last_run_id.as_ref(), Some(&run.id));
+        assert!(fired.next_fire_at > now);
+
+        // Not due again until the next fire time.
+        assert!(runner.tick(now).await.unwrap().is_empty());
+
+        // Due again while the first run is still pending: skipped, but advanced.
+        let later = fired.next_fire_at + chrono::Duration::seconds(1);
+        assert!(runner.tick(later).await.unwrap().is_empty());
+        let skipped = storage.list_schedules(None).await.unwrap().remove(0);
+        assert!(skipped.next_fire_at > later);
+        assert_eq!(skipped.last_run_id.as_ref(), Some(&run.id));
+        assert_eq!(storage.list_runs(None).await.unwrap().len(), 1);
+    }
+}
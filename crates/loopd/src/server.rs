@@ -3,6 +3,7 @@
 //! Implements the local-only REST API from spec Section 4.1.
 //! See also Section 8.1 for auth requirements.
 
+use std::collections::HashMap;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
@@ -10,13 +11,14 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use axum::{
-    extract::{Path as AxumPath, Query, State},
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path as AxumPath, Query, State},
     http::{header, HeaderMap, StatusCode},
     response::{
         sse::{Event as SseEvent, KeepAlive, Sse},
         IntoResponse,
     },
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use chrono::Utc;
@@ -25,21 +27,42 @@ use futures_util::{
     StreamExt,
 };
 use loop_core::{
-    Config, Event, Id, MergeStrategy, ReviewStatus, Run, RunNameSource, RunStatus, WorktreeProvider,
+    events::{EventPayload, EventType, LeaseGrantedPayload, RunStatusChangedPayload},
+    logs,
+    prompt::spec_slug,
+    AgentAction, AgentActionKind, AgentActionStatus, Config, Event, Id, MergeStrategy,
+    MissedFirePolicy, OverlapPolicy, QueueEntry, QueuePolicy, ReviewStatus, Run, RunDependency,
+    RunNameSource, RunStatus, Schedule, TokenUsage, Worker, WorkerStatus, WorktreeProvider,
 };
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::broadcast;
 use tracing::{error, info, warn};
 
+use crate::backend::BackendConfig;
+use crate::bus::OutputChunk;
+use crate::gc::GarbageCollector;
 use crate::git;
 use crate::handlers::review::{create_pr, get_run_diff, merge_run, scrap_run};
+use crate::metrics::{self, SchedulerGauges};
 use crate::naming;
 use crate::scheduler::Scheduler;
-use crate::storage::Storage;
+use crate::schedules;
+use crate::skills::SkillsMetrics;
+use crate::storage::{
+    AgentActionFilter, DeliveryStatus, NotificationDelivery, RunChild, RunFilter, RunSort,
+    StorageBackend, StorageError, UsageAggregate, UsageGroupBy,
+};
+use crate::workers::{
+    self, ClaimResponse, CompleteStepRequest, HeartbeatResponse, RegisterWorkerRequest,
+    RegisterWorkerResponse, StepRequest, HEARTBEAT_INTERVAL, LEASE_TTL,
+};
 
 /// Shared state for HTTP handlers.
 pub struct AppState {
-    pub storage: Arc<Storage>,
+    pub storage: Arc<dyn StorageBackend>,
     pub scheduler: Arc<Scheduler>,
+    pub skills_metrics: Arc<SkillsMetrics>,
     pub auth_token: Option<String>,
 }
 
@@ -48,6 +71,7 @@ impl std::fmt::Debug for AppState {
         f.debug_struct("AppState")
             .field("storage", &self.storage)
             .field("scheduler", &self.scheduler)
+            .field("skills_metrics", &self.skills_metrics)
             .field(
                 "auth_token",
                 &self.auth_token.as_ref().map(|_| "[REDACTED]"),
@@ -67,13 +91,21 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/runs/{id}/cancel", post(cancel_run))
         .route("/runs/{id}/retry", post(retry_run))
         .route("/runs/{id}/reset", post(reset_run))
+        .route("/runs/{id}/priority", post(set_run_priority))
         .route("/runs/{id}/steps", get(list_steps))
+        .route("/runs/{id}/children", get(list_run_children))
+        .route("/runs/{id}/actions", get(list_agent_actions))
+        .route(
+            "/runs/{id}/steps/{step_id}/transcript",
+            get(get_step_transcript),
+        )
         // Postmortem endpoints (postmortem-analysis.md Section 4)
         .route(
             "/runs/{id}/postmortem",
             post(trigger_postmortem).get(get_postmortem),
         )
         // SSE streaming endpoints (Section 4.1)
+        .route("/events", get(stream_all_events))
         .route("/runs/{id}/events", get(stream_events))
         .route("/runs/{id}/output", get(stream_output))
         // Review workflow endpoints (daemon-review-api.md Section 4)
@@ -81,23 +113,50 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/runs/{id}/scrap", post(scrap_run))
         .route("/runs/{id}/merge", post(merge_run))
         .route("/runs/{id}/create-pr", post(create_pr))
+        // Pending queue in claim order
+        .route("/queue", get(get_queue))
+        // Recurring runs
+        .route("/schedules", post(create_schedule).get(list_schedules))
+        .route("/schedules/{id}", delete(delete_schedule))
+        // Distributed mode: worker registration and step leases
+        .route("/workers", get(list_workers))
+        .route("/workers/register", post(register_worker))
+        .route("/workers/{id}/heartbeat", post(worker_heartbeat))
+        .route("/workers/{id}/claim", post(claim_step))
+        .route("/steps/{id}/complete", post(complete_step))
+        .route(
+            "/steps/{id}/artifacts/{name}",
+            put(upload_step_artifact).layer(DefaultBodyLimit::max(MAX_ARTIFACT_UPLOAD_BYTES)),
+        )
+        // Usage and cost accounting
+        .route("/usage", get(get_usage))
+        // Retention: prune finished runs (or preview with dry_run)
+        .route("/gc", post(run_gc))
+        // Webhook notification delivery log
+        .route(
+            "/notifications/deliveries",
+            get(list_notification_deliveries),
+        )
         // Worktree management
         .route("/worktrees", get(list_worktrees).delete(remove_worktree))
-        // Health check
+        // Health check and Prometheus metrics
         .route("/health", get(health_check))
+        .route("/metrics", get(get_metrics))
         .with_state(state)
 }
 
 /// Start the HTTP server.
 pub async fn start_server(
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     scheduler: Arc<Scheduler>,
+    skills_metrics: Arc<SkillsMetrics>,
     port: u16,
     auth_token: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let state = Arc::new(AppState {
         storage,
         scheduler,
+        skills_metrics,
         auth_token,
     });
 
@@ -153,7 +212,7 @@ pub struct ErrorResponse {
 }
 
 /// Request payload for POST /runs (Section 4.1).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct CreateRunRequest {
     pub spec_path: String,
     #[serde(default)]
@@ -183,6 +242,12 @@ pub struct CreateRunRequest {
     pub worktrunk_config_path: Option<String>,
     #[serde(default)]
     pub worktrunk_copy_ignored: Option<bool>,
+    /// Scheduling priority; higher runs are claimed first (default 0).
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Runs that must reach a condition before this one is claimed.
+    #[serde(default)]
+    pub depends_on: Vec<RunDependency>,
 }
 
 /// Response for POST /runs.
@@ -191,25 +256,162 @@ pub struct CreateRunResponse {
     pub run: Run,
 }
 
+/// Largest page `GET /runs` returns when `limit` is set.
+pub const MAX_RUNS_PAGE_LIMIT: u32 = 500;
+
 /// Query params for GET /runs.
+///
+/// Without `limit` every matching run is returned, as before paging existed.
 #[derive(Debug, Deserialize, Default)]
 pub struct ListRunsQuery {
     #[serde(default)]
     pub workspace_root: Option<String>,
+    /// Run status (case-insensitive).
     #[serde(default)]
     pub status: Option<String>,
+    #[serde(default)]
+    pub review_status: Option<ReviewStatus>,
+    /// Substring of the spec path.
+    #[serde(default)]
+    pub spec_path: Option<String>,
+    /// Inclusive lower bound on creation time (Unix epoch milliseconds).
+    #[serde(default)]
+    pub created_after: Option<i64>,
+    /// Exclusive upper bound on creation time (Unix epoch milliseconds).
+    #[serde(default)]
+    pub created_before: Option<i64>,
+    #[serde(default)]
+    pub sort: RunSort,
+    /// Page size, clamped to 1..=`MAX_RUNS_PAGE_LIMIT`.
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Opaque cursor from a previous page's `next_cursor`.
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 /// Response for GET /runs.
 #[derive(Debug, Serialize)]
 pub struct ListRunsResponse {
     pub runs: Vec<Run>,
+    /// Cursor for the next page; absent on the last page or without `limit`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Response for GET /runs/{id}.
 #[derive(Debug, Serialize)]
 pub struct GetRunResponse {
     pub run: Run,
+    /// Token usage and reported cost summed over all steps.
+    pub usage: TokenUsage,
+}
+
+/// Request body for POST /runs/{id}/priority.
+#[derive(Debug, Deserialize)]
+pub struct SetPriorityRequest {
+    pub priority: i32,
+}
+
+/// Response for GET /queue.
+#[derive(Debug, Serialize)]
+pub struct QueueResponse {
+    pub policy: QueuePolicy,
+    /// Pending runs in the order they would be claimed.
+    pub entries: Vec<QueueEntry>,
+}
+
+/// Request body for POST /gc.
+#[derive(Debug, Default, Deserialize)]
+pub struct GcRequest {
+    /// Report what would be pruned without deleting anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Request payload for POST /schedules.
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduleRequest {
+    pub spec_path: String,
+    #[serde(default)]
+    pub plan_path: Option<String>,
+    pub workspace_root: String,
+    #[serde(default)]
+    pub config_override: Option<String>,
+    /// Defaults to the spec's file stem.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub cron: String,
+    #[serde(default)]
+    pub missed_fire_policy: MissedFirePolicy,
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Response for POST /schedules.
+#[derive(Debug, Serialize)]
+pub struct CreateScheduleResponse {
+    pub schedule: Schedule,
+}
+
+/// Query params for GET /schedules.
+#[derive(Debug, Deserialize)]
+pub struct ListSchedulesQuery {
+    #[serde(default)]
+    pub workspace_root: Option<String>,
+}
+
+/// Response for GET /schedules.
+#[derive(Debug, Serialize)]
+pub struct ListSchedulesResponse {
+    pub schedules: Vec<Schedule>,
+}
+
+/// Query params for GET /usage.
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Group results by run, spec, or workspace (default: run).
+    #[serde(default)]
+    pub by: UsageGroupBy,
+    #[serde(default)]
+    pub workspace_root: Option<String>,
+    /// Only include usage recorded at or after this time (Unix epoch ms).
+    #[serde(default)]
+    pub since: Option<i64>,
+    /// Only include usage recorded before this time (Unix epoch ms).
+    #[serde(default)]
+    pub until: Option<i64>,
+}
+
+/// Response for GET /usage.
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    pub by: UsageGroupBy,
+    pub groups: Vec<UsageAggregate>,
+    pub total: TokenUsage,
+}
+
+/// Default and maximum page size for GET /notifications/deliveries.
+const DEFAULT_DELIVERIES_LIMIT: u32 = 50;
+const MAX_DELIVERIES_LIMIT: u32 = 500;
+
+/// Query params for GET /notifications/deliveries.
+#[derive(Debug, Deserialize)]
+pub struct DeliveriesQuery {
+    /// Only `delivered` or `failed` deliveries.
+    #[serde(default)]
+    pub status: Option<DeliveryStatus>,
+    /// Maximum rows, newest first (default 50, max 500).
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// Response for GET /notifications/deliveries.
+#[derive(Debug, Serialize)]
+pub struct DeliveriesResponse {
+    pub deliveries: Vec<NotificationDelivery>,
 }
 
 /// Response for GET /runs/{id}/steps.
@@ -218,6 +420,52 @@ pub struct ListStepsResponse {
     pub steps: Vec<loop_core::Step>,
 }
 
+/// Query params for GET /runs/{id}/steps/{step_id}/transcript.
+#[derive(Debug, Deserialize)]
+pub struct TranscriptQuery {
+    /// Only tool calls and results for this tool (e.g. `Bash`).
+    #[serde(default)]
+    pub tool: Option<String>,
+}
+
+/// Response for GET /runs/{id}/steps/{step_id}/transcript.
+#[derive(Debug, Serialize)]
+pub struct TranscriptResponse {
+    pub step: loop_core::Step,
+    pub entries: Vec<loop_core::TranscriptEntry>,
+}
+
+/// Query params for GET /runs/{id}/actions.
+#[derive(Debug, Deserialize)]
+pub struct ActionsQuery {
+    #[serde(default)]
+    pub step_id: Option<String>,
+    /// Exact tool name (e.g. `Bash`).
+    #[serde(default)]
+    pub tool: Option<String>,
+    #[serde(default)]
+    pub kind: Option<AgentActionKind>,
+    #[serde(default)]
+    pub status: Option<AgentActionStatus>,
+    /// Substring of the redacted arguments (e.g. `rm -rf`).
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// Response for GET /runs/{id}/actions.
+#[derive(Debug, Serialize)]
+pub struct ActionsResponse {
+    pub actions: Vec<AgentAction>,
+}
+
+/// Response for GET /runs/{id}/children.
+#[derive(Debug, Serialize)]
+pub struct ListRunChildrenResponse {
+    pub children: Vec<RunChild>,
+}
+
 /// Request payload for POST /runs/{id}/postmortem.
 ///
 /// See postmortem-analysis.md Section 4.
@@ -285,8 +533,50 @@ pub struct GetPostmortemResponse {
 // --- Handlers ---
 
 /// Health check endpoint.
-async fn health_check() -> impl IntoResponse {
-    Json(serde_json::json!({ "status": "ok" }))
+///
+/// Also reports the API rate-limit breakers that are holding step launches.
+async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "ok",
+        "api_backoff": state.scheduler.governor().snapshot(),
+    }))
+}
+
+/// GET /metrics - Prometheus text exposition of scheduler, step and skills metrics.
+async fn get_metrics(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let queue_depth = state
+        .storage
+        .count_runs_with_status(RunStatus::Pending)
+        .await
+        .map_err(|e| {
+            error!("failed to count pending runs: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to count pending runs: {e}"),
+                }),
+            )
+        })?;
+    let gauges = SchedulerGauges {
+        queue_depth,
+        active_runs: state.scheduler.active_run_count(),
+        max_concurrent: state.scheduler.max_concurrent(),
+        queue_blocked_workspace: state.scheduler.queue_blocked_workspace_count(),
+    };
+    let body = metrics::render(&gauges, state.scheduler.metrics(), &state.skills_metrics);
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    ))
 }
 
 /// POST /runs - Create a new run.
@@ -297,6 +587,88 @@ async fn create_run(
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     check_auth(&state, &headers)?;
 
+    let bad_request = |error: String| {
+        warn!("rejected run: {}", error);
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }))
+    };
+    if req.depends_on.iter().filter(|d| d.branch_from).count() > 1 {
+        return Err(bad_request(
+            "at most one dependency can set branch_from".to_string(),
+        ));
+    }
+    for dependency in &req.depends_on {
+        match state.storage.get_run(&dependency.run_id).await {
+            Ok(_) => {}
+            Err(StorageError::RunNotFound(_)) => {
+                return Err(bad_request(format!(
+                    "dependency run not found: {}",
+                    dependency.run_id
+                )));
+            }
+            Err(e) => {
+                error!("failed to look up dependency: {}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: format!("failed to look up dependency: {e}"),
+                    }),
+                ));
+            }
+        }
+    }
+    let depends_on = req.depends_on.clone();
+
+    let run = new_run(req).map_err(|e| {
+        error!("{}", e);
+        let status = match &e {
+            NewRunError::Config(_) => StatusCode::BAD_REQUEST,
+            NewRunError::Serialize(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (
+            status,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    state
+        .storage
+        .insert_run_with_dependencies(&run, &depends_on)
+        .await
+        .map_err(|e| {
+            error!("failed to create run: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to create run: {e}"),
+                }),
+            )
+        })?;
+
+    info!("created run: {} ({})", run.name, run.id);
+    Ok((StatusCode::CREATED, Json(CreateRunResponse { run })))
+}
+
+/// Why a run could not be built from a [`CreateRunRequest`].
+#[derive(Debug, Error)]
+pub enum NewRunError {
+    #[error("failed to load config: {0}")]
+    Config(String),
+    #[error("failed to serialize config: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Build a pending run from a create request: load and resolve its config,
+/// apply overrides, and pick a name. Shared by `POST /runs` and schedules.
+pub(crate) fn new_run(req: CreateRunRequest) -> Result<Run, NewRunError> {
+    let workspace_root_path = Path::new(&req.workspace_root);
+    let mut config = load_run_config(workspace_root_path, req.config_override.as_deref())
+        .map_err(NewRunError::Config)?;
+
+    apply_run_overrides(&mut config, &req);
+    config.resolve_paths(workspace_root_path);
+
     // Determine run name (default to haiku).
     let name_source = req.name_source.unwrap_or_else(|| {
         if req.name.is_some() {
@@ -309,37 +681,19 @@ async fn create_run(
     let (name, name_source) = if let Some(ref name) = req.name {
         (sanitize_name(name), name_source)
     } else {
-        let result = naming::generate_name(Path::new(&req.spec_path), name_source, "haiku");
+        let result = naming::generate_name(
+            Path::new(&req.spec_path),
+            name_source,
+            &config.run_naming_model,
+            &BackendConfig::from_config(&config),
+        );
         (result.name, result.source)
     };
 
-    let workspace_root_path = Path::new(&req.workspace_root);
-    let mut config =
-        load_run_config(workspace_root_path, req.config_override.as_deref()).map_err(|e| {
-            error!("failed to load config: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("failed to load config: {e}"),
-                }),
-            )
-        })?;
-
-    apply_run_overrides(&mut config, &req);
-    config.resolve_paths(workspace_root_path);
-
-    let config_json = serde_json::to_string(&config).map_err(|e| {
-        error!("failed to serialize config: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("failed to serialize config: {e}"),
-            }),
-        )
-    })?;
+    let config_json = serde_json::to_string(&config)?;
 
     let now = Utc::now();
-    let run = Run {
+    Ok(Run {
         id: Id::new(),
         name,
         name_source,
@@ -357,20 +711,8 @@ async fn create_run(
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
-    };
-
-    state.storage.insert_run(&run).await.map_err(|e| {
-        error!("failed to create run: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("failed to create run: {e}"),
-            }),
-        )
-    })?;
-
-    info!("created run: {} ({})", run.name, run.id);
-    Ok((StatusCode::CREATED, Json(CreateRunResponse { run })))
+        priority: req.priority.unwrap_or_default(),
+    })
 }
 
 fn sanitize_name(name: &str) -> String {
@@ -452,7 +794,7 @@ fn apply_run_overrides(config: &mut Config, req: &CreateRunRequest) {
     }
 }
 
-/// GET /runs - List runs.
+/// GET /runs - List runs with optional filters, sorting and cursor paging.
 async fn list_runs(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -460,26 +802,60 @@ async fn list_runs(
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     check_auth(&state, &headers)?;
 
-    let mut runs = state
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let status = query
+        .status
+        .as_deref()
+        .map(|s| parse_run_status(s).ok_or_else(|| bad_request(format!("invalid status: {s}"))))
+        .transpose()?;
+
+    let filter = RunFilter {
+        workspace_root: query.workspace_root,
+        status,
+        review_status: query.review_status,
+        spec_path_contains: query.spec_path.filter(|s| !s.is_empty()),
+        created_after: query.created_after,
+        created_before: query.created_before,
+        sort: query.sort,
+        limit: query.limit.map(|l| l.clamp(1, MAX_RUNS_PAGE_LIMIT)),
+        cursor: query.cursor.map(Id::from_string),
+    };
+
+    let page = state
         .storage
-        .list_runs(query.workspace_root.as_deref())
+        .list_runs_page(&filter)
         .await
-        .map_err(|e| {
-            error!("failed to list runs: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("failed to list runs: {e}"),
-                }),
-            )
+        .map_err(|e| match e {
+            StorageError::InvalidCursor(_) => bad_request("invalid cursor".to_string()),
+            e => {
+                error!("failed to list runs: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: format!("failed to list runs: {e}"),
+                    }),
+                )
+            }
         })?;
 
-    // Filter by status if provided
-    if let Some(status_filter) = &query.status {
-        runs.retain(|r| r.status.as_str().eq_ignore_ascii_case(status_filter));
-    }
+    Ok(Json(ListRunsResponse {
+        runs: page.runs,
+        next_cursor: page.next_cursor.map(|id| id.to_string()),
+    }))
+}
 
-    Ok(Json(ListRunsResponse { runs }))
+/// Parse a run status name case-insensitively.
+fn parse_run_status(s: &str) -> Option<RunStatus> {
+    [
+        RunStatus::Pending,
+        RunStatus::Running,
+        RunStatus::Paused,
+        RunStatus::Completed,
+        RunStatus::Failed,
+        RunStatus::Canceled,
+    ]
+    .into_iter()
+    .find(|status| status.as_str().eq_ignore_ascii_case(s))
 }
 
 /// GET /runs/{id} - Get a single run.
@@ -501,7 +877,85 @@ async fn get_run(
         )
     })?;
 
-    Ok(Json(GetRunResponse { run }))
+    let usage = state.storage.get_run_usage(&run_id).await.map_err(|e| {
+        error!("failed to load usage for run {}: {}", id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("failed to load usage: {e}"),
+            }),
+        )
+    })?;
+
+    Ok(Json(GetRunResponse { run, usage }))
+}
+
+/// GET /usage - Aggregate token usage and cost by run, spec, or workspace.
+async fn get_usage(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<UsageQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let groups = state
+        .storage
+        .aggregate_usage(
+            query.by,
+            query.workspace_root.as_deref(),
+            query.since,
+            query.until,
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to aggregate usage: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to aggregate usage: {e}"),
+                }),
+            )
+        })?;
+
+    let mut total = TokenUsage::default();
+    for group in &groups {
+        total.add(&group.usage);
+    }
+
+    Ok(Json(UsageResponse {
+        by: query.by,
+        groups,
+        total,
+    }))
+}
+
+/// GET /notifications/deliveries - Recent webhook delivery outcomes.
+async fn list_notification_deliveries(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<DeliveriesQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DELIVERIES_LIMIT)
+        .min(MAX_DELIVERIES_LIMIT);
+    let deliveries = state
+        .storage
+        .list_notification_deliveries(query.status, limit)
+        .await
+        .map_err(|e| {
+            error!("failed to list notification deliveries: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to list notification deliveries: {e}"),
+                }),
+            )
+        })?;
+
+    Ok(Json(DeliveriesResponse { deliveries }))
 }
 
 /// GET /runs/{id}/steps - List steps for a run.
@@ -538,109 +992,715 @@ async fn list_steps(
     Ok(Json(ListStepsResponse { steps }))
 }
 
-/// POST /runs/{id}/pause - Pause a running run.
-async fn pause_run(
+/// GET /runs/{id}/steps/{step_id}/transcript - Typed transcript of a step.
+async fn get_step_transcript(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath((id, step_id)): AxumPath<(String, String)>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+    let step_id = Id::from_string(&step_id);
+
+    let step = match state.storage.get_step(&step_id).await {
+        Ok(step) if step.run_id == run_id => step,
+        _ => {
+            warn!("step not found: {} (run {})", step_id, id);
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("step not found: {step_id}"),
+                }),
+            ));
+        }
+    };
+
+    let entries = state
+        .storage
+        .list_transcript_entries(&step_id, query.tool.as_deref())
+        .await
+        .map_err(|e| {
+            error!("failed to list transcript entries: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to list transcript entries: {e}"),
+                }),
+            )
+        })?;
+
+    Ok(Json(TranscriptResponse { step, entries }))
+}
+
+/// GET /runs/{id}/actions - Audited agent actions for a run.
+async fn list_agent_actions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+    Query(query): Query<ActionsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+
+    state.storage.get_run(&run_id).await.map_err(|e| {
+        warn!("run not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("run not found: {e}"),
+            }),
+        )
+    })?;
+
+    let filter = AgentActionFilter {
+        step_id: query.step_id.map(Id::from_string),
+        tool: query.tool,
+        kind: query.kind,
+        status: query.status,
+        contains: query.contains,
+        limit: query.limit,
+    };
+    let actions = state
+        .storage
+        .list_agent_actions(&run_id, &filter)
+        .await
+        .map_err(|e| {
+            error!("failed to list agent actions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to list agent actions: {e}"),
+                }),
+            )
+        })?;
+
+    Ok(Json(ActionsResponse { actions }))
+}
+
+/// GET /runs/{id}/children - List child runs fanned out from a parent run.
+async fn list_run_children(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+
+    state.storage.get_run(&run_id).await.map_err(|e| {
+        warn!("run not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("run not found: {e}"),
+            }),
+        )
+    })?;
+
+    let children = state
+        .storage
+        .list_run_children(&run_id)
+        .await
+        .map_err(|e| {
+            error!("failed to list child runs: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to list child runs: {e}"),
+                }),
+            )
+        })?;
+
+    Ok(Json(ListRunChildrenResponse { children }))
+}
+
+/// POST /runs/{id}/pause - Pause a running run.
+async fn pause_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+
+    state.scheduler.pause_run(&run_id).await.map_err(|e| {
+        warn!("failed to pause run {}: {}", id, e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("failed to pause run: {e}"),
+            }),
+        )
+    })?;
+
+    info!("paused run: {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /runs/{id}/resume - Resume a paused run.
+async fn resume_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+
+    let result = state.scheduler.resume_run(&run_id).await.map_err(|e| {
+        warn!("failed to resume run {}: {}", id, e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("failed to resume run: {e}"),
+            }),
+        )
+    })?;
+
+    match result {
+        Some(_) => {
+            info!("resumed run: {}", id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "no capacity to resume run".to_string(),
+            }),
+        )),
+    }
+}
+
+/// POST /runs/{id}/cancel - Cancel a run.
+async fn cancel_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+
+    state.scheduler.cancel_run(&run_id).await.map_err(|e| {
+        warn!("failed to cancel run {}: {}", id, e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("failed to cancel run: {e}"),
+            }),
+        )
+    })?;
+
+    info!("canceled run: {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /runs/{id}/retry - Retry a failed run by re-queuing it.
+async fn retry_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+
+    state.scheduler.retry_run(&run_id).await.map_err(|e| {
+        warn!("failed to retry run {}: {}", id, e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("failed to retry run: {e}"),
+            }),
+        )
+    })?;
+
+    info!("retried run: {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /runs/{id}/priority - Change a run's scheduling priority.
+///
+/// Allowed in any status; a retried run re-enters the queue with it.
+async fn set_run_priority(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+    Json(req): Json<SetPriorityRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let run_id = Id::from_string(&id);
+    state
+        .storage
+        .update_run_priority(&run_id, req.priority)
+        .await
+        .map_err(|e| {
+            warn!("failed to set priority of run {}: {}", id, e);
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("run not found: {e}"),
+                }),
+            )
+        })?;
+
+    info!("set priority of run {} to {}", id, req.priority);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /queue - Pending runs in claim order with the reason each is blocked.
+async fn get_queue(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let entries = state.scheduler.queue().await.map_err(|e| {
+        error!("failed to build queue: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("failed to build queue: {e}"),
+            }),
+        )
+    })?;
+
+    Ok(Json(QueueResponse {
+        policy: state.scheduler.queue_policy(),
+        entries,
+    }))
+}
+
+/// POST /gc - Apply the retention policy now and report what was pruned.
+async fn run_gc(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<GcRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let collector = GarbageCollector::new(
+        Arc::clone(&state.storage),
+        state.scheduler.retention().clone(),
+    );
+    let report = collector
+        .collect(Utc::now(), req.dry_run)
+        .await
+        .map_err(|e| {
+            error!("garbage collection failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("garbage collection failed: {e}"),
+                }),
+            )
+        })?;
+    if !report.dry_run && !report.runs.is_empty() {
+        info!(
+            "pruned {} run(s), freed {} bytes",
+            report.runs.len(),
+            report.bytes_freed
+        );
+    }
+
+    Ok(Json(report))
+}
+
+/// POST /schedules - Create a cron schedule for a spec.
+///
+/// The cron expression and config override are validated up front so a bad
+/// schedule is rejected here rather than failing at every fire.
+async fn create_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<CreateScheduleRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let bad_request = |error: String| {
+        warn!("rejected schedule: {}", error);
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }))
+    };
+    let now = Utc::now();
+    let next_fire_at =
+        schedules::next_fire(&req.cron, now).map_err(|e| bad_request(e.to_string()))?;
+    load_run_config(
+        Path::new(&req.workspace_root),
+        req.config_override.as_deref(),
+    )
+    .map_err(|e| bad_request(format!("failed to load config: {e}")))?;
+
+    let schedule = Schedule {
+        id: Id::new(),
+        name: req
+            .name
+            .unwrap_or_else(|| spec_slug(Path::new(&req.spec_path))),
+        workspace_root: req.workspace_root,
+        spec_path: req.spec_path,
+        plan_path: req.plan_path,
+        config_override: req.config_override,
+        cron: req.cron,
+        missed_fire_policy: req.missed_fire_policy,
+        overlap_policy: req.overlap_policy,
+        priority: req.priority,
+        next_fire_at,
+        last_fire_at: None,
+        last_run_id: None,
+        created_at: now,
+        updated_at: now,
+    };
+
+    state
+        .storage
+        .insert_schedule(&schedule)
+        .await
+        .map_err(|e| {
+            error!("failed to create schedule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to create schedule: {e}"),
+                }),
+            )
+        })?;
+
+    info!(
+        "created schedule: {} ({}) '{}', next fire at {}",
+        schedule.name, schedule.id, schedule.cron, schedule.next_fire_at
+    );
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateScheduleResponse { schedule }),
+    ))
+}
+
+/// GET /schedules - List schedules, optionally for one workspace.
+async fn list_schedules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ListSchedulesQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let schedules = state
+        .storage
+        .list_schedules(query.workspace_root.as_deref())
+        .await
+        .map_err(|e| {
+            error!("failed to list schedules: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to list schedules: {e}"),
+                }),
+            )
+        })?;
+
+    Ok(Json(ListSchedulesResponse { schedules }))
+}
+
+/// DELETE /schedules/{id} - Delete a schedule; runs it enqueued are kept.
+async fn delete_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let schedule_id = Id::from_string(&id);
+    state
+        .storage
+        .delete_schedule(&schedule_id)
+        .await
+        .map_err(|e| match e {
+            StorageError::ScheduleNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            ),
+            e => {
+                error!("failed to delete schedule {}: {}", id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: format!("failed to delete schedule: {e}"),
+                    }),
+                )
+            }
+        })?;
+
+    info!("deleted schedule: {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Response from `GET /workers`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListWorkersResponse {
+    pub workers: Vec<Worker>,
+}
+
+/// Map a worker or lease storage error: unknown worker 404, lost lease 409.
+fn worker_error(action: &str, e: StorageError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match e {
+        StorageError::WorkerNotFound(_) => StatusCode::NOT_FOUND,
+        StorageError::StaleLease(_) => StatusCode::CONFLICT,
+        _ => {
+            error!("failed to {}: {}", action, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+        }),
+    )
+}
+
+/// Largest file a worker may upload for a step.
+const MAX_ARTIFACT_UPLOAD_BYTES: usize = 512 * 1024 * 1024;
+
+fn lease_expiry(now: chrono::DateTime<Utc>) -> chrono::DateTime<Utc> {
+    now + chrono::Duration::from_std(LEASE_TTL).unwrap_or_default()
+}
+
+/// GET /workers - Registered workers, oldest first.
+async fn list_workers(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let workers = state
+        .storage
+        .list_workers()
+        .await
+        .map_err(|e| worker_error("list workers", e))?;
+    Ok(Json(ListWorkersResponse { workers }))
+}
+
+/// POST /workers/register - Register a worker process.
+async fn register_worker(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<RegisterWorkerRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    check_auth(&state, &headers)?;
+
+    let now = Utc::now();
+    let worker = Worker {
+        id: Id::new(),
+        hostname: req.hostname,
+        status: WorkerStatus::Online,
+        capabilities: req.capabilities,
+        last_heartbeat: now,
+        created_at: now,
+    };
+    state
+        .storage
+        .insert_worker(&worker)
+        .await
+        .map_err(|e| worker_error("register worker", e))?;
+
+    info!(worker_id = %worker.id, hostname = %worker.hostname, "worker registered");
+    Ok((
+        StatusCode::CREATED,
+        Json(RegisterWorkerResponse {
+            worker,
+            lease_ttl_sec: LEASE_TTL.as_secs(),
+            heartbeat_interval_sec: HEARTBEAT_INTERVAL.as_secs(),
+        }),
+    ))
+}
+
+/// POST /workers/{id}/heartbeat - Keep a worker online and renew its leases.
+async fn worker_heartbeat(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     AxumPath(id): AxumPath<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     check_auth(&state, &headers)?;
 
-    let run_id = Id::from_string(&id);
-
-    state.scheduler.pause_run(&run_id).await.map_err(|e| {
-        warn!("failed to pause run {}: {}", id, e);
-        (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("failed to pause run: {e}"),
-            }),
-        )
-    })?;
-
-    info!("paused run: {}", id);
-    Ok(StatusCode::NO_CONTENT)
+    let now = Utc::now();
+    let lease_expires_at = lease_expiry(now);
+    let leases = state
+        .storage
+        .heartbeat_worker(&Id::from_string(&id), now, lease_expires_at)
+        .await
+        .map_err(|e| worker_error("record heartbeat", e))?;
+    Ok(Json(HeartbeatResponse {
+        leases,
+        lease_expires_at,
+    }))
 }
 
-/// POST /runs/{id}/resume - Resume a paused run.
-async fn resume_run(
+/// POST /workers/{id}/claim - Lease the longest-waiting step to a worker.
+///
+/// Returns 204 when no step is waiting. Claiming keeps the worker online but
+/// does not renew leases it still holds; a claiming worker has let them go.
+async fn claim_step(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     AxumPath(id): AxumPath<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<axum::response::Response, (StatusCode, Json<ErrorResponse>)> {
     check_auth(&state, &headers)?;
 
-    let run_id = Id::from_string(&id);
+    let worker_id = Id::from_string(&id);
+    let now = Utc::now();
+    let expires_at = lease_expiry(now);
+    state
+        .storage
+        .touch_worker(&worker_id, now)
+        .await
+        .map_err(|e| worker_error("record heartbeat", e))?;
+    let Some((lease, request_json)) = state
+        .storage
+        .claim_lease(&worker_id, expires_at)
+        .await
+        .map_err(|e| worker_error("claim step", e))?
+    else {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    };
 
-    let result = state.scheduler.resume_run(&run_id).await.map_err(|e| {
-        warn!("failed to resume run {}: {}", id, e);
+    let request: StepRequest = serde_json::from_str(&request_json).map_err(|e| {
+        error!(step_id = %lease.step_id, "invalid step request: {}", e);
         (
-            StatusCode::BAD_REQUEST,
+            StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: format!("failed to resume run: {e}"),
+                error: format!("invalid step request: {e}"),
             }),
         )
     })?;
-
-    match result {
-        Some(_) => {
-            info!("resumed run: {}", id);
-            Ok(StatusCode::NO_CONTENT)
-        }
-        None => Err((
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse {
-                error: "no capacity to resume run".to_string(),
-            }),
-        )),
+    let payload = EventPayload::LeaseGranted(LeaseGrantedPayload {
+        step_id: lease.step_id.clone(),
+        worker_id: worker_id.clone(),
+        attempt: lease.attempt,
+        expires_at,
+    });
+    if let Err(e) = state
+        .storage
+        .append_event(&lease.run_id, Some(&lease.step_id), &payload)
+        .await
+    {
+        warn!(step_id = %lease.step_id, "failed to record LEASE_GRANTED: {}", e);
     }
+
+    info!(
+        step_id = %lease.step_id,
+        worker_id = %worker_id,
+        attempt = lease.attempt,
+        "lease granted"
+    );
+    Ok(Json(ClaimResponse { lease, request }).into_response())
 }
 
-/// POST /runs/{id}/cancel - Cancel a run.
-async fn cancel_run(
+/// POST /steps/{id}/complete - Record a worker's report for a leased step.
+///
+/// Returns 409 if the worker no longer holds the lease; it should stop.
+async fn complete_step(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     AxumPath(id): AxumPath<String>,
+    Json(req): Json<CompleteStepRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     check_auth(&state, &headers)?;
 
-    let run_id = Id::from_string(&id);
-
-    state.scheduler.cancel_run(&run_id).await.map_err(|e| {
-        warn!("failed to cancel run {}: {}", id, e);
+    let step_id = Id::from_string(&id);
+    let report_json = serde_json::to_string(&req.report).map_err(|e| {
         (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: format!("failed to cancel run: {e}"),
+                error: format!("invalid step report: {e}"),
             }),
         )
     })?;
+    state
+        .storage
+        .complete_lease(&step_id, &req.worker_id, Utc::now(), &report_json)
+        .await
+        .map_err(|e| {
+            if matches!(e, StorageError::StaleLease(_)) {
+                warn!(step_id = %step_id, worker_id = %req.worker_id, "rejected stale completion");
+            }
+            worker_error("complete step", e)
+        })?;
 
-    info!("canceled run: {}", id);
+    info!(step_id = %step_id, worker_id = %req.worker_id, "step completed by worker");
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// POST /runs/{id}/retry - Retry a failed run by re-queuing it.
-async fn retry_run(
+/// Query params for PUT /steps/{id}/artifacts/{name}.
+#[derive(Debug, Deserialize)]
+pub struct UploadArtifactQuery {
+    pub worker_id: String,
+}
+
+/// PUT /steps/{id}/artifacts/{name} - Store a file a worker wrote for a leased step.
+///
+/// The file lands in the controller's run directory, where the run loop
+/// mirrors and registers step logs as usual. Returns 409 if the worker no
+/// longer holds the lease.
+async fn upload_step_artifact(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    AxumPath(id): AxumPath<String>,
+    AxumPath((id, name)): AxumPath<(String, String)>,
+    Query(query): Query<UploadArtifactQuery>,
+    body: Bytes,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     check_auth(&state, &headers)?;
 
-    let run_id = Id::from_string(&id);
+    let step_id = Id::from_string(&id);
+    let worker_id = Id::from_string(&query.worker_id);
+    let request_json = state
+        .storage
+        .held_lease_request(&step_id, &worker_id, Utc::now())
+        .await
+        .map_err(|e| worker_error("upload artifact", e))?
+        .ok_or_else(|| {
+            worker_error(
+                "upload artifact",
+                StorageError::StaleLease(step_id.to_string()),
+            )
+        })?;
+    let request: StepRequest = serde_json::from_str(&request_json).map_err(|e| {
+        error!(step_id = %step_id, "invalid step request: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("invalid step request: {e}"),
+            }),
+        )
+    })?;
 
-    state.scheduler.retry_run(&run_id).await.map_err(|e| {
-        warn!("failed to retry run {}: {}", id, e);
+    let written = workers::store_artifact(&request.run_dir, &name, &body).map_err(|e| {
+        let status = if e.kind() == std::io::ErrorKind::InvalidInput {
+            StatusCode::BAD_REQUEST
+        } else {
+            error!(step_id = %step_id, artifact = %name, "failed to store artifact: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
         (
-            StatusCode::BAD_REQUEST,
+            status,
             Json(ErrorResponse {
-                error: format!("failed to retry run: {e}"),
+                error: e.to_string(),
             }),
         )
     })?;
 
-    info!("retried run: {}", id);
+    info!(
+        step_id = %step_id,
+        worker_id = %worker_id,
+        artifact = %name,
+        bytes = body.len(),
+        written,
+        "artifact uploaded by worker"
+    );
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -819,12 +1879,15 @@ async fn trigger_postmortem(
         ));
     }
 
-    // Check if claude is available
-    if !crate::postmortem::is_claude_available() {
+    // Check if the agent backend is available
+    if !crate::postmortem::is_agent_available(&config) {
         return Err((
             StatusCode::SERVICE_UNAVAILABLE,
             Json(ErrorResponse {
-                error: "claude CLI not found".to_string(),
+                error: format!(
+                    "agent backend '{}' unavailable for analysis",
+                    config.agent_backend.as_str()
+                ),
             }),
         ));
     }
@@ -1133,6 +2196,8 @@ async fn remove_worktree(
 #[derive(Debug, Deserialize, Default)]
 pub struct StreamEventsQuery {
     /// Timestamp (ms since epoch) to start from. Events after this time are returned.
+    ///
+    /// Ignored when the `Last-Event-ID` header is present.
     #[serde(default)]
     pub after: Option<i64>,
 }
@@ -1165,10 +2230,137 @@ impl From<&Event> for SseEventData {
     }
 }
 
+/// Header SSE clients send on reconnect with the id of the last event they saw.
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// How often live streams re-check run status.
+///
+/// Events and output are pushed through the bus; this only catches runs that
+/// end without a terminal event (cancellation) and is a single-row lookup.
+const STREAM_STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+fn is_terminal_status(status: RunStatus) -> bool {
+    matches!(
+        status,
+        RunStatus::Completed | RunStatus::Failed | RunStatus::Canceled
+    )
+}
+
+/// Decode a synthetic `RUN_STATUS_CHANGED` event.
+fn status_change(event: &Event) -> Option<RunStatusChangedPayload> {
+    if event.event_type != EventType::RunStatusChanged.as_str() {
+        return None;
+    }
+    serde_json::from_str(&event.payload_json).ok()
+}
+
+fn is_terminal_event(event: &Event) -> bool {
+    event.event_type == EventType::RunCompleted.as_str()
+        || event.event_type == EventType::RunFailed.as_str()
+        || status_change(event).is_some_and(|change| is_terminal_status(change.to))
+}
+
+/// Whether an event only exists on the bus and cannot be resumed from.
+fn is_synthetic_event(event: &Event) -> bool {
+    event.event_type == EventType::RunStatusChanged.as_str()
+}
+
+/// Convert an event to SSE.
+///
+/// Synthetic events carry no SSE id, so a client's `Last-Event-ID` keeps
+/// pointing at the last stored event.
+fn event_to_sse(event: &Event) -> SseEvent {
+    let data = SseEventData::from(event);
+    let json = serde_json::to_string(&data).unwrap_or_default();
+    let sse = SseEvent::default().event(&data.event_type).data(json);
+    if is_synthetic_event(event) {
+        sse
+    } else {
+        sse.id(data.id)
+    }
+}
+
+/// Live state for one `GET /runs/{id}/events` connection.
+struct EventStream {
+    storage: Arc<dyn StorageBackend>,
+    run_id: Id,
+    rx: broadcast::Receiver<Event>,
+    status_check: tokio::time::Interval,
+    /// Last stored event delivered; everything after it is read from storage.
+    last_id: Option<Id>,
+    done: bool,
+}
+
+impl EventStream {
+    /// Read stored events after the last delivered one.
+    ///
+    /// Bus events only signal that something was written, so events racing
+    /// the history query are never repeated and no per-id state is kept.
+    async fn catch_up(&mut self) -> Option<Vec<Event>> {
+        let events = self
+            .storage
+            .list_events_after(&self.run_id, self.last_id.as_ref())
+            .await
+            .ok()?;
+        if let Some(last) = events.last() {
+            self.last_id = Some(last.id.clone());
+        }
+        Some(events)
+    }
+
+    /// Wait for the next batch of events, or `None` when the stream should end.
+    async fn next_batch(&mut self) -> Option<Vec<Event>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            tokio::select! {
+                received = self.rx.recv() => match received {
+                    Ok(event) if event.run_id == self.run_id => {
+                        let batch = if is_synthetic_event(&event) {
+                            vec![event]
+                        } else {
+                            self.catch_up().await?
+                        };
+                        if batch.iter().any(is_terminal_event) {
+                            self.done = true;
+                        }
+                        if !batch.is_empty() {
+                            return Some(batch);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(
+                            run_id = %self.run_id,
+                            skipped,
+                            "event stream lagged; catching up from storage"
+                        );
+                        let batch = self.catch_up().await?;
+                        if !batch.is_empty() {
+                            return Some(batch);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+                _ = self.status_check.tick() => {
+                    let run = self.storage.get_run(&self.run_id).await.ok()?;
+                    if is_terminal_status(run.status) {
+                        self.done = true;
+                        return self.catch_up().await;
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// GET /runs/{id}/events - Stream events for a run (SSE).
 ///
-/// Returns a Server-Sent Events stream of structured events.
-/// Supports reconnection via `after` query param (timestamp in ms).
+/// Returns a Server-Sent Events stream of structured events: the stored
+/// history first, then live events pushed from the storage bus. Clients
+/// resume with the `Last-Event-ID` header; the millisecond `after` query
+/// param is still honored but can skip events that share a timestamp.
 async fn stream_events(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -1191,158 +2383,245 @@ async fn stream_events(
         )
     })?;
 
-    let storage = Arc::clone(&state.storage);
-    let run_id_clone = run_id.clone();
-    let after_ts = query.after;
-
-    // Create a stream that polls for events
-    let stream = stream::unfold(
-        (storage, run_id_clone, after_ts, run.status, false),
-        move |(storage, run_id, last_ts, status, sent_initial)| async move {
-            // On first iteration, send all historical events
-            if !sent_initial {
-                let events = match storage.list_events(&run_id).await {
-                    Ok(events) => events,
-                    Err(_) => return None,
-                };
-
-                // Filter by after timestamp if provided
-                let filtered: Vec<_> = events
-                    .iter()
-                    .filter(|e| {
-                        if let Some(after) = last_ts {
-                            e.timestamp.timestamp_millis() > after
-                        } else {
-                            true
-                        }
-                    })
-                    .collect();
+    // Subscribe before reading history so nothing written in between is missed.
+    let rx = state.storage.bus().subscribe_events();
+
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(Id::from_string);
+    let mut history = state
+        .storage
+        .list_events_after(&run_id, last_event_id.as_ref())
+        .await
+        .map_err(|e| {
+            error!("failed to list events: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("failed to list events: {e}"),
+                }),
+            )
+        })?;
+    // Live events resume after the newest stored event even when `after`
+    // hides some of the history.
+    let last_id = history
+        .last()
+        .map(|e| e.id.clone())
+        .or(last_event_id.clone());
+    if let (None, Some(after)) = (&last_event_id, query.after) {
+        history.retain(|e| e.timestamp.timestamp_millis() > after);
+    }
+
+    let mut status_check = tokio::time::interval(STREAM_STATUS_CHECK_INTERVAL);
+    status_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    status_check.reset();
+    let mut live = EventStream {
+        storage: Arc::clone(&state.storage),
+        run_id,
+        rx,
+        status_check,
+        last_id,
+        done: is_terminal_status(run.status),
+    };
+    let live_done = live.done || history.iter().any(is_terminal_event);
+    live.done = live_done;
+
+    let history_stream = stream::iter(history.iter().map(event_to_sse).map(Ok).collect::<Vec<_>>());
+    let live_stream = stream::unfold(live, |mut live| async move {
+        let batch = live.next_batch().await?;
+        let events: Vec<_> = batch.iter().map(event_to_sse).map(Ok).collect();
+        Some((stream::iter(events), live))
+    })
+    .flatten();
+
+    Ok(Sse::new(history_stream.chain(live_stream)).keep_alive(KeepAlive::default()))
+}
+
+/// Query params for GET /events.
+///
+/// List filters take comma-separated values; an event passes when it matches
+/// any value of every filter given.
+#[derive(Debug, Deserialize, Default)]
+pub struct FirehoseQuery {
+    /// Only events for runs in this workspace root.
+    #[serde(default)]
+    pub workspace: Option<String>,
+    /// Only these event types (e.g. `RUN_STATUS_CHANGED,RUN_FAILED`).
+    #[serde(default)]
+    pub event_type: Option<String>,
+    /// Only events for runs currently in these statuses.
+    #[serde(default)]
+    pub run_status: Option<String>,
+}
 
-                // Get last timestamp for next poll
-                let new_last_ts = filtered.last().map(|e| e.timestamp.timestamp_millis());
+/// Parsed filters for the firehose.
+#[derive(Debug, Default)]
+struct FirehoseFilter {
+    workspace: Option<String>,
+    event_types: Option<Vec<String>>,
+    run_statuses: Option<Vec<RunStatus>>,
+}
 
-                // Convert events to SSE events
-                let sse_events: Vec<_> = filtered
+impl FirehoseFilter {
+    fn parse(query: FirehoseQuery) -> Result<Self, String> {
+        let split = |raw: &str| -> Vec<String> {
+            raw.split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let event_types = match query.event_type.as_deref() {
+            None => None,
+            Some(raw) => {
+                let types = split(raw)
                     .into_iter()
-                    .map(|e| {
-                        let data = SseEventData::from(e);
-                        let json = serde_json::to_string(&data).unwrap_or_default();
-                        Ok(SseEvent::default()
-                            .event(&data.event_type)
-                            .data(json)
-                            .id(data.id))
+                    .map(|t| {
+                        let upper = t.to_ascii_uppercase();
+                        serde_json::from_value::<EventType>(serde_json::Value::String(upper))
+                            .map(|parsed| parsed.as_str().to_string())
+                            .map_err(|_| format!("invalid event_type: {t}"))
                     })
-                    .collect();
-
-                let events_stream = stream::iter(sse_events);
-                return Some((
-                    events_stream,
-                    (storage, run_id, new_last_ts.or(last_ts), status, true),
-                ));
+                    .collect::<Result<Vec<_>, _>>()?;
+                Some(types)
+            }
+        };
+        let run_statuses = match query.run_status.as_deref() {
+            None => None,
+            Some(raw) => {
+                let statuses = split(raw)
+                    .into_iter()
+                    .map(|s| parse_run_status(&s).ok_or_else(|| format!("invalid run_status: {s}")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Some(statuses)
             }
+        };
+        Ok(Self {
+            workspace: query.workspace.filter(|w| !w.is_empty()),
+            event_types,
+            run_statuses,
+        })
+    }
+
+    /// Whether matching needs the run's workspace or status.
+    fn needs_run(&self) -> bool {
+        self.workspace.is_some() || self.run_statuses.is_some()
+    }
+}
+
+/// Workspace and latest known status of a run seen on the firehose.
+#[derive(Debug, Clone)]
+struct RunSnapshot {
+    workspace_root: String,
+    status: RunStatus,
+}
+
+/// Live state for one `GET /events` connection.
+struct Firehose {
+    storage: Arc<dyn StorageBackend>,
+    rx: broadcast::Receiver<Event>,
+    filter: FirehoseFilter,
+    /// Runs looked up so far, kept current from `RUN_STATUS_CHANGED` events.
+    runs: HashMap<Id, RunSnapshot>,
+}
 
-            // Check if run is terminal (completed, failed, canceled)
-            let current_run = match storage.get_run(&run_id).await {
-                Ok(r) => r,
-                Err(_) => return None,
+impl Firehose {
+    async fn run_snapshot(&mut self, event: &Event) -> Option<RunSnapshot> {
+        if let Some(change) = status_change(event) {
+            let snapshot = RunSnapshot {
+                workspace_root: change.workspace_root,
+                status: change.to,
             };
+            self.runs.insert(event.run_id.clone(), snapshot.clone());
+            return Some(snapshot);
+        }
+        if let Some(snapshot) = self.runs.get(&event.run_id) {
+            return Some(snapshot.clone());
+        }
+        let run = self.storage.get_run(&event.run_id).await.ok()?;
+        let snapshot = RunSnapshot {
+            workspace_root: run.workspace_root,
+            status: run.status,
+        };
+        self.runs.insert(event.run_id.clone(), snapshot.clone());
+        Some(snapshot)
+    }
 
-            let is_terminal = matches!(
-                current_run.status,
-                RunStatus::Completed | RunStatus::Failed | RunStatus::Canceled
-            );
+    async fn matches(&mut self, event: &Event) -> bool {
+        if let Some(types) = &self.filter.event_types {
+            if !types.contains(&event.event_type) {
+                return false;
+            }
+        }
+        if !self.filter.needs_run() {
+            return true;
+        }
+        let Some(run) = self.run_snapshot(event).await else {
+            return false;
+        };
+        if let Some(workspace) = &self.filter.workspace {
+            if &run.workspace_root != workspace {
+                return false;
+            }
+        }
+        if let Some(statuses) = &self.filter.run_statuses {
+            if !statuses.contains(&run.status) {
+                return false;
+            }
+        }
+        true
+    }
 
-            // If terminal and we've already sent initial events, check for new events once more
-            if is_terminal {
-                // Get any new events since last check
-                let events = match storage.list_events(&run_id).await {
-                    Ok(events) => events,
-                    Err(_) => return None,
-                };
-
-                let filtered: Vec<_> = events
-                    .iter()
-                    .filter(|e| {
-                        if let Some(after) = last_ts {
-                            e.timestamp.timestamp_millis() > after
-                        } else {
-                            false // Already sent all in initial batch
-                        }
-                    })
-                    .collect();
-
-                if !filtered.is_empty() {
-                    let sse_events: Vec<_> = filtered
-                        .into_iter()
-                        .map(|e| {
-                            let data = SseEventData::from(e);
-                            let json = serde_json::to_string(&data).unwrap_or_default();
-                            Ok(SseEvent::default()
-                                .event(&data.event_type)
-                                .data(json)
-                                .id(data.id))
-                        })
-                        .collect();
-                    return Some((
-                        stream::iter(sse_events),
-                        (storage, run_id, None, current_run.status, true),
-                    ));
+    /// Wait for the next matching event, or `None` if the bus shuts down.
+    async fn next_event(&mut self) -> Option<SseEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => {
+                    if self.matches(&event).await {
+                        return Some(event_to_sse(&event));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "firehose lagged; events dropped");
+                    let data = serde_json::json!({ "skipped": skipped });
+                    return Some(SseEvent::default().event("lagged").data(data.to_string()));
                 }
-                // Terminal and no more events, end stream
-                return None;
+                Err(broadcast::error::RecvError::Closed) => return None,
             }
+        }
+    }
+}
 
-            // Non-terminal: poll for new events after a delay
-            tokio::time::sleep(Duration::from_secs(1)).await;
+/// GET /events - Stream live events for all runs (SSE).
+///
+/// Live only: there is no history replay, so clients that cannot miss events
+/// should also follow `/runs/{id}/events`. Includes the synthetic
+/// `RUN_STATUS_CHANGED` events, and a `lagged` event when this client fell
+/// behind the bus and events were dropped.
+async fn stream_all_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<FirehoseQuery>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, (StatusCode, Json<ErrorResponse>)>
+{
+    check_auth(&state, &headers)?;
 
-            let events = match storage.list_events(&run_id).await {
-                Ok(events) => events,
-                Err(_) => {
-                    return Some((
-                        stream::iter(vec![]),
-                        (storage, run_id, last_ts, current_run.status, true),
-                    ))
-                }
-            };
+    let filter = FirehoseFilter::parse(query)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
 
-            let filtered: Vec<_> = events
-                .iter()
-                .filter(|e| {
-                    if let Some(after) = last_ts {
-                        e.timestamp.timestamp_millis() > after
-                    } else {
-                        false // Already sent all in initial batch
-                    }
-                })
-                .collect();
-
-            let new_last_ts = filtered.last().map(|e| e.timestamp.timestamp_millis());
-
-            let sse_events: Vec<_> = filtered
-                .into_iter()
-                .map(|e| {
-                    let data = SseEventData::from(e);
-                    let json = serde_json::to_string(&data).unwrap_or_default();
-                    Ok(SseEvent::default()
-                        .event(&data.event_type)
-                        .data(json)
-                        .id(data.id))
-                })
-                .collect();
-
-            Some((
-                stream::iter(sse_events),
-                (
-                    storage,
-                    run_id,
-                    new_last_ts.or(last_ts),
-                    current_run.status,
-                    true,
-                ),
-            ))
-        },
-    )
-    .flatten();
+    let firehose = Firehose {
+        storage: Arc::clone(&state.storage),
+        rx: state.storage.bus().subscribe_events(),
+        filter,
+        runs: HashMap::new(),
+    };
+    let stream = stream::unfold(firehose, |mut firehose| async move {
+        let event = firehose.next_event().await?;
+        Some((Ok(event), firehose))
+    });
 
     Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
@@ -1350,15 +2629,127 @@ async fn stream_events(
 /// Query params for GET /runs/{id}/output.
 #[derive(Debug, Deserialize, Default)]
 pub struct StreamOutputQuery {
-    /// Byte offset to start reading from.
+    /// Byte offset to start reading the first step's output from.
     #[serde(default)]
     pub offset: Option<u64>,
 }
 
+fn output_to_sse(step_id: &Id, offset: u64, content: &str) -> SseEvent {
+    let data = serde_json::json!({
+        "step_id": step_id.to_string(),
+        "offset": offset,
+        "content": content,
+    });
+    SseEvent::default()
+        .event("output")
+        .data(serde_json::to_string(&data).unwrap_or_default())
+}
+
+/// Live state for one `GET /runs/{id}/output` connection.
+struct OutputStream {
+    storage: Arc<dyn StorageBackend>,
+    run_id: Id,
+    rx: broadcast::Receiver<OutputChunk>,
+    /// Watched for the run's terminal event so the stream ends promptly.
+    events: broadcast::Receiver<Event>,
+    status_check: tokio::time::Interval,
+    /// Bytes of each step's log already delivered.
+    sent: HashMap<Id, u64>,
+    done: bool,
+}
+
+impl OutputStream {
+    /// Read finished steps' log files past what has been delivered.
+    ///
+    /// Steps only record `output_path` once they finish; output of the step in
+    /// flight arrives through the bus instead. Finished logs may be compressed
+    /// or evicted by the run's log cap; evicted logs are skipped.
+    async fn catch_up(&mut self) -> Option<Vec<SseEvent>> {
+        let steps = self.storage.list_steps(&self.run_id).await.ok()?;
+        let mut events = Vec::new();
+        for step in steps {
+            let Some(output_path) = &step.output_path else {
+                continue;
+            };
+            let from = self.sent.get(&step.id).copied().unwrap_or(0);
+            let Ok(bytes) = logs::read_from(Path::new(output_path), from) else {
+                continue;
+            };
+            if !bytes.is_empty() {
+                let content = String::from_utf8_lossy(&bytes);
+                events.push(output_to_sse(&step.id, from, &content));
+                self.sent.insert(step.id, from + bytes.len() as u64);
+            }
+        }
+        Some(events)
+    }
+
+    /// Trim a live chunk to the bytes not yet delivered for its step.
+    fn accept(&mut self, chunk: &OutputChunk) -> Option<SseEvent> {
+        let end = chunk.offset + chunk.content.len() as u64;
+        let delivered = self.sent.get(&chunk.step_id).copied().unwrap_or(0);
+        if end <= delivered {
+            return None;
+        }
+        let skip = delivered.saturating_sub(chunk.offset);
+        let content = chunk.content.get(skip as usize..)?;
+        self.sent.insert(chunk.step_id.clone(), end);
+        Some(output_to_sse(&chunk.step_id, chunk.offset + skip, content))
+    }
+
+    /// Wait for the next batch of output, or `None` when the stream should end.
+    async fn next_batch(&mut self) -> Option<Vec<SseEvent>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            tokio::select! {
+                received = self.rx.recv() => match received {
+                    Ok(chunk) if chunk.run_id == self.run_id => {
+                        if let Some(event) = self.accept(&chunk) {
+                            return Some(vec![event]);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Gaps in the live step show up as a jump in `offset`.
+                        warn!(
+                            run_id = %self.run_id,
+                            skipped,
+                            "output stream lagged; catching up from logs"
+                        );
+                        let batch = self.catch_up().await?;
+                        if !batch.is_empty() {
+                            return Some(batch);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+                received = self.events.recv() => match received {
+                    Ok(event) if event.run_id == self.run_id && is_terminal_event(&event) => {
+                        self.done = true;
+                        return self.catch_up().await;
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+                _ = self.status_check.tick() => {
+                    let run = self.storage.get_run(&self.run_id).await.ok()?;
+                    if is_terminal_status(run.status) {
+                        self.done = true;
+                        return self.catch_up().await;
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// GET /runs/{id}/output - Stream raw iteration output (SSE).
 ///
-/// Returns a Server-Sent Events stream of raw output chunks.
-/// Reads from step output files and streams new content as it appears.
+/// Returns a Server-Sent Events stream of raw output chunks: log files of
+/// finished steps first, then chunks pushed by the runner as it writes them.
+/// Each event carries the chunk's byte `offset` within its step log.
 async fn stream_output(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -1381,106 +2772,50 @@ async fn stream_output(
         )
     })?;
 
-    let storage = Arc::clone(&state.storage);
-    let run_id_clone = run_id.clone();
-    let initial_offset = query.offset.unwrap_or(0);
-
-    // Stream state: (storage, run_id, current_step_idx, current_file_offset, run_status)
-    let stream = stream::unfold(
-        (storage, run_id_clone, 0usize, initial_offset, run.status),
-        move |(storage, run_id, step_idx, file_offset, _status)| async move {
-            loop {
-                // Get current run status
-                let current_run = match storage.get_run(&run_id).await {
-                    Ok(r) => r,
-                    Err(_) => return None,
-                };
-
-                // Get all steps
-                let steps = match storage.list_steps(&run_id).await {
-                    Ok(s) => s,
-                    Err(_) => return None,
-                };
-
-                // If no steps yet, wait and retry
-                if steps.is_empty() {
-                    if matches!(
-                        current_run.status,
-                        RunStatus::Completed | RunStatus::Failed | RunStatus::Canceled
-                    ) {
-                        return None; // Run ended with no steps
-                    }
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-                    continue;
-                }
+    // Subscribe before reading logs so chunks written in between are not missed.
+    let rx = state.storage.bus().subscribe_output();
+    let events = state.storage.bus().subscribe_events();
 
-                // Process steps starting from step_idx
-                for (idx, step) in steps.iter().enumerate().skip(step_idx) {
-                    if let Some(output_path) = &step.output_path {
-                        // Try to read output file
-                        let path = std::path::Path::new(output_path);
-                        if path.exists() {
-                            let metadata = match std::fs::metadata(path) {
-                                Ok(m) => m,
-                                Err(_) => continue,
-                            };
-
-                            let current_offset = if idx == step_idx { file_offset } else { 0 };
-                            let file_size = metadata.len();
-
-                            if file_size > current_offset {
-                                // Read new content
-                                let content = match std::fs::read_to_string(path) {
-                                    Ok(c) => c,
-                                    Err(_) => continue,
-                                };
-
-                                let new_content = &content[current_offset as usize..];
-                                if !new_content.is_empty() {
-                                    let data = serde_json::json!({
-                                        "step_id": step.id.to_string(),
-                                        "offset": current_offset,
-                                        "content": new_content,
-                                    });
-                                    let event = Ok(SseEvent::default()
-                                        .event("output")
-                                        .data(serde_json::to_string(&data).unwrap_or_default()));
-
-                                    return Some((
-                                        event,
-                                        (storage, run_id, idx, file_size, current_run.status),
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                }
+    let mut sent = HashMap::new();
+    if let Some(offset) = query.offset.filter(|o| *o > 0) {
+        let steps = state.storage.list_steps(&run_id).await.unwrap_or_default();
+        if let Some(first) = steps.first() {
+            sent.insert(first.id.clone(), offset);
+        }
+    }
 
-                // Check if run is terminal
-                if matches!(
-                    current_run.status,
-                    RunStatus::Completed | RunStatus::Failed | RunStatus::Canceled
-                ) {
-                    return None; // End of stream
-                }
+    let mut status_check = tokio::time::interval(STREAM_STATUS_CHECK_INTERVAL);
+    status_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    status_check.reset();
+    let mut live = OutputStream {
+        storage: Arc::clone(&state.storage),
+        run_id,
+        rx,
+        events,
+        status_check,
+        sent,
+        done: is_terminal_status(run.status),
+    };
+    let history = live.catch_up().await.unwrap_or_default();
 
-                // No new content, wait and retry
-                tokio::time::sleep(Duration::from_millis(500)).await;
-            }
-        },
-    );
+    let history_stream = stream::iter(history.into_iter().map(Ok));
+    let live_stream = stream::unfold(live, |mut live| async move {
+        let batch = live.next_batch().await?;
+        Some((stream::iter(batch.into_iter().map(Ok)), live))
+    })
+    .flatten();
 
-    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+    Ok(Sse::new(history_stream.chain(live_stream)).keep_alive(KeepAlive::default()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::DEFAULT_MAX_CONCURRENT_RUNS;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
     use axum::body::Body;
     use axum::http::Request;
     use axum::response::Response;
-    use loop_core::events::{EventPayload, RunCreatedPayload};
+    use loop_core::events::RunCreatedPayload;
     use tempfile::TempDir;
     use tower::ServiceExt;
 
@@ -1491,12 +2826,13 @@ mod tests {
             .await
             .unwrap();
         storage.migrate_embedded().await.unwrap();
-        let storage = Arc::new(storage);
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
         let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 3));
 
         let state = Arc::new(AppState {
             storage,
             scheduler,
+            skills_metrics: Arc::new(SkillsMetrics::new()),
             auth_token: None,
         });
 
@@ -1582,12 +2918,13 @@ mod tests {
             .await
             .unwrap();
         storage.migrate_embedded().await.unwrap();
-        let storage = Arc::new(storage);
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
         let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 3));
 
         let state = Arc::new(AppState {
             storage,
             scheduler,
+            skills_metrics: Arc::new(SkillsMetrics::new()),
             auth_token: Some("secret-token".to_string()),
         });
 
@@ -1640,6 +2977,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         state.storage.insert_run(&run).await.unwrap();
 
@@ -1734,6 +3072,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         state.storage.insert_run(&run).await.unwrap();
 
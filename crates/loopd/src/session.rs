@@ -0,0 +1,326 @@
+//! Agent session continuity across implementation iterations.
+//!
+//! With `session_resume` enabled, each implementation iteration continues the
+//! previous iteration's Claude session (`--resume <id>`) and is sent only a
+//! delta prompt (runner notes and review feedback) instead of the full prompt,
+//! so the agent keeps its context instead of re-reading the spec every loop.
+//!
+//! A fresh session (with the full prompt) starts when:
+//! - the session has spanned `session_max_turns` iterations,
+//! - the watchdog rewrites the prompt,
+//! - an implementation step fails or reports no session id.
+
+use loop_core::{AgentBackendKind, Config, Step, StepPhase, StepStatus};
+use tracing::info;
+
+/// Review feedback longer than this is cut to its last characters.
+const MAX_REVIEW_FEEDBACK_CHARS: usize = 8_000;
+
+/// Why the current session was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionReset {
+    /// The session reached `session_max_turns` iterations.
+    MaxTurns,
+    /// The watchdog rewrote the prompt.
+    WatchdogRewrite,
+    /// The implementation step failed or reported no session.
+    StepFailed,
+}
+
+impl SessionReset {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MaxTurns => "max_turns",
+            Self::WatchdogRewrite => "watchdog_rewrite",
+            Self::StepFailed => "step_failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ActiveSession {
+    id: String,
+    /// Implementation iterations run in this session so far.
+    turns: u32,
+}
+
+/// Tracks the agent session implementation iterations continue.
+#[derive(Debug, Default)]
+pub struct SessionTracker {
+    enabled: bool,
+    /// Iterations per session (0 = unlimited).
+    max_turns: u32,
+    current: Option<ActiveSession>,
+}
+
+impl SessionTracker {
+    /// Enabled by `session_resume`, for the Claude backend only.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            enabled: config.session_resume && config.agent_backend == AgentBackendKind::Claude,
+            max_turns: config.session_max_turns,
+            current: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Pick up the session of a run's previous iterations, e.g. after a restart.
+    ///
+    /// Only a session whose last implementation step succeeded is continued.
+    pub fn restore(&mut self, steps: &[Step]) {
+        if !self.enabled {
+            return;
+        }
+        let implementation: Vec<&Step> = steps
+            .iter()
+            .filter(|step| step.phase == StepPhase::Implementation)
+            .collect();
+        let Some(last) = implementation.last() else {
+            return;
+        };
+        let Some(id) = last.session_id.as_ref() else {
+            return;
+        };
+        if last.status != StepStatus::Succeeded {
+            return;
+        }
+        let turns = implementation
+            .iter()
+            .rev()
+            .take_while(|step| step.session_id.as_ref() == Some(id))
+            .count() as u32;
+        self.current = Some(ActiveSession {
+            id: id.clone(),
+            turns,
+        });
+    }
+
+    /// Session the next implementation iteration should resume, if any.
+    ///
+    /// Drops the current session first if it has used up its turns.
+    pub fn begin_iteration(&mut self) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        let exhausted = self
+            .current
+            .as_ref()
+            .is_some_and(|session| self.max_turns > 0 && session.turns >= self.max_turns);
+        if exhausted {
+            self.reset(SessionReset::MaxTurns);
+        }
+        self.current.as_ref().map(|session| session.id.clone())
+    }
+
+    /// Record the outcome of an implementation iteration.
+    ///
+    /// `session_id` is the session the agent reported; a resumed session may
+    /// come back under a new id.
+    pub fn finish_iteration(&mut self, session_id: Option<String>, succeeded: bool) {
+        if !self.enabled {
+            return;
+        }
+        match session_id {
+            Some(id) if succeeded => {
+                let turns = self.current.as_ref().map_or(0, |session| session.turns) + 1;
+                self.current = Some(ActiveSession { id, turns });
+            }
+            _ => self.reset(SessionReset::StepFailed),
+        }
+    }
+
+    /// Drop the current session so the next iteration starts fresh.
+    pub fn reset(&mut self, reason: SessionReset) {
+        if let Some(session) = self.current.take() {
+            info!(
+                session_id = %session.id,
+                turns = session.turns,
+                reason = reason.as_str(),
+                "starting a new agent session"
+            );
+        }
+    }
+}
+
+/// Review output worth passing on: empty for approvals, trimmed to a bounded tail.
+pub fn review_feedback(output: &str) -> Option<String> {
+    let output = output.trim();
+    let approved = output
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.trim() == "APPROVED");
+    if output.is_empty() || approved {
+        return None;
+    }
+    let chars = output.chars().count();
+    Some(if chars > MAX_REVIEW_FEEDBACK_CHARS {
+        output
+            .chars()
+            .skip(chars - MAX_REVIEW_FEEDBACK_CHARS)
+            .collect()
+    } else {
+        output.to_string()
+    })
+}
+
+/// Prompt for an iteration that resumes the previous session.
+///
+/// The session already holds the spec, plan, and task rules, so only what
+/// changed since the last iteration is sent. A child run (`subrun_task`)
+/// keeps working on its one task instead of picking the next one.
+pub fn build_resume_prompt(
+    runner_notes: &str,
+    review_feedback: Option<&str>,
+    subrun_task: Option<&str>,
+) -> String {
+    let mut prompt = match subrun_task {
+        Some(task_id) => format!(
+            "Continue with the next iteration. The spec and task rules are unchanged; \
+             keep working only on plan task `{task_id}`.\n"
+        ),
+        None => String::from(
+            "Continue with the next iteration. The spec and task rules are unchanged; \
+             re-read the plan for its current state before choosing the next unchecked task.\n",
+        ),
+    };
+    let runner_notes = runner_notes.trim();
+    if !runner_notes.is_empty() {
+        prompt.push_str("\n## Runner notes\n\n");
+        prompt.push_str(runner_notes);
+        prompt.push('\n');
+    }
+    if let Some(feedback) = review_feedback {
+        prompt.push_str("\n## Review feedback on the previous iteration\n\n");
+        prompt.push_str(feedback.trim());
+        prompt.push('\n');
+    }
+    prompt.push_str(
+        "\nFollow the same task rules and response format as in the first prompt of this session.\n",
+    );
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loop_core::Id;
+
+    fn tracker(max_turns: u32) -> SessionTracker {
+        let config = Config {
+            session_resume: true,
+            session_max_turns: max_turns,
+            ..Config::default()
+        };
+        SessionTracker::from_config(&config)
+    }
+
+    fn implementation_step(status: StepStatus, session_id: Option<&str>) -> Step {
+        Step {
+            id: Id::new(),
+            run_id: Id::new(),
+            phase: StepPhase::Implementation,
+            status,
+            attempt: 1,
+            started_at: None,
+            ended_at: None,
+            exit_code: None,
+            prompt_path: None,
+            output_path: None,
+            session_id: session_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn resumes_until_max_turns() {
+        let mut sessions = tracker(2);
+        assert_eq!(sessions.begin_iteration(), None);
+        sessions.finish_iteration(Some("s1".to_string()), true);
+        assert_eq!(sessions.begin_iteration().as_deref(), Some("s1"));
+        sessions.finish_iteration(Some("s1".to_string()), true);
+
+        // Two iterations used: the third starts a new session.
+        assert_eq!(sessions.begin_iteration(), None);
+        sessions.finish_iteration(Some("s2".to_string()), true);
+        assert_eq!(sessions.begin_iteration().as_deref(), Some("s2"));
+    }
+
+    #[test]
+    fn failures_and_rewrites_reset_the_session() {
+        let mut sessions = tracker(0);
+        sessions.finish_iteration(Some("s1".to_string()), true);
+        sessions.finish_iteration(Some("s1".to_string()), false);
+        assert_eq!(sessions.begin_iteration(), None);
+
+        sessions.finish_iteration(Some("s2".to_string()), true);
+        sessions.reset(SessionReset::WatchdogRewrite);
+        assert_eq!(sessions.begin_iteration(), None);
+    }
+
+    #[test]
+    fn disabled_for_other_backends() {
+        let config = Config {
+            session_resume: true,
+            agent_backend: AgentBackendKind::Command,
+            ..Config::default()
+        };
+        let mut sessions = SessionTracker::from_config(&config);
+        assert!(!sessions.is_enabled());
+        sessions.finish_iteration(Some("s1".to_string()), true);
+        assert_eq!(sessions.begin_iteration(), None);
+    }
+
+    #[test]
+    fn restore_continues_last_successful_session() {
+        let mut sessions = tracker(3);
+        sessions.restore(&[
+            implementation_step(StepStatus::Succeeded, Some("old")),
+            implementation_step(StepStatus::Succeeded, Some("s1")),
+            implementation_step(StepStatus::Succeeded, Some("s1")),
+        ]);
+        assert_eq!(sessions.begin_iteration().as_deref(), Some("s1"));
+        sessions.finish_iteration(Some("s1".to_string()), true);
+        assert_eq!(sessions.begin_iteration(), None);
+
+        let mut sessions = tracker(3);
+        sessions.restore(&[implementation_step(StepStatus::Failed, Some("s1"))]);
+        assert_eq!(sessions.begin_iteration(), None);
+    }
+
+    #[test]
+    fn review_feedback_skips_approvals() {
+        assert_eq!(review_feedback("Looks good.\nAPPROVED\n"), None);
+        assert_eq!(review_feedback("  "), None);
+        assert_eq!(
+            review_feedback("- handle the empty plan case\n").as_deref(),
+            Some("- handle the empty plan case")
+        );
+        let long = "x".repeat(MAX_REVIEW_FEEDBACK_CHARS + 10);
+        assert_eq!(
+            review_feedback(&long).unwrap().len(),
+            MAX_REVIEW_FEEDBACK_CHARS
+        );
+    }
+
+    #[test]
+    fn resume_prompt_includes_only_deltas() {
+        let prompt = build_resume_prompt("", None, None);
+        assert!(!prompt.contains("## Runner notes"));
+        assert!(!prompt.contains("## Review feedback"));
+
+        let prompt = build_resume_prompt(
+            "Runner detected failing verification.\n",
+            Some("Missing test for the error path."),
+            None,
+        );
+        assert!(prompt.contains("## Runner notes\n\nRunner detected failing verification."));
+        assert!(prompt.contains("Missing test for the error path."));
+
+        let prompt = build_resume_prompt("", None, Some("api"));
+        assert!(prompt.contains("keep working only on plan task `api`"));
+        assert!(!prompt.contains("next unchecked task"));
+    }
+}
@@ -171,7 +171,7 @@ fn find_keyword_matches<'a>(
             .name
             .split('-')
             .filter(|s| s.len() >= 2)
-            .map(|s| s.to_lowercase())
+            .map(str::to_lowercase)
             .collect();
 
         let desc_keywords = extract_keywords(&skill.description);
@@ -180,9 +180,7 @@ fn find_keyword_matches<'a>(
         let mut matches: Vec<String> = Vec::new();
 
         for kw in &task_keywords {
-            if name_keywords.contains(kw) {
-                matches.push(kw.clone());
-            } else if desc_keywords.contains(kw) {
+            if name_keywords.contains(kw) || desc_keywords.contains(kw) {
                 matches.push(kw.clone());
             }
         }
@@ -204,7 +202,7 @@ fn find_keyword_matches<'a>(
     }
 
     // Sort by score descending.
-    scored.sort_by(|a, b| b.1.cmp(&a.1));
+    scored.sort_by_key(|s| std::cmp::Reverse(s.1));
 
     // Take top matches up to limit.
     scored
@@ -223,7 +221,7 @@ fn find_keyword_matches<'a>(
 fn extract_keywords(text: &str) -> std::collections::HashSet<String> {
     text.split(|c: char| !c.is_alphanumeric())
         .filter(|word| word.len() >= 3)
-        .map(|word| word.to_lowercase())
+        .map(str::to_lowercase)
         .collect()
 }
 
@@ -183,7 +183,7 @@ pub fn load_skill_body(
     Ok(LoadedSkill {
         content: formatted,
         truncated,
-        original_size: if truncated { Some(original_size) } else { None },
+        original_size: truncated.then_some(original_size),
     })
 }
 
@@ -2,28 +2,79 @@
 //!
 //! Implements persistence for runs, steps, events, and artifacts.
 //! See spec Section 3.2 and Section 4.2.
+//!
+//! [`StorageBackend`] is everything the daemon persists, independent of the
+//! database; [`crate::storage_postgres::PgStorage`] implements it too.
 
 use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
 use loop_core::{
-    events::EventPayload, Artifact, ArtifactLocation, Config, Event, Id, MergeStrategy,
-    ReviewStatus, Run, RunNameSource, RunStatus, RunWorktree, Step, StepPhase, StepStatus,
-    WorktreeProvider,
+    audit::redact_transcript_input,
+    events::{EventPayload, RunStatusChangedPayload},
+    AgentAction, AgentActionKind, AgentActionStatus, Artifact, ArtifactLocation, Config,
+    DependencyCondition, Event, Id, Lease, MergeStrategy, MissedFirePolicy, OverlapPolicy,
+    PrunedRun, ReviewStatus, Run, RunDependency, RunNameSource, RunStatus, RunWorktree, Schedule,
+    Step, StepPhase, StepStatus, TokenUsage, TranscriptEntry, TranscriptEntryKind, Worker,
+    WorkerStatus, WorktreeProvider,
 };
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
+use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqlitePoolOptions, Pool, QueryBuilder, Sqlite};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 use thiserror::Error;
 
+use crate::bus::EventBus;
+use crate::storage_postgres::PgStorage;
+
 /// Default max concurrent runs for pool sizing (used in tests).
 pub const DEFAULT_MAX_CONCURRENT_RUNS: usize = 3;
 
 /// Explicit column list for runs table queries.
 /// Using explicit columns instead of SELECT * ensures correct mapping
 /// regardless of column order in the database (important for ALTER TABLE migrations).
-const RUNS_COLUMNS: &str = "id, name, name_source, status, workspace_root, spec_path, \
+pub(crate) const RUNS_COLUMNS: &str = "id, name, name_source, status, workspace_root, spec_path, \
     plan_path, base_branch, run_branch, merge_target_branch, merge_strategy, \
     worktree_path, config_json, created_at, updated_at, worktree_provider, \
     worktree_cleanup_status, worktree_cleaned_at, review_status, review_action_at, \
-    pr_url, merge_commit";
+    pr_url, merge_commit, priority";
+
+/// Idempotent schema migrations, applied in order on every start.
+///
+/// `0007_add_planning_phase.sql` is a table rebuild and is applied separately
+/// by [`Storage::migrate_embedded`].
+const MIGRATIONS: [&str; 15] = [
+    include_str!("../../../migrations/0001_init.sql"),
+    include_str!("../../../migrations/0002_add_worktree_provider.sql"),
+    include_str!("../../../migrations/0003_add_worktree_cleanup_state.sql"),
+    include_str!("../../../migrations/0004_add_review_fields.sql"),
+    include_str!("../../../migrations/0005_add_step_usage.sql"),
+    include_str!("../../../migrations/0006_add_notification_deliveries.sql"),
+    include_str!("../../../migrations/0008_add_run_children.sql"),
+    include_str!("../../../migrations/0009_add_transcript_entries.sql"),
+    include_str!("../../../migrations/0010_add_agent_actions.sql"),
+    include_str!("../../../migrations/0011_add_step_session.sql"),
+    include_str!("../../../migrations/0012_add_run_priority.sql"),
+    include_str!("../../../migrations/0013_add_schedules.sql"),
+    include_str!("../../../migrations/0014_add_run_dependencies.sql"),
+    include_str!("../../../migrations/0015_add_workers_and_leases.sql"),
+    include_str!("../../../migrations/0016_add_gc_log.sql"),
+];
+
+/// Split a migration file into statements, dropping comment lines first.
+pub(crate) fn migration_statements(migration_sql: &str) -> Vec<String> {
+    let cleaned: String = migration_sql
+        .lines()
+        .filter(|line| !line.trim().starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    cleaned
+        .split(';')
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .map(str::to_string)
+        .collect()
+}
 
 #[derive(Debug, Error)]
 pub enum StorageError {
@@ -39,19 +90,561 @@ pub enum StorageError {
     RunNotFound(String),
     #[error("step not found: {0}")]
     StepNotFound(String),
+    #[error("schedule not found: {0}")]
+    ScheduleNotFound(String),
+    #[error("worker not found: {0}")]
+    WorkerNotFound(String),
+    /// The step is not leased to the worker (expired, requeued or withdrawn).
+    #[error("no live lease on step {0} for this worker")]
+    StaleLease(String),
+    #[error("unsupported database url: {0}")]
+    UnsupportedBackend(String),
+    /// A run page cursor that is not a `UUIDv7` run ID.
+    #[error("invalid cursor: {0}")]
+    InvalidCursor(String),
 }
 
 pub type Result<T> = std::result::Result<T, StorageError>;
 
+/// Creation time, in Unix milliseconds, encoded in a `UUIDv7` run ID.
+pub(crate) fn cursor_created_ms(cursor: &Id) -> Result<i64> {
+    let invalid = || StorageError::InvalidCursor(cursor.to_string());
+    let uuid = uuid::Uuid::parse_str(cursor.as_ref()).map_err(|_| invalid())?;
+    if uuid.get_version_num() != 7 {
+        return Err(invalid());
+    }
+    let (secs, nanos) = uuid.get_timestamp().ok_or_else(invalid)?.to_unix();
+    let ms = secs * 1000 + u64::from(nanos / 1_000_000);
+    i64::try_from(ms).map_err(|_| invalid())
+}
+
+/// Sort order for run listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunSort {
+    /// Newest first.
+    #[default]
+    CreatedDesc,
+    /// Oldest first.
+    CreatedAsc,
+}
+
+/// Filters and paging for [`Storage::list_runs_page`].
+///
+/// Every filter is optional; only the ones set are added to the SQL so the
+/// `idx_runs_status`/`idx_runs_created` indexes stay usable.
+#[derive(Debug, Clone, Default)]
+pub struct RunFilter {
+    pub workspace_root: Option<String>,
+    pub status: Option<RunStatus>,
+    pub review_status: Option<ReviewStatus>,
+    /// Substring match on `spec_path` (case-sensitive).
+    pub spec_path_contains: Option<String>,
+    /// Inclusive lower bound on `created_at` (Unix epoch milliseconds).
+    pub created_after: Option<i64>,
+    /// Exclusive upper bound on `created_at` (Unix epoch milliseconds).
+    pub created_before: Option<i64>,
+    pub sort: RunSort,
+    /// Maximum runs to return; `None` returns every match.
+    pub limit: Option<u32>,
+    /// ID of the last run on the previous page.
+    pub cursor: Option<Id>,
+}
+
+/// One page of runs.
+#[derive(Debug, Clone)]
+pub struct RunPage {
+    pub runs: Vec<Run>,
+    /// Cursor for the next page, or `None` on the last page.
+    pub next_cursor: Option<Id>,
+}
+
+/// Grouping key for usage aggregation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGroupBy {
+    #[default]
+    Run,
+    Spec,
+    Workspace,
+}
+
+/// Aggregated token usage for one run, spec, or workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageAggregate {
+    /// Run ID, spec path, or workspace root depending on the grouping.
+    pub key: String,
+    /// Human-readable label (run name when grouped by run, otherwise the key).
+    pub label: String,
+    /// Number of distinct runs contributing to this group.
+    pub runs: u64,
+    /// Number of steps with recorded usage.
+    pub steps: u64,
+    pub usage: TokenUsage,
+}
+
+/// Filters for [`Storage::list_agent_actions`]; every filter is optional.
+#[derive(Debug, Clone, Default)]
+pub struct AgentActionFilter {
+    pub step_id: Option<Id>,
+    /// Exact tool name (e.g. `Bash`).
+    pub tool: Option<String>,
+    pub kind: Option<AgentActionKind>,
+    pub status: Option<AgentActionStatus>,
+    /// Substring match on the redacted arguments JSON (case-sensitive).
+    pub contains: Option<String>,
+    /// Maximum actions to return; `None` returns every match.
+    pub limit: Option<u32>,
+}
+
+/// Final outcome of a webhook notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    /// Retries were exhausted or the endpoint rejected the request.
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Link between a parent run and a child run fanned out for one plan task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunChild {
+    pub parent_run_id: Id,
+    pub child_run_id: Id,
+    pub task_id: String,
+    pub task_label: String,
+    pub depends_on: Vec<String>,
+    /// When the parent merged the child's branch, if it has.
+    pub merged_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One row of the notification delivery log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationDelivery {
+    pub id: Id,
+    pub run_id: Id,
+    /// Event that triggered the notification.
+    pub event_id: Id,
+    pub event_type: String,
+    /// Name of the webhook the notification was routed to.
+    pub webhook: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// HTTP status of the last attempt, if a response was received.
+    pub response_code: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Rows removed when a run is pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PrunedRows {
+    pub events: u64,
+    pub artifacts: u64,
+}
+
+/// Persistence for everything the daemon stores, independent of the database.
+///
+/// Implemented by the `SQLite` [`Storage`] and by
+/// [`PgStorage`]. Both publish every stored event, and
+/// a synthetic `RUN_STATUS_CHANGED` on status changes, to their [`EventBus`].
+pub trait StorageBackend: Send + Sync + std::fmt::Debug {
+    /// Broadcast bus that receives every event after it is persisted.
+    fn bus(&self) -> &EventBus;
+
+    /// Create or update the schema; safe to run on every start.
+    fn migrate_embedded(&self) -> BoxFuture<'_, Result<()>>;
+
+    // --- Runs ---
+
+    fn insert_run<'a>(&'a self, run: &'a Run) -> BoxFuture<'a, Result<()>>;
+
+    /// Fails with `RunNotFound` for an unknown ID.
+    fn get_run<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<Run>>;
+
+    /// Newest first, optionally filtered by workspace.
+    fn list_runs<'a>(&'a self, workspace_root: Option<&'a str>) -> BoxFuture<'a, Result<Vec<Run>>>;
+
+    /// Runs matching a filter, one page at a time (see [`Storage::list_runs_page`]).
+    fn list_runs_page<'a>(&'a self, filter: &'a RunFilter) -> BoxFuture<'a, Result<RunPage>>;
+
+    fn count_runs_with_status(&self, status: RunStatus) -> BoxFuture<'_, Result<usize>>;
+
+    /// Running runs per workspace, for every workspace that has any.
+    fn count_running_runs_by_workspace(&self) -> BoxFuture<'_, Result<HashMap<String, usize>>>;
+
+    fn count_running_runs_for_workspace<'a>(
+        &'a self,
+        workspace_root: &'a str,
+    ) -> BoxFuture<'a, Result<usize>>;
+
+    fn update_run_status<'a>(&'a self, id: &'a Id, status: RunStatus) -> BoxFuture<'a, Result<()>>;
+
+    fn update_run_worktree<'a>(
+        &'a self,
+        id: &'a Id,
+        worktree: &'a RunWorktree,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn update_run_plan_path<'a>(
+        &'a self,
+        id: &'a Id,
+        plan_path: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn update_run_worktree_cleanup<'a>(
+        &'a self,
+        id: &'a Id,
+        status: &'a str,
+        cleaned_at: Option<i64>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn update_run_priority<'a>(&'a self, id: &'a Id, priority: i32) -> BoxFuture<'a, Result<()>>;
+
+    fn update_run_config<'a>(
+        &'a self,
+        id: &'a Id,
+        config_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn update_review_status<'a>(
+        &'a self,
+        id: &'a Id,
+        status: ReviewStatus,
+        pr_url: Option<&'a str>,
+        merge_commit: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    // --- Steps ---
+
+    fn insert_step<'a>(&'a self, step: &'a Step) -> BoxFuture<'a, Result<()>>;
+
+    /// Fails with `StepNotFound` for an unknown ID.
+    fn get_step<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<Step>>;
+
+    /// In start order; steps not yet started come last.
+    fn list_steps<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Step>>>;
+
+    fn mark_step_started<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>>;
+
+    fn update_step<'a>(
+        &'a self,
+        id: &'a Id,
+        status: StepStatus,
+        exit_code: Option<i32>,
+        output_path: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn set_step_session<'a>(&'a self, id: &'a Id, session_id: &'a str)
+        -> BoxFuture<'a, Result<()>>;
+
+    // --- Events and artifacts ---
+
+    fn append_event<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: Option<&'a Id>,
+        payload: &'a EventPayload,
+    ) -> BoxFuture<'a, Result<Event>>;
+
+    /// Append a run event and set the run's status in one transaction.
+    fn complete_run_atomically<'a>(
+        &'a self,
+        run_id: &'a Id,
+        event: &'a EventPayload,
+        status: RunStatus,
+    ) -> BoxFuture<'a, Result<Event>>;
+
+    fn list_events<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Event>>>;
+
+    /// Events in insertion order after `after_id`; an unknown ID replays all.
+    fn list_events_after<'a>(
+        &'a self,
+        run_id: &'a Id,
+        after_id: Option<&'a Id>,
+    ) -> BoxFuture<'a, Result<Vec<Event>>>;
+
+    fn insert_artifact<'a>(&'a self, artifact: &'a Artifact) -> BoxFuture<'a, Result<()>>;
+
+    fn list_artifacts<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Artifact>>>;
+
+    fn delete_artifact<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>>;
+
+    /// Export a run's events to `report.tsv` format for `bin/loop-analyze`.
+    fn export_report<'a>(
+        &'a self,
+        run_id: &'a Id,
+        report_path: &'a Path,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let run = self.get_run(run_id).await?;
+            let events = self.list_events(run_id).await?;
+            let steps = self.list_steps(run_id).await?;
+            let usage = self.list_step_usage(run_id).await?;
+
+            let rows = events_to_report_rows(&run, &events, &steps, &usage);
+            loop_core::report::write_report(report_path, &rows)
+                .map_err(|e| StorageError::Io(e.to_string()))?;
+
+            Ok(())
+        })
+    }
+
+    // --- Usage ---
+
+    /// Add a step's token usage to what is already recorded for it.
+    fn record_step_usage<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: &'a Id,
+        model: Option<&'a str>,
+        usage: &'a TokenUsage,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn list_step_usage<'a>(
+        &'a self,
+        run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<(Id, TokenUsage)>>>;
+
+    fn get_run_usage<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<TokenUsage>>;
+
+    /// Total cost (USD) recorded at or after `since_ms` (Unix epoch milliseconds).
+    fn cost_since(&self, since_ms: i64) -> BoxFuture<'_, Result<f64>>;
+
+    fn aggregate_usage<'a>(
+        &'a self,
+        group_by: UsageGroupBy,
+        workspace_root: Option<&'a str>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> BoxFuture<'a, Result<Vec<UsageAggregate>>>;
+
+    // --- Transcripts and agent actions ---
+
+    fn append_transcript_entries<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: &'a Id,
+        entries: &'a [TranscriptEntry],
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn list_transcript_entries<'a>(
+        &'a self,
+        step_id: &'a Id,
+        tool_name: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<TranscriptEntry>>>;
+
+    fn record_agent_actions<'a>(&'a self, actions: &'a [AgentAction]) -> BoxFuture<'a, Result<()>>;
+
+    fn list_agent_actions<'a>(
+        &'a self,
+        run_id: &'a Id,
+        filter: &'a AgentActionFilter,
+    ) -> BoxFuture<'a, Result<Vec<AgentAction>>>;
+
+    // --- Dependencies and child runs ---
+
+    /// Insert a run and its dependencies in one transaction.
+    fn insert_run_with_dependencies<'a>(
+        &'a self,
+        run: &'a Run,
+        dependencies: &'a [RunDependency],
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn list_run_dependencies<'a>(
+        &'a self,
+        run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<RunDependency>>>;
+
+    /// Dependencies of every pending run, keyed by the dependent run.
+    fn list_pending_run_dependencies(
+        &self,
+    ) -> BoxFuture<'_, Result<HashMap<Id, Vec<RunDependency>>>>;
+
+    /// Insert a child run and its parent link in one transaction.
+    fn insert_child_run<'a>(
+        &'a self,
+        child: &'a Run,
+        link: &'a RunChild,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn get_run_child<'a>(&'a self, child_run_id: &'a Id)
+        -> BoxFuture<'a, Result<Option<RunChild>>>;
+
+    fn list_run_children<'a>(
+        &'a self,
+        parent_run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<RunChild>>>;
+
+    fn mark_run_child_merged<'a>(&'a self, child_run_id: &'a Id) -> BoxFuture<'a, Result<()>>;
+
+    // --- Notifications ---
+
+    fn record_notification_delivery<'a>(
+        &'a self,
+        delivery: &'a NotificationDelivery,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn list_notification_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+    ) -> BoxFuture<'_, Result<Vec<NotificationDelivery>>>;
+
+    // --- Schedules ---
+
+    fn insert_schedule<'a>(&'a self, schedule: &'a Schedule) -> BoxFuture<'a, Result<()>>;
+
+    fn list_schedules<'a>(
+        &'a self,
+        workspace_root: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<Schedule>>>;
+
+    fn list_due_schedules(&self, now: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Schedule>>>;
+
+    fn delete_schedule<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>>;
+
+    /// Insert the enqueued run (if any) and advance the schedule, atomically.
+    fn fire_schedule<'a>(
+        &'a self,
+        id: &'a Id,
+        run: Option<&'a Run>,
+        next_fire_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    // --- Workers and leases ---
+
+    fn insert_worker<'a>(&'a self, worker: &'a Worker) -> BoxFuture<'a, Result<()>>;
+
+    fn list_workers(&self) -> BoxFuture<'_, Result<Vec<Worker>>>;
+
+    /// Mark the worker online and extend its live leases; returns the steps it holds.
+    fn heartbeat_worker<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<Id>>>;
+
+    /// Mark the worker online without renewing its leases.
+    fn touch_worker<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn mark_workers_offline(&self, cutoff: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Id>>>;
+
+    fn offer_step<'a>(
+        &'a self,
+        step_id: &'a Id,
+        run_id: &'a Id,
+        request_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Grant the longest-waiting unclaimed step; no two workers get the same one.
+    fn claim_lease<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Option<(Lease, String)>>>;
+
+    /// Fails with `StaleLease` unless `worker_id` holds a live lease on the step.
+    fn complete_lease<'a>(
+        &'a self,
+        step_id: &'a Id,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+        result_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn held_lease_request<'a>(
+        &'a self,
+        step_id: &'a Id,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Option<String>>>;
+
+    fn lease_result<'a>(&'a self, step_id: &'a Id) -> BoxFuture<'a, Result<Option<String>>>;
+
+    fn release_lease<'a>(&'a self, step_id: &'a Id) -> BoxFuture<'a, Result<()>>;
+
+    fn clear_leases(&self) -> BoxFuture<'_, Result<u64>>;
+
+    /// Requeue expired leases; returns them as they were before requeueing.
+    fn expire_leases(&self, now: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Lease>>>;
+
+    // --- Retention ---
+
+    /// Finished runs not pruned yet, newest first.
+    fn list_gc_candidates(&self) -> BoxFuture<'_, Result<Vec<Run>>>;
+
+    /// Remove a run's logs and record it in the GC log; `dry_run` rolls back.
+    fn prune_run<'a>(
+        &'a self,
+        pruned: &'a PrunedRun,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> BoxFuture<'a, Result<PrunedRows>>;
+}
+
+/// The database a URL names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseUrl<'a> {
+    /// A `sqlite://` URL or a bare path.
+    Sqlite(&'a Path),
+    /// A `postgres://` or `postgresql://` URL.
+    Postgres(&'a str),
+}
+
+impl<'a> DatabaseUrl<'a> {
+    pub fn parse(database_url: &'a str) -> Result<Self> {
+        if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+            Ok(Self::Postgres(database_url))
+        } else if let Some(path) = database_url.strip_prefix("sqlite://") {
+            Ok(Self::Sqlite(Path::new(path)))
+        } else if database_url.contains("://") {
+            Err(StorageError::UnsupportedBackend(database_url.to_string()))
+        } else {
+            Ok(Self::Sqlite(Path::new(database_url)))
+        }
+    }
+}
+
+/// Open and migrate the backend a database URL names.
+pub async fn open_backend(
+    database_url: &str,
+    max_concurrent_runs: usize,
+) -> Result<Arc<dyn StorageBackend>> {
+    let backend: Arc<dyn StorageBackend> = match DatabaseUrl::parse(database_url)? {
+        DatabaseUrl::Sqlite(path) => Arc::new(Storage::new(path, max_concurrent_runs).await?),
+        DatabaseUrl::Postgres(url) => Arc::new(PgStorage::connect(url, max_concurrent_runs).await?),
+    };
+    backend.migrate_embedded().await?;
+    Ok(backend)
+}
+
 /// Storage backend for the daemon.
 pub struct Storage {
     pool: Pool<Sqlite>,
+    bus: EventBus,
 }
 
 impl std::fmt::Debug for Storage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Storage")
             .field("pool", &"Pool<Sqlite>")
+            .field("bus", &self.bus)
             .finish()
     }
 }
@@ -80,7 +673,15 @@ impl Storage {
             .execute(&pool)
             .await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            bus: EventBus::new(),
+        })
+    }
+
+    /// Broadcast bus that receives every event after it is persisted.
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
     }
 
     /// Run migrations to initialize/update the schema.
@@ -93,34 +694,34 @@ impl Storage {
     /// Run embedded migrations (for when migrations are compiled in).
     pub async fn migrate_embedded(&self) -> Result<()> {
         // Run all embedded migrations in order.
-        let migrations = [
-            include_str!("../../../migrations/0001_init.sql"),
-            include_str!("../../../migrations/0002_add_worktree_provider.sql"),
-            include_str!("../../../migrations/0003_add_worktree_cleanup_state.sql"),
-            include_str!("../../../migrations/0004_add_review_fields.sql"),
-        ];
-
-        for migration_sql in migrations {
-            // Remove comment lines before splitting.
-            let cleaned: String = migration_sql
-                .lines()
-                .filter(|line| !line.trim().starts_with("--"))
-                .collect::<Vec<_>>()
-                .join("\n");
-
-            for statement in cleaned.split(';') {
-                let trimmed = statement.trim();
-                if !trimmed.is_empty() {
-                    match sqlx::query(trimmed).execute(&self.pool).await {
-                        Ok(_) => {}
-                        Err(e) => {
-                            let msg = e.to_string();
-                            // Ignore expected idempotent errors (duplicate column, table exists).
-                            if !msg.contains("duplicate column") && !msg.contains("already exists")
-                            {
-                                return Err(e.into());
-                            }
-                        }
+        for migration_sql in MIGRATIONS {
+            self.apply_migration(migration_sql).await?;
+        }
+
+        // Table rebuilds are not idempotent, so each is guarded by a schema check.
+        if !self.steps_allow_phase(StepPhase::Planning).await? {
+            // The rebuilt table only has the columns from 0001, so later step
+            // columns are added back in the same transaction.
+            self.apply_table_rebuild(concat!(
+                include_str!("../../../migrations/0007_add_planning_phase.sql"),
+                ";\n",
+                include_str!("../../../migrations/0011_add_step_session.sql"),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Execute each statement of an idempotent migration.
+    async fn apply_migration(&self, migration_sql: &str) -> Result<()> {
+        for statement in migration_statements(migration_sql) {
+            match sqlx::query(&statement).execute(&self.pool).await {
+                Ok(_) => {}
+                Err(e) => {
+                    let msg = e.to_string();
+                    // Ignore expected idempotent errors (duplicate column, table exists).
+                    if !msg.contains("duplicate column") && !msg.contains("already exists") {
+                        return Err(e.into());
                     }
                 }
             }
@@ -128,10 +729,53 @@ impl Storage {
         Ok(())
     }
 
+    /// Execute a table rebuild migration in one transaction.
+    ///
+    /// Foreign keys are disabled on the connection for the duration so that
+    /// dropping the old table does not cascade into rows that reference it.
+    async fn apply_table_rebuild(&self, migration_sql: &str) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+
+        let result = async {
+            let mut tx = sqlx::Connection::begin(&mut *conn).await?;
+            for statement in migration_statements(migration_sql) {
+                sqlx::query(&statement).execute(&mut *tx).await?;
+            }
+            tx.commit().await
+        }
+        .await;
+
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await?;
+        result?;
+        Ok(())
+    }
+
+    /// Whether the steps table CHECK constraint accepts the given phase.
+    async fn steps_allow_phase(&self, phase: StepPhase) -> Result<bool> {
+        let sql: Option<String> = sqlx::query_scalar(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'steps'",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(sql.is_some_and(|sql| sql.contains(&format!("'{}'", phase.as_str()))))
+    }
+
     // --- Run operations ---
 
     /// Insert a new run.
     pub async fn insert_run(&self, run: &Run) -> Result<()> {
+        Self::insert_run_with(&self.pool, run).await
+    }
+
+    async fn insert_run_with<'e, E>(executor: E, run: &Run) -> Result<()>
+    where
+        E: sqlx::Executor<'e, Database = Sqlite>,
+    {
         let name_source = run.name_source.as_str();
         let status = run.status.as_str();
         let (
@@ -159,8 +803,9 @@ impl Storage {
             r"
             INSERT INTO runs (id, name, name_source, status, workspace_root, spec_path, plan_path,
                               base_branch, run_branch, merge_target_branch, merge_strategy,
-                              worktree_path, worktree_provider, config_json, created_at, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+                              worktree_path, worktree_provider, config_json, created_at, updated_at,
+                              priority)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
             ",
         )
         .bind(run.id.as_ref())
@@ -179,7 +824,8 @@ impl Storage {
         .bind(&run.config_json)
         .bind(created_at)
         .bind(updated_at)
-        .execute(&self.pool)
+        .bind(run.priority)
+        .execute(executor)
         .await?;
 
         Ok(())
@@ -217,6 +863,104 @@ impl Storage {
         Ok(rows.into_iter().map(RunRow::into_run).collect())
     }
 
+    /// List runs matching a filter, one page at a time.
+    ///
+    /// Pages are keyed on `(created_at, id)`; run IDs are `UUIDv7`, so the ID
+    /// breaks ties between runs created in the same millisecond. The cursor's
+    /// run need not exist any more: without its row, the time encoded in the
+    /// ID stands in for `created_at`. A cursor that is not a `UUIDv7` returns
+    /// `InvalidCursor`.
+    pub async fn list_runs_page(&self, filter: &RunFilter) -> Result<RunPage> {
+        let mut query =
+            QueryBuilder::<Sqlite>::new(format!("SELECT {RUNS_COLUMNS} FROM runs WHERE 1 = 1"));
+        if let Some(ws) = &filter.workspace_root {
+            query.push(" AND workspace_root = ").push_bind(ws);
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status.as_str());
+        }
+        if let Some(review_status) = filter.review_status {
+            query
+                .push(" AND review_status = ")
+                .push_bind(review_status.as_str());
+        }
+        if let Some(needle) = &filter.spec_path_contains {
+            query
+                .push(" AND instr(spec_path, ")
+                .push_bind(needle)
+                .push(") > 0");
+        }
+        if let Some(after) = filter.created_after {
+            query.push(" AND created_at >= ").push_bind(after);
+        }
+        if let Some(before) = filter.created_before {
+            query.push(" AND created_at < ").push_bind(before);
+        }
+
+        let (cmp, order) = match filter.sort {
+            RunSort::CreatedDesc => ("<", "DESC"),
+            RunSort::CreatedAsc => (">", "ASC"),
+        };
+        if let Some(cursor) = &filter.cursor {
+            query
+                .push(format_args!(
+                    " AND (created_at, id) {cmp} \
+                     (COALESCE((SELECT created_at FROM runs WHERE id = "
+                ))
+                .push_bind(cursor.as_ref())
+                .push("), ")
+                .push_bind(cursor_created_ms(cursor)?)
+                .push("), ")
+                .push_bind(cursor.as_ref())
+                .push(")");
+        }
+        query.push(format_args!(" ORDER BY created_at {order}, id {order}"));
+        if let Some(limit) = filter.limit {
+            // Fetch one extra row to learn whether another page exists.
+            query.push(" LIMIT ").push_bind(i64::from(limit) + 1);
+        }
+
+        let rows = query
+            .build_query_as::<RunRow>()
+            .fetch_all(&self.pool)
+            .await?;
+        let mut runs: Vec<Run> = rows.into_iter().map(RunRow::into_run).collect();
+
+        let next_cursor = match filter.limit {
+            Some(limit) if runs.len() > limit as usize => {
+                runs.truncate(limit as usize);
+                runs.last().map(|run| run.id.clone())
+            }
+            _ => None,
+        };
+
+        Ok(RunPage { runs, next_cursor })
+    }
+
+    /// Count runs in a given status across all workspaces.
+    pub async fn count_runs_with_status(&self, status: RunStatus) -> Result<usize> {
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM runs WHERE status = ?1")
+            .bind(status.as_str())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count.0 as usize)
+    }
+
+    /// Count running runs in every workspace that has any.
+    ///
+    /// Used for queue ordering and per-workspace caps in one query.
+    pub async fn count_running_runs_by_workspace(&self) -> Result<HashMap<String, usize>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT workspace_root, COUNT(*) FROM runs WHERE status = 'RUNNING' GROUP BY workspace_root",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(workspace, count)| (workspace, count as usize))
+            .collect())
+    }
+
     /// Count running runs for a specific workspace.
     ///
     /// Used for per-workspace cap enforcement (spec Section 4.2, 5.3).
@@ -231,21 +975,45 @@ impl Storage {
     }
 
     /// Update run status.
+    ///
+    /// Publishes a synthetic `RUN_STATUS_CHANGED` event when the status differs.
     pub async fn update_run_status(&self, id: &Id, status: RunStatus) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let previous = Self::run_status_and_workspace(&mut tx, id).await?;
+
         let now = Utc::now().timestamp_millis();
-        let result = sqlx::query("UPDATE runs SET status = ?1, updated_at = ?2 WHERE id = ?3")
+        sqlx::query("UPDATE runs SET status = ?1, updated_at = ?2 WHERE id = ?3")
             .bind(status.as_str())
             .bind(now)
             .bind(id.as_ref())
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
 
-        if result.rows_affected() == 0 {
-            return Err(StorageError::RunNotFound(id.to_string()));
-        }
+        self.publish_status_change(id, previous, status);
         Ok(())
     }
 
+    /// Read a run's current status and workspace root inside a transaction.
+    async fn run_status_and_workspace(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        id: &Id,
+    ) -> Result<(RunStatus, String)> {
+        let row: Option<(String, String)> =
+            sqlx::query_as("SELECT status, workspace_root FROM runs WHERE id = ?1")
+                .bind(id.as_ref())
+                .fetch_optional(&mut **tx)
+                .await?;
+        let (status, workspace_root) =
+            row.ok_or_else(|| StorageError::RunNotFound(id.to_string()))?;
+        Ok((run_status_from_db(&status), workspace_root))
+    }
+
+    /// Publish a synthetic `RUN_STATUS_CHANGED` event on the bus (not stored).
+    fn publish_status_change(&self, id: &Id, previous: (RunStatus, String), to: RunStatus) {
+        publish_status_change(&self.bus, id, previous, to);
+    }
+
     /// Update worktree fields for a run.
     pub async fn update_run_worktree(&self, id: &Id, worktree: &RunWorktree) -> Result<()> {
         let now = Utc::now().timestamp_millis();
@@ -272,6 +1040,23 @@ impl Storage {
         Ok(())
     }
 
+    /// Set the plan file for a run (used once the planning phase generates one).
+    pub async fn update_run_plan_path(&self, id: &Id, plan_path: &str) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let result = sqlx::query("UPDATE runs SET plan_path = ?1, updated_at = ?2 WHERE id = ?3")
+            .bind(plan_path)
+            .bind(now)
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
     /// Update worktree cleanup status for a run.
     pub async fn update_run_worktree_cleanup(
         &self,
@@ -298,6 +1083,33 @@ impl Storage {
         Ok(())
     }
 
+    /// Set a run's scheduling priority.
+    pub async fn update_run_priority(&self, id: &Id, priority: i32) -> Result<()> {
+        let result = sqlx::query("UPDATE runs SET priority = ?1, updated_at = ?2 WHERE id = ?3")
+            .bind(priority)
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Replace a run's resolved config (`config_json`).
+    pub async fn update_run_config(&self, id: &Id, config_json: &str) -> Result<()> {
+        sqlx::query("UPDATE runs SET config_json = ?1, updated_at = ?2 WHERE id = ?3")
+            .bind(config_json)
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     /// Update review status and related fields for a run.
     pub async fn update_review_status(
         &self,
@@ -339,8 +1151,8 @@ impl Storage {
         sqlx::query(
             r"
             INSERT INTO steps (id, run_id, phase, status, attempt, started_at, ended_at,
-                               exit_code, prompt_path, output_path)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                               exit_code, prompt_path, output_path, session_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
             ",
         )
         .bind(step.id.as_ref())
@@ -353,6 +1165,7 @@ impl Storage {
         .bind(step.exit_code)
         .bind(&step.prompt_path)
         .bind(&step.output_path)
+        .bind(&step.session_id)
         .execute(&self.pool)
         .await?;
 
@@ -370,10 +1183,10 @@ impl Storage {
         Ok(row.into_step())
     }
 
-    /// List steps for a run.
+    /// List steps for a run in start order; steps not yet started come last.
     pub async fn list_steps(&self, run_id: &Id) -> Result<Vec<Step>> {
         let rows = sqlx::query_as::<_, StepRow>(
-            "SELECT * FROM steps WHERE run_id = ?1 ORDER BY started_at ASC",
+            "SELECT * FROM steps WHERE run_id = ?1 ORDER BY started_at IS NULL, started_at ASC, rowid ASC",
         )
         .bind(run_id.as_ref())
         .fetch_all(&self.pool)
@@ -382,6 +1195,22 @@ impl Storage {
         Ok(rows.into_iter().map(StepRow::into_step).collect())
     }
 
+    /// Mark a step as in progress and stamp `started_at`.
+    pub async fn mark_step_started(&self, id: &Id) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let result = sqlx::query("UPDATE steps SET status = ?1, started_at = ?2 WHERE id = ?3")
+            .bind(StepStatus::InProgress.as_str())
+            .bind(now)
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StepNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
     /// Update step status and timing.
     pub async fn update_step(
         &self,
@@ -408,6 +1237,20 @@ impl Storage {
         Ok(())
     }
 
+    /// Record the agent session a step ran in.
+    pub async fn set_step_session(&self, id: &Id, session_id: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE steps SET session_id = ?1 WHERE id = ?2")
+            .bind(session_id)
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StepNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
     // --- Event operations ---
 
     /// Append an event to the audit log.
@@ -434,14 +1277,16 @@ impl Storage {
         .execute(&self.pool)
         .await?;
 
-        Ok(Event {
+        let event = Event {
             id,
             run_id: run_id.clone(),
             step_id: step_id.cloned(),
             event_type,
             timestamp: now,
             payload_json,
-        })
+        };
+        self.bus.publish_event(&event);
+        Ok(event)
     }
 
     /// Atomically append an event and update run status in a single transaction.
@@ -454,6 +1299,7 @@ impl Storage {
         status: RunStatus,
     ) -> Result<Event> {
         let mut tx = self.pool.begin().await?;
+        let previous = Self::run_status_and_workspace(&mut tx, run_id).await?;
         let now = Utc::now();
 
         // Insert event.
@@ -473,27 +1319,26 @@ impl Storage {
         .await?;
 
         // Update run status.
-        let result = sqlx::query("UPDATE runs SET status = ?1, updated_at = ?2 WHERE id = ?3")
+        sqlx::query("UPDATE runs SET status = ?1, updated_at = ?2 WHERE id = ?3")
             .bind(status.as_str())
             .bind(now.timestamp_millis())
             .bind(run_id.as_ref())
             .execute(&mut *tx)
             .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(StorageError::RunNotFound(run_id.to_string()));
-        }
-
         tx.commit().await?;
 
-        Ok(Event {
+        let event = Event {
             id: event_id,
             run_id: run_id.clone(),
             step_id: None,
             event_type,
             timestamp: now,
             payload_json,
-        })
+        };
+        self.bus.publish_event(&event);
+        self.publish_status_change(run_id, previous, status);
+        Ok(event)
     }
 
     /// List events for a run.
@@ -507,6 +1352,29 @@ impl Storage {
         Ok(rows.into_iter().map(EventRow::into_event).collect())
     }
 
+    /// List events for a run in insertion order, starting after `after_id`.
+    ///
+    /// Ordering by rowid keeps events that share a millisecond timestamp in
+    /// the order they were written, so SSE clients can resume by event id.
+    /// An unknown `after_id` replays the full history rather than dropping events.
+    pub async fn list_events_after(
+        &self,
+        run_id: &Id,
+        after_id: Option<&Id>,
+    ) -> Result<Vec<Event>> {
+        let rows = sqlx::query_as::<_, EventRow>(
+            "SELECT * FROM events WHERE run_id = ?1 \
+             AND rowid > COALESCE((SELECT rowid FROM events WHERE id = ?2 AND run_id = ?1), 0) \
+             ORDER BY rowid ASC",
+        )
+        .bind(run_id.as_ref())
+        .bind(after_id.map(std::convert::AsRef::as_ref))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(EventRow::into_event).collect())
+    }
+
     // --- Artifact operations ---
 
     /// Insert an artifact reference.
@@ -538,27 +1406,979 @@ impl Storage {
         Ok(rows.into_iter().map(ArtifactRow::into_artifact).collect())
     }
 
-    // --- Report TSV export (Section 7.1) ---
+    // --- Usage operations ---
 
-    /// Export events for a run to report.tsv format.
+    /// Record token usage for a step.
     ///
-    /// This generates a TSV file compatible with `bin/loop-analyze`.
-    pub async fn export_report(&self, run_id: &Id, report_path: &Path) -> Result<()> {
-        let run = self.get_run(run_id).await?;
-        let events = self.list_events(run_id).await?;
-        let steps = self.list_steps(run_id).await?;
-
-        let rows = events_to_report_rows(&run, &events, &steps);
-        loop_core::report::write_report(report_path, &rows)
-            .map_err(|e| StorageError::Io(e.to_string()))?;
+    /// Repeated calls for the same step accumulate, so retries of a step are
+    /// all accounted for.
+    pub async fn record_step_usage(
+        &self,
+        run_id: &Id,
+        step_id: &Id,
+        model: Option<&str>,
+        usage: &TokenUsage,
+    ) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        sqlx::query(
+            r"
+            INSERT INTO step_usage (step_id, run_id, model, input_tokens, output_tokens,
+                                    cache_creation_input_tokens, cache_read_input_tokens,
+                                    cost_usd, recorded_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(step_id) DO UPDATE SET
+                model = COALESCE(excluded.model, step_usage.model),
+                input_tokens = step_usage.input_tokens + excluded.input_tokens,
+                output_tokens = step_usage.output_tokens + excluded.output_tokens,
+                cache_creation_input_tokens =
+                    step_usage.cache_creation_input_tokens + excluded.cache_creation_input_tokens,
+                cache_read_input_tokens =
+                    step_usage.cache_read_input_tokens + excluded.cache_read_input_tokens,
+                cost_usd = step_usage.cost_usd + excluded.cost_usd,
+                recorded_at = excluded.recorded_at
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(run_id.as_ref())
+        .bind(model)
+        .bind(usage.input_tokens as i64)
+        .bind(usage.output_tokens as i64)
+        .bind(usage.cache_creation_input_tokens as i64)
+        .bind(usage.cache_read_input_tokens as i64)
+        .bind(usage.cost_usd)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
 
         Ok(())
     }
-}
 
-/// Convert events and steps to report TSV rows.
-fn events_to_report_rows(run: &Run, events: &[Event], steps: &[Step]) -> Vec<loop_core::ReportRow> {
-    use loop_core::ReportRow;
+    /// List per-step usage for a run, keyed by step ID.
+    pub async fn list_step_usage(&self, run_id: &Id) -> Result<Vec<(Id, TokenUsage)>> {
+        let rows = sqlx::query_as::<_, StepUsageRow>(
+            r"
+            SELECT step_id, input_tokens, output_tokens, cache_creation_input_tokens,
+                   cache_read_input_tokens, cost_usd
+            FROM step_usage WHERE run_id = ?1 ORDER BY recorded_at ASC
+            ",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (Id::from_string(row.step_id), row.usage.to_usage()))
+            .collect())
+    }
+
+    /// Total usage across all steps of a run.
+    pub async fn get_run_usage(&self, run_id: &Id) -> Result<TokenUsage> {
+        let row = sqlx::query_as::<_, UsageRow>(
+            r"
+            SELECT COALESCE(SUM(input_tokens), 0) AS input_tokens,
+                   COALESCE(SUM(output_tokens), 0) AS output_tokens,
+                   COALESCE(SUM(cache_creation_input_tokens), 0) AS cache_creation_input_tokens,
+                   COALESCE(SUM(cache_read_input_tokens), 0) AS cache_read_input_tokens,
+                   COALESCE(SUM(cost_usd), 0.0) AS cost_usd
+            FROM step_usage WHERE run_id = ?1
+            ",
+        )
+        .bind(run_id.as_ref())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.to_usage())
+    }
+
+    /// Total reported cost (USD) across all runs recorded at or after `since_ms`
+    /// (Unix epoch milliseconds).
+    pub async fn cost_since(&self, since_ms: i64) -> Result<f64> {
+        let cost: f64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(cost_usd), 0.0) FROM step_usage WHERE recorded_at >= ?1",
+        )
+        .bind(since_ms)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(cost)
+    }
+
+    /// Aggregate usage grouped by run, spec, or workspace.
+    ///
+    /// `since`/`until` bound the time the usage was recorded (Unix epoch
+    /// milliseconds, `until` exclusive). Results are ordered by cost, highest first.
+    pub async fn aggregate_usage(
+        &self,
+        group_by: UsageGroupBy,
+        workspace_root: Option<&str>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> Result<Vec<UsageAggregate>> {
+        let (key, label) = match group_by {
+            UsageGroupBy::Run => ("r.id", "MAX(r.name)"),
+            UsageGroupBy::Spec => ("r.spec_path", "r.spec_path"),
+            UsageGroupBy::Workspace => ("r.workspace_root", "r.workspace_root"),
+        };
+        let sql = format!(
+            r"
+            SELECT {key} AS key, {label} AS label,
+                   COUNT(DISTINCT u.run_id) AS runs,
+                   COUNT(*) AS steps,
+                   SUM(u.input_tokens) AS input_tokens,
+                   SUM(u.output_tokens) AS output_tokens,
+                   SUM(u.cache_creation_input_tokens) AS cache_creation_input_tokens,
+                   SUM(u.cache_read_input_tokens) AS cache_read_input_tokens,
+                   SUM(u.cost_usd) AS cost_usd
+            FROM step_usage u JOIN runs r ON r.id = u.run_id
+            WHERE (?1 IS NULL OR r.workspace_root = ?1)
+              AND (?2 IS NULL OR u.recorded_at >= ?2)
+              AND (?3 IS NULL OR u.recorded_at < ?3)
+            GROUP BY {key}
+            ORDER BY cost_usd DESC, key ASC
+            "
+        );
+
+        let rows = sqlx::query_as::<_, UsageAggregateRow>(&sql)
+            .bind(workspace_root)
+            .bind(since)
+            .bind(until)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(UsageAggregateRow::into_aggregate)
+            .collect())
+    }
+
+    // --- Step transcripts ---
+
+    /// Append transcript entries for a step.
+    ///
+    /// Entries are renumbered after any already stored for the step, so a step
+    /// recorded in several batches keeps one sequence. Tool inputs are stored
+    /// redacted ([`loop_core::audit::redact_transcript_input`]).
+    pub async fn append_transcript_entries(
+        &self,
+        run_id: &Id,
+        step_id: &Id,
+        entries: &[TranscriptEntry],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let (next_seq,): (i64,) = sqlx::query_as(
+            "SELECT COALESCE(MAX(seq) + 1, 0) FROM transcript_entries WHERE step_id = ?1",
+        )
+        .bind(step_id.as_ref())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for (index, entry) in entries.iter().enumerate() {
+            let input_json = entry
+                .input
+                .as_ref()
+                .map(|input| {
+                    let input = redact_transcript_input(entry.tool_name.as_deref(), input);
+                    serde_json::to_string(&input)
+                })
+                .transpose()?;
+            sqlx::query(
+                r"
+                INSERT INTO transcript_entries (step_id, run_id, seq, kind, text, tool_use_id,
+                    tool_name, input_json, is_error, at, duration_ms)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                ",
+            )
+            .bind(step_id.as_ref())
+            .bind(run_id.as_ref())
+            .bind(next_seq + index as i64)
+            .bind(entry.kind.as_str())
+            .bind(&entry.text)
+            .bind(&entry.tool_use_id)
+            .bind(&entry.tool_name)
+            .bind(input_json)
+            .bind(entry.is_error)
+            .bind(entry.at.timestamp_millis())
+            .bind(entry.duration_ms.map(|ms| ms as i64))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// List a step's transcript in order, optionally only entries for one tool.
+    pub async fn list_transcript_entries(
+        &self,
+        step_id: &Id,
+        tool_name: Option<&str>,
+    ) -> Result<Vec<TranscriptEntry>> {
+        let rows = sqlx::query_as::<_, TranscriptEntryRow>(
+            r"
+            SELECT * FROM transcript_entries
+            WHERE step_id = ?1 AND (?2 IS NULL OR tool_name = ?2)
+            ORDER BY seq
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(tool_name)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(TranscriptEntryRow::into_entry)
+            .collect())
+    }
+
+    // --- Agent action audit log ---
+
+    /// Record audited agent actions for a step.
+    pub async fn record_agent_actions(&self, actions: &[AgentAction]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for action in actions {
+            sqlx::query(
+                r"
+                INSERT INTO agent_actions (run_id, step_id, tool_use_id, tool, kind,
+                    arguments_json, status, at, duration_ms)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                ",
+            )
+            .bind(action.run_id.as_ref())
+            .bind(action.step_id.as_ref())
+            .bind(&action.tool_use_id)
+            .bind(&action.tool)
+            .bind(action.kind.as_str())
+            .bind(serde_json::to_string(&action.arguments)?)
+            .bind(action.status.as_str())
+            .bind(action.at.timestamp_millis())
+            .bind(action.duration_ms.map(|ms| ms as i64))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// List a run's audited actions in the order they were issued.
+    pub async fn list_agent_actions(
+        &self,
+        run_id: &Id,
+        filter: &AgentActionFilter,
+    ) -> Result<Vec<AgentAction>> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM agent_actions WHERE run_id = ");
+        query.push_bind(run_id.as_ref());
+        if let Some(step_id) = &filter.step_id {
+            query.push(" AND step_id = ").push_bind(step_id.as_ref());
+        }
+        if let Some(tool) = &filter.tool {
+            query.push(" AND tool = ").push_bind(tool);
+        }
+        if let Some(kind) = filter.kind {
+            query.push(" AND kind = ").push_bind(kind.as_str());
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status.as_str());
+        }
+        if let Some(needle) = &filter.contains {
+            query
+                .push(" AND instr(arguments_json, ")
+                .push_bind(needle)
+                .push(") > 0");
+        }
+        query.push(" ORDER BY at, id");
+        if let Some(limit) = filter.limit {
+            query.push(" LIMIT ").push_bind(i64::from(limit));
+        }
+
+        let rows = query
+            .build_query_as::<AgentActionRow>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(AgentActionRow::into_action).collect())
+    }
+
+    // --- Run dependencies ---
+
+    /// Insert a run and the runs it depends on in one transaction, so the
+    /// scheduler never claims it before its dependencies are recorded.
+    pub async fn insert_run_with_dependencies(
+        &self,
+        run: &Run,
+        dependencies: &[RunDependency],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert_run_with(&mut *tx, run).await?;
+        for dependency in dependencies {
+            sqlx::query(
+                r"
+                INSERT INTO run_dependencies (run_id, depends_on_run_id, condition, branch_from)
+                VALUES (?1, ?2, ?3, ?4)
+                ",
+            )
+            .bind(run.id.as_ref())
+            .bind(dependency.run_id.as_ref())
+            .bind(dependency.condition.as_str())
+            .bind(dependency.branch_from)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The runs a run depends on, in insertion order.
+    pub async fn list_run_dependencies(&self, run_id: &Id) -> Result<Vec<RunDependency>> {
+        let rows = sqlx::query_as::<_, RunDependencyRow>(
+            "SELECT * FROM run_dependencies WHERE run_id = ?1 ORDER BY rowid",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| row.into_dependency().1)
+            .collect())
+    }
+
+    /// Dependencies of every pending run, keyed by the dependent run.
+    pub async fn list_pending_run_dependencies(&self) -> Result<HashMap<Id, Vec<RunDependency>>> {
+        let rows = sqlx::query_as::<_, RunDependencyRow>(
+            r"
+            SELECT d.* FROM run_dependencies d
+            JOIN runs r ON r.id = d.run_id
+            WHERE r.status = 'PENDING'
+            ORDER BY d.rowid
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut dependencies: HashMap<Id, Vec<RunDependency>> = HashMap::new();
+        for row in rows {
+            let (run_id, dependency) = row.into_dependency();
+            dependencies.entry(run_id).or_default().push(dependency);
+        }
+        Ok(dependencies)
+    }
+
+    // --- Child runs ---
+
+    /// Insert a child run and its link to the parent in one transaction, so the
+    /// scheduler never claims a child that is not yet linked.
+    pub async fn insert_child_run(&self, child: &Run, link: &RunChild) -> Result<()> {
+        let depends_on_json = serde_json::to_string(&link.depends_on)?;
+        let mut tx = self.pool.begin().await?;
+        Self::insert_run_with(&mut *tx, child).await?;
+        sqlx::query(
+            r"
+            INSERT INTO run_children (child_run_id, parent_run_id, task_id, task_label,
+                depends_on_json, merged_at, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ",
+        )
+        .bind(link.child_run_id.as_ref())
+        .bind(link.parent_run_id.as_ref())
+        .bind(&link.task_id)
+        .bind(&link.task_label)
+        .bind(depends_on_json)
+        .bind(link.merged_at.map(|t| t.timestamp_millis()))
+        .bind(link.created_at.timestamp_millis())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the parent link for a run, if it is a child run.
+    pub async fn get_run_child(&self, child_run_id: &Id) -> Result<Option<RunChild>> {
+        let row =
+            sqlx::query_as::<_, RunChildRow>("SELECT * FROM run_children WHERE child_run_id = ?1")
+                .bind(child_run_id.as_ref())
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(RunChildRow::into_child))
+    }
+
+    /// List the child runs of a parent in creation order.
+    pub async fn list_run_children(&self, parent_run_id: &Id) -> Result<Vec<RunChild>> {
+        let rows = sqlx::query_as::<_, RunChildRow>(
+            "SELECT * FROM run_children WHERE parent_run_id = ?1 ORDER BY created_at, rowid",
+        )
+        .bind(parent_run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(RunChildRow::into_child).collect())
+    }
+
+    /// Record that the parent merged a child's branch.
+    pub async fn mark_run_child_merged(&self, child_run_id: &Id) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let result = sqlx::query("UPDATE run_children SET merged_at = ?1 WHERE child_run_id = ?2")
+            .bind(now)
+            .bind(child_run_id.as_ref())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(child_run_id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // --- Notification delivery log ---
+
+    /// Record the outcome of a webhook delivery.
+    pub async fn record_notification_delivery(
+        &self,
+        delivery: &NotificationDelivery,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO notification_deliveries (id, run_id, event_id, event_type, webhook,
+                status, attempts, response_code, error, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ",
+        )
+        .bind(delivery.id.as_ref())
+        .bind(delivery.run_id.as_ref())
+        .bind(delivery.event_id.as_ref())
+        .bind(&delivery.event_type)
+        .bind(&delivery.webhook)
+        .bind(delivery.status.as_str())
+        .bind(i64::from(delivery.attempts))
+        .bind(delivery.response_code.map(i64::from))
+        .bind(&delivery.error)
+        .bind(delivery.created_at.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// List recent deliveries, newest first, optionally only one status.
+    pub async fn list_notification_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let rows = sqlx::query_as::<_, NotificationDeliveryRow>(
+            r"
+            SELECT * FROM notification_deliveries
+            WHERE ?1 IS NULL OR status = ?1
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ?2
+            ",
+        )
+        .bind(status.map(DeliveryStatus::as_str))
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(NotificationDeliveryRow::into_delivery)
+            .collect())
+    }
+
+    // --- Schedules ---
+
+    /// Insert a new schedule.
+    pub async fn insert_schedule(&self, schedule: &Schedule) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO schedules (id, name, workspace_root, spec_path, plan_path,
+                config_override, cron, missed_fire_policy, overlap_policy, priority,
+                last_run_id, next_fire_at, last_fire_at, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+            ",
+        )
+        .bind(schedule.id.as_ref())
+        .bind(&schedule.name)
+        .bind(&schedule.workspace_root)
+        .bind(&schedule.spec_path)
+        .bind(&schedule.plan_path)
+        .bind(&schedule.config_override)
+        .bind(&schedule.cron)
+        .bind(schedule.missed_fire_policy.as_str())
+        .bind(schedule.overlap_policy.as_str())
+        .bind(schedule.priority)
+        .bind(schedule.last_run_id.as_ref().map(AsRef::<str>::as_ref))
+        .bind(schedule.next_fire_at.timestamp_millis())
+        .bind(schedule.last_fire_at.map(|t| t.timestamp_millis()))
+        .bind(schedule.created_at.timestamp_millis())
+        .bind(schedule.updated_at.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// List schedules, optionally filtered by workspace, oldest first.
+    pub async fn list_schedules(&self, workspace_root: Option<&str>) -> Result<Vec<Schedule>> {
+        let rows = sqlx::query_as::<_, ScheduleRow>(
+            r"
+            SELECT * FROM schedules
+            WHERE ?1 IS NULL OR workspace_root = ?1
+            ORDER BY created_at ASC, rowid ASC
+            ",
+        )
+        .bind(workspace_root)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(ScheduleRow::into_schedule).collect())
+    }
+
+    /// Schedules whose next fire time is at or before `now`, soonest first.
+    pub async fn list_due_schedules(&self, now: DateTime<Utc>) -> Result<Vec<Schedule>> {
+        let rows = sqlx::query_as::<_, ScheduleRow>(
+            "SELECT * FROM schedules WHERE next_fire_at <= ?1 ORDER BY next_fire_at ASC",
+        )
+        .bind(now.timestamp_millis())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(ScheduleRow::into_schedule).collect())
+    }
+
+    /// Delete a schedule. Runs it already enqueued are left alone.
+    pub async fn delete_schedule(&self, id: &Id) -> Result<()> {
+        let result = sqlx::query("DELETE FROM schedules WHERE id = ?1")
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::ScheduleNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Record a fire: insert the enqueued run (if any) and advance the
+    /// schedule to `next_fire_at`, atomically.
+    ///
+    /// Without a run (the fire was skipped) only `next_fire_at` moves.
+    pub async fn fire_schedule(
+        &self,
+        id: &Id,
+        run: Option<&Run>,
+        next_fire_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let result = if let Some(run) = run {
+            Self::insert_run_with(&mut *tx, run).await?;
+            sqlx::query(
+                r"
+                UPDATE schedules SET next_fire_at = ?1, last_fire_at = ?2, last_run_id = ?3,
+                    updated_at = ?2
+                WHERE id = ?4
+                ",
+            )
+            .bind(next_fire_at.timestamp_millis())
+            .bind(now)
+            .bind(run.id.as_ref())
+            .bind(id.as_ref())
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query("UPDATE schedules SET next_fire_at = ?1, updated_at = ?2 WHERE id = ?3")
+                .bind(next_fire_at.timestamp_millis())
+                .bind(now)
+                .bind(id.as_ref())
+                .execute(&mut *tx)
+                .await?
+        };
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::ScheduleNotFound(id.to_string()));
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // --- Workers and leases ---
+
+    /// Register a worker.
+    pub async fn insert_worker(&self, worker: &Worker) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO workers (id, hostname, status, capabilities_json, last_heartbeat, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ",
+        )
+        .bind(worker.id.as_ref())
+        .bind(&worker.hostname)
+        .bind(worker.status.as_str())
+        .bind(serde_json::to_string(&worker.capabilities)?)
+        .bind(worker.last_heartbeat.timestamp_millis())
+        .bind(worker.created_at.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// List registered workers, oldest first.
+    pub async fn list_workers(&self) -> Result<Vec<Worker>> {
+        let rows = sqlx::query_as::<_, WorkerRow>(
+            "SELECT * FROM workers ORDER BY created_at ASC, rowid ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(WorkerRow::into_worker).collect())
+    }
+
+    /// Record a heartbeat: mark the worker online and extend its live leases
+    /// to `expires_at`. Returns the steps it still holds.
+    pub async fn heartbeat_worker(
+        &self,
+        worker_id: &Id,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Vec<Id>> {
+        let mut tx = self.pool.begin().await?;
+        let result =
+            sqlx::query("UPDATE workers SET status = 'ONLINE', last_heartbeat = ?1 WHERE id = ?2")
+                .bind(now.timestamp_millis())
+                .bind(worker_id.as_ref())
+                .execute(&mut *tx)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::WorkerNotFound(worker_id.to_string()));
+        }
+
+        sqlx::query(
+            r"
+            UPDATE leases SET expires_at = ?1
+            WHERE worker_id = ?2 AND result_json IS NULL AND expires_at > ?3
+            ",
+        )
+        .bind(expires_at.timestamp_millis())
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .execute(&mut *tx)
+        .await?;
+        let held: Vec<String> = sqlx::query_scalar(
+            r"
+            SELECT step_id FROM leases
+            WHERE worker_id = ?1 AND result_json IS NULL AND expires_at > ?2
+            ORDER BY created_at ASC
+            ",
+        )
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(held.into_iter().map(Id::from_string).collect())
+    }
+
+    /// Mark a worker online without renewing its leases.
+    ///
+    /// Claims use this: a worker asking for a step has finished or abandoned
+    /// whatever it held, so those leases are left to expire.
+    pub async fn touch_worker(&self, worker_id: &Id, now: DateTime<Utc>) -> Result<()> {
+        let result =
+            sqlx::query("UPDATE workers SET status = 'ONLINE', last_heartbeat = ?1 WHERE id = ?2")
+                .bind(now.timestamp_millis())
+                .bind(worker_id.as_ref())
+                .execute(&self.pool)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::WorkerNotFound(worker_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Mark online workers whose last heartbeat is before `cutoff` offline.
+    /// Returns the workers marked.
+    pub async fn mark_workers_offline(&self, cutoff: DateTime<Utc>) -> Result<Vec<Id>> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            r"
+            UPDATE workers SET status = 'OFFLINE'
+            WHERE status = 'ONLINE' AND last_heartbeat < ?1
+            RETURNING id
+            ",
+        )
+        .bind(cutoff.timestamp_millis())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids.into_iter().map(Id::from_string).collect())
+    }
+
+    /// Offer a step to workers. `request_json` is handed to the claiming worker.
+    pub async fn offer_step(&self, step_id: &Id, run_id: &Id, request_json: &str) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO leases (step_id, run_id, request_json, created_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(run_id.as_ref())
+        .bind(request_json)
+        .bind(Utc::now().timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Grant the longest-waiting unclaimed step to `worker_id` until
+    /// `expires_at`. Returns the lease and the step's request.
+    pub async fn claim_lease(
+        &self,
+        worker_id: &Id,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<(Lease, String)>> {
+        loop {
+            let Some(row) = sqlx::query_as::<_, LeaseRow>(
+                r"
+                SELECT * FROM leases
+                WHERE worker_id IS NULL AND result_json IS NULL
+                ORDER BY created_at ASC, rowid ASC
+                LIMIT 1
+                ",
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            else {
+                return Ok(None);
+            };
+
+            // Conditional on the lease still being unclaimed, so two workers
+            // claiming at once cannot both get it.
+            let claimed = sqlx::query(
+                r"
+                UPDATE leases SET worker_id = ?1, expires_at = ?2, attempt = attempt + 1
+                WHERE step_id = ?3 AND worker_id IS NULL AND result_json IS NULL
+                ",
+            )
+            .bind(worker_id.as_ref())
+            .bind(expires_at.timestamp_millis())
+            .bind(&row.step_id)
+            .execute(&self.pool)
+            .await?;
+            if claimed.rows_affected() == 0 {
+                continue;
+            }
+
+            let request_json = row.request_json.clone();
+            let mut lease = row.into_lease();
+            lease.worker_id = Some(worker_id.clone());
+            lease.expires_at = Some(expires_at);
+            lease.attempt += 1;
+            return Ok(Some((lease, request_json)));
+        }
+    }
+
+    /// Record what a worker reported for a step it holds a live lease on.
+    ///
+    /// Fails with [`StorageError::StaleLease`] once the lease has expired,
+    /// been requeued to another worker, or been withdrawn.
+    pub async fn complete_lease(
+        &self,
+        step_id: &Id,
+        worker_id: &Id,
+        now: DateTime<Utc>,
+        result_json: &str,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            r"
+            UPDATE leases SET result_json = ?1
+            WHERE step_id = ?2 AND worker_id = ?3 AND result_json IS NULL AND expires_at > ?4
+            ",
+        )
+        .bind(result_json)
+        .bind(step_id.as_ref())
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StaleLease(step_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// The request for a step `worker_id` holds a live lease on at `now`.
+    pub async fn held_lease_request(
+        &self,
+        step_id: &Id,
+        worker_id: &Id,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>> {
+        let request_json = sqlx::query_scalar(
+            r"
+            SELECT request_json FROM leases
+            WHERE step_id = ?1 AND worker_id = ?2 AND result_json IS NULL AND expires_at > ?3
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(request_json)
+    }
+
+    /// The result a worker recorded for an offered step, once there is one.
+    pub async fn lease_result(&self, step_id: &Id) -> Result<Option<String>> {
+        let result: Option<Option<String>> =
+            sqlx::query_scalar("SELECT result_json FROM leases WHERE step_id = ?1")
+                .bind(step_id.as_ref())
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(result.flatten())
+    }
+
+    /// Withdraw a step from workers, claimed or not.
+    pub async fn release_lease(&self, step_id: &Id) -> Result<()> {
+        sqlx::query("DELETE FROM leases WHERE step_id = ?1")
+            .bind(step_id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Withdraw every offered step. Returns how many there were.
+    pub async fn clear_leases(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM leases")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Requeue leases that expired at or before `now` so another worker can
+    /// claim the step. Returns the leases as they were before requeueing.
+    pub async fn expire_leases(&self, now: DateTime<Utc>) -> Result<Vec<Lease>> {
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query_as::<_, LeaseRow>(
+            r"
+            SELECT * FROM leases
+            WHERE worker_id IS NOT NULL AND result_json IS NULL AND expires_at <= ?1
+            ",
+        )
+        .bind(now.timestamp_millis())
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query(
+            r"
+            UPDATE leases SET worker_id = NULL, expires_at = NULL
+            WHERE worker_id IS NOT NULL AND result_json IS NULL AND expires_at <= ?1
+            ",
+        )
+        .bind(now.timestamp_millis())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(rows.into_iter().map(LeaseRow::into_lease).collect())
+    }
+
+    // --- Retention ---
+
+    /// Finished runs the garbage collector has not pruned yet, newest first.
+    pub async fn list_gc_candidates(&self) -> Result<Vec<Run>> {
+        let query = format!(
+            r"
+            SELECT {RUNS_COLUMNS} FROM runs
+            WHERE status IN ('COMPLETED', 'FAILED', 'CANCELED')
+                AND id NOT IN (SELECT run_id FROM gc_log)
+            ORDER BY created_at DESC, rowid DESC
+            "
+        );
+        let rows = sqlx::query_as::<_, RunRow>(&query)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(RunRow::into_run).collect())
+    }
+
+    /// Remove a run's artifact rows, transcripts, audited actions and
+    /// step-level events, clear its step file paths and record it in the GC
+    /// log. With `dry_run` the transaction is rolled back, so the counts are
+    /// exactly what a real prune would remove.
+    pub async fn prune_run(
+        &self,
+        pruned: &PrunedRun,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<PrunedRows> {
+        let run_id = pruned.run_id.as_ref();
+        let mut tx = self.pool.begin().await?;
+        let events = sqlx::query("DELETE FROM events WHERE run_id = ?1 AND step_id IS NOT NULL")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let artifacts = sqlx::query("DELETE FROM artifacts WHERE run_id = ?1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM transcript_entries WHERE run_id = ?1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM agent_actions WHERE run_id = ?1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE steps SET prompt_path = NULL, output_path = NULL WHERE run_id = ?1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r"
+            INSERT INTO gc_log (run_id, reason, events_removed, artifacts_removed, bytes_freed, pruned_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(run_id) DO NOTHING
+            ",
+        )
+        .bind(run_id)
+        .bind(pruned.reason.as_str())
+        .bind(events as i64)
+        .bind(artifacts as i64)
+        .bind(pruned.bytes as i64)
+        .bind(now.timestamp_millis())
+        .execute(&mut *tx)
+        .await?;
+
+        if dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+        Ok(PrunedRows { events, artifacts })
+    }
+
+    /// Delete one artifact row, e.g. after its file disappeared from disk.
+    pub async fn delete_artifact(&self, id: &Id) -> Result<()> {
+        sqlx::query("DELETE FROM artifacts WHERE id = ?1")
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Convert events and steps to report TSV rows.
+///
+/// Token usage goes into the message field (`tokens_in=... cost_usd=...`) so the
+/// column layout stays compatible with `bin/loop-analyze`.
+fn events_to_report_rows(
+    run: &Run,
+    events: &[Event],
+    steps: &[Step],
+    usage: &[(Id, TokenUsage)],
+) -> Vec<loop_core::ReportRow> {
+    use loop_core::ReportRow;
 
     let mut rows = Vec::new();
     let run_config = parse_run_config(run);
@@ -566,6 +2386,12 @@ fn events_to_report_rows(run: &Run, events: &[Event], steps: &[Step]) -> Vec<loo
     // Build a map of step_id -> step for quick lookup.
     let step_map: std::collections::HashMap<&str, &Step> =
         steps.iter().map(|s| (s.id.as_ref(), s)).collect();
+    let usage_map: std::collections::HashMap<&str, &TokenUsage> =
+        usage.iter().map(|(id, u)| (id.as_ref(), u)).collect();
+    let mut run_usage = TokenUsage::default();
+    for step_usage in usage_map.values() {
+        run_usage.add(step_usage);
+    }
 
     for event in events {
         let ts = event.timestamp.timestamp_millis();
@@ -618,6 +2444,9 @@ fn events_to_report_rows(run: &Run, events: &[Event], steps: &[Step]) -> Vec<loo
                                 row = row.with_output(meta.len(), count_lines(path));
                             }
                         }
+                        if let Some(step_usage) = usage_map.get(step_id.as_ref()) {
+                            row = row.with_message(format_usage(step_usage));
+                        }
 
                         rows.push(row);
                     }
@@ -628,13 +2457,17 @@ fn events_to_report_rows(run: &Run, events: &[Event], steps: &[Step]) -> Vec<loo
                 let mode = extract_completion_mode(&event.payload_json);
                 let message = format!("mode={mode}");
                 rows.push(ReportRow::new(ts, "COMPLETE_DETECTED").with_message(message));
-                rows.push(ReportRow::new(ts, "RUN_END").with_message("reason=complete"));
+                rows.push(
+                    ReportRow::new(ts, "RUN_END")
+                        .with_message(with_run_usage("reason=complete".to_string(), &run_usage)),
+                );
             }
             "RUN_FAILED" => {
                 let reason = extract_failure_reason(&event.payload_json);
-                rows.push(
-                    ReportRow::new(ts, "RUN_END").with_message(format!("reason=failed:{reason}")),
-                );
+                rows.push(ReportRow::new(ts, "RUN_END").with_message(with_run_usage(
+                    format!("reason=failed:{reason}"),
+                    &run_usage,
+                )));
             }
             "WATCHDOG_REWRITE" => {
                 if let Some(step_id) = &event.step_id {
@@ -658,6 +2491,27 @@ fn events_to_report_rows(run: &Run, events: &[Event], steps: &[Step]) -> Vec<loo
     rows
 }
 
+/// Format token usage as `key=value` pairs for the report message field.
+fn format_usage(usage: &TokenUsage) -> String {
+    format!(
+        "tokens_in={} tokens_out={} cache_write={} cache_read={} cost_usd={:.4}",
+        usage.input_tokens,
+        usage.output_tokens,
+        usage.cache_creation_input_tokens,
+        usage.cache_read_input_tokens,
+        usage.cost_usd,
+    )
+}
+
+/// Append run usage totals to a `RUN_END` message when any usage was recorded.
+fn with_run_usage(message: String, usage: &TokenUsage) -> String {
+    if usage.is_empty() {
+        message
+    } else {
+        format!("{message} {}", format_usage(usage))
+    }
+}
+
 /// Format iteration label from step (e.g., "1", "1R1", "2").
 fn format_iteration_label(step: &Step) -> String {
     match step.phase {
@@ -698,26 +2552,510 @@ fn extract_completion_mode(payload: &str) -> String {
         .unwrap_or_else(|| "trailing".to_string())
 }
 
-/// Extract failure reason from `RUN_FAILED` payload.
-fn extract_failure_reason(payload: &str) -> String {
-    serde_json::from_str::<serde_json::Value>(payload)
-        .ok()
-        .and_then(|v| v.get("reason").and_then(|r| r.as_str()).map(String::from))
-        .unwrap_or_else(|| "unknown".to_string())
+/// Extract failure reason from `RUN_FAILED` payload.
+fn extract_failure_reason(payload: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|v| v.get("reason").and_then(|r| r.as_str()).map(String::from))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Extract watchdog signal from `WATCHDOG_REWRITE` payload.
+fn extract_watchdog_signal(payload: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|v| v.get("signal").and_then(|s| s.as_str()).map(String::from))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl StorageBackend for Storage {
+    fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    fn migrate_embedded(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(Storage::migrate_embedded(self))
+    }
+
+    fn insert_run<'a>(&'a self, run: &'a Run) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_run(self, run))
+    }
+
+    fn get_run<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<Run>> {
+        Box::pin(Storage::get_run(self, id))
+    }
+
+    fn list_runs<'a>(&'a self, workspace_root: Option<&'a str>) -> BoxFuture<'a, Result<Vec<Run>>> {
+        Box::pin(Storage::list_runs(self, workspace_root))
+    }
+
+    fn list_runs_page<'a>(&'a self, filter: &'a RunFilter) -> BoxFuture<'a, Result<RunPage>> {
+        Box::pin(Storage::list_runs_page(self, filter))
+    }
+
+    fn count_runs_with_status(&self, status: RunStatus) -> BoxFuture<'_, Result<usize>> {
+        Box::pin(Storage::count_runs_with_status(self, status))
+    }
+
+    fn count_running_runs_by_workspace(&self) -> BoxFuture<'_, Result<HashMap<String, usize>>> {
+        Box::pin(Storage::count_running_runs_by_workspace(self))
+    }
+
+    fn count_running_runs_for_workspace<'a>(
+        &'a self,
+        workspace_root: &'a str,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(Storage::count_running_runs_for_workspace(
+            self,
+            workspace_root,
+        ))
+    }
+
+    fn update_run_status<'a>(&'a self, id: &'a Id, status: RunStatus) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_run_status(self, id, status))
+    }
+
+    fn update_run_worktree<'a>(
+        &'a self,
+        id: &'a Id,
+        worktree: &'a RunWorktree,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_run_worktree(self, id, worktree))
+    }
+
+    fn update_run_plan_path<'a>(
+        &'a self,
+        id: &'a Id,
+        plan_path: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_run_plan_path(self, id, plan_path))
+    }
+
+    fn update_run_worktree_cleanup<'a>(
+        &'a self,
+        id: &'a Id,
+        status: &'a str,
+        cleaned_at: Option<i64>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_run_worktree_cleanup(
+            self, id, status, cleaned_at,
+        ))
+    }
+
+    fn update_run_priority<'a>(&'a self, id: &'a Id, priority: i32) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_run_priority(self, id, priority))
+    }
+
+    fn update_run_config<'a>(
+        &'a self,
+        id: &'a Id,
+        config_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_run_config(self, id, config_json))
+    }
+
+    fn update_review_status<'a>(
+        &'a self,
+        id: &'a Id,
+        status: ReviewStatus,
+        pr_url: Option<&'a str>,
+        merge_commit: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_review_status(
+            self,
+            id,
+            status,
+            pr_url,
+            merge_commit,
+        ))
+    }
+
+    fn insert_step<'a>(&'a self, step: &'a Step) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_step(self, step))
+    }
+
+    fn get_step<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<Step>> {
+        Box::pin(Storage::get_step(self, id))
+    }
+
+    fn list_steps<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Step>>> {
+        Box::pin(Storage::list_steps(self, run_id))
+    }
+
+    fn mark_step_started<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::mark_step_started(self, id))
+    }
+
+    fn update_step<'a>(
+        &'a self,
+        id: &'a Id,
+        status: StepStatus,
+        exit_code: Option<i32>,
+        output_path: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::update_step(
+            self,
+            id,
+            status,
+            exit_code,
+            output_path,
+        ))
+    }
+
+    fn set_step_session<'a>(
+        &'a self,
+        id: &'a Id,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::set_step_session(self, id, session_id))
+    }
+
+    fn append_event<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: Option<&'a Id>,
+        payload: &'a EventPayload,
+    ) -> BoxFuture<'a, Result<Event>> {
+        Box::pin(Storage::append_event(self, run_id, step_id, payload))
+    }
+
+    fn complete_run_atomically<'a>(
+        &'a self,
+        run_id: &'a Id,
+        event: &'a EventPayload,
+        status: RunStatus,
+    ) -> BoxFuture<'a, Result<Event>> {
+        Box::pin(Storage::complete_run_atomically(
+            self, run_id, event, status,
+        ))
+    }
+
+    fn list_events<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Event>>> {
+        Box::pin(Storage::list_events(self, run_id))
+    }
+
+    fn list_events_after<'a>(
+        &'a self,
+        run_id: &'a Id,
+        after_id: Option<&'a Id>,
+    ) -> BoxFuture<'a, Result<Vec<Event>>> {
+        Box::pin(Storage::list_events_after(self, run_id, after_id))
+    }
+
+    fn insert_artifact<'a>(&'a self, artifact: &'a Artifact) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_artifact(self, artifact))
+    }
+
+    fn list_artifacts<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Artifact>>> {
+        Box::pin(Storage::list_artifacts(self, run_id))
+    }
+
+    fn delete_artifact<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::delete_artifact(self, id))
+    }
+
+    fn record_step_usage<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: &'a Id,
+        model: Option<&'a str>,
+        usage: &'a TokenUsage,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::record_step_usage(
+            self, run_id, step_id, model, usage,
+        ))
+    }
+
+    fn list_step_usage<'a>(
+        &'a self,
+        run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<(Id, TokenUsage)>>> {
+        Box::pin(Storage::list_step_usage(self, run_id))
+    }
+
+    fn get_run_usage<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<TokenUsage>> {
+        Box::pin(Storage::get_run_usage(self, run_id))
+    }
+
+    fn cost_since(&self, since_ms: i64) -> BoxFuture<'_, Result<f64>> {
+        Box::pin(Storage::cost_since(self, since_ms))
+    }
+
+    fn aggregate_usage<'a>(
+        &'a self,
+        group_by: UsageGroupBy,
+        workspace_root: Option<&'a str>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> BoxFuture<'a, Result<Vec<UsageAggregate>>> {
+        Box::pin(Storage::aggregate_usage(
+            self,
+            group_by,
+            workspace_root,
+            since,
+            until,
+        ))
+    }
+
+    fn append_transcript_entries<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: &'a Id,
+        entries: &'a [TranscriptEntry],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::append_transcript_entries(
+            self, run_id, step_id, entries,
+        ))
+    }
+
+    fn list_transcript_entries<'a>(
+        &'a self,
+        step_id: &'a Id,
+        tool_name: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<TranscriptEntry>>> {
+        Box::pin(Storage::list_transcript_entries(self, step_id, tool_name))
+    }
+
+    fn record_agent_actions<'a>(&'a self, actions: &'a [AgentAction]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::record_agent_actions(self, actions))
+    }
+
+    fn list_agent_actions<'a>(
+        &'a self,
+        run_id: &'a Id,
+        filter: &'a AgentActionFilter,
+    ) -> BoxFuture<'a, Result<Vec<AgentAction>>> {
+        Box::pin(Storage::list_agent_actions(self, run_id, filter))
+    }
+
+    fn insert_run_with_dependencies<'a>(
+        &'a self,
+        run: &'a Run,
+        dependencies: &'a [RunDependency],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_run_with_dependencies(
+            self,
+            run,
+            dependencies,
+        ))
+    }
+
+    fn list_run_dependencies<'a>(
+        &'a self,
+        run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<RunDependency>>> {
+        Box::pin(Storage::list_run_dependencies(self, run_id))
+    }
+
+    fn list_pending_run_dependencies(
+        &self,
+    ) -> BoxFuture<'_, Result<HashMap<Id, Vec<RunDependency>>>> {
+        Box::pin(Storage::list_pending_run_dependencies(self))
+    }
+
+    fn insert_child_run<'a>(
+        &'a self,
+        child: &'a Run,
+        link: &'a RunChild,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_child_run(self, child, link))
+    }
+
+    fn get_run_child<'a>(
+        &'a self,
+        child_run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Option<RunChild>>> {
+        Box::pin(Storage::get_run_child(self, child_run_id))
+    }
+
+    fn list_run_children<'a>(
+        &'a self,
+        parent_run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<RunChild>>> {
+        Box::pin(Storage::list_run_children(self, parent_run_id))
+    }
+
+    fn mark_run_child_merged<'a>(&'a self, child_run_id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::mark_run_child_merged(self, child_run_id))
+    }
+
+    fn record_notification_delivery<'a>(
+        &'a self,
+        delivery: &'a NotificationDelivery,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::record_notification_delivery(self, delivery))
+    }
+
+    fn list_notification_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+    ) -> BoxFuture<'_, Result<Vec<NotificationDelivery>>> {
+        Box::pin(Storage::list_notification_deliveries(self, status, limit))
+    }
+
+    fn insert_schedule<'a>(&'a self, schedule: &'a Schedule) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_schedule(self, schedule))
+    }
+
+    fn list_schedules<'a>(
+        &'a self,
+        workspace_root: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<Schedule>>> {
+        Box::pin(Storage::list_schedules(self, workspace_root))
+    }
+
+    fn list_due_schedules(&self, now: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Schedule>>> {
+        Box::pin(Storage::list_due_schedules(self, now))
+    }
+
+    fn delete_schedule<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::delete_schedule(self, id))
+    }
+
+    fn fire_schedule<'a>(
+        &'a self,
+        id: &'a Id,
+        run: Option<&'a Run>,
+        next_fire_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::fire_schedule(self, id, run, next_fire_at))
+    }
+
+    fn insert_worker<'a>(&'a self, worker: &'a Worker) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::insert_worker(self, worker))
+    }
+
+    fn list_workers(&self) -> BoxFuture<'_, Result<Vec<Worker>>> {
+        Box::pin(Storage::list_workers(self))
+    }
+
+    fn heartbeat_worker<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<Id>>> {
+        Box::pin(Storage::heartbeat_worker(self, worker_id, now, expires_at))
+    }
+
+    fn touch_worker<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::touch_worker(self, worker_id, now))
+    }
+
+    fn mark_workers_offline(&self, cutoff: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Id>>> {
+        Box::pin(Storage::mark_workers_offline(self, cutoff))
+    }
+
+    fn offer_step<'a>(
+        &'a self,
+        step_id: &'a Id,
+        run_id: &'a Id,
+        request_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::offer_step(self, step_id, run_id, request_json))
+    }
+
+    fn claim_lease<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Option<(Lease, String)>>> {
+        Box::pin(Storage::claim_lease(self, worker_id, expires_at))
+    }
+
+    fn complete_lease<'a>(
+        &'a self,
+        step_id: &'a Id,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+        result_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::complete_lease(
+            self,
+            step_id,
+            worker_id,
+            now,
+            result_json,
+        ))
+    }
+
+    fn held_lease_request<'a>(
+        &'a self,
+        step_id: &'a Id,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(Storage::held_lease_request(self, step_id, worker_id, now))
+    }
+
+    fn lease_result<'a>(&'a self, step_id: &'a Id) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(Storage::lease_result(self, step_id))
+    }
+
+    fn release_lease<'a>(&'a self, step_id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(Storage::release_lease(self, step_id))
+    }
+
+    fn clear_leases(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(Storage::clear_leases(self))
+    }
+
+    fn expire_leases(&self, now: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Lease>>> {
+        Box::pin(Storage::expire_leases(self, now))
+    }
+
+    fn list_gc_candidates(&self) -> BoxFuture<'_, Result<Vec<Run>>> {
+        Box::pin(Storage::list_gc_candidates(self))
+    }
+
+    fn prune_run<'a>(
+        &'a self,
+        pruned: &'a PrunedRun,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> BoxFuture<'a, Result<PrunedRows>> {
+        Box::pin(Storage::prune_run(self, pruned, now, dry_run))
+    }
 }
 
-/// Extract watchdog signal from `WATCHDOG_REWRITE` payload.
-fn extract_watchdog_signal(payload: &str) -> String {
-    serde_json::from_str::<serde_json::Value>(payload)
-        .ok()
-        .and_then(|v| v.get("signal").and_then(|s| s.as_str()).map(String::from))
-        .unwrap_or_else(|| "unknown".to_string())
+/// Publish a synthetic `RUN_STATUS_CHANGED` event on `bus` (not stored).
+pub(crate) fn publish_status_change(
+    bus: &EventBus,
+    id: &Id,
+    (from, workspace_root): (RunStatus, String),
+    to: RunStatus,
+) {
+    if from == to {
+        return;
+    }
+    let payload = EventPayload::RunStatusChanged(RunStatusChangedPayload {
+        run_id: id.clone(),
+        workspace_root,
+        from,
+        to,
+    });
+    let Ok(payload_json) = payload.to_json() else {
+        return;
+    };
+    bus.publish_event(&Event {
+        id: Id::new(),
+        run_id: id.clone(),
+        step_id: None,
+        event_type: payload.event_type().as_str().to_string(),
+        timestamp: Utc::now(),
+        payload_json,
+    });
 }
 
 // --- Row types for SQLx ---
 
 #[derive(sqlx::FromRow)]
-struct RunRow {
+pub(crate) struct RunRow {
     id: String,
     name: String,
     name_source: String,
@@ -743,23 +3081,30 @@ struct RunRow {
     review_action_at: Option<i64>,
     pr_url: Option<String>,
     merge_commit: Option<String>,
+    // Scheduling priority (migration 0012)
+    priority: i64,
+}
+
+/// Parse a stored run status; unknown values read as failed.
+pub(crate) fn run_status_from_db(status: &str) -> RunStatus {
+    match status {
+        "PENDING" => RunStatus::Pending,
+        "RUNNING" => RunStatus::Running,
+        "PAUSED" => RunStatus::Paused,
+        "COMPLETED" => RunStatus::Completed,
+        "FAILED" => RunStatus::Failed,
+        "CANCELED" => RunStatus::Canceled,
+        _ => RunStatus::Failed,
+    }
 }
 
 impl RunRow {
-    fn into_run(self) -> Run {
+    pub(crate) fn into_run(self) -> Run {
         let name_source = match self.name_source.as_str() {
             "spec_slug" => RunNameSource::SpecSlug,
             _ => RunNameSource::Haiku,
         };
-        let status = match self.status.as_str() {
-            "PENDING" => RunStatus::Pending,
-            "RUNNING" => RunStatus::Running,
-            "PAUSED" => RunStatus::Paused,
-            "COMPLETED" => RunStatus::Completed,
-            "FAILED" => RunStatus::Failed,
-            "CANCELED" => RunStatus::Canceled,
-            _ => RunStatus::Failed,
-        };
+        let status = run_status_from_db(&self.status);
         let worktree = match (self.base_branch, self.run_branch, self.worktree_path) {
             (Some(base), Some(run_br), Some(wt_path)) => Some(RunWorktree {
                 base_branch: base,
@@ -810,12 +3155,13 @@ impl RunRow {
                 .and_then(DateTime::from_timestamp_millis),
             pr_url: self.pr_url,
             merge_commit: self.merge_commit,
+            priority: i32::try_from(self.priority).unwrap_or_default(),
         }
     }
 }
 
 #[derive(sqlx::FromRow)]
-struct StepRow {
+pub(crate) struct StepRow {
     id: String,
     run_id: String,
     phase: String,
@@ -826,11 +3172,13 @@ struct StepRow {
     exit_code: Option<i32>,
     prompt_path: Option<String>,
     output_path: Option<String>,
+    session_id: Option<String>,
 }
 
 impl StepRow {
-    fn into_step(self) -> Step {
+    pub(crate) fn into_step(self) -> Step {
         let phase = match self.phase.as_str() {
+            "planning" => StepPhase::Planning,
             "implementation" => StepPhase::Implementation,
             "review" => StepPhase::Review,
             "verification" => StepPhase::Verification,
@@ -859,12 +3207,13 @@ impl StepRow {
             exit_code: self.exit_code,
             prompt_path: self.prompt_path,
             output_path: self.output_path,
+            session_id: self.session_id,
         }
     }
 }
 
 #[derive(sqlx::FromRow)]
-struct EventRow {
+pub(crate) struct EventRow {
     id: String,
     run_id: String,
     step_id: Option<String>,
@@ -875,7 +3224,7 @@ struct EventRow {
 }
 
 impl EventRow {
-    fn into_event(self) -> Event {
+    pub(crate) fn into_event(self) -> Event {
         Event {
             id: Id::from_string(self.id),
             run_id: Id::from_string(self.run_id),
@@ -888,7 +3237,7 @@ impl EventRow {
 }
 
 #[derive(sqlx::FromRow)]
-struct ArtifactRow {
+pub(crate) struct ArtifactRow {
     id: String,
     run_id: String,
     kind: String,
@@ -898,7 +3247,7 @@ struct ArtifactRow {
 }
 
 impl ArtifactRow {
-    fn into_artifact(self) -> Artifact {
+    pub(crate) fn into_artifact(self) -> Artifact {
         let location = match self.location.as_str() {
             "workspace" => ArtifactLocation::Workspace,
             "global" => ArtifactLocation::Global,
@@ -916,26 +3265,476 @@ impl ArtifactRow {
     }
 }
 
+#[derive(sqlx::FromRow)]
+pub(crate) struct UsageRow {
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_creation_input_tokens: i64,
+    cache_read_input_tokens: i64,
+    cost_usd: f64,
+}
+
+impl UsageRow {
+    pub(crate) fn to_usage(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens as u64,
+            output_tokens: self.output_tokens as u64,
+            cache_creation_input_tokens: self.cache_creation_input_tokens as u64,
+            cache_read_input_tokens: self.cache_read_input_tokens as u64,
+            cost_usd: self.cost_usd,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct StepUsageRow {
+    pub(crate) step_id: String,
+    #[sqlx(flatten)]
+    pub(crate) usage: UsageRow,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct UsageAggregateRow {
+    key: String,
+    label: String,
+    runs: i64,
+    steps: i64,
+    #[sqlx(flatten)]
+    usage: UsageRow,
+}
+
+impl UsageAggregateRow {
+    pub(crate) fn into_aggregate(self) -> UsageAggregate {
+        UsageAggregate {
+            usage: self.usage.to_usage(),
+            key: self.key,
+            label: self.label,
+            runs: self.runs as u64,
+            steps: self.steps as u64,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct NotificationDeliveryRow {
+    id: String,
+    run_id: String,
+    event_id: String,
+    event_type: String,
+    webhook: String,
+    status: String,
+    attempts: i64,
+    response_code: Option<i64>,
+    error: Option<String>,
+    created_at: i64,
+}
+
+impl NotificationDeliveryRow {
+    pub(crate) fn into_delivery(self) -> NotificationDelivery {
+        NotificationDelivery {
+            id: Id::from_string(self.id),
+            run_id: Id::from_string(self.run_id),
+            event_id: Id::from_string(self.event_id),
+            event_type: self.event_type,
+            webhook: self.webhook,
+            status: if self.status == "delivered" {
+                DeliveryStatus::Delivered
+            } else {
+                DeliveryStatus::Failed
+            },
+            attempts: self.attempts as u32,
+            response_code: self.response_code.map(|code| code as u16),
+            error: self.error,
+            created_at: DateTime::from_timestamp_millis(self.created_at).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct ScheduleRow {
+    id: String,
+    name: String,
+    workspace_root: String,
+    spec_path: String,
+    plan_path: Option<String>,
+    config_override: Option<String>,
+    cron: String,
+    missed_fire_policy: String,
+    overlap_policy: String,
+    priority: i64,
+    last_run_id: Option<String>,
+    next_fire_at: i64,
+    last_fire_at: Option<i64>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl ScheduleRow {
+    pub(crate) fn into_schedule(self) -> Schedule {
+        Schedule {
+            id: Id::from_string(self.id),
+            name: self.name,
+            workspace_root: self.workspace_root,
+            spec_path: self.spec_path,
+            plan_path: self.plan_path,
+            config_override: self.config_override,
+            cron: self.cron,
+            missed_fire_policy: match self.missed_fire_policy.as_str() {
+                "catch_up" => MissedFirePolicy::CatchUp,
+                _ => MissedFirePolicy::Skip,
+            },
+            overlap_policy: match self.overlap_policy.as_str() {
+                "allow" => OverlapPolicy::Allow,
+                _ => OverlapPolicy::Skip,
+            },
+            priority: self.priority as i32,
+            last_run_id: self.last_run_id.map(Id::from_string),
+            next_fire_at: DateTime::from_timestamp_millis(self.next_fire_at).unwrap_or_default(),
+            last_fire_at: self.last_fire_at.and_then(DateTime::from_timestamp_millis),
+            created_at: DateTime::from_timestamp_millis(self.created_at).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp_millis(self.updated_at).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct WorkerRow {
+    id: String,
+    hostname: String,
+    status: String,
+    capabilities_json: String,
+    last_heartbeat: i64,
+    created_at: i64,
+}
+
+impl WorkerRow {
+    pub(crate) fn into_worker(self) -> Worker {
+        Worker {
+            id: Id::from_string(self.id),
+            hostname: self.hostname,
+            status: match self.status.as_str() {
+                "OFFLINE" => WorkerStatus::Offline,
+                _ => WorkerStatus::Online,
+            },
+            capabilities: serde_json::from_str(&self.capabilities_json).unwrap_or_default(),
+            last_heartbeat: DateTime::from_timestamp_millis(self.last_heartbeat)
+                .unwrap_or_default(),
+            created_at: DateTime::from_timestamp_millis(self.created_at).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct LeaseRow {
+    step_id: String,
+    run_id: String,
+    worker_id: Option<String>,
+    expires_at: Option<i64>,
+    attempt: i64,
+    pub(crate) request_json: String,
+}
+
+impl LeaseRow {
+    pub(crate) fn into_lease(self) -> Lease {
+        Lease {
+            step_id: Id::from_string(self.step_id),
+            run_id: Id::from_string(self.run_id),
+            worker_id: self.worker_id.map(Id::from_string),
+            expires_at: self.expires_at.and_then(DateTime::from_timestamp_millis),
+            attempt: self.attempt as u32,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct RunDependencyRow {
+    run_id: String,
+    depends_on_run_id: String,
+    condition: String,
+    branch_from: bool,
+}
+
+impl RunDependencyRow {
+    /// The dependent run's ID and the dependency.
+    pub(crate) fn into_dependency(self) -> (Id, RunDependency) {
+        let condition = match self.condition.as_str() {
+            "merged" => DependencyCondition::Merged,
+            "succeeded_verification" => DependencyCondition::SucceededVerification,
+            _ => DependencyCondition::Completed,
+        };
+        (
+            Id::from_string(self.run_id),
+            RunDependency {
+                run_id: Id::from_string(self.depends_on_run_id),
+                condition,
+                branch_from: self.branch_from,
+            },
+        )
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct RunChildRow {
+    child_run_id: String,
+    parent_run_id: String,
+    task_id: String,
+    task_label: String,
+    depends_on_json: String,
+    merged_at: Option<i64>,
+    created_at: i64,
+}
+
+impl RunChildRow {
+    pub(crate) fn into_child(self) -> RunChild {
+        RunChild {
+            parent_run_id: Id::from_string(self.parent_run_id),
+            child_run_id: Id::from_string(self.child_run_id),
+            task_id: self.task_id,
+            task_label: self.task_label,
+            depends_on: serde_json::from_str(&self.depends_on_json).unwrap_or_default(),
+            merged_at: self.merged_at.and_then(DateTime::from_timestamp_millis),
+            created_at: DateTime::from_timestamp_millis(self.created_at).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct TranscriptEntryRow {
+    seq: i64,
+    kind: String,
+    text: Option<String>,
+    tool_use_id: Option<String>,
+    tool_name: Option<String>,
+    input_json: Option<String>,
+    is_error: bool,
+    at: i64,
+    duration_ms: Option<i64>,
+}
+
+impl TranscriptEntryRow {
+    pub(crate) fn into_entry(self) -> TranscriptEntry {
+        TranscriptEntry {
+            seq: self.seq as u32,
+            // The CHECK constraint limits kinds to those TranscriptEntryKind knows.
+            kind: TranscriptEntryKind::parse(&self.kind)
+                .unwrap_or(TranscriptEntryKind::AssistantMessage),
+            text: self.text,
+            tool_use_id: self.tool_use_id,
+            tool_name: self.tool_name,
+            input: self
+                .input_json
+                .and_then(|json| serde_json::from_str(&json).ok()),
+            is_error: self.is_error,
+            at: DateTime::from_timestamp_millis(self.at).unwrap_or_default(),
+            duration_ms: self.duration_ms.map(|ms| ms as u64),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct AgentActionRow {
+    run_id: String,
+    step_id: String,
+    tool_use_id: Option<String>,
+    tool: String,
+    kind: String,
+    arguments_json: String,
+    status: String,
+    at: i64,
+    duration_ms: Option<i64>,
+}
+
+impl AgentActionRow {
+    pub(crate) fn into_action(self) -> AgentAction {
+        AgentAction {
+            run_id: Id::from_string(self.run_id),
+            step_id: Id::from_string(self.step_id),
+            tool_use_id: self.tool_use_id,
+            tool: self.tool,
+            // The CHECK constraints limit kinds and statuses to known values.
+            kind: AgentActionKind::parse(&self.kind).unwrap_or(AgentActionKind::Command),
+            arguments: serde_json::from_str(&self.arguments_json).unwrap_or_default(),
+            status: AgentActionStatus::parse(&self.status).unwrap_or(AgentActionStatus::NoResult),
+            at: DateTime::from_timestamp_millis(self.at).unwrap_or_default(),
+            duration_ms: self.duration_ms.map(|ms| ms as u64),
+        }
+    }
+}
+
+/// Fixtures for running one test body against every storage backend.
 #[cfg(test)]
-mod tests {
-    use super::*;
-    use loop_core::events::RunCreatedPayload;
+pub(crate) mod testing {
+    use super::{open_backend, Storage, StorageBackend, DEFAULT_MAX_CONCURRENT_RUNS};
+    use crate::storage_postgres::PgStorage;
+    use loop_core::Id;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Arc;
+    use std::time::Duration;
     use tempfile::TempDir;
 
-    struct TestStorage {
-        storage: Storage,
-        _dir: TempDir, // Keep alive to prevent cleanup
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) enum Backend {
+        Sqlite,
+        Postgres,
+    }
+
+    /// A freshly migrated backend, plus whatever keeps it alive.
+    pub(crate) struct TestBackend {
+        pub(crate) storage: Arc<dyn StorageBackend>,
+        pub(crate) dir: TempDir,
+        _postgres: Option<TestPostgres>,
     }
 
-    async fn create_test_storage() -> TestStorage {
+    /// Open an empty backend. `None` means Postgres was asked for and
+    /// [`TestPostgres`] cannot provide a server here.
+    pub(crate) async fn open(backend: Backend) -> Option<TestBackend> {
         let dir = TempDir::new().unwrap();
-        let db_path = dir.path().join("test.db");
-        let storage = Storage::new(&db_path, DEFAULT_MAX_CONCURRENT_RUNS)
-            .await
-            .unwrap();
-        storage.migrate_embedded().await.unwrap();
-        TestStorage { storage, _dir: dir }
+        match backend {
+            Backend::Sqlite => {
+                let storage =
+                    Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+                        .await
+                        .unwrap();
+                storage.migrate_embedded().await.unwrap();
+                Some(TestBackend {
+                    storage: Arc::new(storage),
+                    dir,
+                    _postgres: None,
+                })
+            }
+            Backend::Postgres => {
+                let postgres = TestPostgres::start().await?;
+                let storage = open_backend(&postgres.url, DEFAULT_MAX_CONCURRENT_RUNS)
+                    .await
+                    .unwrap();
+                Some(TestBackend {
+                    storage,
+                    dir,
+                    _postgres: Some(postgres),
+                })
+            }
+        }
+    }
+
+    /// A scratch Postgres database for one test.
+    ///
+    /// With `LOOPD_TEST_POSTGRES_URL` set, a new database is created on that
+    /// server (and left behind). Otherwise a throwaway cluster is started
+    /// from the local `initdb`/`postgres` binaries and stopped on drop.
+    pub(crate) struct TestPostgres {
+        pub(crate) url: String,
+        server: Option<Child>,
+        _dir: Option<TempDir>,
+    }
+
+    impl TestPostgres {
+        pub(crate) async fn start() -> Option<Self> {
+            if let Ok(admin_url) = std::env::var("LOOPD_TEST_POSTGRES_URL") {
+                return Some(Self::create_database(&admin_url).await);
+            }
+
+            let dir = TempDir::new().unwrap();
+            let data = dir.path().join("data");
+            let initdb = Command::new("initdb")
+                .arg("-D")
+                .arg(&data)
+                .args(["-A", "trust", "-U", "loopd", "--no-sync"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            if !initdb.is_ok_and(|status| status.success()) {
+                eprintln!("skipping postgres backend: initdb unavailable or failed");
+                return None;
+            }
+
+            let port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            let server = Command::new("postgres")
+                .arg("-D")
+                .arg(&data)
+                .arg("-k")
+                .arg(dir.path())
+                .args(["-p", &port.to_string()])
+                .args(["-c", "listen_addresses=127.0.0.1", "-c", "fsync=off"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let postgres = Self {
+                url: format!("postgres://loopd@127.0.0.1:{port}/postgres"),
+                server: Some(server),
+                _dir: Some(dir),
+            };
+            for _ in 0..100 {
+                if PgStorage::connect(&postgres.url, 1).await.is_ok() {
+                    return Some(postgres);
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("postgres did not accept connections");
+        }
+
+        async fn create_database(admin_url: &str) -> Self {
+            let name = format!("loopd_test_{}", Id::new().as_ref().replace('-', ""));
+            let admin = sqlx::PgPool::connect(admin_url).await.unwrap();
+            sqlx::query(&format!("CREATE DATABASE {name}"))
+                .execute(&admin)
+                .await
+                .unwrap();
+            admin.close().await;
+            let mut url = reqwest::Url::parse(admin_url).unwrap();
+            url.set_path(&name);
+            Self {
+                url: url.to_string(),
+                server: None,
+                _dir: None,
+            }
+        }
+    }
+
+    impl Drop for TestPostgres {
+        fn drop(&mut self) {
+            if let Some(server) = &mut self.server {
+                let _ = server.kill();
+                let _ = server.wait();
+            }
+        }
+    }
+
+    /// Declare `sqlite` and `postgres` tests for the async fn `$test`, each
+    /// passing it the fixture `$setup(Backend)` builds. The Postgres variant
+    /// passes without running when no server is available.
+    macro_rules! backend_test {
+        ($test:ident, $setup:ident) => {
+            mod $test {
+                use $crate::storage::testing::Backend;
+
+                #[tokio::test]
+                async fn sqlite() {
+                    super::$test(super::$setup(Backend::Sqlite).await.unwrap()).await;
+                }
+
+                #[tokio::test]
+                async fn postgres() {
+                    if let Some(fixture) = super::$setup(Backend::Postgres).await {
+                        super::$test(fixture).await;
+                    }
+                }
+            }
+        };
     }
+    pub(crate) use backend_test;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{backend_test, open, TestBackend};
+    use super::*;
+    use loop_core::events::RunCreatedPayload;
+    use tempfile::TempDir;
 
     fn create_test_run() -> Run {
         let now = Utc::now();
@@ -952,45 +3751,327 @@ mod tests {
             worktree_cleaned_at: None,
             config_json: None,
             created_at: now,
-            updated_at: now,
-            review_status: ReviewStatus::default(),
-            review_action_at: None,
-            pr_url: None,
-            merge_commit: None,
+            updated_at: now,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        }
+    }
+
+    backend_test!(insert_and_get_run, open);
+    async fn insert_and_get_run(ts: TestBackend) {
+        let run = create_test_run();
+
+        ts.storage.insert_run(&run).await.unwrap();
+        let retrieved = ts.storage.get_run(&run.id).await.unwrap();
+
+        assert_eq!(retrieved.id, run.id);
+        assert_eq!(retrieved.name, run.name);
+        assert_eq!(retrieved.status, RunStatus::Pending);
+    }
+
+    backend_test!(update_run_status, open);
+    async fn update_run_status(ts: TestBackend) {
+        let run = create_test_run();
+
+        ts.storage.insert_run(&run).await.unwrap();
+        ts.storage
+            .update_run_status(&run.id, RunStatus::Running)
+            .await
+            .unwrap();
+
+        let retrieved = ts.storage.get_run(&run.id).await.unwrap();
+        assert_eq!(retrieved.status, RunStatus::Running);
+    }
+
+    backend_test!(run_priority_round_trips_and_updates, open);
+    async fn run_priority_round_trips_and_updates(ts: TestBackend) {
+        let run = Run {
+            priority: 3,
+            ..create_test_run()
+        };
+        ts.storage.insert_run(&run).await.unwrap();
+        assert_eq!(ts.storage.get_run(&run.id).await.unwrap().priority, 3);
+
+        ts.storage.update_run_priority(&run.id, -2).await.unwrap();
+        assert_eq!(ts.storage.get_run(&run.id).await.unwrap().priority, -2);
+
+        let missing = ts.storage.update_run_priority(&Id::new(), 1).await;
+        assert!(matches!(missing, Err(StorageError::RunNotFound(_))));
+    }
+
+    backend_test!(schedules_round_trip_fire_and_delete, open);
+    async fn schedules_round_trip_fire_and_delete(ts: TestBackend) {
+        let now = DateTime::from_timestamp_millis(Utc::now().timestamp_millis()).unwrap();
+        let schedule = Schedule {
+            id: Id::new(),
+            name: "nightly".to_string(),
+            workspace_root: "/workspace".to_string(),
+            spec_path: "/workspace/spec.md".to_string(),
+            plan_path: None,
+            config_override: Some(r#"{"model":"x"}"#.to_string()),
+            cron: "0 2 * * *".to_string(),
+            missed_fire_policy: MissedFirePolicy::CatchUp,
+            overlap_policy: OverlapPolicy::Allow,
+            priority: 2,
+            next_fire_at: now,
+            last_fire_at: None,
+            last_run_id: None,
+            created_at: now,
+            updated_at: now,
+        };
+        ts.storage.insert_schedule(&schedule).await.unwrap();
+
+        assert_eq!(
+            ts.storage.list_schedules(None).await.unwrap(),
+            vec![schedule.clone()]
+        );
+        assert!(ts
+            .storage
+            .list_schedules(Some("/other"))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(ts.storage.list_due_schedules(now).await.unwrap().len(), 1);
+
+        let next = now + chrono::Duration::days(1);
+        let run = create_test_run();
+        ts.storage
+            .fire_schedule(&schedule.id, Some(&run), next)
+            .await
+            .unwrap();
+        let fired = &ts.storage.list_schedules(None).await.unwrap()[0];
+        assert_eq!(fired.next_fire_at, next);
+        assert_eq!(fired.last_run_id.as_ref(), Some(&run.id));
+        assert!(fired.last_fire_at.is_some());
+        assert!(ts.storage.get_run(&run.id).await.is_ok());
+        assert!(ts.storage.list_due_schedules(now).await.unwrap().is_empty());
+
+        ts.storage
+            .fire_schedule(&schedule.id, None, next + chrono::Duration::days(1))
+            .await
+            .unwrap();
+        let skipped = &ts.storage.list_schedules(None).await.unwrap()[0];
+        assert_eq!(skipped.last_run_id.as_ref(), Some(&run.id));
+
+        ts.storage.delete_schedule(&schedule.id).await.unwrap();
+        assert!(ts.storage.list_schedules(None).await.unwrap().is_empty());
+        assert!(matches!(
+            ts.storage.delete_schedule(&schedule.id).await,
+            Err(StorageError::ScheduleNotFound(_))
+        ));
+    }
+
+    backend_test!(run_dependencies_round_trip_and_list_for_pending_runs, open);
+    async fn run_dependencies_round_trip_and_list_for_pending_runs(ts: TestBackend) {
+        let first = create_test_run();
+        ts.storage.insert_run(&first).await.unwrap();
+        let dependencies = vec![RunDependency {
+            run_id: first.id.clone(),
+            condition: DependencyCondition::Merged,
+            branch_from: true,
+        }];
+        let second = create_test_run();
+        ts.storage
+            .insert_run_with_dependencies(&second, &dependencies)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ts.storage.list_run_dependencies(&second.id).await.unwrap(),
+            dependencies
+        );
+        assert!(ts
+            .storage
+            .list_run_dependencies(&first.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let pending = ts.storage.list_pending_run_dependencies().await.unwrap();
+        assert_eq!(pending.get(&second.id), Some(&dependencies));
+
+        ts.storage
+            .update_run_status(&second.id, RunStatus::Running)
+            .await
+            .unwrap();
+        assert!(ts
+            .storage
+            .list_pending_run_dependencies()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    fn test_worker(hostname: &str) -> Worker {
+        let now = Utc::now();
+        Worker {
+            id: Id::new(),
+            hostname: hostname.to_string(),
+            status: WorkerStatus::Online,
+            capabilities: vec!["shared_fs".to_string()],
+            last_heartbeat: now,
+            created_at: now,
         }
     }
 
-    #[tokio::test]
-    async fn insert_and_get_run() {
-        let ts = create_test_storage().await;
+    backend_test!(leases_are_claimed_once_renewed_and_requeued_on_expiry, open);
+    async fn leases_are_claimed_once_renewed_and_requeued_on_expiry(ts: TestBackend) {
         let run = create_test_run();
-
         ts.storage.insert_run(&run).await.unwrap();
-        let retrieved = ts.storage.get_run(&run.id).await.unwrap();
+        let a = test_worker("box-a");
+        let b = test_worker("box-b");
+        ts.storage.insert_worker(&a).await.unwrap();
+        ts.storage.insert_worker(&b).await.unwrap();
+        let workers = ts.storage.list_workers().await.unwrap();
+        let ids: Vec<&Id> = workers.iter().map(|w| &w.id).collect();
+        assert_eq!(ids, vec![&a.id, &b.id]);
+        assert_eq!(workers[0].capabilities, vec!["shared_fs".to_string()]);
+
+        let step_id = Id::new();
+        ts.storage
+            .offer_step(&step_id, &run.id, r#"{"step":"x"}"#)
+            .await
+            .unwrap();
 
-        assert_eq!(retrieved.id, run.id);
-        assert_eq!(retrieved.name, run.name);
-        assert_eq!(retrieved.status, RunStatus::Pending);
+        let now = Utc::now();
+        let ttl = chrono::Duration::seconds(30);
+        let (lease, request) = ts
+            .storage
+            .claim_lease(&a.id, now + ttl)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lease.step_id, step_id);
+        assert_eq!(lease.worker_id.as_ref(), Some(&a.id));
+        assert_eq!(lease.attempt, 1);
+        assert_eq!(request, r#"{"step":"x"}"#);
+        assert!(ts
+            .storage
+            .claim_lease(&b.id, now + ttl)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(
+            ts.storage
+                .held_lease_request(&step_id, &a.id, now)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"step":"x"}"#)
+        );
+        assert!(ts
+            .storage
+            .held_lease_request(&step_id, &b.id, now)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Claiming marks a worker online but does not keep its leases alive.
+        ts.storage.touch_worker(&a.id, now).await.unwrap();
+        assert!(ts
+            .storage
+            .held_lease_request(&step_id, &a.id, now + ttl)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Heartbeats renew the lease and report it as held.
+        let held = ts
+            .storage
+            .heartbeat_worker(&a.id, now, now + ttl * 2)
+            .await
+            .unwrap();
+        assert_eq!(held, vec![step_id.clone()]);
+        assert!(ts
+            .storage
+            .expire_leases(now + ttl)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Past the renewed expiry the step is requeued for another worker.
+        let late = now + ttl * 3;
+        let expired = ts.storage.expire_leases(late).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].worker_id.as_ref(), Some(&a.id));
+        let (lease, _) = ts
+            .storage
+            .claim_lease(&b.id, late + ttl)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lease.attempt, 2);
+
+        // The first worker's late completion is rejected; the holder's is kept.
+        assert!(matches!(
+            ts.storage.complete_lease(&step_id, &a.id, late, "{}").await,
+            Err(StorageError::StaleLease(_))
+        ));
+        assert!(ts
+            .storage
+            .heartbeat_worker(&a.id, late, late + ttl)
+            .await
+            .unwrap()
+            .is_empty());
+        ts.storage
+            .complete_lease(&step_id, &b.id, late, r#"{"ok":true}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            ts.storage.lease_result(&step_id).await.unwrap().as_deref(),
+            Some(r#"{"ok":true}"#)
+        );
+
+        ts.storage.release_lease(&step_id).await.unwrap();
+        assert!(ts.storage.lease_result(&step_id).await.unwrap().is_none());
+        assert!(matches!(
+            ts.storage
+                .heartbeat_worker(&Id::new(), late, late + ttl)
+                .await,
+            Err(StorageError::WorkerNotFound(_))
+        ));
+
+        let offline = ts.storage.mark_workers_offline(late).await.unwrap();
+        assert_eq!(offline, vec![b.id.clone()]);
+        let workers = ts.storage.list_workers().await.unwrap();
+        assert_eq!(workers[0].status, WorkerStatus::Online);
+        assert_eq!(workers[1].status, WorkerStatus::Offline);
     }
 
-    #[tokio::test]
-    async fn update_run_status() {
-        let ts = create_test_storage().await;
+    backend_test!(update_run_status_publishes_status_change, open);
+    async fn update_run_status_publishes_status_change(ts: TestBackend) {
         let run = create_test_run();
-
         ts.storage.insert_run(&run).await.unwrap();
+        let mut rx = ts.storage.bus().subscribe_events();
+
+        ts.storage
+            .update_run_status(&run.id, RunStatus::Running)
+            .await
+            .unwrap();
+        // Unchanged status is not announced.
         ts.storage
             .update_run_status(&run.id, RunStatus::Running)
             .await
             .unwrap();
 
-        let retrieved = ts.storage.get_run(&run.id).await.unwrap();
-        assert_eq!(retrieved.status, RunStatus::Running);
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.event_type, "RUN_STATUS_CHANGED");
+        let payload: RunStatusChangedPayload = serde_json::from_str(&event.payload_json).unwrap();
+        assert_eq!(payload.from, RunStatus::Pending);
+        assert_eq!(payload.to, RunStatus::Running);
+        assert_eq!(payload.workspace_root, run.workspace_root);
+        assert!(rx.try_recv().is_err());
+
+        // Synthetic: nothing is stored.
+        assert!(ts.storage.list_events(&run.id).await.unwrap().is_empty());
     }
 
-    #[tokio::test]
-    async fn update_run_worktree_cleanup_updates_fields() {
-        let ts = create_test_storage().await;
+    backend_test!(update_run_worktree_cleanup_updates_fields, open);
+    async fn update_run_worktree_cleanup_updates_fields(ts: TestBackend) {
         let run = create_test_run();
 
         ts.storage.insert_run(&run).await.unwrap();
@@ -1008,9 +4089,8 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn insert_and_list_steps() {
-        let ts = create_test_storage().await;
+    backend_test!(insert_and_list_steps, open);
+    async fn insert_and_list_steps(ts: TestBackend) {
         let run = create_test_run();
         ts.storage.insert_run(&run).await.unwrap();
 
@@ -1025,6 +4105,7 @@ mod tests {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         };
 
         ts.storage.insert_step(&step).await.unwrap();
@@ -1034,9 +4115,33 @@ mod tests {
         assert_eq!(steps[0].phase, StepPhase::Implementation);
     }
 
-    #[tokio::test]
-    async fn append_and_list_events() {
-        let ts = create_test_storage().await;
+    backend_test!(set_step_session_persists_session_id, open);
+    async fn set_step_session_persists_session_id(ts: TestBackend) {
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+        let step = create_test_step(&run.id);
+        ts.storage.insert_step(&step).await.unwrap();
+        assert_eq!(
+            ts.storage.get_step(&step.id).await.unwrap().session_id,
+            None
+        );
+
+        ts.storage
+            .set_step_session(&step.id, "session-abc")
+            .await
+            .unwrap();
+        assert_eq!(
+            ts.storage.get_step(&step.id).await.unwrap().session_id,
+            Some("session-abc".to_string())
+        );
+        assert!(matches!(
+            ts.storage.set_step_session(&Id::new(), "session-abc").await,
+            Err(StorageError::StepNotFound(_))
+        ));
+    }
+
+    backend_test!(append_and_list_events, open);
+    async fn append_and_list_events(ts: TestBackend) {
         let run = create_test_run();
         ts.storage.insert_run(&run).await.unwrap();
 
@@ -1058,65 +4163,390 @@ mod tests {
         assert_eq!(events[0].event_type, "RUN_CREATED");
     }
 
-    #[tokio::test]
-    async fn insert_and_list_artifacts() {
-        let ts = create_test_storage().await;
+    backend_test!(insert_and_list_artifacts, open);
+    async fn insert_and_list_artifacts(ts: TestBackend) {
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+
+        let artifact = Artifact {
+            id: Id::new(),
+            run_id: run.id.clone(),
+            kind: "prompt".to_string(),
+            location: ArtifactLocation::Workspace,
+            path: "/workspace/logs/prompt.txt".to_string(),
+            checksum: None,
+        };
+
+        ts.storage.insert_artifact(&artifact).await.unwrap();
+        let artifacts = ts.storage.list_artifacts(&run.id).await.unwrap();
+
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].kind, "prompt");
+    }
+
+    backend_test!(export_report_generates_tsv, open);
+    async fn export_report_generates_tsv(ts: TestBackend) {
+        use loop_core::events::{StepFinishedPayload, StepStartedPayload};
+
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+
+        // Add RUN_CREATED event.
+        let payload = EventPayload::RunCreated(RunCreatedPayload {
+            run_id: run.id.clone(),
+            name: run.name.clone(),
+            name_source: run.name_source,
+            spec_path: run.spec_path.clone(),
+            plan_path: run.plan_path.clone(),
+        });
+        ts.storage
+            .append_event(&run.id, None, &payload)
+            .await
+            .unwrap();
+
+        // Add step and events.
+        let step = Step {
+            id: Id::new(),
+            run_id: run.id.clone(),
+            phase: StepPhase::Implementation,
+            status: StepStatus::Succeeded,
+            attempt: 1,
+            started_at: Some(Utc::now()),
+            ended_at: Some(Utc::now() + chrono::Duration::seconds(60)),
+            exit_code: Some(0),
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        };
+        ts.storage.insert_step(&step).await.unwrap();
+
+        // STEP_STARTED event.
+        let start_payload = EventPayload::StepStarted(StepStartedPayload {
+            step_id: step.id.clone(),
+            phase: "implementation".to_string(),
+            attempt: 1,
+        });
+        ts.storage
+            .append_event(&run.id, Some(&step.id), &start_payload)
+            .await
+            .unwrap();
+
+        // STEP_FINISHED event.
+        let finish_payload = EventPayload::StepFinished(StepFinishedPayload {
+            step_id: step.id.clone(),
+            exit_code: 0,
+            duration_ms: 60000,
+            output_path: "/test/output.log".to_string(),
+        });
+        ts.storage
+            .append_event(&run.id, Some(&step.id), &finish_payload)
+            .await
+            .unwrap();
+
+        // Export report.
+        let report_path = ts.dir.path().join("report.tsv");
+        ts.storage
+            .export_report(&run.id, &report_path)
+            .await
+            .unwrap();
+
+        // Verify file was created and contains expected content.
+        let content = std::fs::read_to_string(&report_path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+
+        // Header + at least RUN_START, ITERATION_START, ITERATION_END.
+        assert!(
+            lines.len() >= 4,
+            "Expected at least 4 lines, got {}",
+            lines.len()
+        );
+        assert!(lines[0].contains("timestamp_ms"), "Header missing");
+        assert!(lines[1].contains("RUN_START"), "RUN_START missing");
+        assert!(
+            lines[2].contains("ITERATION_START"),
+            "ITERATION_START missing"
+        );
+        assert!(lines[3].contains("ITERATION_END"), "ITERATION_END missing");
+    }
+
+    fn create_test_step(run_id: &Id) -> Step {
+        Step {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            phase: StepPhase::Implementation,
+            status: StepStatus::Succeeded,
+            attempt: 1,
+            started_at: Some(Utc::now()),
+            ended_at: Some(Utc::now()),
+            exit_code: Some(0),
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        }
+    }
+
+    fn usage(input: u64, output: u64, cost_usd: f64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            cost_usd,
+            ..TokenUsage::default()
+        }
+    }
+
+    backend_test!(record_step_usage_accumulates_per_step, open);
+    async fn record_step_usage_accumulates_per_step(ts: TestBackend) {
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+        let step = create_test_step(&run.id);
+        ts.storage.insert_step(&step).await.unwrap();
+
+        // Two attempts of the same step.
+        ts.storage
+            .record_step_usage(&run.id, &step.id, Some("opus"), &usage(100, 10, 0.5))
+            .await
+            .unwrap();
+        ts.storage
+            .record_step_usage(&run.id, &step.id, Some("opus"), &usage(50, 5, 0.25))
+            .await
+            .unwrap();
+
+        let per_step = ts.storage.list_step_usage(&run.id).await.unwrap();
+        assert_eq!(per_step.len(), 1);
+        assert_eq!(per_step[0].0, step.id);
+        assert_eq!(per_step[0].1.input_tokens, 150);
+        assert_eq!(per_step[0].1.output_tokens, 15);
+
+        let total = ts.storage.get_run_usage(&run.id).await.unwrap();
+        assert_eq!(total.total_tokens(), 165);
+        assert!((total.cost_usd - 0.75).abs() < 1e-9);
+    }
+
+    backend_test!(transcript_entries_append_in_order_and_filter_by_tool, open);
+    async fn transcript_entries_append_in_order_and_filter_by_tool(ts: TestBackend) {
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+        let step = create_test_step(&run.id);
+        ts.storage.insert_step(&step).await.unwrap();
+
+        let mut builder = loop_core::TranscriptBuilder::new();
+        builder.observe(
+            &serde_json::json!({"type": "assistant", "message": {"content": [
+                {"type": "text", "text": "Checking the build."},
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "cargo build"}},
+                {"type": "tool_use", "id": "t2", "name": "Read", "input": {"file_path": "src/lib.rs"}}
+            ]}}),
+            Utc::now(),
+        );
+        builder.observe(
+            &serde_json::json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "error", "is_error": true}
+            ]}}),
+            Utc::now(),
+        );
+        let entries = builder.finish();
+
+        // Two batches (e.g. two invocations) share one sequence.
+        ts.storage
+            .append_transcript_entries(&run.id, &step.id, &entries[..2])
+            .await
+            .unwrap();
+        ts.storage
+            .append_transcript_entries(&run.id, &step.id, &entries[2..])
+            .await
+            .unwrap();
+
+        let all = ts
+            .storage
+            .list_transcript_entries(&step.id, None)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(
+            all[1].input,
+            Some(serde_json::json!({"command": "cargo build"}))
+        );
+
+        let bash = ts
+            .storage
+            .list_transcript_entries(&step.id, Some("Bash"))
+            .await
+            .unwrap();
+        assert_eq!(bash.len(), 2);
+        assert_eq!(bash[0].kind, TranscriptEntryKind::ToolUse);
+        assert_eq!(bash[1].kind, TranscriptEntryKind::ToolResult);
+        assert!(bash[1].is_error);
+    }
+
+    backend_test!(agent_actions_filter_by_tool_status_and_arguments, open);
+    async fn agent_actions_filter_by_tool_status_and_arguments(ts: TestBackend) {
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+        let step = create_test_step(&run.id);
+        ts.storage.insert_step(&step).await.unwrap();
+
+        let mut builder = loop_core::TranscriptBuilder::new();
+        builder.observe(
+            &serde_json::json!({"type": "assistant", "message": {"content": [
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "rm -rf build"}},
+                {"type": "tool_use", "id": "t2", "name": "Write",
+                 "input": {"file_path": "notes.md", "content": "hi"}},
+                {"type": "tool_use", "id": "t3", "name": "Bash",
+                 "input": {"command": "API_TOKEN=abc make deploy"}}
+            ]}}),
+            Utc::now(),
+        );
+        builder.observe(
+            &serde_json::json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "", "is_error": false},
+                {"type": "tool_result", "tool_use_id": "t3", "content": "denied", "is_error": true}
+            ]}}),
+            Utc::now(),
+        );
+        let actions =
+            loop_core::audit::actions_from_transcript(&run.id, &step.id, &builder.finish());
+        ts.storage.record_agent_actions(&actions).await.unwrap();
+
+        let all = ts
+            .storage
+            .list_agent_actions(&run.id, &AgentActionFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].arguments["command"], "rm -rf build");
+        assert_eq!(all[1].kind, AgentActionKind::FileWrite);
+        assert_eq!(
+            all[2].arguments["command"],
+            "API_TOKEN=[REDACTED] make deploy"
+        );
+
+        let rm = ts
+            .storage
+            .list_agent_actions(
+                &run.id,
+                &AgentActionFilter {
+                    contains: Some("rm -rf".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(rm.len(), 1);
+        assert_eq!(rm[0].step_id, step.id);
+        assert_eq!(rm[0].status, AgentActionStatus::Succeeded);
+
+        let failed_bash = ts
+            .storage
+            .list_agent_actions(
+                &run.id,
+                &AgentActionFilter {
+                    tool: Some("Bash".to_string()),
+                    status: Some(AgentActionStatus::Failed),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(failed_bash.len(), 1);
+        assert_eq!(failed_bash[0].tool_use_id.as_deref(), Some("t3"));
+    }
+
+    backend_test!(get_run_usage_is_zero_without_records, open);
+    async fn get_run_usage_is_zero_without_records(ts: TestBackend) {
         let run = create_test_run();
         ts.storage.insert_run(&run).await.unwrap();
 
-        let artifact = Artifact {
-            id: Id::new(),
-            run_id: run.id.clone(),
-            kind: "prompt".to_string(),
-            location: ArtifactLocation::Workspace,
-            path: "/workspace/logs/prompt.txt".to_string(),
-            checksum: None,
-        };
+        let total = ts.storage.get_run_usage(&run.id).await.unwrap();
+        assert!(total.is_empty());
+    }
 
-        ts.storage.insert_artifact(&artifact).await.unwrap();
-        let artifacts = ts.storage.list_artifacts(&run.id).await.unwrap();
+    backend_test!(cost_since_sums_across_runs, open);
+    async fn cost_since_sums_across_runs(ts: TestBackend) {
+        let before = Utc::now().timestamp_millis();
+        for cost in [1.0, 2.5] {
+            let run = create_test_run();
+            ts.storage.insert_run(&run).await.unwrap();
+            let step = create_test_step(&run.id);
+            ts.storage.insert_step(&step).await.unwrap();
+            ts.storage
+                .record_step_usage(&run.id, &step.id, None, &usage(10, 1, cost))
+                .await
+                .unwrap();
+        }
 
-        assert_eq!(artifacts.len(), 1);
-        assert_eq!(artifacts[0].kind, "prompt");
+        let total = ts.storage.cost_since(before).await.unwrap();
+        assert!((total - 3.5).abs() < 1e-9);
+
+        let future = Utc::now().timestamp_millis() + 60_000;
+        let none = ts.storage.cost_since(future).await.unwrap();
+        assert!(none.abs() < f64::EPSILON);
     }
 
-    #[tokio::test]
-    async fn export_report_generates_tsv() {
-        use loop_core::events::{RunCreatedPayload, StepFinishedPayload, StepStartedPayload};
+    backend_test!(aggregate_usage_groups_and_filters, open);
+    async fn aggregate_usage_groups_and_filters(ts: TestBackend) {
+        let run_a = create_test_run();
+        let mut run_b = create_test_run();
+        run_b.name = "other-run".to_string();
+        run_b.workspace_root = "/other".to_string();
+        run_b.spec_path = "/other/spec.md".to_string();
+        for (run, cost) in [(&run_a, 1.0), (&run_b, 3.0)] {
+            ts.storage.insert_run(run).await.unwrap();
+            let step = create_test_step(&run.id);
+            ts.storage.insert_step(&step).await.unwrap();
+            ts.storage
+                .record_step_usage(&run.id, &step.id, None, &usage(10, 1, cost))
+                .await
+                .unwrap();
+        }
 
-        let ts = create_test_storage().await;
-        let run = create_test_run();
-        ts.storage.insert_run(&run).await.unwrap();
+        let by_run = ts
+            .storage
+            .aggregate_usage(UsageGroupBy::Run, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(by_run.len(), 2);
+        // Highest cost first; run groups are labelled by name.
+        assert_eq!(by_run[0].key, run_b.id.to_string());
+        assert_eq!(by_run[0].label, "other-run");
+        assert_eq!(by_run[0].steps, 1);
 
-        // Add RUN_CREATED event.
-        let payload = EventPayload::RunCreated(RunCreatedPayload {
-            run_id: run.id.clone(),
-            name: run.name.clone(),
-            name_source: run.name_source,
-            spec_path: run.spec_path.clone(),
-            plan_path: run.plan_path.clone(),
-        });
-        ts.storage
-            .append_event(&run.id, None, &payload)
+        let by_workspace = ts
+            .storage
+            .aggregate_usage(UsageGroupBy::Workspace, Some("/workspace"), None, None)
             .await
             .unwrap();
+        assert_eq!(by_workspace.len(), 1);
+        assert_eq!(by_workspace[0].key, "/workspace");
+        assert_eq!(by_workspace[0].runs, 1);
+        assert_eq!(by_workspace[0].usage.input_tokens, 10);
 
-        // Add step and events.
-        let step = Step {
-            id: Id::new(),
-            run_id: run.id.clone(),
-            phase: StepPhase::Implementation,
-            status: StepStatus::Succeeded,
-            attempt: 1,
-            started_at: Some(Utc::now()),
-            ended_at: Some(Utc::now() + chrono::Duration::seconds(60)),
-            exit_code: Some(0),
-            prompt_path: None,
-            output_path: None,
-        };
+        let by_spec = ts
+            .storage
+            .aggregate_usage(UsageGroupBy::Spec, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(by_spec.len(), 2);
+
+        // Date range excludes everything recorded before `since`.
+        let future = Utc::now().timestamp_millis() + 60_000;
+        let none = ts
+            .storage
+            .aggregate_usage(UsageGroupBy::Run, None, Some(future), None)
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+    }
+
+    backend_test!(export_report_includes_usage_in_messages, open);
+    async fn export_report_includes_usage_in_messages(ts: TestBackend) {
+        use loop_core::events::{RunFailedPayload, StepFinishedPayload, StepStartedPayload};
+
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+        let step = create_test_step(&run.id);
         ts.storage.insert_step(&step).await.unwrap();
 
-        // STEP_STARTED event.
         let start_payload = EventPayload::StepStarted(StepStartedPayload {
             step_id: step.id.clone(),
             phase: "implementation".to_string(),
@@ -1126,43 +4556,47 @@ mod tests {
             .append_event(&run.id, Some(&step.id), &start_payload)
             .await
             .unwrap();
-
-        // STEP_FINISHED event.
         let finish_payload = EventPayload::StepFinished(StepFinishedPayload {
             step_id: step.id.clone(),
             exit_code: 0,
-            duration_ms: 60000,
+            duration_ms: 1000,
             output_path: "/test/output.log".to_string(),
         });
         ts.storage
             .append_event(&run.id, Some(&step.id), &finish_payload)
             .await
             .unwrap();
+        ts.storage
+            .record_step_usage(&run.id, &step.id, Some("opus"), &usage(200, 40, 0.125))
+            .await
+            .unwrap();
+        let failed_payload = EventPayload::RunFailed(RunFailedPayload {
+            run_id: run.id.clone(),
+            reason: "test".to_string(),
+        });
+        ts.storage
+            .append_event(&run.id, None, &failed_payload)
+            .await
+            .unwrap();
 
-        // Export report.
-        let report_path = ts._dir.path().join("report.tsv");
+        let report_path = ts.dir.path().join("report.tsv");
         ts.storage
             .export_report(&run.id, &report_path)
             .await
             .unwrap();
 
-        // Verify file was created and contains expected content.
         let content = std::fs::read_to_string(&report_path).unwrap();
-        let lines: Vec<&str> = content.lines().collect();
+        let iteration_end = content
+            .lines()
+            .find(|l| l.contains("ITERATION_END"))
+            .unwrap();
+        assert!(iteration_end.contains("tokens_in=200 tokens_out=40"));
+        assert!(iteration_end.contains("cost_usd=0.1250"));
+        // Column count is unchanged for bin/loop-analyze compatibility.
+        assert_eq!(iteration_end.split('\t').count(), 11);
 
-        // Header + at least RUN_START, ITERATION_START, ITERATION_END.
-        assert!(
-            lines.len() >= 4,
-            "Expected at least 4 lines, got {}",
-            lines.len()
-        );
-        assert!(lines[0].contains("timestamp_ms"), "Header missing");
-        assert!(lines[1].contains("RUN_START"), "RUN_START missing");
-        assert!(
-            lines[2].contains("ITERATION_START"),
-            "ITERATION_START missing"
-        );
-        assert!(lines[3].contains("ITERATION_END"), "ITERATION_END missing");
+        let run_end = content.lines().find(|l| l.contains("RUN_END")).unwrap();
+        assert!(run_end.contains("reason=failed:test tokens_in=200"));
     }
 
     #[tokio::test]
@@ -1202,27 +4636,139 @@ mod tests {
         storage.insert_run(&run).await.unwrap();
     }
 
+    backend_test!(child_runs_link_to_parent_and_track_merges, open);
+    async fn child_runs_link_to_parent_and_track_merges(ts: TestBackend) {
+        let parent = create_test_run();
+        ts.storage.insert_run(&parent).await.unwrap();
+
+        let child = create_test_run();
+        let link = RunChild {
+            parent_run_id: parent.id.clone(),
+            child_run_id: child.id.clone(),
+            task_id: "storage".to_string(),
+            task_label: "Add storage".to_string(),
+            depends_on: vec!["types".to_string()],
+            merged_at: None,
+            created_at: Utc::now(),
+        };
+        ts.storage.insert_child_run(&child, &link).await.unwrap();
+
+        assert_eq!(ts.storage.get_run(&child.id).await.unwrap().id, child.id);
+        assert!(ts
+            .storage
+            .get_run_child(&parent.id)
+            .await
+            .unwrap()
+            .is_none());
+        let stored = ts.storage.get_run_child(&child.id).await.unwrap().unwrap();
+        assert_eq!(stored.parent_run_id, parent.id);
+        assert_eq!(stored.depends_on, vec!["types".to_string()]);
+        assert!(stored.merged_at.is_none());
+
+        // A second child for the same task is rejected, and nothing is inserted.
+        let duplicate = create_test_run();
+        let duplicate_link = RunChild {
+            child_run_id: duplicate.id.clone(),
+            ..link.clone()
+        };
+        assert!(ts
+            .storage
+            .insert_child_run(&duplicate, &duplicate_link)
+            .await
+            .is_err());
+        assert!(ts.storage.get_run(&duplicate.id).await.is_err());
+
+        ts.storage.mark_run_child_merged(&child.id).await.unwrap();
+        let children = ts.storage.list_run_children(&parent.id).await.unwrap();
+        assert_eq!(children.len(), 1);
+        assert!(children[0].merged_at.is_some());
+    }
+
     #[tokio::test]
-    async fn get_run_not_found() {
-        let ts = create_test_storage().await;
+    async fn migrate_embedded_rebuilds_steps_for_planning_phase() {
+        let dir = TempDir::new().unwrap();
+        let storage = Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+
+        // A database created before the planning phase existed.
+        for migration_sql in MIGRATIONS {
+            storage.apply_migration(migration_sql).await.unwrap();
+        }
+        assert!(!storage
+            .steps_allow_phase(StepPhase::Planning)
+            .await
+            .unwrap());
+        let run = create_test_run();
+        storage.insert_run(&run).await.unwrap();
+        let step = create_test_step(&run.id);
+        storage.insert_step(&step).await.unwrap();
+        let payload = EventPayload::StepStarted(loop_core::events::StepStartedPayload {
+            step_id: step.id.clone(),
+            phase: "implementation".to_string(),
+            attempt: 1,
+        });
+        storage
+            .append_event(&run.id, Some(&step.id), &payload)
+            .await
+            .unwrap();
+        storage
+            .record_step_usage(&run.id, &step.id, None, &usage(10, 5, 0.1))
+            .await
+            .unwrap();
+
+        storage.migrate_embedded().await.unwrap();
+        storage.migrate_embedded().await.unwrap();
+        assert!(storage
+            .steps_allow_phase(StepPhase::Planning)
+            .await
+            .unwrap());
+
+        // Existing rows and references survive the rebuild.
+        assert_eq!(storage.list_steps(&run.id).await.unwrap()[0].id, step.id);
+        let events = storage.list_events(&run.id).await.unwrap();
+        assert_eq!(events[0].step_id.as_ref(), Some(&step.id));
+        assert_eq!(
+            storage.get_run_usage(&run.id).await.unwrap().input_tokens,
+            10
+        );
+
+        let mut planning = create_test_step(&run.id);
+        planning.phase = StepPhase::Planning;
+        storage.insert_step(&planning).await.unwrap();
+        assert_eq!(
+            storage.get_step(&planning.id).await.unwrap().phase,
+            StepPhase::Planning
+        );
+
+        storage
+            .update_run_plan_path(&run.id, "/workspace/specs/planning/spec-plan.md")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_run(&run.id).await.unwrap().plan_path.as_deref(),
+            Some("/workspace/specs/planning/spec-plan.md")
+        );
+    }
+
+    backend_test!(get_run_not_found, open);
+    async fn get_run_not_found(ts: TestBackend) {
         let missing_id = Id::new();
 
         let result = ts.storage.get_run(&missing_id).await;
         assert!(matches!(result, Err(StorageError::RunNotFound(_))));
     }
 
-    #[tokio::test]
-    async fn get_step_not_found() {
-        let ts = create_test_storage().await;
+    backend_test!(get_step_not_found, open);
+    async fn get_step_not_found(ts: TestBackend) {
         let missing_id = Id::new();
 
         let result = ts.storage.get_step(&missing_id).await;
         assert!(matches!(result, Err(StorageError::StepNotFound(_))));
     }
 
-    #[tokio::test]
-    async fn update_run_status_not_found() {
-        let ts = create_test_storage().await;
+    backend_test!(update_run_status_not_found, open);
+    async fn update_run_status_not_found(ts: TestBackend) {
         let missing_id = Id::new();
 
         let result = ts
@@ -1232,9 +4778,8 @@ mod tests {
         assert!(matches!(result, Err(StorageError::RunNotFound(_))));
     }
 
-    #[tokio::test]
-    async fn update_step_not_found() {
-        let ts = create_test_storage().await;
+    backend_test!(update_step_not_found, open);
+    async fn update_step_not_found(ts: TestBackend) {
         let missing_id = Id::new();
 
         let result = ts
@@ -1244,10 +4789,8 @@ mod tests {
         assert!(matches!(result, Err(StorageError::StepNotFound(_))));
     }
 
-    #[tokio::test]
-    async fn list_runs_filters_by_workspace() {
-        let ts = create_test_storage().await;
-
+    backend_test!(list_runs_filters_by_workspace, open);
+    async fn list_runs_filters_by_workspace(ts: TestBackend) {
         // Create runs in different workspaces.
         let now = Utc::now();
         let run1 = Run {
@@ -1268,6 +4811,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         let run2 = Run {
             id: Id::new(),
@@ -1287,6 +4831,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
 
         ts.storage.insert_run(&run1).await.unwrap();
@@ -1302,9 +4847,8 @@ mod tests {
         assert_eq!(all.len(), 2);
     }
 
-    #[tokio::test]
-    async fn run_worktree_fields_round_trip() {
-        let ts = create_test_storage().await;
+    backend_test!(run_worktree_fields_round_trip, open);
+    async fn run_worktree_fields_round_trip(ts: TestBackend) {
         let now = Utc::now();
 
         let run = Run {
@@ -1332,6 +4876,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
 
         ts.storage.insert_run(&run).await.unwrap();
@@ -1353,9 +4898,8 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn worktree_provider_round_trip() {
-        let ts = create_test_storage().await;
+    backend_test!(worktree_provider_round_trip, open);
+    async fn worktree_provider_round_trip(ts: TestBackend) {
         let now = Utc::now();
 
         // Test each provider variant round-trips correctly.
@@ -1389,6 +4933,7 @@ mod tests {
                 review_action_at: None,
                 pr_url: None,
                 merge_commit: None,
+                priority: 0,
             };
 
             ts.storage.insert_run(&run).await.unwrap();
@@ -1403,9 +4948,8 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn update_step_updates_fields() {
-        let ts = create_test_storage().await;
+    backend_test!(update_step_updates_fields, open);
+    async fn update_step_updates_fields(ts: TestBackend) {
         let run = create_test_run();
         ts.storage.insert_run(&run).await.unwrap();
 
@@ -1420,6 +4964,7 @@ mod tests {
             exit_code: None,
             prompt_path: Some("/workspace/prompt.txt".to_string()),
             output_path: None,
+            session_id: None,
         };
         ts.storage.insert_step(&step).await.unwrap();
 
@@ -1444,11 +4989,10 @@ mod tests {
         assert!(updated.ended_at.is_some());
     }
 
-    #[tokio::test]
-    async fn event_with_step_id_persists() {
+    backend_test!(event_with_step_id_persists, open);
+    async fn event_with_step_id_persists(ts: TestBackend) {
         use loop_core::events::StepStartedPayload;
 
-        let ts = create_test_storage().await;
         let run = create_test_run();
         ts.storage.insert_run(&run).await.unwrap();
 
@@ -1463,6 +5007,7 @@ mod tests {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         };
         ts.storage.insert_step(&step).await.unwrap();
 
@@ -1482,11 +5027,10 @@ mod tests {
         assert_eq!(events[0].event_type, "STEP_STARTED");
     }
 
-    #[tokio::test]
-    async fn multiple_events_preserve_order() {
-        use loop_core::events::{RunCreatedPayload, RunStartedPayload};
+    backend_test!(multiple_events_preserve_order, open);
+    async fn multiple_events_preserve_order(ts: TestBackend) {
+        use loop_core::events::RunStartedPayload;
 
-        let ts = create_test_storage().await;
         let run = create_test_run();
         ts.storage.insert_run(&run).await.unwrap();
 
@@ -1522,9 +5066,314 @@ mod tests {
         assert!(events[0].timestamp <= events[1].timestamp);
     }
 
-    #[tokio::test]
-    async fn count_running_runs_for_workspace() {
-        let ts = create_test_storage().await;
+    backend_test!(list_events_after_resumes_by_id_and_publishes_to_bus, open);
+    async fn list_events_after_resumes_by_id_and_publishes_to_bus(ts: TestBackend) {
+        use loop_core::events::RunStartedPayload;
+
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+        let mut rx = ts.storage.bus().subscribe_events();
+
+        // No delay between appends: several events share a millisecond.
+        let mut appended = Vec::new();
+        for i in 0..5 {
+            let payload = EventPayload::RunStarted(RunStartedPayload {
+                run_id: run.id.clone(),
+                worker_id: format!("worker-{i}"),
+            });
+            let event = ts
+                .storage
+                .append_event(&run.id, None, &payload)
+                .await
+                .unwrap();
+            appended.push(event.id);
+        }
+
+        for id in &appended {
+            assert_eq!(&rx.recv().await.unwrap().id, id);
+        }
+
+        let all = ts.storage.list_events_after(&run.id, None).await.unwrap();
+        let ids: Vec<_> = all.iter().map(|e| e.id.clone()).collect();
+        assert_eq!(ids, appended);
+
+        let rest = ts
+            .storage
+            .list_events_after(&run.id, Some(&appended[1]))
+            .await
+            .unwrap();
+        let ids: Vec<_> = rest.iter().map(|e| e.id.clone()).collect();
+        assert_eq!(ids, appended[2..]);
+
+        let unknown = ts
+            .storage
+            .list_events_after(&run.id, Some(&Id::new()))
+            .await
+            .unwrap();
+        assert_eq!(unknown.len(), 5);
+    }
+
+    /// Insert runs created one second apart, oldest first.
+    async fn insert_runs_created_in_order(storage: &dyn StorageBackend, count: usize) -> Vec<Run> {
+        let base = Utc::now() - chrono::Duration::hours(1);
+        let mut runs = Vec::new();
+        for i in 0..count {
+            let mut run = create_test_run();
+            run.created_at = base + chrono::Duration::seconds(i as i64);
+            run.spec_path = format!("/workspace/specs/spec-{i}.md");
+            storage.insert_run(&run).await.unwrap();
+            runs.push(run);
+        }
+        runs
+    }
+
+    backend_test!(list_runs_page_walks_cursor_in_both_orders, open);
+    async fn list_runs_page_walks_cursor_in_both_orders(ts: TestBackend) {
+        let runs = insert_runs_created_in_order(&*ts.storage, 5).await;
+
+        let mut filter = RunFilter {
+            limit: Some(2),
+            ..RunFilter::default()
+        };
+        let mut seen = Vec::new();
+        loop {
+            let page = ts.storage.list_runs_page(&filter).await.unwrap();
+            seen.extend(page.runs.iter().map(|r| r.id.clone()));
+            match page.next_cursor {
+                Some(cursor) => filter.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        let newest_first: Vec<Id> = runs.iter().rev().map(|r| r.id.clone()).collect();
+        assert_eq!(seen, newest_first);
+
+        let page = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                sort: RunSort::CreatedAsc,
+                limit: Some(3),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.runs[0].id, runs[0].id);
+        assert_eq!(page.next_cursor, Some(runs[2].id.clone()));
+    }
+
+    backend_test!(list_runs_page_ties_on_created_at_use_id, open);
+    async fn list_runs_page_ties_on_created_at_use_id(ts: TestBackend) {
+        let created_at = Utc::now();
+        for _ in 0..3 {
+            let mut run = create_test_run();
+            run.created_at = created_at;
+            ts.storage.insert_run(&run).await.unwrap();
+        }
+
+        let first = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                limit: Some(2),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        let second = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                limit: Some(2),
+                cursor: first.next_cursor.clone(),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(first.runs.len(), 2);
+        assert_eq!(second.runs.len(), 1);
+        assert!(second.next_cursor.is_none());
+        assert!(first.runs.iter().all(|r| r.id != second.runs[0].id));
+    }
+
+    backend_test!(list_runs_page_applies_filters, open);
+    async fn list_runs_page_applies_filters(ts: TestBackend) {
+        let runs = insert_runs_created_in_order(&*ts.storage, 4).await;
+        ts.storage
+            .update_run_status(&runs[1].id, RunStatus::Failed)
+            .await
+            .unwrap();
+        ts.storage
+            .update_review_status(&runs[2].id, ReviewStatus::Merged, None, None)
+            .await
+            .unwrap();
+
+        let failed = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                status: Some(RunStatus::Failed),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(failed.runs.len(), 1);
+        assert_eq!(failed.runs[0].id, runs[1].id);
+
+        let merged = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                review_status: Some(ReviewStatus::Merged),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(merged.runs.len(), 1);
+        assert_eq!(merged.runs[0].id, runs[2].id);
+
+        let by_spec = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                spec_path_contains: Some("spec-3".to_string()),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(by_spec.runs.len(), 1);
+        assert_eq!(by_spec.runs[0].id, runs[3].id);
+
+        let window = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                created_after: Some(runs[1].created_at.timestamp_millis()),
+                created_before: Some(runs[3].created_at.timestamp_millis()),
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        let ids: Vec<Id> = window.runs.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(ids, vec![runs[2].id.clone(), runs[1].id.clone()]);
+        assert!(window.next_cursor.is_none());
+    }
+
+    backend_test!(list_runs_page_pages_past_a_deleted_anchor, open);
+    async fn list_runs_page_pages_past_a_deleted_anchor(ts: TestBackend) {
+        let mut runs = Vec::new();
+        for _ in 0..5 {
+            let mut run = create_test_run();
+            let created_ms = cursor_created_ms(&run.id).unwrap();
+            run.created_at = DateTime::from_timestamp_millis(created_ms).unwrap();
+            runs.push(run);
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+        // runs[2] ended the previous page, then was deleted.
+        for (i, run) in runs.iter().enumerate() {
+            if i != 2 {
+                ts.storage.insert_run(run).await.unwrap();
+            }
+        }
+
+        let page_after = |sort| RunFilter {
+            sort,
+            limit: Some(10),
+            cursor: Some(runs[2].id.clone()),
+            ..RunFilter::default()
+        };
+        let ids = |page: RunPage| -> Vec<Id> { page.runs.into_iter().map(|r| r.id).collect() };
+        let older = ts
+            .storage
+            .list_runs_page(&page_after(RunSort::CreatedDesc))
+            .await
+            .unwrap();
+        assert_eq!(ids(older), vec![runs[1].id.clone(), runs[0].id.clone()]);
+        let newer = ts
+            .storage
+            .list_runs_page(&page_after(RunSort::CreatedAsc))
+            .await
+            .unwrap();
+        assert_eq!(ids(newer), vec![runs[3].id.clone(), runs[4].id.clone()]);
+    }
+
+    backend_test!(list_runs_page_rejects_malformed_cursor, open);
+    async fn list_runs_page_rejects_malformed_cursor(ts: TestBackend) {
+        let result = ts
+            .storage
+            .list_runs_page(&RunFilter {
+                cursor: Some(Id::from_string("not-a-run")),
+                limit: Some(10),
+                ..RunFilter::default()
+            })
+            .await;
+        assert!(matches!(result, Err(StorageError::InvalidCursor(_))));
+    }
+
+    backend_test!(count_runs_with_status_counts_pending, open);
+    async fn count_runs_with_status_counts_pending(ts: TestBackend) {
+        for _ in 0..2 {
+            ts.storage.insert_run(&create_test_run()).await.unwrap();
+        }
+        let running = create_test_run();
+        ts.storage.insert_run(&running).await.unwrap();
+        ts.storage
+            .update_run_status(&running.id, RunStatus::Running)
+            .await
+            .unwrap();
+
+        let pending = ts
+            .storage
+            .count_runs_with_status(RunStatus::Pending)
+            .await
+            .unwrap();
+        assert_eq!(pending, 2);
+    }
+
+    backend_test!(
+        notification_deliveries_round_trip_and_filter_by_status,
+        open
+    );
+    async fn notification_deliveries_round_trip_and_filter_by_status(ts: TestBackend) {
+        let run = create_test_run();
+        ts.storage.insert_run(&run).await.unwrap();
+
+        let base = Utc::now();
+        for (i, status) in [DeliveryStatus::Delivered, DeliveryStatus::Failed]
+            .into_iter()
+            .enumerate()
+        {
+            let delivery = NotificationDelivery {
+                id: Id::new(),
+                run_id: run.id.clone(),
+                event_id: Id::new(),
+                event_type: "RUN_FAILED".to_string(),
+                webhook: format!("hook-{i}"),
+                status,
+                attempts: 3,
+                response_code: (status == DeliveryStatus::Failed).then_some(503),
+                error: (status == DeliveryStatus::Failed).then(|| "HTTP 503".to_string()),
+                created_at: base + chrono::Duration::seconds(i as i64),
+            };
+            ts.storage
+                .record_notification_delivery(&delivery)
+                .await
+                .unwrap();
+        }
+
+        let all = ts
+            .storage
+            .list_notification_deliveries(None, 10)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].webhook, "hook-1");
+
+        let failed = ts
+            .storage
+            .list_notification_deliveries(Some(DeliveryStatus::Failed), 10)
+            .await
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].response_code, Some(503));
+        assert_eq!(failed[0].error.as_deref(), Some("HTTP 503"));
+        assert_eq!(failed[0].attempts, 3);
+    }
+
+    backend_test!(count_running_runs_for_workspace, open);
+    async fn count_running_runs_for_workspace(ts: TestBackend) {
         let now = Utc::now();
 
         // Create runs in different workspaces and statuses.
@@ -1546,6 +5395,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         let run2 = Run {
             id: Id::new(),
@@ -1565,6 +5415,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         let run3 = Run {
             id: Id::new(),
@@ -1584,6 +5435,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
         let run4 = Run {
             id: Id::new(),
@@ -1603,6 +5455,7 @@ mod tests {
             review_action_at: None,
             pr_url: None,
             merge_commit: None,
+            priority: 0,
         };
 
         ts.storage.insert_run(&run1).await.unwrap();
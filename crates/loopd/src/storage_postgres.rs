@@ -0,0 +1,2199 @@
+//! Postgres implementation of [`StorageBackend`].
+//!
+//! Covers every table with the same semantics as the `SQLite`
+//! [`Storage`](crate::storage::Storage): timestamps are epoch milliseconds,
+//! enums are stored via `as_str()`, and stored events are published on the
+//! backend's [`EventBus`]. `seq` columns replace `SQLite`'s `rowid` for
+//! insertion order. Schema lives in `migrations/postgres/`.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use loop_core::audit::redact_transcript_input;
+use loop_core::events::EventPayload;
+use loop_core::{
+    AgentAction, Artifact, Event, Id, Lease, PrunedRun, ReviewStatus, Run, RunDependency,
+    RunStatus, RunWorktree, Schedule, Step, StepStatus, TokenUsage, TranscriptEntry, Worker,
+};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+use crate::bus::EventBus;
+use crate::storage::{
+    cursor_created_ms, migration_statements, publish_status_change, run_status_from_db,
+    AgentActionFilter, AgentActionRow, ArtifactRow, DeliveryStatus, EventRow, LeaseRow,
+    NotificationDelivery, NotificationDeliveryRow, PrunedRows, Result, RunChild, RunChildRow,
+    RunDependencyRow, RunFilter, RunPage, RunRow, RunSort, ScheduleRow, StepRow, StepUsageRow,
+    StorageBackend, StorageError, TranscriptEntryRow, UsageAggregate, UsageAggregateRow,
+    UsageGroupBy, UsageRow, WorkerRow, RUNS_COLUMNS,
+};
+
+/// Idempotent Postgres schema migrations, applied in order on every start.
+const PG_MIGRATIONS: [&str; 2] = [
+    include_str!("../../../migrations/postgres/0001_init.sql"),
+    include_str!("../../../migrations/postgres/0002_add_daemon_tables.sql"),
+];
+
+/// Advisory lock key held while migrating, so controllers starting together
+/// do not race on `CREATE TABLE IF NOT EXISTS`.
+const MIGRATION_LOCK_KEY: i64 = 0x6c6f_6f70_6400;
+
+/// Postgres storage backend.
+pub struct PgStorage {
+    pool: PgPool,
+    bus: EventBus,
+}
+
+impl std::fmt::Debug for PgStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PgStorage")
+            .field("pool", &"Pool<Postgres>")
+            .field("bus", &self.bus)
+            .finish()
+    }
+}
+
+impl PgStorage {
+    /// Connect to the database at `database_url`.
+    ///
+    /// Pool size scales with `max_concurrent_runs` the same way as `SQLite`.
+    pub async fn connect(database_url: &str, max_concurrent_runs: usize) -> Result<Self> {
+        let pool_size = (max_concurrent_runs * 3 + 2).min(20) as u32;
+        let pool = PgPoolOptions::new()
+            .max_connections(pool_size)
+            .connect(database_url)
+            .await?;
+        Ok(Self {
+            pool,
+            bus: EventBus::new(),
+        })
+    }
+
+    /// Run the embedded Postgres migrations under an advisory lock.
+    pub async fn migrate_embedded(&self) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(MIGRATION_LOCK_KEY)
+            .execute(&mut *conn)
+            .await?;
+
+        let result = async {
+            for migration_sql in PG_MIGRATIONS {
+                for statement in migration_statements(migration_sql) {
+                    sqlx::query(&statement).execute(&mut *conn).await?;
+                }
+            }
+            Ok::<_, sqlx::Error>(())
+        }
+        .await;
+
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(MIGRATION_LOCK_KEY)
+            .execute(&mut *conn)
+            .await?;
+        result?;
+        Ok(())
+    }
+
+    // --- Run operations ---
+
+    pub async fn insert_run(&self, run: &Run) -> Result<()> {
+        Self::insert_run_with(&self.pool, run).await
+    }
+
+    async fn insert_run_with<'e, E>(executor: E, run: &Run) -> Result<()>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        let (
+            base_branch,
+            run_branch,
+            merge_target,
+            merge_strategy,
+            worktree_path,
+            worktree_provider,
+        ) = match &run.worktree {
+            Some(wt) => (
+                Some(wt.base_branch.as_str()),
+                Some(wt.run_branch.as_str()),
+                wt.merge_target_branch.as_deref(),
+                Some(wt.merge_strategy.as_str()),
+                Some(wt.worktree_path.as_str()),
+                Some(wt.provider.as_str()),
+            ),
+            None => (None, None, None, None, None, None),
+        };
+
+        sqlx::query(
+            r"
+            INSERT INTO runs (id, name, name_source, status, workspace_root, spec_path, plan_path,
+                              base_branch, run_branch, merge_target_branch, merge_strategy,
+                              worktree_path, worktree_provider, config_json, created_at, updated_at,
+                              priority)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            ",
+        )
+        .bind(run.id.as_ref())
+        .bind(&run.name)
+        .bind(run.name_source.as_str())
+        .bind(run.status.as_str())
+        .bind(&run.workspace_root)
+        .bind(&run.spec_path)
+        .bind(&run.plan_path)
+        .bind(base_branch)
+        .bind(run_branch)
+        .bind(merge_target)
+        .bind(merge_strategy)
+        .bind(worktree_path)
+        .bind(worktree_provider)
+        .bind(&run.config_json)
+        .bind(run.created_at.timestamp_millis())
+        .bind(run.updated_at.timestamp_millis())
+        .bind(i64::from(run.priority))
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_run(&self, id: &Id) -> Result<Run> {
+        let query = format!("SELECT {RUNS_COLUMNS} FROM runs WHERE id = $1");
+        let row = sqlx::query_as::<_, RunRow>(&query)
+            .bind(id.as_ref())
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| StorageError::RunNotFound(id.to_string()))?;
+        Ok(row.into_run())
+    }
+
+    pub async fn list_runs(&self, workspace_root: Option<&str>) -> Result<Vec<Run>> {
+        let query = format!(
+            "SELECT {RUNS_COLUMNS} FROM runs WHERE $1::TEXT IS NULL OR workspace_root = $1 \
+             ORDER BY created_at DESC, id DESC"
+        );
+        let rows = sqlx::query_as::<_, RunRow>(&query)
+            .bind(workspace_root)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(RunRow::into_run).collect())
+    }
+
+    /// Update run status, publishing `RUN_STATUS_CHANGED` when it differs.
+    pub async fn update_run_status(&self, id: &Id, status: RunStatus) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let previous = Self::lock_run(&mut tx, id).await?;
+        sqlx::query("UPDATE runs SET status = $1, updated_at = $2 WHERE id = $3")
+            .bind(status.as_str())
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        publish_status_change(&self.bus, id, previous, status);
+        Ok(())
+    }
+
+    /// Lock a run row for the transaction; returns its status and workspace.
+    async fn lock_run(
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        id: &Id,
+    ) -> Result<(RunStatus, String)> {
+        let row: Option<(String, String)> =
+            sqlx::query_as("SELECT status, workspace_root FROM runs WHERE id = $1 FOR UPDATE")
+                .bind(id.as_ref())
+                .fetch_optional(&mut **tx)
+                .await?;
+        let (status, workspace_root) =
+            row.ok_or_else(|| StorageError::RunNotFound(id.to_string()))?;
+        Ok((run_status_from_db(&status), workspace_root))
+    }
+
+    /// List runs matching a filter, one page at a time, keyed on
+    /// `(created_at, id)` like [`Storage::list_runs_page`](crate::storage::Storage::list_runs_page).
+    pub async fn list_runs_page(&self, filter: &RunFilter) -> Result<RunPage> {
+        let mut query =
+            QueryBuilder::<Postgres>::new(format!("SELECT {RUNS_COLUMNS} FROM runs WHERE 1 = 1"));
+        if let Some(ws) = &filter.workspace_root {
+            query.push(" AND workspace_root = ").push_bind(ws);
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status.as_str());
+        }
+        if let Some(review_status) = filter.review_status {
+            query
+                .push(" AND review_status = ")
+                .push_bind(review_status.as_str());
+        }
+        if let Some(needle) = &filter.spec_path_contains {
+            query
+                .push(" AND strpos(spec_path, ")
+                .push_bind(needle)
+                .push(") > 0");
+        }
+        if let Some(after) = filter.created_after {
+            query.push(" AND created_at >= ").push_bind(after);
+        }
+        if let Some(before) = filter.created_before {
+            query.push(" AND created_at < ").push_bind(before);
+        }
+
+        let (cmp, order) = match filter.sort {
+            RunSort::CreatedDesc => ("<", "DESC"),
+            RunSort::CreatedAsc => (">", "ASC"),
+        };
+        if let Some(cursor) = &filter.cursor {
+            query
+                .push(format_args!(
+                    " AND (created_at, id) {cmp} \
+                     (COALESCE((SELECT created_at FROM runs WHERE id = "
+                ))
+                .push_bind(cursor.as_ref())
+                .push("), ")
+                .push_bind(cursor_created_ms(cursor)?)
+                .push("), ")
+                .push_bind(cursor.as_ref())
+                .push(")");
+        }
+        query.push(format_args!(" ORDER BY created_at {order}, id {order}"));
+        if let Some(limit) = filter.limit {
+            // Fetch one extra row to learn whether another page exists.
+            query.push(" LIMIT ").push_bind(i64::from(limit) + 1);
+        }
+
+        let rows = query
+            .build_query_as::<RunRow>()
+            .fetch_all(&self.pool)
+            .await?;
+        let mut runs: Vec<Run> = rows.into_iter().map(RunRow::into_run).collect();
+        let next_cursor = match filter.limit {
+            Some(limit) if runs.len() > limit as usize => {
+                runs.truncate(limit as usize);
+                runs.last().map(|run| run.id.clone())
+            }
+            _ => None,
+        };
+        Ok(RunPage { runs, next_cursor })
+    }
+
+    pub async fn count_runs_with_status(&self, status: RunStatus) -> Result<usize> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM runs WHERE status = $1")
+            .bind(status.as_str())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as usize)
+    }
+
+    pub async fn count_running_runs_by_workspace(&self) -> Result<HashMap<String, usize>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT workspace_root, COUNT(*) FROM runs WHERE status = 'RUNNING' \
+             GROUP BY workspace_root",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(workspace, count)| (workspace, count as usize))
+            .collect())
+    }
+
+    pub async fn count_running_runs_for_workspace(&self, workspace_root: &str) -> Result<usize> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM runs WHERE workspace_root = $1 AND status = 'RUNNING'",
+        )
+        .bind(workspace_root)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as usize)
+    }
+
+    pub async fn update_run_worktree(&self, id: &Id, worktree: &RunWorktree) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE runs SET base_branch = $1, run_branch = $2, merge_target_branch = $3, \
+             merge_strategy = $4, worktree_path = $5, worktree_provider = $6, updated_at = $7 \
+             WHERE id = $8",
+        )
+        .bind(&worktree.base_branch)
+        .bind(&worktree.run_branch)
+        .bind(&worktree.merge_target_branch)
+        .bind(worktree.merge_strategy.as_str())
+        .bind(&worktree.worktree_path)
+        .bind(worktree.provider.as_str())
+        .bind(Utc::now().timestamp_millis())
+        .bind(id.as_ref())
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn update_run_plan_path(&self, id: &Id, plan_path: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE runs SET plan_path = $1, updated_at = $2 WHERE id = $3")
+            .bind(plan_path)
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn update_run_worktree_cleanup(
+        &self,
+        id: &Id,
+        status: &str,
+        cleaned_at: Option<i64>,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE runs SET worktree_cleanup_status = $1, worktree_cleaned_at = $2, \
+             updated_at = $3 WHERE id = $4",
+        )
+        .bind(status)
+        .bind(cleaned_at)
+        .bind(Utc::now().timestamp_millis())
+        .bind(id.as_ref())
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn update_run_priority(&self, id: &Id, priority: i32) -> Result<()> {
+        let result = sqlx::query("UPDATE runs SET priority = $1, updated_at = $2 WHERE id = $3")
+            .bind(i64::from(priority))
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn update_run_config(&self, id: &Id, config_json: &str) -> Result<()> {
+        sqlx::query("UPDATE runs SET config_json = $1, updated_at = $2 WHERE id = $3")
+            .bind(config_json)
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_review_status(
+        &self,
+        id: &Id,
+        status: ReviewStatus,
+        pr_url: Option<&str>,
+        merge_commit: Option<&str>,
+    ) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let result = sqlx::query(
+            "UPDATE runs SET review_status = $1, review_action_at = $2, pr_url = $3, \
+             merge_commit = $4, updated_at = $2 WHERE id = $5",
+        )
+        .bind(status.as_str())
+        .bind(now)
+        .bind(pr_url)
+        .bind(merge_commit)
+        .bind(id.as_ref())
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    // --- Step operations ---
+
+    pub async fn insert_step(&self, step: &Step) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO steps (id, run_id, phase, status, attempt, started_at, ended_at,
+                               exit_code, prompt_path, output_path, session_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ",
+        )
+        .bind(step.id.as_ref())
+        .bind(step.run_id.as_ref())
+        .bind(step.phase.as_str())
+        .bind(step.status.as_str())
+        .bind(i64::from(step.attempt))
+        .bind(step.started_at.map(|t| t.timestamp_millis()))
+        .bind(step.ended_at.map(|t| t.timestamp_millis()))
+        .bind(step.exit_code)
+        .bind(&step.prompt_path)
+        .bind(&step.output_path)
+        .bind(&step.session_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_step(&self, id: &Id) -> Result<Step> {
+        let row = sqlx::query_as::<_, StepRow>("SELECT * FROM steps WHERE id = $1")
+            .bind(id.as_ref())
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| StorageError::StepNotFound(id.to_string()))?;
+        Ok(row.into_step())
+    }
+
+    pub async fn list_steps(&self, run_id: &Id) -> Result<Vec<Step>> {
+        let rows = sqlx::query_as::<_, StepRow>(
+            "SELECT * FROM steps WHERE run_id = $1 \
+             ORDER BY started_at IS NULL, started_at ASC, seq ASC",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(StepRow::into_step).collect())
+    }
+
+    pub async fn mark_step_started(&self, id: &Id) -> Result<()> {
+        let result = sqlx::query("UPDATE steps SET status = $1, started_at = $2 WHERE id = $3")
+            .bind(StepStatus::InProgress.as_str())
+            .bind(Utc::now().timestamp_millis())
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StepNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn update_step(
+        &self,
+        id: &Id,
+        status: StepStatus,
+        exit_code: Option<i32>,
+        output_path: Option<&str>,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE steps SET status = $1, ended_at = $2, exit_code = $3, output_path = $4 \
+             WHERE id = $5",
+        )
+        .bind(status.as_str())
+        .bind(Utc::now().timestamp_millis())
+        .bind(exit_code)
+        .bind(output_path)
+        .bind(id.as_ref())
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StepNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn set_step_session(&self, id: &Id, session_id: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE steps SET session_id = $1 WHERE id = $2")
+            .bind(session_id)
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StepNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    // --- Event operations ---
+
+    pub async fn append_event(
+        &self,
+        run_id: &Id,
+        step_id: Option<&Id>,
+        payload: &EventPayload,
+    ) -> Result<Event> {
+        let event = Event {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            step_id: step_id.cloned(),
+            event_type: payload.event_type().as_str().to_string(),
+            timestamp: Utc::now(),
+            payload_json: payload.to_json()?,
+        };
+        Self::insert_event(&self.pool, &event).await?;
+        self.bus.publish_event(&event);
+        Ok(event)
+    }
+
+    async fn insert_event<'e, E>(executor: E, event: &Event) -> Result<()>
+    where
+        E: sqlx::Executor<'e, Database = Postgres>,
+    {
+        sqlx::query(
+            "INSERT INTO events (id, run_id, step_id, type, ts, payload_json) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(event.id.as_ref())
+        .bind(event.run_id.as_ref())
+        .bind(event.step_id.as_ref().map(std::convert::AsRef::as_ref))
+        .bind(&event.event_type)
+        .bind(event.timestamp.timestamp_millis())
+        .bind(&event.payload_json)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Append a run event and update run status in one transaction.
+    pub async fn complete_run_atomically(
+        &self,
+        run_id: &Id,
+        payload: &EventPayload,
+        status: RunStatus,
+    ) -> Result<Event> {
+        let event = Event {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            step_id: None,
+            event_type: payload.event_type().as_str().to_string(),
+            timestamp: Utc::now(),
+            payload_json: payload.to_json()?,
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let previous = Self::lock_run(&mut tx, run_id).await?;
+        Self::insert_event(&mut *tx, &event).await?;
+        sqlx::query("UPDATE runs SET status = $1, updated_at = $2 WHERE id = $3")
+            .bind(status.as_str())
+            .bind(event.timestamp.timestamp_millis())
+            .bind(run_id.as_ref())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.bus.publish_event(&event);
+        publish_status_change(&self.bus, run_id, previous, status);
+        Ok(event)
+    }
+
+    pub async fn list_events(&self, run_id: &Id) -> Result<Vec<Event>> {
+        let rows = sqlx::query_as::<_, EventRow>(
+            "SELECT * FROM events WHERE run_id = $1 ORDER BY ts ASC, seq ASC",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(EventRow::into_event).collect())
+    }
+
+    pub async fn list_events_after(
+        &self,
+        run_id: &Id,
+        after_id: Option<&Id>,
+    ) -> Result<Vec<Event>> {
+        let rows = sqlx::query_as::<_, EventRow>(
+            "SELECT * FROM events WHERE run_id = $1 \
+             AND seq > COALESCE((SELECT seq FROM events WHERE id = $2 AND run_id = $1), 0) \
+             ORDER BY seq ASC",
+        )
+        .bind(run_id.as_ref())
+        .bind(after_id.map(std::convert::AsRef::as_ref))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(EventRow::into_event).collect())
+    }
+
+    // --- Artifact operations ---
+
+    pub async fn insert_artifact(&self, artifact: &Artifact) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO artifacts (id, run_id, kind, location, path, checksum) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(artifact.id.as_ref())
+        .bind(artifact.run_id.as_ref())
+        .bind(&artifact.kind)
+        .bind(artifact.location.as_str())
+        .bind(&artifact.path)
+        .bind(&artifact.checksum)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_artifacts(&self, run_id: &Id) -> Result<Vec<Artifact>> {
+        let rows = sqlx::query_as::<_, ArtifactRow>(
+            "SELECT * FROM artifacts WHERE run_id = $1 ORDER BY kind",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ArtifactRow::into_artifact).collect())
+    }
+
+    pub async fn delete_artifact(&self, id: &Id) -> Result<()> {
+        sqlx::query("DELETE FROM artifacts WHERE id = $1")
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // --- Usage operations ---
+
+    /// Record token usage for a step, adding to what is already recorded.
+    pub async fn record_step_usage(
+        &self,
+        run_id: &Id,
+        step_id: &Id,
+        model: Option<&str>,
+        usage: &TokenUsage,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO step_usage (step_id, run_id, model, input_tokens, output_tokens,
+                                    cache_creation_input_tokens, cache_read_input_tokens,
+                                    cost_usd, recorded_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (step_id) DO UPDATE SET
+                model = COALESCE(excluded.model, step_usage.model),
+                input_tokens = step_usage.input_tokens + excluded.input_tokens,
+                output_tokens = step_usage.output_tokens + excluded.output_tokens,
+                cache_creation_input_tokens =
+                    step_usage.cache_creation_input_tokens + excluded.cache_creation_input_tokens,
+                cache_read_input_tokens =
+                    step_usage.cache_read_input_tokens + excluded.cache_read_input_tokens,
+                cost_usd = step_usage.cost_usd + excluded.cost_usd,
+                recorded_at = excluded.recorded_at
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(run_id.as_ref())
+        .bind(model)
+        .bind(usage.input_tokens as i64)
+        .bind(usage.output_tokens as i64)
+        .bind(usage.cache_creation_input_tokens as i64)
+        .bind(usage.cache_read_input_tokens as i64)
+        .bind(usage.cost_usd)
+        .bind(Utc::now().timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_step_usage(&self, run_id: &Id) -> Result<Vec<(Id, TokenUsage)>> {
+        let rows = sqlx::query_as::<_, StepUsageRow>(
+            r"
+            SELECT step_id, input_tokens, output_tokens, cache_creation_input_tokens,
+                   cache_read_input_tokens, cost_usd
+            FROM step_usage WHERE run_id = $1 ORDER BY recorded_at ASC, seq ASC
+            ",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (Id::from_string(row.step_id), row.usage.to_usage()))
+            .collect())
+    }
+
+    /// Total usage across all steps of a run.
+    ///
+    /// `SUM` over `BIGINT` is `NUMERIC` in Postgres, so totals are cast back.
+    pub async fn get_run_usage(&self, run_id: &Id) -> Result<TokenUsage> {
+        let row = sqlx::query_as::<_, UsageRow>(
+            r"
+            SELECT COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                   COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                   COALESCE(SUM(cache_creation_input_tokens), 0)::BIGINT
+                       AS cache_creation_input_tokens,
+                   COALESCE(SUM(cache_read_input_tokens), 0)::BIGINT AS cache_read_input_tokens,
+                   COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION AS cost_usd
+            FROM step_usage WHERE run_id = $1
+            ",
+        )
+        .bind(run_id.as_ref())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.to_usage())
+    }
+
+    pub async fn cost_since(&self, since_ms: i64) -> Result<f64> {
+        let cost: f64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(cost_usd), 0)::DOUBLE PRECISION FROM step_usage \
+             WHERE recorded_at >= $1",
+        )
+        .bind(since_ms)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(cost)
+    }
+
+    pub async fn aggregate_usage(
+        &self,
+        group_by: UsageGroupBy,
+        workspace_root: Option<&str>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> Result<Vec<UsageAggregate>> {
+        let (key, label) = match group_by {
+            UsageGroupBy::Run => ("r.id", "MAX(r.name)"),
+            UsageGroupBy::Spec => ("r.spec_path", "r.spec_path"),
+            UsageGroupBy::Workspace => ("r.workspace_root", "r.workspace_root"),
+        };
+        let sql = format!(
+            r"
+            SELECT {key} AS key, {label} AS label,
+                   COUNT(DISTINCT u.run_id) AS runs,
+                   COUNT(*) AS steps,
+                   SUM(u.input_tokens)::BIGINT AS input_tokens,
+                   SUM(u.output_tokens)::BIGINT AS output_tokens,
+                   SUM(u.cache_creation_input_tokens)::BIGINT AS cache_creation_input_tokens,
+                   SUM(u.cache_read_input_tokens)::BIGINT AS cache_read_input_tokens,
+                   SUM(u.cost_usd) AS cost_usd
+            FROM step_usage u JOIN runs r ON r.id = u.run_id
+            WHERE ($1::TEXT IS NULL OR r.workspace_root = $1)
+              AND ($2::BIGINT IS NULL OR u.recorded_at >= $2)
+              AND ($3::BIGINT IS NULL OR u.recorded_at < $3)
+            GROUP BY {key}
+            ORDER BY cost_usd DESC, key ASC
+            "
+        );
+        let rows = sqlx::query_as::<_, UsageAggregateRow>(&sql)
+            .bind(workspace_root)
+            .bind(since)
+            .bind(until)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(UsageAggregateRow::into_aggregate)
+            .collect())
+    }
+
+    // --- Step transcripts ---
+
+    /// Append transcript entries for a step after any already stored, with
+    /// tool inputs redacted.
+    pub async fn append_transcript_entries(
+        &self,
+        run_id: &Id,
+        step_id: &Id,
+        entries: &[TranscriptEntry],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let next_seq: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(seq) + 1, 0) FROM transcript_entries WHERE step_id = $1",
+        )
+        .bind(step_id.as_ref())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for (index, entry) in entries.iter().enumerate() {
+            let input_json = entry
+                .input
+                .as_ref()
+                .map(|input| {
+                    let input = redact_transcript_input(entry.tool_name.as_deref(), input);
+                    serde_json::to_string(&input)
+                })
+                .transpose()?;
+            sqlx::query(
+                r"
+                INSERT INTO transcript_entries (step_id, run_id, seq, kind, text, tool_use_id,
+                    tool_name, input_json, is_error, at, duration_ms)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                ",
+            )
+            .bind(step_id.as_ref())
+            .bind(run_id.as_ref())
+            .bind(next_seq + index as i64)
+            .bind(entry.kind.as_str())
+            .bind(&entry.text)
+            .bind(&entry.tool_use_id)
+            .bind(&entry.tool_name)
+            .bind(input_json)
+            .bind(entry.is_error)
+            .bind(entry.at.timestamp_millis())
+            .bind(entry.duration_ms.map(|ms| ms as i64))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn list_transcript_entries(
+        &self,
+        step_id: &Id,
+        tool_name: Option<&str>,
+    ) -> Result<Vec<TranscriptEntry>> {
+        let rows = sqlx::query_as::<_, TranscriptEntryRow>(
+            r"
+            SELECT * FROM transcript_entries
+            WHERE step_id = $1 AND ($2::TEXT IS NULL OR tool_name = $2)
+            ORDER BY seq
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(tool_name)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(TranscriptEntryRow::into_entry)
+            .collect())
+    }
+
+    // --- Agent action audit log ---
+
+    pub async fn record_agent_actions(&self, actions: &[AgentAction]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for action in actions {
+            sqlx::query(
+                r"
+                INSERT INTO agent_actions (run_id, step_id, tool_use_id, tool, kind,
+                    arguments_json, status, at, duration_ms)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ",
+            )
+            .bind(action.run_id.as_ref())
+            .bind(action.step_id.as_ref())
+            .bind(&action.tool_use_id)
+            .bind(&action.tool)
+            .bind(action.kind.as_str())
+            .bind(serde_json::to_string(&action.arguments)?)
+            .bind(action.status.as_str())
+            .bind(action.at.timestamp_millis())
+            .bind(action.duration_ms.map(|ms| ms as i64))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn list_agent_actions(
+        &self,
+        run_id: &Id,
+        filter: &AgentActionFilter,
+    ) -> Result<Vec<AgentAction>> {
+        let mut query =
+            QueryBuilder::<Postgres>::new("SELECT * FROM agent_actions WHERE run_id = ");
+        query.push_bind(run_id.as_ref());
+        if let Some(step_id) = &filter.step_id {
+            query.push(" AND step_id = ").push_bind(step_id.as_ref());
+        }
+        if let Some(tool) = &filter.tool {
+            query.push(" AND tool = ").push_bind(tool);
+        }
+        if let Some(kind) = filter.kind {
+            query.push(" AND kind = ").push_bind(kind.as_str());
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status.as_str());
+        }
+        if let Some(needle) = &filter.contains {
+            query
+                .push(" AND strpos(arguments_json, ")
+                .push_bind(needle)
+                .push(") > 0");
+        }
+        query.push(" ORDER BY at, id");
+        if let Some(limit) = filter.limit {
+            query.push(" LIMIT ").push_bind(i64::from(limit));
+        }
+
+        let rows = query
+            .build_query_as::<AgentActionRow>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(AgentActionRow::into_action).collect())
+    }
+
+    // --- Run dependencies ---
+
+    /// Insert a run and the runs it depends on in one transaction.
+    pub async fn insert_run_with_dependencies(
+        &self,
+        run: &Run,
+        dependencies: &[RunDependency],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert_run_with(&mut *tx, run).await?;
+        for dependency in dependencies {
+            sqlx::query(
+                r"
+                INSERT INTO run_dependencies (run_id, depends_on_run_id, condition, branch_from)
+                VALUES ($1, $2, $3, $4)
+                ",
+            )
+            .bind(run.id.as_ref())
+            .bind(dependency.run_id.as_ref())
+            .bind(dependency.condition.as_str())
+            .bind(dependency.branch_from)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn list_run_dependencies(&self, run_id: &Id) -> Result<Vec<RunDependency>> {
+        let rows = sqlx::query_as::<_, RunDependencyRow>(
+            "SELECT * FROM run_dependencies WHERE run_id = $1 ORDER BY seq",
+        )
+        .bind(run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| row.into_dependency().1)
+            .collect())
+    }
+
+    pub async fn list_pending_run_dependencies(&self) -> Result<HashMap<Id, Vec<RunDependency>>> {
+        let rows = sqlx::query_as::<_, RunDependencyRow>(
+            r"
+            SELECT d.* FROM run_dependencies d
+            JOIN runs r ON r.id = d.run_id
+            WHERE r.status = 'PENDING'
+            ORDER BY d.seq
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut dependencies: HashMap<Id, Vec<RunDependency>> = HashMap::new();
+        for row in rows {
+            let (run_id, dependency) = row.into_dependency();
+            dependencies.entry(run_id).or_default().push(dependency);
+        }
+        Ok(dependencies)
+    }
+
+    // --- Child runs ---
+
+    /// Insert a child run and its link to the parent in one transaction.
+    pub async fn insert_child_run(&self, child: &Run, link: &RunChild) -> Result<()> {
+        let depends_on_json = serde_json::to_string(&link.depends_on)?;
+        let mut tx = self.pool.begin().await?;
+        Self::insert_run_with(&mut *tx, child).await?;
+        sqlx::query(
+            r"
+            INSERT INTO run_children (child_run_id, parent_run_id, task_id, task_label,
+                depends_on_json, merged_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ",
+        )
+        .bind(link.child_run_id.as_ref())
+        .bind(link.parent_run_id.as_ref())
+        .bind(&link.task_id)
+        .bind(&link.task_label)
+        .bind(depends_on_json)
+        .bind(link.merged_at.map(|t| t.timestamp_millis()))
+        .bind(link.created_at.timestamp_millis())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_run_child(&self, child_run_id: &Id) -> Result<Option<RunChild>> {
+        let row =
+            sqlx::query_as::<_, RunChildRow>("SELECT * FROM run_children WHERE child_run_id = $1")
+                .bind(child_run_id.as_ref())
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(RunChildRow::into_child))
+    }
+
+    pub async fn list_run_children(&self, parent_run_id: &Id) -> Result<Vec<RunChild>> {
+        let rows = sqlx::query_as::<_, RunChildRow>(
+            "SELECT * FROM run_children WHERE parent_run_id = $1 ORDER BY created_at, seq",
+        )
+        .bind(parent_run_id.as_ref())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(RunChildRow::into_child).collect())
+    }
+
+    pub async fn mark_run_child_merged(&self, child_run_id: &Id) -> Result<()> {
+        let result = sqlx::query("UPDATE run_children SET merged_at = $1 WHERE child_run_id = $2")
+            .bind(Utc::now().timestamp_millis())
+            .bind(child_run_id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::RunNotFound(child_run_id.to_string()));
+        }
+        Ok(())
+    }
+
+    // --- Notification delivery log ---
+
+    pub async fn record_notification_delivery(
+        &self,
+        delivery: &NotificationDelivery,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO notification_deliveries (id, run_id, event_id, event_type, webhook,
+                status, attempts, response_code, error, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ",
+        )
+        .bind(delivery.id.as_ref())
+        .bind(delivery.run_id.as_ref())
+        .bind(delivery.event_id.as_ref())
+        .bind(&delivery.event_type)
+        .bind(&delivery.webhook)
+        .bind(delivery.status.as_str())
+        .bind(i64::from(delivery.attempts))
+        .bind(delivery.response_code.map(i64::from))
+        .bind(&delivery.error)
+        .bind(delivery.created_at.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_notification_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let rows = sqlx::query_as::<_, NotificationDeliveryRow>(
+            r"
+            SELECT * FROM notification_deliveries
+            WHERE $1::TEXT IS NULL OR status = $1
+            ORDER BY created_at DESC, seq DESC
+            LIMIT $2
+            ",
+        )
+        .bind(status.map(DeliveryStatus::as_str))
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(NotificationDeliveryRow::into_delivery)
+            .collect())
+    }
+
+    // --- Schedules ---
+
+    pub async fn insert_schedule(&self, schedule: &Schedule) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO schedules (id, name, workspace_root, spec_path, plan_path,
+                config_override, cron, missed_fire_policy, overlap_policy, priority,
+                last_run_id, next_fire_at, last_fire_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            ",
+        )
+        .bind(schedule.id.as_ref())
+        .bind(&schedule.name)
+        .bind(&schedule.workspace_root)
+        .bind(&schedule.spec_path)
+        .bind(&schedule.plan_path)
+        .bind(&schedule.config_override)
+        .bind(&schedule.cron)
+        .bind(schedule.missed_fire_policy.as_str())
+        .bind(schedule.overlap_policy.as_str())
+        .bind(i64::from(schedule.priority))
+        .bind(schedule.last_run_id.as_ref().map(AsRef::<str>::as_ref))
+        .bind(schedule.next_fire_at.timestamp_millis())
+        .bind(schedule.last_fire_at.map(|t| t.timestamp_millis()))
+        .bind(schedule.created_at.timestamp_millis())
+        .bind(schedule.updated_at.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_schedules(&self, workspace_root: Option<&str>) -> Result<Vec<Schedule>> {
+        let rows = sqlx::query_as::<_, ScheduleRow>(
+            r"
+            SELECT * FROM schedules
+            WHERE $1::TEXT IS NULL OR workspace_root = $1
+            ORDER BY created_at ASC, seq ASC
+            ",
+        )
+        .bind(workspace_root)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ScheduleRow::into_schedule).collect())
+    }
+
+    pub async fn list_due_schedules(&self, now: DateTime<Utc>) -> Result<Vec<Schedule>> {
+        let rows = sqlx::query_as::<_, ScheduleRow>(
+            "SELECT * FROM schedules WHERE next_fire_at <= $1 ORDER BY next_fire_at ASC, seq ASC",
+        )
+        .bind(now.timestamp_millis())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ScheduleRow::into_schedule).collect())
+    }
+
+    pub async fn delete_schedule(&self, id: &Id) -> Result<()> {
+        let result = sqlx::query("DELETE FROM schedules WHERE id = $1")
+            .bind(id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::ScheduleNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Insert the enqueued run (if any) and advance the schedule, atomically.
+    pub async fn fire_schedule(
+        &self,
+        id: &Id,
+        run: Option<&Run>,
+        next_fire_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let result = if let Some(run) = run {
+            Self::insert_run_with(&mut *tx, run).await?;
+            sqlx::query(
+                r"
+                UPDATE schedules SET next_fire_at = $1, last_fire_at = $2, last_run_id = $3,
+                    updated_at = $2
+                WHERE id = $4
+                ",
+            )
+            .bind(next_fire_at.timestamp_millis())
+            .bind(now)
+            .bind(run.id.as_ref())
+            .bind(id.as_ref())
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query("UPDATE schedules SET next_fire_at = $1, updated_at = $2 WHERE id = $3")
+                .bind(next_fire_at.timestamp_millis())
+                .bind(now)
+                .bind(id.as_ref())
+                .execute(&mut *tx)
+                .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(StorageError::ScheduleNotFound(id.to_string()));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // --- Workers and leases ---
+
+    pub async fn insert_worker(&self, worker: &Worker) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO workers (id, hostname, status, capabilities_json, last_heartbeat, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+        )
+        .bind(worker.id.as_ref())
+        .bind(&worker.hostname)
+        .bind(worker.status.as_str())
+        .bind(serde_json::to_string(&worker.capabilities)?)
+        .bind(worker.last_heartbeat.timestamp_millis())
+        .bind(worker.created_at.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_workers(&self) -> Result<Vec<Worker>> {
+        let rows = sqlx::query_as::<_, WorkerRow>(
+            "SELECT * FROM workers ORDER BY created_at ASC, seq ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(WorkerRow::into_worker).collect())
+    }
+
+    /// Mark the worker online and extend its live leases to `expires_at`.
+    /// Returns the steps it still holds.
+    pub async fn heartbeat_worker(
+        &self,
+        worker_id: &Id,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Vec<Id>> {
+        let mut tx = self.pool.begin().await?;
+        let result =
+            sqlx::query("UPDATE workers SET status = 'ONLINE', last_heartbeat = $1 WHERE id = $2")
+                .bind(now.timestamp_millis())
+                .bind(worker_id.as_ref())
+                .execute(&mut *tx)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::WorkerNotFound(worker_id.to_string()));
+        }
+
+        let held: Vec<String> = sqlx::query_scalar(
+            r"
+            WITH renewed AS (
+                UPDATE leases SET expires_at = $1
+                WHERE worker_id = $2 AND result_json IS NULL AND expires_at > $3
+                RETURNING step_id, created_at, seq
+            )
+            SELECT step_id FROM renewed ORDER BY created_at ASC, seq ASC
+            ",
+        )
+        .bind(expires_at.timestamp_millis())
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(held.into_iter().map(Id::from_string).collect())
+    }
+
+    pub async fn touch_worker(&self, worker_id: &Id, now: DateTime<Utc>) -> Result<()> {
+        let result =
+            sqlx::query("UPDATE workers SET status = 'ONLINE', last_heartbeat = $1 WHERE id = $2")
+                .bind(now.timestamp_millis())
+                .bind(worker_id.as_ref())
+                .execute(&self.pool)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::WorkerNotFound(worker_id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn mark_workers_offline(&self, cutoff: DateTime<Utc>) -> Result<Vec<Id>> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            r"
+            UPDATE workers SET status = 'OFFLINE'
+            WHERE status = 'ONLINE' AND last_heartbeat < $1
+            RETURNING id
+            ",
+        )
+        .bind(cutoff.timestamp_millis())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ids.into_iter().map(Id::from_string).collect())
+    }
+
+    pub async fn offer_step(&self, step_id: &Id, run_id: &Id, request_json: &str) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO leases (step_id, run_id, request_json, created_at)
+            VALUES ($1, $2, $3, $4)
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(run_id.as_ref())
+        .bind(request_json)
+        .bind(Utc::now().timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Grant the longest-waiting unclaimed step to `worker_id` until
+    /// `expires_at`. `SKIP LOCKED` keeps concurrent claims from getting the
+    /// same step.
+    pub async fn claim_lease(
+        &self,
+        worker_id: &Id,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<(Lease, String)>> {
+        let row = sqlx::query_as::<_, LeaseRow>(
+            r"
+            UPDATE leases SET worker_id = $1, expires_at = $2, attempt = attempt + 1
+            WHERE step_id = (
+                SELECT step_id FROM leases
+                WHERE worker_id IS NULL AND result_json IS NULL
+                ORDER BY created_at ASC, seq ASC
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            ",
+        )
+        .bind(worker_id.as_ref())
+        .bind(expires_at.timestamp_millis())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| {
+            let request_json = row.request_json.clone();
+            (row.into_lease(), request_json)
+        }))
+    }
+
+    pub async fn complete_lease(
+        &self,
+        step_id: &Id,
+        worker_id: &Id,
+        now: DateTime<Utc>,
+        result_json: &str,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            r"
+            UPDATE leases SET result_json = $1
+            WHERE step_id = $2 AND worker_id = $3 AND result_json IS NULL AND expires_at > $4
+            ",
+        )
+        .bind(result_json)
+        .bind(step_id.as_ref())
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::StaleLease(step_id.to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn held_lease_request(
+        &self,
+        step_id: &Id,
+        worker_id: &Id,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>> {
+        let request_json = sqlx::query_scalar(
+            r"
+            SELECT request_json FROM leases
+            WHERE step_id = $1 AND worker_id = $2 AND result_json IS NULL AND expires_at > $3
+            ",
+        )
+        .bind(step_id.as_ref())
+        .bind(worker_id.as_ref())
+        .bind(now.timestamp_millis())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(request_json)
+    }
+
+    pub async fn lease_result(&self, step_id: &Id) -> Result<Option<String>> {
+        let result: Option<Option<String>> =
+            sqlx::query_scalar("SELECT result_json FROM leases WHERE step_id = $1")
+                .bind(step_id.as_ref())
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(result.flatten())
+    }
+
+    pub async fn release_lease(&self, step_id: &Id) -> Result<()> {
+        sqlx::query("DELETE FROM leases WHERE step_id = $1")
+            .bind(step_id.as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn clear_leases(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM leases")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Requeue leases that expired at or before `now`. Returns the leases as
+    /// they were before requeueing.
+    pub async fn expire_leases(&self, now: DateTime<Utc>) -> Result<Vec<Lease>> {
+        let rows = sqlx::query_as::<_, LeaseRow>(
+            r"
+            WITH expired AS (
+                SELECT * FROM leases
+                WHERE worker_id IS NOT NULL AND result_json IS NULL AND expires_at <= $1
+                FOR UPDATE
+            )
+            UPDATE leases SET worker_id = NULL, expires_at = NULL
+            FROM expired
+            WHERE leases.step_id = expired.step_id
+            RETURNING expired.*
+            ",
+        )
+        .bind(now.timestamp_millis())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(LeaseRow::into_lease).collect())
+    }
+
+    // --- Retention ---
+
+    pub async fn list_gc_candidates(&self) -> Result<Vec<Run>> {
+        let query = format!(
+            r"
+            SELECT {RUNS_COLUMNS} FROM runs
+            WHERE status IN ('COMPLETED', 'FAILED', 'CANCELED')
+                AND id NOT IN (SELECT run_id FROM gc_log)
+            ORDER BY created_at DESC, id DESC
+            "
+        );
+        let rows = sqlx::query_as::<_, RunRow>(&query)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(RunRow::into_run).collect())
+    }
+
+    /// Remove a run's logs and record it in the GC log. With `dry_run` the
+    /// transaction is rolled back.
+    pub async fn prune_run(
+        &self,
+        pruned: &PrunedRun,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<PrunedRows> {
+        let run_id = pruned.run_id.as_ref();
+        let mut tx = self.pool.begin().await?;
+        let events = sqlx::query("DELETE FROM events WHERE run_id = $1 AND step_id IS NOT NULL")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let artifacts = sqlx::query("DELETE FROM artifacts WHERE run_id = $1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM transcript_entries WHERE run_id = $1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM agent_actions WHERE run_id = $1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE steps SET prompt_path = NULL, output_path = NULL WHERE run_id = $1")
+            .bind(run_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r"
+            INSERT INTO gc_log (run_id, reason, events_removed, artifacts_removed, bytes_freed, pruned_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (run_id) DO NOTHING
+            ",
+        )
+        .bind(run_id)
+        .bind(pruned.reason.as_str())
+        .bind(events as i64)
+        .bind(artifacts as i64)
+        .bind(pruned.bytes as i64)
+        .bind(now.timestamp_millis())
+        .execute(&mut *tx)
+        .await?;
+
+        if dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+        Ok(PrunedRows { events, artifacts })
+    }
+}
+
+impl StorageBackend for PgStorage {
+    fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    fn migrate_embedded(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(PgStorage::migrate_embedded(self))
+    }
+
+    fn insert_run<'a>(&'a self, run: &'a Run) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_run(self, run))
+    }
+
+    fn get_run<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<Run>> {
+        Box::pin(PgStorage::get_run(self, id))
+    }
+
+    fn list_runs<'a>(&'a self, workspace_root: Option<&'a str>) -> BoxFuture<'a, Result<Vec<Run>>> {
+        Box::pin(PgStorage::list_runs(self, workspace_root))
+    }
+
+    fn list_runs_page<'a>(&'a self, filter: &'a RunFilter) -> BoxFuture<'a, Result<RunPage>> {
+        Box::pin(PgStorage::list_runs_page(self, filter))
+    }
+
+    fn count_runs_with_status(&self, status: RunStatus) -> BoxFuture<'_, Result<usize>> {
+        Box::pin(PgStorage::count_runs_with_status(self, status))
+    }
+
+    fn count_running_runs_by_workspace(&self) -> BoxFuture<'_, Result<HashMap<String, usize>>> {
+        Box::pin(PgStorage::count_running_runs_by_workspace(self))
+    }
+
+    fn count_running_runs_for_workspace<'a>(
+        &'a self,
+        workspace_root: &'a str,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(PgStorage::count_running_runs_for_workspace(
+            self,
+            workspace_root,
+        ))
+    }
+
+    fn update_run_status<'a>(&'a self, id: &'a Id, status: RunStatus) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_run_status(self, id, status))
+    }
+
+    fn update_run_worktree<'a>(
+        &'a self,
+        id: &'a Id,
+        worktree: &'a RunWorktree,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_run_worktree(self, id, worktree))
+    }
+
+    fn update_run_plan_path<'a>(
+        &'a self,
+        id: &'a Id,
+        plan_path: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_run_plan_path(self, id, plan_path))
+    }
+
+    fn update_run_worktree_cleanup<'a>(
+        &'a self,
+        id: &'a Id,
+        status: &'a str,
+        cleaned_at: Option<i64>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_run_worktree_cleanup(
+            self, id, status, cleaned_at,
+        ))
+    }
+
+    fn update_run_priority<'a>(&'a self, id: &'a Id, priority: i32) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_run_priority(self, id, priority))
+    }
+
+    fn update_run_config<'a>(
+        &'a self,
+        id: &'a Id,
+        config_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_run_config(self, id, config_json))
+    }
+
+    fn update_review_status<'a>(
+        &'a self,
+        id: &'a Id,
+        status: ReviewStatus,
+        pr_url: Option<&'a str>,
+        merge_commit: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_review_status(
+            self,
+            id,
+            status,
+            pr_url,
+            merge_commit,
+        ))
+    }
+
+    fn insert_step<'a>(&'a self, step: &'a Step) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_step(self, step))
+    }
+
+    fn get_step<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<Step>> {
+        Box::pin(PgStorage::get_step(self, id))
+    }
+
+    fn list_steps<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Step>>> {
+        Box::pin(PgStorage::list_steps(self, run_id))
+    }
+
+    fn mark_step_started<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::mark_step_started(self, id))
+    }
+
+    fn update_step<'a>(
+        &'a self,
+        id: &'a Id,
+        status: StepStatus,
+        exit_code: Option<i32>,
+        output_path: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::update_step(
+            self,
+            id,
+            status,
+            exit_code,
+            output_path,
+        ))
+    }
+
+    fn set_step_session<'a>(
+        &'a self,
+        id: &'a Id,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::set_step_session(self, id, session_id))
+    }
+
+    fn append_event<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: Option<&'a Id>,
+        payload: &'a EventPayload,
+    ) -> BoxFuture<'a, Result<Event>> {
+        Box::pin(PgStorage::append_event(self, run_id, step_id, payload))
+    }
+
+    fn complete_run_atomically<'a>(
+        &'a self,
+        run_id: &'a Id,
+        event: &'a EventPayload,
+        status: RunStatus,
+    ) -> BoxFuture<'a, Result<Event>> {
+        Box::pin(PgStorage::complete_run_atomically(
+            self, run_id, event, status,
+        ))
+    }
+
+    fn list_events<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Event>>> {
+        Box::pin(PgStorage::list_events(self, run_id))
+    }
+
+    fn list_events_after<'a>(
+        &'a self,
+        run_id: &'a Id,
+        after_id: Option<&'a Id>,
+    ) -> BoxFuture<'a, Result<Vec<Event>>> {
+        Box::pin(PgStorage::list_events_after(self, run_id, after_id))
+    }
+
+    fn insert_artifact<'a>(&'a self, artifact: &'a Artifact) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_artifact(self, artifact))
+    }
+
+    fn list_artifacts<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<Vec<Artifact>>> {
+        Box::pin(PgStorage::list_artifacts(self, run_id))
+    }
+
+    fn delete_artifact<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::delete_artifact(self, id))
+    }
+
+    fn record_step_usage<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: &'a Id,
+        model: Option<&'a str>,
+        usage: &'a TokenUsage,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::record_step_usage(
+            self, run_id, step_id, model, usage,
+        ))
+    }
+
+    fn list_step_usage<'a>(
+        &'a self,
+        run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<(Id, TokenUsage)>>> {
+        Box::pin(PgStorage::list_step_usage(self, run_id))
+    }
+
+    fn get_run_usage<'a>(&'a self, run_id: &'a Id) -> BoxFuture<'a, Result<TokenUsage>> {
+        Box::pin(PgStorage::get_run_usage(self, run_id))
+    }
+
+    fn cost_since(&self, since_ms: i64) -> BoxFuture<'_, Result<f64>> {
+        Box::pin(PgStorage::cost_since(self, since_ms))
+    }
+
+    fn aggregate_usage<'a>(
+        &'a self,
+        group_by: UsageGroupBy,
+        workspace_root: Option<&'a str>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> BoxFuture<'a, Result<Vec<UsageAggregate>>> {
+        Box::pin(PgStorage::aggregate_usage(
+            self,
+            group_by,
+            workspace_root,
+            since,
+            until,
+        ))
+    }
+
+    fn append_transcript_entries<'a>(
+        &'a self,
+        run_id: &'a Id,
+        step_id: &'a Id,
+        entries: &'a [TranscriptEntry],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::append_transcript_entries(
+            self, run_id, step_id, entries,
+        ))
+    }
+
+    fn list_transcript_entries<'a>(
+        &'a self,
+        step_id: &'a Id,
+        tool_name: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<TranscriptEntry>>> {
+        Box::pin(PgStorage::list_transcript_entries(self, step_id, tool_name))
+    }
+
+    fn record_agent_actions<'a>(&'a self, actions: &'a [AgentAction]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::record_agent_actions(self, actions))
+    }
+
+    fn list_agent_actions<'a>(
+        &'a self,
+        run_id: &'a Id,
+        filter: &'a AgentActionFilter,
+    ) -> BoxFuture<'a, Result<Vec<AgentAction>>> {
+        Box::pin(PgStorage::list_agent_actions(self, run_id, filter))
+    }
+
+    fn insert_run_with_dependencies<'a>(
+        &'a self,
+        run: &'a Run,
+        dependencies: &'a [RunDependency],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_run_with_dependencies(
+            self,
+            run,
+            dependencies,
+        ))
+    }
+
+    fn list_run_dependencies<'a>(
+        &'a self,
+        run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<RunDependency>>> {
+        Box::pin(PgStorage::list_run_dependencies(self, run_id))
+    }
+
+    fn list_pending_run_dependencies(
+        &self,
+    ) -> BoxFuture<'_, Result<HashMap<Id, Vec<RunDependency>>>> {
+        Box::pin(PgStorage::list_pending_run_dependencies(self))
+    }
+
+    fn insert_child_run<'a>(
+        &'a self,
+        child: &'a Run,
+        link: &'a RunChild,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_child_run(self, child, link))
+    }
+
+    fn get_run_child<'a>(
+        &'a self,
+        child_run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Option<RunChild>>> {
+        Box::pin(PgStorage::get_run_child(self, child_run_id))
+    }
+
+    fn list_run_children<'a>(
+        &'a self,
+        parent_run_id: &'a Id,
+    ) -> BoxFuture<'a, Result<Vec<RunChild>>> {
+        Box::pin(PgStorage::list_run_children(self, parent_run_id))
+    }
+
+    fn mark_run_child_merged<'a>(&'a self, child_run_id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::mark_run_child_merged(self, child_run_id))
+    }
+
+    fn record_notification_delivery<'a>(
+        &'a self,
+        delivery: &'a NotificationDelivery,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::record_notification_delivery(self, delivery))
+    }
+
+    fn list_notification_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: u32,
+    ) -> BoxFuture<'_, Result<Vec<NotificationDelivery>>> {
+        Box::pin(PgStorage::list_notification_deliveries(self, status, limit))
+    }
+
+    fn insert_schedule<'a>(&'a self, schedule: &'a Schedule) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_schedule(self, schedule))
+    }
+
+    fn list_schedules<'a>(
+        &'a self,
+        workspace_root: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<Schedule>>> {
+        Box::pin(PgStorage::list_schedules(self, workspace_root))
+    }
+
+    fn list_due_schedules(&self, now: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Schedule>>> {
+        Box::pin(PgStorage::list_due_schedules(self, now))
+    }
+
+    fn delete_schedule<'a>(&'a self, id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::delete_schedule(self, id))
+    }
+
+    fn fire_schedule<'a>(
+        &'a self,
+        id: &'a Id,
+        run: Option<&'a Run>,
+        next_fire_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::fire_schedule(self, id, run, next_fire_at))
+    }
+
+    fn insert_worker<'a>(&'a self, worker: &'a Worker) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::insert_worker(self, worker))
+    }
+
+    fn list_workers(&self) -> BoxFuture<'_, Result<Vec<Worker>>> {
+        Box::pin(PgStorage::list_workers(self))
+    }
+
+    fn heartbeat_worker<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<Id>>> {
+        Box::pin(PgStorage::heartbeat_worker(
+            self, worker_id, now, expires_at,
+        ))
+    }
+
+    fn touch_worker<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::touch_worker(self, worker_id, now))
+    }
+
+    fn mark_workers_offline(&self, cutoff: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Id>>> {
+        Box::pin(PgStorage::mark_workers_offline(self, cutoff))
+    }
+
+    fn offer_step<'a>(
+        &'a self,
+        step_id: &'a Id,
+        run_id: &'a Id,
+        request_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::offer_step(self, step_id, run_id, request_json))
+    }
+
+    fn claim_lease<'a>(
+        &'a self,
+        worker_id: &'a Id,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Option<(Lease, String)>>> {
+        Box::pin(PgStorage::claim_lease(self, worker_id, expires_at))
+    }
+
+    fn complete_lease<'a>(
+        &'a self,
+        step_id: &'a Id,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+        result_json: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::complete_lease(
+            self,
+            step_id,
+            worker_id,
+            now,
+            result_json,
+        ))
+    }
+
+    fn held_lease_request<'a>(
+        &'a self,
+        step_id: &'a Id,
+        worker_id: &'a Id,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(PgStorage::held_lease_request(self, step_id, worker_id, now))
+    }
+
+    fn lease_result<'a>(&'a self, step_id: &'a Id) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(PgStorage::lease_result(self, step_id))
+    }
+
+    fn release_lease<'a>(&'a self, step_id: &'a Id) -> BoxFuture<'a, Result<()>> {
+        Box::pin(PgStorage::release_lease(self, step_id))
+    }
+
+    fn clear_leases(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(PgStorage::clear_leases(self))
+    }
+
+    fn expire_leases(&self, now: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<Lease>>> {
+        Box::pin(PgStorage::expire_leases(self, now))
+    }
+
+    fn list_gc_candidates(&self) -> BoxFuture<'_, Result<Vec<Run>>> {
+        Box::pin(PgStorage::list_gc_candidates(self))
+    }
+
+    fn prune_run<'a>(
+        &'a self,
+        pruned: &'a PrunedRun,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> BoxFuture<'a, Result<PrunedRows>> {
+        Box::pin(PgStorage::prune_run(self, pruned, now, dry_run))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::open_backend;
+    use crate::storage::testing::{backend_test, open, TestBackend};
+    use loop_core::events::{EventType, RunCompletedPayload, StepStartedPayload};
+    use loop_core::{ArtifactLocation, MergeStrategy, RunNameSource, StepPhase, WorktreeProvider};
+
+    fn run(workspace_root: &str) -> Run {
+        let now = Utc::now();
+        Run {
+            id: Id::new(),
+            name: "test-run".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Pending,
+            workspace_root: workspace_root.to_string(),
+            spec_path: format!("{workspace_root}/spec.md"),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: Some("{}".to_string()),
+            created_at: now,
+            updated_at: now,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 3,
+        }
+    }
+
+    fn step(run_id: &Id, phase: StepPhase, started: bool) -> Step {
+        Step {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            phase,
+            status: StepStatus::Queued,
+            attempt: 1,
+            started_at: started.then(Utc::now),
+            ended_at: None,
+            exit_code: None,
+            prompt_path: None,
+            output_path: None,
+            session_id: Some("session-1".to_string()),
+        }
+    }
+
+    backend_test!(backends_round_trip_runs_and_steps, open);
+    async fn backends_round_trip_runs_and_steps(tb: TestBackend) {
+        let backend = tb.storage;
+        let mut a = run("/workspace/a");
+        a.worktree = Some(RunWorktree {
+            base_branch: "main".to_string(),
+            run_branch: "run/test".to_string(),
+            merge_target_branch: None,
+            merge_strategy: MergeStrategy::Squash,
+            worktree_path: "../a.run-test".to_string(),
+            provider: WorktreeProvider::Git,
+        });
+        let b = run("/workspace/b");
+        backend.insert_run(&a).await.unwrap();
+        backend.insert_run(&b).await.unwrap();
+
+        let fetched = backend.get_run(&a.id).await.unwrap();
+        assert_eq!(fetched.priority, 3);
+        let worktree = fetched.worktree.unwrap();
+        assert_eq!(worktree.run_branch, "run/test");
+        assert_eq!(worktree.provider, WorktreeProvider::Git);
+        assert_eq!(fetched.config_json.as_deref(), Some("{}"));
+        assert_eq!(backend.list_runs(None).await.unwrap().len(), 2);
+        let in_b = backend.list_runs(Some("/workspace/b")).await.unwrap();
+        assert_eq!(in_b.len(), 1);
+        assert_eq!(in_b[0].id, b.id);
+        assert!(matches!(
+            backend.get_run(&Id::new()).await,
+            Err(StorageError::RunNotFound(_))
+        ));
+
+        let mut events = backend.bus().subscribe_events();
+        backend
+            .update_run_status(&a.id, RunStatus::Running)
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.get_run(&a.id).await.unwrap().status,
+            RunStatus::Running
+        );
+        let changed = events.try_recv().unwrap();
+        assert_eq!(changed.event_type, EventType::RunStatusChanged.as_str());
+
+        let queued = step(&a.id, StepPhase::Verification, false);
+        let planning = step(&a.id, StepPhase::Planning, true);
+        backend.insert_step(&queued).await.unwrap();
+        backend.insert_step(&planning).await.unwrap();
+        let ids: Vec<Id> = backend
+            .list_steps(&a.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|step| step.id)
+            .collect();
+        assert_eq!(ids, vec![planning.id.clone(), queued.id.clone()]);
+
+        backend.mark_step_started(&queued.id).await.unwrap();
+        backend
+            .update_step(
+                &queued.id,
+                StepStatus::Failed,
+                Some(2),
+                Some("/tmp/out.log"),
+            )
+            .await
+            .unwrap();
+        let finished = backend.get_step(&queued.id).await.unwrap();
+        assert_eq!(finished.status, StepStatus::Failed);
+        assert_eq!(finished.exit_code, Some(2));
+        assert_eq!(finished.output_path.as_deref(), Some("/tmp/out.log"));
+        assert_eq!(finished.session_id.as_deref(), Some("session-1"));
+        assert!(finished.started_at.is_some() && finished.ended_at.is_some());
+        assert!(matches!(
+            backend.get_step(&Id::new()).await,
+            Err(StorageError::StepNotFound(_))
+        ));
+        assert!(matches!(
+            backend.mark_step_started(&Id::new()).await,
+            Err(StorageError::StepNotFound(_))
+        ));
+    }
+
+    backend_test!(backends_order_events_and_resume_after_id, open);
+    async fn backends_order_events_and_resume_after_id(tb: TestBackend) {
+        let backend = tb.storage;
+        let r = run("/workspace");
+        backend.insert_run(&r).await.unwrap();
+        let s = step(&r.id, StepPhase::Implementation, true);
+        backend.insert_step(&s).await.unwrap();
+
+        let mut bus = backend.bus().subscribe_events();
+        let mut appended = Vec::new();
+        for attempt in 1..=3 {
+            let payload = EventPayload::StepStarted(StepStartedPayload {
+                step_id: s.id.clone(),
+                phase: "implementation".to_string(),
+                attempt,
+            });
+            let event = backend
+                .append_event(&r.id, Some(&s.id), &payload)
+                .await
+                .unwrap();
+            assert_eq!(bus.try_recv().unwrap().id, event.id);
+            appended.push(event.id);
+        }
+
+        let listed: Vec<Id> = backend
+            .list_events(&r.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| event.id)
+            .collect();
+        assert_eq!(listed, appended);
+        let after = backend
+            .list_events_after(&r.id, Some(&appended[1]))
+            .await
+            .unwrap();
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].id, appended[2]);
+        assert_eq!(after[0].step_id.as_ref(), Some(&s.id));
+        let replay = backend
+            .list_events_after(&r.id, Some(&Id::new()))
+            .await
+            .unwrap();
+        assert_eq!(replay.len(), 3);
+    }
+
+    backend_test!(backends_complete_runs_atomically_and_store_artifacts, open);
+    async fn backends_complete_runs_atomically_and_store_artifacts(tb: TestBackend) {
+        let backend = tb.storage;
+        let r = run("/workspace");
+        backend.insert_run(&r).await.unwrap();
+
+        let payload = EventPayload::RunCompleted(RunCompletedPayload {
+            run_id: r.id.clone(),
+            mode: "exit_on_complete".to_string(),
+        });
+        let mut bus = backend.bus().subscribe_events();
+        let event = backend
+            .complete_run_atomically(&r.id, &payload, RunStatus::Completed)
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.get_run(&r.id).await.unwrap().status,
+            RunStatus::Completed
+        );
+        assert_eq!(bus.try_recv().unwrap().id, event.id);
+        assert_eq!(
+            bus.try_recv().unwrap().event_type,
+            EventType::RunStatusChanged.as_str()
+        );
+        assert_eq!(backend.list_events(&r.id).await.unwrap().len(), 1);
+
+        // An unknown run leaves no event behind.
+        let missing = Id::new();
+        assert!(matches!(
+            backend
+                .complete_run_atomically(&missing, &payload, RunStatus::Failed)
+                .await,
+            Err(StorageError::RunNotFound(_))
+        ));
+        assert!(backend.list_events(&missing).await.unwrap().is_empty());
+
+        for kind in ["summary", "plan"] {
+            backend
+                .insert_artifact(&Artifact {
+                    id: Id::new(),
+                    run_id: r.id.clone(),
+                    kind: kind.to_string(),
+                    location: ArtifactLocation::Global,
+                    path: format!("/logs/{kind}.md"),
+                    checksum: None,
+                })
+                .await
+                .unwrap();
+        }
+        let kinds: Vec<String> = backend
+            .list_artifacts(&r.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|artifact| artifact.kind)
+            .collect();
+        assert_eq!(kinds, vec!["plan", "summary"]);
+    }
+
+    #[tokio::test]
+    async fn open_backend_rejects_unknown_schemes() {
+        assert!(matches!(
+            open_backend("mysql://localhost/loopd", 1).await,
+            Err(StorageError::UnsupportedBackend(_))
+        ));
+    }
+}
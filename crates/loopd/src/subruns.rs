@@ -0,0 +1,794 @@
+//! Parallel child runs fanned out from a plan task graph.
+//!
+//! With `parallel_tasks=true`, a run whose plan gives every unfinished task an
+//! ID (see [`loop_core::plan::TaskGraph`]) does not implement tasks itself.
+//! It creates one child run per task once the task's dependencies are merged,
+//! waits for the children, and merges each child branch into its own run
+//! branch in dependency order with [`git::merge_branch`].
+//!
+//! Children are ordinary runs claimed by the scheduler; the `run_children`
+//! table links them to the parent. Supervision is driven entirely by that
+//! table and run statuses, so a resumed parent picks up where it left off.
+//!
+//! Each child works from its own copy of the plan ([`CHILD_PLAN_FILE`]) inside
+//! its own worktree, so nothing a child does to the plan dirties the parent's
+//! working copy before the merge.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+use loop_core::events::EventType;
+use loop_core::plan::{PlanTask, TaskGraph};
+use loop_core::{Config, Id, MergeStrategy, ReviewStatus, Run, RunStatus, RunWorktree, TaskState};
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::git;
+use crate::scheduler::{Scheduler, SchedulerError};
+use crate::storage::{RunChild, StorageBackend, StorageError};
+use crate::worktree;
+
+/// How often the parent re-checks its children when no status change arrives.
+pub const CHILD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Child's copy of the parent plan, relative to the child worktree.
+/// Excluded from git so it never ends up in the child's commit.
+pub const CHILD_PLAN_FILE: &str = ".loop/subrun-plan.md";
+
+#[derive(Debug, Error)]
+pub enum SubrunError {
+    #[error("storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("scheduler error: {0}")]
+    Scheduler(#[from] SchedulerError),
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("git error: {0}")]
+    Git(#[from] git::GitError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SubrunError>;
+
+/// How supervision of a parent run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupervisionOutcome {
+    /// Every unfinished task was implemented by a child and merged.
+    Completed { merged: usize },
+    /// A child failed or could not be merged; the reason is for `RUN_FAILED`.
+    Failed(String),
+    /// The parent was canceled.
+    Canceled,
+}
+
+/// Whether and how a run hands its plan to child runs.
+#[derive(Debug)]
+pub enum FanOut {
+    /// Fan the plan's unfinished tasks out along this graph.
+    Graph(TaskGraph),
+    /// Not applicable: `parallel_tasks` is off, or there is no plan to fan out.
+    Off,
+    /// `parallel_tasks` is on, but the run implements its tasks serially.
+    /// `cause` is a stable tag for `PARALLEL_TASKS_SERIAL`.
+    Serial { cause: &'static str, detail: String },
+}
+
+/// Branch for a child run: `<run branch prefix><parent run id>/<task>`.
+///
+/// The parent's own branch is named after the run, not its ID, so the child
+/// refs never nest under it (git cannot store `run/x/task` next to `run/x`).
+pub fn child_branch(prefix: &str, parent_run_id: &Id, task_id: &str) -> String {
+    format!("{prefix}{parent_run_id}/{task_id}")
+}
+
+/// Load the plan's task graph if this run should fan out into child runs.
+///
+/// The run falls back to serial execution when some unfinished task has no ID
+/// or the graph is invalid.
+pub fn fan_out_graph(config: &Config, plan_file: &Path) -> FanOut {
+    if !config.parallel_tasks {
+        return FanOut::Off;
+    }
+    let Ok(content) = std::fs::read_to_string(plan_file) else {
+        return FanOut::Off;
+    };
+    match TaskGraph::from_content(&content) {
+        Ok(graph) if graph.is_parallelizable() => FanOut::Graph(graph),
+        Ok(graph) if graph.unfinished().next().is_none() => FanOut::Off,
+        Ok(_) => FanOut::Serial {
+            cause: "untagged_tasks",
+            detail: "plan has unfinished tasks without ids".to_string(),
+        },
+        Err(e) => FanOut::Serial {
+            cause: "invalid_graph",
+            detail: format!("invalid plan task graph: {e}"),
+        },
+    }
+}
+
+/// Whether the scheduler can run children next to their waiting parent.
+///
+/// The parent holds a run slot while it supervises, so with a single global or
+/// per-workspace slot the children could never be claimed.
+pub fn has_room_for_children(scheduler: &Scheduler) -> bool {
+    scheduler.max_concurrent() >= 2
+        && scheduler
+            .max_runs_per_workspace()
+            .is_none_or(|cap| cap >= 2)
+}
+
+/// Build a child run for one task, branched from the parent's run branch.
+///
+/// The child's plan path is [`CHILD_PLAN_FILE`] in its own worktree, seeded by
+/// [`seed_child_plan`] once the worktree exists. It inherits the parent's
+/// config with planning, fan-out and merge-to-target turned off.
+pub fn build_child_run(
+    parent: &Run,
+    parent_worktree: &RunWorktree,
+    task: &PlanTask,
+    config: &Config,
+) -> Result<(Run, RunChild)> {
+    let task_id = task.id.clone().unwrap_or_default();
+    let workspace_root = Path::new(&parent.workspace_root);
+    let run_branch = child_branch(&config.run_branch_prefix, &parent.id, &task_id);
+    let expanded =
+        git::expand_worktree_template(&config.worktree_path_template, workspace_root, &run_branch);
+    let worktree_path = git::resolve_worktree_path(&expanded, workspace_root);
+    let plan_path = worktree_path.join(CHILD_PLAN_FILE);
+
+    let mut child_config = config.clone();
+    child_config.parallel_tasks = false;
+    child_config.planner = false;
+    child_config.merge_target_branch = None;
+
+    let now = Utc::now();
+    let child = Run {
+        id: Id::new(),
+        name: format!("{}-{task_id}", parent.name),
+        name_source: parent.name_source,
+        status: RunStatus::Pending,
+        workspace_root: parent.workspace_root.clone(),
+        spec_path: parent.spec_path.clone(),
+        plan_path: Some(plan_path.to_string_lossy().to_string()),
+        worktree: Some(RunWorktree {
+            base_branch: parent_worktree.run_branch.clone(),
+            run_branch,
+            merge_target_branch: None,
+            merge_strategy: MergeStrategy::None,
+            worktree_path: worktree_path.to_string_lossy().to_string(),
+            provider: parent_worktree.provider,
+        }),
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: Some(serde_json::to_string(&child_config)?),
+        created_at: now,
+        updated_at: now,
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: parent.priority,
+    };
+    let link = RunChild {
+        parent_run_id: parent.id.clone(),
+        child_run_id: child.id.clone(),
+        task_id,
+        task_label: task.label.clone(),
+        depends_on: task.depends_on.clone(),
+        merged_at: None,
+        created_at: now,
+    };
+    Ok((child, link))
+}
+
+/// Copy the parent's working plan into a child's worktree.
+///
+/// The parent's plan may have uncommitted edits, so the child's branch does
+/// not necessarily contain it. An existing copy is kept, so a resumed child
+/// keeps its own state.
+pub async fn seed_child_plan(
+    storage: &dyn StorageBackend,
+    link: &RunChild,
+    child: &Run,
+) -> Result<()> {
+    let (Some(child_plan), Some(child_worktree)) = (&child.plan_path, &child.worktree) else {
+        return Ok(());
+    };
+    let child_plan = Path::new(child_plan);
+    git::exclude_locally(Path::new(&child_worktree.worktree_path), CHILD_PLAN_FILE)?;
+    if child_plan.exists() {
+        return Ok(());
+    }
+
+    let parent = storage.get_run(&link.parent_run_id).await?;
+    let Some(parent_plan) = parent.plan_path.as_deref() else {
+        return Ok(());
+    };
+    let parent_plan = match parent.worktree.as_ref() {
+        Some(wt) => {
+            crate::remap_to_worktree(parent_plan, &parent.workspace_root, &wt.worktree_path)
+        }
+        None => parent_plan.to_string(),
+    };
+    if let Some(dir) = child_plan.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::copy(&parent_plan, child_plan)?;
+    Ok(())
+}
+
+/// The parent run being supervised and where its branch is checked out.
+#[derive(Debug)]
+pub struct Parent<'a> {
+    pub run: &'a Run,
+    pub worktree: &'a RunWorktree,
+    pub working_dir: &'a Path,
+    pub config: &'a Config,
+}
+
+/// Spawn, wait for and merge child runs until the plan is done or a child fails.
+pub async fn supervise(
+    scheduler: &Scheduler,
+    storage: &dyn StorageBackend,
+    parent: &Parent<'_>,
+    graph: &TaskGraph,
+    cancel_token: &CancellationToken,
+) -> Result<SupervisionOutcome> {
+    let mut events = storage.bus().subscribe_events();
+    let workspace_root = Path::new(&parent.run.workspace_root);
+
+    loop {
+        let children = storage.list_run_children(&parent.run.id).await?;
+        let mut child_runs = Vec::with_capacity(children.len());
+        for link in &children {
+            child_runs.push((link, storage.get_run(&link.child_run_id).await?));
+        }
+
+        let parent_status = storage.get_run(&parent.run.id).await?.status;
+        if cancel_token.is_cancelled() || parent_status == RunStatus::Canceled {
+            cancel_unfinished(scheduler, &child_runs).await;
+            return Ok(if parent_status == RunStatus::Canceled {
+                SupervisionOutcome::Canceled
+            } else {
+                SupervisionOutcome::Failed("subruns_interrupted".to_string())
+            });
+        }
+
+        if let Some((link, child)) = child_runs
+            .iter()
+            .find(|(_, child)| matches!(child.status, RunStatus::Failed | RunStatus::Canceled))
+        {
+            cancel_unfinished(scheduler, &child_runs).await;
+            return Ok(SupervisionOutcome::Failed(format!(
+                "subrun_failed:{}:{}",
+                link.task_id,
+                child.status.as_str()
+            )));
+        }
+
+        // Merge completed children whose dependencies are already merged.
+        let mut merged: HashSet<String> = children
+            .iter()
+            .filter(|link| link.merged_at.is_some())
+            .map(|link| link.task_id.clone())
+            .collect();
+        let order = graph.dependency_order().unwrap_or_default();
+        for task in order {
+            let Some(task_id) = task.id.as_ref() else {
+                continue;
+            };
+            let Some((link, child)) = child_runs.iter().find(|(link, _)| &link.task_id == task_id)
+            else {
+                continue;
+            };
+            let deps_merged = task
+                .depends_on
+                .iter()
+                .all(|dep| merged.contains(dep) || graph.get(dep).is_some_and(|t| !is_open(t)));
+            if link.merged_at.is_some() || child.status != RunStatus::Completed || !deps_merged {
+                continue;
+            }
+            let Some(child_worktree) = child.worktree.as_ref() else {
+                continue;
+            };
+
+            if let Err(e) = git::merge_branch(parent.working_dir, &child_worktree.run_branch) {
+                cancel_unfinished(scheduler, &child_runs).await;
+                return Ok(SupervisionOutcome::Failed(format!(
+                    "subrun_merge_failed:{task_id}:{e}"
+                )));
+            }
+            let merge_commit = git::get_head_commit(parent.working_dir).ok();
+            storage.mark_run_child_merged(&child.id).await?;
+            storage
+                .update_review_status(
+                    &child.id,
+                    ReviewStatus::Merged,
+                    None,
+                    merge_commit.as_deref(),
+                )
+                .await?;
+            info!(
+                run_id = %parent.run.id,
+                child_run_id = %child.id,
+                task = %task_id,
+                branch = %child_worktree.run_branch,
+                "merged child run"
+            );
+            if parent.config.worktree_cleanup {
+                remove_child_worktree(
+                    storage,
+                    workspace_root,
+                    child,
+                    child_worktree,
+                    parent.config,
+                )
+                .await;
+            }
+            merged.insert(task_id.clone());
+        }
+
+        let open: Vec<&PlanTask> = graph.tasks().iter().filter(|t| is_open(t)).collect();
+        if open
+            .iter()
+            .all(|task| task.id.as_ref().is_some_and(|id| merged.contains(id)))
+        {
+            return Ok(SupervisionOutcome::Completed {
+                merged: merged.len(),
+            });
+        }
+
+        // Spawn children for tasks whose dependencies are merged.
+        let spawned: HashSet<&str> = children.iter().map(|link| link.task_id.as_str()).collect();
+        for task in graph.ready(&merged) {
+            let task_id = task.id.as_deref().unwrap_or_default();
+            if spawned.contains(task_id) {
+                continue;
+            }
+            let (child, link) = build_child_run(parent.run, parent.worktree, task, parent.config)?;
+            storage.insert_child_run(&child, &link).await?;
+            info!(
+                run_id = %parent.run.id,
+                child_run_id = %child.id,
+                task = %task_id,
+                "spawned child run"
+            );
+        }
+
+        // Wait for a child status change, cancellation, or the poll interval.
+        let child_ids: HashSet<Id> = storage
+            .list_run_children(&parent.run.id)
+            .await?
+            .into_iter()
+            .map(|link| link.child_run_id)
+            .collect();
+        let status_change = async {
+            loop {
+                match events.recv().await {
+                    Ok(event)
+                        if event.event_type == EventType::RunStatusChanged.as_str()
+                            && child_ids.contains(&event.run_id) =>
+                    {
+                        return;
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => return,
+                    Err(RecvError::Closed) => std::future::pending::<()>().await,
+                }
+            }
+        };
+        tokio::select! {
+            () = cancel_token.cancelled() => {}
+            () = status_change => {}
+            () = tokio::time::sleep(CHILD_POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// Tasks the parent still has to deliver (pending or blocked in the plan).
+fn is_open(task: &PlanTask) -> bool {
+    task.state != TaskState::Done
+}
+
+async fn cancel_unfinished(scheduler: &Scheduler, child_runs: &[(&RunChild, Run)]) {
+    for (link, child) in child_runs {
+        if matches!(
+            child.status,
+            RunStatus::Pending | RunStatus::Running | RunStatus::Paused
+        ) {
+            if let Err(e) = scheduler.cancel_run(&child.id).await {
+                warn!(
+                    child_run_id = %child.id,
+                    task = %link.task_id,
+                    error = %e,
+                    "failed to cancel child run"
+                );
+            }
+        }
+    }
+}
+
+/// Remove a merged child's worktree; failures are logged, not fatal.
+async fn remove_child_worktree(
+    storage: &dyn StorageBackend,
+    workspace_root: &Path,
+    child: &Run,
+    child_worktree: &RunWorktree,
+    config: &Config,
+) {
+    match worktree::cleanup(workspace_root, child_worktree, config) {
+        Ok(()) => {
+            let cleaned_at = Utc::now().timestamp_millis();
+            if let Err(e) = storage
+                .update_run_worktree_cleanup(&child.id, "cleaned", Some(cleaned_at))
+                .await
+            {
+                warn!(child_run_id = %child.id, error = %e, "failed to record worktree cleanup");
+            }
+        }
+        Err(e) => warn!(
+            child_run_id = %child.id,
+            worktree_path = %child_worktree.worktree_path,
+            error = %e,
+            "child worktree cleanup failed (non-fatal)"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+    use loop_core::{RunNameSource, WorktreeProvider};
+    use std::process::Command;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    const PLAN: &str =
+        "# Plan\n\n- [ ] [types] Add types\n- [ ] [api] Add API (depends-on: types)\n";
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// A repository checked out on the parent run branch.
+    fn setup_parent_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), &["init", "-b", "main"]);
+        git(dir.path(), &["config", "user.email", "test@test.com"]);
+        git(dir.path(), &["config", "user.name", "Test"]);
+        std::fs::write(dir.path().join("README.md"), "# Test").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-m", "Initial commit"]);
+        git(dir.path(), &["checkout", "-b", "run/parent"]);
+        dir
+    }
+
+    fn parent_worktree(dir: &Path) -> RunWorktree {
+        RunWorktree {
+            base_branch: "main".to_string(),
+            run_branch: "run/parent".to_string(),
+            merge_target_branch: Some("main".to_string()),
+            merge_strategy: MergeStrategy::Squash,
+            worktree_path: dir.to_string_lossy().to_string(),
+            provider: WorktreeProvider::Git,
+        }
+    }
+
+    fn parent_run(dir: &Path) -> Run {
+        let now = Utc::now();
+        Run {
+            id: Id::new(),
+            name: "parent".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Running,
+            workspace_root: dir.to_string_lossy().to_string(),
+            spec_path: dir.join("spec.md").to_string_lossy().to_string(),
+            plan_path: Some(dir.join("plan.md").to_string_lossy().to_string()),
+            worktree: Some(parent_worktree(dir)),
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: now,
+            updated_at: now,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        }
+    }
+
+    fn parallel_config() -> Config {
+        Config {
+            parallel_tasks: true,
+            planner: true,
+            worktree_cleanup: false,
+            merge_target_branch: Some("main".to_string()),
+            ..Config::default()
+        }
+    }
+
+    /// Wait for the child run implementing `task_id` to be spawned.
+    async fn wait_for_child(storage: &dyn StorageBackend, parent_id: &Id, task_id: &str) -> Run {
+        for _ in 0..200 {
+            let children = storage.list_run_children(parent_id).await.unwrap();
+            if let Some(link) = children.iter().find(|link| link.task_id == task_id) {
+                return storage.get_run(&link.child_run_id).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!("child run for {task_id} was not spawned");
+    }
+
+    /// Commit a file on the child's branch the way the child's agent would.
+    fn commit_on_child_branch(repo: &Path, child: &Run, file: &str) {
+        let branch = &child.worktree.as_ref().unwrap().run_branch;
+        let checkout = repo.join(format!(".child-{file}"));
+        let checkout_str = checkout.to_string_lossy().to_string();
+        git(
+            repo,
+            &["worktree", "add", "-b", branch, &checkout_str, "run/parent"],
+        );
+        std::fs::write(checkout.join(file), file).unwrap();
+        git(&checkout, &["add", file]);
+        git(&checkout, &["commit", "-m", file]);
+        git(repo, &["worktree", "remove", &checkout_str]);
+    }
+
+    async fn setup_storage(dir: &Path) -> (Arc<dyn StorageBackend>, Scheduler) {
+        let storage = Storage::new(&dir.join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+            .await
+            .unwrap();
+        storage.migrate_embedded().await.unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(storage);
+        let scheduler = Scheduler::new(Arc::clone(&storage), 3);
+        (storage, scheduler)
+    }
+
+    #[test]
+    fn child_branch_nests_under_the_parent_run_id() {
+        let parent = Id::from_string("0190a1b2-parent");
+        assert_eq!(
+            child_branch("run/", &parent, "storage"),
+            "run/0190a1b2-parent/storage"
+        );
+    }
+
+    #[test]
+    fn build_child_run_branches_from_parent_and_disables_fan_out() {
+        let dir = TempDir::new().unwrap();
+        let parent = parent_run(dir.path());
+        let worktree = parent_worktree(dir.path());
+        let graph = TaskGraph::from_content(PLAN).unwrap();
+
+        let (child, link) = build_child_run(
+            &parent,
+            &worktree,
+            graph.get("api").unwrap(),
+            &parallel_config(),
+        )
+        .unwrap();
+
+        assert_eq!(child.name, "parent-api");
+        assert_eq!(child.status, RunStatus::Pending);
+        let child_worktree = child.worktree.as_ref().unwrap();
+        assert_eq!(child_worktree.base_branch, "run/parent");
+        assert_eq!(child_worktree.run_branch, format!("run/{}/api", parent.id));
+        assert_eq!(child_worktree.merge_strategy, MergeStrategy::None);
+        assert!(child_worktree.merge_target_branch.is_none());
+        assert_ne!(child_worktree.worktree_path, worktree.worktree_path);
+        let child_plan = Path::new(&child_worktree.worktree_path).join(CHILD_PLAN_FILE);
+        assert_eq!(child.plan_path.as_deref(), child_plan.to_str());
+
+        let child_config: Config =
+            serde_json::from_str(child.config_json.as_deref().unwrap()).unwrap();
+        assert!(!child_config.parallel_tasks);
+        assert!(!child_config.planner);
+        assert!(child_config.merge_target_branch.is_none());
+
+        assert_eq!(link.parent_run_id, parent.id);
+        assert_eq!(link.child_run_id, child.id);
+        assert_eq!(link.task_id, "api");
+        assert_eq!(link.depends_on, vec!["types".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn seed_child_plan_copies_parent_plan_into_child_worktree() {
+        let repo = setup_parent_repo();
+        let (storage, _scheduler) = setup_storage(repo.path()).await;
+        let parent = parent_run(repo.path());
+        storage.insert_run(&parent).await.unwrap();
+        // The parent's plan is not committed, so the child branch lacks it.
+        std::fs::write(repo.path().join("plan.md"), PLAN).unwrap();
+        let graph = TaskGraph::from_content(PLAN).unwrap();
+        let config = Config {
+            worktree_path_template: ".worktrees/{{ run_branch | sanitize }}".to_string(),
+            ..parallel_config()
+        };
+        let (child, link) = build_child_run(
+            &parent,
+            &parent_worktree(repo.path()),
+            graph.get("types").unwrap(),
+            &config,
+        )
+        .unwrap();
+        storage.insert_child_run(&child, &link).await.unwrap();
+        let child_worktree = child.worktree.as_ref().unwrap();
+        git(
+            repo.path(),
+            &[
+                "worktree",
+                "add",
+                "-b",
+                &child_worktree.run_branch,
+                &child_worktree.worktree_path,
+                "run/parent",
+            ],
+        );
+
+        seed_child_plan(&*storage, &link, &child).await.unwrap();
+
+        let child_plan = child.plan_path.as_deref().unwrap();
+        assert_eq!(std::fs::read_to_string(child_plan).unwrap(), PLAN);
+        let status = Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(&child_worktree.worktree_path)
+            .output()
+            .unwrap();
+        assert!(status.stdout.is_empty(), "child plan copy must be ignored");
+
+        // A resumed child keeps its own copy.
+        std::fs::write(child_plan, "edited").unwrap();
+        seed_child_plan(&*storage, &link, &child).await.unwrap();
+        assert_eq!(std::fs::read_to_string(child_plan).unwrap(), "edited");
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("plan.md")).unwrap(),
+            PLAN
+        );
+    }
+
+    #[test]
+    fn fan_out_graph_requires_parallel_tasks_and_task_ids() {
+        let dir = TempDir::new().unwrap();
+        let plan_file = dir.path().join("plan.md");
+        std::fs::write(&plan_file, PLAN).unwrap();
+
+        assert!(matches!(
+            fan_out_graph(&parallel_config(), &plan_file),
+            FanOut::Graph(_)
+        ));
+        assert!(matches!(
+            fan_out_graph(&Config::default(), &plan_file),
+            FanOut::Off
+        ));
+
+        std::fs::write(&plan_file, format!("{PLAN}- [ ] Untagged task\n")).unwrap();
+        assert!(matches!(
+            fan_out_graph(&parallel_config(), &plan_file),
+            FanOut::Serial {
+                cause: "untagged_tasks",
+                ..
+            }
+        ));
+
+        std::fs::write(&plan_file, "- [x] [types] Add types\n").unwrap();
+        assert!(matches!(
+            fan_out_graph(&parallel_config(), &plan_file),
+            FanOut::Off
+        ));
+    }
+
+    #[tokio::test]
+    async fn supervise_merges_children_in_dependency_order() {
+        let repo = setup_parent_repo();
+        let (storage, scheduler) = setup_storage(repo.path()).await;
+        let parent = parent_run(repo.path());
+        storage.insert_run(&parent).await.unwrap();
+        let worktree = parent_worktree(repo.path());
+        let config = parallel_config();
+        let graph = TaskGraph::from_content(PLAN).unwrap();
+        let cancel_token = CancellationToken::new();
+        let supervised = Parent {
+            run: &parent,
+            worktree: &worktree,
+            working_dir: repo.path(),
+            config: &config,
+        };
+
+        let drive_children = async {
+            let types = wait_for_child(&*storage, &parent.id, "types").await;
+            // `api` depends on `types`, so it waits until `types` is merged.
+            assert_eq!(
+                storage.list_run_children(&parent.id).await.unwrap().len(),
+                1
+            );
+            commit_on_child_branch(repo.path(), &types, "types.rs");
+            storage
+                .update_run_status(&types.id, RunStatus::Completed)
+                .await
+                .unwrap();
+
+            let api = wait_for_child(&*storage, &parent.id, "api").await;
+            assert!(repo.path().join("types.rs").exists());
+            commit_on_child_branch(repo.path(), &api, "api.rs");
+            storage
+                .update_run_status(&api.id, RunStatus::Completed)
+                .await
+                .unwrap();
+        };
+
+        let (outcome, ()) = tokio::join!(
+            supervise(&scheduler, &*storage, &supervised, &graph, &cancel_token),
+            drive_children
+        );
+
+        assert_eq!(
+            outcome.unwrap(),
+            SupervisionOutcome::Completed { merged: 2 }
+        );
+        assert!(repo.path().join("api.rs").exists());
+        let children = storage.list_run_children(&parent.id).await.unwrap();
+        assert!(children.iter().all(|link| link.merged_at.is_some()));
+        for link in children {
+            let child = storage.get_run(&link.child_run_id).await.unwrap();
+            assert_eq!(child.review_status, ReviewStatus::Merged);
+        }
+    }
+
+    #[tokio::test]
+    async fn supervise_fails_when_a_child_fails() {
+        let repo = setup_parent_repo();
+        let (storage, scheduler) = setup_storage(repo.path()).await;
+        let parent = parent_run(repo.path());
+        storage.insert_run(&parent).await.unwrap();
+        let worktree = parent_worktree(repo.path());
+        let config = parallel_config();
+        let graph = TaskGraph::from_content(PLAN).unwrap();
+        let cancel_token = CancellationToken::new();
+        let supervised = Parent {
+            run: &parent,
+            worktree: &worktree,
+            working_dir: repo.path(),
+            config: &config,
+        };
+
+        let fail_child = async {
+            let types = wait_for_child(&*storage, &parent.id, "types").await;
+            storage
+                .update_run_status(&types.id, RunStatus::Failed)
+                .await
+                .unwrap();
+        };
+
+        let (outcome, ()) = tokio::join!(
+            supervise(&scheduler, &*storage, &supervised, &graph, &cancel_token),
+            fail_child
+        );
+
+        match outcome.unwrap() {
+            SupervisionOutcome::Failed(reason) => {
+                assert!(reason.starts_with("subrun_failed:types:"), "{reason}");
+            }
+            other => panic!("expected failure, got {other:?}"),
+        }
+        assert_eq!(
+            storage.list_run_children(&parent.id).await.unwrap().len(),
+            1
+        );
+    }
+}
@@ -7,16 +7,31 @@
 //! - Signal to scheduler when verification fails (requeue implementation)
 
 use chrono::Utc;
-use loop_core::{Config, Step};
+use loop_core::{
+    Config, ResourceLimitHit, ResourceLimitKind, ResourceLimitsConfig, Step, StepPhase,
+};
+use serde::{Deserialize, Serialize};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
+use crate::resources::{exit_signal, ResourceGuard};
+use crate::sandbox::{SandboxConfig, SandboxError, SandboxedProcess};
+use crate::workers::{StepDispatch, StepReport};
+
+/// How long to wait for output after the command exits.
+///
+/// Background processes that inherited the pipes can keep them open.
+const OUTPUT_CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Error)]
 pub enum VerifierError {
     #[error("io error: {0}")]
@@ -27,12 +42,16 @@ pub enum VerifierError {
     Timeout(u32),
     #[error("no verification commands configured")]
     NoCommands,
+    #[error("sandbox error: {0}")]
+    Sandbox(#[from] SandboxError),
+    #[error("worker error: {0}")]
+    Worker(String),
 }
 
 pub type Result<T> = std::result::Result<T, VerifierError>;
 
 /// Result of verification execution.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationResult {
     /// Whether all verification commands passed.
     pub passed: bool,
@@ -45,13 +64,13 @@ pub struct VerificationResult {
 }
 
 /// Result of a single verification command.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandResult {
     /// The command that was executed.
     pub cmd: String,
     /// Exit code from the command.
     pub exit_code: i32,
-    /// Whether this command passed (exit code 0).
+    /// Whether this command passed (exit code 0 and no resource limit hit).
     pub passed: bool,
     /// Duration in milliseconds.
     pub duration_ms: u64,
@@ -59,6 +78,10 @@ pub struct CommandResult {
     pub stdout: String,
     /// Stderr output.
     pub stderr: String,
+    /// Output line reporting access the sandbox denied (failed commands only).
+    pub sandbox_violation: Option<String>,
+    /// Resource limit the command ran into, if that is why it failed.
+    pub limit_exceeded: Option<ResourceLimitHit>,
 }
 
 /// Verifier configuration.
@@ -68,6 +91,10 @@ pub struct VerifierConfig {
     pub verify_cmds: Vec<String>,
     /// Timeout per command in seconds (0 = no timeout).
     pub timeout_sec: u32,
+    /// Process sandbox for the commands.
+    pub sandbox: SandboxConfig,
+    /// Resource limits; the verification phase's limits apply to each command.
+    pub resource_limits: ResourceLimitsConfig,
 }
 
 impl VerifierConfig {
@@ -76,6 +103,8 @@ impl VerifierConfig {
         Self {
             verify_cmds: config.verify_cmds.clone(),
             timeout_sec: config.verify_timeout_sec,
+            sandbox: SandboxConfig::from_config(config),
+            resource_limits: config.resource_limits.clone(),
         }
     }
 }
@@ -84,12 +113,24 @@ impl VerifierConfig {
 #[derive(Debug)]
 pub struct Verifier {
     config: VerifierConfig,
+    /// Hands verification to workers instead of running it (distributed mode).
+    dispatch: Option<Arc<StepDispatch>>,
 }
 
 impl Verifier {
     /// Create a new verifier with the given configuration.
     pub fn new(config: VerifierConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            dispatch: None,
+        }
+    }
+
+    /// Run verification on workers through `dispatch` rather than in-process.
+    #[must_use]
+    pub fn with_dispatch(mut self, dispatch: Arc<StepDispatch>) -> Self {
+        self.dispatch = Some(dispatch);
+        self
     }
 
     /// Create a verifier from loop-core Config.
@@ -147,6 +188,18 @@ impl Verifier {
                 runner_notes_path: None,
             });
         }
+        if let Some(dispatch) = &self.dispatch {
+            return match dispatch
+                .dispatch(&dispatch.request(step, run_dir, working_dir))
+                .await
+            {
+                Ok(StepReport::Verification { outcome }) => outcome.map_err(VerifierError::Worker),
+                Ok(StepReport::Agent(_)) => Err(VerifierError::Worker(
+                    "worker sent an agent report for a verification step".to_string(),
+                )),
+                Err(e) => Err(VerifierError::Worker(e.to_string())),
+            };
+        }
 
         info!(
             step_id = %step.id,
@@ -159,7 +212,7 @@ impl Verifier {
         let mut all_passed = true;
 
         for cmd in &self.config.verify_cmds {
-            let result = self.execute_command(cmd, working_dir).await?;
+            let result = self.execute_command(cmd, run_dir, working_dir).await?;
 
             if !result.passed {
                 all_passed = false;
@@ -197,7 +250,12 @@ impl Verifier {
     }
 
     /// Execute a single verification command.
-    async fn execute_command(&self, cmd: &str, working_dir: &Path) -> Result<CommandResult> {
+    async fn execute_command(
+        &self,
+        cmd: &str,
+        run_dir: &Path,
+        working_dir: &Path,
+    ) -> Result<CommandResult> {
         debug!(cmd = %cmd, "executing verification command");
 
         let start = Utc::now();
@@ -211,56 +269,90 @@ impl Verifier {
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
+        let sandbox = self.config.sandbox.for_run(working_dir, run_dir);
+        if let Some(sandbox) = &sandbox {
+            sandbox.apply(&mut process, SandboxedProcess::Verification)?;
+        }
+        let limits = ResourceGuard::new(
+            self.config
+                .resource_limits
+                .for_phase(StepPhase::Verification),
+            self.config.resource_limits.cgroup_parent.as_deref(),
+        );
+        if let Some(limits) = &limits {
+            limits.apply(&mut process)?;
+        }
 
         let mut child = process.spawn()?;
+        if let Some(limits) = &limits {
+            limits.watch_cpu(child.id());
+        }
 
-        // Take stdout/stderr handles before waiting so we can capture output
-        // even if we need to kill the process.
-        let mut stdout_handle = child.stdout.take();
-        let mut stderr_handle = child.stderr.take();
+        // Read output while the command runs so a full pipe cannot stall it.
+        let output_exceeded = CancellationToken::new();
+        let stdout_task = capture_output(child.stdout.take(), limits.as_ref(), &output_exceeded);
+        let stderr_task = capture_output(child.stderr.take(), limits.as_ref(), &output_exceeded);
 
         // Wait for process with optional timeout.
-        let exit_code = if self.config.timeout_sec > 0 {
-            let timeout_duration = Duration::from_secs(u64::from(self.config.timeout_sec));
-
-            tokio::select! {
-                result = child.wait() => {
-                    result?.code().unwrap_or(-1)
+        let timeout_sec = self.config.timeout_sec;
+        let deadline = async {
+            if timeout_sec > 0 {
+                tokio::time::sleep(Duration::from_secs(u64::from(timeout_sec))).await;
+            } else {
+                std::future::pending::<()>().await;
+            }
+        };
+        let status = tokio::select! {
+            result = child.wait() => result?,
+            () = output_exceeded.cancelled() => {
+                warn!(cmd = %cmd, "verification command exceeded the output size limit; killing");
+                if let Err(e) = child.kill().await {
+                    warn!(cmd = %cmd, error = %e, "failed to kill process over output limit");
                 }
-                () = tokio::time::sleep(timeout_duration) => {
-                    // Kill the process on timeout to prevent zombies
-                    if let Err(e) = child.kill().await {
-                        warn!(cmd = %cmd, error = %e, "failed to kill timed-out process");
-                    }
-                    // Reap the process to prevent zombie
-                    let _ = child.wait().await;
-                    warn!(cmd = %cmd, timeout_sec = self.config.timeout_sec, "verification command timed out");
-                    return Err(VerifierError::Timeout(self.config.timeout_sec));
+                child.wait().await?
+            }
+            () = deadline => {
+                // Kill the process on timeout to prevent zombies
+                if let Err(e) = child.kill().await {
+                    warn!(cmd = %cmd, error = %e, "failed to kill timed-out process");
                 }
+                // Reap the process to prevent zombie
+                let _ = child.wait().await;
+                warn!(cmd = %cmd, timeout_sec = self.config.timeout_sec, "verification command timed out");
+                return Err(VerifierError::Timeout(self.config.timeout_sec));
             }
-        } else {
-            child.wait().await?.code().unwrap_or(-1)
         };
+        let exit_code = status.code().unwrap_or(-1);
 
-        // Read captured output
-        let mut stdout = Vec::new();
-        let mut stderr = Vec::new();
-        if let Some(ref mut handle) = stdout_handle {
-            let _ = handle.read_to_end(&mut stdout).await;
-        }
-        if let Some(ref mut handle) = stderr_handle {
-            let _ = handle.read_to_end(&mut stderr).await;
-        }
+        let stdout = collect_output(stdout_task).await;
+        let stderr = collect_output(stderr_task).await;
 
         let end = Utc::now();
         let duration_ms = (end - start).num_milliseconds() as u64;
 
         let stdout_str = String::from_utf8_lossy(&stdout).to_string();
         let stderr_str = String::from_utf8_lossy(&stderr).to_string();
-        let passed = exit_code == 0;
+        let limit_exceeded = limits.as_ref().and_then(|limits| {
+            if output_exceeded.is_cancelled() {
+                limits.limits().hit(ResourceLimitKind::OutputSize)
+            } else if exit_code != 0 {
+                limits.limit_hit(exit_signal(&status), &format!("{stdout_str}\n{stderr_str}"))
+            } else {
+                None
+            }
+        });
+        let passed = exit_code == 0 && limit_exceeded.is_none();
+        let sandbox_violation = sandbox.as_ref().filter(|_| !passed).and_then(|sandbox| {
+            let process = SandboxedProcess::Verification;
+            sandbox
+                .find_violation(&stderr_str, process)
+                .or_else(|| sandbox.find_violation(&stdout_str, process))
+        });
 
         if passed {
             debug!(cmd = %cmd, duration_ms = duration_ms, "verification command passed");
+        } else if let Some(limit) = &limit_exceeded {
+            warn!(cmd = %cmd, %limit, duration_ms = duration_ms, "verification command hit a resource limit");
         } else {
             warn!(cmd = %cmd, exit_code = exit_code, duration_ms = duration_ms, "verification command failed");
         }
@@ -272,6 +364,8 @@ impl Verifier {
             duration_ms,
             stdout: stdout_str,
             stderr: stderr_str,
+            sandbox_violation,
+            limit_exceeded,
         })
     }
 
@@ -292,6 +386,14 @@ impl Verifier {
                     "--- FAILED: {} (exit {}) ---\n",
                     result.cmd, result.exit_code
                 ));
+                if let Some(violation) = &result.sandbox_violation {
+                    notes.push_str(&format!(
+                        "The sandbox denied access outside the worktree: {violation}\n"
+                    ));
+                }
+                if let Some(limit) = &result.limit_exceeded {
+                    notes.push_str(&limit.runner_note());
+                }
 
                 // Include last 120 lines of output (matches bin/loop).
                 let combined_output = if result.stderr.is_empty() {
@@ -316,10 +418,49 @@ impl Verifier {
     }
 }
 
+/// Read a command's output stream in the background, capped by `limits`.
+fn capture_output<R>(
+    reader: Option<R>,
+    limits: Option<&ResourceGuard>,
+    exceeded: &CancellationToken,
+) -> Option<JoinHandle<Vec<u8>>>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let reader = reader?;
+    let mut reader: Box<dyn AsyncRead + Send + Unpin> = match limits {
+        Some(limits) => Box::new(limits.cap_output(reader, exceeded)),
+        None => Box::new(reader),
+    };
+    Some(tokio::spawn(async move {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf).await;
+        buf
+    }))
+}
+
+/// Output read by a `capture_output` task; empty if it does not finish in time.
+async fn collect_output(task: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    let Some(task) = task else {
+        return Vec::new();
+    };
+    match tokio::time::timeout(OUTPUT_CAPTURE_TIMEOUT, task).await {
+        Ok(Ok(buf)) => buf,
+        Ok(Err(e)) => {
+            warn!(error = %e, "output capture task failed");
+            Vec::new()
+        }
+        Err(_) => {
+            warn!("output capture timed out");
+            Vec::new()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use loop_core::{Id, StepPhase, StepStatus};
+    use loop_core::{Id, StepStatus};
     use tempfile::TempDir;
 
     fn create_test_step() -> Step {
@@ -334,6 +475,7 @@ mod tests {
             exit_code: None,
             prompt_path: None,
             output_path: None,
+            session_id: None,
         }
     }
 
@@ -385,6 +527,8 @@ mod tests {
         let config = VerifierConfig {
             verify_cmds: vec!["cargo test".to_string()],
             timeout_sec: 0,
+            sandbox: SandboxConfig::default(),
+            resource_limits: ResourceLimitsConfig::default(),
         };
         let verifier = Verifier::new(config);
         assert!(verifier.has_commands());
@@ -411,6 +555,8 @@ mod tests {
         let config = VerifierConfig {
             verify_cmds: vec!["true".to_string()],
             timeout_sec: 10,
+            sandbox: SandboxConfig::default(),
+            resource_limits: ResourceLimitsConfig::default(),
         };
         let verifier = Verifier::new(config);
         let step = create_test_step();
@@ -433,6 +579,8 @@ mod tests {
         let config = VerifierConfig {
             verify_cmds: vec!["false".to_string()],
             timeout_sec: 10,
+            sandbox: SandboxConfig::default(),
+            resource_limits: ResourceLimitsConfig::default(),
         };
         let verifier = Verifier::new(config);
         let step = create_test_step();
@@ -461,6 +609,8 @@ mod tests {
         let config = VerifierConfig {
             verify_cmds: vec!["false".to_string(), "true".to_string(), "false".to_string()],
             timeout_sec: 10,
+            sandbox: SandboxConfig::default(),
+            resource_limits: ResourceLimitsConfig::default(),
         };
         let verifier = Verifier::new(config);
         let step = create_test_step();
@@ -480,6 +630,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::manual_string_new)]
     fn format_failure_notes_includes_context() {
         let verifier = Verifier::new(VerifierConfig::default());
         let results = vec![
@@ -490,6 +641,8 @@ mod tests {
                 duration_ms: 1000,
                 stdout: "test output\nmore output".to_string(),
                 stderr: "error output".to_string(),
+                sandbox_violation: None,
+                limit_exceeded: None,
             },
             CommandResult {
                 cmd: "cargo clippy".to_string(),
@@ -498,6 +651,8 @@ mod tests {
                 duration_ms: 500,
                 stdout: "".to_string(),
                 stderr: "".to_string(),
+                sandbox_violation: None,
+                limit_exceeded: None,
             },
         ];
 
@@ -509,4 +664,70 @@ mod tests {
         // Should not include passing command.
         assert!(!notes.contains("cargo clippy"));
     }
+
+    #[tokio::test]
+    async fn execute_reports_sandbox_violation() {
+        if !crate::sandbox::is_supported() {
+            eprintln!("skipping: landlock unavailable");
+            return;
+        }
+        let dir = TempDir::new().unwrap();
+        let working_dir = dir.path().join("worktree");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&working_dir).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let config = VerifierConfig {
+            verify_cmds: vec![format!("touch {}/escaped", outside.display())],
+            timeout_sec: 10,
+            sandbox: SandboxConfig {
+                mode: loop_core::SandboxMode::Fs,
+                ..Default::default()
+            },
+            resource_limits: ResourceLimitsConfig::default(),
+        };
+        let verifier = Verifier::new(config);
+        let step = create_test_step();
+        let run_dir = dir.path().join("run-test");
+
+        let result = verifier
+            .execute(&step, &run_dir, &working_dir)
+            .await
+            .unwrap();
+        assert!(!result.passed);
+        assert!(!outside.join("escaped").exists());
+        let violation = result.commands[0].sandbox_violation.as_deref().unwrap();
+        assert!(violation.contains("Permission denied"), "{violation}");
+
+        let notes = std::fs::read_to_string(result.runner_notes_path.unwrap()).unwrap();
+        assert!(notes.contains("The sandbox denied access outside the worktree"));
+    }
+
+    #[tokio::test]
+    async fn execute_stops_command_over_output_limit() {
+        let dir = TempDir::new().unwrap();
+        let mut resource_limits = ResourceLimitsConfig::default();
+        resource_limits
+            .apply_key("verification.max_output_mb", "1")
+            .unwrap();
+        let config = VerifierConfig {
+            verify_cmds: vec!["yes".to_string()],
+            timeout_sec: 30,
+            sandbox: SandboxConfig::default(),
+            resource_limits,
+        };
+        let verifier = Verifier::new(config);
+        let step = create_test_step();
+        let run_dir = dir.path().join("run-test");
+
+        let result = verifier.execute(&step, &run_dir, dir.path()).await.unwrap();
+        assert!(!result.passed);
+        let command = &result.commands[0];
+        assert_eq!(command.stdout.len(), 1024 * 1024);
+        let limit = command.limit_exceeded.unwrap();
+        assert_eq!(limit.kind, ResourceLimitKind::OutputSize);
+        assert_eq!(limit.limit, 1);
+
+        let notes = std::fs::read_to_string(result.runner_notes_path.unwrap()).unwrap();
+        assert!(notes.contains("output_size limit of 1 MB exceeded (max_output_mb)"));
+    }
 }
@@ -0,0 +1,1005 @@
+//! Distributed mode: a controller offers steps to worker processes through
+//! leases.
+//!
+//! A controller (`loopd --distributed`) schedules runs as usual, but its
+//! runners and verifier hand each step to a [`StepDispatch`] instead of
+//! executing it. The step is written to the `leases` table and the run waits
+//! until a worker reports back.
+//!
+//! A worker (`loopd --controller <url>`) registers, claims a step with
+//! `POST /workers/{id}/claim`, executes it with the local [`Runner`] or
+//! [`Verifier`], and reports the result with `POST /steps/{id}/complete`.
+//! While the step runs it heartbeats, which renews its leases. Before
+//! reporting, it uploads the files the step wrote to the run directory with
+//! `PUT /steps/{id}/artifacts/{name}`. Workers work in the run's worktree in
+//! place, so controller and workers must share a filesystem.
+//!
+//! A lease that is not renewed within [`LEASE_TTL`] expires. The worker stops
+//! the step once the lease it last renewed has run out, and the
+//! [`LeaseReaper`] requeues the step [`LEASE_GRACE`] later, recording
+//! `LEASE_EXPIRED`. A completion from the worker that lost the lease is
+//! rejected, and that worker abandons the step at its next heartbeat.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use loop_core::events::{EventPayload, LeaseExpiredPayload};
+use loop_core::{
+    Config, Id, Lease, PermissionDenial, ResourceLimitHit, Step, StepPhase, TokenUsage,
+    TranscriptEntry, Worker as WorkerInfo,
+};
+use reqwest::header::AUTHORIZATION;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::runner::{Runner, RunnerConfig, RunnerError, StepResult};
+use crate::sandbox::SandboxViolation;
+use crate::storage::{StorageBackend, StorageError};
+use crate::verifier::{VerificationResult, Verifier, VerifierConfig};
+
+/// How long a lease lasts without a heartbeat.
+pub const LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// How long after expiry a lease is requeued, so a partitioned worker has
+/// stopped the step before another worker starts it.
+pub const LEASE_GRACE: Duration = Duration::from_secs(10);
+
+/// How often workers heartbeat while executing a step.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A worker with no heartbeat or claim for this long is marked offline.
+pub const WORKER_OFFLINE_AFTER: Duration = Duration::from_secs(90);
+
+/// How often an idle worker asks the controller for a step.
+const CLAIM_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the controller checks whether an offered step has a result.
+const RESULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often expired leases and silent workers are looked for.
+const REAPER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many times a worker tries to upload each artifact.
+const ARTIFACT_UPLOAD_ATTEMPTS: u32 = 3;
+
+/// Pause between artifact upload attempts.
+const ARTIFACT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Everything a worker needs to execute a step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRequest {
+    pub step: Step,
+    /// The run's config, with paths already resolved against its workspace.
+    pub config: Config,
+    pub run_dir: PathBuf,
+    pub working_dir: PathBuf,
+    /// Agent prompt; `None` for verification steps.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Agent session the step continues.
+    #[serde(default)]
+    pub resume_session: Option<String>,
+    /// Skill `allowed_tools` merged into the tool policy for the step.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+}
+
+/// What a worker reports back for a step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StepReport {
+    Agent(AgentReport),
+    Verification {
+        outcome: Result<VerificationResult, String>,
+    },
+}
+
+impl StepReport {
+    /// The same report with its outcome replaced by a failure.
+    #[must_use]
+    pub fn failed(self, message: String) -> Self {
+        match self {
+            Self::Agent(report) => Self::Agent(AgentReport {
+                outcome: Err(StepFailure::Other { message }),
+                ..report
+            }),
+            Self::Verification { .. } => Self::Verification {
+                outcome: Err(message),
+            },
+        }
+    }
+}
+
+/// Outcome of an agent step, plus everything its runner accumulated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentReport {
+    pub outcome: Result<StepResult, StepFailure>,
+    #[serde(default)]
+    pub usage: TokenUsage,
+    #[serde(default)]
+    pub transcript: Vec<TranscriptEntry>,
+    #[serde(default)]
+    pub permission_denials: Vec<PermissionDenial>,
+    #[serde(default)]
+    pub sandbox_violations: Vec<SandboxViolation>,
+}
+
+/// A [`RunnerError`] as sent over the wire, keeping the variants the
+/// controller acts on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum StepFailure {
+    ExitCode {
+        code: i32,
+        output_tail: String,
+    },
+    TransientApiError {
+        code: i32,
+        output_tail: String,
+    },
+    ResourceLimit {
+        limit: ResourceLimitHit,
+        code: i32,
+        output_tail: String,
+    },
+    Cancelled,
+    Other {
+        message: String,
+    },
+}
+
+impl From<&RunnerError> for StepFailure {
+    fn from(error: &RunnerError) -> Self {
+        match error {
+            RunnerError::ExitCode { code, output_tail } => Self::ExitCode {
+                code: *code,
+                output_tail: output_tail.clone(),
+            },
+            RunnerError::TransientApiError { code, output_tail } => Self::TransientApiError {
+                code: *code,
+                output_tail: output_tail.clone(),
+            },
+            RunnerError::ResourceLimit {
+                limit,
+                code,
+                output_tail,
+            } => Self::ResourceLimit {
+                limit: *limit,
+                code: *code,
+                output_tail: output_tail.clone(),
+            },
+            RunnerError::Cancelled => Self::Cancelled,
+            other => Self::Other {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+impl From<StepFailure> for RunnerError {
+    fn from(failure: StepFailure) -> Self {
+        match failure {
+            StepFailure::ExitCode { code, output_tail } => Self::ExitCode { code, output_tail },
+            StepFailure::TransientApiError { code, output_tail } => {
+                Self::TransientApiError { code, output_tail }
+            }
+            StepFailure::ResourceLimit {
+                limit,
+                code,
+                output_tail,
+            } => Self::ResourceLimit {
+                limit,
+                code,
+                output_tail,
+            },
+            StepFailure::Cancelled => Self::Cancelled,
+            StepFailure::Other { message } => Self::Worker(message),
+        }
+    }
+}
+
+/// Errors from handing a step to workers.
+#[derive(Debug, Error)]
+pub enum DispatchError {
+    #[error("storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("invalid step request or report: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("step withdrawn: run canceled")]
+    Cancelled,
+}
+
+/// Hands a run's steps to workers and waits for their reports.
+#[derive(Debug)]
+pub struct StepDispatch {
+    storage: Arc<dyn StorageBackend>,
+    config: Config,
+    cancel_token: CancellationToken,
+}
+
+impl StepDispatch {
+    /// `cancel_token` is the run's; cancelling it withdraws the waiting step.
+    pub fn new(
+        storage: Arc<dyn StorageBackend>,
+        config: Config,
+        cancel_token: CancellationToken,
+    ) -> Self {
+        Self {
+            storage,
+            config,
+            cancel_token,
+        }
+    }
+
+    /// A request for `step` with no prompt, session or skill tools.
+    pub fn request(&self, step: &Step, run_dir: &Path, working_dir: &Path) -> StepRequest {
+        StepRequest {
+            step: step.clone(),
+            config: self.config.clone(),
+            run_dir: run_dir.to_path_buf(),
+            working_dir: working_dir.to_path_buf(),
+            prompt: None,
+            resume_session: None,
+            allowed_tools: Vec::new(),
+        }
+    }
+
+    /// Offer `request` to workers and wait until one reports back.
+    pub async fn dispatch(&self, request: &StepRequest) -> Result<StepReport, DispatchError> {
+        let step_id = &request.step.id;
+        self.storage
+            .offer_step(
+                step_id,
+                &request.step.run_id,
+                &serde_json::to_string(request)?,
+            )
+            .await?;
+        info!(
+            step_id = %step_id,
+            phase = request.step.phase.as_str(),
+            "offered step to workers"
+        );
+
+        let result_json = loop {
+            tokio::select! {
+                () = self.cancel_token.cancelled() => {
+                    self.storage.release_lease(step_id).await?;
+                    return Err(DispatchError::Cancelled);
+                }
+                () = tokio::time::sleep(RESULT_POLL_INTERVAL) => {}
+            }
+            if let Some(result_json) = self.storage.lease_result(step_id).await? {
+                break result_json;
+            }
+        };
+        self.storage.release_lease(step_id).await?;
+        Ok(serde_json::from_str(&result_json)?)
+    }
+}
+
+/// Requeues expired leases and marks silent workers offline.
+#[derive(Debug, Clone)]
+pub struct LeaseReaper {
+    storage: Arc<dyn StorageBackend>,
+}
+
+impl LeaseReaper {
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        Self { storage }
+    }
+
+    /// Reap every [`REAPER_INTERVAL`] until `cancel` fires.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.tick(Utc::now()).await {
+                    warn!("lease check failed: {}", e);
+                }
+                tokio::select! {
+                    () = cancel.cancelled() => break,
+                    () = tokio::time::sleep(REAPER_INTERVAL) => {}
+                }
+            }
+        })
+    }
+
+    /// Requeue the leases expired for at least [`LEASE_GRACE`] at `now`;
+    /// returns them.
+    pub async fn tick(&self, now: DateTime<Utc>) -> Result<Vec<Lease>, StorageError> {
+        let grace = chrono::Duration::from_std(LEASE_GRACE).unwrap_or_default();
+        let expired = self.storage.expire_leases(now - grace).await?;
+        for lease in &expired {
+            let Some(worker_id) = lease.worker_id.clone() else {
+                continue;
+            };
+            warn!(
+                step_id = %lease.step_id,
+                worker_id = %worker_id,
+                attempt = lease.attempt,
+                "lease expired; requeueing step"
+            );
+            let payload = EventPayload::LeaseExpired(LeaseExpiredPayload {
+                step_id: lease.step_id.clone(),
+                worker_id,
+                attempt: lease.attempt,
+            });
+            self.storage
+                .append_event(&lease.run_id, Some(&lease.step_id), &payload)
+                .await?;
+        }
+
+        let cutoff = now - chrono::Duration::from_std(WORKER_OFFLINE_AFTER).unwrap_or_default();
+        for worker_id in self.storage.mark_workers_offline(cutoff).await? {
+            warn!(worker_id = %worker_id, "no heartbeat; worker marked offline");
+        }
+        Ok(expired)
+    }
+}
+
+/// Body of `POST /workers/register`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterWorkerRequest {
+    pub hostname: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// Response from `POST /workers/register`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterWorkerResponse {
+    pub worker: WorkerInfo,
+    pub lease_ttl_sec: u64,
+    pub heartbeat_interval_sec: u64,
+}
+
+/// Response from `POST /workers/{id}/heartbeat`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatResponse {
+    /// Steps the worker still holds; it should abandon any others.
+    pub leases: Vec<Id>,
+    pub lease_expires_at: DateTime<Utc>,
+}
+
+/// Response from `POST /workers/{id}/claim` when a step was granted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimResponse {
+    pub lease: Lease,
+    pub request: StepRequest,
+}
+
+/// Body of `POST /steps/{id}/complete`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteStepRequest {
+    pub worker_id: Id,
+    pub report: StepReport,
+}
+
+/// Execute a claimed step with the local runner or verifier.
+pub async fn run_step(request: &StepRequest, cancel: CancellationToken) -> StepReport {
+    let step = &request.step;
+    if step.phase == StepPhase::Verification {
+        let verifier = Verifier::new(VerifierConfig::from_config(&request.config));
+        let outcome = tokio::select! {
+            result = verifier.execute(step, &request.run_dir, &request.working_dir) => {
+                result.map_err(|e| e.to_string())
+            }
+            () = cancel.cancelled() => Err("cancelled".to_string()),
+        };
+        return StepReport::Verification { outcome };
+    }
+
+    let config = if step.phase == StepPhase::Review {
+        RunnerConfig::from_config_for_review(&request.config)
+    } else {
+        RunnerConfig::from_config(&request.config)
+    };
+    let runner = Runner::new(config);
+    runner.set_resume_session(request.resume_session.clone());
+    runner.set_step_allowed_tools(request.allowed_tools.clone());
+    let outcome = runner
+        .execute_step(
+            step,
+            request.prompt.as_deref().unwrap_or_default(),
+            &request.run_dir,
+            &request.working_dir,
+            cancel,
+        )
+        .await;
+    StepReport::Agent(AgentReport {
+        outcome: outcome.map_err(|e| StepFailure::from(&e)),
+        usage: runner.take_usage(),
+        transcript: runner.take_transcript(),
+        permission_denials: runner.take_permission_denials(),
+        sandbox_violations: runner.take_sandbox_violations(),
+    })
+}
+
+/// Files a step wrote to `run_dir`: the top-level files modified since `since`.
+pub fn step_artifacts(run_dir: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(run_dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.metadata().is_ok_and(|meta| {
+                meta.is_file() && meta.modified().is_ok_and(|modified| modified >= since)
+            })
+        })
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Store an uploaded artifact as `run_dir/name`.
+///
+/// `name` must be a plain file name. A file that already has the same
+/// content (a shared filesystem) is left alone; otherwise the content is
+/// written beside it and renamed into place. Returns whether it was written.
+pub fn store_artifact(run_dir: &Path, name: &str, content: &[u8]) -> std::io::Result<bool> {
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid artifact name: {name}"),
+        ));
+    }
+
+    let path = run_dir.join(name);
+    if std::fs::read(&path).is_ok_and(|existing| existing == content) {
+        return Ok(false);
+    }
+    std::fs::create_dir_all(run_dir)?;
+    let partial = run_dir.join(format!(".{name}.upload"));
+    std::fs::write(&partial, content)?;
+    std::fs::rename(&partial, &path)?;
+    Ok(true)
+}
+
+/// Errors from talking to the controller.
+#[derive(Debug, Error)]
+pub enum WorkerError {
+    #[error("controller request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("controller returned {status}: {message}")]
+    Controller { status: u16, message: String },
+    #[error("failed to read artifact: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl WorkerError {
+    /// Whether the controller rejected the request because the lease is gone.
+    fn is_lease_lost(&self) -> bool {
+        matches!(self, Self::Controller { status, .. } if *status == StatusCode::CONFLICT.as_u16())
+    }
+}
+
+/// Worker mode settings.
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Base URL of the controller, e.g. `http://127.0.0.1:7700`.
+    pub controller_url: String,
+    pub auth_token: Option<String>,
+    pub hostname: String,
+    pub capabilities: Vec<String>,
+}
+
+/// A worker process: claims steps from a controller and executes them.
+#[derive(Debug)]
+pub struct Worker {
+    config: WorkerConfig,
+    http: reqwest::Client,
+}
+
+impl Worker {
+    pub fn new(config: WorkerConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Register, then claim and execute steps until `cancel` fires.
+    ///
+    /// A step in flight at shutdown is abandoned; its lease expires and the
+    /// controller requeues it.
+    pub async fn run(&self, cancel: CancellationToken) -> Result<(), WorkerError> {
+        let registration: RegisterWorkerResponse = self
+            .post(
+                "/workers/register",
+                &RegisterWorkerRequest {
+                    hostname: self.config.hostname.clone(),
+                    capabilities: self.config.capabilities.clone(),
+                },
+            )
+            .await?
+            .json()
+            .await?;
+        let worker_id = registration.worker.id;
+        let lease_ttl = Duration::from_secs(registration.lease_ttl_sec);
+        info!(
+            worker_id = %worker_id,
+            controller = %self.config.controller_url,
+            "registered with controller"
+        );
+
+        while !cancel.is_cancelled() {
+            let claimed_at = Instant::now();
+            match self.claim(&worker_id).await {
+                Ok(Some(claim)) => {
+                    let lease_deadline = claimed_at + lease_ttl;
+                    self.execute(&worker_id, claim, lease_deadline, lease_ttl, &cancel)
+                        .await;
+                }
+                Ok(None) => {}
+                Err(e) => warn!(worker_id = %worker_id, error = %e, "claim failed"),
+            }
+            tokio::select! {
+                () = cancel.cancelled() => {}
+                () = tokio::time::sleep(CLAIM_INTERVAL) => {}
+            }
+        }
+        info!(worker_id = %worker_id, "worker stopped");
+        Ok(())
+    }
+
+    async fn claim(&self, worker_id: &Id) -> Result<Option<ClaimResponse>, WorkerError> {
+        let response = self
+            .post(&format!("/workers/{worker_id}/claim"), &())
+            .await?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        Ok(Some(response.json().await?))
+    }
+
+    /// Execute a claimed step, heartbeating until it finishes, then upload its
+    /// artifacts and report it.
+    ///
+    /// Deadlines count from when each claim or heartbeat was sent, so the
+    /// worker gives up no later than the controller's copy of the lease.
+    async fn execute(
+        &self,
+        worker_id: &Id,
+        claim: ClaimResponse,
+        lease_deadline: Instant,
+        lease_ttl: Duration,
+        cancel: &CancellationToken,
+    ) {
+        let step_id = claim.lease.step_id.clone();
+        info!(
+            step_id = %step_id,
+            run_id = %claim.lease.run_id,
+            phase = claim.request.step.phase.as_str(),
+            attempt = claim.lease.attempt,
+            "claimed step"
+        );
+
+        let started = SystemTime::now();
+        let step_cancel = cancel.child_token();
+        let run = run_step(&claim.request, step_cancel.clone());
+        tokio::pin!(run);
+        let lease_expiry = tokio::time::sleep_until(lease_deadline);
+        tokio::pin!(lease_expiry);
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await;
+        let report = loop {
+            tokio::select! {
+                report = &mut run => break report,
+                () = &mut lease_expiry, if !step_cancel.is_cancelled() => {
+                    warn!(step_id = %step_id, "lease expired without renewal; abandoning step");
+                    step_cancel.cancel();
+                }
+                _ = heartbeat.tick() => {
+                    let sent = Instant::now();
+                    match self.heartbeat(worker_id).await {
+                        Ok(held) if !held.leases.contains(&step_id) => {
+                            warn!(step_id = %step_id, "lease lost; abandoning step");
+                            step_cancel.cancel();
+                        }
+                        Ok(_) => lease_expiry.as_mut().reset(sent + lease_ttl),
+                        Err(e) => warn!(step_id = %step_id, error = %e, "heartbeat failed"),
+                    }
+                }
+            }
+        };
+        if step_cancel.is_cancelled() {
+            return;
+        }
+
+        let report = match self
+            .upload_artifacts(worker_id, &step_id, &claim.request.run_dir, started)
+            .await
+        {
+            Ok(()) => report,
+            Err(e) if e.is_lease_lost() => {
+                warn!(step_id = %step_id, "lease lost during artifact upload; abandoning step");
+                return;
+            }
+            Err(e) => {
+                warn!(step_id = %step_id, error = %e, "artifact upload failed; failing step");
+                report.failed(format!("artifact upload failed: {e}"))
+            }
+        };
+
+        let complete = CompleteStepRequest {
+            worker_id: worker_id.clone(),
+            report,
+        };
+        match self
+            .post(&format!("/steps/{step_id}/complete"), &complete)
+            .await
+        {
+            Ok(_) => info!(step_id = %step_id, "reported step"),
+            Err(e) => warn!(step_id = %step_id, error = %e, "failed to report step"),
+        }
+    }
+
+    /// Upload the files the step wrote to its run directory, retrying each
+    /// a few times.
+    async fn upload_artifacts(
+        &self,
+        worker_id: &Id,
+        step_id: &Id,
+        run_dir: &Path,
+        since: SystemTime,
+    ) -> Result<(), WorkerError> {
+        for path in step_artifacts(run_dir, since) {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let content = tokio::fs::read(&path).await?;
+            let url = format!(
+                "{}/steps/{step_id}/artifacts/{name}",
+                self.config.controller_url.trim_end_matches('/')
+            );
+            let mut attempt = 1;
+            loop {
+                let request = self
+                    .http
+                    .put(&url)
+                    .query(&[("worker_id", worker_id.as_ref())])
+                    .body(content.clone());
+                match self.send(request).await {
+                    Ok(_) => break,
+                    Err(e) if e.is_lease_lost() || attempt == ARTIFACT_UPLOAD_ATTEMPTS => {
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        warn!(
+                            step_id = %step_id,
+                            artifact = name,
+                            attempt,
+                            error = %e,
+                            "artifact upload failed; retrying"
+                        );
+                        attempt += 1;
+                        tokio::time::sleep(ARTIFACT_RETRY_DELAY).await;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn heartbeat(&self, worker_id: &Id) -> Result<HeartbeatResponse, WorkerError> {
+        Ok(self
+            .post(&format!("/workers/{worker_id}/heartbeat"), &())
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// POST `body` to the controller; non-2xx responses become errors.
+    async fn post<T: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<reqwest::Response, WorkerError> {
+        let url = format!("{}{path}", self.config.controller_url.trim_end_matches('/'));
+        self.send(self.http.post(&url).json(body)).await
+    }
+
+    /// Send `request` with the worker's auth; non-2xx responses become errors.
+    async fn send(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, WorkerError> {
+        if let Some(token) = &self.config.auth_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let message = response.text().await.unwrap_or_default();
+            return Err(WorkerError::Controller { status, message });
+        }
+        Ok(response)
+    }
+}
+
+/// This machine's hostname, for worker registration.
+pub fn local_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+    use loop_core::events::EventType;
+    use loop_core::{ReviewStatus, Run, RunNameSource, RunStatus, StepStatus, WorkerStatus};
+    use tempfile::TempDir;
+
+    fn worker(hostname: &str) -> WorkerInfo {
+        WorkerInfo {
+            id: Id::new(),
+            hostname: hostname.to_string(),
+            status: WorkerStatus::Online,
+            capabilities: vec!["shared_fs".to_string()],
+            last_heartbeat: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn step_failure_keeps_actionable_runner_errors() {
+        let error = RunnerError::TransientApiError {
+            code: 1,
+            output_tail: "overloaded".to_string(),
+        };
+        let failure = StepFailure::from(&error);
+        let json = serde_json::to_string(&failure).unwrap();
+        assert!(json.contains("\"error\":\"transient_api_error\""));
+        let back: StepFailure = serde_json::from_str(&json).unwrap();
+        assert!(matches!(
+            RunnerError::from(back),
+            RunnerError::TransientApiError { code: 1, .. }
+        ));
+
+        let failure = StepFailure::from(&RunnerError::Worker("disk full".to_string()));
+        assert_eq!(
+            failure,
+            StepFailure::Other {
+                message: "worker error: disk full".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatch_requeues_expired_lease_and_takes_new_workers_report() {
+        let dir = TempDir::new().unwrap();
+        let storage: Arc<dyn StorageBackend> = Arc::new(
+            Storage::new(&dir.path().join("test.db"), DEFAULT_MAX_CONCURRENT_RUNS)
+                .await
+                .unwrap(),
+        );
+        storage.migrate_embedded().await.unwrap();
+
+        let now = Utc::now();
+        let run = Run {
+            id: Id::new(),
+            name: "distributed".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Running,
+            workspace_root: "/workspace".to_string(),
+            spec_path: "/workspace/spec.md".to_string(),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: now,
+            updated_at: now,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        };
+        storage.insert_run(&run).await.unwrap();
+        let step = Step {
+            id: Id::new(),
+            run_id: run.id.clone(),
+            phase: StepPhase::Verification,
+            status: StepStatus::InProgress,
+            attempt: 1,
+            started_at: Some(now),
+            ended_at: None,
+            exit_code: None,
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        };
+        storage.insert_step(&step).await.unwrap();
+        let (a, b) = (worker("a"), worker("b"));
+        storage.insert_worker(&a).await.unwrap();
+        storage.insert_worker(&b).await.unwrap();
+
+        let dispatch = StepDispatch::new(
+            Arc::clone(&storage),
+            Config::default(),
+            CancellationToken::new(),
+        );
+        let request = dispatch.request(&step, dir.path(), dir.path());
+        let waiting = tokio::spawn(async move { dispatch.dispatch(&request).await });
+
+        let ttl = chrono::Duration::from_std(LEASE_TTL).unwrap();
+        let claimed = loop {
+            if let Some(claimed) = storage.claim_lease(&a.id, now + ttl).await.unwrap() {
+                break claimed;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        assert_eq!(claimed.0.attempt, 1);
+        let offered: StepRequest = serde_json::from_str(&claimed.1).unwrap();
+        assert_eq!(offered.step.id, step.id);
+
+        // Worker a goes silent: once the grace period after expiry has passed,
+        // the step is requeued.
+        let reaper = LeaseReaper::new(Arc::clone(&storage));
+        assert!(reaper.tick(now + ttl).await.unwrap().is_empty());
+        let expired = reaper.tick(now + ttl * 2).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        let events = storage.list_events(&run.id).await.unwrap();
+        assert!(events
+            .iter()
+            .any(|event| event.event_type == EventType::LeaseExpired.as_str()));
+
+        let report = StepReport::Verification {
+            outcome: Err("late".to_string()),
+        };
+        let report_json = serde_json::to_string(&report).unwrap();
+        assert!(matches!(
+            storage
+                .complete_lease(&step.id, &a.id, now, &report_json)
+                .await,
+            Err(StorageError::StaleLease(_))
+        ));
+
+        let (lease, _) = storage
+            .claim_lease(&b.id, Utc::now() + ttl)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lease.attempt, 2);
+        let report = StepReport::Verification {
+            outcome: Err("tests failed".to_string()),
+        };
+        storage
+            .complete_lease(
+                &step.id,
+                &b.id,
+                Utc::now(),
+                &serde_json::to_string(&report).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let received = waiting.await.unwrap().unwrap();
+        assert!(matches!(
+            received,
+            StepReport::Verification { outcome: Err(message) } if message == "tests failed"
+        ));
+        assert!(storage.lease_result(&step.id).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn store_artifact_writes_plain_names_once() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run");
+        assert!(store_artifact(&run_dir, "iter-01-impl.log", b"output").unwrap());
+        assert_eq!(
+            std::fs::read(run_dir.join("iter-01-impl.log")).unwrap(),
+            b"output"
+        );
+        // Identical content, as on a shared filesystem, is left in place.
+        assert!(!store_artifact(&run_dir, "iter-01-impl.log", b"output").unwrap());
+        assert!(store_artifact(&run_dir, "iter-01-impl.log", b"newer").unwrap());
+
+        for name in ["../escape.log", "nested/file.log", "/abs.log", "..", ""] {
+            let err = store_artifact(&run_dir, name, b"x").unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{name}");
+        }
+        assert!(!dir.path().join("escape.log").exists());
+    }
+
+    #[test]
+    fn step_artifacts_lists_files_written_since_the_step_started() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("old.log"), "before").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(30);
+        std::fs::File::options()
+            .write(true)
+            .open(dir.path().join("old.log"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let since = SystemTime::now() - Duration::from_secs(1);
+        std::fs::write(dir.path().join("iter-02-verify.log"), "after").unwrap();
+        std::fs::create_dir(dir.path().join("subdir")).unwrap();
+
+        assert_eq!(
+            step_artifacts(dir.path(), since),
+            vec![dir.path().join("iter-02-verify.log")]
+        );
+        assert!(step_artifacts(&dir.path().join("missing"), since).is_empty());
+    }
+
+    #[tokio::test]
+    async fn worker_abandons_step_once_its_lease_runs_out() {
+        let dir = TempDir::new().unwrap();
+        // Nothing listens here, so every heartbeat fails.
+        let worker = Worker::new(WorkerConfig {
+            controller_url: "http://127.0.0.1:9".to_string(),
+            auth_token: None,
+            hostname: "partitioned".to_string(),
+            capabilities: Vec::new(),
+        });
+        let config = Config {
+            verify_cmds: vec!["sleep 30".to_string()],
+            ..Config::default()
+        };
+        let now = Utc::now();
+        let step = Step {
+            id: Id::new(),
+            run_id: Id::new(),
+            phase: StepPhase::Verification,
+            status: StepStatus::InProgress,
+            attempt: 1,
+            started_at: Some(now),
+            ended_at: None,
+            exit_code: None,
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        };
+        let claim = ClaimResponse {
+            lease: Lease {
+                step_id: step.id.clone(),
+                run_id: step.run_id.clone(),
+                worker_id: None,
+                expires_at: None,
+                attempt: 1,
+            },
+            request: StepRequest {
+                step,
+                config,
+                run_dir: dir.path().to_path_buf(),
+                working_dir: dir.path().to_path_buf(),
+                prompt: None,
+                resume_session: None,
+                allowed_tools: Vec::new(),
+            },
+        };
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(200);
+        worker
+            .execute(
+                &Id::new(),
+                claim,
+                deadline,
+                LEASE_TTL,
+                &CancellationToken::new(),
+            )
+            .await;
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
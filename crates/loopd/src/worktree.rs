@@ -11,12 +11,13 @@ use loop_core::config::Config;
 use loop_core::types::{RunWorktree, WorktreeProvider};
 use std::path::Path;
 use std::process::Command;
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 use thiserror::Error;
 use tracing::info;
 
 use crate::git;
+use crate::metrics::WORKTREE_CREATE_DURATION;
 use crate::worktree_worktrunk::WorktrunkProvider;
 
 #[derive(Debug, Error)]
@@ -36,7 +37,6 @@ pub type Result<T> = std::result::Result<T, WorktreeError>;
 static WORKTREE_PROVIDER_GIT_COUNT: AtomicUsize = AtomicUsize::new(0);
 static WORKTREE_PROVIDER_WORKTRUNK_COUNT: AtomicUsize = AtomicUsize::new(0);
 static WORKTREE_PROVIDER_AUTO_COUNT: AtomicUsize = AtomicUsize::new(0);
-static WORKTREE_CREATE_DURATION_MS: AtomicU64 = AtomicU64::new(0);
 
 /// Trait for worktree lifecycle management.
 ///
@@ -175,7 +175,7 @@ fn record_worktree_create_metrics(provider: WorktreeProvider, duration_ms: u64)
         }
         WorktreeProvider::Auto => WORKTREE_PROVIDER_AUTO_COUNT.fetch_add(1, Ordering::SeqCst) + 1,
     };
-    WORKTREE_CREATE_DURATION_MS.store(duration_ms, Ordering::SeqCst);
+    WORKTREE_CREATE_DURATION.observe_ms(duration_ms);
 
     info!(
         provider = ?provider,
@@ -185,6 +185,15 @@ fn record_worktree_create_metrics(provider: WorktreeProvider, duration_ms: u64)
     );
 }
 
+/// Number of worktrees created with the given provider since daemon start.
+pub fn created_count(provider: WorktreeProvider) -> usize {
+    match provider {
+        WorktreeProvider::Git => WORKTREE_PROVIDER_GIT_COUNT.load(Ordering::SeqCst),
+        WorktreeProvider::Worktrunk => WORKTREE_PROVIDER_WORKTRUNK_COUNT.load(Ordering::SeqCst),
+        WorktreeProvider::Auto => WORKTREE_PROVIDER_AUTO_COUNT.load(Ordering::SeqCst),
+    }
+}
+
 /// Git worktree provider using native git commands.
 ///
 /// Wraps functions from `crates/loopd/src/git.rs`.
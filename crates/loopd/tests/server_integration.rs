@@ -8,14 +8,25 @@ use axum::http::{Request, StatusCode};
 use axum::response::Response;
 use chrono::Utc;
 use http_body_util::BodyExt;
-use loop_core::events::{EventPayload, RunCreatedPayload, RunStartedPayload, StepFinishedPayload};
-use loop_core::{Id, ReviewStatus, Run, RunNameSource, RunStatus, Step, StepPhase, StepStatus};
+use loop_core::events::{
+    EventPayload, EventType, RunCompletedPayload, RunCreatedPayload, RunStartedPayload,
+    StepFinishedPayload,
+};
+use loop_core::{
+    Config, Id, RetentionPolicy, ReviewStatus, Run, RunNameSource, RunStatus, Step, StepPhase,
+    StepStatus, TokenUsage, TranscriptBuilder,
+};
+use loopd::bus::OutputChunk;
+use loopd::governor::{detect_rate_limit, AttemptOutcome};
 use loopd::scheduler::Scheduler;
 use loopd::server::{create_router, AppState};
-use loopd::storage::{Storage, DEFAULT_MAX_CONCURRENT_RUNS};
+use loopd::skills::SkillsMetrics;
+use loopd::storage::{RunChild, Storage, StorageBackend, DEFAULT_MAX_CONCURRENT_RUNS};
+use loopd::workers::StepRequest;
 use serde_json::Value;
 use std::sync::Arc;
 use tempfile::TempDir;
+use tokio_util::sync::CancellationToken;
 use tower::ServiceExt;
 
 async fn create_test_app() -> (axum::Router, Arc<AppState>, TempDir) {
@@ -25,12 +36,13 @@ async fn create_test_app() -> (axum::Router, Arc<AppState>, TempDir) {
         .await
         .unwrap();
     storage.migrate_embedded().await.unwrap();
-    let storage = Arc::new(storage);
+    let storage: Arc<dyn StorageBackend> = Arc::new(storage);
     let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 3));
 
     let state = Arc::new(AppState {
         storage,
         scheduler,
+        skills_metrics: Arc::new(SkillsMetrics::new()),
         auth_token: None,
     });
 
@@ -206,6 +218,86 @@ async fn run_lifecycle_filter_by_workspace() {
     assert_eq!(json["runs"].as_array().unwrap().len(), 1);
 }
 
+#[tokio::test]
+async fn list_runs_pages_with_cursor_and_filters() {
+    let (app, state, _dir) = create_test_app().await;
+
+    let base = Utc::now() - chrono::Duration::minutes(10);
+    for i in 0..5 {
+        let run = Run {
+            id: Id::new(),
+            name: format!("feature-{i}"),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Pending,
+            workspace_root: "/workspace".to_string(),
+            spec_path: format!("/workspace/specs/feature-{i}.md"),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: base + chrono::Duration::seconds(i),
+            updated_at: base,
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        };
+        state.storage.insert_run(&run).await.unwrap();
+    }
+
+    // Walk every page of two.
+    let mut uri = "/runs?limit=2&sort=created_asc".to_string();
+    let mut seen = Vec::new();
+    loop {
+        let response: Response = app
+            .clone()
+            .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body_to_json(response).await;
+        for run in json["runs"].as_array().unwrap() {
+            seen.push(run["spec_path"].as_str().unwrap().to_string());
+        }
+        match json["next_cursor"].as_str() {
+            Some(cursor) => uri = format!("/runs?limit=2&sort=created_asc&cursor={cursor}"),
+            None => break,
+        }
+    }
+    let expected: Vec<String> = (0..5)
+        .map(|i| format!("/workspace/specs/feature-{i}.md"))
+        .collect();
+    assert_eq!(seen, expected);
+
+    // Status and spec substring filters are pushed down.
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/runs?status=pending&spec_path=feature-3&review_status=pending")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let json = body_to_json(response).await;
+    let runs = json["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(json.get("next_cursor").is_none());
+
+    // Invalid status and unknown cursor are rejected.
+    for uri in ["/runs?status=bogus", "/runs?limit=2&cursor=not-a-run"] {
+        let response: Response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
 #[tokio::test]
 async fn run_lifecycle_pause_resume_cancel() {
     let (_, state, _dir) = create_test_app().await;
@@ -230,6 +322,7 @@ async fn run_lifecycle_pause_resume_cancel() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -319,6 +412,7 @@ async fn run_lifecycle_pause_nonexistent_fails() {
 }
 
 #[tokio::test]
+#[allow(clippy::if_then_some_else_none)]
 async fn run_lifecycle_list_steps() {
     let (_, state, _dir) = create_test_app().await;
 
@@ -342,6 +436,7 @@ async fn run_lifecycle_list_steps() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -369,29 +464,1101 @@ async fn run_lifecycle_list_steps() {
             exit_code: if i < 2 { Some(0) } else { None },
             prompt_path: Some(format!("/workspace/logs/loop/prompt-{}.txt", i)),
             output_path: Some(format!("/workspace/logs/loop/output-{}.log", i)),
+            session_id: None,
         };
         state.storage.insert_step(&step).await.unwrap();
     }
 
-    let app = create_router(Arc::clone(&state));
+    let app = create_router(Arc::clone(&state));
+
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/steps", run_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    let steps = json["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 3);
+    assert_eq!(steps[0]["phase"], "implementation");
+    assert_eq!(steps[1]["phase"], "review");
+    assert_eq!(steps[2]["phase"], "verification");
+}
+
+#[tokio::test]
+async fn run_children_lists_fanned_out_tasks() {
+    let (app, state, _dir) = create_test_app().await;
+
+    let parent = Run {
+        id: Id::new(),
+        name: "parent".to_string(),
+        name_source: RunNameSource::SpecSlug,
+        status: RunStatus::Running,
+        workspace_root: "/workspace".to_string(),
+        spec_path: "/workspace/spec.md".to_string(),
+        plan_path: Some("/workspace/plan.md".to_string()),
+        worktree: None,
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: 0,
+    };
+    state.storage.insert_run(&parent).await.unwrap();
+
+    let child = Run {
+        id: Id::new(),
+        name: "parent-storage".to_string(),
+        status: RunStatus::Pending,
+        ..parent.clone()
+    };
+    let link = RunChild {
+        parent_run_id: parent.id.clone(),
+        child_run_id: child.id.clone(),
+        task_id: "storage".to_string(),
+        task_label: "Add storage".to_string(),
+        depends_on: vec!["types".to_string()],
+        merged_at: None,
+        created_at: Utc::now(),
+    };
+    state.storage.insert_child_run(&child, &link).await.unwrap();
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/children", parent.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    let children = json["children"].as_array().unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0]["child_run_id"], child.id.to_string());
+    assert_eq!(children[0]["task_id"], "storage");
+    assert_eq!(children[0]["depends_on"], serde_json::json!(["types"]));
+    assert!(children[0]["merged_at"].is_null());
+
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/children", Id::new()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn step_transcript_filters_by_tool() {
+    let (app, state, _dir) = create_test_app().await;
+
+    let run = Run {
+        id: Id::new(),
+        name: "test-run".to_string(),
+        name_source: RunNameSource::SpecSlug,
+        status: RunStatus::Running,
+        workspace_root: "/workspace".to_string(),
+        spec_path: "/workspace/spec.md".to_string(),
+        plan_path: None,
+        worktree: None,
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: 0,
+    };
+    state.storage.insert_run(&run).await.unwrap();
+    let step = Step {
+        id: Id::new(),
+        run_id: run.id.clone(),
+        phase: StepPhase::Implementation,
+        status: StepStatus::Failed,
+        attempt: 2,
+        started_at: Some(Utc::now()),
+        ended_at: Some(Utc::now()),
+        exit_code: Some(1),
+        prompt_path: None,
+        output_path: None,
+        session_id: None,
+    };
+    state.storage.insert_step(&step).await.unwrap();
+
+    let mut builder = TranscriptBuilder::new();
+    builder.observe(
+        &serde_json::json!({"type": "assistant", "message": {"content": [
+            {"type": "text", "text": "Building."},
+            {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "cargo build"}},
+            {"type": "tool_use", "id": "t2", "name": "Bash",
+             "input": {"command": "GITHUB_TOKEN=ghp_abcdef123456 gh pr list"}}
+        ]}}),
+        Utc::now(),
+    );
+    builder.observe(
+        &serde_json::json!({"type": "user", "message": {"content": [
+            {"type": "tool_result", "tool_use_id": "t1", "content": "error[E0308]", "is_error": true}
+        ]}}),
+        Utc::now(),
+    );
+    state
+        .storage
+        .append_transcript_entries(&run.id, &step.id, &builder.finish())
+        .await
+        .unwrap();
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/runs/{}/steps/{}/transcript?tool=Bash",
+                    run.id, step.id
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["step"]["attempt"], 2);
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0]["kind"], "tool_use");
+    assert_eq!(entries[0]["input"]["command"], "cargo build");
+    // Secrets in tool inputs never reach the database or the API.
+    assert_eq!(
+        entries[1]["input"]["command"],
+        "GITHUB_TOKEN=[REDACTED] gh pr list"
+    );
+    assert_eq!(entries[2]["kind"], "tool_result");
+    assert_eq!(entries[2]["tool_name"], "Bash");
+    assert_eq!(entries[2]["is_error"], true);
+
+    // A step from another run is not found.
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/steps/{}/transcript", Id::new(), step.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn run_actions_audit_log_filters_commands() {
+    let (app, state, _dir) = create_test_app().await;
+
+    let run = Run {
+        id: Id::new(),
+        name: "test-run".to_string(),
+        name_source: RunNameSource::SpecSlug,
+        status: RunStatus::Running,
+        workspace_root: "/workspace".to_string(),
+        spec_path: "/workspace/spec.md".to_string(),
+        plan_path: None,
+        worktree: None,
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: 0,
+    };
+    state.storage.insert_run(&run).await.unwrap();
+    let step = Step {
+        id: Id::new(),
+        run_id: run.id.clone(),
+        phase: StepPhase::Implementation,
+        status: StepStatus::Succeeded,
+        attempt: 1,
+        started_at: Some(Utc::now()),
+        ended_at: Some(Utc::now()),
+        exit_code: Some(0),
+        prompt_path: None,
+        output_path: None,
+        session_id: None,
+    };
+    state.storage.insert_step(&step).await.unwrap();
+
+    let mut builder = TranscriptBuilder::new();
+    builder.observe(
+        &serde_json::json!({"type": "assistant", "message": {"content": [
+            {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "rm -rf src/old"}},
+            {"type": "tool_use", "id": "t2", "name": "Edit",
+             "input": {"file_path": "src/lib.rs", "old_string": "a", "new_string": "b"}},
+            {"type": "tool_use", "id": "t3", "name": "WebFetch",
+             "input": {"url": "https://example.com/?token=abc", "prompt": "summarize"}}
+        ]}}),
+        Utc::now(),
+    );
+    let actions = loop_core::audit::actions_from_transcript(&run.id, &step.id, &builder.finish());
+    state.storage.record_agent_actions(&actions).await.unwrap();
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/actions", run.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    let actions = json["actions"].as_array().unwrap();
+    assert_eq!(actions.len(), 3);
+    assert_eq!(actions[1]["kind"], "file_edit");
+    assert!(actions[1]["arguments"].get("new_string").is_none());
+    assert_eq!(
+        actions[2]["arguments"]["url"],
+        "https://example.com/?token=[REDACTED]"
+    );
+    assert_eq!(actions[2]["status"], "no_result");
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/runs/{}/actions?kind=command&contains=rm%20-rf",
+                    run.id
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    let actions = json["actions"].as_array().unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0]["tool"], "Bash");
+    assert_eq!(actions[0]["step_id"], step.id.to_string());
+
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/actions", Id::new()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+// --- Usage Tests ---
+
+#[tokio::test]
+async fn usage_totals_on_get_run_and_usage_endpoint() {
+    let (_, state, _dir) = create_test_app().await;
+
+    let run_id = Id::new();
+    let run = Run {
+        id: run_id.clone(),
+        name: "test-run".to_string(),
+        name_source: RunNameSource::SpecSlug,
+        status: RunStatus::Completed,
+        workspace_root: "/workspace".to_string(),
+        spec_path: "/workspace/spec.md".to_string(),
+        plan_path: None,
+        worktree: None,
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: 0,
+    };
+    state.storage.insert_run(&run).await.unwrap();
+
+    for cost in [0.25, 0.5] {
+        let step = Step {
+            id: Id::new(),
+            run_id: run_id.clone(),
+            phase: StepPhase::Implementation,
+            status: StepStatus::Succeeded,
+            attempt: 1,
+            started_at: Some(Utc::now()),
+            ended_at: Some(Utc::now()),
+            exit_code: Some(0),
+            prompt_path: None,
+            output_path: None,
+            session_id: None,
+        };
+        state.storage.insert_step(&step).await.unwrap();
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 20,
+            cost_usd: cost,
+            ..TokenUsage::default()
+        };
+        state
+            .storage
+            .record_step_usage(&run_id, &step.id, Some("opus"), &usage)
+            .await
+            .unwrap();
+    }
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}", run_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["usage"]["input_tokens"], 200);
+    assert_eq!(json["usage"]["output_tokens"], 40);
+    assert_eq!(json["usage"]["cost_usd"], 0.75);
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/usage?by=workspace")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["by"], "workspace");
+    let groups = json["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["key"], "/workspace");
+    assert_eq!(groups[0]["runs"], 1);
+    assert_eq!(groups[0]["steps"], 2);
+    assert_eq!(json["total"]["input_tokens"], 200);
+
+    // Unknown grouping is rejected.
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/usage?by=model")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn metrics_endpoint_reports_queue_and_skills() {
+    let (_, state, _dir) = create_test_app().await;
+
+    for _ in 0..2 {
+        let run = Run {
+            id: Id::new(),
+            name: "queued-run".to_string(),
+            name_source: RunNameSource::SpecSlug,
+            status: RunStatus::Pending,
+            workspace_root: "/workspace".to_string(),
+            spec_path: "/workspace/spec.md".to_string(),
+            plan_path: None,
+            worktree: None,
+            worktree_cleanup_status: None,
+            worktree_cleaned_at: None,
+            config_json: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            review_status: ReviewStatus::default(),
+            review_action_at: None,
+            pr_url: None,
+            merge_commit: None,
+            priority: 0,
+        };
+        state.storage.insert_run(&run).await.unwrap();
+    }
+    state.skills_metrics.inc_selected(3);
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response.headers()["content-type"].to_str().unwrap();
+    assert!(content_type.starts_with("text/plain; version=0.0.4"));
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains("loopd_queue_depth 2\n"));
+    assert!(text.contains("loopd_active_runs 0\n"));
+    assert!(text.contains("loopd_skills_selected_total 3\n"));
+    assert!(text.contains("loopd_step_duration_seconds_count{phase=\"review\"} 0\n"));
+}
+
+#[tokio::test]
+async fn health_reports_open_api_backoff() {
+    let (_, state, _dir) = create_test_app().await;
+    let step = Step {
+        id: Id::new(),
+        run_id: Id::new(),
+        phase: StepPhase::Implementation,
+        status: StepStatus::InProgress,
+        attempt: 1,
+        started_at: Some(Utc::now()),
+        ended_at: None,
+        exit_code: None,
+        prompt_path: None,
+        output_path: None,
+        session_id: None,
+    };
+    let signal = detect_rate_limit("API Error: 529 overloaded_error retry-after: 90").unwrap();
+    let permit = state
+        .scheduler
+        .governor()
+        .acquire("opus", &CancellationToken::new())
+        .await
+        .unwrap();
+    permit
+        .finish(AttemptOutcome::Throttled(signal), &step)
+        .await;
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = body_to_json(response).await;
+    assert_eq!(json["status"], "ok");
+    let backoff = json["api_backoff"].as_array().unwrap();
+    assert_eq!(backoff.len(), 1);
+    assert_eq!(backoff[0]["model"], "opus");
+    assert_eq!(backoff[0]["state"], "open");
+    assert_eq!(backoff[0]["signal"], "overloaded");
+    assert_eq!(backoff[0]["trips"], 1);
+}
+
+// --- Queue Tests ---
+
+#[tokio::test]
+async fn queue_orders_by_priority_and_priority_can_change() {
+    let (app, _, _dir) = create_test_app().await;
+
+    let mut ids = Vec::new();
+    for (spec, priority) in [("low.md", 0), ("high.md", 5)] {
+        let body = serde_json::json!({
+            "spec_path": format!("/workspace/{spec}"),
+            "workspace_root": "/workspace",
+            "name_source": "spec_slug",
+            "priority": priority
+        });
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let json = body_to_json(response).await;
+        assert_eq!(json["run"]["priority"], priority);
+        ids.push(json["run"]["id"].as_str().unwrap().to_string());
+    }
+
+    let queue_ids = |json: &Value| -> Vec<String> {
+        json["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["run_id"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/queue")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["policy"], "fifo");
+    assert_eq!(queue_ids(&json), vec![ids[1].clone(), ids[0].clone()]);
+    assert!(json["entries"][0].get("blocked").is_none());
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/runs/{}/priority", ids[0]))
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"priority": 10}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/queue")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let json = body_to_json(response).await;
+    assert_eq!(queue_ids(&json), vec![ids[0].clone(), ids[1].clone()]);
+
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/runs/missing/priority")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"priority": 1}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn runs_with_dependencies_are_blocked_in_queue() {
+    let (app, _, _dir) = create_test_app().await;
+
+    let create = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/runs")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&body).unwrap()))
+            .unwrap()
+    };
+
+    let response: Response = app
+        .clone()
+        .oneshot(create(serde_json::json!({
+            "spec_path": "/workspace/base.md",
+            "workspace_root": "/workspace",
+            "name_source": "spec_slug"
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let base_id = body_to_json(response).await["run"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response: Response = app
+        .clone()
+        .oneshot(create(serde_json::json!({
+            "spec_path": "/workspace/follow-up.md",
+            "workspace_root": "/workspace",
+            "name_source": "spec_slug",
+            "depends_on": [{"run_id": base_id, "condition": "merged", "branch_from": true}]
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let dependent_id = body_to_json(response).await["run"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/queue")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    let dependent = json["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["run_id"] == dependent_id.as_str())
+        .unwrap();
+    assert_eq!(dependent["blocked"]["reason"], "dependency");
+    assert_eq!(dependent["blocked"]["depends_on"], base_id.as_str());
+    assert_eq!(dependent["blocked"]["condition"], "merged");
+
+    let response: Response = app
+        .clone()
+        .oneshot(create(serde_json::json!({
+            "spec_path": "/workspace/orphan.md",
+            "workspace_root": "/workspace",
+            "depends_on": [{"run_id": "missing"}]
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response: Response = app
+        .oneshot(create(serde_json::json!({
+            "spec_path": "/workspace/two-bases.md",
+            "workspace_root": "/workspace",
+            "depends_on": [
+                {"run_id": base_id, "branch_from": true},
+                {"run_id": dependent_id, "branch_from": true}
+            ]
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn schedules_create_list_delete() {
+    let (app, _, _dir) = create_test_app().await;
+
+    let post = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/schedules")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&body).unwrap()))
+            .unwrap()
+    };
+
+    let response: Response = app
+        .clone()
+        .oneshot(post(serde_json::json!({
+            "spec_path": "/workspace/nightly-audit.md",
+            "workspace_root": "/workspace",
+            "cron": "0 2 * * *",
+            "missed_fire_policy": "catch_up",
+            "priority": 3
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let json = body_to_json(response).await;
+    let schedule = &json["schedule"];
+    assert_eq!(schedule["name"], "nightly-audit");
+    assert_eq!(schedule["missed_fire_policy"], "catch_up");
+    assert_eq!(schedule["overlap_policy"], "skip");
+    assert_eq!(schedule["priority"], 3);
+    assert!(schedule["next_fire_at"].is_string());
+    let id = schedule["id"].as_str().unwrap().to_string();
+
+    // Invalid cron expressions are rejected up front.
+    let response: Response = app
+        .clone()
+        .oneshot(post(serde_json::json!({
+            "spec_path": "/workspace/spec.md",
+            "workspace_root": "/workspace",
+            "cron": "every night"
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response: Response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/schedules?workspace_root=/workspace")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["schedules"].as_array().unwrap().len(), 1);
+    assert_eq!(json["schedules"][0]["id"], id.as_str());
+
+    let delete = |id: &str| {
+        Request::builder()
+            .method("DELETE")
+            .uri(format!("/schedules/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response: Response = app.clone().oneshot(delete(&id)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response: Response = app.clone().oneshot(delete(&id)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/schedules")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let json = body_to_json(response).await;
+    assert!(json["schedules"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn workers_register_claim_heartbeat_and_complete() {
+    let (app, state, dir) = create_test_app().await;
+
+    let post = |uri: String, body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&body).unwrap()))
+            .unwrap()
+    };
+
+    let response: Response = app
+        .clone()
+        .oneshot(post(
+            "/workers/register".to_string(),
+            serde_json::json!({"hostname": "builder-1", "capabilities": ["shared_fs"]}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let json = body_to_json(response).await;
+    assert_eq!(json["worker"]["status"], "ONLINE");
+    assert_eq!(json["lease_ttl_sec"], 30);
+    let worker_id = json["worker"]["id"].as_str().unwrap().to_string();
+
+    // Nothing offered yet.
+    let claim = || post(format!("/workers/{worker_id}/claim"), Value::Null);
+    let response: Response = app.clone().oneshot(claim()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let now = Utc::now();
+    let run = Run {
+        id: Id::new(),
+        name: "distributed".to_string(),
+        name_source: RunNameSource::SpecSlug,
+        status: RunStatus::Running,
+        workspace_root: "/workspace".to_string(),
+        spec_path: "/workspace/spec.md".to_string(),
+        plan_path: None,
+        worktree: None,
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: None,
+        created_at: now,
+        updated_at: now,
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: 0,
+    };
+    state.storage.insert_run(&run).await.unwrap();
+    let step = Step {
+        id: Id::new(),
+        run_id: run.id.clone(),
+        phase: StepPhase::Verification,
+        status: StepStatus::InProgress,
+        attempt: 1,
+        started_at: Some(now),
+        ended_at: None,
+        exit_code: None,
+        prompt_path: None,
+        output_path: None,
+        session_id: None,
+    };
+    state.storage.insert_step(&step).await.unwrap();
+    let request = StepRequest {
+        step: step.clone(),
+        config: Config::default(),
+        run_dir: dir.path().join("run"),
+        working_dir: "/workspace".into(),
+        prompt: None,
+        resume_session: None,
+        allowed_tools: Vec::new(),
+    };
+    state
+        .storage
+        .offer_step(&step.id, &run.id, &serde_json::to_string(&request).unwrap())
+        .await
+        .unwrap();
+
+    let response: Response = app.clone().oneshot(claim()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["lease"]["step_id"], step.id.to_string());
+    assert_eq!(json["lease"]["attempt"], 1);
+    assert_eq!(json["request"]["step"]["phase"], "verification");
+
+    // Already leased: nobody else gets it.
+    let response: Response = app.clone().oneshot(claim()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response: Response = app
+        .clone()
+        .oneshot(post(format!("/workers/{worker_id}/heartbeat"), Value::Null))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["leases"][0], step.id.to_string());
+
+    // The holder uploads what the step wrote into the controller's run directory.
+    let upload = |name: &str, worker: &str| {
+        Request::builder()
+            .method("PUT")
+            .uri(format!(
+                "/steps/{}/artifacts/{name}?worker_id={worker}",
+                step.id
+            ))
+            .body(Body::from("verify output"))
+            .unwrap()
+    };
+    let response: Response = app
+        .clone()
+        .oneshot(upload("iter-02-verify.log", &worker_id))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("run/iter-02-verify.log")).unwrap(),
+        "verify output"
+    );
+    let response: Response = app
+        .clone()
+        .oneshot(upload("..%2Fescape.log", &worker_id))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!dir.path().join("escape.log").exists());
+    let response: Response = app
+        .clone()
+        .oneshot(upload("iter-02-verify.log", Id::new().as_ref()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let complete = || {
+        post(
+            format!("/steps/{}/complete", step.id),
+            serde_json::json!({
+                "worker_id": worker_id,
+                "report": {"kind": "verification", "outcome": {"Err": "tests failed"}}
+            }),
+        )
+    };
+    let response: Response = app.clone().oneshot(complete()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(state
+        .storage
+        .lease_result(&step.id)
+        .await
+        .unwrap()
+        .is_some());
+    // The lease is spent; a second completion is stale.
+    let response: Response = app.clone().oneshot(complete()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let events = state.storage.list_events(&run.id).await.unwrap();
+    assert!(events
+        .iter()
+        .any(|event| event.event_type == EventType::LeaseGranted.as_str()));
+
+    let response: Response = app
+        .clone()
+        .oneshot(post(
+            format!("/workers/{}/heartbeat", Id::new()),
+            Value::Null,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/workers")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["workers"].as_array().unwrap().len(), 1);
+    assert_eq!(json["workers"][0]["hostname"], "builder-1");
+}
+
+#[tokio::test]
+async fn gc_previews_then_prunes_runs_over_the_workspace_limit() {
+    let (_, state, dir) = create_test_app().await;
+    let scheduler = Scheduler::new(Arc::clone(&state.storage), 3).with_retention(RetentionPolicy {
+        max_runs_per_workspace: Some(1),
+        ..RetentionPolicy::default()
+    });
+    let app = create_router(Arc::new(AppState {
+        storage: Arc::clone(&state.storage),
+        scheduler: Arc::new(scheduler),
+        skills_metrics: Arc::new(SkillsMetrics::new()),
+        auth_token: None,
+    }));
+
+    let config = Config {
+        global_log_dir: dir.path().join("global"),
+        ..Config::default()
+    };
+    let mut runs = Vec::new();
+    for age_hours in [2, 1] {
+        let mut run = running_run(&dir.path().to_string_lossy());
+        run.status = RunStatus::Completed;
+        run.config_json = Some(serde_json::to_string(&config).unwrap());
+        run.created_at = Utc::now() - chrono::Duration::hours(age_hours);
+        state.storage.insert_run(&run).await.unwrap();
+        let run_dir = loop_core::workspace_run_dir(dir.path(), &run.id);
+        std::fs::create_dir_all(&run_dir).unwrap();
+        std::fs::write(run_dir.join("summary.json"), "{}").unwrap();
+        state
+            .storage
+            .insert_artifact(&loop_core::Artifact {
+                id: Id::new(),
+                run_id: run.id.clone(),
+                kind: "summary".to_string(),
+                location: loop_core::ArtifactLocation::Workspace,
+                path: run_dir.join("summary.json").to_string_lossy().to_string(),
+                checksum: None,
+            })
+            .await
+            .unwrap();
+        runs.push(run);
+    }
+    let (old, recent) = (&runs[0], &runs[1]);
+
+    let gc = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/gc")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&body).unwrap()))
+            .unwrap()
+    };
+
+    let response: Response = app
+        .clone()
+        .oneshot(gc(serde_json::json!({"dry_run": true})))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["dry_run"], true);
+    assert_eq!(json["policy"]["max_runs_per_workspace"], 1);
+    assert_eq!(json["runs"].as_array().unwrap().len(), 1);
+    assert_eq!(json["runs"][0]["run_id"], old.id.0.as_str());
+    assert_eq!(json["runs"][0]["reason"], "max_runs_per_workspace");
+    assert_eq!(json["artifacts_removed"], 1);
+    assert_eq!(json["bytes_freed"], 2);
+    assert!(loop_core::workspace_run_dir(dir.path(), &old.id).exists());
+
+    let response: Response = app
+        .clone()
+        .oneshot(gc(serde_json::json!({})))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_to_json(response).await;
+    assert_eq!(json["dry_run"], false);
+    assert_eq!(json["runs"][0]["run_id"], old.id.0.as_str());
+    assert!(!loop_core::workspace_run_dir(dir.path(), &old.id).exists());
+    assert!(loop_core::workspace_run_dir(dir.path(), &recent.id).exists());
+    assert!(state
+        .storage
+        .list_artifacts(&old.id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        state
+            .storage
+            .list_artifacts(&recent.id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
 
+    // The pruned run keeps its row and is not selected again.
     let response: Response = app
+        .clone()
         .oneshot(
             Request::builder()
-                .uri(format!("/runs/{}/steps", run_id))
+                .uri(format!("/runs/{}", old.id))
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
+    let response: Response = app.oneshot(gc(serde_json::json!({}))).await.unwrap();
     let json = body_to_json(response).await;
-    let steps = json["steps"].as_array().unwrap();
-    assert_eq!(steps.len(), 3);
-    assert_eq!(steps[0]["phase"], "implementation");
-    assert_eq!(steps[1]["phase"], "review");
-    assert_eq!(steps[2]["phase"], "verification");
+    assert!(json["runs"].as_array().unwrap().is_empty());
 }
 
 // --- SSE Streaming Tests ---
@@ -420,6 +1587,7 @@ async fn sse_events_returns_correct_content_type() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -469,6 +1637,7 @@ async fn sse_events_streams_historical_events() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -542,6 +1711,7 @@ async fn sse_events_filters_by_after_timestamp() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -625,6 +1795,7 @@ async fn sse_events_includes_step_events() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -639,6 +1810,7 @@ async fn sse_events_includes_step_events() {
         exit_code: Some(0),
         prompt_path: None,
         output_path: Some("/workspace/logs/output.log".to_string()),
+        session_id: None,
     };
     state.storage.insert_step(&step).await.unwrap();
 
@@ -701,6 +1873,7 @@ async fn sse_output_returns_correct_content_type() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -755,6 +1928,7 @@ async fn sse_output_streams_step_output_content() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     state.storage.insert_run(&run).await.unwrap();
 
@@ -769,6 +1943,7 @@ async fn sse_output_streams_step_output_content() {
         exit_code: Some(0),
         prompt_path: None,
         output_path: Some(output_path.to_string_lossy().to_string()),
+        session_id: None,
     };
     state.storage.insert_step(&step).await.unwrap();
 
@@ -794,6 +1969,349 @@ async fn sse_output_streams_step_output_content() {
     assert!(body_str.contains("Test output line 2"));
 }
 
+fn running_run(workspace_root: &str) -> Run {
+    Run {
+        id: Id::new(),
+        name: "live-run".to_string(),
+        name_source: RunNameSource::SpecSlug,
+        status: RunStatus::Running,
+        workspace_root: workspace_root.to_string(),
+        spec_path: "/workspace/spec.md".to_string(),
+        plan_path: None,
+        worktree: None,
+        worktree_cleanup_status: None,
+        worktree_cleaned_at: None,
+        config_json: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        review_status: ReviewStatus::default(),
+        review_action_at: None,
+        pr_url: None,
+        merge_commit: None,
+        priority: 0,
+    }
+}
+
+#[tokio::test]
+async fn sse_events_resumes_from_last_event_id() {
+    let (_, state, _dir) = create_test_app().await;
+    let mut run = running_run("/workspace");
+    run.status = RunStatus::Completed;
+    state.storage.insert_run(&run).await.unwrap();
+
+    // Appended back to back, so they can share a millisecond timestamp.
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        let payload = EventPayload::RunStarted(RunStartedPayload {
+            run_id: run.id.clone(),
+            worker_id: format!("worker-{i}"),
+        });
+        let event = state
+            .storage
+            .append_event(&run.id, None, &payload)
+            .await
+            .unwrap();
+        ids.push(event.id);
+    }
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/events", run.id))
+                .header("Last-Event-ID", ids[0].to_string())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body_str = String::from_utf8_lossy(&bytes);
+    assert!(!body_str.contains("worker-0"));
+    assert!(body_str.contains("worker-1"));
+    assert!(body_str.contains("worker-2"));
+}
+
+#[tokio::test]
+async fn sse_events_pushes_live_events_until_run_completes() {
+    let (_, state, _dir) = create_test_app().await;
+    let run = running_run("/workspace");
+    state.storage.insert_run(&run).await.unwrap();
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/events", run.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = tokio::spawn(async move { response.into_body().collect().await });
+
+    let started = EventPayload::RunStarted(RunStartedPayload {
+        run_id: run.id.clone(),
+        worker_id: "worker-live".to_string(),
+    });
+    state
+        .storage
+        .append_event(&run.id, None, &started)
+        .await
+        .unwrap();
+    let completed = EventPayload::RunCompleted(RunCompletedPayload {
+        run_id: run.id.clone(),
+        mode: "exact".to_string(),
+    });
+    state
+        .storage
+        .complete_run_atomically(&run.id, &completed, RunStatus::Completed)
+        .await
+        .unwrap();
+
+    // Well under the status re-check interval: the terminal event ends the stream.
+    let bytes = tokio::time::timeout(std::time::Duration::from_secs(2), body)
+        .await
+        .expect("stream should end on RUN_COMPLETED")
+        .unwrap()
+        .unwrap()
+        .to_bytes();
+    let body_str = String::from_utf8_lossy(&bytes);
+    assert!(body_str.contains("worker-live"));
+    assert!(body_str.contains("RUN_COMPLETED"));
+}
+
+#[tokio::test]
+async fn sse_events_delivers_each_live_event_once() {
+    let (_, state, _dir) = create_test_app().await;
+    let run = running_run("/workspace");
+    state.storage.insert_run(&run).await.unwrap();
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/events", run.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = tokio::spawn(async move { response.into_body().collect().await });
+
+    // Back to back, so one catch-up read can cover several bus notifications.
+    for i in 0..5 {
+        let payload = EventPayload::RunStarted(RunStartedPayload {
+            run_id: run.id.clone(),
+            worker_id: format!("worker-burst-{i}"),
+        });
+        state
+            .storage
+            .append_event(&run.id, None, &payload)
+            .await
+            .unwrap();
+    }
+    let completed = EventPayload::RunCompleted(RunCompletedPayload {
+        run_id: run.id.clone(),
+        mode: "exact".to_string(),
+    });
+    state
+        .storage
+        .complete_run_atomically(&run.id, &completed, RunStatus::Completed)
+        .await
+        .unwrap();
+
+    let bytes = tokio::time::timeout(std::time::Duration::from_secs(2), body)
+        .await
+        .expect("stream should end on RUN_COMPLETED")
+        .unwrap()
+        .unwrap()
+        .to_bytes();
+    let body_str = String::from_utf8_lossy(&bytes);
+    for i in 0..5 {
+        assert_eq!(body_str.matches(&format!("worker-burst-{i}")).count(), 1);
+    }
+    assert_eq!(body_str.matches("event: RUN_COMPLETED").count(), 1);
+}
+
+#[tokio::test]
+async fn sse_output_pushes_live_chunks_from_bus() {
+    let (_, state, dir) = create_test_app().await;
+    let run = running_run(&dir.path().to_string_lossy());
+    state.storage.insert_run(&run).await.unwrap();
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/output", run.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body();
+
+    let step_id = Id::new();
+    state.storage.bus().publish_output(OutputChunk {
+        run_id: run.id.clone(),
+        step_id: step_id.clone(),
+        offset: 0,
+        content: "live line\n".to_string(),
+    });
+
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(2), body.frame())
+        .await
+        .expect("chunk should be pushed without polling")
+        .unwrap()
+        .unwrap();
+    let data = frame.into_data().unwrap();
+    let text = String::from_utf8_lossy(&data);
+    assert!(text.contains("live line"));
+    assert!(text.contains(&step_id.to_string()));
+}
+
+#[tokio::test]
+async fn sse_events_ends_when_run_is_canceled() {
+    let (_, state, _dir) = create_test_app().await;
+    let run = running_run("/workspace");
+    state.storage.insert_run(&run).await.unwrap();
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/runs/{}/events", run.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = tokio::spawn(async move { response.into_body().collect().await });
+
+    state
+        .storage
+        .update_run_status(&run.id, RunStatus::Canceled)
+        .await
+        .unwrap();
+
+    let bytes = tokio::time::timeout(std::time::Duration::from_secs(2), body)
+        .await
+        .expect("stream should end on RUN_STATUS_CHANGED to CANCELED")
+        .unwrap()
+        .unwrap()
+        .to_bytes();
+    let body_str = String::from_utf8_lossy(&bytes);
+    assert!(body_str.contains("RUN_STATUS_CHANGED"));
+    assert!(body_str.contains("CANCELED"));
+}
+
+/// Read SSE frames until `done` accepts the accumulated text.
+async fn read_sse_until(body: &mut Body, done: impl Fn(&str) -> bool) -> String {
+    let mut text = String::new();
+    while !done(&text) {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(2), body.frame())
+            .await
+            .expect("timed out waiting for SSE frame")
+            .unwrap()
+            .unwrap();
+        if let Ok(data) = frame.into_data() {
+            text.push_str(&String::from_utf8_lossy(&data));
+        }
+    }
+    text
+}
+
+#[tokio::test]
+async fn firehose_streams_matching_events_across_runs() {
+    let (_, state, _dir) = create_test_app().await;
+    let mut run_a = running_run("/ws-a");
+    run_a.status = RunStatus::Pending;
+    let run_b = running_run("/ws-b");
+    state.storage.insert_run(&run_a).await.unwrap();
+    state.storage.insert_run(&run_b).await.unwrap();
+
+    let app = create_router(Arc::clone(&state));
+    let response: Response = app
+        .oneshot(
+            Request::builder()
+                .uri("/events?workspace=/ws-a&event_type=run_started,RUN_STATUS_CHANGED")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body();
+
+    // Other workspace: filtered out.
+    let started_b = EventPayload::RunStarted(RunStartedPayload {
+        run_id: run_b.id.clone(),
+        worker_id: "worker-b".to_string(),
+    });
+    state
+        .storage
+        .append_event(&run_b.id, None, &started_b)
+        .await
+        .unwrap();
+    // Other event type: filtered out.
+    let created_a = EventPayload::RunCreated(RunCreatedPayload {
+        run_id: run_a.id.clone(),
+        name: run_a.name.clone(),
+        name_source: run_a.name_source,
+        spec_path: run_a.spec_path.clone(),
+        plan_path: None,
+    });
+    state
+        .storage
+        .append_event(&run_a.id, None, &created_a)
+        .await
+        .unwrap();
+    state
+        .storage
+        .update_run_status(&run_a.id, RunStatus::Running)
+        .await
+        .unwrap();
+    let started_a = EventPayload::RunStarted(RunStartedPayload {
+        run_id: run_a.id.clone(),
+        worker_id: "worker-a".to_string(),
+    });
+    state
+        .storage
+        .append_event(&run_a.id, None, &started_a)
+        .await
+        .unwrap();
+
+    let text = read_sse_until(&mut body, |t| t.contains("worker-a")).await;
+    let status_at = text.find("event: RUN_STATUS_CHANGED").unwrap();
+    assert!(status_at < text.find("worker-a").unwrap());
+    assert!(text.contains("\"to\":\"RUNNING\""));
+    assert!(!text.contains("worker-b"));
+    assert!(!text.contains("RUN_CREATED"));
+}
+
+#[tokio::test]
+async fn firehose_rejects_unknown_filters() {
+    let (app, _, _dir) = create_test_app().await;
+
+    for uri in [
+        "/events?event_type=NOT_AN_EVENT",
+        "/events?run_status=sleeping",
+    ] {
+        let response: Response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
 // --- Auth Token Tests ---
 
 #[tokio::test]
@@ -804,12 +2322,13 @@ async fn auth_token_blocks_unauthorized_requests() {
         .await
         .unwrap();
     storage.migrate_embedded().await.unwrap();
-    let storage = Arc::new(storage);
+    let storage: Arc<dyn StorageBackend> = Arc::new(storage);
     let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 3));
 
     let state = Arc::new(AppState {
         storage: Arc::clone(&storage),
         scheduler,
+        skills_metrics: Arc::new(SkillsMetrics::new()),
         auth_token: Some("test-secret-token".to_string()),
     });
 
@@ -833,6 +2352,7 @@ async fn auth_token_blocks_unauthorized_requests() {
         review_action_at: None,
         pr_url: None,
         merge_commit: None,
+        priority: 0,
     };
     storage.insert_run(&run).await.unwrap();
 
@@ -882,12 +2402,13 @@ async fn auth_token_rejects_invalid_token() {
         .await
         .unwrap();
     storage.migrate_embedded().await.unwrap();
-    let storage = Arc::new(storage);
+    let storage: Arc<dyn StorageBackend> = Arc::new(storage);
     let scheduler = Arc::new(Scheduler::new(Arc::clone(&storage), 3));
 
     let state = Arc::new(AppState {
         storage,
         scheduler,
+        skills_metrics: Arc::new(SkillsMetrics::new()),
         auth_token: Some("correct-token".to_string()),
     });
 
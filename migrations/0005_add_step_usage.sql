@@ -0,0 +1,18 @@
+-- Token usage and reported cost per step
+-- Captured from agent stream usage blocks (message_start, message_delta, result).
+
+CREATE TABLE IF NOT EXISTS step_usage (
+    step_id TEXT PRIMARY KEY REFERENCES steps(id) ON DELETE CASCADE,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    model TEXT,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_creation_input_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_read_input_tokens INTEGER NOT NULL DEFAULT 0,
+    cost_usd REAL NOT NULL DEFAULT 0,
+    -- Timestamp (Unix epoch milliseconds)
+    recorded_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_step_usage_run ON step_usage(run_id);
+CREATE INDEX IF NOT EXISTS idx_step_usage_recorded ON step_usage(recorded_at);
@@ -0,0 +1,20 @@
+-- Delivery log for webhook notifications
+-- One row per (event, webhook) once delivery succeeds or retries are exhausted.
+
+CREATE TABLE IF NOT EXISTS notification_deliveries (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    event_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    webhook TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('delivered', 'failed')),
+    attempts INTEGER NOT NULL,
+    -- HTTP status of the last attempt (NULL when the request never completed)
+    response_code INTEGER,
+    error TEXT,
+    -- Timestamp (Unix epoch milliseconds)
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_deliveries_run ON notification_deliveries(run_id);
+CREATE INDEX IF NOT EXISTS idx_notification_deliveries_status ON notification_deliveries(status, created_at);
@@ -0,0 +1,29 @@
+-- Allow the 'planning' step phase
+-- SQLite cannot alter a CHECK constraint, so the steps table is rebuilt.
+-- Storage::migrate_embedded applies this only while the steps schema lacks 'planning',
+-- on a single connection with foreign keys disabled so events and step_usage keep their step ids.
+
+CREATE TABLE steps_new (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    phase TEXT NOT NULL CHECK (phase IN ('planning', 'implementation', 'review', 'verification', 'watchdog', 'merge')),
+    status TEXT NOT NULL CHECK (status IN ('QUEUED', 'IN_PROGRESS', 'SUCCEEDED', 'FAILED', 'RETRYING', 'CANCELED')),
+    attempt INTEGER NOT NULL DEFAULT 1,
+    -- Timestamps (Unix epoch milliseconds)
+    started_at INTEGER,
+    ended_at INTEGER,
+    exit_code INTEGER,
+    prompt_path TEXT,
+    output_path TEXT
+);
+
+INSERT INTO steps_new (id, run_id, phase, status, attempt, started_at, ended_at, exit_code, prompt_path, output_path)
+SELECT id, run_id, phase, status, attempt, started_at, ended_at, exit_code, prompt_path, output_path FROM steps ORDER BY rowid;
+
+DROP TABLE steps;
+
+ALTER TABLE steps_new RENAME TO steps;
+
+CREATE INDEX IF NOT EXISTS idx_steps_run ON steps(run_id);
+CREATE INDEX IF NOT EXISTS idx_steps_status ON steps(status);
+CREATE INDEX IF NOT EXISTS idx_steps_phase ON steps(phase);
@@ -0,0 +1,17 @@
+-- Parent/child links for runs fanned out from a plan task graph
+-- Each child run implements one plan task on its own branch; the parent merges it back.
+
+CREATE TABLE IF NOT EXISTS run_children (
+    child_run_id TEXT PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+    parent_run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    task_id TEXT NOT NULL,
+    task_label TEXT NOT NULL,
+    -- JSON array of task ids this task depends on
+    depends_on_json TEXT NOT NULL DEFAULT '[]',
+    -- Timestamps (Unix epoch milliseconds)
+    merged_at INTEGER,
+    created_at INTEGER NOT NULL,
+    UNIQUE (parent_run_id, task_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_children_parent ON run_children(parent_run_id);
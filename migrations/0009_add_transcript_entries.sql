@@ -0,0 +1,21 @@
+-- Typed step transcripts parsed from agent stream-json output
+-- One row per assistant message, tool call, or tool result, in step order.
+
+CREATE TABLE IF NOT EXISTS transcript_entries (
+    step_id TEXT NOT NULL REFERENCES steps(id) ON DELETE CASCADE,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('assistant_message', 'tool_use', 'tool_result')),
+    text TEXT,
+    tool_use_id TEXT,
+    tool_name TEXT,
+    input_json TEXT,
+    is_error INTEGER NOT NULL DEFAULT 0,
+    -- Timestamp (Unix epoch milliseconds)
+    at INTEGER NOT NULL,
+    duration_ms INTEGER,
+    PRIMARY KEY (step_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcript_entries_run ON transcript_entries(run_id);
+CREATE INDEX IF NOT EXISTS idx_transcript_entries_tool ON transcript_entries(step_id, tool_name);
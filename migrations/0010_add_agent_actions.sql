@@ -0,0 +1,19 @@
+-- Audit log of agent actions: shell commands, file writes and edits, web fetches
+-- Arguments are redacted before insert (see loop-core audit.rs).
+
+CREATE TABLE IF NOT EXISTS agent_actions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    step_id TEXT NOT NULL REFERENCES steps(id) ON DELETE CASCADE,
+    tool_use_id TEXT,
+    tool TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('command', 'file_write', 'file_edit', 'web_fetch')),
+    arguments_json TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('succeeded', 'failed', 'no_result')),
+    -- Timestamp (Unix epoch milliseconds)
+    at INTEGER NOT NULL,
+    duration_ms INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_actions_run ON agent_actions(run_id, at);
+CREATE INDEX IF NOT EXISTS idx_agent_actions_step ON agent_actions(step_id);
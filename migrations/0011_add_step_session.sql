@@ -0,0 +1,5 @@
+-- Agent session a step ran in
+-- Recorded when session resume is enabled so later iterations (and a restarted
+-- daemon) can continue the conversation instead of starting a new one.
+
+ALTER TABLE steps ADD COLUMN session_id TEXT;
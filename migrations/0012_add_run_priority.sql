@@ -0,0 +1,5 @@
+-- Scheduling priority for pending runs
+-- Higher values are claimed first; aging raises a waiting run's effective
+-- priority so low-priority runs are not starved.
+
+ALTER TABLE runs ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
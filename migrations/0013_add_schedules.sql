@@ -0,0 +1,27 @@
+-- Cron schedules that enqueue recurring runs
+-- The daemon polls for rows whose next_fire_at has passed and inserts a
+-- pending run per fire, subject to the missed-fire and overlap policies.
+
+CREATE TABLE IF NOT EXISTS schedules (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    workspace_root TEXT NOT NULL,
+    spec_path TEXT NOT NULL,
+    plan_path TEXT,
+    -- Config file path or inline JSON, as accepted by POST /runs
+    config_override TEXT,
+    cron TEXT NOT NULL,
+    missed_fire_policy TEXT NOT NULL DEFAULT 'skip' CHECK (missed_fire_policy IN ('skip', 'catch_up')),
+    overlap_policy TEXT NOT NULL DEFAULT 'skip' CHECK (overlap_policy IN ('skip', 'allow')),
+    priority INTEGER NOT NULL DEFAULT 0,
+    -- No foreign key: the run may be deleted while the schedule lives on
+    last_run_id TEXT,
+    -- Timestamps (Unix epoch milliseconds)
+    next_fire_at INTEGER NOT NULL,
+    last_fire_at INTEGER,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_schedules_next_fire ON schedules(next_fire_at);
+CREATE INDEX IF NOT EXISTS idx_schedules_workspace ON schedules(workspace_root);
@@ -0,0 +1,15 @@
+-- Run dependencies: a pending run is not claimed until every run it
+-- depends on reaches the given condition.
+
+CREATE TABLE IF NOT EXISTS run_dependencies (
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    -- No foreign key: a dependency that is deleted fails its dependents
+    depends_on_run_id TEXT NOT NULL,
+    condition TEXT NOT NULL DEFAULT 'completed'
+        CHECK (condition IN ('completed', 'merged', 'succeeded_verification')),
+    -- 1 when the dependent run's base branch is taken from this run
+    branch_from INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (run_id, depends_on_run_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_dependencies_depends_on ON run_dependencies(depends_on_run_id);
@@ -0,0 +1,31 @@
+-- Distributed mode: workers register with the controller and claim steps
+-- through leases that they renew with heartbeats.
+
+CREATE TABLE IF NOT EXISTS workers (
+    id TEXT PRIMARY KEY,
+    hostname TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'ONLINE' CHECK (status IN ('ONLINE', 'OFFLINE')),
+    capabilities_json TEXT NOT NULL DEFAULT '[]',
+    -- Timestamps (Unix epoch milliseconds)
+    last_heartbeat INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+-- One row per step offered to workers. worker_id and expires_at are NULL
+-- until a worker claims it, and again once an expired lease is requeued.
+CREATE TABLE IF NOT EXISTS leases (
+    -- No foreign key to steps: the steps table is rebuilt by migration 0007
+    step_id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    worker_id TEXT,
+    expires_at INTEGER,
+    -- Times the lease was granted
+    attempt INTEGER NOT NULL DEFAULT 0,
+    -- What the worker needs to execute the step, and what it reported back
+    request_json TEXT NOT NULL,
+    result_json TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_leases_worker ON leases(worker_id);
+CREATE INDEX IF NOT EXISTS idx_leases_expires ON leases(expires_at);
@@ -0,0 +1,14 @@
+-- Retention: one row per run pruned by the garbage collector. The run row
+-- itself is kept; this records what was removed and why.
+
+CREATE TABLE IF NOT EXISTS gc_log (
+    run_id TEXT PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL CHECK (reason IN ('max_age', 'max_runs_per_workspace')),
+    events_removed INTEGER NOT NULL DEFAULT 0,
+    artifacts_removed INTEGER NOT NULL DEFAULT 0,
+    bytes_freed INTEGER NOT NULL DEFAULT 0,
+    -- Timestamp (Unix epoch milliseconds)
+    pruned_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_gc_log_pruned_at ON gc_log(pruned_at);
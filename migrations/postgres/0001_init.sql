@@ -0,0 +1,78 @@
+-- Postgres schema for runs, steps, events and artifacts
+-- Mirrors the SQLite schema after migrations 0001-0012. Timestamps stay Unix
+-- epoch milliseconds; seq columns stand in for SQLite's rowid ordering.
+
+CREATE TABLE IF NOT EXISTS runs (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    name_source TEXT NOT NULL CHECK (name_source IN ('spec_slug', 'haiku')),
+    status TEXT NOT NULL CHECK (status IN ('PENDING', 'RUNNING', 'PAUSED', 'COMPLETED', 'FAILED', 'CANCELED')),
+    workspace_root TEXT NOT NULL,
+    spec_path TEXT NOT NULL,
+    plan_path TEXT,
+    base_branch TEXT,
+    run_branch TEXT,
+    merge_target_branch TEXT,
+    merge_strategy TEXT CHECK (merge_strategy IS NULL OR merge_strategy IN ('none', 'merge', 'squash')),
+    worktree_path TEXT,
+    config_json TEXT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    worktree_provider TEXT CHECK (worktree_provider IS NULL OR worktree_provider IN ('auto', 'worktrunk', 'git')),
+    worktree_cleanup_status TEXT,
+    worktree_cleaned_at BIGINT,
+    review_status TEXT DEFAULT 'pending',
+    review_action_at BIGINT,
+    pr_url TEXT,
+    merge_commit TEXT,
+    priority BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_runs_status ON runs(status);
+CREATE INDEX IF NOT EXISTS idx_runs_workspace ON runs(workspace_root);
+CREATE INDEX IF NOT EXISTS idx_runs_created ON runs(created_at);
+
+CREATE TABLE IF NOT EXISTS steps (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    phase TEXT NOT NULL CHECK (phase IN ('planning', 'implementation', 'review', 'verification', 'watchdog', 'merge')),
+    status TEXT NOT NULL CHECK (status IN ('QUEUED', 'IN_PROGRESS', 'SUCCEEDED', 'FAILED', 'RETRYING', 'CANCELED')),
+    attempt BIGINT NOT NULL DEFAULT 1,
+    started_at BIGINT,
+    ended_at BIGINT,
+    exit_code INTEGER,
+    prompt_path TEXT,
+    output_path TEXT,
+    session_id TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_steps_run ON steps(run_id);
+CREATE INDEX IF NOT EXISTS idx_steps_status ON steps(status);
+CREATE INDEX IF NOT EXISTS idx_steps_phase ON steps(phase);
+
+CREATE TABLE IF NOT EXISTS events (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    step_id TEXT REFERENCES steps(id) ON DELETE SET NULL,
+    type TEXT NOT NULL,
+    ts BIGINT NOT NULL,
+    payload_json TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_run ON events(run_id, seq);
+CREATE INDEX IF NOT EXISTS idx_events_type ON events(type);
+CREATE INDEX IF NOT EXISTS idx_events_ts ON events(ts);
+
+CREATE TABLE IF NOT EXISTS artifacts (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    location TEXT NOT NULL CHECK (location IN ('workspace', 'global')),
+    path TEXT NOT NULL,
+    checksum TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_artifacts_run ON artifacts(run_id);
+CREATE INDEX IF NOT EXISTS idx_artifacts_kind ON artifacts(kind);
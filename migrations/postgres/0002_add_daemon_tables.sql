@@ -0,0 +1,155 @@
+-- Postgres schema for the remaining daemon tables: usage, notifications, child
+-- runs, transcripts, agent actions, schedules, dependencies, workers, leases
+-- and the GC log. Mirrors SQLite migrations 0005-0016; seq columns stand in
+-- for SQLite's rowid ordering and flags are BOOLEAN rather than 0/1.
+
+CREATE TABLE IF NOT EXISTS step_usage (
+    seq BIGSERIAL,
+    step_id TEXT PRIMARY KEY REFERENCES steps(id) ON DELETE CASCADE,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    model TEXT,
+    input_tokens BIGINT NOT NULL DEFAULT 0,
+    output_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_creation_input_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_read_input_tokens BIGINT NOT NULL DEFAULT 0,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    recorded_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_step_usage_run ON step_usage(run_id);
+CREATE INDEX IF NOT EXISTS idx_step_usage_recorded ON step_usage(recorded_at);
+
+CREATE TABLE IF NOT EXISTS notification_deliveries (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    event_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    webhook TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('delivered', 'failed')),
+    attempts BIGINT NOT NULL,
+    response_code BIGINT,
+    error TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_deliveries_run ON notification_deliveries(run_id);
+CREATE INDEX IF NOT EXISTS idx_notification_deliveries_status ON notification_deliveries(status, created_at);
+
+CREATE TABLE IF NOT EXISTS run_children (
+    seq BIGSERIAL,
+    child_run_id TEXT PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+    parent_run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    task_id TEXT NOT NULL,
+    task_label TEXT NOT NULL,
+    depends_on_json TEXT NOT NULL DEFAULT '[]',
+    merged_at BIGINT,
+    created_at BIGINT NOT NULL,
+    UNIQUE (parent_run_id, task_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_children_parent ON run_children(parent_run_id);
+
+CREATE TABLE IF NOT EXISTS transcript_entries (
+    step_id TEXT NOT NULL REFERENCES steps(id) ON DELETE CASCADE,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    seq BIGINT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('assistant_message', 'tool_use', 'tool_result')),
+    text TEXT,
+    tool_use_id TEXT,
+    tool_name TEXT,
+    input_json TEXT,
+    is_error BOOLEAN NOT NULL DEFAULT FALSE,
+    at BIGINT NOT NULL,
+    duration_ms BIGINT,
+    PRIMARY KEY (step_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcript_entries_run ON transcript_entries(run_id);
+CREATE INDEX IF NOT EXISTS idx_transcript_entries_tool ON transcript_entries(step_id, tool_name);
+
+CREATE TABLE IF NOT EXISTS agent_actions (
+    id BIGSERIAL PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    step_id TEXT NOT NULL REFERENCES steps(id) ON DELETE CASCADE,
+    tool_use_id TEXT,
+    tool TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('command', 'file_write', 'file_edit', 'web_fetch')),
+    arguments_json TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('succeeded', 'failed', 'no_result')),
+    at BIGINT NOT NULL,
+    duration_ms BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_actions_run ON agent_actions(run_id, at);
+CREATE INDEX IF NOT EXISTS idx_agent_actions_step ON agent_actions(step_id);
+
+CREATE TABLE IF NOT EXISTS schedules (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    workspace_root TEXT NOT NULL,
+    spec_path TEXT NOT NULL,
+    plan_path TEXT,
+    config_override TEXT,
+    cron TEXT NOT NULL,
+    missed_fire_policy TEXT NOT NULL DEFAULT 'skip' CHECK (missed_fire_policy IN ('skip', 'catch_up')),
+    overlap_policy TEXT NOT NULL DEFAULT 'skip' CHECK (overlap_policy IN ('skip', 'allow')),
+    priority BIGINT NOT NULL DEFAULT 0,
+    last_run_id TEXT,
+    next_fire_at BIGINT NOT NULL,
+    last_fire_at BIGINT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_schedules_next_fire ON schedules(next_fire_at);
+CREATE INDEX IF NOT EXISTS idx_schedules_workspace ON schedules(workspace_root);
+
+CREATE TABLE IF NOT EXISTS run_dependencies (
+    seq BIGSERIAL,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    depends_on_run_id TEXT NOT NULL,
+    condition TEXT NOT NULL DEFAULT 'completed'
+        CHECK (condition IN ('completed', 'merged', 'succeeded_verification')),
+    branch_from BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (run_id, depends_on_run_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_dependencies_depends_on ON run_dependencies(depends_on_run_id);
+
+CREATE TABLE IF NOT EXISTS workers (
+    seq BIGSERIAL,
+    id TEXT PRIMARY KEY,
+    hostname TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'ONLINE' CHECK (status IN ('ONLINE', 'OFFLINE')),
+    capabilities_json TEXT NOT NULL DEFAULT '[]',
+    last_heartbeat BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS leases (
+    seq BIGSERIAL,
+    step_id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    worker_id TEXT,
+    expires_at BIGINT,
+    attempt BIGINT NOT NULL DEFAULT 0,
+    request_json TEXT NOT NULL,
+    result_json TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_leases_worker ON leases(worker_id);
+CREATE INDEX IF NOT EXISTS idx_leases_expires ON leases(expires_at);
+
+CREATE TABLE IF NOT EXISTS gc_log (
+    run_id TEXT PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL CHECK (reason IN ('max_age', 'max_runs_per_workspace')),
+    events_removed BIGINT NOT NULL DEFAULT 0,
+    artifacts_removed BIGINT NOT NULL DEFAULT 0,
+    bytes_freed BIGINT NOT NULL DEFAULT 0,
+    pruned_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_gc_log_pruned_at ON gc_log(pruned_at);